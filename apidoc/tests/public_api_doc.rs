@@ -7,6 +7,6 @@
 fn public_api_surface_docs_are_current() {
     zenutils_apidoc::ApiDoc::new()
         .workspace_dir("..")
-        .crates(["enough", "almost-enough", "enough-tokio", "enough-ffi"])
+        .crates(["enough", "almost-enough", "enough-tokio", "enough-futures", "enough-async", "enough-ffi", "enough-derive"])
         .run();
 }
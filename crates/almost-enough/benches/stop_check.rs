@@ -4,7 +4,7 @@
 //! dispatch path. Complements stop_check_zen (which focuses on
 //! codec-realistic, layout-immune comparisons) with isolated per-type
 //! measurements including types not covered there (StopRef, ChildStopper,
-//! OrStop, WithTimeout).
+//! OrStop, WithTimeout, Throttled).
 //!
 //! Run with: cargo bench --bench stop_check
 
@@ -111,6 +111,15 @@ fn main() {
                 let stop: WithTimeout<_> = source.as_ref().with_timeout(Duration::from_secs(3600));
                 b.iter(|| black_box(&stop).check())
             });
+
+            group.bench("with_timeout_throttled_128", |b| {
+                let source = StopSource::new();
+                let stop = source
+                    .as_ref()
+                    .with_timeout(Duration::from_secs(3600))
+                    .throttle_checks(128);
+                b.iter(|| black_box(&stop).check())
+            });
         });
 
         // ═══════════════════════════════════════════════════════════
@@ -307,6 +316,82 @@ fn main() {
                 })
             });
         });
+
+        // ═══════════════════════════════════════════════════════════
+        // 5. Tight loop checking a timeout on every item
+        //
+        // Throttled skips the clock read on all but every N-th check.
+        // ═══════════════════════════════════════════════════════════
+
+        suite.compare("hot_loop_timeout_every_item", |group| {
+            group.config().sort_by_speed(true).cache_firewall(false);
+            group.baseline("with_timeout");
+            group.throughput(zenbench::Throughput::Elements(HOT_LOOP_ITERS as u64));
+
+            group.bench("with_timeout", |b| {
+                let stop = Stopper::new().with_timeout(Duration::from_secs(3600));
+                b.iter(|| {
+                    let mut acc = 0usize;
+                    for i in 0..HOT_LOOP_ITERS {
+                        let _ = check_generic(&stop);
+                        acc = acc.wrapping_add(trivial_work(i));
+                    }
+                    black_box(acc)
+                })
+            });
+
+            for interval in [16, 128, 1024] {
+                group.bench(format!("throttled_{interval}"), move |b| {
+                    let stop = Stopper::new()
+                        .with_timeout(Duration::from_secs(3600))
+                        .throttle_checks(interval);
+                    b.iter(|| {
+                        let mut acc = 0usize;
+                        for i in 0..HOT_LOOP_ITERS {
+                            let _ = check_generic(&stop);
+                            acc = acc.wrapping_add(trivial_work(i));
+                        }
+                        black_box(acc)
+                    })
+                });
+            }
+        });
+
+        // ═══════════════════════════════════════════════════════════
+        // 6. cancel() cost
+        //
+        // Construction is measured alone as the baseline, so each
+        // *_cancel entry's delta is the first cancel() on a fresh node:
+        // one compare-exchange on the packed state word.
+        // ═══════════════════════════════════════════════════════════
+
+        suite.compare("cancel", |group| {
+            group.config().sort_by_speed(true).cache_firewall(false);
+            group.baseline("stopper_new");
+
+            group.bench("stopper_new", |b| b.iter(|| black_box(Stopper::new())));
+
+            group.bench("stopper_cancel", |b| {
+                b.iter(|| {
+                    let stop = Stopper::new();
+                    stop.cancel();
+                    black_box(stop)
+                })
+            });
+
+            group.bench("sync_stopper_cancel", |b| {
+                b.iter(|| {
+                    let stop = SyncStopper::new();
+                    stop.cancel();
+                    black_box(stop)
+                })
+            });
+
+            group.bench("stopper_cancel_again", |b| {
+                let stop = Stopper::cancelled();
+                b.iter(|| black_box(&stop).cancel())
+            });
+        });
     });
 
     if let Err(e) = result.save("stop_check_results.json") {
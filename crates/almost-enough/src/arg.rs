@@ -0,0 +1,233 @@
+//! A single parameter type that accepts borrowed or owned stops.
+//!
+//! Library authors choosing between `impl Stop`, `&dyn Stop` and
+//! [`BoxedStop`] trade monomorphization against flexibility. [`StopArg`]
+//! sidesteps the choice: take `impl Into<StopArg<'_>>` and callers can pass
+//! a reference to any stop, a `&dyn Stop`, or an owned [`BoxedStop`] /
+//! [`StopToken`] — with one non-generic function body and no forced boxing
+//! for borrowed stops.
+//!
+//! # Example
+//!
+//! ```rust
+//! use almost_enough::{StopArg, Stopper, StopSource, BoxedStop, Stop, Unstoppable};
+//!
+//! pub fn decode<'a>(data: &[u8], stop: impl Into<StopArg<'a>>) -> bool {
+//!     decode_inner(data, stop.into())
+//! }
+//!
+//! // Only one copy of this function exists
+//! fn decode_inner(data: &[u8], stop: StopArg<'_>) -> bool {
+//!     !stop.should_stop() && !data.is_empty()
+//! }
+//!
+//! let source = StopSource::new();
+//! let stopper = Stopper::new();
+//! let dyn_stop: &dyn Stop = &stopper;
+//!
+//! assert!(decode(b"x", &source));                        // borrowed concrete type
+//! assert!(decode(b"x", dyn_stop));                       // borrowed trait object
+//! assert!(decode(b"x", BoxedStop::new(stopper.clone()))); // owned
+//! assert!(decode(b"x", Unstoppable));                    // owned, no allocation
+//! ```
+
+use crate::{BoxedStop, Stop, StopReason, StopToken, Unstoppable};
+
+/// A borrowed `&dyn Stop` or an owned [`BoxedStop`], with a uniform [`Stop`] impl.
+///
+/// Build one with `Into`/`From`; take `impl Into<StopArg<'_>>` as a
+/// function parameter and convert once at the top of the function.
+///
+/// | From | Variant | Allocates |
+/// |------|---------|-----------|
+/// | `&T` where `T: Stop` | `Borrowed` | no |
+/// | `&dyn Stop` | `Borrowed` | no |
+/// | `Unstoppable` | `Owned` | no |
+/// | `BoxedStop` | `Owned` | no (already boxed) |
+/// | `StopToken` | `Owned` | one `Box` for the token |
+pub enum StopArg<'a> {
+    /// A borrowed stop.
+    Borrowed(&'a dyn Stop),
+    /// An owned, type-erased stop.
+    Owned(BoxedStop),
+}
+
+impl<'a> StopArg<'a> {
+    /// View this argument as a `&dyn Stop`.
+    #[inline]
+    pub fn as_dyn(&self) -> &(dyn Stop + 'a) {
+        match self {
+            Self::Borrowed(stop) => *stop,
+            Self::Owned(stop) => stop,
+        }
+    }
+}
+
+impl Stop for StopArg<'_> {
+    #[inline]
+    fn check(&self) -> Result<(), StopReason> {
+        match self {
+            Self::Borrowed(stop) => stop.check(),
+            Self::Owned(stop) => stop.check(),
+        }
+    }
+
+    #[inline]
+    fn should_stop(&self) -> bool {
+        match self {
+            Self::Borrowed(stop) => stop.should_stop(),
+            Self::Owned(stop) => stop.should_stop(),
+        }
+    }
+
+    #[inline]
+    fn may_stop(&self) -> bool {
+        match self {
+            Self::Borrowed(stop) => stop.may_stop(),
+            Self::Owned(stop) => stop.may_stop(),
+        }
+    }
+}
+
+impl<'a> AsRef<dyn Stop + 'a> for StopArg<'a> {
+    #[inline]
+    fn as_ref(&self) -> &(dyn Stop + 'a) {
+        self.as_dyn()
+    }
+}
+
+impl<'a, T: Stop> From<&'a T> for StopArg<'a> {
+    #[inline]
+    fn from(stop: &'a T) -> Self {
+        Self::Borrowed(stop)
+    }
+}
+
+impl<'a> From<&'a dyn Stop> for StopArg<'a> {
+    #[inline]
+    fn from(stop: &'a dyn Stop) -> Self {
+        Self::Borrowed(stop)
+    }
+}
+
+impl From<Unstoppable> for StopArg<'_> {
+    #[inline]
+    fn from(stop: Unstoppable) -> Self {
+        Self::Owned(BoxedStop::new(stop))
+    }
+}
+
+impl From<BoxedStop> for StopArg<'_> {
+    #[inline]
+    fn from(stop: BoxedStop) -> Self {
+        Self::Owned(stop)
+    }
+}
+
+impl From<StopToken> for StopArg<'_> {
+    #[inline]
+    fn from(stop: StopToken) -> Self {
+        Self::Owned(BoxedStop::new(stop))
+    }
+}
+
+impl core::fmt::Debug for StopArg<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Borrowed(_) => f.debug_tuple("Borrowed").finish_non_exhaustive(),
+            Self::Owned(stop) => f.debug_tuple("Owned").field(stop).finish(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ChildStopper, StopExt, StopSource, Stopper};
+
+    fn accept<'a>(stop: impl Into<StopArg<'a>>) -> StopArg<'a> {
+        stop.into()
+    }
+
+    #[test]
+    fn borrowed_concrete() {
+        let stopper = Stopper::new();
+        let arg = accept(&stopper);
+        assert!(matches!(arg, StopArg::Borrowed(_)));
+        assert!(!arg.should_stop());
+        stopper.cancel();
+        assert_eq!(arg.check(), Err(StopReason::Cancelled));
+    }
+
+    #[test]
+    fn borrowed_dyn() {
+        let source = StopSource::new();
+        let dyn_stop: &dyn Stop = &source;
+        let arg = accept(dyn_stop);
+        source.cancel();
+        assert!(arg.should_stop());
+    }
+
+    #[test]
+    fn borrowed_stop_ref() {
+        let source = StopSource::new();
+        let stop_ref = source.as_ref();
+        let arg = accept(&stop_ref);
+        source.cancel();
+        assert!(arg.should_stop());
+    }
+
+    #[test]
+    fn owned_boxed_and_token() {
+        let stopper = Stopper::new();
+        let boxed = accept(BoxedStop::new(stopper.clone()));
+        let token = accept(stopper.clone().into_token());
+        assert!(matches!(boxed, StopArg::Owned(_)));
+        stopper.cancel();
+        assert!(boxed.should_stop());
+        assert!(token.should_stop());
+    }
+
+    #[test]
+    fn unstoppable_may_not_stop() {
+        let arg = accept(Unstoppable);
+        assert!(!arg.may_stop());
+        assert!(arg.check().is_ok());
+    }
+
+    #[test]
+    fn as_dyn_and_as_ref() {
+        let child = ChildStopper::new();
+        let arg = accept(&child);
+        child.cancel();
+        assert!(arg.as_dyn().should_stop());
+        let r: &dyn Stop = arg.as_ref();
+        assert!(r.should_stop());
+    }
+
+    #[test]
+    fn erased_as_ref_impls() {
+        let stopper = Stopper::new();
+        let token = stopper.clone().into_token();
+        let boxed = stopper.clone().into_boxed();
+        stopper.cancel();
+        assert!(AsRef::<dyn Stop>::as_ref(&token).should_stop());
+        assert!(AsRef::<dyn Stop>::as_ref(&boxed).should_stop());
+        assert!(!AsRef::<dyn Stop>::as_ref(&BoxedStop::new(Unstoppable)).should_stop());
+    }
+
+    #[test]
+    fn debug_format() {
+        let source = StopSource::new();
+        let debug = alloc::format!("{:?}", accept(&source));
+        assert!(debug.contains("Borrowed"));
+        let debug = alloc::format!("{:?}", accept(Unstoppable));
+        assert!(debug.contains("Owned"));
+    }
+
+    #[test]
+    fn stop_arg_is_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<StopArg<'_>>();
+    }
+}
@@ -0,0 +1,188 @@
+//! Bare `AtomicBool` flags as stops.
+//!
+//! Plenty of code already has an `AtomicBool` "stop requested" flag.
+//! [`AtomicBoolStop`] adapts one to [`Stop`] without a hand-written
+//! newtype: borrow it with [`AtomicBoolStop::from_ref()`] (works in `no_std`)
+//! or share it with [`AtomicBoolStop::from_arc()`] (`alloc`). The flag keeps
+//! its own meaning — `true` means stop — and is only ever read.
+//!
+//! # Example
+//!
+//! ```rust
+//! use almost_enough::{AtomicBoolStop, Stop, StopReason};
+//! use core::sync::atomic::{AtomicBool, Ordering};
+//!
+//! static SHUTDOWN: AtomicBool = AtomicBool::new(false);
+//!
+//! let stop = AtomicBoolStop::from_ref(&SHUTDOWN, Ordering::Relaxed);
+//! assert!(stop.check().is_ok());
+//!
+//! SHUTDOWN.store(true, Ordering::Relaxed);
+//! assert_eq!(stop.check(), Err(StopReason::Cancelled));
+//! ```
+
+use core::ops::Deref;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+#[cfg(feature = "alloc")]
+use alloc::sync::Arc;
+
+use crate::{Stop, StopReason};
+
+/// A [`Stop`] that reads an existing [`AtomicBool`] flag.
+///
+/// `F` is whatever holds the flag: `&AtomicBool` from
+/// [`from_ref()`](Self::from_ref) or `Arc<AtomicBool>` from
+/// [`from_arc()`](Self::from_arc). A set flag reports
+/// [`StopReason::Cancelled`].
+///
+/// The ordering is chosen by the caller. `Relaxed` is enough to notice the
+/// flag; use `Acquire` (paired with a `Release` store by whoever sets it)
+/// when data written before setting the flag must be visible after seeing
+/// it.
+#[derive(Debug, Clone)]
+pub struct AtomicBoolStop<F> {
+    flag: F,
+    ordering: Ordering,
+}
+
+impl<'a> AtomicBoolStop<&'a AtomicBool> {
+    /// Borrow `flag`, loading it with `ordering`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `ordering` is `Release` or `AcqRel`, which are not valid
+    /// for a load.
+    #[inline]
+    #[track_caller]
+    pub fn from_ref(flag: &'a AtomicBool, ordering: Ordering) -> Self {
+        Self::new(flag, ordering)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl AtomicBoolStop<Arc<AtomicBool>> {
+    /// Share `flag`, loading it with `ordering`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `ordering` is `Release` or `AcqRel`, which are not valid
+    /// for a load.
+    #[inline]
+    #[track_caller]
+    pub fn from_arc(flag: Arc<AtomicBool>, ordering: Ordering) -> Self {
+        Self::new(flag, ordering)
+    }
+}
+
+impl<F: Deref<Target = AtomicBool>> AtomicBoolStop<F> {
+    /// Wrap any pointer to an `AtomicBool`, loading it with `ordering`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `ordering` is `Release` or `AcqRel`, which are not valid
+    /// for a load.
+    #[inline]
+    #[track_caller]
+    pub fn new(flag: F, ordering: Ordering) -> Self {
+        assert!(
+            matches!(
+                ordering,
+                Ordering::Relaxed | Ordering::Acquire | Ordering::SeqCst
+            ),
+            "invalid load ordering {ordering:?}: use Relaxed, Acquire or SeqCst"
+        );
+        Self { flag, ordering }
+    }
+
+    /// The ordering used to load the flag.
+    #[inline]
+    pub fn ordering(&self) -> Ordering {
+        self.ordering
+    }
+
+    /// Get a reference to the flag.
+    #[inline]
+    pub fn flag(&self) -> &AtomicBool {
+        &self.flag
+    }
+
+    /// Unwrap and return the flag's holder.
+    #[inline]
+    pub fn into_inner(self) -> F {
+        self.flag
+    }
+
+    /// Load the flag, matching the ordering so each arm is a plain load.
+    #[inline(always)]
+    fn is_set(&self) -> bool {
+        match self.ordering {
+            Ordering::Relaxed => self.flag.load(Ordering::Relaxed),
+            Ordering::Acquire => self.flag.load(Ordering::Acquire),
+            _ => self.flag.load(Ordering::SeqCst),
+        }
+    }
+}
+
+impl<F: Deref<Target = AtomicBool> + Send + Sync> Stop for AtomicBoolStop<F> {
+    #[inline]
+    fn check(&self) -> Result<(), StopReason> {
+        if self.is_set() {
+            Err(StopReason::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+
+    #[inline]
+    fn should_stop(&self) -> bool {
+        self.is_set()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_ref_reads_flag() {
+        let flag = AtomicBool::new(false);
+        let stop = AtomicBoolStop::from_ref(&flag, Ordering::Acquire);
+        assert!(!stop.should_stop());
+        assert_eq!(stop.ordering(), Ordering::Acquire);
+
+        flag.store(true, Ordering::Release);
+        assert!(stop.should_stop());
+        assert_eq!(stop.check(), Err(StopReason::Cancelled));
+
+        flag.store(false, Ordering::Release);
+        assert!(stop.check().is_ok());
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn from_arc_is_shared() {
+        let flag = Arc::new(AtomicBool::new(false));
+        let stop = AtomicBoolStop::from_arc(Arc::clone(&flag), Ordering::SeqCst);
+        let copy = stop.clone();
+        flag.store(true, Ordering::SeqCst);
+        assert!(stop.should_stop());
+        assert!(copy.should_stop());
+        assert!(Arc::ptr_eq(&copy.into_inner(), &flag));
+    }
+
+    #[test]
+    fn usable_as_dyn_stop() {
+        let flag = AtomicBool::new(true);
+        let stop = AtomicBoolStop::from_ref(&flag, Ordering::Relaxed);
+        let dyn_stop: &dyn Stop = &stop;
+        assert!(dyn_stop.should_stop());
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid load ordering")]
+    fn rejects_store_ordering() {
+        let flag = AtomicBool::new(false);
+        let _ = AtomicBoolStop::from_ref(&flag, Ordering::Release);
+    }
+}
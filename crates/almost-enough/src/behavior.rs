@@ -0,0 +1,163 @@
+//! Versioned behavior switches.
+//!
+//! Some semantics have more than one defensible answer — for example, which
+//! reason to report when a timeout wrapper's inner stop is cancelled *and*
+//! its deadline has passed. [`Behavior`] names those choices so wrappers can
+//! be configured per instance, and so a future release can change a default
+//! by adding a new version constant instead of silently changing what
+//! existing code observes.
+//!
+//! # Versions
+//!
+//! [`Behavior::V1`] is the behavior of this release and is what
+//! [`Behavior::default()`] returns. Code that must keep today's semantics
+//! across upgrades can pin it explicitly; code that wants a different choice
+//! adjusts individual switches:
+//!
+//! ```rust
+//! # #[cfg(feature = "std")]
+//! # fn main() {
+//! use almost_enough::{Behavior, Stop, StopReason, Stopper, TieBreak, TimeoutExt};
+//! use std::time::Duration;
+//!
+//! let stopper = Stopper::cancelled();
+//!
+//! // V1: the inner reason wins a tie
+//! let stop = stopper.clone().with_timeout(Duration::ZERO);
+//! assert_eq!(stop.check(), Err(StopReason::Cancelled));
+//!
+//! // Opt in to the deadline winning instead
+//! let stop = stopper
+//!     .with_timeout(Duration::ZERO)
+//!     .with_behavior(Behavior::V1.with_tie_break(TieBreak::Deadline));
+//! assert_eq!(stop.check(), Err(StopReason::TimedOut));
+//! # }
+//! # #[cfg(not(feature = "std"))]
+//! # fn main() {}
+//! ```
+
+use crate::StopReason;
+
+/// Which reason a timeout wrapper reports when its inner stop has stopped
+/// and its deadline has also passed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
+pub enum TieBreak {
+    /// Report the inner stop's reason (usually [`StopReason::Cancelled`]).
+    ///
+    /// The deadline is not consulted once the inner stop has stopped, so
+    /// this is also the cheaper choice.
+    #[default]
+    Inner,
+    /// Report [`StopReason::TimedOut`] if the deadline has passed, even
+    /// though the inner stop also stopped.
+    ///
+    /// Useful when callers retry timeouts but not cancellations and a late
+    /// cancel should not mask a missed deadline.
+    Deadline,
+}
+
+/// A set of behavior switches for wrappers with contentious semantics.
+///
+/// Start from a version constant and adjust with the `with_*` methods. All
+/// methods are `const`, so a configuration can live in a `const` item.
+///
+/// Currently consumed by `WithTimeout`, `DebouncedTimeout` and
+/// `WithSystemDeadline` (with the `std` feature).
+///
+/// # Example
+///
+/// ```rust
+/// use almost_enough::{Behavior, TieBreak};
+///
+/// const STRICT_DEADLINES: Behavior = Behavior::V1.with_tie_break(TieBreak::Deadline);
+///
+/// assert_eq!(Behavior::default(), Behavior::V1);
+/// assert_eq!(STRICT_DEADLINES.tie_break(), TieBreak::Deadline);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Behavior {
+    tie_break: TieBreak,
+}
+
+impl Behavior {
+    /// The original semantics: the inner reason wins ties.
+    pub const V1: Self = Self {
+        tie_break: TieBreak::Inner,
+    };
+
+    /// How ties between an inner stop and a deadline are reported.
+    #[inline]
+    pub const fn tie_break(self) -> TieBreak {
+        self.tie_break
+    }
+
+    /// Set how ties between an inner stop and a deadline are reported.
+    #[inline]
+    pub const fn with_tie_break(mut self, tie_break: TieBreak) -> Self {
+        self.tie_break = tie_break;
+        self
+    }
+
+    /// Resolve the reason to report after the inner stop returned `inner`.
+    ///
+    /// `deadline_passed` is only called when the tie-break needs it.
+    #[inline]
+    #[cfg_attr(not(feature = "std"), allow(dead_code))]
+    pub(crate) fn resolve_tie(
+        self,
+        inner: StopReason,
+        deadline_passed: impl FnOnce() -> bool,
+    ) -> StopReason {
+        match self.tie_break {
+            TieBreak::Deadline if deadline_passed() => StopReason::TimedOut,
+            _ => inner,
+        }
+    }
+}
+
+impl Default for Behavior {
+    #[inline]
+    fn default() -> Self {
+        Self::V1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_is_v1() {
+        assert_eq!(Behavior::default(), Behavior::V1);
+        assert_eq!(Behavior::V1.tie_break(), TieBreak::Inner);
+        assert_eq!(TieBreak::default(), TieBreak::Inner);
+    }
+
+    #[test]
+    fn inner_wins_without_reading_deadline() {
+        let reason = Behavior::V1.resolve_tie(StopReason::Cancelled, || {
+            panic!("deadline should not be consulted")
+        });
+        assert_eq!(reason, StopReason::Cancelled);
+    }
+
+    #[test]
+    fn deadline_wins_only_when_passed() {
+        let behavior = Behavior::V1.with_tie_break(TieBreak::Deadline);
+        assert_eq!(
+            behavior.resolve_tie(StopReason::Cancelled, || true),
+            StopReason::TimedOut
+        );
+        assert_eq!(
+            behavior.resolve_tie(StopReason::Cancelled, || false),
+            StopReason::Cancelled
+        );
+    }
+
+    #[test]
+    fn usable_in_const() {
+        const B: Behavior = Behavior::V1.with_tie_break(TieBreak::Deadline);
+        assert_eq!(B.tie_break(), TieBreak::Deadline);
+    }
+}
@@ -5,8 +5,14 @@
 //!
 //! # When to Use
 //!
-//! **Prefer [`StopToken`](crate::StopToken)** which is `Clone` (via `Arc`).
-//! `BoxedStop` is retained for cases where unique ownership is required.
+//! **Prefer [`StopToken`](crate::StopToken)** which is `Clone` (via `Arc`)
+//! and supports indirection collapsing.
+//!
+//! `BoxedStop` is `Clone` too, so it can be stored in long-lived structs
+//! that derive `Clone`. A `BoxedStop` built from a [`CloneStop`] with
+//! [`BoxedStop::cloneable()`] or [`CloneStop::clone_boxed()`] clones the
+//! stop itself, exactly as cloning the concrete type would; one built with
+//! [`BoxedStop::new()`] shares the boxed stop between clones.
 //!
 //! Generic functions like `fn process(stop: impl Stop)` are monomorphized
 //! for each concrete type, increasing binary size. `BoxedStop` provides a
@@ -40,14 +46,18 @@
 //! ```
 
 use alloc::boxed::Box;
+use alloc::sync::Arc;
 
-use crate::{Stop, StopReason};
+use crate::{CloneStop, Stop, StopReason};
 
 /// A heap-allocated [`Stop`] implementation.
 ///
-/// **Prefer [`StopToken`](crate::StopToken)** which is `Clone` (via `Arc`) and
-/// supports indirection collapsing. `BoxedStop` is retained for cases where
-/// unique ownership is required.
+/// **Prefer [`StopToken`](crate::StopToken)** which supports indirection
+/// collapsing.
+///
+/// Cloning a `BoxedStop` built with [`cloneable()`](Self::cloneable) or
+/// [`CloneStop::clone_boxed()`] clones the stop itself; one built with
+/// [`new()`](Self::new) shares the boxed stop between clones.
 ///
 /// No-op stops (like `Unstoppable`) are optimized away at construction —
 /// `check()` short-circuits without any vtable dispatch.
@@ -71,19 +81,123 @@ use crate::{Stop, StopReason};
 /// process(BoxedStop::new(StopSource::new()));
 /// process(BoxedStop::new(Stopper::new()));
 /// ```
-pub struct BoxedStop(Option<Box<dyn Stop + Send + Sync>>);
+pub struct BoxedStop(Repr);
+
+enum Repr {
+    /// A no-op stop; nothing allocated.
+    Never,
+    /// A shared singleton such as [`CANCELLED`](crate::CANCELLED).
+    Static(&'static dyn Stop),
+    /// From [`BoxedStop::new()`]; clones share it.
+    Boxed(Arc<dyn Stop + Send + Sync>),
+    /// From [`BoxedStop::cloneable()`]; clones clone the stop.
+    Cloneable(Box<dyn DynClone + Send + Sync>),
+}
+
+/// Object-safe cloning for [`Repr::Cloneable`].
+trait DynClone: Stop {
+    fn clone_box(&self) -> Box<dyn DynClone + Send + Sync>;
+    fn as_stop(&self) -> &(dyn Stop + 'static);
+}
+
+impl<T: CloneStop> DynClone for T {
+    fn clone_box(&self) -> Box<dyn DynClone + Send + Sync> {
+        Box::new(self.clone())
+    }
+
+    fn as_stop(&self) -> &(dyn Stop + 'static) {
+        self
+    }
+}
 
 impl BoxedStop {
+    /// A boxed stop that never stops, without allocating.
+    ///
+    /// Same as `BoxedStop::new(Unstoppable)`; usable as a cheap default.
+    #[inline]
+    pub const fn never() -> Self {
+        Self(Repr::Never)
+    }
+
+    /// A boxed stop that is already cancelled, without allocating.
+    ///
+    /// Backed by the [`CANCELLED`](crate::CANCELLED) singleton.
+    ///
+    /// ```rust
+    /// use almost_enough::{BoxedStop, Stop, StopReason};
+    ///
+    /// assert_eq!(BoxedStop::cancelled().check(), Err(StopReason::Cancelled));
+    /// ```
+    #[inline]
+    pub fn cancelled() -> Self {
+        Self(Repr::Static(crate::CANCELLED))
+    }
+
     /// Create a new boxed stop from any [`Stop`] implementation.
     ///
     /// No-op stops (where `may_stop()` returns false) are not allocated —
     /// `check()` will short-circuit to `Ok(())`.
+    ///
+    /// Clones of the result share `stop`; use
+    /// [`cloneable()`](Self::cloneable) to clone it instead.
     #[inline]
     pub fn new<T: Stop + 'static>(stop: T) -> Self {
         if !stop.may_stop() {
-            return Self(None);
+            return Self::never();
+        }
+        Self(Repr::Boxed(Arc::new(stop)))
+    }
+
+    /// Create a new boxed stop whose clones clone `stop`.
+    ///
+    /// Cloning the result behaves like cloning `stop` itself, so a stop
+    /// that keeps per-clone state keeps it per clone of the `BoxedStop`.
+    /// No-op stops are not allocated, as with [`new()`](Self::new).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use almost_enough::{BoxedStop, Stop, Stopper};
+    ///
+    /// #[derive(Clone)]
+    /// struct Job {
+    ///     stop: BoxedStop,
+    /// }
+    ///
+    /// let stopper = Stopper::new();
+    /// let job = Job { stop: BoxedStop::cloneable(stopper.clone()) };
+    /// let retry = job.clone();
+    ///
+    /// stopper.cancel();
+    /// assert!(job.stop.should_stop() && retry.stop.should_stop());
+    /// ```
+    #[inline]
+    pub fn cloneable<T: CloneStop>(stop: T) -> Self {
+        if !stop.may_stop() {
+            return Self::never();
         }
-        Self(Some(Box::new(stop)))
+        Self(Repr::Cloneable(Box::new(stop)))
+    }
+}
+
+impl Clone for BoxedStop {
+    /// Clones the stop if this was built with
+    /// [`cloneable()`](Self::cloneable), otherwise shares it.
+    fn clone(&self) -> Self {
+        Self(match &self.0 {
+            Repr::Never => Repr::Never,
+            Repr::Static(inner) => Repr::Static(*inner),
+            Repr::Boxed(inner) => Repr::Boxed(Arc::clone(inner)),
+            Repr::Cloneable(inner) => Repr::Cloneable((**inner).clone_box()),
+        })
+    }
+}
+
+impl Default for BoxedStop {
+    /// Same as [`BoxedStop::never()`].
+    #[inline]
+    fn default() -> Self {
+        Self::never()
     }
 }
 
@@ -91,22 +205,38 @@ impl Stop for BoxedStop {
     #[inline]
     fn check(&self) -> Result<(), StopReason> {
         match &self.0 {
-            Some(inner) => inner.check(),
-            None => Ok(()),
+            Repr::Never => Ok(()),
+            Repr::Static(inner) => inner.check(),
+            Repr::Boxed(inner) => inner.check(),
+            Repr::Cloneable(inner) => inner.check(),
         }
     }
 
     #[inline]
     fn should_stop(&self) -> bool {
         match &self.0 {
-            Some(inner) => inner.should_stop(),
-            None => false,
+            Repr::Never => false,
+            Repr::Static(inner) => inner.should_stop(),
+            Repr::Boxed(inner) => inner.should_stop(),
+            Repr::Cloneable(inner) => inner.should_stop(),
         }
     }
 
     #[inline]
     fn may_stop(&self) -> bool {
-        self.0.is_some()
+        !matches!(self.0, Repr::Never)
+    }
+}
+
+impl AsRef<dyn Stop> for BoxedStop {
+    #[inline]
+    fn as_ref(&self) -> &(dyn Stop + 'static) {
+        match &self.0 {
+            Repr::Never => crate::NEVER,
+            Repr::Static(inner) => *inner,
+            Repr::Boxed(inner) => &**inner,
+            Repr::Cloneable(inner) => (**inner).as_stop(),
+        }
     }
 }
 
@@ -172,6 +302,59 @@ mod tests {
         assert!(BoxedStop::new(Stopper::new()).may_stop());
     }
 
+    #[test]
+    fn singletons_without_allocation() {
+        let never = BoxedStop::never();
+        assert!(!never.may_stop());
+        assert!(BoxedStop::default().check().is_ok());
+
+        let cancelled = BoxedStop::cancelled();
+        assert!(cancelled.may_stop());
+        assert!(cancelled.should_stop());
+        assert_eq!(cancelled.as_ref().check(), Err(StopReason::Cancelled));
+    }
+
+    #[test]
+    fn clone_shares_or_clones() {
+        use core::sync::atomic::{AtomicUsize, Ordering};
+
+        static CLONES: AtomicUsize = AtomicUsize::new(0);
+
+        struct Counted(Stopper);
+
+        impl Clone for Counted {
+            fn clone(&self) -> Self {
+                CLONES.fetch_add(1, Ordering::Relaxed);
+                Self(self.0.clone())
+            }
+        }
+
+        impl Stop for Counted {
+            fn check(&self) -> Result<(), StopReason> {
+                self.0.check()
+            }
+        }
+
+        let stopper = Stopper::new();
+        let shared = BoxedStop::new(Counted(stopper.clone()));
+        let _ = shared.clone();
+        assert_eq!(CLONES.load(Ordering::Relaxed), 0);
+
+        let cloned = BoxedStop::cloneable(Counted(stopper.clone()));
+        let copy = cloned.clone();
+        assert_eq!(CLONES.load(Ordering::Relaxed), 1);
+        let by_ref = Counted(stopper.clone()).clone_boxed();
+        assert_eq!(CLONES.load(Ordering::Relaxed), 2);
+
+        stopper.cancel();
+        for stop in [&shared, &cloned, &copy, &by_ref] {
+            assert_eq!(stop.check(), Err(StopReason::Cancelled));
+            assert_eq!(stop.as_ref().check(), Err(StopReason::Cancelled));
+        }
+        assert!(!BoxedStop::cloneable(Unstoppable).clone().may_stop());
+        assert!(BoxedStop::cancelled().clone().should_stop());
+    }
+
     #[test]
     fn unstoppable_no_allocation() {
         // Unstoppable wraps to None — no heap allocation
@@ -0,0 +1,294 @@
+//! Work-budget cancellation.
+//!
+//! [`BudgetStop`] trips after a fixed amount of work has been spent, either
+//! counted in `check()` calls or in caller-defined units via
+//! [`consume()`](BudgetStop::consume). Works in `no_std` environments.
+//!
+//! # Example
+//!
+//! ```rust
+//! use almost_enough::{BudgetStop, Stop, StopReason};
+//!
+//! // Decode at most 1000 pixels
+//! let budget = BudgetStop::new(1000);
+//!
+//! assert!(budget.consume(600).is_ok());
+//! assert!(budget.consume(400).is_ok()); // exactly at the limit
+//! assert!(!budget.should_stop());
+//!
+//! assert_eq!(budget.consume(1), Err(StopReason::TimedOut));
+//! assert!(budget.should_stop());
+//! ```
+//!
+//! # Composition
+//!
+//! `BudgetStop` is a regular [`Stop`], so it composes with real
+//! cancellation via [`.or()`](crate::StopExt::or):
+//!
+//! ```rust
+//! use almost_enough::{BudgetStop, StopSource, Stop, StopExt};
+//!
+//! let cancel = StopSource::new();
+//! let budget = BudgetStop::checks(100);
+//! let stop = cancel.as_ref().or(&budget);
+//!
+//! let mut iterations = 0;
+//! while stop.check().is_ok() {
+//!     iterations += 1;
+//! }
+//! assert_eq!(iterations, 100);
+//! ```
+//!
+//! # Stop Reason
+//!
+//! An exhausted budget reports [`StopReason::TimedOut`]: a work budget is the
+//! work-unit analogue of a deadline, and like a timeout the operation might
+//! succeed if retried with a larger budget.
+
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use crate::{Stop, StopReason};
+
+/// A [`Stop`] implementation that trips once a work budget is spent.
+///
+/// Two accounting modes are available:
+///
+/// - [`BudgetStop::new(units)`](Self::new) — only [`consume()`](Self::consume)
+///   spends the budget; `check()` just observes it.
+/// - [`BudgetStop::checks(n)`](Self::checks) — every `check()` (or
+///   `should_stop()`) spends one unit, so the first `n` checks pass and the
+///   next one stops.
+///
+/// Spending exactly the whole budget is allowed; the stop trips when a
+/// request exceeds what remains. Once tripped, it stays tripped.
+///
+/// # Example
+///
+/// ```rust
+/// use almost_enough::{BudgetStop, Stop};
+///
+/// let budget = BudgetStop::checks(3);
+/// assert!(budget.check().is_ok());
+/// assert!(budget.check().is_ok());
+/// assert!(budget.check().is_ok());
+/// assert!(budget.check().is_err());
+/// ```
+///
+/// # Thread Safety
+///
+/// The budget is shared through atomics, so one `BudgetStop` can bound the
+/// combined work of several threads (share it by reference or `Arc`).
+pub struct BudgetStop {
+    remaining: AtomicUsize,
+    exhausted: AtomicBool,
+    count_checks: bool,
+}
+
+impl BudgetStop {
+    /// Create a budget of `units` that is spent via [`consume()`](Self::consume).
+    ///
+    /// `check()` does not spend anything in this mode.
+    #[inline]
+    pub const fn new(units: usize) -> Self {
+        Self {
+            remaining: AtomicUsize::new(units),
+            exhausted: AtomicBool::new(false),
+            count_checks: false,
+        }
+    }
+
+    /// Create a budget that allows `n` calls to `check()` before stopping.
+    ///
+    /// [`consume()`](Self::consume) can still be used to spend extra units.
+    #[inline]
+    pub const fn checks(n: usize) -> Self {
+        Self {
+            remaining: AtomicUsize::new(n),
+            exhausted: AtomicBool::new(false),
+            count_checks: true,
+        }
+    }
+
+    /// Spend `units` from the budget.
+    ///
+    /// Returns `Err(StopReason::TimedOut)` if fewer than `units` remain (the
+    /// budget is then marked exhausted) or if it was already exhausted.
+    #[inline]
+    pub fn consume(&self, units: usize) -> Result<(), StopReason> {
+        if self.exhausted.load(Ordering::Relaxed) {
+            return Err(StopReason::TimedOut);
+        }
+        let spent = self
+            .remaining
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |r| {
+                r.checked_sub(units)
+            });
+        match spent {
+            Ok(_) => Ok(()),
+            Err(_) => {
+                self.remaining.store(0, Ordering::Relaxed);
+                self.exhausted.store(true, Ordering::Relaxed);
+                Err(StopReason::TimedOut)
+            }
+        }
+    }
+
+    /// Units left in the budget.
+    #[inline]
+    pub fn remaining(&self) -> usize {
+        self.remaining.load(Ordering::Relaxed)
+    }
+
+    /// Returns `true` once a request has exceeded the budget.
+    #[inline]
+    pub fn is_exhausted(&self) -> bool {
+        self.exhausted.load(Ordering::Relaxed)
+    }
+
+    /// Returns `true` if `check()` spends one unit per call.
+    #[inline]
+    pub fn counts_checks(&self) -> bool {
+        self.count_checks
+    }
+}
+
+impl Stop for BudgetStop {
+    #[inline]
+    fn check(&self) -> Result<(), StopReason> {
+        if self.count_checks {
+            self.consume(1)
+        } else if self.exhausted.load(Ordering::Relaxed) {
+            Err(StopReason::TimedOut)
+        } else {
+            Ok(())
+        }
+    }
+
+    #[inline]
+    fn should_stop(&self) -> bool {
+        self.check().is_err()
+    }
+}
+
+impl core::fmt::Debug for BudgetStop {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("BudgetStop")
+            .field("remaining", &self.remaining())
+            .field("exhausted", &self.is_exhausted())
+            .field("count_checks", &self.count_checks)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{StopExt, StopSource};
+
+    #[test]
+    fn units_budget_allows_exact_spend() {
+        let budget = BudgetStop::new(10);
+        assert!(budget.consume(4).is_ok());
+        assert!(budget.consume(6).is_ok());
+        assert_eq!(budget.remaining(), 0);
+        assert!(!budget.is_exhausted());
+        assert!(budget.check().is_ok());
+    }
+
+    #[test]
+    fn units_budget_trips_on_overdraw() {
+        let budget = BudgetStop::new(10);
+        assert!(budget.consume(8).is_ok());
+        assert_eq!(budget.consume(3), Err(StopReason::TimedOut));
+        assert!(budget.is_exhausted());
+        assert_eq!(budget.remaining(), 0);
+        assert_eq!(budget.check(), Err(StopReason::TimedOut));
+        assert!(budget.should_stop());
+    }
+
+    #[test]
+    fn units_budget_check_does_not_spend() {
+        let budget = BudgetStop::new(1);
+        for _ in 0..100 {
+            assert!(budget.check().is_ok());
+        }
+        assert_eq!(budget.remaining(), 1);
+    }
+
+    #[test]
+    fn exhausted_stays_exhausted() {
+        let budget = BudgetStop::new(5);
+        assert!(budget.consume(6).is_err());
+        assert_eq!(budget.consume(0), Err(StopReason::TimedOut));
+    }
+
+    #[test]
+    fn checks_budget_counts_calls() {
+        let budget = BudgetStop::checks(3);
+        assert!(budget.counts_checks());
+        assert!(budget.check().is_ok());
+        assert!(!budget.should_stop());
+        assert!(budget.check().is_ok());
+        assert_eq!(budget.check(), Err(StopReason::TimedOut));
+        assert!(budget.should_stop());
+    }
+
+    #[test]
+    fn zero_checks_stops_immediately() {
+        let budget = BudgetStop::checks(0);
+        assert_eq!(budget.check(), Err(StopReason::TimedOut));
+    }
+
+    #[test]
+    fn composes_with_or() {
+        let cancel = StopSource::new();
+        let budget = BudgetStop::new(100);
+        let stop = cancel.as_ref().or(&budget);
+
+        assert!(stop.check().is_ok());
+        cancel.cancel();
+        assert_eq!(stop.check(), Err(StopReason::Cancelled));
+
+        let cancel = StopSource::new();
+        let stop = cancel.as_ref().or(&budget);
+        assert!(budget.consume(101).is_err());
+        assert_eq!(stop.check(), Err(StopReason::TimedOut));
+    }
+
+    #[test]
+    fn const_construction() {
+        static BUDGET: BudgetStop = BudgetStop::new(42);
+        assert_eq!(BUDGET.remaining(), 42);
+    }
+
+    #[test]
+    fn budget_is_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<BudgetStop>();
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod std_tests {
+    use super::*;
+
+    #[test]
+    fn shared_across_threads() {
+        let budget = BudgetStop::new(1000);
+        std::thread::scope(|s| {
+            for _ in 0..4 {
+                s.spawn(|| while budget.consume(1).is_ok() {});
+            }
+        });
+        assert!(budget.is_exhausted());
+        assert_eq!(budget.remaining(), 0);
+    }
+
+    #[test]
+    fn debug_format() {
+        let budget = BudgetStop::new(7);
+        let debug = format!("{budget:?}");
+        assert!(debug.contains("BudgetStop"));
+        assert!(debug.contains("remaining: 7"));
+    }
+}
@@ -0,0 +1,245 @@
+//! Remember why a stop fired.
+//!
+//! Combinators such as [`OrStop`](crate::OrStop) and `WithTimeout` work out
+//! a [`StopReason`] in `check()`, but code that branches on
+//! `should_stop()` only gets a `bool`. Calling `check()` afterwards is a
+//! second evaluation: a timeout may have expired in between, or an inner
+//! stop may report differently. [`ReasonCache`] records the reason of the
+//! first failure so it can be read back with
+//! [`last_reason()`](ReasonCache::last_reason). Works in `no_std`
+//! environments.
+//!
+//! # Example
+//!
+//! ```rust
+//! use almost_enough::{Stop, StopExt, StopReason, StopSource};
+//!
+//! let user = StopSource::new();
+//! let budget = StopSource::new();
+//! let stop = user.as_ref().or(budget.as_ref()).cache_reason();
+//!
+//! user.cancel();
+//! if stop.should_stop() {
+//!     assert_eq!(stop.last_reason(), Some(StopReason::Cancelled));
+//! }
+//! ```
+
+use core::sync::atomic::{AtomicU8, Ordering};
+
+use crate::reason::{NOT_STOPPED, decode, encode};
+use crate::{Stop, StopReason};
+
+/// A [`Stop`] wrapper that caches the reason of the first failed check.
+///
+/// Created by [`StopExt::cache_reason()`](crate::StopExt::cache_reason) or
+/// [`ReasonCache::new()`]. Once the inner stop fails, the reason is
+/// latched: later checks report it without consulting the inner stop, and
+/// [`last_reason()`](Self::last_reason) returns it.
+///
+/// `should_stop()` calls the inner stop's `check()` rather than its
+/// `should_stop()`, so that a `true` always has a reason behind it. For
+/// most stops the two cost the same.
+///
+/// Wrap a `Copy` combinator such as `OrStop` rather than adding state to
+/// it; the wrapper itself is `Clone` but not `Copy`.
+///
+/// # Example
+///
+/// ```rust
+/// # #[cfg(feature = "std")]
+/// # fn main() {
+/// use almost_enough::{ReasonCache, Stop, StopReason, Stopper, TimeoutExt};
+/// use std::time::Duration;
+///
+/// let stop = ReasonCache::new(Stopper::new().with_timeout(Duration::ZERO));
+/// assert!(stop.last_reason().is_none());
+///
+/// assert!(stop.should_stop());
+/// assert_eq!(stop.last_reason(), Some(StopReason::TimedOut));
+/// # }
+/// # #[cfg(not(feature = "std"))]
+/// # fn main() {}
+/// ```
+pub struct ReasonCache<T> {
+    inner: T,
+    state: AtomicU8,
+}
+
+impl<T: Stop> ReasonCache<T> {
+    /// Wrap `inner`.
+    #[inline]
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            state: AtomicU8::new(NOT_STOPPED),
+        }
+    }
+
+    /// The reason of the first failed check, or `None` if no check has
+    /// failed yet.
+    ///
+    /// Never consults the inner stop.
+    #[inline]
+    pub fn last_reason(&self) -> Option<StopReason> {
+        match self.state.load(Ordering::Relaxed) {
+            NOT_STOPPED => None,
+            state => Some(decode(state)),
+        }
+    }
+
+    /// Get a reference to the inner stop.
+    #[inline]
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Unwrap and return the inner stop.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    /// Record `reason` unless another thread recorded one first, and
+    /// return whichever reason is stored.
+    #[cold]
+    fn record(&self, reason: StopReason) -> StopReason {
+        match self.state.compare_exchange(
+            NOT_STOPPED,
+            encode(reason),
+            Ordering::Relaxed,
+            Ordering::Relaxed,
+        ) {
+            Ok(_) => reason,
+            Err(current) => decode(current),
+        }
+    }
+}
+
+impl<T: Stop> Stop for ReasonCache<T> {
+    #[inline]
+    fn check(&self) -> Result<(), StopReason> {
+        if let Some(reason) = self.last_reason() {
+            return Err(reason);
+        }
+        self.inner.check().map_err(|reason| self.record(reason))
+    }
+
+    #[inline]
+    fn should_stop(&self) -> bool {
+        self.check().is_err()
+    }
+
+    #[inline]
+    fn may_stop(&self) -> bool {
+        self.inner.may_stop()
+    }
+}
+
+impl<T: Clone> Clone for ReasonCache<T> {
+    /// The clone keeps any cached reason.
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            state: AtomicU8::new(self.state.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+impl<T: core::fmt::Debug> core::fmt::Debug for ReasonCache<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let state = self.state.load(Ordering::Relaxed);
+        let reason = (state != NOT_STOPPED).then(|| decode(state));
+        f.debug_struct("ReasonCache")
+            .field("inner", &self.inner)
+            .field("last_reason", &reason)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FnStop, StopExt, StopSource, Unstoppable};
+    use core::sync::atomic::AtomicUsize;
+
+    #[test]
+    fn none_until_a_check_fails() {
+        let source = StopSource::new();
+        let stop = source.as_ref().cache_reason();
+        assert!(!stop.should_stop());
+        assert_eq!(stop.last_reason(), None);
+
+        // Cancelling alone does not populate the cache
+        source.cancel();
+        assert_eq!(stop.last_reason(), None);
+        assert!(stop.should_stop());
+        assert_eq!(stop.last_reason(), Some(StopReason::Cancelled));
+    }
+
+    #[test]
+    fn first_reason_wins() {
+        let calls = AtomicUsize::new(0);
+        let stop = FnStop::with_reason(|| match calls.fetch_add(1, Ordering::Relaxed) {
+            0 => Some(StopReason::TimedOut),
+            _ => Some(StopReason::Cancelled),
+        })
+        .cache_reason();
+
+        assert!(stop.should_stop());
+        assert_eq!(stop.check(), Err(StopReason::TimedOut));
+        assert_eq!(stop.last_reason(), Some(StopReason::TimedOut));
+        // The inner stop was only evaluated once
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn or_stop_reason_is_kept() {
+        let a = StopSource::new();
+        let failed = FnStop::with_reason(|| Some(StopReason::Failed));
+        let stop = a.as_ref().or(failed).cache_reason();
+        assert!(stop.should_stop());
+        assert_eq!(stop.last_reason(), Some(StopReason::Failed));
+        assert!(stop.inner().first().check().is_ok());
+    }
+
+    #[test]
+    fn clone_keeps_cached_reason() {
+        let source = StopSource::cancelled();
+        let stop = ReasonCache::new(source.as_ref());
+        let _ = stop.check();
+        let clone = stop.clone();
+        assert_eq!(clone.last_reason(), Some(StopReason::Cancelled));
+    }
+
+    #[test]
+    fn forwards_may_stop() {
+        assert!(!ReasonCache::new(Unstoppable).may_stop());
+        let source = StopSource::new();
+        assert!(ReasonCache::new(source.as_ref()).may_stop());
+    }
+
+    #[test]
+    fn is_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<ReasonCache<Unstoppable>>();
+        assert_eq!(ReasonCache::new(Unstoppable).into_inner(), Unstoppable);
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod std_tests {
+    use super::*;
+    use crate::{StopExt, Stopper, TimeoutExt};
+    use std::time::Duration;
+
+    #[test]
+    fn timeout_reason_after_should_stop() {
+        let stop = Stopper::new()
+            .with_timeout(Duration::from_millis(1))
+            .cache_reason();
+        std::thread::sleep(Duration::from_millis(5));
+        assert!(stop.should_stop());
+        assert_eq!(stop.last_reason(), Some(StopReason::TimedOut));
+        assert!(format!("{stop:?}").contains("TimedOut"));
+    }
+}
@@ -0,0 +1,193 @@
+//! A cached value tied to a stopper's epoch.
+//!
+//! Long-lived structs often cache state derived per request (scaled
+//! buffers, lookup tables) that is only valid until the request is
+//! cancelled. [`StopCell`] stores the value together with the
+//! [`Stopper::epoch()`] it was computed under, and recomputes it once that
+//! stopper has changed state, or when a different stopper is passed in.
+
+use alloc::sync::Arc;
+
+use crate::Stopper;
+
+/// A value cached under a [`Stopper`] epoch.
+///
+/// The value is recomputed once the stopper it was computed under changes
+/// [epoch](Stopper::epoch), or when a different stopper is passed in. The
+/// cell keeps a clone of that stopper, so it can tell it apart from any
+/// other, even one created after it was dropped.
+///
+/// # Example
+///
+/// ```rust
+/// use almost_enough::{StopCell, Stopper};
+///
+/// struct Resizer {
+///     lut: StopCell<Vec<u16>>,
+/// }
+///
+/// let mut resizer = Resizer { lut: StopCell::new() };
+/// let request = Stopper::new();
+///
+/// let mut builds = 0;
+/// resizer.lut.get_or_recompute(&request, || { builds += 1; vec![0; 256] });
+/// resizer.lut.get_or_recompute(&request, || { builds += 1; vec![0; 256] });
+/// assert_eq!(builds, 1);
+///
+/// request.cancel(); // the cached table is now stale
+/// assert!(resizer.lut.get(&request).is_none());
+/// resizer.lut.get_or_recompute(&request, || { builds += 1; vec![0; 256] });
+/// assert_eq!(builds, 2);
+/// ```
+#[derive(Debug, Clone)]
+pub struct StopCell<T> {
+    entry: Option<Entry<T>>,
+}
+
+#[derive(Debug, Clone)]
+struct Entry<T> {
+    stopper: Stopper,
+    epoch: u64,
+    value: T,
+}
+
+impl<T> Entry<T> {
+    #[inline]
+    fn is_current(&self, stop: &Stopper) -> bool {
+        Arc::ptr_eq(&self.stopper.inner, &stop.inner) && self.epoch == stop.epoch()
+    }
+}
+
+impl<T> StopCell<T> {
+    /// An empty cell.
+    #[inline]
+    pub const fn new() -> Self {
+        Self { entry: None }
+    }
+
+    /// The cached value, recomputed with `f` if the cell is empty or was
+    /// filled under another stopper or an earlier epoch of `stop`.
+    #[inline]
+    pub fn get_or_recompute(&mut self, stop: &Stopper, f: impl FnOnce() -> T) -> &T {
+        match &mut self.entry {
+            Some(entry) if entry.is_current(stop) => {}
+            slot => {
+                // Read the epoch first: a cancel during `f` makes the value stale
+                let epoch = stop.epoch();
+                *slot = Some(Entry {
+                    stopper: stop.clone(),
+                    epoch,
+                    value: f(),
+                });
+            }
+        }
+        // Filled above
+        &self.entry.as_ref().unwrap().value
+    }
+
+    /// The cached value, if it is still valid under `stop`.
+    #[inline]
+    pub fn get(&self, stop: &Stopper) -> Option<&T> {
+        self.entry
+            .as_ref()
+            .filter(|entry| entry.is_current(stop))
+            .map(|entry| &entry.value)
+    }
+
+    /// The epoch the cached value was computed under, if any.
+    #[inline]
+    pub fn epoch(&self) -> Option<u64> {
+        self.entry.as_ref().map(|entry| entry.epoch)
+    }
+
+    /// Drop the cached value, valid or not.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.entry = None;
+    }
+
+    /// Take the cached value, valid or not, leaving the cell empty.
+    #[inline]
+    pub fn take(&mut self) -> Option<T> {
+        self.entry.take().map(|entry| entry.value)
+    }
+
+    /// Unwrap the cached value, valid or not.
+    #[inline]
+    pub fn into_inner(self) -> Option<T> {
+        self.entry.map(|entry| entry.value)
+    }
+}
+
+impl<T> Default for StopCell<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn caches_until_cancel() {
+        let stop = Stopper::new();
+        let mut cell = StopCell::new();
+        assert!(cell.get(&stop).is_none());
+        assert_eq!(cell.epoch(), None);
+
+        assert_eq!(*cell.get_or_recompute(&stop, || 1), 1);
+        assert_eq!(*cell.get_or_recompute(&stop, || 2), 1);
+        assert_eq!(cell.get(&stop), Some(&1));
+        assert_eq!(cell.epoch(), Some(0));
+
+        stop.cancel();
+        assert!(cell.get(&stop).is_none());
+        assert_eq!(*cell.get_or_recompute(&stop, || 3), 3);
+        assert_eq!(cell.epoch(), Some(1));
+
+        // Cancelling again does not change the epoch
+        stop.cancel();
+        assert_eq!(*cell.get_or_recompute(&stop, || 4), 3);
+    }
+
+    #[test]
+    fn other_stopper_invalidates() {
+        let a = Stopper::new();
+        let b = Stopper::new();
+        let mut cell = StopCell::new();
+        cell.get_or_recompute(&a, || "a");
+        assert!(cell.get(&b).is_none());
+        assert_eq!(*cell.get_or_recompute(&b, || "b"), "b");
+        assert!(cell.get(&a).is_none());
+
+        // Clones share the stopper, so they share the cache
+        assert_eq!(cell.get(&b.clone()), Some(&"b"));
+    }
+
+    #[test]
+    fn cancel_during_recompute_leaves_value_stale() {
+        let stop = Stopper::new();
+        let mut cell = StopCell::new();
+        cell.get_or_recompute(&stop, || {
+            stop.cancel();
+            0
+        });
+        assert!(cell.get(&stop).is_none());
+    }
+
+    #[test]
+    fn take_and_clear() {
+        let stop = Stopper::new();
+        let mut cell = StopCell::default();
+        cell.get_or_recompute(&stop, || 5);
+        assert_eq!(cell.clone().into_inner(), Some(5));
+        assert_eq!(cell.take(), Some(5));
+        assert_eq!(cell.take(), None);
+
+        cell.get_or_recompute(&stop, || 6);
+        cell.clear();
+        assert!(cell.get(&stop).is_none());
+    }
+}
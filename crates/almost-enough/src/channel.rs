@@ -0,0 +1,263 @@
+//! Cancellation driven by a control channel.
+//!
+//! Worker threads are often handed the receiving end of a channel by a
+//! controller. [`ChannelStop`] turns that receiver into a [`Stop`]: the
+//! worker stops when the controller sends a message (any message is the
+//! stop signal) or when every sender is dropped — "the controller went
+//! away" is treated as cancellation.
+//!
+//! Works with [`std::sync::mpsc`] receivers; with the `crossbeam` feature,
+//! `crossbeam_channel` receivers too.
+//!
+//! # Example
+//!
+//! ```rust
+//! use almost_enough::{ChannelStop, Stop};
+//! use std::sync::mpsc;
+//!
+//! let (controller, rx) = mpsc::channel::<()>();
+//! let stop = ChannelStop::new(rx);
+//!
+//! let worker = std::thread::spawn(move || {
+//!     let mut items = 0u64;
+//!     while !stop.should_stop() {
+//!         items += 1;
+//!         std::thread::yield_now();
+//!     }
+//!     items
+//! });
+//!
+//! drop(controller); // controller went away
+//! worker.join().unwrap();
+//! ```
+
+use std::boxed::Box;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Mutex, TryLockError, mpsc};
+
+use crate::{Stop, StopReason};
+
+const RUNNING: u8 = 0;
+const MESSAGE: u8 = 1;
+const DISCONNECTED: u8 = 2;
+
+/// Polls the receiver once; returns the tripped state, if any.
+type Probe = Box<dyn Fn() -> u8 + Send + Sync>;
+
+/// A [`Stop`] that trips when a message arrives on a channel or all of
+/// its senders are dropped.
+///
+/// Both cases report [`StopReason::Cancelled`];
+/// [`is_disconnected()`](Self::is_disconnected) tells them apart. The
+/// message itself is discarded, so use a dedicated control channel
+/// (typically `Sender<()>`) rather than a data channel.
+///
+/// Once tripped, the state is latched and `check()` is a single atomic
+/// load. Until then each `check()` is a non-blocking `try_recv()`; a
+/// `std::sync::mpsc::Receiver` is not `Sync`, so it sits behind a mutex
+/// and a check that finds it locked by another thread reports "not yet".
+///
+/// Requires the `std` feature.
+///
+/// # Example
+///
+/// ```rust
+/// use almost_enough::{ChannelStop, Stop, StopReason};
+/// use std::sync::mpsc;
+///
+/// let (tx, rx) = mpsc::channel();
+/// let stop = ChannelStop::new(rx);
+/// assert!(stop.check().is_ok());
+///
+/// tx.send(()).unwrap(); // explicit stop request
+/// assert_eq!(stop.check(), Err(StopReason::Cancelled));
+/// assert!(!stop.is_disconnected());
+/// ```
+pub struct ChannelStop {
+    probe: Probe,
+    state: AtomicU8,
+}
+
+impl ChannelStop {
+    /// Stop when `rx` receives a message or its senders are all dropped.
+    ///
+    /// Accepts receivers from both [`mpsc::channel()`] and
+    /// [`mpsc::sync_channel()`].
+    pub fn new<T: Send + 'static>(rx: mpsc::Receiver<T>) -> Self {
+        let rx = Mutex::new(rx);
+        Self::from_probe(Box::new(move || {
+            let rx = match rx.try_lock() {
+                Ok(rx) => rx,
+                Err(TryLockError::Poisoned(e)) => e.into_inner(),
+                Err(TryLockError::WouldBlock) => return RUNNING,
+            };
+            match rx.try_recv() {
+                Ok(_) => MESSAGE,
+                Err(mpsc::TryRecvError::Empty) => RUNNING,
+                Err(mpsc::TryRecvError::Disconnected) => DISCONNECTED,
+            }
+        }))
+    }
+
+    /// Stop when a `crossbeam_channel` receiver receives a message or its
+    /// senders are all dropped.
+    ///
+    /// Requires the `crossbeam` feature.
+    #[cfg(feature = "crossbeam")]
+    pub fn crossbeam<T: Send + 'static>(rx: crossbeam_channel::Receiver<T>) -> Self {
+        Self::from_probe(Box::new(move || match rx.try_recv() {
+            Ok(_) => MESSAGE,
+            Err(crossbeam_channel::TryRecvError::Empty) => RUNNING,
+            Err(crossbeam_channel::TryRecvError::Disconnected) => DISCONNECTED,
+        }))
+    }
+
+    fn from_probe(probe: Probe) -> Self {
+        Self {
+            probe,
+            state: AtomicU8::new(RUNNING),
+        }
+    }
+
+    /// Returns `true` if the stop tripped because every sender was dropped.
+    ///
+    /// Only reflects what a previous `check()` observed.
+    #[inline]
+    pub fn is_disconnected(&self) -> bool {
+        self.state.load(Ordering::Relaxed) == DISCONNECTED
+    }
+
+    #[cold]
+    fn poll(&self) -> u8 {
+        match (self.probe)() {
+            RUNNING => RUNNING,
+            tripped => {
+                // Keep the first observation if threads race
+                match self.state.compare_exchange(
+                    RUNNING,
+                    tripped,
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => tripped,
+                    Err(current) => current,
+                }
+            }
+        }
+    }
+}
+
+impl Stop for ChannelStop {
+    #[inline]
+    fn check(&self) -> Result<(), StopReason> {
+        if self.should_stop() {
+            Err(StopReason::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+
+    #[inline]
+    fn should_stop(&self) -> bool {
+        self.state.load(Ordering::Relaxed) != RUNNING || self.poll() != RUNNING
+    }
+}
+
+impl core::fmt::Debug for ChannelStop {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let state = match self.state.load(Ordering::Relaxed) {
+            RUNNING => "running",
+            MESSAGE => "message",
+            _ => "disconnected",
+        };
+        f.debug_struct("ChannelStop")
+            .field("state", &state)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn message_stops() {
+        let (tx, rx) = mpsc::channel();
+        let stop = ChannelStop::new(rx);
+        assert!(!stop.should_stop());
+
+        tx.send("stop").unwrap();
+        assert_eq!(stop.check(), Err(StopReason::Cancelled));
+        assert!(!stop.is_disconnected());
+    }
+
+    #[test]
+    fn dropped_sender_stops() {
+        let (tx, rx) = mpsc::sync_channel::<()>(0);
+        let stop = ChannelStop::new(rx);
+        let tx2 = tx.clone();
+        drop(tx);
+        assert!(stop.check().is_ok());
+
+        drop(tx2);
+        assert_eq!(stop.check(), Err(StopReason::Cancelled));
+        assert!(stop.is_disconnected());
+    }
+
+    #[test]
+    fn state_latches() {
+        let (tx, rx) = mpsc::channel();
+        let stop = ChannelStop::new(rx);
+        tx.send(()).unwrap();
+        assert!(stop.should_stop());
+        // Later disconnect does not change the recorded cause
+        drop(tx);
+        assert!(stop.should_stop());
+        assert!(!stop.is_disconnected());
+    }
+
+    #[test]
+    fn shared_across_threads() {
+        let (tx, rx) = mpsc::channel::<()>();
+        let stop = Arc::new(ChannelStop::new(rx));
+        let workers: Vec<_> = (0..4)
+            .map(|_| {
+                let stop = Arc::clone(&stop);
+                std::thread::spawn(move || while !stop.should_stop() {})
+            })
+            .collect();
+        drop(tx);
+        for worker in workers {
+            worker.join().unwrap();
+        }
+        assert!(stop.is_disconnected());
+    }
+
+    #[cfg(feature = "crossbeam")]
+    #[test]
+    fn crossbeam_receiver() {
+        let (tx, rx) = crossbeam_channel::unbounded::<u32>();
+        let stop = ChannelStop::crossbeam(rx);
+        assert!(!stop.should_stop());
+        tx.send(1).unwrap();
+        assert!(stop.should_stop());
+        assert!(!stop.is_disconnected());
+
+        let (tx, rx) = crossbeam_channel::bounded::<()>(1);
+        let stop = ChannelStop::crossbeam(rx);
+        drop(tx);
+        assert!(stop.should_stop());
+        assert!(stop.is_disconnected());
+    }
+
+    #[test]
+    fn debug_and_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<ChannelStop>();
+        let (_tx, rx) = mpsc::channel::<()>();
+        let debug = format!("{:?}", ChannelStop::new(rx));
+        assert!(debug.contains("ChannelStop"));
+        assert!(debug.contains("running"));
+    }
+}
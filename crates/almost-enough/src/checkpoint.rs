@@ -0,0 +1,176 @@
+//! Adaptive check frequency for hot loops.
+//!
+//! Hot loops usually check every N iterations (`if i % 16 == 0`), with N
+//! guessed once. It is rarely right: too small on a fast iteration wastes
+//! time in `check()`, too large on a slow one delays cancellation. A
+//! [`Checkpoint`] embedded in the loop picks N itself, measuring the time
+//! between checks and adjusting N to keep it near a target latency.
+//!
+//! # Example
+//!
+//! ```rust
+//! use almost_enough::{Checkpoint, Stop, StopReason, Stopper};
+//!
+//! fn sum(data: &[u64], stop: &impl Stop) -> Result<u64, StopReason> {
+//!     let mut cp = Checkpoint::new();
+//!     let mut total = 0;
+//!     for &x in data {
+//!         cp.tick(stop)?;
+//!         total += x;
+//!     }
+//!     Ok(total)
+//! }
+//!
+//! assert_eq!(sum(&[1, 2, 3], &Stopper::new()), Ok(6));
+//! assert_eq!(sum(&[1, 2, 3], &Stopper::cancelled()), Err(StopReason::Cancelled));
+//! ```
+
+use std::time::{Duration, Instant};
+
+use crate::{Stop, StopReason};
+
+/// Largest interval a [`Checkpoint`] grows to.
+const MAX_INTERVAL: u32 = 1 << 20;
+
+/// Checks a stop every N ticks, adapting N to a target time between checks.
+///
+/// Call [`tick()`](Self::tick) once per iteration. Between checks a tick is
+/// a counter increment and compare. At each check the checkpoint reads the
+/// clock: if the checks came less than half the target apart, N doubles;
+/// if more than the target apart, N halves (down to 1). N settles within a
+/// factor of two of the target and follows the loop when its iterations
+/// speed up or slow down.
+///
+/// The first tick always checks, so a stop that has already fired is
+/// noticed immediately. A `Checkpoint` is per loop and per thread
+/// (`tick()` takes `&mut self`); it does not hold the stop, so one can be
+/// reused across calls with different stops.
+#[derive(Debug, Clone)]
+pub struct Checkpoint {
+    count: u32,
+    interval: u32,
+    target: Duration,
+    last: Option<Instant>,
+}
+
+impl Checkpoint {
+    /// Target 1 ms between checks.
+    #[inline]
+    pub fn new() -> Self {
+        Self::with_target(Duration::from_millis(1))
+    }
+
+    /// Target `target` between checks.
+    ///
+    /// Smaller targets react faster and spend more time checking.
+    #[inline]
+    pub fn with_target(target: Duration) -> Self {
+        Self {
+            count: 0,
+            interval: 1,
+            target,
+            last: None,
+        }
+    }
+
+    /// Count one iteration, checking `stop` if this is the N-th.
+    #[inline]
+    pub fn tick(&mut self, stop: &(impl Stop + ?Sized)) -> Result<(), StopReason> {
+        self.count += 1;
+        if self.count < self.interval {
+            return Ok(());
+        }
+        self.adapt();
+        stop.check()
+    }
+
+    /// The current number of ticks per check.
+    #[inline]
+    pub fn interval(&self) -> u32 {
+        self.interval
+    }
+
+    /// The target time between checks.
+    #[inline]
+    pub fn target(&self) -> Duration {
+        self.target
+    }
+
+    #[cold]
+    fn adapt(&mut self) {
+        self.count = 0;
+        let now = Instant::now();
+        if let Some(last) = self.last.replace(now) {
+            let elapsed = now - last;
+            if elapsed < self.target / 2 {
+                self.interval = (self.interval * 2).min(MAX_INTERVAL);
+            } else if elapsed > self.target {
+                self.interval = (self.interval / 2).max(1);
+            }
+        }
+    }
+}
+
+impl Default for Checkpoint {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BudgetStop, Stopper, Unstoppable};
+
+    #[test]
+    fn first_tick_checks() {
+        let mut cp = Checkpoint::new();
+        assert_eq!(cp.tick(&Stopper::cancelled()), Err(StopReason::Cancelled));
+    }
+
+    #[test]
+    fn grows_on_fast_loops() {
+        let mut cp = Checkpoint::with_target(Duration::from_secs(60));
+        for _ in 0..10_000 {
+            cp.tick(&Unstoppable).unwrap();
+        }
+        assert!(cp.interval() > 1000, "interval {}", cp.interval());
+    }
+
+    #[test]
+    fn checks_far_fewer_times_than_ticks() {
+        let budget = BudgetStop::checks(usize::MAX);
+        let mut cp = Checkpoint::with_target(Duration::from_secs(60));
+        for _ in 0..100_000 {
+            cp.tick(&budget).unwrap();
+        }
+        let checks = usize::MAX - budget.remaining();
+        assert!(checks < 100, "checked {checks} times");
+    }
+
+    #[test]
+    fn shrinks_on_slow_loops() {
+        let mut cp = Checkpoint::with_target(Duration::from_secs(60));
+        for _ in 0..100 {
+            cp.tick(&Unstoppable).unwrap();
+        }
+        let grown = cp.interval();
+        assert!(grown > 1);
+
+        cp.target = Duration::from_micros(1);
+        for _ in 0..grown * 4 {
+            cp.tick(&Unstoppable).unwrap();
+            std::thread::sleep(Duration::from_micros(10));
+        }
+        assert_eq!(cp.interval(), 1);
+    }
+
+    #[test]
+    fn interval_is_capped() {
+        let mut cp = Checkpoint::with_target(Duration::MAX);
+        for _ in 0..(MAX_INTERVAL * 3) {
+            cp.tick(&Unstoppable).unwrap();
+        }
+        assert_eq!(cp.interval(), MAX_INTERVAL);
+    }
+}
@@ -0,0 +1,381 @@
+//! Command-line tool glue: Ctrl-C, an optional timeout, and a stop message.
+//!
+//! Most CLI tools want the same three things from cancellation: stop on
+//! Ctrl-C/SIGTERM, stop after an optional `--timeout`, and tell the user on
+//! stderr why the run ended. [`cli_stop()`] wires all three up in one call.
+//!
+//! Requires the `signal` feature.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use almost_enough::cli::{CliStopArgs, cli_stop, parse_timeout};
+//! use almost_enough::Stop;
+//!
+//! // e.g. from `--timeout 30s`
+//! let timeout = Some(parse_timeout("30s").expect("invalid --timeout"));
+//!
+//! let stop = cli_stop(CliStopArgs::new("mytool").timeout(timeout))?;
+//!
+//! for file in ["a.png", "b.png"] {
+//!     if stop.check().is_err() {
+//!         // "mytool: interrupted" or "mytool: timed out after 30s"
+//!         // has already been printed to stderr
+//!         std::process::exit(stop.exit_code().unwrap_or(1));
+//!     }
+//!     // ... process file ...
+//! }
+//! # Ok::<(), almost_enough::SignalInstallError>(())
+//! ```
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::{SignalInstallError, SignalStop, Stop, StopReason, Stopper};
+
+/// Conventional exit status after SIGINT (128 + 2).
+const EXIT_INTERRUPTED: i32 = 130;
+/// Exit status used by coreutils `timeout(1)`.
+const EXIT_TIMED_OUT: i32 = 124;
+/// Generic failure status, for stops that were neither a signal nor a timeout.
+const EXIT_FAILURE: i32 = 1;
+
+/// Configuration for [`cli_stop()`].
+///
+/// # Example
+///
+/// ```rust
+/// use almost_enough::cli::CliStopArgs;
+/// use std::time::Duration;
+///
+/// let args = CliStopArgs::new("convert")
+///     .timeout(Some(Duration::from_secs(60)))
+///     .verbose(false);
+/// ```
+#[derive(Debug, Clone)]
+pub struct CliStopArgs {
+    program: String,
+    timeout: Option<Duration>,
+    verbose: bool,
+}
+
+impl CliStopArgs {
+    /// Arguments for a tool named `program`, used as the message prefix.
+    ///
+    /// Defaults: no timeout, messages enabled.
+    pub fn new(program: impl Into<String>) -> Self {
+        Self {
+            program: program.into(),
+            timeout: None,
+            verbose: true,
+        }
+    }
+
+    /// Stop after `timeout` (typically the parsed `--timeout` flag).
+    pub fn timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Whether to print why the tool stopped to stderr (default: `true`).
+    pub fn verbose(mut self, verbose: bool) -> Self {
+        self.verbose = verbose;
+        self
+    }
+}
+
+/// Shared message state, so only one clone prints.
+struct CliInner {
+    program: String,
+    timeout: Option<Duration>,
+    verbose: bool,
+    reported: AtomicBool,
+}
+
+/// The [`Stop`] returned by [`cli_stop()`].
+///
+/// Stops on a termination signal or when the timeout elapses. The first
+/// time any clone observes the stop through `check()` or `should_stop()`,
+/// a one-line explanation is printed to stderr (unless disabled with
+/// [`CliStopArgs::verbose(false)`](CliStopArgs::verbose)).
+#[derive(Clone)]
+pub struct CliStop {
+    root: Stopper,
+    deadline: Option<Instant>,
+    inner: Arc<CliInner>,
+}
+
+impl CliStop {
+    fn new(root: Stopper, args: CliStopArgs) -> Self {
+        let deadline = args.timeout.and_then(|t| Instant::now().checked_add(t));
+        Self {
+            root,
+            deadline,
+            inner: Arc::new(CliInner {
+                program: args.program,
+                timeout: args.timeout,
+                verbose: args.verbose,
+                reported: AtomicBool::new(false),
+            }),
+        }
+    }
+
+    /// Cancel as if Ctrl-C had been pressed.
+    #[inline]
+    pub fn cancel(&self) {
+        self.root.cancel();
+    }
+
+    /// Time left before the timeout, or `None` if there is no timeout.
+    #[inline]
+    pub fn remaining(&self) -> Option<Duration> {
+        self.deadline
+            .map(|d| d.saturating_duration_since(Instant::now()))
+    }
+
+    /// Conventional process exit status for the current stop reason.
+    ///
+    /// `130` when interrupted, `124` when timed out (matching coreutils
+    /// `timeout`), `1` when the cancellation check failed, or `None` if not
+    /// stopped.
+    pub fn exit_code(&self) -> Option<i32> {
+        self.reason().map(exit_code_for)
+    }
+
+    #[inline]
+    fn reason(&self) -> Option<StopReason> {
+        if let Err(reason) = self.root.check() {
+            return Some(reason);
+        }
+        match self.deadline {
+            Some(deadline) if Instant::now() >= deadline => Some(StopReason::TimedOut),
+            _ => None,
+        }
+    }
+
+    fn message(&self, reason: StopReason) -> String {
+        let program = &self.inner.program;
+        match (reason, self.inner.timeout) {
+            (StopReason::TimedOut, Some(timeout)) => {
+                format!("{program}: timed out after {}", format_duration(timeout))
+            }
+            (StopReason::TimedOut, None) => format!("{program}: timed out"),
+            (StopReason::Cancelled, _) => format!("{program}: interrupted"),
+            (StopReason::Failed, _) => format!("{program}: cancellation check failed"),
+            (reason, _) => format!("{program}: stopped ({reason})"),
+        }
+    }
+
+    #[cold]
+    fn report(&self, reason: StopReason) {
+        // Load first: every failing check lands here, and only the first
+        // needs to write the shared flag
+        if self.inner.verbose
+            && !self.inner.reported.load(Ordering::Relaxed)
+            && !self.inner.reported.swap(true, Ordering::Relaxed)
+        {
+            eprintln!("{}", self.message(reason));
+        }
+    }
+}
+
+impl Stop for CliStop {
+    #[inline]
+    fn check(&self) -> Result<(), StopReason> {
+        match self.reason() {
+            None => Ok(()),
+            Some(reason) => {
+                self.report(reason);
+                Err(reason)
+            }
+        }
+    }
+
+    #[inline]
+    fn should_stop(&self) -> bool {
+        self.check().is_err()
+    }
+}
+
+impl core::fmt::Debug for CliStop {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("CliStop")
+            .field("program", &self.inner.program)
+            .field("timeout", &self.inner.timeout)
+            .field("verbose", &self.inner.verbose)
+            .field("is_cancelled", &self.root.is_cancelled())
+            .finish()
+    }
+}
+
+/// Install the process signal handler and build a [`CliStop`] from `args`.
+///
+/// Uses [`SignalStop::install()`], so the usual one-handler-per-process
+/// rules apply.
+pub fn cli_stop(args: CliStopArgs) -> Result<CliStop, SignalInstallError> {
+    let signal = SignalStop::install()?;
+    Ok(CliStop::new(signal.into(), args))
+}
+
+/// Error returned by [`parse_timeout()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseTimeoutError {
+    input: String,
+}
+
+impl core::fmt::Display for ParseTimeoutError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "invalid timeout {:?}: expected a number with optional unit (ms, s, m, h), e.g. 1m30s",
+            self.input
+        )
+    }
+}
+
+impl std::error::Error for ParseTimeoutError {}
+
+/// Parse a `--timeout` value such as `"30"`, `"30s"`, `"500ms"`, `"1.5m"`,
+/// `"1m30s"` or `"2h"`.
+///
+/// A bare number is seconds. Fractions are allowed; negative values are not.
+/// The grammar is the one of `time::parse_duration()` (`config` feature),
+/// so a timeout reads the same on the command line and in a config file.
+///
+/// # Example
+///
+/// ```rust
+/// use almost_enough::cli::parse_timeout;
+/// use std::time::Duration;
+///
+/// assert_eq!(parse_timeout("90"), Ok(Duration::from_secs(90)));
+/// assert_eq!(parse_timeout("250ms"), Ok(Duration::from_millis(250)));
+/// assert_eq!(parse_timeout("1.5m"), Ok(Duration::from_secs(90)));
+/// assert!(parse_timeout("soon").is_err());
+/// ```
+pub fn parse_timeout(input: &str) -> Result<Duration, ParseTimeoutError> {
+    crate::time::parse::parse(input).ok_or_else(|| ParseTimeoutError {
+        input: input.to_owned(),
+    })
+}
+
+/// Exit status for a stop reason; see [`CliStop::exit_code()`].
+fn exit_code_for(reason: StopReason) -> i32 {
+    match reason {
+        StopReason::Cancelled => EXIT_INTERRUPTED,
+        StopReason::TimedOut => EXIT_TIMED_OUT,
+        StopReason::Failed => EXIT_FAILURE,
+        _ => EXIT_FAILURE,
+    }
+}
+
+/// Format a duration the way a user would type it back into `--timeout`.
+fn format_duration(duration: Duration) -> String {
+    let millis = duration.as_millis();
+    if millis % 1000 != 0 {
+        format!("{millis}ms")
+    } else {
+        format!("{}s", duration.as_secs())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cli(args: CliStopArgs) -> CliStop {
+        CliStop::new(Stopper::new(), args)
+    }
+
+    #[test]
+    fn runs_until_cancelled() {
+        let stop = cli(CliStopArgs::new("tool").verbose(false));
+        assert!(stop.check().is_ok());
+        assert_eq!(stop.exit_code(), None);
+        assert_eq!(stop.remaining(), None);
+
+        stop.cancel();
+        assert_eq!(stop.check(), Err(StopReason::Cancelled));
+        assert_eq!(stop.exit_code(), Some(130));
+        assert_eq!(stop.message(StopReason::Cancelled), "tool: interrupted");
+    }
+
+    #[test]
+    fn times_out() {
+        let stop = cli(CliStopArgs::new("tool")
+            .timeout(Some(Duration::ZERO))
+            .verbose(false));
+        assert_eq!(stop.check(), Err(StopReason::TimedOut));
+        assert_eq!(stop.exit_code(), Some(124));
+        assert_eq!(stop.remaining(), Some(Duration::ZERO));
+        assert_eq!(
+            stop.message(StopReason::TimedOut),
+            "tool: timed out after 0s"
+        );
+    }
+
+    #[test]
+    fn cancel_wins_over_timeout() {
+        let stop = cli(CliStopArgs::new("tool")
+            .timeout(Some(Duration::ZERO))
+            .verbose(false));
+        stop.cancel();
+        assert_eq!(stop.check(), Err(StopReason::Cancelled));
+    }
+
+    #[test]
+    fn failed_is_not_reported_as_interrupt() {
+        let stop = cli(CliStopArgs::new("tool").verbose(false));
+        assert_eq!(
+            stop.message(StopReason::Failed),
+            "tool: cancellation check failed"
+        );
+        assert_eq!(exit_code_for(StopReason::Failed), 1);
+        assert_eq!(exit_code_for(StopReason::Cancelled), 130);
+        assert_eq!(exit_code_for(StopReason::TimedOut), 124);
+    }
+
+    #[test]
+    fn reports_once_across_clones() {
+        let stop = cli(CliStopArgs::new("tool"));
+        let clone = stop.clone();
+        stop.cancel();
+        assert!(stop.should_stop());
+        assert!(clone.should_stop());
+        assert!(stop.inner.reported.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn parse_timeout_units() {
+        assert_eq!(parse_timeout("30"), Ok(Duration::from_secs(30)));
+        assert_eq!(parse_timeout("30s"), Ok(Duration::from_secs(30)));
+        assert_eq!(parse_timeout(" 500ms "), Ok(Duration::from_millis(500)));
+        assert_eq!(parse_timeout("2m"), Ok(Duration::from_secs(120)));
+        assert_eq!(parse_timeout("1h"), Ok(Duration::from_secs(3600)));
+        assert_eq!(parse_timeout("0.25s"), Ok(Duration::from_millis(250)));
+    }
+
+    #[test]
+    fn parse_timeout_rejects_garbage() {
+        for bad in ["", "s", "-1", "10x", "1e400", "abc", "5 minutes"] {
+            let err = parse_timeout(bad).unwrap_err();
+            assert!(err.to_string().contains("invalid timeout"), "{bad}");
+        }
+    }
+
+    #[test]
+    fn format_duration_round_trips() {
+        for d in [Duration::from_secs(30), Duration::from_millis(1500)] {
+            assert_eq!(parse_timeout(&format_duration(d)), Ok(d));
+        }
+    }
+
+    #[test]
+    fn debug_and_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<CliStop>();
+        let debug = format!("{:?}", cli(CliStopArgs::new("tool")));
+        assert!(debug.contains("CliStop"));
+    }
+}
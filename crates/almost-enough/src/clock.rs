@@ -0,0 +1,319 @@
+//! Deadlines driven by a caller-supplied clock.
+//!
+//! `WithTimeout` needs `std::time::Instant`, which embedded targets don't
+//! have. [`WithClockTimeout`] does the same job with any monotonic tick
+//! counter — a hardware timer, a SysTick counter, an RTOS tick — supplied
+//! through the [`Clock`] trait. Works in `no_std` environments.
+//!
+//! Ticks are opaque: the wrapper only compares them, so any unit works as
+//! long as the timeout is given in the same unit.
+//!
+//! # Example
+//!
+//! ```rust
+//! use almost_enough::{StopSource, Stop, StopReason, WithClockTimeout};
+//! use core::sync::atomic::{AtomicU64, Ordering};
+//!
+//! // Stand-in for a hardware timer counting microseconds
+//! static TICKS: AtomicU64 = AtomicU64::new(0);
+//! let clock = || TICKS.load(Ordering::Relaxed);
+//!
+//! let source = StopSource::new();
+//! let stop = WithClockTimeout::new(source.as_ref(), clock, 5_000);
+//! assert!(stop.check().is_ok());
+//!
+//! TICKS.store(5_000, Ordering::Relaxed);
+//! assert_eq!(stop.check(), Err(StopReason::TimedOut));
+//! ```
+
+use crate::{Behavior, Stop, StopReason};
+
+/// A monotonic tick source for [`WithClockTimeout`].
+///
+/// Implemented for every `Fn() -> u64 + Send + Sync`, so a function or
+/// closure reading a hardware counter can be passed directly.
+///
+/// `now_ticks()` must never go backwards. A counter narrower than 64 bits
+/// should be extended (e.g. by counting overflows) so it does not wrap
+/// within the lifetime of a deadline.
+///
+/// # Example
+///
+/// ```rust
+/// use almost_enough::Clock;
+///
+/// struct Timer2;
+///
+/// impl Clock for Timer2 {
+///     fn now_ticks(&self) -> u64 {
+///         // e.g. read a memory-mapped counter register
+///         42
+///     }
+/// }
+///
+/// assert_eq!(Timer2.now_ticks(), 42);
+/// ```
+pub trait Clock: Send + Sync {
+    /// The current time, in ticks.
+    fn now_ticks(&self) -> u64;
+}
+
+impl<F> Clock for F
+where
+    F: Fn() -> u64 + Send + Sync,
+{
+    #[inline]
+    fn now_ticks(&self) -> u64 {
+        self()
+    }
+}
+
+/// A [`Stop`] wrapper that adds a deadline measured by a [`Clock`].
+///
+/// The `no_std` counterpart of `WithTimeout`: returns
+/// [`StopReason::TimedOut`] once the clock reaches the deadline, or the
+/// inner stop's reason if it stops first. If both have happened, the
+/// wrapper's [`Behavior`] decides which is reported.
+///
+/// # Example
+///
+/// ```rust
+/// use almost_enough::{Stop, StopReason, StopSource, WithClockTimeout};
+/// use core::sync::atomic::{AtomicU64, Ordering};
+///
+/// let ticks = AtomicU64::new(100);
+/// let source = StopSource::new();
+///
+/// // Deadline at tick 150, tightened to tick 120
+/// let stop = WithClockTimeout::new(source.as_ref(), || ticks.load(Ordering::Relaxed), 50)
+///     .tighten(20);
+/// assert_eq!(stop.deadline_ticks(), 120);
+/// assert_eq!(stop.remaining_ticks(), 20);
+///
+/// ticks.store(120, Ordering::Relaxed);
+/// assert_eq!(stop.check(), Err(StopReason::TimedOut));
+/// ```
+#[derive(Clone)]
+pub struct WithClockTimeout<T, C> {
+    inner: T,
+    clock: C,
+    deadline: u64,
+    behavior: Behavior,
+}
+
+impl<T: Stop, C: Clock> WithClockTimeout<T, C> {
+    /// Create a wrapper that times out `timeout_ticks` from now.
+    ///
+    /// The deadline saturates at `u64::MAX` (never, in practice).
+    #[inline]
+    pub fn new(inner: T, clock: C, timeout_ticks: u64) -> Self {
+        let deadline = clock.now_ticks().saturating_add(timeout_ticks);
+        Self::with_deadline(inner, clock, deadline)
+    }
+
+    /// Create a wrapper with an absolute deadline, in ticks.
+    #[inline]
+    pub fn with_deadline(inner: T, clock: C, deadline_ticks: u64) -> Self {
+        Self {
+            inner,
+            clock,
+            deadline: deadline_ticks,
+            behavior: Behavior::V1,
+        }
+    }
+
+    /// Set the [`Behavior`] used to resolve ties with the inner stop.
+    #[inline]
+    pub fn with_behavior(mut self, behavior: Behavior) -> Self {
+        self.behavior = behavior;
+        self
+    }
+
+    /// Get the configured [`Behavior`].
+    #[inline]
+    pub fn behavior(&self) -> Behavior {
+        self.behavior
+    }
+
+    /// Get the deadline, in ticks.
+    #[inline]
+    pub fn deadline_ticks(&self) -> u64 {
+        self.deadline
+    }
+
+    /// Get the ticks remaining until the deadline.
+    ///
+    /// Returns 0 if the deadline has passed.
+    #[inline]
+    pub fn remaining_ticks(&self) -> u64 {
+        self.deadline.saturating_sub(self.clock.now_ticks())
+    }
+
+    /// Move the deadline to `timeout_ticks` from now, if that is earlier.
+    #[inline]
+    pub fn tighten(self, timeout_ticks: u64) -> Self {
+        let deadline = self.clock.now_ticks().saturating_add(timeout_ticks);
+        self.tighten_deadline(deadline)
+    }
+
+    /// Move the deadline to `deadline_ticks`, if that is earlier.
+    #[inline]
+    pub fn tighten_deadline(mut self, deadline_ticks: u64) -> Self {
+        self.deadline = self.deadline.min(deadline_ticks);
+        self
+    }
+
+    /// Get a reference to the clock.
+    #[inline]
+    pub fn clock(&self) -> &C {
+        &self.clock
+    }
+
+    /// Get a reference to the inner stop.
+    #[inline]
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Unwrap and return the inner stop.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    #[inline]
+    fn deadline_passed(&self) -> bool {
+        self.clock.now_ticks() >= self.deadline
+    }
+}
+
+impl<T: Stop, C: Clock> Stop for WithClockTimeout<T, C> {
+    #[inline]
+    fn check(&self) -> Result<(), StopReason> {
+        if let Err(reason) = self.inner.check() {
+            return Err(self.behavior.resolve_tie(reason, || self.deadline_passed()));
+        }
+        if self.deadline_passed() {
+            Err(StopReason::TimedOut)
+        } else {
+            Ok(())
+        }
+    }
+
+    #[inline]
+    fn should_stop(&self) -> bool {
+        self.inner.should_stop() || self.deadline_passed()
+    }
+}
+
+impl<T: core::fmt::Debug, C> core::fmt::Debug for WithClockTimeout<T, C> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("WithClockTimeout")
+            .field("inner", &self.inner)
+            .field("deadline", &self.deadline)
+            .field("behavior", &self.behavior)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{StopSource, TieBreak, Unstoppable};
+    use core::sync::atomic::{AtomicU64, Ordering};
+
+    struct ManualClock(AtomicU64);
+
+    impl ManualClock {
+        fn set(&self, ticks: u64) {
+            self.0.store(ticks, Ordering::Relaxed);
+        }
+    }
+
+    impl Clock for &ManualClock {
+        fn now_ticks(&self) -> u64 {
+            self.0.load(Ordering::Relaxed)
+        }
+    }
+
+    #[test]
+    fn times_out_at_deadline() {
+        let clock = ManualClock(AtomicU64::new(10));
+        let stop = WithClockTimeout::new(Unstoppable, &clock, 5);
+        assert_eq!(stop.deadline_ticks(), 15);
+
+        clock.set(14);
+        assert!(!stop.should_stop());
+        assert_eq!(stop.remaining_ticks(), 1);
+
+        clock.set(15);
+        assert!(stop.should_stop());
+        assert_eq!(stop.check(), Err(StopReason::TimedOut));
+        assert_eq!(stop.remaining_ticks(), 0);
+    }
+
+    #[test]
+    fn inner_reason_wins_before_deadline() {
+        let clock = ManualClock(AtomicU64::new(0));
+        let source = StopSource::new();
+        let stop = WithClockTimeout::new(source.as_ref(), &clock, 100);
+        source.cancel();
+        assert_eq!(stop.check(), Err(StopReason::Cancelled));
+    }
+
+    #[test]
+    fn tie_break_selects_reported_reason() {
+        let clock = ManualClock(AtomicU64::new(0));
+        let source = StopSource::new();
+        source.cancel();
+        let stop = WithClockTimeout::with_deadline(source.as_ref(), &clock, 0);
+        assert_eq!(stop.check(), Err(StopReason::Cancelled));
+
+        let stop = stop.with_behavior(Behavior::V1.with_tie_break(TieBreak::Deadline));
+        assert_eq!(stop.check(), Err(StopReason::TimedOut));
+    }
+
+    #[test]
+    fn tighten_only_moves_earlier() {
+        let clock = ManualClock(AtomicU64::new(0));
+        let stop = WithClockTimeout::new(Unstoppable, &clock, 100)
+            .tighten(200)
+            .tighten_deadline(150);
+        assert_eq!(stop.deadline_ticks(), 100);
+        let stop = stop.tighten(30);
+        assert_eq!(stop.deadline_ticks(), 30);
+    }
+
+    #[test]
+    fn deadline_saturates() {
+        let stop = WithClockTimeout::new(Unstoppable, || u64::MAX - 1, u64::MAX);
+        assert_eq!(stop.deadline_ticks(), u64::MAX);
+        assert!(!stop.should_stop());
+    }
+
+    #[test]
+    fn fn_pointer_clock_is_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<WithClockTimeout<Unstoppable, fn() -> u64>>();
+
+        let stop: WithClockTimeout<Unstoppable, fn() -> u64> =
+            WithClockTimeout::new(Unstoppable, || 0, 1);
+        assert_eq!(stop.clock().now_ticks(), 0);
+        assert_eq!(stop.into_inner(), Unstoppable);
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod alloc_tests {
+    use super::*;
+    use crate::Unstoppable;
+
+    #[test]
+    fn debug_omits_clock() {
+        extern crate alloc;
+        let stop = WithClockTimeout::new(Unstoppable, || 7, 3);
+        let debug = alloc::format!("{:?}", stop);
+        assert!(debug.contains("WithClockTimeout"));
+        assert!(debug.contains("deadline: 10"));
+    }
+}
@@ -0,0 +1,362 @@
+//! Short uninterruptible windows.
+//!
+//! Writers of databases and file formats need brief stretches where
+//! stopping would leave a torn output: between writing a block and
+//! updating the index that points at it, say. [`CriticalStop`] wraps a
+//! stop and hands out [`CriticalSection`] guards. While any section is
+//! held, a cancellation is recorded by the inner stop but `check()` keeps
+//! reporting `Ok`; once the last section is released, the pending reason
+//! is reported as usual.
+//!
+//! A stuck section must not block shutdown forever, so deferral is capped:
+//! once the outermost open section has been held longer than the
+//! configured maximum, `check()` reports the reason even inside it.
+//!
+//! Entering is race-free with respect to `cancel()`:
+//! [`enter()`](CriticalStop::enter) fails if the stop has already fired,
+//! and any cancellation after a successful `enter()` is deferred.
+//! Checking a flag and then setting a "busy" marker by hand leaves a gap
+//! between the two in which a cancellation is neither refused nor
+//! deferred.
+//!
+//! # Example
+//!
+//! ```rust
+//! use almost_enough::{CriticalStop, Stop, StopReason, Stopper};
+//! use std::time::Duration;
+//!
+//! let stopper = Stopper::new();
+//! let stop = CriticalStop::new(stopper.clone(), Duration::from_secs(2));
+//!
+//! {
+//!     let _section = stop.enter().unwrap();
+//!     // write block ...
+//!     stopper.cancel();
+//!     assert!(stop.check().is_ok()); // deferred
+//!     // ... update index
+//! }
+//!
+//! assert_eq!(stop.check(), Err(StopReason::Cancelled));
+//! assert!(stop.enter().is_err());
+//! ```
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+use crate::{Stop, StopReason};
+
+/// Open-section bookkeeping, only touched on enter/exit and on the
+/// cancelled path of `check()`.
+struct Sections {
+    open: usize,
+    /// When the outermost open section was entered.
+    since: Option<Instant>,
+}
+
+/// A [`Stop`] wrapper whose cancellation can be deferred by
+/// [`CriticalSection`] guards.
+///
+/// Created by [`CriticalStop::new()`] or
+/// [`StopExt::with_critical_sections()`](crate::StopExt::with_critical_sections).
+/// Share it by reference or in an `Arc`; sections borrow it.
+///
+/// Only the inner stop's reason is deferred: a `CriticalStop` nested
+/// inside an [`OrStop`](crate::OrStop) or a timeout wrapper does not hold
+/// back the other stops. Put it outermost.
+///
+/// # Example
+///
+/// ```rust
+/// use almost_enough::{Stop, StopExt, Stopper};
+/// use std::time::Duration;
+///
+/// let stopper = Stopper::new();
+/// let stop = stopper.clone().with_critical_sections(Duration::from_millis(1));
+///
+/// let section = stop.enter().unwrap();
+/// stopper.cancel();
+/// std::thread::sleep(Duration::from_millis(5));
+///
+/// // Held past the maximum: the cancellation gets through anyway
+/// assert!(stop.should_stop());
+/// drop(section);
+/// ```
+pub struct CriticalStop<T> {
+    inner: T,
+    max_deferral: Duration,
+    /// Mirror of `Sections::open`, so uncancelled checks skip the lock.
+    open: AtomicUsize,
+    sections: Mutex<Sections>,
+}
+
+impl<T: Stop> CriticalStop<T> {
+    /// Wrap `inner`, deferring its cancellation for at most
+    /// `max_deferral` while a section is held.
+    pub fn new(inner: T, max_deferral: Duration) -> Self {
+        Self {
+            inner,
+            max_deferral,
+            open: AtomicUsize::new(0),
+            sections: Mutex::new(Sections {
+                open: 0,
+                since: None,
+            }),
+        }
+    }
+
+    /// Enter a critical section.
+    ///
+    /// Returns the inner stop's reason, without entering, if it has
+    /// already stopped, unless another open section is deferring that
+    /// cancellation, in which case the new section joins it. Sections nest
+    /// and may be held from several threads at once; the maximum is
+    /// measured from the first one entered until all are released.
+    pub fn enter(&self) -> Result<CriticalSection<'_, T>, StopReason> {
+        let nested = self.open_section() > 1;
+        let section = CriticalSection { stop: self };
+        if let Err(reason) = self.inner.check() {
+            // Only an already-running section may defer a cancellation
+            if !nested || !self.deferring() {
+                return Err(reason);
+            }
+        }
+        Ok(section)
+    }
+
+    /// Whether the inner stop has fired but is being held back by an
+    /// open section.
+    pub fn is_deferring(&self) -> bool {
+        self.inner.should_stop() && self.deferring()
+    }
+
+    /// Number of sections currently held.
+    #[inline]
+    pub fn open_sections(&self) -> usize {
+        self.open.load(Ordering::Relaxed)
+    }
+
+    /// The configured maximum deferral.
+    #[inline]
+    pub fn max_deferral(&self) -> Duration {
+        self.max_deferral
+    }
+
+    /// Get a reference to the inner stop.
+    #[inline]
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Unwrap and return the inner stop.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Sections> {
+        self.sections.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Returns the number of open sections, including the new one.
+    fn open_section(&self) -> usize {
+        let mut sections = self.lock();
+        sections.open += 1;
+        if sections.open == 1 {
+            sections.since = Some(Instant::now());
+        }
+        self.open.store(sections.open, Ordering::Release);
+        sections.open
+    }
+
+    fn close_section(&self) {
+        let mut sections = self.lock();
+        sections.open -= 1;
+        if sections.open == 0 {
+            sections.since = None;
+        }
+        self.open.store(sections.open, Ordering::Release);
+    }
+
+    /// Whether an open section is within the maximum deferral.
+    #[cold]
+    fn deferring(&self) -> bool {
+        if self.open.load(Ordering::Acquire) == 0 {
+            return false;
+        }
+        self.lock()
+            .since
+            .is_some_and(|since| since.elapsed() < self.max_deferral)
+    }
+}
+
+impl<T: Stop> Stop for CriticalStop<T> {
+    #[inline]
+    fn check(&self) -> Result<(), StopReason> {
+        match self.inner.check() {
+            Err(_) if self.deferring() => Ok(()),
+            result => result,
+        }
+    }
+
+    #[inline]
+    fn should_stop(&self) -> bool {
+        self.inner.should_stop() && !self.deferring()
+    }
+
+    #[inline]
+    fn may_stop(&self) -> bool {
+        self.inner.may_stop()
+    }
+}
+
+impl<T: core::fmt::Debug> core::fmt::Debug for CriticalStop<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("CriticalStop")
+            .field("inner", &self.inner)
+            .field("max_deferral", &self.max_deferral)
+            .field("open_sections", &self.open.load(Ordering::Relaxed))
+            .finish()
+    }
+}
+
+/// Guard returned by [`CriticalStop::enter()`]; the section ends when it
+/// is dropped.
+#[must_use = "the critical section ends when the guard is dropped"]
+pub struct CriticalSection<'a, T: Stop> {
+    stop: &'a CriticalStop<T>,
+}
+
+impl<T: Stop> CriticalSection<'_, T> {
+    /// Whether a cancellation arrived and is waiting for the section(s) to
+    /// end.
+    pub fn is_deferring(&self) -> bool {
+        self.stop.is_deferring()
+    }
+}
+
+impl<T: Stop> Drop for CriticalSection<'_, T> {
+    fn drop(&mut self) {
+        self.stop.close_section();
+    }
+}
+
+impl<T: Stop> core::fmt::Debug for CriticalSection<'_, T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("CriticalSection")
+            .field("open_sections", &self.stop.open_sections())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{StopExt, Stopper, Unstoppable};
+    use std::sync::Arc;
+
+    const LONG: Duration = Duration::from_secs(60);
+
+    #[test]
+    fn passthrough_without_sections() {
+        let stopper = Stopper::new();
+        let stop = CriticalStop::new(stopper.clone(), LONG);
+        assert!(stop.check().is_ok());
+        stopper.cancel();
+        assert_eq!(stop.check(), Err(StopReason::Cancelled));
+        assert!(stop.should_stop());
+        assert!(!stop.is_deferring());
+    }
+
+    #[test]
+    fn defers_until_released() {
+        let stopper = Stopper::new();
+        let stop = stopper.clone().with_critical_sections(LONG);
+
+        let section = stop.enter().unwrap();
+        assert_eq!(stop.open_sections(), 1);
+        assert!(!section.is_deferring());
+
+        stopper.cancel();
+        assert!(stop.check().is_ok());
+        assert!(!stop.should_stop());
+        assert!(section.is_deferring());
+
+        drop(section);
+        assert_eq!(stop.open_sections(), 0);
+        assert_eq!(stop.check(), Err(StopReason::Cancelled));
+    }
+
+    #[test]
+    fn enter_refused_after_cancel() {
+        let stop = CriticalStop::new(Stopper::cancelled(), LONG);
+        assert_eq!(stop.enter().unwrap_err(), StopReason::Cancelled);
+        assert_eq!(stop.open_sections(), 0);
+    }
+
+    #[test]
+    fn nested_sections_hold_until_last_release() {
+        let stopper = Stopper::new();
+        let stop = CriticalStop::new(stopper.clone(), LONG);
+        let outer = stop.enter().unwrap();
+        stopper.cancel();
+
+        // A nested section may open while the deferral is in effect
+        let inner = stop.enter().unwrap();
+        drop(outer);
+        assert!(stop.check().is_ok());
+        drop(inner);
+        assert!(stop.should_stop());
+    }
+
+    #[test]
+    fn max_deferral_overrides() {
+        let stopper = Stopper::new();
+        let stop = CriticalStop::new(stopper.clone(), Duration::from_millis(1));
+        let section = stop.enter().unwrap();
+        stopper.cancel();
+        std::thread::sleep(Duration::from_millis(5));
+        assert_eq!(stop.check(), Err(StopReason::Cancelled));
+        assert!(!section.is_deferring());
+        assert!(stop.enter().is_err());
+    }
+
+    #[test]
+    fn sections_across_threads() {
+        let stopper = Stopper::new();
+        let stop = Arc::new(CriticalStop::new(stopper.clone(), LONG));
+        let section = stop.enter().unwrap();
+
+        let worker = {
+            let stop = Arc::clone(&stop);
+            std::thread::spawn(move || {
+                let _section = stop.enter().unwrap();
+                stop.check()
+            })
+        };
+        stopper.cancel();
+        assert!(worker.join().unwrap().is_ok());
+        drop(section);
+        assert!(stop.should_stop());
+    }
+
+    #[test]
+    fn forwards_may_stop_and_accessors() {
+        let stop = CriticalStop::new(Unstoppable, LONG);
+        assert!(!stop.may_stop());
+        assert_eq!(stop.max_deferral(), LONG);
+        assert_eq!(stop.inner(), &Unstoppable);
+        let section = stop.enter().unwrap();
+        assert!(format!("{section:?}").contains("open_sections: 1"));
+        assert!(format!("{stop:?}").contains("CriticalStop"));
+        drop(section);
+        assert_eq!(stop.into_inner(), Unstoppable);
+    }
+
+    #[test]
+    fn is_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<CriticalStop<Stopper>>();
+        assert_send_sync::<CriticalSection<'static, Stopper>>();
+    }
+}
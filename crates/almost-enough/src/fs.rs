@@ -0,0 +1,391 @@
+//! Cancellation-aware filesystem helpers.
+//!
+//! Cleanup and quota features in asset pipelines need "how big is this
+//! directory tree?", and on a cold cache or a network mount the answer can
+//! take minutes. [`disk_usage_stoppable()`] walks the tree iteratively,
+//! checks its stop before every directory entry, and on a stop returns the
+//! totals gathered so far instead of an error, so a quota check can act on a
+//! lower bound rather than hang or give up.
+//!
+//! # Partial results
+//!
+//! The returned [`DiskUsage`] is always a lower bound: every count covers
+//! only what was visited. [`is_complete()`](DiskUsage::is_complete) says
+//! whether it is also exact; otherwise [`stopped()`](DiskUsage::stopped),
+//! [`budget_exhausted()`](DiskUsage::budget_exhausted),
+//! [`depth_limited()`](DiskUsage::depth_limited) and
+//! [`unreadable()`](DiskUsage::unreadable) say why not. Only a root path
+//! that cannot be read at all is an `Err`.
+//!
+//! # Example
+//!
+//! ```rust
+//! use almost_enough::fs::{DiskUsageOptions, disk_usage_stoppable};
+//! use almost_enough::{Stopper, TimeoutExt};
+//! use std::time::Duration;
+//!
+//! let stop = Stopper::new().with_timeout(Duration::from_secs(5));
+//! let options = DiskUsageOptions::new().with_max_entries(100_000);
+//! let usage = disk_usage_stoppable(env!("CARGO_MANIFEST_DIR"), &stop, &options).unwrap();
+//!
+//! if usage.is_complete() {
+//!     println!("{} bytes in {} files", usage.bytes(), usage.files());
+//! } else {
+//!     println!("at least {} bytes", usage.bytes());
+//! }
+//! ```
+
+use std::fs::{self, Metadata};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::{Stop, StopReason};
+
+/// Limits for [`disk_usage_stoppable()`].
+///
+/// The default has no limits besides the stop.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DiskUsageOptions {
+    max_depth: Option<usize>,
+    max_entries: Option<u64>,
+}
+
+impl DiskUsageOptions {
+    /// No limits.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            max_depth: None,
+            max_entries: None,
+        }
+    }
+
+    /// Count entries at most `depth` levels below the root.
+    ///
+    /// `1` counts the root's direct children without entering any
+    /// subdirectory; `0` counts only the root itself.
+    #[inline]
+    pub const fn with_max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    /// Stop after visiting `entries` directory entries.
+    ///
+    /// A budget bounds the work (and syscalls) independently of time, so
+    /// one huge tree cannot starve the rest of a cleanup pass.
+    #[inline]
+    pub const fn with_max_entries(mut self, entries: u64) -> Self {
+        self.max_entries = Some(entries);
+        self
+    }
+
+    /// The configured depth limit.
+    #[inline]
+    pub const fn max_depth(&self) -> Option<usize> {
+        self.max_depth
+    }
+
+    /// The configured entry budget.
+    #[inline]
+    pub const fn max_entries(&self) -> Option<u64> {
+        self.max_entries
+    }
+}
+
+/// Totals from [`disk_usage_stoppable()`]; a lower bound unless
+/// [`is_complete()`](Self::is_complete).
+#[derive(Debug, Default)]
+pub struct DiskUsage {
+    bytes: u64,
+    files: u64,
+    dirs: u64,
+    entries: u64,
+    unreadable: u64,
+    first_error: Option<(PathBuf, io::Error)>,
+    stopped: Option<StopReason>,
+    budget_exhausted: bool,
+    depth_limited: bool,
+}
+
+impl DiskUsage {
+    /// Apparent size in bytes of every non-directory entry visited.
+    ///
+    /// Sizes come from the entries' own metadata: symbolic links are not
+    /// followed, and a file with several hard links is counted once per
+    /// link.
+    #[inline]
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+
+    /// Non-directory entries visited (files, symbolic links, ...).
+    #[inline]
+    pub fn files(&self) -> u64 {
+        self.files
+    }
+
+    /// Directories visited, including the root.
+    #[inline]
+    pub fn dirs(&self) -> u64 {
+        self.dirs
+    }
+
+    /// Directory entries visited, charged against
+    /// [`with_max_entries()`](DiskUsageOptions::with_max_entries).
+    #[inline]
+    pub fn entries(&self) -> u64 {
+        self.entries
+    }
+
+    /// Entries or directories that could not be read and were skipped.
+    #[inline]
+    pub fn unreadable(&self) -> u64 {
+        self.unreadable
+    }
+
+    /// The first read error and the path it occurred at.
+    #[inline]
+    pub fn first_error(&self) -> Option<(&Path, &io::Error)> {
+        self.first_error
+            .as_ref()
+            .map(|(path, e)| (path.as_path(), e))
+    }
+
+    /// Why the walk was stopped early, if it was.
+    #[inline]
+    pub fn stopped(&self) -> Option<StopReason> {
+        self.stopped
+    }
+
+    /// Whether the entry budget ran out.
+    #[inline]
+    pub fn budget_exhausted(&self) -> bool {
+        self.budget_exhausted
+    }
+
+    /// Whether a directory was left unentered because of the depth limit.
+    #[inline]
+    pub fn depth_limited(&self) -> bool {
+        self.depth_limited
+    }
+
+    /// Whether the totals are exact: nothing stopped, limited or skipped
+    /// the walk.
+    #[inline]
+    pub fn is_complete(&self) -> bool {
+        self.stopped.is_none()
+            && !self.budget_exhausted
+            && !self.depth_limited
+            && self.unreadable == 0
+    }
+
+    fn count(&mut self, metadata: &Metadata) {
+        if metadata.is_dir() {
+            self.dirs += 1;
+        } else {
+            self.files += 1;
+            self.bytes = self.bytes.saturating_add(metadata.len());
+        }
+    }
+
+    fn skip(&mut self, path: &Path, error: io::Error) {
+        self.unreadable += 1;
+        if self.first_error.is_none() {
+            self.first_error = Some((path.to_path_buf(), error));
+        }
+    }
+}
+
+/// Total the sizes under `path`, returning early with partial totals if
+/// `stop` fires.
+///
+/// The tree is walked depth-first without recursion, and `stop` is checked
+/// before each directory is opened and before each entry. Symbolic links
+/// are counted but never followed, so the walk cannot loop. Unreadable
+/// entries are skipped and counted in [`DiskUsage::unreadable()`].
+///
+/// A root that is not a directory is counted as a single file.
+///
+/// # Errors
+///
+/// Returns an error only if `path` itself cannot be inspected. See the
+/// [module docs](self) for the partial-result contract.
+pub fn disk_usage_stoppable(
+    path: impl AsRef<Path>,
+    stop: &impl Stop,
+    options: &DiskUsageOptions,
+) -> io::Result<DiskUsage> {
+    let path = path.as_ref();
+    let root = fs::symlink_metadata(path)?;
+    let mut usage = DiskUsage::default();
+    usage.count(&root);
+    if !root.is_dir() {
+        return Ok(usage);
+    }
+    if options.max_depth == Some(0) {
+        usage.depth_limited = true;
+        return Ok(usage);
+    }
+
+    let mut pending = vec![(path.to_path_buf(), 0usize)];
+    while let Some((dir, depth)) = pending.pop() {
+        if let Err(reason) = stop.check() {
+            usage.stopped = Some(reason);
+            return Ok(usage);
+        }
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                usage.skip(&dir, e);
+                continue;
+            }
+        };
+        for entry in entries {
+            if let Err(reason) = stop.check() {
+                usage.stopped = Some(reason);
+                return Ok(usage);
+            }
+            if options.max_entries.is_some_and(|max| usage.entries >= max) {
+                usage.budget_exhausted = true;
+                return Ok(usage);
+            }
+            usage.entries += 1;
+
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    usage.skip(&dir, e);
+                    continue;
+                }
+            };
+            // `DirEntry::metadata()` does not follow symbolic links
+            let metadata = match entry.metadata() {
+                Ok(metadata) => metadata,
+                Err(e) => {
+                    usage.skip(&entry.path(), e);
+                    continue;
+                }
+            };
+            usage.count(&metadata);
+            if metadata.is_dir() {
+                if options.max_depth.is_some_and(|max| depth + 1 >= max) {
+                    usage.depth_limited = true;
+                } else {
+                    pending.push((entry.path(), depth + 1));
+                }
+            }
+        }
+    }
+    Ok(usage)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{StopSource, Stopper, Unstoppable};
+
+    /// A scratch tree: `a` (3 bytes), `sub/b` (5), `sub/deep/c` (7).
+    struct Tree(PathBuf);
+
+    impl Tree {
+        fn new(name: &str) -> Self {
+            let root = std::env::temp_dir().join(format!(
+                "almost-enough-du-{}-{}",
+                name,
+                std::process::id()
+            ));
+            let _ = fs::remove_dir_all(&root);
+            fs::create_dir_all(root.join("sub/deep")).unwrap();
+            fs::write(root.join("a"), b"aaa").unwrap();
+            fs::write(root.join("sub/b"), b"bbbbb").unwrap();
+            fs::write(root.join("sub/deep/c"), b"ccccccc").unwrap();
+            Self(root)
+        }
+    }
+
+    impl Drop for Tree {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn totals_whole_tree() {
+        let tree = Tree::new("whole");
+        let usage = disk_usage_stoppable(&tree.0, &Unstoppable, &DiskUsageOptions::new()).unwrap();
+        assert!(usage.is_complete());
+        assert_eq!(usage.bytes(), 15);
+        assert_eq!(usage.files(), 3);
+        assert_eq!(usage.dirs(), 3);
+        assert_eq!(usage.entries(), 5);
+        assert!(usage.first_error().is_none());
+    }
+
+    #[test]
+    fn stop_returns_partial_totals() {
+        let tree = Tree::new("stopped");
+        let usage =
+            disk_usage_stoppable(&tree.0, &Stopper::cancelled(), &DiskUsageOptions::default())
+                .unwrap();
+        assert_eq!(usage.stopped(), Some(StopReason::Cancelled));
+        assert!(!usage.is_complete());
+        assert_eq!(usage.dirs(), 1);
+        assert_eq!(usage.entries(), 0);
+    }
+
+    #[test]
+    fn entry_budget_bounds_the_walk() {
+        let tree = Tree::new("budget");
+        let options = DiskUsageOptions::new().with_max_entries(2);
+        assert_eq!(options.max_entries(), Some(2));
+        let usage = disk_usage_stoppable(&tree.0, &StopSource::new(), &options).unwrap();
+        assert!(usage.budget_exhausted());
+        assert_eq!(usage.entries(), 2);
+        assert!(usage.bytes() <= 15);
+        assert!(usage.stopped().is_none());
+    }
+
+    #[test]
+    fn depth_limit() {
+        let tree = Tree::new("depth");
+        let options = DiskUsageOptions::new().with_max_depth(1);
+        assert_eq!(options.max_depth(), Some(1));
+        let usage = disk_usage_stoppable(&tree.0, &Unstoppable, &options).unwrap();
+        assert!(usage.depth_limited());
+        assert_eq!(usage.bytes(), 3);
+        assert_eq!(usage.dirs(), 2);
+
+        let two = DiskUsageOptions::new().with_max_depth(2);
+        let usage = disk_usage_stoppable(&tree.0, &Unstoppable, &two).unwrap();
+        assert_eq!(usage.bytes(), 8);
+
+        let zero = DiskUsageOptions::new().with_max_depth(0);
+        let usage = disk_usage_stoppable(&tree.0, &Unstoppable, &zero).unwrap();
+        assert_eq!((usage.dirs(), usage.entries()), (1, 0));
+        assert!(!usage.is_complete());
+    }
+
+    #[test]
+    fn file_root_and_missing_root() {
+        let tree = Tree::new("roots");
+        let usage =
+            disk_usage_stoppable(tree.0.join("a"), &Unstoppable, &DiskUsageOptions::new()).unwrap();
+        assert_eq!((usage.files(), usage.bytes()), (1, 3));
+        assert!(usage.is_complete());
+
+        let missing = disk_usage_stoppable(tree.0.join("nope"), &Unstoppable, &Default::default());
+        assert_eq!(missing.unwrap_err().kind(), io::ErrorKind::NotFound);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_are_not_followed() {
+        let tree = Tree::new("symlink");
+        std::os::unix::fs::symlink(&tree.0, tree.0.join("sub/loop")).unwrap();
+        let usage = disk_usage_stoppable(&tree.0, &Unstoppable, &DiskUsageOptions::new()).unwrap();
+        assert_eq!(usage.files(), 4);
+        assert_eq!(usage.dirs(), 3);
+        assert!(usage.is_complete());
+    }
+}
@@ -37,6 +37,21 @@
 //! let (tx, rx) = crossbeam_channel::bounded::<()>(1);
 //! let stop = FnStop::new(move || rx.try_recv().is_ok());
 //! ```
+//!
+//! # Panicking Closures
+//!
+//! A panic inside the closure unwinds through `check()` into whatever hot
+//! loop is calling it. With the `std` feature,
+//! [`FnStop::catching()`](FnStop::catching) returns a [`CatchingFnStop`] that
+//! catches the unwind, reports [`StopReason::Failed`] from then on, and keeps
+//! the panic payload for later inspection.
+//!
+//! # Reporting a Reason
+//!
+//! `FnStop` always reports [`StopReason::Cancelled`]. When the closure knows
+//! *why* to stop — a deadline it tracks itself, or an error code from a C
+//! callback — use [`FnStop::with_reason()`] or [`FnStop::try_fn()`] to get a
+//! [`ReasonFnStop`] that passes the closure's reason through unchanged.
 
 use crate::{Stop, StopReason};
 
@@ -75,6 +90,26 @@ where
     }
 }
 
+#[cfg(feature = "std")]
+impl<F> FnStop<F>
+where
+    F: Fn() -> bool + Send + Sync,
+{
+    /// Catch panics raised by the closure.
+    ///
+    /// A panicking probe is treated as "stopped" with
+    /// [`StopReason::Failed`] instead of unwinding into the caller. See
+    /// [`CatchingFnStop`].
+    #[inline]
+    pub fn catching(self) -> CatchingFnStop<F> {
+        CatchingFnStop {
+            f: self.f,
+            panicked: AtomicBool::new(false),
+            payload: Mutex::new(None),
+        }
+    }
+}
+
 impl<F> Stop for FnStop<F>
 where
     F: Fn() -> bool + Send + Sync,
@@ -108,6 +143,273 @@ impl<F> core::fmt::Debug for FnStop<F> {
     }
 }
 
+impl FnStop<fn() -> bool> {
+    /// Create a stop from a closure that returns the reason to stop.
+    ///
+    /// The closure returns `None` to continue or `Some(reason)` to stop
+    /// with that reason. See [`ReasonFnStop`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use almost_enough::{FnStop, Stop, StopReason};
+    /// use core::sync::atomic::{AtomicU8, Ordering};
+    ///
+    /// // 0 = running, 1 = cancelled by the user, 2 = out of time
+    /// let state = AtomicU8::new(0);
+    /// let stop = FnStop::with_reason(|| match state.load(Ordering::Relaxed) {
+    ///     0 => None,
+    ///     2 => Some(StopReason::TimedOut),
+    ///     _ => Some(StopReason::Cancelled),
+    /// });
+    ///
+    /// assert!(stop.check().is_ok());
+    /// state.store(2, Ordering::Relaxed);
+    /// assert_eq!(stop.check(), Err(StopReason::TimedOut));
+    /// ```
+    #[inline]
+    pub fn with_reason<G>(f: G) -> ReasonFnStop<G>
+    where
+        G: Fn() -> Option<StopReason> + Send + Sync,
+    {
+        ReasonFnStop { f }
+    }
+
+    /// Create a stop from a closure shaped like [`Stop::check()`].
+    ///
+    /// The closure returns `Ok(())` to continue or `Err(reason)` to stop,
+    /// so `?` can be used inside it. Equivalent to
+    /// [`with_reason()`](Self::with_reason) with `move || f().err()`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use almost_enough::{FnStop, Stop, StopReason};
+    /// use core::sync::atomic::{AtomicI32, Ordering};
+    ///
+    /// // Status written by a foreign callback: negative means "timed out"
+    /// let status = AtomicI32::new(0);
+    /// let stop = FnStop::try_fn(|| match status.load(Ordering::Acquire) {
+    ///     0 => Ok(()),
+    ///     s if s < 0 => Err(StopReason::TimedOut),
+    ///     _ => Err(StopReason::Cancelled),
+    /// });
+    ///
+    /// status.store(-1, Ordering::Release);
+    /// assert_eq!(stop.check(), Err(StopReason::TimedOut));
+    /// ```
+    #[inline]
+    pub fn try_fn<G>(f: G) -> ReasonFnStop<impl Fn() -> Option<StopReason> + Send + Sync>
+    where
+        G: Fn() -> Result<(), StopReason> + Send + Sync,
+    {
+        ReasonFnStop {
+            f: move || f().err(),
+        }
+    }
+}
+
+/// A [`Stop`] backed by a closure that reports its own [`StopReason`].
+///
+/// Created by [`FnStop::with_reason()`] or [`FnStop::try_fn()`]. Unlike
+/// [`FnStop`], which always reports [`StopReason::Cancelled`], the reason
+/// the closure returns is passed through unchanged, so a probe can tell
+/// callers whether work was cancelled, timed out, or failed.
+///
+/// The closure may capture whatever state it needs; since `check()` takes
+/// `&self`, mutable state goes behind atomics or a lock.
+///
+/// # Example
+///
+/// ```rust
+/// use almost_enough::{FnStop, Stop, StopReason};
+/// use core::sync::atomic::{AtomicU32, Ordering};
+///
+/// // Stateful probe: allow 3 checks, then report a timeout
+/// let checks = AtomicU32::new(0);
+/// let stop = FnStop::with_reason(|| {
+///     (checks.fetch_add(1, Ordering::Relaxed) >= 3).then_some(StopReason::TimedOut)
+/// });
+///
+/// assert!(stop.check().is_ok());
+/// assert!(stop.check().is_ok());
+/// assert!(stop.check().is_ok());
+/// assert_eq!(stop.check(), Err(StopReason::TimedOut));
+/// ```
+pub struct ReasonFnStop<F> {
+    f: F,
+}
+
+impl<F> ReasonFnStop<F>
+where
+    F: Fn() -> Option<StopReason> + Send + Sync,
+{
+    /// Unwrap and return the closure.
+    #[inline]
+    pub fn into_inner(self) -> F {
+        self.f
+    }
+}
+
+impl<F> Stop for ReasonFnStop<F>
+where
+    F: Fn() -> Option<StopReason> + Send + Sync,
+{
+    #[inline]
+    fn check(&self) -> Result<(), StopReason> {
+        match (self.f)() {
+            None => Ok(()),
+            Some(reason) => Err(reason),
+        }
+    }
+
+    #[inline]
+    fn should_stop(&self) -> bool {
+        (self.f)().is_some()
+    }
+}
+
+impl<F: Clone> Clone for ReasonFnStop<F> {
+    fn clone(&self) -> Self {
+        Self { f: self.f.clone() }
+    }
+}
+
+impl<F: Copy> Copy for ReasonFnStop<F> {}
+
+impl<F> core::fmt::Debug for ReasonFnStop<F> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ReasonFnStop").finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "std")]
+use std::any::Any;
+#[cfg(feature = "std")]
+use std::panic::{AssertUnwindSafe, catch_unwind};
+#[cfg(feature = "std")]
+use std::sync::Mutex;
+#[cfg(feature = "std")]
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// An [`FnStop`] that turns a panicking closure into [`StopReason::Failed`].
+///
+/// Created by [`FnStop::catching()`]. The first panic is caught, its payload
+/// is stored, and every later `check()` returns `Err(StopReason::Failed)`
+/// without calling the closure again — a broken probe degrades to
+/// cancellation rather than crashing the work it guards.
+///
+/// The panic hook still runs (so the panic message is printed as usual);
+/// only the unwind is stopped.
+///
+/// Requires the `std` feature.
+///
+/// # Example
+///
+/// ```rust
+/// use almost_enough::{FnStop, Stop, StopReason};
+///
+/// # let hook = std::panic::take_hook();
+/// # std::panic::set_hook(Box::new(|_| {}));
+/// let stop = FnStop::new(|| -> bool { panic!("lock poisoned") }).catching();
+///
+/// assert_eq!(stop.check(), Err(StopReason::Failed));
+/// assert!(stop.has_panicked());
+/// assert_eq!(stop.panic_message().as_deref(), Some("lock poisoned"));
+/// # std::panic::set_hook(hook);
+/// ```
+#[cfg(feature = "std")]
+pub struct CatchingFnStop<F> {
+    f: F,
+    panicked: AtomicBool,
+    payload: Mutex<Option<Box<dyn Any + Send>>>,
+}
+
+#[cfg(feature = "std")]
+impl<F> CatchingFnStop<F>
+where
+    F: Fn() -> bool + Send + Sync,
+{
+    /// Returns `true` if the closure has panicked.
+    #[inline]
+    pub fn has_panicked(&self) -> bool {
+        self.panicked.load(Ordering::Acquire)
+    }
+
+    /// Take the stored panic payload, if any.
+    ///
+    /// The stop stays in the failed state after the payload is taken. Pass
+    /// the payload to [`std::panic::resume_unwind`] to re-raise the panic.
+    pub fn take_panic(&self) -> Option<Box<dyn Any + Send>> {
+        self.payload
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take()
+    }
+
+    /// The panic message, if the closure panicked with a string payload
+    /// that has not been taken.
+    pub fn panic_message(&self) -> Option<String> {
+        let payload = self.payload.lock().unwrap_or_else(|e| e.into_inner());
+        let payload = payload.as_ref()?;
+        payload
+            .downcast_ref::<&str>()
+            .map(|s| String::from(*s))
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+    }
+
+    /// Unwrap and return the closure.
+    #[inline]
+    pub fn into_inner(self) -> F {
+        self.f
+    }
+
+    #[cold]
+    fn record_panic(&self, payload: Box<dyn Any + Send>) {
+        let mut slot = self.payload.lock().unwrap_or_else(|e| e.into_inner());
+        // Keep the first payload if several threads panic concurrently
+        if slot.is_none() && !self.panicked.load(Ordering::Acquire) {
+            *slot = Some(payload);
+        }
+        self.panicked.store(true, Ordering::Release);
+    }
+}
+
+#[cfg(feature = "std")]
+impl<F> Stop for CatchingFnStop<F>
+where
+    F: Fn() -> bool + Send + Sync,
+{
+    #[inline]
+    fn check(&self) -> Result<(), StopReason> {
+        if self.panicked.load(Ordering::Relaxed) {
+            return Err(StopReason::Failed);
+        }
+        match catch_unwind(AssertUnwindSafe(|| (self.f)())) {
+            Ok(false) => Ok(()),
+            Ok(true) => Err(StopReason::Cancelled),
+            Err(payload) => {
+                self.record_panic(payload);
+                Err(StopReason::Failed)
+            }
+        }
+    }
+
+    #[inline]
+    fn should_stop(&self) -> bool {
+        self.check().is_err()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<F> core::fmt::Debug for CatchingFnStop<F> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("CatchingFnStop")
+            .field("panicked", &self.panicked.load(Ordering::Relaxed))
+            .finish_non_exhaustive()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -169,6 +471,55 @@ mod tests {
         assert!(!stop.should_stop()); // Original still usable
         assert!(!stop2.should_stop());
     }
+
+    #[test]
+    fn with_reason_passes_reason_through() {
+        let flag = AtomicBool::new(false);
+        let stop =
+            FnStop::with_reason(|| flag.load(Ordering::Relaxed).then_some(StopReason::TimedOut));
+
+        assert!(!stop.should_stop());
+        assert!(stop.check().is_ok());
+
+        flag.store(true, Ordering::Relaxed);
+        assert!(stop.should_stop());
+        assert_eq!(stop.check(), Err(StopReason::TimedOut));
+    }
+
+    #[test]
+    fn try_fn_maps_result() {
+        let failed = AtomicBool::new(false);
+        let stop = FnStop::try_fn(|| {
+            if failed.load(Ordering::Relaxed) {
+                Err(StopReason::Failed)
+            } else {
+                Ok(())
+            }
+        });
+
+        assert!(stop.check().is_ok());
+        failed.store(true, Ordering::Relaxed);
+        assert_eq!(stop.check(), Err(StopReason::Failed));
+        assert!(stop.should_stop());
+    }
+
+    #[test]
+    fn try_fn_forwards_inner_stop() {
+        let inner = FnStop::new(|| true);
+        let stop = FnStop::try_fn(|| inner.check());
+        assert_eq!(stop.check(), Err(StopReason::Cancelled));
+    }
+
+    #[test]
+    fn reason_fn_stop_is_send_sync_and_copy() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<ReasonFnStop<fn() -> Option<StopReason>>>();
+
+        let stop: ReasonFnStop<fn() -> Option<StopReason>> = FnStop::with_reason(|| None);
+        let copy = stop;
+        assert!(!stop.should_stop());
+        assert_eq!((copy.into_inner())(), None);
+    }
 }
 
 #[cfg(all(test, feature = "alloc"))]
@@ -181,5 +532,100 @@ mod alloc_tests {
         let stop = FnStop::new(|| false);
         let debug = alloc::format!("{:?}", stop);
         assert!(debug.contains("FnStop"));
+
+        let debug = alloc::format!("{:?}", FnStop::with_reason(|| None));
+        assert!(debug.contains("ReasonFnStop"));
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod catching_tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    /// Run `f` with the default panic hook silenced.
+    fn quietly<R>(f: impl FnOnce() -> R) -> R {
+        let hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(|_| {}));
+        let result = f();
+        std::panic::set_hook(hook);
+        result
+    }
+
+    #[test]
+    fn passes_through_normal_results() {
+        let flag = AtomicBool::new(false);
+        let stop = FnStop::new(|| flag.load(Ordering::Relaxed)).catching();
+        assert!(stop.check().is_ok());
+        flag.store(true, Ordering::Relaxed);
+        assert_eq!(stop.check(), Err(StopReason::Cancelled));
+        assert!(!stop.has_panicked());
+        assert!(stop.take_panic().is_none());
+    }
+
+    #[test]
+    fn panic_becomes_failed_and_latches() {
+        let calls = AtomicUsize::new(0);
+        let stop = FnStop::new(|| -> bool {
+            calls.fetch_add(1, Ordering::Relaxed);
+            panic!("probe broke");
+        })
+        .catching();
+
+        quietly(|| {
+            assert_eq!(stop.check(), Err(StopReason::Failed));
+            assert!(stop.should_stop());
+            assert_eq!(stop.check(), Err(StopReason::Failed));
+        });
+
+        // The closure is not called again after the first panic
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+        assert!(stop.has_panicked());
+        assert_eq!(stop.panic_message().as_deref(), Some("probe broke"));
+    }
+
+    #[test]
+    fn string_payload_and_take() {
+        let code = std::hint::black_box(42);
+        let stop = FnStop::new(move || -> bool { panic!("formatted {code}") }).catching();
+        quietly(|| assert!(stop.should_stop()));
+
+        assert_eq!(stop.panic_message().as_deref(), Some("formatted 42"));
+        let payload = stop.take_panic().unwrap();
+        assert_eq!(payload.downcast_ref::<String>().unwrap(), "formatted 42");
+
+        // Payload taken, still failed
+        assert!(stop.take_panic().is_none());
+        assert!(stop.panic_message().is_none());
+        assert_eq!(stop.check(), Err(StopReason::Failed));
+    }
+
+    #[test]
+    fn poisoned_lock_in_probe() {
+        let state = std::sync::Arc::new(Mutex::new(false));
+        let poisoner = std::sync::Arc::clone(&state);
+        quietly(|| {
+            let _ = std::thread::spawn(move || {
+                let _guard = poisoner.lock().unwrap();
+                panic!("poison");
+            })
+            .join();
+        });
+
+        let stop = FnStop::new(move || *state.lock().unwrap()).catching();
+        quietly(|| assert_eq!(stop.check(), Err(StopReason::Failed)));
+        assert!(stop.has_panicked());
+    }
+
+    #[test]
+    fn debug_and_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<CatchingFnStop<fn() -> bool>>();
+
+        let stop = FnStop::new(|| false).catching();
+        let debug = format!("{stop:?}");
+        assert!(debug.contains("CatchingFnStop"));
+        assert!(debug.contains("panicked: false"));
+        assert!(!stop.into_inner()());
     }
 }
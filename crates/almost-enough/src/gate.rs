@@ -0,0 +1,277 @@
+//! A stop that can be switched off at runtime.
+//!
+//! [`GatedStop`] passes its inner stop through while *armed* and ignores
+//! it while *disarmed*. Disarm it for a whole phase with
+//! [`disarm()`](GatedStop::disarm) / [`arm()`](GatedStop::arm), or for a
+//! scope with [`hold()`](GatedStop::hold), whose [`GateHold`] guard re-arms
+//! the gate when the last one is dropped. Code inside the scope (committing
+//! a file, say) keeps checking the same stop as everything else and simply
+//! never sees the cancellation; once the gate re-opens, the pending reason
+//! is reported as usual.
+//!
+//! Unlike [`CriticalStop`](crate::CriticalStop), a gate has no time limit
+//! and can be closed even after the inner stop has fired. Prefer
+//! `CriticalStop` when a stuck section must not be able to block shutdown.
+//! `GatedStop` needs no clock and works in `no_std`.
+//!
+//! # Example
+//!
+//! ```rust
+//! use almost_enough::{Stop, StopExt, StopReason, StopSource};
+//!
+//! let source = StopSource::new();
+//! let stop = source.as_ref().gate();
+//!
+//! {
+//!     let _hold = stop.hold();
+//!     // rename temp file over the target ...
+//!     source.cancel();
+//!     assert!(stop.check().is_ok()); // ignored while held
+//!     // ... fsync the directory
+//! }
+//!
+//! assert_eq!(stop.check(), Err(StopReason::Cancelled));
+//! ```
+
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use crate::{Stop, StopReason};
+
+/// A [`Stop`] wrapper that ignores its inner stop while disarmed.
+///
+/// Created by [`GatedStop::new()`] or [`StopExt::gate()`](crate::StopExt::gate),
+/// armed. It is armed only when [`disarm()`](Self::disarm) has not been
+/// called (or was undone by [`arm()`](Self::arm)) *and* no [`GateHold`] is
+/// alive. Share it by reference or in an `Arc`; holds borrow it.
+///
+/// Only the inner stop is gated: a `GatedStop` nested inside an
+/// [`OrStop`](crate::OrStop) or a timeout wrapper does not hold back the
+/// other stops. Put it outermost.
+///
+/// # Example
+///
+/// ```rust
+/// use almost_enough::{GatedStop, Stop, StopSource};
+///
+/// let source = StopSource::cancelled();
+/// let stop = GatedStop::new(source.as_ref());
+/// assert!(stop.should_stop());
+///
+/// stop.disarm();
+/// assert!(!stop.should_stop());
+///
+/// stop.arm();
+/// assert!(stop.should_stop());
+/// ```
+pub struct GatedStop<T> {
+    inner: T,
+    disarmed: AtomicBool,
+    holds: AtomicUsize,
+}
+
+impl<T: Stop> GatedStop<T> {
+    /// Wrap `inner`, armed.
+    #[inline]
+    pub const fn new(inner: T) -> Self {
+        Self {
+            inner,
+            disarmed: AtomicBool::new(false),
+            holds: AtomicUsize::new(0),
+        }
+    }
+
+    /// Pass the inner stop through again, unless a [`GateHold`] is alive.
+    #[inline]
+    pub fn arm(&self) {
+        self.disarmed.store(false, Ordering::Release);
+    }
+
+    /// Ignore the inner stop until [`arm()`](Self::arm) is called.
+    #[inline]
+    pub fn disarm(&self) {
+        self.disarmed.store(true, Ordering::Release);
+    }
+
+    /// Ignore the inner stop until the returned guard is dropped.
+    ///
+    /// Holds nest and may be taken from several threads; the gate stays
+    /// closed until the last one is released. A hold does not undo an
+    /// explicit [`disarm()`](Self::disarm) when it ends.
+    #[inline]
+    pub fn hold(&self) -> GateHold<'_, T> {
+        self.holds.fetch_add(1, Ordering::AcqRel);
+        GateHold { gate: self }
+    }
+
+    /// Whether the inner stop is currently passed through.
+    #[inline]
+    pub fn is_armed(&self) -> bool {
+        !self.disarmed.load(Ordering::Acquire) && self.holds.load(Ordering::Acquire) == 0
+    }
+
+    /// Number of [`GateHold`]s currently alive.
+    #[inline]
+    pub fn holds(&self) -> usize {
+        self.holds.load(Ordering::Relaxed)
+    }
+
+    /// Whether the inner stop has fired but the gate is holding it back.
+    #[inline]
+    pub fn is_withholding(&self) -> bool {
+        !self.is_armed() && self.inner.should_stop()
+    }
+
+    /// Get a reference to the inner stop.
+    #[inline]
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Unwrap and return the inner stop.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T: Stop> Stop for GatedStop<T> {
+    #[inline]
+    fn check(&self) -> Result<(), StopReason> {
+        if self.is_armed() {
+            self.inner.check()
+        } else {
+            Ok(())
+        }
+    }
+
+    #[inline]
+    fn should_stop(&self) -> bool {
+        self.is_armed() && self.inner.should_stop()
+    }
+
+    #[inline]
+    fn may_stop(&self) -> bool {
+        self.inner.may_stop()
+    }
+}
+
+impl<T: core::fmt::Debug> core::fmt::Debug for GatedStop<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("GatedStop")
+            .field("inner", &self.inner)
+            .field("disarmed", &self.disarmed.load(Ordering::Relaxed))
+            .field("holds", &self.holds.load(Ordering::Relaxed))
+            .finish()
+    }
+}
+
+/// Guard returned by [`GatedStop::hold()`]; the gate re-opens when the
+/// last hold is dropped.
+#[must_use = "the gate re-opens when the guard is dropped"]
+pub struct GateHold<'a, T> {
+    gate: &'a GatedStop<T>,
+}
+
+impl<T> Drop for GateHold<'_, T> {
+    #[inline]
+    fn drop(&mut self) {
+        self.gate.holds.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+impl<T> core::fmt::Debug for GateHold<'_, T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("GateHold")
+            .field("holds", &self.gate.holds.load(Ordering::Relaxed))
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{StopExt, StopSource, Unstoppable};
+
+    #[test]
+    fn passthrough_when_armed() {
+        let source = StopSource::new();
+        let stop = source.as_ref().gate();
+        assert!(stop.is_armed());
+        assert!(stop.check().is_ok());
+        assert!(stop.may_stop());
+        source.cancel();
+        assert_eq!(stop.check(), Err(StopReason::Cancelled));
+        assert!(!stop.is_withholding());
+    }
+
+    #[test]
+    fn nested_holds_release_together() {
+        let source = StopSource::cancelled();
+        let stop = GatedStop::new(source.as_ref());
+
+        let outer = stop.hold();
+        let inner = stop.hold();
+        assert_eq!(stop.holds(), 2);
+        assert!(stop.is_withholding());
+        assert!(!stop.should_stop());
+
+        drop(outer);
+        assert!(stop.check().is_ok());
+        drop(inner);
+        assert_eq!(stop.holds(), 0);
+        assert!(stop.should_stop());
+    }
+
+    #[test]
+    fn hold_keeps_explicit_disarm() {
+        let source = StopSource::cancelled();
+        let stop = GatedStop::new(source.as_ref());
+        stop.disarm();
+        drop(stop.hold());
+        assert!(!stop.is_armed());
+        assert!(stop.check().is_ok());
+
+        let hold = stop.hold();
+        stop.arm();
+        assert!(!stop.should_stop());
+        drop(hold);
+        assert!(stop.should_stop());
+    }
+
+    #[test]
+    fn accessors() {
+        let stop = Unstoppable.gate();
+        assert!(!stop.may_stop());
+        let _: &Unstoppable = stop.inner();
+        assert_eq!(stop.into_inner(), Unstoppable);
+    }
+
+    #[test]
+    fn is_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<GatedStop<StopSource>>();
+        assert_send_sync::<GateHold<'_, StopSource>>();
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod std_tests {
+    use super::*;
+    use crate::Stopper;
+
+    #[test]
+    fn holds_from_other_threads() {
+        let stopper = Stopper::cancelled();
+        let stop = GatedStop::new(stopper);
+        std::thread::scope(|s| {
+            let hold = stop.hold();
+            s.spawn(|| {
+                assert!(!stop.should_stop());
+                drop(hold);
+            });
+        });
+        assert!(stop.should_stop());
+        assert!(format!("{stop:?}").contains("holds: 0"));
+        assert!(format!("{:?}", stop.hold()).contains("holds: 1"));
+    }
+}
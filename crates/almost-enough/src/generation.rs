@@ -0,0 +1,243 @@
+//! Reusable cancellation with generations.
+//!
+//! A pooled resource (a connection, a decoder, a worker slot) is handed to
+//! one job after another. Giving each job a fresh [`Stopper`](crate::Stopper)
+//! means allocating per job, and resetting a shared flag instead races with
+//! observers from the previous job: a late check by the old job would see
+//! the flag cleared and carry on.
+//!
+//! A [`GenerationStopper`] pairs the flag with a generation counter.
+//! [`token()`](GenerationStopper::token) issues a [`GenerationToken`] for
+//! the current generation, [`cancel()`](GenerationStopper::cancel) stops
+//! the current generation's tokens, and
+//! [`renew()`](GenerationStopper::renew) starts a new, uncancelled
+//! generation. Tokens of earlier generations stay stopped for good, so
+//! reuse never revives an old job.
+//!
+//! Flag and generation share one atomic word; a token check is a single
+//! `Acquire` load. `cancel()` and `renew()` publish with `Release`, so
+//! writes made before them are visible to a token that observes them.
+//! The counter has 56 bits (24 bits on targets without 64-bit atomics),
+//! so a token would only alias a later generation after surviving that
+//! many cancels and renewals.
+//!
+//! # Example
+//!
+//! ```rust
+//! use almost_enough::{GenerationStopper, Stop, StopReason};
+//!
+//! let slot = GenerationStopper::new();
+//!
+//! let first_job = slot.token();
+//! slot.cancel();
+//! assert_eq!(first_job.check(), Err(StopReason::Cancelled));
+//!
+//! // Reuse the slot for the next job
+//! slot.renew();
+//! let second_job = slot.token();
+//! assert!(second_job.check().is_ok());
+//! assert!(first_job.should_stop()); // still stopped
+//! ```
+
+use alloc::sync::Arc;
+use core::sync::atomic::Ordering;
+
+use crate::state::PackedState;
+use crate::{Cancellable, Stop, StopReason};
+
+/// A reusable stopper whose [`renew()`](Self::renew) starts a new
+/// generation; see [`GenerationToken`].
+///
+/// Clones share the same state. As a [`Stop`], it reports the current
+/// generation.
+#[derive(Debug, Clone)]
+pub struct GenerationStopper {
+    state: Arc<PackedState>,
+}
+
+impl GenerationStopper {
+    /// Create a stopper at its first generation, not cancelled.
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new(PackedState::new(false)),
+        }
+    }
+
+    /// A token for the current generation.
+    ///
+    /// A token issued after the current generation was cancelled reports
+    /// the cancellation immediately.
+    #[inline]
+    pub fn token(&self) -> GenerationToken {
+        GenerationToken {
+            state: Arc::clone(&self.state),
+            generation: self.state.load(Ordering::Acquire).generation(),
+        }
+    }
+
+    /// Cancel the current generation.
+    ///
+    /// Idempotent until the next [`renew()`](Self::renew); tokens of
+    /// earlier generations are unaffected, as they are already stopped.
+    #[inline]
+    pub fn cancel(&self) {
+        self.state.cancel(StopReason::Cancelled, Ordering::Release);
+    }
+
+    /// Start a new generation, not cancelled.
+    ///
+    /// Every token issued before this call reports
+    /// [`StopReason::Cancelled`] from now on, whether or not its
+    /// generation was cancelled; only tokens issued afterwards can run.
+    #[inline]
+    pub fn renew(&self) {
+        self.state.renew(Ordering::Release);
+    }
+
+    /// Whether the current generation is cancelled.
+    #[inline]
+    pub fn is_cancelled(&self) -> bool {
+        self.state.is_cancelled(Ordering::Acquire)
+    }
+}
+
+impl Default for GenerationStopper {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Stop for GenerationStopper {
+    #[inline]
+    fn check(&self) -> Result<(), StopReason> {
+        self.state.check(Ordering::Acquire)
+    }
+
+    #[inline]
+    fn should_stop(&self) -> bool {
+        self.is_cancelled()
+    }
+}
+
+impl Cancellable for GenerationStopper {
+    #[inline]
+    fn stop(&self) {
+        self.cancel();
+    }
+}
+
+/// A [`Stop`] for one generation of a [`GenerationStopper`].
+///
+/// Stops when its generation is cancelled or the stopper is
+/// [renewed](GenerationStopper::renew). Clones belong to the same
+/// generation.
+#[derive(Debug, Clone)]
+pub struct GenerationToken {
+    state: Arc<PackedState>,
+    /// The state's transition count when the token was issued.
+    generation: u64,
+}
+
+impl GenerationToken {
+    /// Whether the stopper has not been renewed since this token was
+    /// issued.
+    #[inline]
+    pub fn is_current(&self) -> bool {
+        self.state(Ordering::Acquire).is_some()
+    }
+
+    /// The state of this token's generation, or `None` once it has been
+    /// renewed away.
+    #[inline]
+    fn state(&self, order: Ordering) -> Option<Result<(), StopReason>> {
+        let now = self.state.load(order);
+        // Cancelling bumps the transition count once (or not at all when
+        // it has saturated); renewing always clears the flag.
+        if now.generation() == self.generation
+            || (now.is_cancelled() && now.generation() == self.generation + 1)
+        {
+            Some(now.check())
+        } else {
+            None
+        }
+    }
+}
+
+impl Stop for GenerationToken {
+    #[inline]
+    fn check(&self) -> Result<(), StopReason> {
+        self.state(Ordering::Acquire)
+            .unwrap_or(Err(StopReason::Cancelled))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cancel_reaches_current_tokens() {
+        let stop = GenerationStopper::default();
+        let token = stop.token();
+        let clone = token.clone();
+        assert!(token.check().is_ok());
+        assert!(token.is_current());
+
+        Cancellable::stop(&stop);
+        assert_eq!(token.check(), Err(StopReason::Cancelled));
+        assert!(clone.should_stop());
+        assert!(token.is_current());
+        assert!(stop.is_cancelled());
+        assert_eq!(stop.check(), Err(StopReason::Cancelled));
+
+        // Issued after the cancel: stopped from the start
+        assert!(stop.token().should_stop());
+    }
+
+    #[test]
+    fn renew_retires_old_tokens() {
+        let stop = GenerationStopper::new();
+        let uncancelled = stop.token();
+        stop.renew();
+        assert!(!stop.is_cancelled());
+        assert!(!uncancelled.is_current());
+        assert_eq!(uncancelled.check(), Err(StopReason::Cancelled));
+
+        let cancelled = stop.token();
+        stop.cancel();
+        stop.renew();
+        let fresh = stop.token();
+        assert!(cancelled.should_stop());
+        assert!(fresh.check().is_ok());
+
+        // Cancelling the new generation only affects its own tokens
+        stop.cancel();
+        assert!(fresh.should_stop());
+        stop.cancel();
+        stop.renew();
+        assert!(stop.token().check().is_ok());
+        assert!(fresh.should_stop());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn concurrent_observers_never_revive() {
+        let stop = GenerationStopper::new();
+        let token = stop.token();
+        let observer = std::thread::spawn(move || {
+            while !token.should_stop() {
+                std::hint::spin_loop();
+            }
+            for _ in 0..1000 {
+                assert!(token.should_stop());
+            }
+        });
+        stop.cancel();
+        for _ in 0..100 {
+            stop.renew();
+            stop.cancel();
+        }
+        observer.join().unwrap();
+    }
+}
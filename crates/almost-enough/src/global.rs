@@ -0,0 +1,78 @@
+//! A process-wide "application stop".
+//!
+//! [`global()`] returns one [`Stopper`] shared by the whole process,
+//! created on first use. Libraries can fall back to it when a caller passes
+//! no stop, and a shutdown handler cancels everything that did so with
+//! [`set_global_cancelled()`].
+//!
+//! # When not to use it
+//!
+//! The global stop is a fallback, not a substitute for passing a stop
+//! down. It cannot be reset, so once cancelled it stays cancelled for the
+//! life of the process: tests that cancel it affect every other test in the
+//! same binary, and a long-running server cannot use it per request. It
+//! also cancels *every* user at once; work that must outlive shutdown
+//! (flushing a log, committing a transaction) should be given its own stop.
+//! Prefer taking an explicit `impl Stop` parameter, and reach for the
+//! global only where an API has no room for one.
+//!
+//! Requires the `global` feature.
+//!
+//! # Example
+//!
+//! ```rust
+//! use almost_enough::{Stop, StopReason, global};
+//!
+//! // A library entry point where the stop is optional
+//! fn encode(data: &[u8], stop: Option<&dyn Stop>) -> Result<usize, StopReason> {
+//!     let stop = stop.unwrap_or(global());
+//!     stop.check()?;
+//!     Ok(data.len())
+//! }
+//!
+//! assert_eq!(encode(b"abc", None), Ok(3));
+//! ```
+
+use std::sync::OnceLock;
+
+use crate::Stopper;
+
+static GLOBAL: OnceLock<Stopper> = OnceLock::new();
+
+/// The process-wide [`Stopper`], created on first use.
+///
+/// Every call returns the same stopper, labeled `"global"`. Read the
+/// [module docs](self) before relying on it; cancel it with
+/// [`set_global_cancelled()`].
+#[inline]
+pub fn global() -> &'static Stopper {
+    GLOBAL.get_or_init(|| Stopper::with_label("global"))
+}
+
+/// Cancel the process-wide stopper, e.g. from a shutdown handler.
+///
+/// Idempotent, and permanent: the global stopper cannot be reset.
+#[inline]
+pub fn set_global_cancelled() {
+    global().cancel();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{NodeLabel, Stop, StopReason};
+
+    // The only test that cancels the global; keep it that way.
+    #[test]
+    fn shared_and_cancellable() {
+        let first = global();
+        assert!(core::ptr::eq(first, global()));
+        assert_eq!(first.label(), Some(NodeLabel::Name("global")));
+
+        let clone = first.clone();
+        set_global_cancelled();
+        set_global_cancelled();
+        assert_eq!(clone.check(), Err(StopReason::Cancelled));
+        assert!(global().should_stop());
+    }
+}
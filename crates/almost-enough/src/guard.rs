@@ -149,6 +149,8 @@ impl<C: Cancellable> CancelGuard<C> {
 impl<C: Cancellable> Drop for CancelGuard<C> {
     fn drop(&mut self) {
         if let Some(source) = self.source.take() {
+            #[cfg(feature = "log")]
+            crate::logging::guard_cancelled();
             source.stop();
         }
     }
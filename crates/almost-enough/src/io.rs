@@ -0,0 +1,510 @@
+//! Cancellation-aware I/O helpers.
+//!
+//! [`StopReader`] and [`StopWriter`] check a stop before every read or
+//! write and fail with an [`io::Error`] once it trips, so a streaming
+//! decoder that only takes `impl Read` becomes cancellable without an API
+//! change. [`stop_reason()`] recovers the [`StopReason`] from that error.
+//!
+//! Shutdown paths that flush buffered output tend to pick one of two bad
+//! options: flush unconditionally (and hang if the sink is stuck) or drop the
+//! buffer (and silently lose logs). [`flush_with_deadline()`] sits in
+//! between: it flushes normally until the stop trips, then keeps going for at
+//! most a grace period and reports how many bytes it had to drop.
+//!
+//! # Example
+//!
+//! ```rust
+//! use almost_enough::io::flush_with_deadline;
+//! use almost_enough::Stopper;
+//! use std::io::{BufWriter, Write};
+//! use std::time::Duration;
+//!
+//! let mut log = BufWriter::new(Vec::new());
+//! writeln!(log, "shutting down").unwrap();
+//!
+//! let shutdown = Stopper::cancelled();
+//! let (sink, report) = flush_with_deadline(log, &shutdown, Duration::from_millis(50));
+//!
+//! assert!(report.is_complete());
+//! assert_eq!(sink, b"shutting down\n");
+//! ```
+
+use core::fmt;
+use std::io::{self, BufRead, BufWriter, ErrorKind, Read, Write};
+use std::time::{Duration, Instant};
+
+use crate::{Stop, StopReason};
+
+/// The payload of an [`io::Error`] produced by a tripped stop.
+#[derive(Debug)]
+struct Stopped(StopReason);
+
+impl fmt::Display for Stopped {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl std::error::Error for Stopped {}
+
+/// Convert a stop into an [`io::Error`].
+///
+/// [`StopReason::TimedOut`] becomes [`ErrorKind::TimedOut`]; a cancellation
+/// becomes [`ErrorKind::Other`]. Not [`ErrorKind::Interrupted`]: `read_exact`,
+/// `read_to_end`, `write_all` and `io::copy` retry that kind, and would
+/// spin forever on a stop that stays tripped.
+fn stopped(reason: StopReason) -> io::Error {
+    let kind = match reason {
+        StopReason::TimedOut => ErrorKind::TimedOut,
+        _ => ErrorKind::Other,
+    };
+    io::Error::new(kind, Stopped(reason))
+}
+
+/// The [`StopReason`] behind an error from [`StopReader`] or
+/// [`StopWriter`], or `None` for any other error.
+///
+/// Also finds the reason when the error was wrapped by a decoder that
+/// keeps the original [`io::Error`] as its payload.
+pub fn stop_reason(error: &io::Error) -> Option<StopReason> {
+    let inner = error.get_ref()?;
+    if let Some(Stopped(reason)) = inner.downcast_ref::<Stopped>() {
+        return Some(*reason);
+    }
+    stop_reason(inner.downcast_ref::<io::Error>()?)
+}
+
+/// A [`Read`] adapter that checks a stop before every read.
+///
+/// Once the stop trips, every read fails without touching the inner reader;
+/// see [`stop_reason()`] for the error. A single read that blocks is not
+/// interrupted, so the stop is observed at the granularity of the caller's
+/// reads. [`BufRead`] is forwarded when the inner reader implements it,
+/// checking before each refill.
+///
+/// # Example
+///
+/// ```rust
+/// use almost_enough::io::{StopReader, stop_reason};
+/// use almost_enough::{StopReason, Stopper};
+/// use std::io::Read;
+///
+/// let stop = Stopper::new();
+/// let mut reader = StopReader::new(&b"hello world"[..], stop.clone());
+///
+/// let mut head = [0; 5];
+/// reader.read_exact(&mut head).unwrap();
+/// assert_eq!(&head, b"hello");
+///
+/// stop.cancel();
+/// let err = reader.read_to_end(&mut Vec::new()).unwrap_err();
+/// assert_eq!(stop_reason(&err), Some(StopReason::Cancelled));
+/// ```
+#[derive(Debug)]
+pub struct StopReader<R, S> {
+    inner: R,
+    stop: S,
+}
+
+impl<R, S: Stop> StopReader<R, S> {
+    /// Wrap `inner`, checking `stop` before every read.
+    #[inline]
+    pub fn new(inner: R, stop: S) -> Self {
+        Self { inner, stop }
+    }
+
+    /// Get a reference to the inner reader.
+    #[inline]
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the inner reader.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Get a reference to the stop.
+    #[inline]
+    pub fn stop(&self) -> &S {
+        &self.stop
+    }
+
+    /// Unwrap and return the inner reader.
+    #[inline]
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read, S: Stop> Read for StopReader<R, S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.stop.check().map_err(stopped)?;
+        self.inner.read(buf)
+    }
+
+    fn read_vectored(&mut self, bufs: &mut [io::IoSliceMut<'_>]) -> io::Result<usize> {
+        self.stop.check().map_err(stopped)?;
+        self.inner.read_vectored(bufs)
+    }
+}
+
+impl<R: BufRead, S: Stop> BufRead for StopReader<R, S> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.stop.check().map_err(stopped)?;
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.inner.consume(amt);
+    }
+}
+
+/// A [`Write`] adapter that checks a stop before every write.
+///
+/// Once the stop trips, every write fails without touching the inner
+/// writer; see [`stop_reason()`] for the error. [`flush()`](Write::flush) is
+/// forwarded unchecked, so bytes the inner writer already accepted can still
+/// be pushed out after a cancellation. To bound that flush, use
+/// [`flush_with_deadline()`].
+///
+/// # Example
+///
+/// ```rust
+/// use almost_enough::io::{StopWriter, stop_reason};
+/// use almost_enough::{StopReason, Stopper};
+/// use std::io::Write;
+///
+/// let stop = Stopper::new();
+/// let mut writer = StopWriter::new(Vec::new(), stop.clone());
+/// writer.write_all(b"partial").unwrap();
+///
+/// stop.cancel();
+/// let err = writer.write_all(b" output").unwrap_err();
+/// assert_eq!(stop_reason(&err), Some(StopReason::Cancelled));
+/// assert_eq!(writer.into_inner(), b"partial");
+/// ```
+#[derive(Debug)]
+pub struct StopWriter<W, S> {
+    inner: W,
+    stop: S,
+}
+
+impl<W, S: Stop> StopWriter<W, S> {
+    /// Wrap `inner`, checking `stop` before every write.
+    #[inline]
+    pub fn new(inner: W, stop: S) -> Self {
+        Self { inner, stop }
+    }
+
+    /// Get a reference to the inner writer.
+    #[inline]
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the inner writer.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Get a reference to the stop.
+    #[inline]
+    pub fn stop(&self) -> &S {
+        &self.stop
+    }
+
+    /// Unwrap and return the inner writer.
+    #[inline]
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write, S: Stop> Write for StopWriter<W, S> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.stop.check().map_err(stopped)?;
+        self.inner.write(buf)
+    }
+
+    fn write_vectored(&mut self, bufs: &[io::IoSlice<'_>]) -> io::Result<usize> {
+        self.stop.check().map_err(stopped)?;
+        self.inner.write_vectored(bufs)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Largest slice handed to the inner writer between deadline checks.
+const CHUNK: usize = 8 * 1024;
+
+/// Outcome of [`flush_with_deadline()`].
+#[derive(Debug)]
+pub struct FlushReport {
+    written: usize,
+    dropped: usize,
+    error: Option<io::Error>,
+}
+
+impl FlushReport {
+    /// Bytes written to the inner writer.
+    #[inline]
+    pub fn written(&self) -> usize {
+        self.written
+    }
+
+    /// Bytes discarded because the grace period ran out or a write failed.
+    #[inline]
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+
+    /// The I/O error that ended the flush early, if any.
+    #[inline]
+    pub fn error(&self) -> Option<&io::Error> {
+        self.error.as_ref()
+    }
+
+    /// Returns `true` if every buffered byte was written and flushed.
+    #[inline]
+    pub fn is_complete(&self) -> bool {
+        self.dropped == 0 && self.error.is_none()
+    }
+}
+
+/// Flush a [`BufWriter`], bounding the time spent once `stop` trips.
+///
+/// Buffered bytes are written to the inner writer in chunks. While `stop`
+/// has not tripped this behaves like a normal flush. Once it trips (before
+/// or during the flush), writing continues for at most `grace`; whatever is
+/// still buffered when the grace period ends is dropped and counted in
+/// [`FlushReport::dropped()`]. A write error also ends the flush, dropping
+/// the remainder.
+///
+/// The deadline is checked between writes, so a single write that blocks
+/// indefinitely is not interrupted — pair this with a sink that has its own
+/// write timeout if that matters.
+///
+/// Returns the inner writer together with the report.
+pub fn flush_with_deadline<W: Write>(
+    writer: BufWriter<W>,
+    stop: &impl Stop,
+    grace: Duration,
+) -> (W, FlushReport) {
+    let (mut inner, buffered) = writer.into_parts();
+    // A panicked writer still hands back the unwritten bytes
+    let buffered = buffered.unwrap_or_else(|panicked| panicked.into_inner());
+
+    let mut deadline: Option<Instant> = None;
+    let mut written = 0;
+    let mut error = None;
+
+    while written < buffered.len() {
+        if deadline.is_none() && stop.should_stop() {
+            deadline = Some(Instant::now() + grace);
+        }
+        if deadline.is_some_and(|d| Instant::now() >= d) {
+            break;
+        }
+        let end = buffered.len().min(written + CHUNK);
+        match inner.write(&buffered[written..end]) {
+            Ok(0) => {
+                error = Some(io::Error::from(ErrorKind::WriteZero));
+                break;
+            }
+            Ok(n) => written += n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => {
+                error = Some(e);
+                break;
+            }
+        }
+    }
+
+    let dropped = buffered.len() - written;
+    if dropped == 0 && error.is_none() {
+        error = inner.flush().err();
+    }
+    (
+        inner,
+        FlushReport {
+            written,
+            dropped,
+            error,
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Stopper, Unstoppable};
+
+    /// Accepts at most `per_write` bytes per call, sleeping `delay` each time.
+    struct SlowSink {
+        data: Vec<u8>,
+        per_write: usize,
+        delay: Duration,
+    }
+
+    impl Write for SlowSink {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            std::thread::sleep(self.delay);
+            let n = buf.len().min(self.per_write);
+            self.data.extend_from_slice(&buf[..n]);
+            Ok(n)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    struct FailingSink;
+
+    impl Write for FailingSink {
+        fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+            Err(io::Error::from(ErrorKind::BrokenPipe))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn buffered<W: Write>(inner: W, bytes: usize) -> BufWriter<W> {
+        // Larger than the write so BufWriter keeps everything buffered
+        let mut writer = BufWriter::with_capacity(bytes + 1, inner);
+        writer.write_all(&vec![b'x'; bytes]).unwrap();
+        writer
+    }
+
+    #[test]
+    fn not_stopped_flushes_everything() {
+        let sink = SlowSink {
+            data: Vec::new(),
+            per_write: 100,
+            delay: Duration::ZERO,
+        };
+        let (sink, report) =
+            flush_with_deadline(buffered(sink, 1000), &Unstoppable, Duration::ZERO);
+        assert!(report.is_complete());
+        assert_eq!(report.written(), 1000);
+        assert_eq!(sink.data.len(), 1000);
+    }
+
+    #[test]
+    fn stopped_with_generous_grace_completes() {
+        let stop = Stopper::cancelled();
+        let (sink, report) =
+            flush_with_deadline(buffered(Vec::new(), 20_000), &stop, Duration::from_secs(10));
+        assert!(report.is_complete());
+        assert_eq!(sink.len(), 20_000);
+    }
+
+    #[test]
+    fn stopped_with_zero_grace_drops_everything() {
+        let stop = Stopper::cancelled();
+        let (sink, report) = flush_with_deadline(buffered(Vec::new(), 500), &stop, Duration::ZERO);
+        assert_eq!(report.written(), 0);
+        assert_eq!(report.dropped(), 500);
+        assert!(!report.is_complete());
+        assert!(sink.is_empty());
+    }
+
+    #[test]
+    fn grace_period_bounds_slow_sink() {
+        let sink = SlowSink {
+            data: Vec::new(),
+            per_write: 10,
+            delay: Duration::from_millis(5),
+        };
+        let stop = Stopper::cancelled();
+        let start = Instant::now();
+        let (sink, report) =
+            flush_with_deadline(buffered(sink, 10_000), &stop, Duration::from_millis(30));
+
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert!(report.dropped() > 0);
+        assert_eq!(report.written() + report.dropped(), 10_000);
+        assert_eq!(sink.data.len(), report.written());
+    }
+
+    #[test]
+    fn write_error_drops_remainder() {
+        let (_, report) =
+            flush_with_deadline(buffered(FailingSink, 64), &Unstoppable, Duration::ZERO);
+        assert_eq!(report.dropped(), 64);
+        assert_eq!(report.error().unwrap().kind(), ErrorKind::BrokenPipe);
+    }
+
+    #[test]
+    fn empty_buffer() {
+        let stop = Stopper::cancelled();
+        let (_, report) = flush_with_deadline(BufWriter::new(Vec::new()), &stop, Duration::ZERO);
+        assert!(report.is_complete());
+        assert_eq!(report.written(), 0);
+    }
+
+    #[test]
+    fn reader_passes_through_until_stopped() {
+        let stop = Stopper::new();
+        let mut reader = StopReader::new(io::BufReader::new(&b"one\ntwo\n"[..]), stop.clone());
+
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        assert_eq!(line, "one\n");
+
+        stop.cancel();
+        let err = reader.read_line(&mut line).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Other);
+        assert_eq!(stop_reason(&err), Some(StopReason::Cancelled));
+    }
+
+    #[test]
+    fn retrying_helpers_do_not_spin() {
+        let stop = Stopper::cancelled();
+        let mut reader = StopReader::new(io::repeat(0), &stop);
+        let mut writer = StopWriter::new(io::sink(), &stop);
+
+        assert!(reader.read_exact(&mut [0; 4]).is_err());
+        assert!(reader.read_to_end(&mut Vec::new()).is_err());
+        assert!(writer.write_all(b"x").is_err());
+        let err = io::copy(&mut reader, &mut io::sink()).unwrap_err();
+        assert_eq!(stop_reason(&err), Some(StopReason::Cancelled));
+    }
+
+    #[test]
+    fn timeout_maps_to_timed_out() {
+        use crate::TimeoutExt;
+        let stop = Unstoppable.with_timeout(Duration::ZERO);
+        let mut writer = StopWriter::new(Vec::new(), stop);
+        let err = writer.write(b"x").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::TimedOut);
+        assert_eq!(stop_reason(&err), Some(StopReason::TimedOut));
+        assert!(writer.get_ref().is_empty());
+    }
+
+    #[test]
+    fn writer_flush_is_unchecked() {
+        let stop = Stopper::new();
+        let mut writer = StopWriter::new(BufWriter::new(Vec::new()), stop.clone());
+        writer.write_all(b"buffered").unwrap();
+        stop.cancel();
+        writer.flush().unwrap();
+        assert_eq!(writer.get_ref().get_ref(), b"buffered");
+    }
+
+    #[test]
+    fn stop_reason_ignores_other_errors() {
+        assert_eq!(stop_reason(&io::Error::from(ErrorKind::TimedOut)), None);
+        assert_eq!(stop_reason(&io::Error::other("boom")), None);
+
+        let wrapped = io::Error::other(stopped(StopReason::TimedOut));
+        assert_eq!(stop_reason(&wrapped), Some(StopReason::TimedOut));
+    }
+}
@@ -0,0 +1,315 @@
+//! Iterator adapters with periodic cancellation checks.
+//!
+//! [`StopIterExt`] replaces the hand-rolled pattern
+//!
+//! ```text
+//! for (i, item) in items.enumerate() {
+//!     if i % 64 == 0 { stop.check()?; }
+//!     ...
+//! }
+//! ```
+//!
+//! with an adapter. Works in `no_std` environments.
+//!
+//! # Example
+//!
+//! ```rust
+//! use almost_enough::{StopIterExt, StopSource, StopReason};
+//!
+//! let source = StopSource::new();
+//!
+//! // Checks the stop before items 0, 64, 128, ...
+//! let sum: Result<u64, StopReason> = (0..1000u64)
+//!     .stop_every(source.as_ref(), 64)
+//!     .sum();
+//! assert_eq!(sum, Ok(499_500));
+//!
+//! source.cancel();
+//! let sum: Result<u64, StopReason> = (0..1000u64)
+//!     .stop_every(source.as_ref(), 64)
+//!     .sum();
+//! assert_eq!(sum, Err(StopReason::Cancelled));
+//! ```
+
+use core::iter::FusedIterator;
+
+use crate::{Stop, StopReason};
+
+/// Extension trait adding cancellation-checking adapters to iterators.
+///
+/// Automatically implemented for all [`Iterator`]s.
+///
+/// Both adapters check `stop` before the first item and then before every
+/// `interval`-th item. An `interval` of 0 is treated as 1 (check before
+/// every item).
+pub trait StopIterExt: Iterator + Sized {
+    /// Yield `Ok(item)` until `stop` trips, then yield one `Err(reason)`
+    /// and end.
+    ///
+    /// Collects naturally into `Result<Vec<_>, StopReason>`, or sums into
+    /// `Result<T, StopReason>`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use almost_enough::{BudgetStop, StopIterExt, StopReason};
+    ///
+    /// let budget = BudgetStop::checks(3);
+    /// let items: Vec<_> = (0..10).stop_every(&budget, 2).collect();
+    ///
+    /// // Checks before items 0, 2 and 4 pass; the one before 6 trips
+    /// assert_eq!(items.len(), 7);
+    /// assert_eq!(items[6], Err(StopReason::TimedOut));
+    /// ```
+    #[inline]
+    fn stop_every<S: Stop>(self, stop: S, interval: usize) -> StopEvery<Self, S> {
+        StopEvery {
+            iter: self,
+            stop,
+            interval: interval.max(1),
+            countdown: 0,
+            done: false,
+        }
+    }
+
+    /// Yield items until `stop` trips, then end silently.
+    ///
+    /// Use this when a partial result is acceptable; the reason is
+    /// available afterwards via [`UntilStopped::stopped()`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use almost_enough::{BudgetStop, StopIterExt, StopReason};
+    ///
+    /// let budget = BudgetStop::checks(2);
+    /// let mut iter = (0..100).until_stopped(&budget, 10);
+    /// let taken: Vec<_> = iter.by_ref().collect();
+    ///
+    /// assert_eq!(taken.len(), 20);
+    /// assert_eq!(iter.stopped(), Some(StopReason::TimedOut));
+    /// ```
+    #[inline]
+    fn until_stopped<S: Stop>(self, stop: S, interval: usize) -> UntilStopped<Self, S> {
+        UntilStopped {
+            inner: self.stop_every(stop, interval),
+            stopped: None,
+        }
+    }
+}
+
+impl<I: Iterator> StopIterExt for I {}
+
+/// Iterator returned by [`StopIterExt::stop_every()`].
+#[derive(Debug, Clone)]
+#[must_use = "iterator adapters are lazy and do nothing unless consumed"]
+pub struct StopEvery<I, S> {
+    iter: I,
+    stop: S,
+    interval: usize,
+    /// Items left before the next check.
+    countdown: usize,
+    done: bool,
+}
+
+impl<I, S> StopEvery<I, S> {
+    /// Unwrap and return the underlying iterator.
+    #[inline]
+    pub fn into_inner(self) -> I {
+        self.iter
+    }
+}
+
+impl<I: Iterator, S: Stop> Iterator for StopEvery<I, S> {
+    type Item = Result<I::Item, StopReason>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        if self.countdown == 0 {
+            if let Err(reason) = self.stop.check() {
+                self.done = true;
+                return Some(Err(reason));
+            }
+            self.countdown = self.interval;
+        }
+        self.countdown -= 1;
+        match self.iter.next() {
+            Some(item) => Some(Ok(item)),
+            None => {
+                self.done = true;
+                None
+            }
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.done {
+            return (0, Some(0));
+        }
+        // May end early on a stop, and may add one `Err`
+        let (_, upper) = self.iter.size_hint();
+        (0, upper.and_then(|n| n.checked_add(1)))
+    }
+}
+
+impl<I: Iterator, S: Stop> FusedIterator for StopEvery<I, S> {}
+
+/// Iterator returned by [`StopIterExt::until_stopped()`].
+#[derive(Debug, Clone)]
+#[must_use = "iterator adapters are lazy and do nothing unless consumed"]
+pub struct UntilStopped<I, S> {
+    inner: StopEvery<I, S>,
+    stopped: Option<StopReason>,
+}
+
+impl<I, S> UntilStopped<I, S> {
+    /// The reason iteration ended early, or `None` if it hasn't.
+    #[inline]
+    pub fn stopped(&self) -> Option<StopReason> {
+        self.stopped
+    }
+
+    /// Unwrap and return the underlying iterator.
+    #[inline]
+    pub fn into_inner(self) -> I {
+        self.inner.into_inner()
+    }
+}
+
+impl<I: Iterator, S: Stop> Iterator for UntilStopped<I, S> {
+    type Item = I::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.inner.next()? {
+            Ok(item) => Some(item),
+            Err(reason) => {
+                self.stopped = Some(reason);
+                None
+            }
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (_, upper) = self.inner.iter.size_hint();
+        if self.inner.done {
+            (0, Some(0))
+        } else {
+            (0, upper)
+        }
+    }
+}
+
+impl<I: Iterator, S: Stop> FusedIterator for UntilStopped<I, S> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BudgetStop, StopSource, Unstoppable};
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Counts how many times `check()` is called.
+    struct CountingStop<'a>(&'a AtomicUsize);
+
+    impl Stop for CountingStop<'_> {
+        fn check(&self) -> Result<(), StopReason> {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            Ok(())
+        }
+
+        fn should_stop(&self) -> bool {
+            self.check().is_err()
+        }
+    }
+
+    #[test]
+    fn unstopped_yields_everything() {
+        let mut iter = (0..5).stop_every(Unstoppable, 2);
+        for i in 0..5 {
+            assert_eq!(iter.next(), Some(Ok(i)));
+        }
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn checks_every_interval() {
+        let calls = AtomicUsize::new(0);
+        let count = (0..10).stop_every(CountingStop(&calls), 4).count();
+        assert_eq!(count, 10);
+        // Before items 0, 4 and 8
+        assert_eq!(calls.load(Ordering::Relaxed), 3);
+    }
+
+    #[test]
+    fn zero_interval_checks_every_item() {
+        let calls = AtomicUsize::new(0);
+        let count = (0..5).stop_every(CountingStop(&calls), 0).count();
+        assert_eq!(count, 5);
+        // Once per item, plus the call that found the iterator exhausted
+        assert_eq!(calls.load(Ordering::Relaxed), 6);
+    }
+
+    #[test]
+    fn error_is_yielded_once_then_fused() {
+        let source = StopSource::new();
+        source.cancel();
+        let mut iter = (0..5).stop_every(source.as_ref(), 1);
+        assert_eq!(iter.next(), Some(Err(StopReason::Cancelled)));
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.size_hint(), (0, Some(0)));
+    }
+
+    #[test]
+    fn stops_mid_iteration() {
+        let budget = BudgetStop::checks(2);
+        let mut seen = 0;
+        let mut reason = None;
+        for item in (0..100).stop_every(&budget, 5) {
+            match item {
+                Ok(_) => seen += 1,
+                Err(r) => reason = Some(r),
+            }
+        }
+        assert_eq!(seen, 10);
+        assert_eq!(reason, Some(StopReason::TimedOut));
+    }
+
+    #[test]
+    fn until_stopped_records_reason() {
+        let source = StopSource::new();
+        let mut iter = (0..3).until_stopped(source.as_ref(), 1);
+        assert_eq!(iter.next(), Some(0));
+        source.cancel();
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.stopped(), Some(StopReason::Cancelled));
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn until_stopped_completes_without_reason() {
+        let mut iter = (0..3).until_stopped(Unstoppable, 1);
+        assert_eq!(iter.by_ref().sum::<i32>(), 3);
+        assert_eq!(iter.stopped(), None);
+    }
+
+    #[test]
+    fn size_hint_bounds() {
+        let iter = (0..10).stop_every(Unstoppable, 1);
+        assert_eq!(iter.size_hint(), (0, Some(11)));
+        let iter = (0..10).until_stopped(Unstoppable, 1);
+        assert_eq!(iter.size_hint(), (0, Some(10)));
+    }
+
+    #[test]
+    fn into_inner_returns_remaining() {
+        let mut iter = (0..5).stop_every(Unstoppable, 1);
+        iter.next();
+        assert_eq!(iter.into_inner().next(), Some(1));
+    }
+}
@@ -0,0 +1,380 @@
+//! Joining a cancellable worker thread with a deadline.
+//!
+//! Shutting down a worker correctly takes several steps: cancel its stop,
+//! wait for it with a timeout (which `JoinHandle` cannot do), tell a panic
+//! apart from a normal return, and decide what to do with a thread that
+//! ignores the cancellation. [`StoppableJoinHandle`] bundles the thread
+//! with its [`Stopper`] and does all of it in
+//! [`stop_and_join()`](StoppableJoinHandle::stop_and_join).
+//!
+//! # Example
+//!
+//! ```rust
+//! use almost_enough::{JoinOutcome, Stop, StoppableJoinHandle, Stopper};
+//! use std::time::Duration;
+//!
+//! let worker = StoppableJoinHandle::spawn(Stopper::new(), |stop| {
+//!     let mut processed = 0u64;
+//!     while !stop.should_stop() {
+//!         processed += 1;
+//!         std::thread::yield_now();
+//!     }
+//!     processed
+//! });
+//!
+//! match worker.stop_and_join(Duration::from_secs(5)) {
+//!     JoinOutcome::Completed(processed) => println!("stopped after {processed}"),
+//!     JoinOutcome::Panicked(payload) => std::panic::resume_unwind(payload),
+//!     JoinOutcome::TimedOutStillRunning(straggler) => straggler.detach(),
+//! }
+//! ```
+
+use std::any::Any;
+use std::sync::{Arc, Condvar, Mutex, PoisonError};
+use std::thread::{self, JoinHandle, Thread};
+use std::time::Duration;
+
+use crate::{Outcome, StopReason, Stopper};
+
+/// Completion flag set by the worker thread, even when it unwinds.
+#[derive(Default)]
+struct Finished {
+    done: Mutex<bool>,
+    cond: Condvar,
+}
+
+impl Finished {
+    /// Wait until the worker has finished or `timeout` elapses.
+    fn wait(&self, timeout: Duration) -> bool {
+        let done = self.done.lock().unwrap_or_else(PoisonError::into_inner);
+        let (done, _) = self
+            .cond
+            .wait_timeout_while(done, timeout, |done| !*done)
+            .unwrap_or_else(PoisonError::into_inner);
+        *done
+    }
+}
+
+/// Marks the worker finished when dropped, i.e. on return or unwind.
+struct MarkFinished(Arc<Finished>);
+
+impl Drop for MarkFinished {
+    fn drop(&mut self) {
+        *self.0.done.lock().unwrap_or_else(PoisonError::into_inner) = true;
+        self.0.cond.notify_all();
+    }
+}
+
+/// The result of [`StoppableJoinHandle::stop_and_join()`] or
+/// [`join_timeout()`](StoppableJoinHandle::join_timeout).
+#[must_use = "a panicked worker is only reported, not propagated"]
+pub enum JoinOutcome<T> {
+    /// The worker returned a value.
+    Completed(T),
+    /// The worker did not finish in time. Join it again later, or call
+    /// [`detach()`](StoppableJoinHandle::detach) (dropping it also
+    /// detaches).
+    TimedOutStillRunning(StoppableJoinHandle<T>),
+    /// The worker panicked; the payload can be re-raised with
+    /// [`std::panic::resume_unwind`].
+    Panicked(Box<dyn Any + Send + 'static>),
+}
+
+impl<T> JoinOutcome<T> {
+    /// Whether the worker returned a value.
+    #[inline]
+    pub fn is_completed(&self) -> bool {
+        matches!(self, Self::Completed(_))
+    }
+
+    /// Re-raise a worker panic on the current thread.
+    ///
+    /// Returns the value on completion, or the still-running handle on
+    /// timeout.
+    pub fn resume_panic(self) -> Result<T, StoppableJoinHandle<T>> {
+        match self {
+            Self::Completed(value) => Ok(value),
+            Self::TimedOutStillRunning(handle) => Err(handle),
+            Self::Panicked(payload) => std::panic::resume_unwind(payload),
+        }
+    }
+}
+
+/// A worker still running past the timeout becomes
+/// [`Stopped`](Outcome::Stopped) with [`StopReason::TimedOut`] and is
+/// detached; a panic payload is leaked, as in
+/// [`Outcome::from_join()`].
+impl<T> From<JoinOutcome<T>> for Outcome<T> {
+    fn from(outcome: JoinOutcome<T>) -> Self {
+        match outcome {
+            JoinOutcome::Completed(value) => Self::Completed(value),
+            JoinOutcome::TimedOutStillRunning(handle) => {
+                handle.detach();
+                Self::Stopped {
+                    reason: StopReason::TimedOut,
+                    partial: None,
+                }
+            }
+            JoinOutcome::Panicked(payload) => {
+                std::mem::forget(payload);
+                Self::Panicked
+            }
+        }
+    }
+}
+
+impl<T: core::fmt::Debug> core::fmt::Debug for JoinOutcome<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Completed(value) => f.debug_tuple("Completed").field(value).finish(),
+            Self::TimedOutStillRunning(handle) => {
+                f.debug_tuple("TimedOutStillRunning").field(handle).finish()
+            }
+            Self::Panicked(_) => f.debug_tuple("Panicked").finish_non_exhaustive(),
+        }
+    }
+}
+
+/// A worker thread bundled with the [`Stopper`] that cancels it.
+///
+/// Created by [`StoppableJoinHandle::spawn()`]. Dropping the handle detaches
+/// the thread without cancelling it, like a plain `JoinHandle`.
+///
+/// # Example
+///
+/// ```rust
+/// use almost_enough::{JoinOutcome, StoppableJoinHandle, Stopper};
+/// use std::time::Duration;
+///
+/// let worker = StoppableJoinHandle::spawn(Stopper::new(), |_stop| {
+///     // Ignores its stop entirely
+///     std::thread::sleep(Duration::from_millis(200));
+/// });
+///
+/// let outcome = worker.stop_and_join(Duration::from_millis(1));
+/// assert!(matches!(outcome, JoinOutcome::TimedOutStillRunning(_)));
+/// ```
+pub struct StoppableJoinHandle<T> {
+    handle: JoinHandle<T>,
+    stopper: Stopper,
+    finished: Arc<Finished>,
+}
+
+impl<T: Send + 'static> StoppableJoinHandle<T> {
+    /// Spawn `work` on a new thread, passing it a clone of `stopper`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the OS fails to create a thread, like [`thread::spawn`].
+    pub fn spawn<F>(stopper: Stopper, work: F) -> Self
+    where
+        F: FnOnce(Stopper) -> T + Send + 'static,
+    {
+        let finished = Arc::new(Finished::default());
+        let handle = {
+            let stopper = stopper.clone();
+            let mark = MarkFinished(Arc::clone(&finished));
+            thread::spawn(move || {
+                let _mark = mark;
+                work(stopper)
+            })
+        };
+        Self {
+            handle,
+            stopper,
+            finished,
+        }
+    }
+}
+
+impl<T> StoppableJoinHandle<T> {
+    /// Cancel the worker, then wait up to `timeout` for it to finish.
+    pub fn stop_and_join(self, timeout: Duration) -> JoinOutcome<T> {
+        self.stopper.cancel();
+        self.join_timeout(timeout)
+    }
+
+    /// Wait up to `timeout` for the worker to finish, without cancelling it.
+    pub fn join_timeout(self, timeout: Duration) -> JoinOutcome<T> {
+        if !self.finished.wait(timeout) {
+            return JoinOutcome::TimedOutStillRunning(self);
+        }
+        match self.handle.join() {
+            Ok(value) => JoinOutcome::Completed(value),
+            Err(payload) => JoinOutcome::Panicked(payload),
+        }
+    }
+
+    /// Wait for the worker to finish, like [`JoinHandle::join()`].
+    pub fn join(self) -> thread::Result<T> {
+        self.handle.join()
+    }
+
+    /// Let the worker keep running in the background.
+    ///
+    /// Its stop stays cancelled if it was cancelled.
+    pub fn detach(self) {}
+
+    /// Whether the worker has finished (returned or panicked).
+    #[inline]
+    pub fn is_finished(&self) -> bool {
+        self.handle.is_finished()
+    }
+
+    /// Cancel the worker without waiting for it.
+    #[inline]
+    pub fn cancel(&self) {
+        self.stopper.cancel();
+    }
+
+    /// The worker's stop.
+    #[inline]
+    pub fn stopper(&self) -> &Stopper {
+        &self.stopper
+    }
+
+    /// The worker thread.
+    #[inline]
+    pub fn thread(&self) -> &Thread {
+        self.handle.thread()
+    }
+
+    /// Split into the underlying `JoinHandle` and `Stopper`.
+    pub fn into_parts(self) -> (JoinHandle<T>, Stopper) {
+        (self.handle, self.stopper)
+    }
+}
+
+impl<T> core::fmt::Debug for StoppableJoinHandle<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("StoppableJoinHandle")
+            .field("thread", self.handle.thread())
+            .field("stopper", &self.stopper)
+            .field("finished", &self.handle.is_finished())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Stop;
+
+    const LONG: Duration = Duration::from_secs(10);
+
+    #[test]
+    fn cooperative_worker_completes() {
+        let worker = StoppableJoinHandle::spawn(Stopper::new(), |stop| {
+            while !stop.should_stop() {
+                thread::yield_now();
+            }
+            7
+        });
+        let outcome = worker.stop_and_join(LONG);
+        assert!(outcome.is_completed());
+        assert_eq!(outcome.resume_panic().unwrap(), 7);
+    }
+
+    #[test]
+    fn straggler_times_out_and_can_be_rejoined() {
+        let release = Stopper::new();
+        let worker = {
+            let release = release.clone();
+            StoppableJoinHandle::spawn(Stopper::new(), move |_stop| {
+                while !release.should_stop() {
+                    thread::yield_now();
+                }
+                "late"
+            })
+        };
+
+        let straggler = match worker.stop_and_join(Duration::from_millis(5)) {
+            JoinOutcome::TimedOutStillRunning(handle) => handle,
+            other => panic!("expected timeout, got {other:?}"),
+        };
+        assert!(straggler.stopper().is_cancelled());
+        assert!(format!("{straggler:?}").contains("finished: false"));
+
+        release.cancel();
+        assert_eq!(straggler.join_timeout(LONG).resume_panic().unwrap(), "late");
+    }
+
+    #[test]
+    fn panic_is_reported() {
+        let worker = StoppableJoinHandle::spawn(Stopper::new(), |_stop| -> u8 {
+            panic!("worker failed");
+        });
+        match worker.stop_and_join(LONG) {
+            JoinOutcome::Panicked(payload) => {
+                assert_eq!(payload.downcast_ref::<&str>(), Some(&"worker failed"));
+            }
+            other => panic!("expected panic, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn converts_to_outcome() {
+        let done = StoppableJoinHandle::spawn(Stopper::new(), |_stop| 3u8);
+        assert_eq!(
+            Outcome::from(done.stop_and_join(LONG)),
+            Outcome::Completed(3)
+        );
+
+        let release = Stopper::new();
+        let straggler = {
+            let release = release.clone();
+            StoppableJoinHandle::spawn(Stopper::new(), move |_stop| {
+                while !release.should_stop() {
+                    thread::yield_now();
+                }
+                0u8
+            })
+        };
+        let outcome = Outcome::from(straggler.stop_and_join(Duration::from_millis(1)));
+        assert_eq!(outcome.reason(), Some(StopReason::TimedOut));
+        release.cancel();
+
+        let panicked = StoppableJoinHandle::spawn(Stopper::new(), |_stop| -> u8 {
+            panic!("worker failed");
+        });
+        assert!(Outcome::from(panicked.stop_and_join(LONG)).is_panicked());
+    }
+
+    #[test]
+    #[should_panic(expected = "worker failed")]
+    fn resume_panic_propagates() {
+        let worker = StoppableJoinHandle::spawn(Stopper::new(), |_stop| -> u8 {
+            panic!("worker failed");
+        });
+        let _ = worker.stop_and_join(LONG).resume_panic();
+    }
+
+    #[test]
+    fn join_timeout_does_not_cancel() {
+        let worker = StoppableJoinHandle::spawn(Stopper::new(), |stop| stop.is_cancelled());
+        assert!(!worker.join_timeout(LONG).resume_panic().unwrap());
+    }
+
+    #[test]
+    fn parts_and_detach() {
+        let worker = StoppableJoinHandle::spawn(Stopper::new(), |stop| {
+            while !stop.should_stop() {
+                thread::yield_now();
+            }
+        });
+        worker.cancel();
+        let (handle, stopper) = worker.into_parts();
+        assert!(stopper.is_cancelled());
+        handle.join().unwrap();
+
+        let worker = StoppableJoinHandle::spawn(Stopper::new(), |_stop| ());
+        assert!(worker.thread().name().is_none());
+        worker.detach();
+    }
+
+    #[test]
+    fn is_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<StoppableJoinHandle<u32>>();
+    }
+}
@@ -0,0 +1,189 @@
+//! Labels identifying which node of a stop tree fired.
+//!
+//! [`StopReason`] is a plain `Copy` code, so it cannot say *who* cancelled.
+//! A [`Stopper`](crate::Stopper) or [`ChildStopper`](crate::ChildStopper)
+//! can instead be created with a [`NodeLabel`] — a `u64` id or a
+//! `&'static str` name — and a tree node reports the label of the node
+//! whose cancellation it inherited via
+//! [`cancelled_by()`](crate::ChildStopper::cancelled_by), or together with
+//! the reason via [`check_labeled()`](crate::ChildStopper::check_labeled).
+//!
+//! # Example
+//!
+//! ```rust
+//! # #[cfg(feature = "alloc")]
+//! # fn main() {
+//! use almost_enough::{ChildStopper, NodeLabel, StopReason};
+//!
+//! let request = ChildStopper::with_label("request");
+//! let decode = request.child_with_label("decode");
+//! let resize = decode.child_with_label(7u64);
+//!
+//! decode.cancel();
+//! assert_eq!(resize.cancelled_by(), Some(NodeLabel::Name("decode")));
+//!
+//! let err = resize.check_labeled().unwrap_err();
+//! assert_eq!(err.reason, StopReason::Cancelled);
+//! assert_eq!(err.to_string(), "operation cancelled (by decode)");
+//! # }
+//! # #[cfg(not(feature = "alloc"))]
+//! # fn main() {}
+//! ```
+
+use crate::StopReason;
+
+/// Identifies a cancellation node in diagnostics.
+///
+/// Converts from `u64` and `&'static str`, so constructors taking
+/// `impl Into<NodeLabel>` accept either directly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum NodeLabel {
+    /// A numeric id, e.g. a request or job number.
+    Id(u64),
+    /// A static name, e.g. a pipeline stage.
+    Name(&'static str),
+}
+
+impl From<u64> for NodeLabel {
+    #[inline]
+    fn from(id: u64) -> Self {
+        Self::Id(id)
+    }
+}
+
+impl From<&'static str> for NodeLabel {
+    #[inline]
+    fn from(name: &'static str) -> Self {
+        Self::Name(name)
+    }
+}
+
+impl core::fmt::Display for NodeLabel {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Id(id) => write!(f, "#{id}"),
+            Self::Name(name) => f.write_str(name),
+        }
+    }
+}
+
+/// A [`StopReason`] together with the label of the node that fired.
+///
+/// Returned by [`ChildStopper::check_labeled()`](crate::ChildStopper::check_labeled).
+/// Converts into a plain `StopReason`, so `?` works in functions returning
+/// `Result<_, StopReason>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LabeledReason {
+    /// Why the operation stopped.
+    pub reason: StopReason,
+    /// The label of the node that fired, or `None` if it was unlabeled.
+    pub label: Option<NodeLabel>,
+}
+
+impl From<LabeledReason> for StopReason {
+    #[inline]
+    fn from(labeled: LabeledReason) -> Self {
+        labeled.reason
+    }
+}
+
+impl core::fmt::Display for LabeledReason {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.label {
+            Some(label) => write!(f, "{} (by {label})", self.reason),
+            None => write!(f, "{}", self.reason),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for LabeledReason {}
+
+/// Trace and span IDs of the request that caused a cancellation.
+///
+/// When a cancel is triggered by a remote request abort, attach the
+/// request's W3C/OpenTelemetry IDs with
+/// [`Stopper::cancel_with_trace()`](crate::Stopper::cancel_with_trace) or
+/// [`ChildStopper::cancel_with_trace()`](crate::ChildStopper::cancel_with_trace)
+/// so local cancellation events can be correlated with the remote trace.
+/// The IDs are carried as raw bytes; no tracing backend is assumed.
+///
+/// Displays as lowercase hex, `"<trace_id>-<span_id>"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TraceContext {
+    /// The 16-byte trace id.
+    pub trace_id: [u8; 16],
+    /// The 8-byte span id.
+    pub span_id: [u8; 8],
+}
+
+impl TraceContext {
+    /// Create a trace context from its IDs.
+    #[inline]
+    pub const fn new(trace_id: [u8; 16], span_id: [u8; 8]) -> Self {
+        Self { trace_id, span_id }
+    }
+}
+
+/// Formats bytes as lowercase hex.
+pub(crate) struct Hex<'a>(pub(crate) &'a [u8]);
+
+impl core::fmt::Display for Hex<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        for byte in self.0 {
+            write!(f, "{byte:02x}")?;
+        }
+        Ok(())
+    }
+}
+
+impl core::fmt::Display for TraceContext {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}-{}", Hex(&self.trace_id), Hex(&self.span_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conversions() {
+        assert_eq!(NodeLabel::from(3), NodeLabel::Id(3));
+        assert_eq!(NodeLabel::from("io"), NodeLabel::Name("io"));
+
+        let labeled = LabeledReason {
+            reason: StopReason::TimedOut,
+            label: Some(NodeLabel::Id(3)),
+        };
+        assert_eq!(StopReason::from(labeled), StopReason::TimedOut);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn display() {
+        use alloc::string::ToString;
+
+        assert_eq!(NodeLabel::Id(42).to_string(), "#42");
+        let labeled = LabeledReason {
+            reason: StopReason::Cancelled,
+            label: Some(NodeLabel::Id(42)),
+        };
+        assert_eq!(labeled.to_string(), "operation cancelled (by #42)");
+        let unlabeled = LabeledReason {
+            reason: StopReason::Cancelled,
+            label: None,
+        };
+        assert_eq!(unlabeled.to_string(), "operation cancelled");
+
+        let mut trace_id = [0; 16];
+        trace_id[0] = 0x4b;
+        trace_id[15] = 0x0e;
+        let trace = TraceContext::new(trace_id, [0xa0, 0, 0, 0, 0, 0, 0, 0x01]);
+        assert_eq!(
+            trace.to_string(),
+            "4b00000000000000000000000000000e-a000000000000001"
+        );
+    }
+}
@@ -70,14 +70,71 @@
 //! | [`Unstoppable`] | core | Zero-cost "never stop" |
 //! | [`StopSource`] / [`StopRef`] | core | Stack-based, borrowed, zero-alloc |
 //! | [`FnStop`] | core | Wrap any closure |
+//! | [`CatchingFnStop`] | std | `FnStop` that turns a panicking closure into `StopReason::Failed` |
+//! | [`ReasonFnStop`] | core | Closure that reports its own `StopReason` |
+//! | [`AtomicBoolStop`] | core | Existing `AtomicBool` flag (borrowed or `Arc`) with a chosen load ordering |
 //! | [`OrStop`] | core | Combine multiple stops |
+//! | [`BudgetStop`] | core | Stop after N checks or N units of work |
+//! | [`WithClockTimeout`] | core | Deadline from a caller-supplied tick [`Clock`] (`no_std`) |
+//! | [`Throttled`] | core | Consult an expensive stop only every N checks |
+//! | [`GatedStop`] | core | Arm/disarm at runtime; `hold()` ignores cancellation for a scope |
+//! | [`SplitStop`] | core | `.split()` into two hedged branches; the first to finish cancels the other |
+//! | [`StopIterExt`] | core | `.stop_every(stop, n)` iterator adapters |
+//! | [`race_steps`] | core | Resumable stepped loop: check between steps, return partial state on stop |
+//! | [`Outcome`] | core | `Completed` / `Stopped { reason, partial }` / `Panicked`, converted from every runner's result |
+//! | [`TrackedStop`] | core | Where was this stop last checked? (`check-location` feature) |
+//! | [`ReasonCache`] | core | Keep the reason of the first failed check for `should_stop()` callers |
+//! | [`NodeLabel`] | core | Tag a `Stopper`/`ChildStopper` so a tree reports which node fired |
+//! | [`TraceContext`] | core | Trace/span IDs carried by `cancel_with_trace()` for correlation |
+//! | [`Behavior`] | core | Versioned switches for contentious wrapper semantics |
 //! | [`Stopper`] | alloc | **Default choice** - Arc-based, clone to share |
 //! | [`SyncStopper`] | alloc | Like Stopper with Acquire/Release ordering |
 //! | [`ChildStopper`] | alloc | Hierarchical parent-child cancellation |
+//! | [`LinkedStopper`] | alloc | Owns N sources, stops when any stops |
+//! | [`WeakStopper`] | alloc | `Stopper::downgrade()`: observe without keeping it alive; a dropped stopper reads as cancelled |
+//! | [`GenerationStopper`] | alloc | Reusable: `renew()` starts a new generation; old `GenerationToken`s stay stopped |
+//! | [`StopCell`] | alloc | Cached value recomputed once its `Stopper`'s epoch changes |
 //! | [`StopToken`] | alloc | **Type-erased dynamic dispatch** - Arc-based, `Clone` |
 //! | [`BoxedStop`] | alloc | Type-erased (prefer `StopToken`) |
+//! | [`NEVER`] / [`CANCELLED`] | core | `&'static dyn Stop` singletons for defaults and tests |
+//! | [`StopArg`] | alloc | One parameter type for borrowed or owned stops |
 //! | [`WithTimeout`] | std | Add deadline to any `Stop` |
 //! | [`DebouncedTimeout`] | std | Like `WithTimeout`, skips most clock reads |
+//! | [`LatchedTimeout`] | std | Like `WithTimeout`, stays `TimedOut` once expired; `on_expire` callbacks |
+//! | [`Checkpoint`] | std | `cp.tick(&stop)?` in hot loops; checks every N ticks, adapting N to ~1ms between checks |
+//! | [`CheckAt`] | std | `check_at(now)` / `should_stop_at(now)`: reuse a timestamp the loop already took |
+//! | [`WithSharedDeadline`] | std | One `SharedDeadlineHandle` behind many stops; tighten them all at once |
+//! | [`WithSystemDeadline`] | std | Stop at a wall-clock time (suspend/clock-change aware) |
+//! | [`time::sleep_until_cancelled`] / [`time::every`] | std | Sleep or tick, waking as soon as a stop fires |
+//! | [`time::StopTicker`] | std | Drift-free `wait_tick()` for maintenance loops; `Err(reason)` the moment the stop fires |
+//! | [`time::StopAwareLimiter`] | std | Token-bucket rate limiter; `acquire(&stop)` returns on cancel or deadline and closes the bucket |
+//! | [`time::FencedStop`] | std | `wait_fence_or_stop(interval)`: poll a GPU/accelerator fence with backoff, cancellable |
+//! | [`PropagatingStopper`] | std | Tree cancellation with O(1) `check()` at any depth |
+//! | [`PausableStopper`] | std | Stopper that can also pause workers at `check()` |
+//! | [`CriticalStop`] | std | Defer cancellation while a `CriticalSection` guard is held (capped) |
+//! | [`MeteredStop`] | std | Count checks and measure cancel-to-observe latency |
+//! | [`OwnershipReport`] | std | Strong/weak counts behind a `Stopper`; clone backtraces with `debug-history` |
+//! | [`StopSnapshot`] | std | Checkpoint a stop's reason and remaining time; restore with `Stopper::from_snapshot()` |
+//! | [`StopScope`] | std | Scoped threads sharing a stop; first failure cancels all |
+//! | [`StoppableJoinHandle`] | std | Worker thread + stop; cancel and join with a timeout |
+//! | [`StopListener`] | std | `stop.listen()`: one-shot registration to `wait()`, `try_wait()` or `.await`, resolving exactly once |
+//! | [`WorkerGuard`] | std | `stop.worker()?` registers a worker; `Stopper::cancel_and_wait()` blocks until all guards drop |
+//! | [`ProcessStop`] | std | Kills a child process (or, on unix, its process group) when the stop fires |
+//! | [`ShutdownCoordinator`] | std | Cancel registered workers, wait for their acks, report stragglers |
+//! | [`ChannelStop`] | std | Stop on a control-channel message or sender disconnect |
+//! | [`SlowDropDetector`] | std | Callback when a scope outlives its cancellation too long |
+//! | [`fs::disk_usage_stoppable`] | std | Directory-tree size with an entry budget; partial totals on stop |
+//! | [`io::flush_with_deadline`] | std | Bounded best-effort flush on shutdown, reports dropped bytes |
+//! | [`io::StopReader`] / [`io::StopWriter`] | std | `Read`/`Write` adapters that fail once the stop trips |
+//! | [`net::resolve_with_stop`] | std | DNS lookup that returns when the stop fires, abandoning the helper |
+//! | `time::parse_duration` | config | `"30s"`, `"1m30s"`, `"250ms"` from config; `WithTimeout::from_str_duration()` |
+//! | `StopRegistry` | global | Stoppers by string ID (`"jobs/export/42"`) that survive plugin hot-reloads; cancel in-flight requests by ID |
+//! | `TracedStop` | tracing | `tracing` events on cancel and on first observed cancellation |
+//! | `test_util::StopTracker` | test-util | Assert every stop a test created was observed, completed or disarmed |
+//! | `PerformanceClock` | wasm | `performance.now()` clock for deadlines on `wasm32-unknown-unknown` |
+//! | `SignalStop` | signal | Cancel on SIGINT/SIGTERM/Ctrl-C |
+//! | `cli::cli_stop` | signal | Ctrl-C + `--timeout` + stderr stop message for CLI tools |
+//! | `SystemdService` | systemd | sd_notify readiness, watchdog and shutdown (unix) |
 //!
 //! ## StopExt Extension Trait
 //!
@@ -189,10 +246,43 @@
 //! # fn main() {}
 //! ```
 //!
+//! ## Logging
+//!
+//! With the `log` feature, cancellation is reported through the `log`
+//! facade, for binaries that don't use `tracing`. The messages match
+//! [`TracedStop`](https://docs.rs/almost-enough/latest/almost_enough/struct.TracedStop.html)'s,
+//! with the stop's [`NodeLabel`] (`-` if unlabeled) and the reason in the
+//! text:
+//!
+//! | Message | Level | Emitted when |
+//! |---------|-------|--------------|
+//! | `cancel requested` | `DEBUG` | A `cancel()` stops a `Stopper` or `ChildStopper` |
+//! | `deadline expired` | `DEBUG` | The timer thread fires (`cancel_after()`) or a `LatchedTimeout` latches |
+//! | `cancelled by guard` | `DEBUG`, `WARN` while panicking | An armed `CancelGuard` is dropped |
+//!
+//! Each transition is logged once: cancelling or checking a stop that has
+//! already stopped emits nothing. A plain `WithTimeout` keeps no state, so
+//! its expiry is not logged. The feature enables `alloc`, as every
+//! logged type needs it, but works without `std`.
+//!
 //! ## Feature Flags
 //!
 //! - **`std`** (default) - Full functionality including timeouts
 //! - **`alloc`** - Arc-based types, `into_boxed()`, `child()`, `StopDropRoll`
+//! - **`signal`** - `SignalStop` for SIGINT/SIGTERM/Ctrl-C driven cancellation, and the `cli` module
+//! - **`systemd`** - `SystemdService`: sd_notify readiness, watchdog and shutdown (unix)
+//! - **`crossbeam`** - `ChannelStop::crossbeam` for `crossbeam_channel` receivers
+//! - **`check-location`** - `TrackedStop` records where it was last checked
+//! - **`timer`** - `Stopper::cancel_after()` and `Stopper::with_timeout_active()`: deadlines that actively cancel, served by one shared timer thread
+//! - **`config`** - `time::parse_duration()` and `WithTimeout::from_str_duration()` for timeouts written as strings (`"30s"`, `"1m30s"`, `"250ms"`)
+//! - **`debug-history`** - `OwnershipReport` lists the creation backtrace of every live `Stopper` clone (slow; for debugging shutdown hangs)
+//! - **`global`** - `global()`: a lazily created process-wide `Stopper`, and `set_global_cancelled()` for shutdown handlers; `StopRegistry` for stoppers looked up by string ID
+//! - **`defmt`** - `defmt::Format` for `StopReason` (enables `enough/defmt`)
+//! - **`serde`** - `Serialize`/`Deserialize` for `StopSnapshot`
+//! - **`tracing`** - `TracedStop`/`TracedStopper`: `tracing` events when a stop is cancelled and when it is first observed
+//! - **`log`** - `log` events on cancel, deadline expiry and guard-triggered cancels (see [Logging](#logging))
+//! - **`test-util`** - `test_util::StopTracker`: cancellation coverage checks for tests
+//! - **`wasm`** - `PerformanceClock` and `with_performance_timeout()`: deadlines via `performance.now()` on `wasm32-unknown-unknown`, where `Instant` panics
 //! - **None** - Core trait and stack-based types only
 
 #![cfg_attr(not(feature = "std"), no_std)]
@@ -229,48 +319,237 @@ pub use enough::{Never, Stop, StopReason, Unstoppable};
 /// # #[cfg(not(feature = "alloc"))]
 /// # fn main() {}
 /// ```
-pub trait CloneStop: Stop + Clone + 'static {}
+///
+/// `CloneStop` itself is not object-safe (`Clone` isn't). To store a
+/// cloneable stop behind dynamic dispatch, box it with
+/// [`clone_boxed()`](Self::clone_boxed): the resulting [`BoxedStop`] is
+/// `Clone` and clones the stop itself.
+pub trait CloneStop: Stop + Clone + 'static {
+    /// Clone this stop into a [`BoxedStop`] whose clones clone it again.
+    ///
+    /// Same as [`BoxedStop::cloneable(self.clone())`](BoxedStop::cloneable).
+    ///
+    /// ```rust
+    /// use almost_enough::{BoxedStop, CloneStop, Stop, Stopper};
+    ///
+    /// let stopper = Stopper::new();
+    /// let stops: Vec<BoxedStop> = vec![stopper.clone_boxed(); 4];
+    /// stopper.cancel();
+    /// assert!(stops.iter().all(|stop| stop.should_stop()));
+    /// ```
+    #[cfg(feature = "alloc")]
+    #[inline]
+    fn clone_boxed(&self) -> BoxedStop {
+        BoxedStop::cloneable(self.clone())
+    }
+}
 
 /// Blanket implementation: any `Stop + Clone + 'static` is `CloneStop`.
 impl<T: Stop + Clone + 'static> CloneStop for T {}
 
+/// A stop that never stops, as a `'static` trait object.
+///
+/// For defaults of `&dyn Stop` fields and parameters.
+///
+/// ```rust
+/// use almost_enough::{NEVER, Stop};
+///
+/// fn run(stop: Option<&dyn Stop>) -> bool {
+///     stop.unwrap_or(NEVER).should_stop()
+/// }
+///
+/// assert!(!run(None));
+/// ```
+pub static NEVER: &dyn Stop = &Unstoppable;
+
+/// A stop that is already cancelled, as a `'static` trait object.
+///
+/// Reports [`StopReason::Cancelled`]; handy in tests that need an
+/// already-stopped token in one expression.
+///
+/// ```rust
+/// use almost_enough::{CANCELLED, Stop, StopReason};
+///
+/// assert_eq!(CANCELLED.check(), Err(StopReason::Cancelled));
+/// ```
+pub static CANCELLED: &dyn Stop = &ALREADY_CANCELLED;
+
+static ALREADY_CANCELLED: StopSource = StopSource::cancelled();
+
 // Core modules (no_std, no alloc)
+mod atomic;
+mod behavior;
+mod budget;
+mod cache;
+mod clock;
 mod func;
+mod gate;
+mod iter;
+mod label;
 mod or;
+mod outcome;
+mod reason;
 mod source;
+mod split;
+mod steps;
+mod throttle;
+mod track;
 
+pub use atomic::AtomicBoolStop;
+pub use behavior::{Behavior, TieBreak};
+pub use budget::BudgetStop;
+pub use cache::ReasonCache;
+pub use clock::{Clock, WithClockTimeout};
+#[cfg(feature = "std")]
+pub use func::CatchingFnStop;
 pub use func::FnStop;
+pub use func::ReasonFnStop;
+pub use gate::{GateHold, GatedStop};
+pub use iter::{StopEvery, StopIterExt, UntilStopped};
+pub use label::{LabeledReason, NodeLabel, TraceContext};
 pub use or::OrStop;
+pub use outcome::Outcome;
 pub use source::{StopRef, StopSource};
+pub use split::{Branch, Side, SplitStop};
+#[cfg(feature = "std")]
+pub use steps::race_steps_with_timeout;
+pub use steps::{StepOutcome, StepResult, race_steps};
+pub use throttle::Throttled;
+pub use track::TrackedStop;
 
 // Alloc-dependent modules
 #[cfg(feature = "alloc")]
+mod arg;
+#[cfg(feature = "alloc")]
 mod boxed;
 #[cfg(feature = "alloc")]
+mod cell;
+#[cfg(feature = "alloc")]
+mod generation;
+#[cfg(feature = "alloc")]
+mod linked;
+#[cfg(feature = "alloc")]
+mod state;
+#[cfg(feature = "alloc")]
 mod stopper;
 #[cfg(feature = "alloc")]
 mod sync_stopper;
 #[cfg(feature = "alloc")]
 mod tree;
+#[cfg(feature = "alloc")]
+mod weak;
 
+#[cfg(feature = "alloc")]
+pub use arg::StopArg;
 #[cfg(feature = "alloc")]
 pub use boxed::BoxedStop;
 #[cfg(feature = "alloc")]
+pub use cell::StopCell;
+#[cfg(feature = "alloc")]
+pub use generation::{GenerationStopper, GenerationToken};
+#[cfg(feature = "alloc")]
+pub use linked::LinkedStopper;
+#[cfg(feature = "alloc")]
 mod stop_token;
 #[cfg(feature = "alloc")]
 pub use stop_token::StopToken;
 #[cfg(feature = "alloc")]
 pub use stopper::Stopper;
+#[cfg(feature = "std")]
+pub use stopper::{StopListener, WaitCancelled};
 #[cfg(feature = "alloc")]
 pub use sync_stopper::SyncStopper;
 #[cfg(feature = "alloc")]
-pub use tree::ChildStopper;
+pub use tree::{AlreadyStopped, CancelOrigin, ChildStopper, StoppedParentPolicy};
+#[cfg(feature = "std")]
+pub use tree::{ChildLimitError, ChildLimitPolicy};
+#[cfg(feature = "alloc")]
+pub use weak::WeakStopper;
 
 // Std-dependent modules
 #[cfg(feature = "std")]
+pub mod fs;
+#[cfg(feature = "std")]
+pub mod io;
+#[cfg(feature = "std")]
+pub mod net;
+#[cfg(feature = "std")]
 pub mod time;
 #[cfg(feature = "std")]
-pub use time::{DebouncedTimeout, DebouncedTimeoutExt, TimeoutExt, WithTimeout};
+pub use time::{
+    CheckAt, DebouncedTimeout, DebouncedTimeoutExt, LatchedTimeout, SharedDeadlineHandle,
+    TimeoutExt, WithSharedDeadline, WithSystemDeadline, WithTimeout,
+};
+
+// Cancellation coverage checks for tests
+#[cfg(feature = "test-util")]
+pub mod test_util;
+
+// Process-wide fallback stop
+#[cfg(feature = "global")]
+pub mod global;
+#[cfg(feature = "global")]
+pub use global::{global, set_global_cancelled};
+#[cfg(feature = "global")]
+pub mod registry;
+#[cfg(feature = "global")]
+pub use registry::StopRegistry;
+
+// Active deadlines on a shared timer thread
+#[cfg(feature = "timer")]
+mod timer;
+
+// Adaptive check interval for hot loops
+#[cfg(feature = "std")]
+mod checkpoint;
+#[cfg(feature = "std")]
+pub use checkpoint::Checkpoint;
+
+// Clone ownership diagnostics
+#[cfg(feature = "std")]
+mod ownership;
+#[cfg(feature = "std")]
+pub use ownership::OwnershipReport;
+
+// Checkpointable stop state
+#[cfg(feature = "std")]
+mod snapshot;
+#[cfg(feature = "std")]
+pub use snapshot::{RestoredStop, StopSnapshot};
+
+// tracing events for cancellation
+#[cfg(feature = "tracing")]
+mod traced;
+#[cfg(feature = "tracing")]
+pub use traced::{TracedStop, TracedStopper};
+
+// log events for cancellation
+#[cfg(feature = "log")]
+mod logging;
+
+// Browser clock for wasm32-unknown-unknown
+#[cfg(feature = "wasm")]
+mod wasm;
+#[cfg(feature = "wasm")]
+pub use wasm::{PerformanceClock, PerformanceTimeoutExt};
+
+// Killing child processes on stop
+#[cfg(feature = "std")]
+mod process;
+#[cfg(feature = "std")]
+pub use process::ProcessStop;
+
+// OS signal integration
+#[cfg(feature = "signal")]
+pub mod cli;
+#[cfg(feature = "signal")]
+mod signal;
+#[cfg(feature = "signal")]
+pub use signal::{SignalInstallError, SignalStop};
+#[cfg(all(feature = "systemd", unix))]
+mod systemd;
+#[cfg(all(feature = "systemd", unix))]
+pub use systemd::SystemdService;
 
 // Cancel guard module
 #[cfg(feature = "alloc")]
@@ -278,6 +557,66 @@ mod guard;
 #[cfg(feature = "alloc")]
 pub use guard::{CancelGuard, Cancellable, StopDropRoll};
 
+// Pausable cancellation
+#[cfg(feature = "std")]
+mod pause;
+#[cfg(feature = "std")]
+pub use pause::PausableStopper;
+
+// Control-channel cancellation
+#[cfg(feature = "std")]
+mod channel;
+#[cfg(feature = "std")]
+pub use channel::ChannelStop;
+
+// Cancellation-compliance watchdog
+#[cfg(feature = "std")]
+mod slow_drop;
+#[cfg(feature = "std")]
+pub use slow_drop::{SlowDrop, SlowDropDetector};
+
+// Eagerly propagated tree cancellation
+#[cfg(feature = "std")]
+mod propagating;
+#[cfg(feature = "std")]
+pub use propagating::PropagatingStopper;
+
+// Deferred cancellation around critical sections
+#[cfg(feature = "std")]
+mod critical;
+#[cfg(feature = "std")]
+pub use critical::{CriticalSection, CriticalStop};
+
+// Check counting and cancellation latency
+#[cfg(feature = "std")]
+mod metered;
+#[cfg(feature = "std")]
+pub use metered::MeteredStop;
+
+// Structured concurrency
+#[cfg(feature = "std")]
+mod scope;
+#[cfg(feature = "std")]
+pub use scope::{StopScope, stop_scope};
+
+// Worker threads joined with a deadline
+#[cfg(feature = "std")]
+mod join;
+#[cfg(feature = "std")]
+pub use join::{JoinOutcome, StoppableJoinHandle};
+
+// Coordinated shutdown of registered workers
+#[cfg(feature = "std")]
+mod shutdown;
+#[cfg(feature = "std")]
+pub use shutdown::{ShutdownCoordinator, ShutdownReport, WorkerHandle};
+
+// Stopper::cancel_and_wait() and the guards it waits for
+#[cfg(feature = "std")]
+mod workers;
+#[cfg(feature = "std")]
+pub use workers::WorkerGuard;
+
 /// Extension trait providing ergonomic combinators for [`Stop`] implementations.
 ///
 /// This trait is automatically implemented for all `Stop + Sized` types.
@@ -298,6 +637,41 @@ pub use guard::{CancelGuard, Cancellable, StopDropRoll};
 /// source_b.cancel();
 /// assert!(combined.should_stop());
 /// ```
+///
+/// # Trait objects
+///
+/// `dyn Stop` is unsized, but `&dyn Stop`, `&mut dyn Stop`, `Box<dyn Stop>`
+/// and `Arc<dyn Stop>` all implement `Stop`, so the combinators are
+/// available on type-erased stops as-is; the wrapper holds the reference
+/// or pointer:
+///
+/// ```rust
+/// # #[cfg(feature = "std")]
+/// # fn main() {
+/// use almost_enough::{Stop, StopExt, StopSource, TimeoutExt};
+/// use std::time::Duration;
+///
+/// fn with_deadline(stop: &dyn Stop, local: &StopSource) -> bool {
+///     let combined = stop
+///         .or(local.as_ref())
+///         .throttle_checks(8)
+///         .with_timeout(Duration::from_secs(30));
+///     combined.should_stop()
+/// }
+///
+/// let outer = StopSource::new();
+/// let local = StopSource::new();
+/// local.cancel();
+/// assert!(with_deadline(&outer.as_ref(), &local));
+/// # }
+/// # #[cfg(not(feature = "std"))]
+/// # fn main() {}
+/// ```
+///
+/// Methods that need an owned `'static` stop, such as
+/// [`into_boxed()`](Self::into_boxed) and [`child()`](Self::child), need
+/// `Box<dyn Stop + Send + Sync>` or `Arc<dyn Stop + Send + Sync>` rather
+/// than a borrow.
 pub trait StopExt: Stop + Sized {
     /// Combine this stop with another, stopping if either stops.
     ///
@@ -340,6 +714,209 @@ pub trait StopExt: Stop + Sized {
         OrStop::new(self, other)
     }
 
+    /// Consult this stop only on every `interval`-th check.
+    ///
+    /// Returns a [`Throttled`] wrapper whose checks in between cost one
+    /// atomic load and a counter increment — useful for stops such as
+    /// `WithTimeout` whose `check()` reads the clock. Cancellation is
+    /// noticed up to `interval - 1` checks late.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use almost_enough::{StopSource, Stop, StopExt};
+    ///
+    /// let source = StopSource::new();
+    /// let stop = source.as_ref().throttle_checks(64);
+    /// assert!(!stop.should_stop());
+    /// ```
+    #[inline]
+    fn throttle_checks(self, interval: u32) -> Throttled<Self> {
+        Throttled::new(self, interval)
+    }
+
+    /// Remember where this stop was last checked.
+    ///
+    /// Returns a [`TrackedStop`]; with the `check-location` feature its
+    /// [`last_check_location()`](TrackedStop::last_check_location) reports
+    /// the source location of the most recent check, which points at the
+    /// loop a hung job is stuck in (or the last one that still checks).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use almost_enough::{StopSource, Stop, StopExt};
+    ///
+    /// let source = StopSource::new();
+    /// let stop = source.as_ref().track_checks();
+    /// let _ = stop.check();
+    /// # #[cfg(feature = "check-location")]
+    /// assert!(stop.last_check_location().is_some());
+    /// ```
+    #[inline]
+    fn track_checks(self) -> TrackedStop<Self> {
+        TrackedStop::new(self)
+    }
+
+    /// Remember the reason of the first failed check.
+    ///
+    /// Returns a [`ReasonCache`] whose
+    /// [`last_reason()`](ReasonCache::last_reason) tells code that
+    /// branched on `should_stop()` why it stopped, without evaluating the
+    /// stop again.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use almost_enough::{StopSource, Stop, StopExt, StopReason};
+    ///
+    /// let source = StopSource::cancelled();
+    /// let stop = source.as_ref().cache_reason();
+    /// assert!(stop.should_stop());
+    /// assert_eq!(stop.last_reason(), Some(StopReason::Cancelled));
+    /// ```
+    #[inline]
+    fn cache_reason(self) -> ReasonCache<Self> {
+        ReasonCache::new(self)
+    }
+
+    /// Make this stop switchable, e.g. to ignore it while committing.
+    ///
+    /// Returns an armed [`GatedStop`]; while a [`GateHold`] from
+    /// [`hold()`](GatedStop::hold) is alive, or after
+    /// [`disarm()`](GatedStop::disarm), this stop is ignored.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use almost_enough::{Stop, StopExt, StopSource};
+    ///
+    /// let source = StopSource::cancelled();
+    /// let stop = source.as_ref().gate();
+    /// let hold = stop.hold();
+    /// assert!(!stop.should_stop());
+    /// drop(hold);
+    /// assert!(stop.should_stop());
+    /// ```
+    #[inline]
+    fn gate(self) -> GatedStop<Self> {
+        GatedStop::new(self)
+    }
+
+    /// Split into two branches for hedged parallel work.
+    ///
+    /// Returns a [`SplitStop`] whose [`branches()`](SplitStop::branches)
+    /// both stop when this stop does; the branch that calls
+    /// [`finish()`](Branch::finish) first also cancels its sibling, without
+    /// touching this stop.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use almost_enough::{Side, Stop, StopExt, StopSource};
+    ///
+    /// let source = StopSource::new();
+    /// let split = source.as_ref().split();
+    /// let (left, right) = split.branches();
+    ///
+    /// assert!(right.finish());
+    /// assert!(left.should_stop());
+    /// assert_eq!(split.merge(), Ok(Some(Side::Right)));
+    /// ```
+    #[inline]
+    fn split(self) -> SplitStop<Self> {
+        SplitStop::new(self)
+    }
+
+    /// Let [`CriticalSection`]s defer this stop for up to `max_deferral`.
+    ///
+    /// Returns a [`CriticalStop`]; while a section from
+    /// [`enter()`](CriticalStop::enter) is held, a cancellation is
+    /// recorded but not reported, so a writer can finish a step that must
+    /// not be torn.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use almost_enough::{Stop, StopExt, Stopper};
+    /// use std::time::Duration;
+    ///
+    /// let stopper = Stopper::new();
+    /// let stop = stopper.clone().with_critical_sections(Duration::from_secs(1));
+    /// let section = stop.enter().unwrap();
+    /// stopper.cancel();
+    /// assert!(!stop.should_stop());
+    /// drop(section);
+    /// assert!(stop.should_stop());
+    /// ```
+    #[cfg(feature = "std")]
+    #[inline]
+    fn with_critical_sections(self, max_deferral: std::time::Duration) -> CriticalStop<Self> {
+        CriticalStop::new(self, max_deferral)
+    }
+
+    /// Count checks and time how long cancellation takes to be observed.
+    ///
+    /// Returns a [`MeteredStop`]; see its docs for how the cancellation
+    /// time is recorded.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use almost_enough::{Stop, StopExt, Stopper};
+    ///
+    /// let stop = Stopper::new().metered();
+    /// stop.cancel();
+    /// assert!(stop.should_stop());
+    /// assert_eq!(stop.checks(), 1);
+    /// assert!(stop.time_to_observe().is_some());
+    /// ```
+    #[cfg(feature = "std")]
+    #[inline]
+    fn metered(self) -> MeteredStop<Self> {
+        MeteredStop::new(self)
+    }
+
+    /// Emit `tracing` events, named `name`, on cancellation.
+    ///
+    /// Returns a [`TracedStop`]; requires the `tracing` feature.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use almost_enough::{Stop, StopExt, Stopper};
+    ///
+    /// let stop = Stopper::new().traced("thumbnail");
+    /// stop.cancel(); // DEBUG "cancel requested"
+    /// assert!(stop.should_stop()); // DEBUG "cancellation observed"
+    /// ```
+    #[cfg(feature = "tracing")]
+    #[inline]
+    fn traced(self, name: impl Into<NodeLabel>) -> TracedStop<Self> {
+        TracedStop::new(self, name)
+    }
+
+    /// Run a resumable stepped computation, checking this stop between
+    /// steps.
+    ///
+    /// Shorthand for [`race_steps(&self, init, step)`](race_steps()).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use almost_enough::{StepResult, StopExt, StopSource};
+    ///
+    /// let source = StopSource::new();
+    /// let outcome = source.as_ref().race_steps(1u64, |n| {
+    ///     if n > 1000 { StepResult::Done(n) } else { StepResult::Continue(n * 2) }
+    /// });
+    /// assert_eq!(outcome.into_result(), Ok(1024));
+    /// ```
+    #[inline]
+    fn race_steps<S>(&self, init: S, step: impl FnMut(S) -> StepResult<S>) -> StepOutcome<S> {
+        race_steps(self, init, step)
+    }
+
     /// Convert this stop into a boxed trait object.
     ///
     /// This is useful for preventing monomorphization at API boundaries.
@@ -463,6 +1040,43 @@ impl<T: Stop + Sized> StopExt for T {}
 mod tests {
     use super::*;
 
+    #[cfg(feature = "std")]
+    #[test]
+    fn combinators_on_trait_objects() {
+        use std::sync::Arc;
+        use std::time::Duration;
+
+        fn borrowed(stop: &dyn Stop) -> bool {
+            stop.or(Unstoppable)
+                .throttle_checks(1)
+                .with_timeout(Duration::from_secs(60))
+                .should_stop()
+        }
+        fn borrowed_mut(stop: &mut dyn Stop) -> bool {
+            stop.or(Unstoppable).cache_reason().should_stop()
+        }
+
+        let source = StopSource::new();
+        assert!(!borrowed(&source.as_ref()));
+        source.cancel();
+        assert!(borrowed(&source.as_ref()));
+        assert!(borrowed_mut(&mut source.as_ref()));
+
+        let shared: Arc<dyn Stop + Send + Sync> = Arc::new(Stopper::cancelled());
+        let child = shared.child();
+        assert!(child.should_stop());
+        let boxed: Box<dyn Stop + Send + Sync> = Box::new(Stopper::new());
+        assert!(!boxed.into_boxed().should_stop());
+    }
+
+    #[test]
+    fn static_singletons() {
+        assert!(!NEVER.may_stop());
+        assert!(NEVER.check().is_ok());
+        assert!(CANCELLED.should_stop());
+        assert_eq!(CANCELLED.check(), Err(StopReason::Cancelled));
+    }
+
     #[test]
     fn or_extension_works() {
         let a = StopSource::new();
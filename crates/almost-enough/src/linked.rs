@@ -0,0 +1,299 @@
+//! Linked cancellation over any number of sources.
+//!
+//! [`LinkedStopper`] owns a set of [`BoxedStop`]s and stops as soon as any
+//! of them stops — the equivalent of .NET's
+//! `CancellationTokenSource.CreateLinkedTokenSource`. Unlike nesting
+//! [`OrStop`](crate::OrStop)s, the inputs are moved in, the result is a
+//! single clonable handle, and the number of sources is not fixed at
+//! compile time.
+//!
+//! # Example
+//!
+//! ```rust
+//! use almost_enough::{LinkedStopper, Stopper, StopExt, Stop};
+//!
+//! let user = Stopper::new();
+//! let shutdown = Stopper::new();
+//!
+//! let linked: LinkedStopper = [user.clone().into_boxed(), shutdown.clone().into_boxed()]
+//!     .into_iter()
+//!     .collect();
+//!
+//! assert!(!linked.should_stop());
+//! shutdown.cancel();
+//! assert!(linked.should_stop());
+//! ```
+
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU8, Ordering};
+
+use crate::reason::{CANCELLED, NOT_STOPPED, decode, encode};
+use crate::{BoxedStop, Stop, StopReason};
+
+/// Inner state shared by all clones.
+struct LinkedInner {
+    /// Latched stop reason (`NOT_STOPPED` until something trips).
+    state: AtomicU8,
+    /// Sources that may stop; no-op sources are dropped at construction.
+    sources: Box<[BoxedStop]>,
+}
+
+/// A clonable stop that trips when any of its owned sources trips.
+///
+/// Build one by collecting [`BoxedStop`]s — `LinkedStopper::from_iter(..)`
+/// or `.collect()`. It can also be cancelled directly with
+/// [`cancel()`](Self::cancel), which does not affect the sources.
+///
+/// # Check Path
+///
+/// The first stop observed is latched with its [`StopReason`], so once
+/// tripped every `check()` is a single atomic load. Until then, sources are
+/// checked in order and the scan stops at the first one that trips. Sources
+/// that can never stop (`Unstoppable`, etc.) are discarded up front.
+///
+/// # Example
+///
+/// ```rust
+/// use almost_enough::{BoxedStop, ChildStopper, LinkedStopper, Stop, StopReason, Stopper};
+///
+/// let request = Stopper::new();
+/// let shutdown = ChildStopper::new();
+/// let linked = LinkedStopper::from_iter([
+///     BoxedStop::new(request.clone()),
+///     BoxedStop::new(shutdown.child()),
+/// ]);
+///
+/// let worker = linked.clone();
+/// request.cancel();
+/// assert_eq!(worker.check(), Err(StopReason::Cancelled));
+/// ```
+#[derive(Clone)]
+pub struct LinkedStopper {
+    inner: Arc<LinkedInner>,
+}
+
+impl LinkedStopper {
+    /// Cancel this linked stopper directly.
+    ///
+    /// The owned sources are not affected.
+    #[inline]
+    pub fn cancel(&self) {
+        let _ = self.inner.state.compare_exchange(
+            NOT_STOPPED,
+            CANCELLED,
+            Ordering::Relaxed,
+            Ordering::Relaxed,
+        );
+    }
+
+    /// Returns `true` if this stopper has tripped, either directly or
+    /// because a source stopped (as last observed by a check).
+    #[inline]
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.state.load(Ordering::Relaxed) != NOT_STOPPED
+    }
+
+    /// Number of linked sources that can stop.
+    #[inline]
+    pub fn source_count(&self) -> usize {
+        self.inner.sources.len()
+    }
+
+    #[cold]
+    fn latch(&self, reason: StopReason) -> StopReason {
+        match self.inner.state.compare_exchange(
+            NOT_STOPPED,
+            encode(reason),
+            Ordering::Relaxed,
+            Ordering::Relaxed,
+        ) {
+            Ok(_) => reason,
+            // Another thread latched first; report what it saw
+            Err(existing) => decode(existing),
+        }
+    }
+}
+
+impl FromIterator<BoxedStop> for LinkedStopper {
+    fn from_iter<I: IntoIterator<Item = BoxedStop>>(iter: I) -> Self {
+        let sources: Vec<BoxedStop> = iter.into_iter().filter(|s| s.may_stop()).collect();
+        Self {
+            inner: Arc::new(LinkedInner {
+                state: AtomicU8::new(NOT_STOPPED),
+                sources: sources.into_boxed_slice(),
+            }),
+        }
+    }
+}
+
+impl Stop for LinkedStopper {
+    #[inline]
+    fn check(&self) -> Result<(), StopReason> {
+        let state = self.inner.state.load(Ordering::Relaxed);
+        if state != NOT_STOPPED {
+            return Err(decode(state));
+        }
+        for source in self.inner.sources.iter() {
+            if let Err(reason) = source.check() {
+                return Err(self.latch(reason));
+            }
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn should_stop(&self) -> bool {
+        self.check().is_err()
+    }
+}
+
+impl core::fmt::Debug for LinkedStopper {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("LinkedStopper")
+            .field("is_cancelled", &self.is_cancelled())
+            .field("sources", &self.inner.sources.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ChildStopper, FnStop, StopExt, StopToken, Stopper, Unstoppable};
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn trips_when_any_source_trips() {
+        let a = Stopper::new();
+        let b = Stopper::new();
+        let c = ChildStopper::new();
+        let linked = LinkedStopper::from_iter([
+            a.clone().into_boxed(),
+            b.clone().into_boxed(),
+            c.clone().into_boxed(),
+        ]);
+
+        assert!(!linked.should_stop());
+        assert!(linked.check().is_ok());
+
+        b.cancel();
+        assert!(linked.should_stop());
+        assert_eq!(linked.check(), Err(StopReason::Cancelled));
+        assert!(!a.should_stop());
+    }
+
+    #[test]
+    fn latches_first_reason() {
+        let flag = Arc::new(core::sync::atomic::AtomicBool::new(false));
+        let probe = Arc::clone(&flag);
+        let linked: LinkedStopper = [BoxedStop::new(FnStop::new(move || {
+            probe.load(Ordering::Relaxed)
+        }))]
+        .into_iter()
+        .collect();
+
+        flag.store(true, Ordering::Relaxed);
+        assert!(linked.should_stop());
+        // The source recovering does not untrip the linked stopper
+        flag.store(false, Ordering::Relaxed);
+        assert!(linked.should_stop());
+        assert!(linked.is_cancelled());
+    }
+
+    #[test]
+    fn short_circuits_after_trip() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&calls);
+        let first = Stopper::cancelled();
+        let linked = LinkedStopper::from_iter([
+            first.into_boxed(),
+            BoxedStop::new(FnStop::new(move || {
+                counter.fetch_add(1, Ordering::Relaxed);
+                false
+            })),
+        ]);
+
+        for _ in 0..10 {
+            assert!(linked.should_stop());
+        }
+        assert_eq!(calls.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn drops_noop_sources() {
+        let linked = LinkedStopper::from_iter([
+            BoxedStop::new(Unstoppable),
+            Stopper::new().into_boxed(),
+            BoxedStop::new(Unstoppable),
+        ]);
+        assert_eq!(linked.source_count(), 1);
+
+        let empty = LinkedStopper::from_iter(core::iter::empty());
+        assert_eq!(empty.source_count(), 0);
+        assert!(!empty.should_stop());
+    }
+
+    #[test]
+    fn direct_cancel_leaves_sources_alone() {
+        let a = Stopper::new();
+        let linked = LinkedStopper::from_iter([a.clone().into_boxed()]);
+        let clone = linked.clone();
+
+        linked.cancel();
+        assert!(clone.should_stop());
+        assert_eq!(clone.check(), Err(StopReason::Cancelled));
+        assert!(!a.should_stop());
+    }
+
+    #[test]
+    fn converts_to_token() {
+        let a = Stopper::new();
+        let token: StopToken = LinkedStopper::from_iter([a.clone().into_boxed()]).into_token();
+        a.cancel();
+        assert!(token.should_stop());
+    }
+
+    #[test]
+    fn reason_round_trip() {
+        for reason in [
+            StopReason::Cancelled,
+            StopReason::TimedOut,
+            StopReason::Failed,
+        ] {
+            assert_eq!(decode(encode(reason)), reason);
+        }
+    }
+
+    #[test]
+    fn debug_format() {
+        let linked = LinkedStopper::from_iter([Stopper::new().into_boxed()]);
+        let debug = alloc::format!("{:?}", linked);
+        assert!(debug.contains("LinkedStopper"));
+        assert!(debug.contains("sources: 1"));
+    }
+
+    #[test]
+    fn linked_is_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<LinkedStopper>();
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod std_tests {
+    use super::*;
+    use crate::{Stopper, TimeoutExt};
+    use std::time::Duration;
+
+    #[test]
+    fn timeout_source_reports_timed_out() {
+        let linked = LinkedStopper::from_iter([
+            BoxedStop::new(Stopper::new()),
+            BoxedStop::new(Stopper::new().with_timeout(Duration::ZERO)),
+        ]);
+        assert_eq!(linked.check(), Err(StopReason::TimedOut));
+        assert_eq!(linked.check(), Err(StopReason::TimedOut));
+    }
+}
@@ -0,0 +1,129 @@
+//! `log` events for cancellation; the messages are listed under "Logging"
+//! in the crate docs.
+
+use crate::{NodeLabel, StopReason};
+
+/// Formats an optional label, `-` when there is none.
+struct Label(Option<NodeLabel>);
+
+impl core::fmt::Display for Label {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match &self.0 {
+            Some(label) => label.fmt(f),
+            None => f.write_str("-"),
+        }
+    }
+}
+
+/// A stop was cancelled with `reason`; a `TimedOut` reason is its deadline.
+#[cold]
+pub(crate) fn stopped(label: Option<NodeLabel>, reason: StopReason) {
+    if reason.is_timed_out() {
+        deadline_expired(label);
+    } else {
+        log::debug!("cancel requested: stop={} reason={reason:?}", Label(label));
+    }
+}
+
+/// A timeout was first seen to have passed its deadline.
+#[cold]
+pub(crate) fn deadline_expired(label: Option<NodeLabel>) {
+    log::debug!("deadline expired: stop={}", Label(label));
+}
+
+/// An armed guard was dropped and cancelled its source.
+#[cold]
+pub(crate) fn guard_cancelled() {
+    #[cfg(feature = "std")]
+    if std::thread::panicking() {
+        log::warn!("cancelled by guard: panicking");
+        return;
+    }
+    log::debug!("cancelled by guard");
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use std::string::{String, ToString};
+    use std::sync::{Mutex, Once};
+    use std::vec::Vec;
+
+    use crate::{ChildStopper, StopDropRoll, Stopper};
+
+    /// Records every message; tests filter by their own labels, since other
+    /// tests log concurrently.
+    struct Recorder(Mutex<Vec<(log::Level, String)>>);
+
+    impl log::Log for Recorder {
+        fn enabled(&self, _: &log::Metadata<'_>) -> bool {
+            true
+        }
+
+        fn log(&self, record: &log::Record<'_>) {
+            let message = record.args().to_string();
+            self.0.lock().unwrap().push((record.level(), message));
+        }
+
+        fn flush(&self) {}
+    }
+
+    static RECORDER: Recorder = Recorder(Mutex::new(Vec::new()));
+
+    fn logged(needle: &str) -> Vec<(log::Level, String)> {
+        static INIT: Once = Once::new();
+        INIT.call_once(|| {
+            log::set_logger(&RECORDER).unwrap();
+            log::set_max_level(log::LevelFilter::Debug);
+        });
+        RECORDER
+            .0
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, message)| message.contains(needle))
+            .cloned()
+            .collect()
+    }
+
+    #[test]
+    fn cancel_is_logged_once() {
+        logged("");
+        let stop = Stopper::with_label("log-cancel");
+        stop.cancel();
+        stop.cancel();
+        let child = ChildStopper::with_label("log-child");
+        child.cancel();
+        assert_eq!(
+            logged("log-"),
+            [
+                (
+                    log::Level::Debug,
+                    "cancel requested: stop=log-cancel reason=Cancelled".into()
+                ),
+                (
+                    log::Level::Debug,
+                    "cancel requested: stop=log-child reason=Cancelled".into()
+                ),
+            ]
+        );
+    }
+
+    #[cfg(feature = "timer")]
+    #[test]
+    fn timer_expiry_is_logged() {
+        logged("");
+        let stop = Stopper::with_label("log-timer");
+        stop.cancel_after(core::time::Duration::ZERO);
+        assert_eq!(logged("log-timer")[0].1, "deadline expired: stop=log-timer");
+    }
+
+    #[test]
+    fn guard_cancel_is_logged() {
+        logged("");
+        let before = logged("cancelled by guard").len();
+        let stop = Stopper::new();
+        drop(stop.stop_on_drop());
+        stop.stop_on_drop().disarm();
+        assert!(logged("cancelled by guard").len() > before);
+    }
+}
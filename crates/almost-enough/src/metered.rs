@@ -0,0 +1,299 @@
+//! Check-frequency instrumentation.
+//!
+//! How often should a codec check its stop? Too rarely and cancellation
+//! lags; too often and the checks show up in profiles. [`MeteredStop`]
+//! answers with numbers: how many checks ran, when cancellation was
+//! requested, and when a check first observed it. The gap between the two
+//! is the cancellation latency the check frequency buys.
+//!
+//! The wrapper can only timestamp a cancellation it hears about. Cancel
+//! through it ([`cancel()`](MeteredStop::cancel) or
+//! [`Cancellable::stop()`]), or call
+//! [`record_cancel()`](MeteredStop::record_cancel) next to the code that
+//! cancels the inner stop. Clones share one set of counters, so a clone can
+//! go to the canceller while the workers check the original.
+//!
+//! # Example
+//!
+//! ```rust
+//! use almost_enough::{Stop, StopExt, Stopper};
+//!
+//! let stop = Stopper::new().metered();
+//! let canceller = stop.clone();
+//!
+//! let mut rows = 0;
+//! while stop.check().is_ok() {
+//!     rows += 1; // decode a row ...
+//!     if rows == 100 {
+//!         canceller.cancel();
+//!     }
+//! }
+//!
+//! assert_eq!(stop.checks(), 101);
+//! let latency = stop.time_to_observe().unwrap();
+//! println!("cancellation seen after {latency:?}");
+//! ```
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::{Cancellable, Stop, StopReason};
+
+/// Shared counters. Timestamps are nanoseconds after `epoch`, plus one so
+/// that zero means "not yet".
+struct Meter {
+    epoch: Instant,
+    checks: AtomicU64,
+    cancelled_at: AtomicU64,
+    observed_at: AtomicU64,
+}
+
+impl Meter {
+    /// Record now in `slot` unless it is already set.
+    fn stamp(&self, slot: &AtomicU64) {
+        if slot.load(Ordering::Relaxed) != 0 {
+            return;
+        }
+        let nanos = self.epoch.elapsed().as_nanos().min(u64::MAX as u128 - 1) as u64;
+        let _ = slot.compare_exchange(0, nanos + 1, Ordering::Relaxed, Ordering::Relaxed);
+    }
+
+    fn instant(&self, slot: &AtomicU64) -> Option<Instant> {
+        match slot.load(Ordering::Relaxed) {
+            0 => None,
+            stamp => Some(self.epoch + Duration::from_nanos(stamp - 1)),
+        }
+    }
+}
+
+/// A [`Stop`] wrapper that counts checks and timestamps cancellation and
+/// its first observation.
+///
+/// Created by [`MeteredStop::new()`] or
+/// [`StopExt::metered()`](crate::StopExt::metered). Both `check()` and
+/// `should_stop()` count as checks. Counting is one relaxed atomic
+/// increment per check, so the wrapper is meant for tuning and diagnostics
+/// rather than for every production stop.
+pub struct MeteredStop<T> {
+    inner: T,
+    meter: Arc<Meter>,
+}
+
+impl<T: Stop> MeteredStop<T> {
+    /// Wrap `inner` with zeroed counters.
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            meter: Arc::new(Meter {
+                epoch: Instant::now(),
+                checks: AtomicU64::new(0),
+                cancelled_at: AtomicU64::new(0),
+                observed_at: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// Record now as the time cancellation was requested.
+    ///
+    /// Only the first call counts. Use it when the inner stop is cancelled
+    /// by code that does not go through this wrapper.
+    #[inline]
+    pub fn record_cancel(&self) {
+        self.meter.stamp(&self.meter.cancelled_at);
+    }
+
+    /// Number of `check()` and `should_stop()` calls so far, across all
+    /// clones.
+    #[inline]
+    pub fn checks(&self) -> u64 {
+        self.meter.checks.load(Ordering::Relaxed)
+    }
+
+    /// When cancellation was requested, if it has been recorded.
+    #[inline]
+    pub fn cancelled_at(&self) -> Option<Instant> {
+        self.meter.instant(&self.meter.cancelled_at)
+    }
+
+    /// When a check first reported a stop.
+    #[inline]
+    pub fn observed_at(&self) -> Option<Instant> {
+        self.meter.instant(&self.meter.observed_at)
+    }
+
+    /// How long after the recorded cancellation a check first observed it.
+    ///
+    /// `None` until both have happened. Stops that fire on their own, such
+    /// as timeouts, have no recorded cancellation; use
+    /// [`observed_at()`](Self::observed_at) for those.
+    pub fn time_to_observe(&self) -> Option<Duration> {
+        let cancelled = self.cancelled_at()?;
+        Some(self.observed_at()?.saturating_duration_since(cancelled))
+    }
+
+    /// Get a reference to the inner stop.
+    #[inline]
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Unwrap and return the inner stop.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    #[inline]
+    fn count(&self) {
+        self.meter.checks.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[cold]
+    fn observed(&self) {
+        self.meter.stamp(&self.meter.observed_at);
+    }
+}
+
+impl<T: Cancellable + Stop> MeteredStop<T> {
+    /// Record the cancellation time, then cancel the inner stop.
+    #[inline]
+    pub fn cancel(&self) {
+        self.record_cancel();
+        self.inner.stop();
+    }
+}
+
+impl<T: Stop> Stop for MeteredStop<T> {
+    #[inline]
+    fn check(&self) -> Result<(), StopReason> {
+        self.count();
+        let result = self.inner.check();
+        if result.is_err() {
+            self.observed();
+        }
+        result
+    }
+
+    #[inline]
+    fn should_stop(&self) -> bool {
+        self.count();
+        let stop = self.inner.should_stop();
+        if stop {
+            self.observed();
+        }
+        stop
+    }
+
+    #[inline]
+    fn may_stop(&self) -> bool {
+        self.inner.may_stop()
+    }
+}
+
+impl<T: Cancellable + Stop> Cancellable for MeteredStop<T> {
+    #[inline]
+    fn stop(&self) {
+        self.cancel();
+    }
+}
+
+impl<T: Clone> Clone for MeteredStop<T> {
+    /// The clone shares this wrapper's counters.
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            meter: Arc::clone(&self.meter),
+        }
+    }
+}
+
+impl<T: core::fmt::Debug> core::fmt::Debug for MeteredStop<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("MeteredStop")
+            .field("inner", &self.inner)
+            .field("checks", &self.meter.checks.load(Ordering::Relaxed))
+            .field(
+                "cancelled_at",
+                &self.meter.instant(&self.meter.cancelled_at),
+            )
+            .field("observed_at", &self.meter.instant(&self.meter.observed_at))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{StopDropRoll, StopExt, Stopper, TimeoutExt, Unstoppable};
+
+    #[test]
+    fn counts_checks_and_stamps_observation() {
+        let stop = Stopper::new().metered();
+        assert!(stop.check().is_ok());
+        assert!(!stop.should_stop());
+        assert_eq!(stop.checks(), 2);
+        assert_eq!(stop.observed_at(), None);
+
+        stop.cancel();
+        let cancelled = stop.cancelled_at().unwrap();
+        assert!(stop.inner().is_cancelled());
+        assert_eq!(stop.check(), Err(StopReason::Cancelled));
+        let observed = stop.observed_at().unwrap();
+        assert!(observed >= cancelled);
+
+        // Later observations keep the first timestamp
+        assert!(stop.should_stop());
+        assert_eq!(stop.observed_at(), Some(observed));
+        assert_eq!(stop.time_to_observe(), Some(observed - cancelled));
+        assert_eq!(stop.checks(), 4);
+    }
+
+    #[test]
+    fn clones_share_counters() {
+        let stop = MeteredStop::new(Stopper::new());
+        let worker = stop.clone();
+        let _ = worker.check();
+        let _ = worker.check();
+        assert_eq!(stop.checks(), 2);
+
+        {
+            let _guard = stop.clone().stop_on_drop();
+        }
+        assert!(worker.cancelled_at().is_some());
+        assert!(worker.should_stop());
+        assert!(stop.time_to_observe().is_some());
+    }
+
+    #[test]
+    fn external_cancel_needs_record() {
+        let stopper = Stopper::new();
+        let stop = stopper.clone().metered();
+        stopper.cancel();
+        assert!(stop.should_stop());
+        assert_eq!(stop.time_to_observe(), None);
+
+        stop.record_cancel();
+        let first = stop.cancelled_at();
+        stop.record_cancel();
+        assert_eq!(stop.cancelled_at(), first);
+        assert_eq!(stop.time_to_observe(), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn timeouts_are_observed_without_cancel() {
+        let stop = Unstoppable.with_timeout(Duration::ZERO).metered();
+        assert_eq!(stop.check(), Err(StopReason::TimedOut));
+        assert!(stop.observed_at().is_some());
+        assert!(stop.cancelled_at().is_none());
+        assert!(stop.may_stop());
+        assert!(format!("{stop:?}").contains("checks: 1"));
+        assert!(!stop.into_inner().inner().may_stop());
+    }
+
+    #[test]
+    fn is_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<MeteredStop<Stopper>>();
+    }
+}
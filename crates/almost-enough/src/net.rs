@@ -0,0 +1,238 @@
+//! Cancellation-aware name resolution.
+//!
+//! `getaddrinfo` cannot be interrupted: a resolver that hangs on an
+//! unreachable DNS server blocks the calling thread for as long as the
+//! system timeout says (often 30 seconds or more), no matter how often the
+//! surrounding code checks its stop. [`resolve_with_stop()`] runs the
+//! lookup on a detached helper thread and waits for either the answer or
+//! the stop. If the stop wins, the caller returns immediately and the
+//! helper is abandoned; it finishes (and exits) on its own.
+//!
+//! Abandonment is bounded: at most [`MAX_HELPERS`] helper threads exist at
+//! once, abandoned or not. When all are busy, new calls wait — still
+//! honoring their stop — for one to finish, so a stuck resolver cannot
+//! make the process spawn threads without limit.
+//!
+//! # Example
+//!
+//! ```rust
+//! use almost_enough::net::resolve_with_stop;
+//! use almost_enough::{Stopper, TimeoutExt};
+//! use std::time::Duration;
+//!
+//! let stop = Stopper::new().with_timeout(Duration::from_secs(5));
+//! let addrs = resolve_with_stop("127.0.0.1:8080", &stop).unwrap();
+//! assert_eq!(addrs[0].port(), 8080);
+//! ```
+
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::Duration;
+use std::{fmt, io};
+
+use crate::{Stop, StopReason};
+
+/// Maximum number of resolver helper threads alive at once.
+pub const MAX_HELPERS: usize = 16;
+
+/// How often a waiting caller re-checks its stop.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Helper threads currently alive, including abandoned ones.
+static HELPERS: AtomicUsize = AtomicUsize::new(0);
+
+/// Error returned by [`resolve_with_stop()`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ResolveError {
+    /// The stop fired before the lookup finished.
+    Stopped(StopReason),
+    /// The lookup itself failed.
+    Io(io::Error),
+}
+
+impl fmt::Display for ResolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResolveError::Stopped(reason) => write!(f, "name resolution stopped: {reason}"),
+            ResolveError::Io(e) => write!(f, "name resolution failed: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for ResolveError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ResolveError::Stopped(_) => None,
+            ResolveError::Io(e) => Some(e),
+        }
+    }
+}
+
+impl From<StopReason> for ResolveError {
+    fn from(reason: StopReason) -> Self {
+        ResolveError::Stopped(reason)
+    }
+}
+
+impl From<ResolveError> for io::Error {
+    /// A stop becomes [`io::ErrorKind::Interrupted`] (cancelled) or
+    /// [`io::ErrorKind::TimedOut`].
+    fn from(error: ResolveError) -> Self {
+        match error {
+            ResolveError::Io(e) => e,
+            ResolveError::Stopped(StopReason::TimedOut) => {
+                io::Error::new(io::ErrorKind::TimedOut, error)
+            }
+            ResolveError::Stopped(_) => io::Error::new(io::ErrorKind::Interrupted, error),
+        }
+    }
+}
+
+/// Resolve `addr` (`"host:port"`, as accepted by [`ToSocketAddrs`] for
+/// `str`) without letting an unresponsive resolver outlast `stop`.
+///
+/// The lookup runs on a detached helper thread. Returns
+/// [`ResolveError::Stopped`] as soon as `stop` fires (checked every 10ms),
+/// abandoning the helper; otherwise returns the lookup's result. If `stop`
+/// has already fired, no thread is started.
+///
+/// # Example
+///
+/// ```rust
+/// use almost_enough::net::{ResolveError, resolve_with_stop};
+/// use almost_enough::{StopReason, Stopper};
+///
+/// let stop = Stopper::cancelled();
+/// assert!(matches!(
+///     resolve_with_stop("example.com:443", &stop),
+///     Err(ResolveError::Stopped(StopReason::Cancelled))
+/// ));
+/// ```
+pub fn resolve_with_stop(addr: &str, stop: &impl Stop) -> Result<Vec<SocketAddr>, ResolveError> {
+    let addr = addr.to_owned();
+    run_detached(stop, move || addr.to_socket_addrs().map(Iterator::collect))?
+        .unwrap_or_else(|| Err(io::Error::other("resolver thread panicked")))
+        .map_err(ResolveError::Io)
+}
+
+/// Releases a helper slot when the helper thread ends.
+struct Slot;
+
+impl Slot {
+    /// Wait for a free helper slot, giving up if `stop` fires.
+    fn acquire(stop: &impl Stop) -> Result<Self, StopReason> {
+        loop {
+            stop.check()?;
+            let current = HELPERS.load(Ordering::Relaxed);
+            if current < MAX_HELPERS
+                && HELPERS
+                    .compare_exchange_weak(
+                        current,
+                        current + 1,
+                        Ordering::Acquire,
+                        Ordering::Relaxed,
+                    )
+                    .is_ok()
+            {
+                return Ok(Slot);
+            }
+            if current >= MAX_HELPERS {
+                std::thread::sleep(POLL_INTERVAL);
+            }
+        }
+    }
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        HELPERS.fetch_sub(1, Ordering::Release);
+    }
+}
+
+/// Run `f` on a detached helper thread, returning early if `stop` fires.
+///
+/// Returns `Ok(None)` if `f` panicked.
+fn run_detached<T, F>(stop: &impl Stop, f: F) -> Result<Option<T>, StopReason>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    let slot = Slot::acquire(stop)?;
+    let (tx, rx) = mpsc::sync_channel(1);
+    std::thread::Builder::new()
+        .name("resolve-with-stop".into())
+        .spawn(move || {
+            let _slot = slot;
+            // The receiver is gone if the caller gave up
+            let _ = tx.send(f());
+        })
+        .expect("failed to spawn resolver helper thread");
+
+    loop {
+        match rx.recv_timeout(POLL_INTERVAL) {
+            Ok(value) => return Ok(Some(value)),
+            Err(RecvTimeoutError::Timeout) => stop.check()?,
+            // The helper dropped its sender without sending: it panicked
+            Err(RecvTimeoutError::Disconnected) => return Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Stopper, TimeoutExt, Unstoppable};
+    use std::time::Instant;
+
+    #[test]
+    fn resolves_literal_address() {
+        let addrs = resolve_with_stop("127.0.0.1:443", &Unstoppable).unwrap();
+        assert_eq!(addrs, vec![SocketAddr::from(([127, 0, 0, 1], 443))]);
+    }
+
+    #[test]
+    fn lookup_error_is_io() {
+        let err = resolve_with_stop("missing-port", &Unstoppable).unwrap_err();
+        assert!(matches!(err, ResolveError::Io(_)));
+        assert!(err.to_string().contains("name resolution failed"));
+    }
+
+    #[test]
+    fn already_stopped_returns_immediately() {
+        let err = resolve_with_stop("127.0.0.1:1", &Stopper::cancelled()).unwrap_err();
+        assert!(matches!(err, ResolveError::Stopped(StopReason::Cancelled)));
+        let io: io::Error = err.into();
+        assert_eq!(io.kind(), io::ErrorKind::Interrupted);
+    }
+
+    #[test]
+    fn abandons_hung_work() {
+        let (release, hang) = mpsc::channel::<()>();
+        let stop = Unstoppable.with_timeout(Duration::from_millis(30));
+        let start = Instant::now();
+
+        let result = run_detached(&stop, move || hang.recv().is_ok());
+        assert_eq!(result, Err(StopReason::TimedOut));
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        // Let the abandoned helper finish
+        drop(release);
+    }
+
+    #[test]
+    fn panicking_helper_reports_none() {
+        let hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(|_| {}));
+        let result = run_detached(&Unstoppable, || -> u8 { panic!("boom") });
+        std::panic::set_hook(hook);
+        assert_eq!(result, Ok(None));
+    }
+
+    #[test]
+    fn timeout_maps_to_io_timed_out() {
+        let io: io::Error = ResolveError::Stopped(StopReason::TimedOut).into();
+        assert_eq!(io.kind(), io::ErrorKind::TimedOut);
+    }
+}
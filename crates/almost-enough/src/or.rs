@@ -26,6 +26,10 @@ use crate::{Stop, StopReason};
 ///
 /// The combined stop will trigger when either source stops.
 ///
+/// `should_stop()` does not say which half fired; wrap the combination with
+/// [`cache_reason()`](crate::StopExt::cache_reason) to read the reason back
+/// afterwards.
+///
 /// # Example
 ///
 /// ```rust
@@ -95,6 +99,15 @@ impl<A: Stop, B: Stop> Stop for OrStop<A, B> {
     }
 }
 
+#[cfg(feature = "std")]
+impl<A: crate::CheckAt, B: crate::CheckAt> crate::CheckAt for OrStop<A, B> {
+    #[inline]
+    fn check_at(&self, now: std::time::Instant) -> Result<(), StopReason> {
+        self.a.check_at(now)?;
+        self.b.check_at(now)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -239,3 +252,28 @@ mod tests {
         assert!(combined.may_stop());
     }
 }
+
+#[cfg(all(test, feature = "std"))]
+mod std_tests {
+    use super::*;
+    use crate::{CheckAt, StopSource, TimeoutExt};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn check_at_forwards_to_both_halves() {
+        let source = StopSource::new();
+        let combined = OrStop::new(
+            source.as_ref(),
+            source.as_ref().with_timeout(Duration::from_secs(60)),
+        );
+        let now = Instant::now();
+        assert!(combined.check_at(now).is_ok());
+        assert_eq!(
+            combined.check_at(now + Duration::from_secs(120)),
+            Err(StopReason::TimedOut)
+        );
+
+        source.cancel();
+        assert!(combined.should_stop_at(now));
+    }
+}
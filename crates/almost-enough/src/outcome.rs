@@ -0,0 +1,239 @@
+//! One result shape for cancellable work.
+
+use crate::{StepOutcome, StopReason};
+
+/// How a piece of cancellable work ended.
+///
+/// Each runner reports its own way: [`race_steps()`](crate::race_steps)
+/// returns a [`StepOutcome`], [`StoppableJoinHandle`](crate::StoppableJoinHandle)
+/// a `JoinOutcome`, thread joins a `thread::Result`, and plain functions a
+/// `Result<T, StopReason>`. Code that drives several of them ends up with
+/// one `match` per runner. `Outcome` is the common shape: every runner's
+/// result converts into it (with `From`, or
+/// [`from_join()`](Self::from_join) for thread joins), so callers match on
+/// three cases.
+///
+/// # Example
+///
+/// ```rust
+/// use almost_enough::{BudgetStop, Outcome, StepResult, StopReason, race_steps};
+///
+/// // One handler for every runner
+/// fn progress(outcome: Outcome<u32>) -> Option<u32> {
+///     match outcome {
+///         Outcome::Completed(total) => Some(total),
+///         Outcome::Stopped { reason, partial } => {
+///             assert_eq!(reason, StopReason::TimedOut);
+///             partial
+///         }
+///         Outcome::Panicked => None,
+///     }
+/// }
+///
+/// let budget = BudgetStop::checks(3);
+/// let steps = race_steps(&budget, 0u32, |n| StepResult::Continue(n + 1));
+/// assert_eq!(progress(steps.into()), Some(3));
+///
+/// let plain: Result<u32, StopReason> = Err(StopReason::TimedOut);
+/// assert_eq!(progress(plain.into()), None);
+/// ```
+///
+/// `partial` is whatever the runner could hand back when it stopped: the
+/// resumable state of a stepped computation, or `None` when the work's
+/// progress is lost.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[must_use]
+pub enum Outcome<T> {
+    /// The work ran to completion.
+    Completed(T),
+    /// The work was stopped before completing.
+    Stopped {
+        /// Why the work stopped.
+        reason: StopReason,
+        /// The progress made before stopping, if the runner kept it.
+        partial: Option<T>,
+    },
+    /// The work panicked. The payload is not kept; runners that report
+    /// panics themselves (such as `JoinOutcome`) still offer it.
+    Panicked,
+}
+
+impl<T> Outcome<T> {
+    /// Whether the work ran to completion.
+    #[inline]
+    pub fn is_completed(&self) -> bool {
+        matches!(self, Self::Completed(_))
+    }
+
+    /// Whether the work was stopped.
+    #[inline]
+    pub fn is_stopped(&self) -> bool {
+        matches!(self, Self::Stopped { .. })
+    }
+
+    /// Whether the work panicked.
+    #[inline]
+    pub fn is_panicked(&self) -> bool {
+        matches!(self, Self::Panicked)
+    }
+
+    /// The stop reason, if the work was stopped.
+    #[inline]
+    pub fn reason(&self) -> Option<StopReason> {
+        match self {
+            Self::Stopped { reason, .. } => Some(*reason),
+            _ => None,
+        }
+    }
+
+    /// The value, if the work completed.
+    #[inline]
+    pub fn completed(self) -> Option<T> {
+        match self {
+            Self::Completed(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// The completed value or the partial progress, whichever there is.
+    #[inline]
+    pub fn into_value(self) -> Option<T> {
+        match self {
+            Self::Completed(value) => Some(value),
+            Self::Stopped { partial, .. } => partial,
+            Self::Panicked => None,
+        }
+    }
+
+    /// Map the completed value and the partial progress with `f`.
+    #[inline]
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Outcome<U> {
+        match self {
+            Self::Completed(value) => Outcome::Completed(f(value)),
+            Self::Stopped { reason, partial } => Outcome::Stopped {
+                reason,
+                partial: partial.map(f),
+            },
+            Self::Panicked => Outcome::Panicked,
+        }
+    }
+}
+
+/// A stopped `Err` keeps no partial progress.
+impl<T> From<Result<T, StopReason>> for Outcome<T> {
+    #[inline]
+    fn from(result: Result<T, StopReason>) -> Self {
+        match result {
+            Ok(value) => Self::Completed(value),
+            Err(reason) => Self::Stopped {
+                reason,
+                partial: None,
+            },
+        }
+    }
+}
+
+/// The resumable state of a stopped computation becomes `partial`.
+impl<S> From<StepOutcome<S>> for Outcome<S> {
+    #[inline]
+    fn from(outcome: StepOutcome<S>) -> Self {
+        match outcome {
+            StepOutcome::Completed(state) => Self::Completed(state),
+            StepOutcome::Stopped { state, reason } => Self::Stopped {
+                reason,
+                partial: Some(state),
+            },
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T> Outcome<T> {
+    /// The outcome of joining a thread whose work returns
+    /// `Result<T, StopReason>`, as from [`JoinHandle::join()`] or
+    /// [`StoppableJoinHandle::join()`](crate::StoppableJoinHandle::join).
+    ///
+    /// The panic payload is leaked rather than dropped, as dropping it can
+    /// panic again.
+    ///
+    /// ```rust
+    /// use almost_enough::{Outcome, Stop, StopReason, Stopper};
+    ///
+    /// let stop = Stopper::new();
+    /// let worker = {
+    ///     let stop = stop.clone();
+    ///     std::thread::spawn(move || -> Result<u32, StopReason> {
+    ///         stop.check()?;
+    ///         Ok(42)
+    ///     })
+    /// };
+    /// assert_eq!(Outcome::from_join(worker.join()), Outcome::Completed(42));
+    ///
+    /// let panicked = std::thread::spawn(|| -> Result<u32, StopReason> { panic!("boom") });
+    /// assert!(Outcome::from_join(panicked.join()).is_panicked());
+    /// ```
+    ///
+    /// [`JoinHandle::join()`]: std::thread::JoinHandle::join
+    pub fn from_join(result: std::thread::Result<Result<T, StopReason>>) -> Self {
+        match result {
+            Ok(result) => result.into(),
+            Err(payload) => {
+                // Dropping the payload can panic again
+                std::mem::forget(payload);
+                Self::Panicked
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_result_and_steps() {
+        let done: Outcome<u8> = Ok(1).into();
+        assert!(done.is_completed());
+        assert_eq!(done.reason(), None);
+        assert_eq!(done.completed(), Some(1));
+
+        let stopped: Outcome<u8> = Err(StopReason::TimedOut).into();
+        assert!(stopped.is_stopped());
+        assert_eq!(stopped.reason(), Some(StopReason::TimedOut));
+        assert_eq!(stopped.into_value(), None);
+
+        let resumable: Outcome<u8> = StepOutcome::Stopped {
+            state: 5,
+            reason: StopReason::Cancelled,
+        }
+        .into();
+        assert_eq!(resumable.completed(), None);
+        assert_eq!(resumable.into_value(), Some(5));
+        assert_eq!(
+            Outcome::from(StepOutcome::Completed(2u8)),
+            Outcome::Completed(2)
+        );
+    }
+
+    #[test]
+    fn map_keeps_shape() {
+        let partial = Outcome::Stopped {
+            reason: StopReason::Cancelled,
+            partial: Some(3u8),
+        };
+        assert_eq!(
+            partial.map(u32::from),
+            Outcome::Stopped {
+                reason: StopReason::Cancelled,
+                partial: Some(3u32)
+            }
+        );
+        assert_eq!(
+            Outcome::Completed(2u8).map(|n| n * 2),
+            Outcome::Completed(4)
+        );
+        let panicked: Outcome<u8> = Outcome::Panicked;
+        assert!(panicked.map(|n| n + 1).is_panicked());
+        assert_eq!(panicked.into_value(), None);
+    }
+}
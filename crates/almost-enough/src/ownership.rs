@@ -0,0 +1,278 @@
+//! Who is keeping a stopper alive?
+//!
+//! A shutdown that waits for every handle of a stopper to be dropped hangs
+//! forever if one clone was parked in a struct nobody tears down.
+//! [`Stopper::ownership_report()`] (and the same method on
+//! [`SyncStopper`](crate::SyncStopper) and
+//! [`ChildStopper`](crate::ChildStopper)) returns the strong and weak counts
+//! of the shared state. With the `debug-history` feature, a `Stopper` also
+//! records a backtrace each time it is created or cloned and forgets it
+//! when that handle is dropped, so the report lists where every
+//! outstanding clone came from.
+//!
+//! Capturing a backtrace per clone is slow; `debug-history` is meant for
+//! debug builds and tests, not production.
+//!
+//! # Example
+//!
+//! ```rust
+//! use almost_enough::Stopper;
+//!
+//! let stop = Stopper::new();
+//! let forgotten = stop.clone();
+//!
+//! let report = stop.ownership_report();
+//! assert_eq!(report.strong_count(), 2);
+//! # drop(forgotten);
+//! ```
+
+use alloc::sync::Arc;
+use core::fmt;
+
+#[cfg(feature = "debug-history")]
+use alloc::collections::BTreeMap;
+#[cfg(feature = "debug-history")]
+use alloc::vec::Vec;
+#[cfg(feature = "debug-history")]
+use core::sync::atomic::{AtomicU64, Ordering};
+#[cfg(feature = "debug-history")]
+use std::backtrace::Backtrace;
+#[cfg(feature = "debug-history")]
+use std::sync::{Mutex, PoisonError};
+
+use crate::Stopper;
+
+/// Reference counts of a stop's shared state, from
+/// [`Stopper::ownership_report()`] and friends.
+///
+/// A point-in-time snapshot: other threads may clone or drop handles while
+/// it is being read.
+#[derive(Debug, Clone)]
+pub struct OwnershipReport {
+    strong: usize,
+    weak: usize,
+    #[cfg(feature = "debug-history")]
+    clones: Vec<Arc<Backtrace>>,
+}
+
+impl OwnershipReport {
+    pub(crate) fn of<T>(arc: &Arc<T>) -> Self {
+        Self {
+            strong: Arc::strong_count(arc),
+            weak: Arc::weak_count(arc),
+            #[cfg(feature = "debug-history")]
+            clones: Vec::new(),
+        }
+    }
+
+    /// Strong references, including the handle the report was taken from.
+    ///
+    /// Counts every owner of the state: clones, and also
+    /// [`StopToken`](crate::StopToken)s converted from the stopper.
+    #[inline]
+    pub fn strong_count(&self) -> usize {
+        self.strong
+    }
+
+    /// Weak references, such as deadlines pending on the shared timer
+    /// thread (`Stopper::cancel_after()`, `timer` feature).
+    #[inline]
+    pub fn weak_count(&self) -> usize {
+        self.weak
+    }
+
+    /// Returns `true` if the handle the report was taken from is the only
+    /// strong reference left.
+    #[inline]
+    pub fn is_unique(&self) -> bool {
+        self.strong == 1
+    }
+
+    /// Creation backtraces of the live `Stopper` handles, oldest first.
+    ///
+    /// Only `Stopper` handles are tracked; owners that hold the state some
+    /// other way (a `StopToken`, a `SyncStopper` or `ChildStopper` report)
+    /// are counted in [`strong_count()`](Self::strong_count) but listed
+    /// here only as [`untracked()`](Self::untracked).
+    ///
+    /// Requires the `debug-history` feature.
+    #[cfg(feature = "debug-history")]
+    pub fn clone_backtraces(&self) -> impl ExactSizeIterator<Item = &Backtrace> {
+        self.clones.iter().map(|backtrace| &**backtrace)
+    }
+
+    /// Strong references without a recorded backtrace.
+    ///
+    /// Requires the `debug-history` feature.
+    #[cfg(feature = "debug-history")]
+    #[inline]
+    pub fn untracked(&self) -> usize {
+        self.strong.saturating_sub(self.clones.len())
+    }
+}
+
+impl fmt::Display for OwnershipReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} strong, {} weak", self.strong, self.weak)?;
+        #[cfg(feature = "debug-history")]
+        {
+            write!(f, ", {} untracked", self.untracked())?;
+            for (i, backtrace) in self.clones.iter().enumerate() {
+                write!(f, "\n\nhandle #{i} created at:\n{backtrace}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Stopper {
+    /// Report who owns this stopper's shared state.
+    ///
+    /// Counts every strong and weak reference; see [`OwnershipReport`].
+    /// With the `debug-history` feature the report also carries the
+    /// creation backtrace of every live `Stopper` handle.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use almost_enough::Stopper;
+    ///
+    /// let stop = Stopper::new();
+    /// assert!(stop.ownership_report().is_unique());
+    ///
+    /// let worker = stop.clone();
+    /// assert_eq!(stop.ownership_report().strong_count(), 2);
+    ///
+    /// drop(worker);
+    /// assert!(stop.ownership_report().is_unique());
+    /// ```
+    pub fn ownership_report(&self) -> OwnershipReport {
+        #[cfg_attr(not(feature = "debug-history"), allow(unused_mut))]
+        let mut report = OwnershipReport::of(&self.inner);
+        #[cfg(feature = "debug-history")]
+        {
+            report.clones = self.inner.history.backtraces();
+        }
+        report
+    }
+}
+
+/// Creation backtraces of the live handles of one stopper.
+#[cfg(feature = "debug-history")]
+#[derive(Default)]
+pub(crate) struct History {
+    next: AtomicU64,
+    live: Mutex<BTreeMap<u64, Arc<Backtrace>>>,
+}
+
+#[cfg(feature = "debug-history")]
+impl History {
+    fn live(&self) -> std::sync::MutexGuard<'_, BTreeMap<u64, Arc<Backtrace>>> {
+        self.live.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn backtraces(&self) -> Vec<Arc<Backtrace>> {
+        self.live().values().cloned().collect()
+    }
+}
+
+/// One live handle's entry in its stopper's [`History`], removed on drop.
+///
+/// Cloning records a new entry with a fresh backtrace, so a `Stopper` can
+/// keep deriving `Clone`.
+#[cfg(feature = "debug-history")]
+pub(crate) struct CloneSite {
+    id: u64,
+    history: Arc<History>,
+}
+
+#[cfg(feature = "debug-history")]
+impl CloneSite {
+    pub(crate) fn record(history: &Arc<History>) -> Self {
+        let id = history.next.fetch_add(1, Ordering::Relaxed);
+        history
+            .live()
+            .insert(id, Arc::new(Backtrace::force_capture()));
+        Self {
+            id,
+            history: Arc::clone(history),
+        }
+    }
+}
+
+#[cfg(feature = "debug-history")]
+impl Clone for CloneSite {
+    fn clone(&self) -> Self {
+        Self::record(&self.history)
+    }
+}
+
+#[cfg(feature = "debug-history")]
+impl Drop for CloneSite {
+    fn drop(&mut self) {
+        self.history.live().remove(&self.id);
+    }
+}
+
+#[cfg(feature = "debug-history")]
+impl fmt::Debug for CloneSite {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("CloneSite").field(&self.id).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ChildStopper, StopToken, SyncStopper};
+
+    #[test]
+    fn counts_clones_and_tokens() {
+        let stop = Stopper::new();
+        let clone = stop.clone();
+        let token = StopToken::from(clone);
+        let report = stop.ownership_report();
+        assert_eq!(report.strong_count(), 2);
+        assert!(!report.is_unique());
+        drop(token);
+        assert!(stop.ownership_report().is_unique());
+    }
+
+    #[test]
+    fn other_arc_backed_stops() {
+        let sync = SyncStopper::new();
+        let _other = sync.clone();
+        assert_eq!(sync.ownership_report().strong_count(), 2);
+
+        let parent = ChildStopper::new();
+        let child = parent.child();
+        // A live child keeps its parent node alive
+        assert_eq!(parent.ownership_report().strong_count(), 2);
+        assert!(child.ownership_report().is_unique());
+        drop(child);
+        assert!(parent.ownership_report().is_unique());
+    }
+
+    #[cfg(feature = "debug-history")]
+    #[test]
+    fn records_live_clones_only() {
+        fn park(stop: &Stopper) -> Stopper {
+            stop.clone()
+        }
+
+        let stop = Stopper::new();
+        let dropped = stop.clone();
+        let parked = park(&stop);
+        drop(dropped);
+        let _token = StopToken::from(stop.clone());
+
+        let report = stop.ownership_report();
+        assert_eq!(report.strong_count(), 3);
+        assert_eq!(report.clone_backtraces().len(), 2);
+        assert_eq!(report.untracked(), 1);
+        let text = report.to_string();
+        assert!(text.contains("park"), "{text}");
+        drop(parked);
+        assert_eq!(stop.ownership_report().clone_backtraces().len(), 1);
+    }
+}
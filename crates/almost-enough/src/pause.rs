@@ -0,0 +1,369 @@
+//! Pausable cancellation.
+//!
+//! [`PausableStopper`] adds a third state to a stopper: besides running and
+//! cancelled, it can be paused. Worker loops keep calling `check()` as
+//! usual; while paused, `check()` parks the calling thread until
+//! [`resume()`](PausableStopper::resume) or
+//! [`cancel()`](PausableStopper::cancel) is called. No new [`StopReason`] is
+//! needed — a paused worker simply doesn't return from `check()` yet.
+//!
+//! # Example
+//!
+//! ```rust
+//! use almost_enough::{PausableStopper, Stop};
+//! use std::sync::atomic::{AtomicUsize, Ordering};
+//! use std::sync::Arc;
+//!
+//! let control = PausableStopper::new();
+//! let progress = Arc::new(AtomicUsize::new(0));
+//!
+//! let worker = {
+//!     let stop = control.clone();
+//!     let progress = Arc::clone(&progress);
+//!     std::thread::spawn(move || {
+//!         while stop.check().is_ok() {
+//!             progress.fetch_add(1, Ordering::Relaxed);
+//!             std::thread::yield_now();
+//!         }
+//!     })
+//! };
+//!
+//! control.pause();   // worker parks at its next check()
+//! control.resume();  // ...and continues
+//! control.cancel();  // check() returns Err, worker exits
+//! worker.join().unwrap();
+//! ```
+//!
+//! # Pausing and Deadlines
+//!
+//! A worker parked inside `check()` does not observe other stops. When the
+//! pausable stopper is combined with a timeout, call
+//! [`wait_if_paused()`](PausableStopper::wait_if_paused) with the combined
+//! stop instead, which wakes periodically to check it.
+
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+use crate::{Stop, StopReason};
+
+const CANCELLED: u8 = 0b01;
+const PAUSED: u8 = 0b10;
+
+/// How often [`PausableStopper::wait_if_paused()`] re-checks the other stop.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+struct PauseInner {
+    state: AtomicU8,
+    lock: Mutex<()>,
+    cond: Condvar,
+}
+
+/// A stopper that can also be paused and resumed.
+///
+/// Clone to share; any clone can pause, resume, cancel or check. While
+/// paused, [`check()`](Stop::check) and [`should_stop()`](Stop::should_stop)
+/// block until resumed or cancelled. Cancellation always wins: cancelling a
+/// paused stopper wakes all parked workers, and they see
+/// [`StopReason::Cancelled`].
+///
+/// Requires the `std` feature.
+///
+/// # Example
+///
+/// ```rust
+/// use almost_enough::{PausableStopper, Stop, StopReason};
+///
+/// let stop = PausableStopper::new();
+/// stop.pause();
+/// assert!(stop.is_paused());
+///
+/// stop.resume();
+/// assert!(stop.check().is_ok()); // doesn't block
+///
+/// stop.cancel();
+/// assert_eq!(stop.check(), Err(StopReason::Cancelled));
+/// ```
+///
+/// # Performance
+///
+/// When not paused, `check()` is a single atomic load, like
+/// [`Stopper`](crate::Stopper).
+#[doc(alias = "PauseableStopper")]
+#[derive(Clone)]
+pub struct PausableStopper {
+    inner: Arc<PauseInner>,
+}
+
+impl PausableStopper {
+    /// Create a new, running stopper.
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(PauseInner {
+                state: AtomicU8::new(0),
+                lock: Mutex::new(()),
+                cond: Condvar::new(),
+            }),
+        }
+    }
+
+    /// Pause: subsequent checks park until [`resume()`](Self::resume).
+    ///
+    /// Has no effect once cancelled.
+    #[inline]
+    pub fn pause(&self) {
+        self.inner.state.fetch_or(PAUSED, Ordering::SeqCst);
+    }
+
+    /// Resume paused workers.
+    pub fn resume(&self) {
+        self.inner.state.fetch_and(!PAUSED, Ordering::SeqCst);
+        self.notify();
+    }
+
+    /// Cancel, waking any paused workers.
+    pub fn cancel(&self) {
+        self.inner.state.fetch_or(CANCELLED, Ordering::SeqCst);
+        self.notify();
+    }
+
+    /// Returns `true` if paused (and not cancelled).
+    #[inline]
+    pub fn is_paused(&self) -> bool {
+        self.inner.state.load(Ordering::Relaxed) == PAUSED
+    }
+
+    /// Returns `true` if cancelled. Never blocks.
+    #[inline]
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.state.load(Ordering::Relaxed) & CANCELLED != 0
+    }
+
+    /// Block while paused, returning early if `stop` trips.
+    ///
+    /// `stop` is typically a composition that includes this stopper, such
+    /// as a timeout wrapper around it. It is re-checked periodically while
+    /// parked, so its deadline is honored even during a pause. Returns
+    /// `Err` if this stopper is cancelled or `stop` trips.
+    pub fn wait_if_paused(&self, stop: &impl Stop) -> Result<(), StopReason> {
+        loop {
+            let state = self.inner.state.load(Ordering::SeqCst);
+            if state & CANCELLED != 0 {
+                return Err(StopReason::Cancelled);
+            }
+            if state & PAUSED == 0 {
+                return Ok(());
+            }
+            // `stop` may wrap this stopper; don't re-enter its blocking check
+            self.without_pause(|| stop.check())?;
+            self.park(Some(POLL_INTERVAL));
+        }
+    }
+
+    /// Run `f`, which may call back into this stopper's `check()`, with
+    /// pause-blocking bypassed for the current thread.
+    fn without_pause<R>(&self, f: impl FnOnce() -> R) -> R {
+        BYPASS.with(|bypass| {
+            let addr = Arc::as_ptr(&self.inner) as usize;
+            let previous = bypass.replace(addr);
+            let result = f();
+            bypass.set(previous);
+            result
+        })
+    }
+
+    #[inline]
+    fn bypassed(&self) -> bool {
+        BYPASS.with(|bypass| bypass.get() == Arc::as_ptr(&self.inner) as usize)
+    }
+
+    /// Park until the state changes (or `timeout` elapses).
+    #[cold]
+    fn park(&self, timeout: Option<Duration>) {
+        let guard = self.inner.lock.lock().unwrap_or_else(|e| e.into_inner());
+        // Re-check under the lock so a resume/cancel can't slip in unseen
+        if self.inner.state.load(Ordering::SeqCst) != PAUSED {
+            return;
+        }
+        match timeout {
+            Some(timeout) => drop(self.inner.cond.wait_timeout(guard, timeout)),
+            None => drop(self.inner.cond.wait(guard)),
+        }
+    }
+
+    fn notify(&self) {
+        drop(self.inner.lock.lock().unwrap_or_else(|e| e.into_inner()));
+        self.inner.cond.notify_all();
+    }
+
+    #[cold]
+    fn check_slow(&self) -> Result<(), StopReason> {
+        loop {
+            let state = self.inner.state.load(Ordering::SeqCst);
+            if state & CANCELLED != 0 {
+                return Err(StopReason::Cancelled);
+            }
+            if state & PAUSED == 0 || self.bypassed() {
+                return Ok(());
+            }
+            self.park(None);
+        }
+    }
+}
+
+std::thread_local! {
+    /// Address of the `PauseInner` whose pause is bypassed on this thread.
+    static BYPASS: core::cell::Cell<usize> = const { core::cell::Cell::new(0) };
+}
+
+impl Default for PausableStopper {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Stop for PausableStopper {
+    #[inline]
+    fn check(&self) -> Result<(), StopReason> {
+        if self.inner.state.load(Ordering::Relaxed) == 0 {
+            Ok(())
+        } else {
+            self.check_slow()
+        }
+    }
+
+    #[inline]
+    fn should_stop(&self) -> bool {
+        self.check().is_err()
+    }
+}
+
+impl core::fmt::Debug for PausableStopper {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("PausableStopper")
+            .field("paused", &self.is_paused())
+            .field("cancelled", &self.is_cancelled())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TimeoutExt;
+    use std::sync::atomic::AtomicUsize;
+    use std::thread;
+    use std::time::Instant;
+
+    fn spawn_counter(stop: PausableStopper) -> (Arc<AtomicUsize>, thread::JoinHandle<()>) {
+        let count = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&count);
+        let handle = thread::spawn(move || {
+            while stop.check().is_ok() {
+                counter.fetch_add(1, Ordering::Relaxed);
+                thread::yield_now();
+            }
+        });
+        (count, handle)
+    }
+
+    #[test]
+    fn running_check_does_not_block() {
+        let stop = PausableStopper::new();
+        assert!(stop.check().is_ok());
+        assert!(!stop.should_stop());
+        assert!(!stop.is_paused());
+    }
+
+    #[test]
+    fn pause_parks_and_resume_continues() {
+        let stop = PausableStopper::new();
+        let (count, handle) = spawn_counter(stop.clone());
+
+        stop.pause();
+        thread::sleep(Duration::from_millis(20));
+        let parked_at = count.load(Ordering::Relaxed);
+        thread::sleep(Duration::from_millis(20));
+        // At most one iteration was in flight when the pause landed
+        assert!(count.load(Ordering::Relaxed) <= parked_at + 1);
+
+        stop.resume();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while count.load(Ordering::Relaxed) <= parked_at + 1 && Instant::now() < deadline {
+            thread::yield_now();
+        }
+        assert!(count.load(Ordering::Relaxed) > parked_at + 1);
+
+        stop.cancel();
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn cancel_wakes_paused_workers() {
+        let stop = PausableStopper::new();
+        stop.pause();
+        let workers: Vec<_> = (0..3)
+            .map(|_| {
+                let stop = stop.clone();
+                thread::spawn(move || stop.check())
+            })
+            .collect();
+
+        thread::sleep(Duration::from_millis(10));
+        stop.cancel();
+        for worker in workers {
+            assert_eq!(worker.join().unwrap(), Err(StopReason::Cancelled));
+        }
+        assert!(!stop.is_paused());
+        assert!(stop.is_cancelled());
+    }
+
+    #[test]
+    fn pause_after_cancel_is_ignored() {
+        let stop = PausableStopper::new();
+        stop.cancel();
+        stop.pause();
+        assert!(!stop.is_paused());
+        assert_eq!(stop.check(), Err(StopReason::Cancelled));
+    }
+
+    #[test]
+    fn wait_if_paused_honors_outer_deadline() {
+        let stop = PausableStopper::new();
+        let outer = stop.clone().with_timeout(Duration::from_millis(30));
+        stop.pause();
+
+        let start = Instant::now();
+        assert_eq!(stop.wait_if_paused(&outer), Err(StopReason::TimedOut));
+        assert!(start.elapsed() < Duration::from_secs(5));
+        // Still paused afterwards
+        assert!(stop.is_paused());
+    }
+
+    #[test]
+    fn wait_if_paused_returns_on_resume() {
+        let stop = PausableStopper::new();
+        stop.pause();
+        let resumer = stop.clone();
+        let handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(10));
+            resumer.resume();
+        });
+        assert_eq!(stop.wait_if_paused(&crate::Unstoppable), Ok(()));
+        handle.join().unwrap();
+
+        // Not paused: returns immediately
+        assert_eq!(stop.wait_if_paused(&stop), Ok(()));
+    }
+
+    #[test]
+    fn debug_and_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<PausableStopper>();
+
+        let stop = PausableStopper::default();
+        stop.pause();
+        let debug = format!("{stop:?}");
+        assert!(debug.contains("paused: true"));
+    }
+}
@@ -0,0 +1,326 @@
+//! Killing a child process when a stop fires.
+
+use std::io;
+use std::process::{Child, Command, ExitStatus};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock, PoisonError};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::time::sleep_until_cancelled;
+use crate::{Stop, StopReason};
+
+/// The first pause between exit polls; doubled after each poll up to
+/// [`POLL_LIMIT`].
+const FIRST_POLL: Duration = Duration::from_micros(50);
+
+/// The longest pause between exit polls.
+const POLL_LIMIT: Duration = Duration::from_millis(10);
+
+/// A child process that is killed when a stop fires.
+///
+/// External tools cannot check a stop. Pipelines that call out to them
+/// end up blocking in [`Child::wait()`] while the job they belong to is
+/// cancelled. `ProcessStop` takes the [`Child`] and a stop, and a watcher
+/// thread kills the process as soon as the stop fires: immediately for a
+/// [`Stopper`](crate::Stopper), which wakes the watcher, and within 10ms
+/// for other stops. The watcher exits with the process.
+///
+/// [`wait()`](Self::wait) then returns the killed process's status, and
+/// [`killed()`](Self::killed) reports the stop reason that killed it.
+///
+/// On unix, [`spawn_group()`](Self::spawn_group) starts the process in its
+/// own process group and kills the whole group, so helpers the tool starts
+/// itself go too.
+///
+/// Like [`Child`], dropping a `ProcessStop` neither kills nor waits for the
+/// process; the watcher stops watching.
+///
+/// # Example
+///
+/// ```rust
+/// # #[cfg(unix)] {
+/// use almost_enough::{ProcessStop, StopReason, Stopper};
+/// use std::process::Command;
+///
+/// let stop = Stopper::new();
+/// let mut convert = ProcessStop::spawn(stop.clone(), Command::new("sleep").arg("60"))?;
+///
+/// stop.cancel(); // e.g. the request was aborted
+/// let status = convert.wait()?;
+/// assert!(!status.success());
+/// assert_eq!(convert.killed(), Some(StopReason::Cancelled));
+/// # }
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct ProcessStop {
+    shared: Arc<Shared>,
+    watcher: Option<JoinHandle<()>>,
+}
+
+/// State shared with the watcher thread.
+struct Shared {
+    child: Mutex<Child>,
+    /// Kill the child's process group, not just the child.
+    group: bool,
+    /// Set by `Drop` to end the watcher.
+    detached: AtomicBool,
+    /// The reason the watcher killed the process.
+    killed: OnceLock<StopReason>,
+}
+
+impl ProcessStop {
+    /// Kill `child` once `stop` fires.
+    ///
+    /// Spawns the watcher thread. If `stop` has already fired, the child
+    /// is killed right away.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the watcher thread cannot be spawned, after killing the
+    /// child. [`spawn()`](Self::spawn) returns that error instead.
+    pub fn new<S: Stop + Send + 'static>(stop: S, child: Child) -> Self {
+        Self::watch(stop, child, false).expect("failed to spawn process watcher thread")
+    }
+
+    /// Spawn `command` and kill the process once `stop` fires.
+    ///
+    /// Returns the error from [`Command::spawn()`] if the process cannot be
+    /// started, or from spawning the watcher thread (the process is then
+    /// killed).
+    pub fn spawn<S: Stop + Send + 'static>(stop: S, command: &mut Command) -> io::Result<Self> {
+        Self::watch(stop, command.spawn()?, false)
+    }
+
+    /// Spawn `command` as the leader of a new process group, and kill the
+    /// whole group once `stop` fires.
+    ///
+    /// The group is killed with the `kill` utility, as std offers no safe
+    /// way to signal a group; the child itself is then killed directly, so
+    /// it goes even if `kill` is missing. Errors as [`spawn()`](Self::spawn).
+    #[cfg(unix)]
+    pub fn spawn_group<S: Stop + Send + 'static>(
+        stop: S,
+        command: &mut Command,
+    ) -> io::Result<Self> {
+        use std::os::unix::process::CommandExt;
+
+        Self::watch(stop, command.process_group(0).spawn()?, true)
+    }
+
+    fn watch<S: Stop + Send + 'static>(stop: S, child: Child, group: bool) -> io::Result<Self> {
+        let shared = Arc::new(Shared {
+            child: Mutex::new(child),
+            group,
+            detached: AtomicBool::new(false),
+            killed: OnceLock::new(),
+        });
+        let watched = Arc::clone(&shared);
+        let watcher = thread::Builder::new()
+            .name("process-stop-watcher".into())
+            .spawn(move || watched.run(&stop))
+            .inspect_err(|_| {
+                // Unwatched, the process could not be stopped
+                let _ = shared.child().kill();
+            })?;
+        Ok(Self {
+            shared,
+            watcher: Some(watcher),
+        })
+    }
+
+    /// The OS process ID.
+    pub fn id(&self) -> u32 {
+        self.shared.child().id()
+    }
+
+    /// Wait for the process to exit, returning its status.
+    ///
+    /// Returns early only in the sense that a stop that fires meanwhile
+    /// kills the process; the status is then that of the killed process.
+    pub fn wait(&mut self) -> io::Result<ExitStatus> {
+        if let Some(watcher) = self.watcher.take() {
+            // The watcher ends once the process exits or is killed
+            let _ = watcher.join();
+        }
+        self.shared.child().wait()
+    }
+
+    /// The process's status if it has exited, without blocking.
+    pub fn try_wait(&self) -> io::Result<Option<ExitStatus>> {
+        self.shared.child().try_wait()
+    }
+
+    /// The reason of the stop that killed the process, or `None` if it has
+    /// not been killed.
+    pub fn killed(&self) -> Option<StopReason> {
+        self.shared.killed.get().copied()
+    }
+}
+
+impl Shared {
+    fn child(&self) -> MutexGuard<'_, Child> {
+        self.child.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// The watcher: poll for exit with a growing pause, and kill on stop.
+    fn run<S: Stop + 'static>(&self, stop: &S) {
+        let mut pause = FIRST_POLL;
+        while !self.detached.load(Ordering::Relaxed) {
+            {
+                let mut child = self.child();
+                if !matches!(child.try_wait(), Ok(None)) {
+                    return;
+                }
+                if let Err(reason) = stop.check() {
+                    self.kill(&mut child, reason);
+                    return;
+                }
+            }
+            // A Stopper wakes this as soon as it is cancelled
+            let _ = sleep_until_cancelled(stop, pause);
+            pause = pause.saturating_mul(2).min(POLL_LIMIT);
+        }
+    }
+
+    fn kill(&self, child: &mut Child, reason: StopReason) {
+        #[cfg(unix)]
+        if self.group {
+            // A negative PID names the group the child leads
+            let _ = Command::new("kill")
+                .arg("-KILL")
+                .arg("--")
+                .arg(format!("-{}", child.id()))
+                .stdout(std::process::Stdio::null())
+                .stderr(std::process::Stdio::null())
+                .status();
+        }
+        let _ = self.killed.set(reason);
+        let _ = child.kill();
+    }
+}
+
+impl Drop for ProcessStop {
+    fn drop(&mut self) {
+        self.shared.detached.store(true, Ordering::Relaxed);
+    }
+}
+
+impl core::fmt::Debug for ProcessStop {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ProcessStop")
+            .field("id", &self.id())
+            .field("group", &self.shared.group)
+            .field("killed", &self.killed())
+            .finish()
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::{Stopper, TimeoutExt, Unstoppable};
+    use std::time::Instant;
+
+    fn sleep_60() -> Command {
+        let mut command = Command::new("sleep");
+        command.arg("60");
+        command
+    }
+
+    #[test]
+    fn exit_without_stop() {
+        let mut process = ProcessStop::spawn(Unstoppable, &mut Command::new("true")).unwrap();
+        assert!(process.wait().unwrap().success());
+        assert_eq!(process.killed(), None);
+        assert!(process.try_wait().unwrap().is_some());
+    }
+
+    #[test]
+    fn cancel_kills() {
+        let stop = Stopper::new();
+        let mut process = ProcessStop::spawn(stop.clone(), &mut sleep_60()).unwrap();
+        assert!(process.try_wait().unwrap().is_none());
+        let start = Instant::now();
+        stop.cancel();
+        assert!(!process.wait().unwrap().success());
+        assert!(start.elapsed() < Duration::from_secs(30));
+        assert_eq!(process.killed(), Some(StopReason::Cancelled));
+        assert!(format!("{process:?}").contains("Cancelled"));
+    }
+
+    #[test]
+    fn timeout_kills() {
+        let stop = Stopper::new().with_timeout(Duration::from_millis(20));
+        let mut process = ProcessStop::spawn(stop, &mut sleep_60()).unwrap();
+        assert!(!process.wait().unwrap().success());
+        assert_eq!(process.killed(), Some(StopReason::TimedOut));
+    }
+
+    #[test]
+    fn already_stopped_kills_at_once() {
+        let child = sleep_60().spawn().unwrap();
+        let mut process = ProcessStop::new(Stopper::cancelled(), child);
+        assert!(!process.wait().unwrap().success());
+        assert_eq!(process.killed(), Some(StopReason::Cancelled));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn group_kill_reaches_grandchildren() {
+        let stop = Stopper::new();
+        let mut shell = Command::new("sh");
+        // The shell prints the grandchild's PID, then waits for it
+        shell
+            .args(["-c", "sleep 60 & echo $!; wait"])
+            .stdout(std::process::Stdio::piped());
+        let mut process = ProcessStop::spawn_group(stop.clone(), &mut shell).unwrap();
+        let mut line = String::new();
+        {
+            use std::io::BufRead;
+            let stdout = process.shared.child().stdout.take().unwrap();
+            io::BufReader::new(stdout).read_line(&mut line).unwrap();
+        }
+        let grandchild = line.trim().to_string();
+
+        stop.cancel();
+        assert!(!process.wait().unwrap().success());
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while alive(&grandchild) {
+            assert!(Instant::now() < deadline, "grandchild survived");
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    /// Whether `pid` is running; a zombie left for a non-reaping init
+    /// counts as gone.
+    #[cfg(target_os = "linux")]
+    fn alive(pid: &str) -> bool {
+        std::fs::read_to_string(format!("/proc/{pid}/stat")).is_ok_and(|stat| {
+            stat.rsplit(')')
+                .next()
+                .is_some_and(|s| !s.starts_with(" Z"))
+        })
+    }
+
+    #[test]
+    fn drop_detaches() {
+        let stop = Stopper::new();
+        let process = ProcessStop::spawn(stop.clone(), &mut sleep_60()).unwrap();
+        let id = process.id();
+        drop(process);
+        stop.cancel();
+        thread::sleep(Duration::from_millis(50));
+        // Still running: dropping detached the watcher
+        assert!(
+            Command::new("kill")
+                .args(["-0", &id.to_string()])
+                .status()
+                .unwrap()
+                .success()
+        );
+        let _ = Command::new("kill")
+            .args(["-KILL", &id.to_string()])
+            .status();
+    }
+}
@@ -0,0 +1,280 @@
+//! Hierarchical cancellation with eager propagation.
+//!
+//! [`PropagatingStopper`] is an alternative to [`ChildStopper`] for deep
+//! trees checked in hot loops. `ChildStopper::check()` walks the parent
+//! chain on every call; `PropagatingStopper` instead pushes cancellation
+//! down to every descendant when [`cancel()`](PropagatingStopper::cancel) is
+//! called, so `check()` is a single relaxed load at any depth.
+//!
+//! The trade-off is on the cold side: creating a child takes a lock on its
+//! parent, and cancelling visits every live descendant.
+//!
+//! # Example
+//!
+//! ```rust
+//! use almost_enough::{PropagatingStopper, Stop};
+//!
+//! let request = PropagatingStopper::new();
+//! let decode = request.child();
+//! let row = decode.child().child().child();
+//!
+//! request.cancel();
+//! assert!(decode.should_stop());
+//! assert!(row.should_stop()); // one load, no parent walk
+//! ```
+//!
+//! [`ChildStopper`]: crate::ChildStopper
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::vec::Vec;
+
+use crate::{Stop, StopReason};
+
+struct PropagatingInner {
+    cancelled: AtomicBool,
+    /// Children registered for eager cancellation.
+    children: Mutex<Vec<Weak<PropagatingInner>>>,
+    /// Keeps intermediate nodes alive so propagation reaches the leaves.
+    /// Never read on the check path.
+    _parent: Option<Arc<PropagatingInner>>,
+}
+
+impl PropagatingInner {
+    fn new(cancelled: bool, parent: Option<Arc<PropagatingInner>>) -> Self {
+        Self {
+            cancelled: AtomicBool::new(cancelled),
+            children: Mutex::new(Vec::new()),
+            _parent: parent,
+        }
+    }
+}
+
+/// A tree-structured stopper whose `check()` is O(1) at any depth.
+///
+/// Like [`ChildStopper`](crate::ChildStopper): clone to share, any clone
+/// can cancel, and cancelling a node cancels its descendants but not its
+/// parent or siblings. Unlike `ChildStopper`, the parent is not consulted
+/// on `check()` — cancellation is written into each descendant when it
+/// happens.
+///
+/// Each child keeps its parent alive (as `ChildStopper` does), so an
+/// ancestor can be cancelled through any handle to it even when the
+/// intermediate nodes have no other owners.
+///
+/// Requires the `std` feature.
+///
+/// # Example
+///
+/// ```rust
+/// use almost_enough::{PropagatingStopper, Stop};
+///
+/// let parent = PropagatingStopper::new();
+/// let child_a = parent.child();
+/// let child_b = parent.child();
+///
+/// child_a.cancel();
+/// assert!(child_a.should_stop());
+/// assert!(!child_b.should_stop());
+/// assert!(!parent.should_stop());
+///
+/// parent.cancel();
+/// assert!(child_b.should_stop());
+/// ```
+///
+/// # Performance
+///
+/// - Size: 8 bytes (one pointer)
+/// - `check()`: one relaxed atomic load, independent of depth
+/// - `child()`: one lock on the parent
+/// - `cancel()`: visits every live descendant once
+#[derive(Clone)]
+pub struct PropagatingStopper {
+    inner: Arc<PropagatingInner>,
+}
+
+impl PropagatingStopper {
+    /// Create a new root.
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(PropagatingInner::new(false, None)),
+        }
+    }
+
+    /// Create a child of this node.
+    ///
+    /// A child of an already-cancelled node starts cancelled.
+    pub fn child(&self) -> Self {
+        let mut children = self.lock_children();
+        if self.inner.cancelled.load(Ordering::Relaxed) {
+            return Self {
+                inner: Arc::new(PropagatingInner::new(true, None)),
+            };
+        }
+        let child = Arc::new(PropagatingInner::new(false, Some(Arc::clone(&self.inner))));
+        // Amortized pruning of dropped children
+        if children.len() == children.capacity() {
+            children.retain(|c| c.strong_count() > 0);
+        }
+        children.push(Arc::downgrade(&child));
+        Self { inner: child }
+    }
+
+    /// Cancel this node and all of its descendants.
+    ///
+    /// Does not affect the parent or siblings. Idempotent.
+    pub fn cancel(&self) {
+        // Iterative to avoid recursion depth limits on deep trees
+        let mut pending = Vec::new();
+        Self::cancel_node(&self.inner, &mut pending);
+        while let Some(node) = pending.pop() {
+            Self::cancel_node(&node, &mut pending);
+        }
+    }
+
+    fn cancel_node(node: &PropagatingInner, pending: &mut Vec<Arc<PropagatingInner>>) {
+        if node.cancelled.swap(true, Ordering::Relaxed) {
+            // Already cancelled, so its descendants were too
+            return;
+        }
+        // `child()` reads the flag under this lock, so no child is missed
+        let children =
+            core::mem::take(&mut *node.children.lock().unwrap_or_else(|e| e.into_inner()));
+        pending.extend(children.iter().filter_map(Weak::upgrade));
+    }
+
+    /// Check if this node has been cancelled.
+    #[inline]
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::Relaxed)
+    }
+
+    fn lock_children(&self) -> std::sync::MutexGuard<'_, Vec<Weak<PropagatingInner>>> {
+        self.inner
+            .children
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Default for PropagatingStopper {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Stop for PropagatingStopper {
+    #[inline]
+    fn check(&self) -> Result<(), StopReason> {
+        if self.inner.cancelled.load(Ordering::Relaxed) {
+            Err(StopReason::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+
+    #[inline]
+    fn should_stop(&self) -> bool {
+        self.is_cancelled()
+    }
+}
+
+impl core::fmt::Debug for PropagatingStopper {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("PropagatingStopper")
+            .field("is_cancelled", &self.is_cancelled())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn cancel_reaches_deep_descendants() {
+        let root = PropagatingStopper::new();
+        let mut leaf = root.child();
+        for _ in 0..1_000 {
+            leaf = leaf.child();
+        }
+        assert!(!leaf.should_stop());
+
+        root.cancel();
+        assert_eq!(leaf.check(), Err(StopReason::Cancelled));
+    }
+
+    #[test]
+    fn cancel_does_not_reach_parent_or_siblings() {
+        let root = PropagatingStopper::new();
+        let a = root.child();
+        let b = root.child();
+        let a_child = a.child();
+
+        a.cancel();
+        assert!(a_child.is_cancelled());
+        assert!(!b.is_cancelled());
+        assert!(!root.is_cancelled());
+    }
+
+    #[test]
+    fn child_of_cancelled_starts_cancelled() {
+        let root = PropagatingStopper::new();
+        root.cancel();
+        assert!(root.child().is_cancelled());
+    }
+
+    #[test]
+    fn clones_share_state() {
+        let a = PropagatingStopper::new();
+        let child = a.clone().child();
+        a.clone().cancel();
+        assert!(a.is_cancelled());
+        assert!(child.is_cancelled());
+    }
+
+    #[test]
+    fn dropped_children_are_pruned() {
+        let root = PropagatingStopper::new();
+        for _ in 0..1000 {
+            drop(root.child());
+        }
+        let live = root.child();
+        assert!(root.lock_children().len() <= 64);
+        root.cancel();
+        assert!(live.is_cancelled());
+    }
+
+    #[test]
+    fn dropped_intermediate_handles_keep_propagating() {
+        let root = PropagatingStopper::new();
+        let leaf = root.child().child().child();
+        assert!(!leaf.is_cancelled());
+        root.cancel();
+        assert!(leaf.is_cancelled());
+    }
+
+    #[test]
+    fn concurrent_child_creation_and_cancel() {
+        for _ in 0..50 {
+            let root = PropagatingStopper::new();
+            let spawner = {
+                let root = root.clone();
+                thread::spawn(move || (0..100).map(|_| root.child()).collect::<Vec<_>>())
+            };
+            root.cancel();
+            let children = spawner.join().unwrap();
+            assert!(children.iter().all(PropagatingStopper::is_cancelled));
+        }
+    }
+
+    #[test]
+    fn debug_and_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<PropagatingStopper>();
+        let debug = format!("{:?}", PropagatingStopper::default());
+        assert!(debug.contains("PropagatingStopper"));
+    }
+}
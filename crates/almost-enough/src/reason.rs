@@ -0,0 +1,26 @@
+//! Compact `StopReason` encoding for latching a reason in an `AtomicU8`.
+
+use crate::StopReason;
+
+pub(crate) const NOT_STOPPED: u8 = 0;
+pub(crate) const CANCELLED: u8 = 1;
+pub(crate) const TIMED_OUT: u8 = 2;
+pub(crate) const FAILED: u8 = 3;
+
+#[inline]
+pub(crate) const fn encode(reason: StopReason) -> u8 {
+    match reason {
+        StopReason::TimedOut => TIMED_OUT,
+        StopReason::Failed => FAILED,
+        _ => CANCELLED,
+    }
+}
+
+#[inline]
+pub(crate) const fn decode(state: u8) -> StopReason {
+    match state {
+        TIMED_OUT => StopReason::TimedOut,
+        FAILED => StopReason::Failed,
+        _ => StopReason::Cancelled,
+    }
+}
@@ -0,0 +1,296 @@
+//! Stoppers looked up by stable string IDs.
+//!
+//! An application that hot-reloads plugins cannot keep cancellation state
+//! in the plugin: the `Arc`s and statics of the old code are gone once it
+//! is unloaded, and the reloaded code has no way to find the flag a job was
+//! started with. A [`StopRegistry`] maps string IDs such as
+//! `"jobs/export/42"` to [`Stopper`]s. A slot stays in the registry until
+//! it is [`remove()`](StopRegistry::remove)d, even while no handle to it is
+//! alive, so the old and the new code both reach the same flag by ID.
+//!
+//! # Reloading
+//!
+//! Statics are per copy of this crate, and a reloaded library gets a fresh
+//! copy. The registry therefore has to live on the side that is *not*
+//! reloaded: the host uses [`StopRegistry::global()`] (or its own
+//! registry) and hands a clone to each plugin as it is loaded. Host and
+//! plugins must be built against the same build of `almost-enough`, as
+//! with any Rust types passed across a reload boundary; for a stable ABI,
+//! use the handle table in `enough-ffi` instead.
+//!
+//! # Cancelling requests by ID
+//!
+//! A server can key slots by request or job ID: the handler
+//! [`register()`](StopRegistry::register)s its ID and passes the returned
+//! token down, and an admin endpoint that only knows the ID calls
+//! [`cancel()`](StopRegistry::cancel) without a token ever reaching it.
+//! Lookups and cancellations share a read lock, so they run concurrently
+//! with each other and with in-flight handlers; only creating and removing
+//! slots takes the write lock.
+//!
+//! ```rust
+//! use almost_enough::{Stop, StopRegistry};
+//!
+//! let registry = StopRegistry::new();
+//! let request = registry.register("req-17");
+//!
+//! // Admin endpoint
+//! assert!(registry.cancel("req-17"));
+//! assert!(!registry.cancel("req-18"));
+//! assert!(request.should_stop());
+//!
+//! // Handler done
+//! registry.remove("req-17");
+//! ```
+//!
+//! Requires the `global` feature.
+//!
+//! # Example
+//!
+//! ```rust
+//! use almost_enough::{Stop, StopRegistry};
+//!
+//! let registry = StopRegistry::new();
+//!
+//! // Old plugin code starts a job, then is unloaded
+//! let job = registry.acquire("jobs/export/42");
+//! drop(job);
+//!
+//! // The reloaded code finds the same slot
+//! let job = registry.acquire("jobs/export/42");
+//! registry.cancel_prefix("jobs/export/");
+//! assert!(job.should_stop());
+//! ```
+
+use std::collections::BTreeMap;
+use std::ops::Bound;
+use std::sync::{Arc, OnceLock, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use crate::{ChildStopper, StopExt, Stopper};
+
+static GLOBAL: OnceLock<StopRegistry> = OnceLock::new();
+
+/// A map from string IDs to [`Stopper`]s; see the [module docs](self).
+///
+/// Cloning shares the registry.
+#[derive(Debug, Clone, Default)]
+pub struct StopRegistry {
+    slots: Arc<RwLock<BTreeMap<Box<str>, Stopper>>>,
+}
+
+impl StopRegistry {
+    /// Create an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The process-wide registry of this copy of the crate, created on
+    /// first use.
+    ///
+    /// Used by the free [`acquire()`] function.
+    #[inline]
+    pub fn global() -> &'static StopRegistry {
+        GLOBAL.get_or_init(StopRegistry::new)
+    }
+
+    fn slots(&self) -> RwLockReadGuard<'_, BTreeMap<Box<str>, Stopper>> {
+        self.slots.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn slots_mut(&self) -> RwLockWriteGuard<'_, BTreeMap<Box<str>, Stopper>> {
+        self.slots.write().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// The stopper in slot `id`, creating the slot if it is empty.
+    ///
+    /// Every call with the same `id` returns a clone of the same stopper
+    /// until the slot is removed.
+    pub fn acquire(&self, id: &str) -> Stopper {
+        if let Some(stopper) = self.get(id) {
+            return stopper;
+        }
+        // Another thread may have created the slot in between
+        self.slots_mut().entry(id.into()).or_default().clone()
+    }
+
+    /// A child of the stopper in slot `id`, creating the slot if it is
+    /// empty.
+    ///
+    /// Cancelling the slot stops the child; cancelling the child does not
+    /// touch the slot, so a handler can abandon its own work without
+    /// affecting other holders of the ID.
+    pub fn register(&self, id: &str) -> ChildStopper {
+        self.acquire(id).child()
+    }
+
+    /// The stopper in slot `id`, without creating one.
+    pub fn get(&self, id: &str) -> Option<Stopper> {
+        self.slots().get(id).cloned()
+    }
+
+    /// Empty slot `id` and return its stopper.
+    ///
+    /// Existing handles keep working; the next [`acquire()`](Self::acquire)
+    /// of `id` creates a fresh, uncancelled stopper.
+    pub fn remove(&self, id: &str) -> Option<Stopper> {
+        self.slots_mut().remove(id)
+    }
+
+    /// Cancel slot `id`, returning `false` if there is no such slot.
+    ///
+    /// The slot stays in the registry, so later
+    /// [`acquire()`](Self::acquire)s of `id` see the cancellation.
+    pub fn cancel(&self, id: &str) -> bool {
+        self.slots().get(id).map(Stopper::cancel).is_some()
+    }
+
+    /// Cancel every slot, returning how many there were.
+    pub fn cancel_all(&self) -> usize {
+        self.cancel_prefix("")
+    }
+
+    /// Cancel every slot whose ID starts with `prefix`, returning how many
+    /// matched.
+    ///
+    /// With path-like IDs, `cancel_prefix("jobs/export/")` cancels all
+    /// export jobs; `cancel_prefix("")` cancels everything.
+    pub fn cancel_prefix(&self, prefix: &str) -> usize {
+        let slots = self.slots();
+        let matching = slots
+            .range::<str, _>((Bound::Included(prefix), Bound::Unbounded))
+            .take_while(|(id, _)| id.starts_with(prefix));
+        let mut count = 0;
+        for (_, stopper) in matching {
+            stopper.cancel();
+            count += 1;
+        }
+        count
+    }
+
+    /// The IDs of all slots, in order.
+    pub fn ids(&self) -> Vec<String> {
+        self.slots().keys().map(|id| id.to_string()).collect()
+    }
+
+    /// The number of slots.
+    pub fn len(&self) -> usize {
+        self.slots().len()
+    }
+
+    /// Returns `true` if the registry has no slots.
+    pub fn is_empty(&self) -> bool {
+        self.slots().is_empty()
+    }
+}
+
+/// [`StopRegistry::global()`]`.acquire(id)`.
+///
+/// Only reload-safe in the code that is not reloaded; see the
+/// [module docs](self#reloading).
+#[inline]
+pub fn acquire(id: &str) -> Stopper {
+    StopRegistry::global().acquire(id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Stop, StopReason};
+
+    #[test]
+    fn slot_outlives_handles() {
+        let registry = StopRegistry::new();
+        let plugin = registry.clone();
+        plugin.acquire("jobs/1").cancel();
+        assert_eq!(registry.len(), 1);
+
+        // A later plugin generation sees the cancellation
+        let reloaded = registry.clone();
+        assert_eq!(
+            reloaded.acquire("jobs/1").check(),
+            Err(StopReason::Cancelled)
+        );
+        assert!(reloaded.acquire("jobs/2").check().is_ok());
+    }
+
+    #[test]
+    fn remove_frees_the_slot() {
+        let registry = StopRegistry::new();
+        let old = registry.acquire("a");
+        old.cancel();
+        assert!(registry.remove("a").is_some());
+        assert!(registry.get("a").is_none());
+        assert!(registry.is_empty());
+
+        assert!(!registry.acquire("a").should_stop());
+        assert!(old.should_stop());
+    }
+
+    #[test]
+    fn cancel_prefix_matches_exactly() {
+        let registry = StopRegistry::new();
+        for id in [
+            "jobs/export/1",
+            "jobs/export/2",
+            "jobs/exporter",
+            "jobs/import/1",
+        ] {
+            registry.acquire(id);
+        }
+        assert_eq!(registry.cancel_prefix("jobs/export/"), 2);
+        let cancelled: Vec<_> = registry
+            .ids()
+            .into_iter()
+            .filter(|id| registry.get(id).unwrap().should_stop())
+            .collect();
+        assert_eq!(cancelled, ["jobs/export/1", "jobs/export/2"]);
+        assert_eq!(registry.cancel_prefix(""), 4);
+    }
+
+    #[test]
+    fn cancel_by_id() {
+        let registry = StopRegistry::new();
+        let first = registry.register("req/1");
+        let second = registry.register("req/2");
+
+        // Cancelling a child leaves the slot alone
+        let abandoned = registry.register("req/2");
+        abandoned.cancel();
+        assert!(!second.should_stop());
+
+        assert!(registry.cancel("req/1"));
+        assert!(!registry.cancel("req/3"));
+        assert!(registry.get("req/3").is_none());
+        assert!(first.should_stop());
+        assert!(!second.should_stop());
+        assert!(registry.register("req/1").should_stop());
+
+        assert_eq!(registry.cancel_all(), 2);
+        assert!(second.should_stop());
+    }
+
+    #[test]
+    fn concurrent_acquire_shares_slot() {
+        let registry = StopRegistry::new();
+        let stoppers: Vec<Stopper> = std::thread::scope(|s| {
+            let handles: Vec<_> = (0..8)
+                .map(|_| s.spawn(|| registry.acquire("shared")))
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        assert_eq!(registry.len(), 1);
+        registry.cancel("shared");
+        assert!(stoppers.iter().all(Stop::should_stop));
+    }
+
+    #[test]
+    fn global_registry() {
+        let stop = acquire("registry::tests::global_registry");
+        assert!(std::ptr::eq(StopRegistry::global(), StopRegistry::global()));
+        let again = StopRegistry::global()
+            .get("registry::tests::global_registry")
+            .unwrap();
+        stop.cancel();
+        assert!(again.should_stop());
+    }
+}
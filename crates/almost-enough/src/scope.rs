@@ -0,0 +1,271 @@
+//! Structured concurrency with shared cancellation.
+//!
+//! [`stop_scope()`] is the cancellation analogue of [`std::thread::scope`]:
+//! every thread spawned in the scope shares one [`ChildStopper`], the first
+//! task to fail (by returning `Err` or panicking) cancels all the others, and
+//! the scope joins every thread before returning.
+//!
+//! # Example
+//!
+//! ```rust
+//! use almost_enough::{stop_scope, Stop, Stopper};
+//!
+//! let request = Stopper::new();
+//! let chunks = [1, 2, 3, 4];
+//!
+//! let result = stop_scope(request.clone(), |s| {
+//!     for &chunk in &chunks {
+//!         s.spawn(move |stop| {
+//!             stop.check().map_err(|_| "cancelled")?;
+//!             if chunk == 3 {
+//!                 return Err("bad chunk");
+//!             }
+//!             Ok(())
+//!         });
+//!     }
+//! });
+//!
+//! assert_eq!(result, Err("bad chunk"));
+//! assert!(!request.is_cancelled()); // the parent is never cancelled by the scope
+//! ```
+
+use std::any::Any;
+use std::panic::{AssertUnwindSafe, catch_unwind, resume_unwind};
+use std::sync::{Arc, Mutex};
+use std::thread::Scope;
+
+use crate::{ChildStopper, Stop, StopDropRoll};
+
+/// The first failure observed in a scope.
+enum Failure<E> {
+    Error(E),
+    Panic(Box<dyn Any + Send>),
+}
+
+/// Handle for spawning cancellation-linked threads inside [`stop_scope()`].
+///
+/// Every task receives the scope's [`ChildStopper`]. It is cancelled when
+/// the parent stop is cancelled or when any task in the scope fails.
+pub struct StopScope<'scope, 'env, E> {
+    scope: &'scope Scope<'scope, 'env>,
+    stop: ChildStopper,
+    failure: Arc<Mutex<Option<Failure<E>>>>,
+}
+
+impl<'scope, E: Send + 'scope> StopScope<'scope, '_, E> {
+    /// Spawn a scoped thread that shares this scope's stop.
+    ///
+    /// If `task` returns `Err` or panics, the scope's stop is cancelled so
+    /// the remaining tasks wind down. Only the first failure is kept.
+    pub fn spawn<F>(&self, task: F)
+    where
+        F: FnOnce(&ChildStopper) -> Result<(), E> + Send + 'scope,
+    {
+        let stop = self.stop.clone();
+        let failure = Arc::clone(&self.failure);
+        self.scope.spawn(move || {
+            let guard = stop.clone().stop_on_drop();
+            let failed = match catch_unwind(AssertUnwindSafe(|| task(&stop))) {
+                Ok(Ok(())) => None,
+                Ok(Err(e)) => Some(Failure::Error(e)),
+                Err(payload) => Some(Failure::Panic(payload)),
+            };
+            match failed {
+                None => guard.disarm(),
+                Some(failed) => {
+                    let mut slot = failure.lock().unwrap_or_else(|e| e.into_inner());
+                    if slot.is_none() {
+                        *slot = Some(failed);
+                    }
+                    // Dropping the guard cancels the scope
+                }
+            }
+        });
+    }
+
+    /// The stop shared by every task in this scope.
+    #[inline]
+    pub fn stop(&self) -> &ChildStopper {
+        &self.stop
+    }
+}
+
+impl<E> core::fmt::Debug for StopScope<'_, '_, E> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("StopScope")
+            .field("stop", &self.stop)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Run `f` with a [`StopScope`] whose stop is a child of `parent`.
+///
+/// All threads spawned through the scope are joined before this returns.
+/// Returns `Ok` with `f`'s result if every task succeeded, or the first
+/// task error. If a task panicked (and no error came first), the panic is
+/// resumed on the calling thread after all tasks have been joined.
+///
+/// Cancelling `parent` cancels the scope; the scope never cancels `parent`.
+/// Pass [`Unstoppable`](crate::Unstoppable) for a scope with no parent.
+///
+/// # Example
+///
+/// ```rust
+/// use almost_enough::{stop_scope, Stop, Unstoppable};
+///
+/// let result: Result<u32, ()> = stop_scope(Unstoppable, |s| {
+///     s.spawn(|stop| {
+///         while !stop.should_stop() {
+///             // Keeps running until a sibling fails...
+///             break;
+///         }
+///         Ok(())
+///     });
+///     42
+/// });
+/// assert_eq!(result, Ok(42));
+/// ```
+pub fn stop_scope<'env, P, E, F, R>(parent: P, f: F) -> Result<R, E>
+where
+    P: Stop + 'static,
+    E: Send,
+    F: for<'scope> FnOnce(&StopScope<'scope, 'env, E>) -> R,
+{
+    let stop = ChildStopper::with_parent(parent);
+    let failure = Arc::new(Mutex::new(None));
+    let result = std::thread::scope(|scope| {
+        let handle = StopScope {
+            scope,
+            stop: stop.clone(),
+            failure: Arc::clone(&failure),
+        };
+        // If `f` itself panics, cancel the tasks so the implicit join is quick
+        let guard = stop.clone().stop_on_drop();
+        let result = f(&handle);
+        guard.disarm();
+        result
+    });
+    let failure = failure.lock().unwrap_or_else(|e| e.into_inner()).take();
+    match failure {
+        None => Ok(result),
+        Some(Failure::Error(e)) => Err(e),
+        Some(Failure::Panic(payload)) => resume_unwind(payload),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{StopReason, Stopper, Unstoppable};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::{Duration, Instant};
+
+    /// Spin until cancelled, with a safety deadline.
+    fn wait_for_cancel(stop: &ChildStopper) -> Result<(), StopReason> {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while Instant::now() < deadline {
+            stop.check()?;
+            std::thread::yield_now();
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn all_succeed() {
+        let counter = AtomicUsize::new(0);
+        let result: Result<&str, ()> = stop_scope(Unstoppable, |s| {
+            for _ in 0..4 {
+                s.spawn(|_| {
+                    counter.fetch_add(1, Ordering::Relaxed);
+                    Ok(())
+                });
+            }
+            "done"
+        });
+        assert_eq!(result, Ok("done"));
+        assert_eq!(counter.load(Ordering::Relaxed), 4);
+    }
+
+    #[test]
+    fn error_cancels_siblings() {
+        let result = stop_scope(Unstoppable, |s| {
+            for _ in 0..3 {
+                s.spawn(|stop| wait_for_cancel(stop).map_err(|_| "sibling cancelled"));
+            }
+            s.spawn(|_| Err("boom"));
+        });
+        // The first failure wins; siblings only fail after it cancels them
+        assert_eq!(result, Err("boom"));
+    }
+
+    #[test]
+    fn panic_cancels_siblings_and_resumes() {
+        let hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(|_| {}));
+        let outcome = catch_unwind(|| {
+            stop_scope(Unstoppable, |s| {
+                s.spawn(|stop| wait_for_cancel(stop).map_err(|_| ()));
+                s.spawn(|_| -> Result<(), ()> { panic!("task panicked") });
+            })
+        });
+        std::panic::set_hook(hook);
+
+        let payload = outcome.unwrap_err();
+        assert_eq!(payload.downcast_ref::<&str>(), Some(&"task panicked"));
+    }
+
+    #[test]
+    fn parent_cancellation_propagates() {
+        let parent = Stopper::new();
+        let result = stop_scope(parent.clone(), |s| {
+            s.spawn(wait_for_cancel);
+            parent.cancel();
+        });
+        assert_eq!(result, Err(StopReason::Cancelled));
+    }
+
+    #[test]
+    fn scope_never_cancels_parent() {
+        let parent = Stopper::new();
+        let result = stop_scope(parent.clone(), |s| {
+            s.spawn(|_| Err(()));
+        });
+        assert_eq!(result, Err(()));
+        assert!(!parent.is_cancelled());
+    }
+
+    #[test]
+    fn scope_stop_is_shared() {
+        let result: Result<(), ()> = stop_scope(Unstoppable, |s| {
+            let outer = s.stop().clone();
+            s.spawn(move |stop| {
+                outer.cancel();
+                assert!(stop.should_stop());
+                Ok(())
+            });
+        });
+        assert_eq!(result, Ok(()));
+    }
+
+    #[test]
+    fn borrows_from_environment() {
+        let mut totals = [0u32; 4];
+        let result: Result<(), ()> = stop_scope(Unstoppable, |s| {
+            for (i, slot) in totals.iter_mut().enumerate() {
+                s.spawn(move |_| {
+                    *slot = i as u32 * 10;
+                    Ok(())
+                });
+            }
+        });
+        assert_eq!(result, Ok(()));
+        assert_eq!(totals, [0, 10, 20, 30]);
+    }
+
+    #[test]
+    fn debug_format() {
+        let _: Result<(), ()> = stop_scope(Unstoppable, |s| {
+            assert!(format!("{s:?}").contains("StopScope"));
+        });
+    }
+}
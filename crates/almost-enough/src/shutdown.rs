@@ -0,0 +1,387 @@
+//! Graceful shutdown of registered workers.
+//!
+//! A server typically runs several long-lived subsystems (listeners,
+//! flushers, background jobs) and on exit must cancel them all, wait for
+//! each to wind down, and give up on stragglers after a deadline.
+//! [`ShutdownCoordinator`] hands each subsystem a [`WorkerHandle`] holding a
+//! child stop; the worker acknowledges completion by calling
+//! [`done()`](WorkerHandle::done) or simply dropping the handle.
+//! [`shutdown()`](ShutdownCoordinator::shutdown) cancels every worker,
+//! waits for the acknowledgements, and reports which workers did not make
+//! the deadline.
+//!
+//! # Example
+//!
+//! ```rust
+//! use almost_enough::{ShutdownCoordinator, Stop};
+//! use std::time::Duration;
+//!
+//! let coordinator = ShutdownCoordinator::new();
+//!
+//! for name in ["listener", "flusher"] {
+//!     let worker = coordinator.register(name);
+//!     std::thread::spawn(move || {
+//!         while !worker.should_stop() {
+//!             std::thread::sleep(Duration::from_millis(1));
+//!         }
+//!         // flush, close sockets, ...
+//!         worker.done();
+//!     });
+//! }
+//!
+//! let report = coordinator.shutdown(Duration::from_secs(5));
+//! assert!(report.is_clean());
+//! assert_eq!(report.completed(), 2);
+//! ```
+
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+use crate::{ChildStopper, NodeLabel, Stop, StopReason};
+
+/// Workers that have not yet acknowledged completion.
+#[derive(Default)]
+struct Registry {
+    next_id: u64,
+    pending: Vec<(u64, NodeLabel)>,
+    completed: usize,
+}
+
+#[derive(Default)]
+struct Shared {
+    registry: Mutex<Registry>,
+    cond: Condvar,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, Registry> {
+        self.registry.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Hands out cancellation tokens to subsystems and shuts them down
+/// together.
+///
+/// Workers are cancelled through a shared [`ChildStopper`] root, so the
+/// coordinator can itself hang off a parent stop (e.g. a `SignalStop`)
+/// via [`with_parent()`](Self::with_parent).
+pub struct ShutdownCoordinator {
+    root: ChildStopper,
+    shared: Arc<Shared>,
+}
+
+impl ShutdownCoordinator {
+    /// Create a coordinator with its own root stop.
+    pub fn new() -> Self {
+        Self::from_root(ChildStopper::new())
+    }
+
+    /// Create a coordinator whose workers are also cancelled when `parent`
+    /// stops.
+    pub fn with_parent<T: Stop + 'static>(parent: T) -> Self {
+        Self::from_root(ChildStopper::with_parent(parent))
+    }
+
+    fn from_root(root: ChildStopper) -> Self {
+        Self {
+            root,
+            shared: Arc::new(Shared::default()),
+        }
+    }
+
+    /// Register a worker and return its handle.
+    ///
+    /// The handle's stop is a child of the coordinator's root, labelled
+    /// with `label` for [`ShutdownReport::timed_out()`] and
+    /// [`ChildStopper::cancelled_by()`]. Registering after shutdown has
+    /// begun returns an already-cancelled handle that is still tracked.
+    pub fn register(&self, label: impl Into<NodeLabel>) -> WorkerHandle {
+        let label = label.into();
+        let id = {
+            let mut registry = self.shared.lock();
+            let id = registry.next_id;
+            registry.next_id += 1;
+            registry.pending.push((id, label));
+            id
+        };
+        WorkerHandle {
+            stop: self.root.child_with_label(label),
+            id,
+            shared: Arc::clone(&self.shared),
+        }
+    }
+
+    /// Cancel every worker, then wait up to `timeout` for all of them to
+    /// acknowledge completion.
+    ///
+    /// May be called more than once, e.g. to wait again for the stragglers
+    /// from a previous report.
+    pub fn shutdown(&self, timeout: Duration) -> ShutdownReport {
+        self.root.cancel();
+        let deadline = Instant::now().checked_add(timeout);
+        let mut registry = self.shared.lock();
+        while !registry.pending.is_empty() {
+            registry = match deadline {
+                Some(deadline) => {
+                    let remaining = deadline.saturating_duration_since(Instant::now());
+                    if remaining.is_zero() {
+                        break;
+                    }
+                    self.shared
+                        .cond
+                        .wait_timeout(registry, remaining)
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .0
+                }
+                None => self
+                    .shared
+                    .cond
+                    .wait(registry)
+                    .unwrap_or_else(PoisonError::into_inner),
+            };
+        }
+        ShutdownReport {
+            completed: registry.completed,
+            timed_out: registry.pending.iter().map(|&(_, label)| label).collect(),
+        }
+    }
+
+    /// Whether [`shutdown()`](Self::shutdown) has been called or the parent
+    /// has stopped.
+    #[inline]
+    pub fn is_shutting_down(&self) -> bool {
+        self.root.should_stop()
+    }
+
+    /// Number of registered workers that have not yet acknowledged
+    /// completion.
+    pub fn active(&self) -> usize {
+        self.shared.lock().pending.len()
+    }
+
+    /// The root stop shared by every worker.
+    #[inline]
+    pub fn stop(&self) -> &ChildStopper {
+        &self.root
+    }
+}
+
+impl Default for ShutdownCoordinator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl core::fmt::Debug for ShutdownCoordinator {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ShutdownCoordinator")
+            .field("shutting_down", &self.is_shutting_down())
+            .field("active", &self.active())
+            .finish()
+    }
+}
+
+/// A registered worker's stop and completion acknowledgement.
+///
+/// Implements [`Stop`] by delegating to its child stop. Dropping the
+/// handle acknowledges completion, so a worker that returns early or
+/// panics does not hold up shutdown.
+pub struct WorkerHandle {
+    stop: ChildStopper,
+    id: u64,
+    shared: Arc<Shared>,
+}
+
+impl WorkerHandle {
+    /// Acknowledge that this worker has finished.
+    ///
+    /// Equivalent to dropping the handle, but reads better at the end of a
+    /// worker loop.
+    #[inline]
+    pub fn done(self) {}
+
+    /// The worker's stop, e.g. to hand to code taking `impl Stop + Clone`.
+    #[inline]
+    pub fn stop(&self) -> &ChildStopper {
+        &self.stop
+    }
+
+    /// This worker's label.
+    #[inline]
+    pub fn label(&self) -> Option<NodeLabel> {
+        self.stop.label()
+    }
+}
+
+impl Stop for WorkerHandle {
+    #[inline]
+    fn check(&self) -> Result<(), StopReason> {
+        self.stop.check()
+    }
+
+    #[inline]
+    fn should_stop(&self) -> bool {
+        self.stop.should_stop()
+    }
+}
+
+impl Drop for WorkerHandle {
+    fn drop(&mut self) {
+        let mut registry = self.shared.lock();
+        if let Some(pos) = registry.pending.iter().position(|&(id, _)| id == self.id) {
+            registry.pending.swap_remove(pos);
+            registry.completed += 1;
+        }
+        drop(registry);
+        self.shared.cond.notify_all();
+    }
+}
+
+impl core::fmt::Debug for WorkerHandle {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("WorkerHandle")
+            .field("label", &self.label())
+            .field("stop", &self.stop)
+            .finish()
+    }
+}
+
+/// The outcome of [`ShutdownCoordinator::shutdown()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShutdownReport {
+    completed: usize,
+    timed_out: Vec<NodeLabel>,
+}
+
+impl ShutdownReport {
+    /// Whether every registered worker acknowledged completion in time.
+    #[inline]
+    pub fn is_clean(&self) -> bool {
+        self.timed_out.is_empty()
+    }
+
+    /// Number of workers that have acknowledged completion so far.
+    #[inline]
+    pub fn completed(&self) -> usize {
+        self.completed
+    }
+
+    /// Labels of the workers still running at the deadline, in no
+    /// particular order.
+    #[inline]
+    pub fn timed_out(&self) -> &[NodeLabel] {
+        &self.timed_out
+    }
+}
+
+impl core::fmt::Display for ShutdownReport {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{} worker(s) completed", self.completed)?;
+        if let Some((first, rest)) = self.timed_out.split_first() {
+            write!(f, ", timed out: {first}")?;
+            for label in rest {
+                write!(f, ", {label}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Stopper;
+    use std::thread;
+
+    const LONG: Duration = Duration::from_secs(10);
+
+    #[test]
+    fn clean_shutdown() {
+        let coordinator = ShutdownCoordinator::new();
+        let workers: Vec<_> = (0..4u64)
+            .map(|i| {
+                let worker = coordinator.register(i);
+                thread::spawn(move || {
+                    while !worker.should_stop() {
+                        thread::yield_now();
+                    }
+                    worker.done();
+                })
+            })
+            .collect();
+        assert_eq!(coordinator.active(), 4);
+        assert!(!coordinator.is_shutting_down());
+
+        let report = coordinator.shutdown(LONG);
+        assert!(report.is_clean());
+        assert_eq!(report.completed(), 4);
+        assert_eq!(report.to_string(), "4 worker(s) completed");
+        assert_eq!(coordinator.active(), 0);
+        workers.into_iter().for_each(|w| w.join().unwrap());
+    }
+
+    #[test]
+    fn reports_stragglers_and_can_wait_again() {
+        let coordinator = ShutdownCoordinator::new();
+        let quick = coordinator.register("quick");
+        let slow = coordinator.register("slow");
+        let quick = thread::spawn(move || {
+            while !quick.should_stop() {
+                thread::yield_now();
+            }
+        });
+
+        let report = coordinator.shutdown(Duration::from_millis(20));
+        assert!(!report.is_clean());
+        assert_eq!(report.timed_out(), &[NodeLabel::Name("slow")]);
+        assert_eq!(report.to_string(), "1 worker(s) completed, timed out: slow");
+        assert_eq!(slow.stop().cancelled_by(), None);
+        assert!(slow.should_stop());
+
+        drop(slow);
+        assert!(coordinator.shutdown(LONG).is_clean());
+        quick.join().unwrap();
+    }
+
+    #[test]
+    fn panicking_worker_still_acknowledges() {
+        let coordinator = ShutdownCoordinator::new();
+        let worker = coordinator.register("panics");
+        let handle = thread::spawn(move || {
+            let _worker = worker;
+            panic!("worker failed");
+        });
+        assert!(handle.join().is_err());
+        assert!(coordinator.shutdown(Duration::ZERO).is_clean());
+    }
+
+    #[test]
+    fn parent_stop_cancels_workers() {
+        let parent = Stopper::new();
+        let coordinator = ShutdownCoordinator::with_parent(parent.clone());
+        let worker = coordinator.register("job");
+        assert_eq!(worker.label(), Some(NodeLabel::Name("job")));
+
+        parent.cancel();
+        assert!(coordinator.is_shutting_down());
+        assert_eq!(worker.check(), Err(StopReason::Cancelled));
+    }
+
+    #[test]
+    fn register_after_shutdown_is_cancelled() {
+        let coordinator = ShutdownCoordinator::default();
+        assert!(coordinator.shutdown(Duration::ZERO).is_clean());
+        let late = coordinator.register("late");
+        assert!(late.should_stop());
+        assert_eq!(coordinator.active(), 1);
+        assert!(format!("{coordinator:?}").contains("active: 1"));
+        assert!(format!("{late:?}").contains("late"));
+    }
+
+    #[test]
+    fn is_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<ShutdownCoordinator>();
+        assert_send_sync::<WorkerHandle>();
+    }
+}
@@ -0,0 +1,167 @@
+//! OS signal driven cancellation.
+//!
+//! [`SignalStop`] cancels when the process receives SIGINT, SIGTERM or
+//! SIGHUP (unix) or Ctrl-C, Ctrl-Break or a console close event (Windows).
+//! CLI tools install it once and pass it into their processing pipeline to
+//! get graceful shutdown.
+//!
+//! Requires the `signal` feature.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use almost_enough::{SignalStop, Stop, StopExt};
+//!
+//! let stop = SignalStop::install().expect("failed to install signal handler");
+//!
+//! // Give each stage its own child so it can be cancelled independently
+//! let decode = stop.child();
+//! while !decode.should_stop() {
+//!     // ... work ...
+//!     # break;
+//! }
+//! ```
+//!
+//! # One Handler Per Process
+//!
+//! Signal handlers are process-global. The first call to
+//! [`SignalStop::install()`] registers the handler; later calls return
+//! handles to the same underlying [`Stopper`]. Installation fails if another
+//! `ctrlc` handler was registered elsewhere in the process.
+
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use crate::{Stop, StopReason, Stopper};
+
+static SIGNAL_STOPPER: OnceLock<Result<Stopper, SignalInstallError>> = OnceLock::new();
+static SIGNAL_COUNT: AtomicU32 = AtomicU32::new(0);
+
+/// Error returned when the process signal handler cannot be installed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignalInstallError {
+    message: String,
+}
+
+impl core::fmt::Display for SignalInstallError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "failed to install signal handler: {}", self.message)
+    }
+}
+
+impl std::error::Error for SignalInstallError {}
+
+/// A [`Stop`] that is cancelled by process termination signals.
+///
+/// Wraps a process-wide [`Stopper`], so it composes like any other stop:
+/// `.child()`, `.or()`, `.with_timeout()` and conversion to
+/// [`StopToken`](crate::StopToken) all work. The stopper can also be
+/// cancelled programmatically via [`cancel()`](Self::cancel).
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use almost_enough::{SignalStop, Stop, TimeoutExt};
+/// use std::time::Duration;
+///
+/// let stop = SignalStop::install()?
+///     .with_timeout(Duration::from_secs(600));
+///
+/// if stop.should_stop() {
+///     eprintln!("interrupted");
+/// }
+/// # Ok::<(), almost_enough::SignalInstallError>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct SignalStop {
+    stopper: Stopper,
+}
+
+impl SignalStop {
+    /// Install the process signal handler and return a handle to it.
+    ///
+    /// Idempotent: every call after the first returns a handle to the same
+    /// stopper (or the same error).
+    pub fn install() -> Result<Self, SignalInstallError> {
+        SIGNAL_STOPPER
+            .get_or_init(|| {
+                let stopper = Stopper::new();
+                let handler_stopper = stopper.clone();
+                ctrlc::set_handler(move || {
+                    SIGNAL_COUNT.fetch_add(1, Ordering::Relaxed);
+                    handler_stopper.cancel();
+                })
+                .map(|()| stopper)
+                .map_err(|e| SignalInstallError {
+                    message: e.to_string(),
+                })
+            })
+            .clone()
+            .map(|stopper| Self { stopper })
+    }
+
+    /// Number of termination signals received since installation.
+    ///
+    /// Useful for "press Ctrl-C again to force quit" behavior.
+    #[inline]
+    pub fn signal_count() -> u32 {
+        SIGNAL_COUNT.load(Ordering::Relaxed)
+    }
+
+    /// Cancel as if a signal had been received.
+    ///
+    /// Does not change [`signal_count()`](Self::signal_count).
+    #[inline]
+    pub fn cancel(&self) {
+        self.stopper.cancel();
+    }
+
+    /// Check if a signal was received or [`cancel()`](Self::cancel) was called.
+    #[inline]
+    pub fn is_cancelled(&self) -> bool {
+        self.stopper.is_cancelled()
+    }
+
+    /// Get the underlying process-wide [`Stopper`].
+    #[inline]
+    pub fn stopper(&self) -> &Stopper {
+        &self.stopper
+    }
+}
+
+impl Stop for SignalStop {
+    #[inline]
+    fn check(&self) -> Result<(), StopReason> {
+        self.stopper.check()
+    }
+
+    #[inline]
+    fn should_stop(&self) -> bool {
+        self.stopper.should_stop()
+    }
+}
+
+impl From<SignalStop> for Stopper {
+    #[inline]
+    fn from(stop: SignalStop) -> Self {
+        stop.stopper
+    }
+}
+
+impl From<SignalStop> for crate::StopToken {
+    #[inline]
+    fn from(stop: SignalStop) -> Self {
+        stop.stopper.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signal_stop_is_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<SignalStop>();
+    }
+}
@@ -0,0 +1,243 @@
+//! Detect scopes that keep running long after cancellation.
+//!
+//! Cooperative cancellation only works if code actually checks. A
+//! [`SlowDropDetector`] is created alongside a cancellable scope; if the
+//! stop trips and the detector is not dropped within a threshold, a
+//! callback runs — to log, record a metric, or abort the process.
+//!
+//! # Example
+//!
+//! ```rust
+//! use almost_enough::{SlowDropDetector, Stopper};
+//! use std::time::Duration;
+//!
+//! let stop = Stopper::new();
+//! {
+//!     let _detector = SlowDropDetector::new(
+//!         stop.clone(),
+//!         Duration::from_secs(5),
+//!         |slow| eprintln!("request ignored {} for over {:?}", slow.reason(), slow.threshold()),
+//!     );
+//!     // ... request handling that should honor `stop` ...
+//! } // dropped here: no report unless this ran 5s past cancellation
+//! ```
+
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::{Stop, StopReason};
+
+/// How often the watcher polls the stop before it trips.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Passed to the [`SlowDropDetector`] callback.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SlowDrop {
+    reason: StopReason,
+    threshold: Duration,
+}
+
+impl SlowDrop {
+    /// Why the stop tripped.
+    #[inline]
+    pub fn reason(&self) -> StopReason {
+        self.reason
+    }
+
+    /// How long the scope was allowed to run after the stop tripped.
+    #[inline]
+    pub fn threshold(&self) -> Duration {
+        self.threshold
+    }
+}
+
+/// Shared between the guard and its watcher thread.
+struct Watch {
+    dropped: Mutex<bool>,
+    cond: Condvar,
+}
+
+/// Guard that reports when a scope outlives its cancellation by too long.
+///
+/// A background thread watches the stop. Once it trips, the scope has
+/// `threshold` to drop the detector; otherwise the callback is invoked
+/// (on the watcher thread) while the scope is still running. The callback
+/// runs at most once.
+///
+/// Dropping the detector never blocks. The watcher thread exits promptly
+/// after the detector is dropped or the callback has run.
+///
+/// Since each detector owns a thread, use it for coarse scopes such as
+/// requests or jobs, not per-item work. Requires the `std` feature.
+///
+/// # Example
+///
+/// ```rust
+/// use almost_enough::{SlowDropDetector, Stopper};
+/// use std::sync::atomic::{AtomicBool, Ordering};
+/// use std::sync::Arc;
+/// use std::time::Duration;
+///
+/// let stop = Stopper::new();
+/// let fired = Arc::new(AtomicBool::new(false));
+/// let flag = Arc::clone(&fired);
+/// let detector = SlowDropDetector::new(stop.clone(), Duration::from_millis(20), move |_| {
+///     flag.store(true, Ordering::SeqCst);
+/// });
+///
+/// stop.cancel();
+/// std::thread::sleep(Duration::from_millis(200)); // ignores the cancellation
+/// assert!(fired.load(Ordering::SeqCst));
+/// drop(detector);
+/// ```
+pub struct SlowDropDetector {
+    watch: Arc<Watch>,
+    threshold: Duration,
+}
+
+impl SlowDropDetector {
+    /// Watch `stop`; call `on_slow` if this detector is still alive
+    /// `threshold` after `stop` trips.
+    pub fn new<S, F>(stop: S, threshold: Duration, on_slow: F) -> Self
+    where
+        S: Stop + 'static,
+        F: FnOnce(SlowDrop) + Send + 'static,
+    {
+        let watch = Arc::new(Watch {
+            dropped: Mutex::new(false),
+            cond: Condvar::new(),
+        });
+        let watcher = Arc::clone(&watch);
+        std::thread::Builder::new()
+            .name("slow-drop-detector".into())
+            .spawn(move || {
+                if let Some(slow) = watcher.run(&stop, threshold) {
+                    on_slow(slow);
+                }
+            })
+            .expect("failed to spawn slow-drop watcher thread");
+        Self { watch, threshold }
+    }
+
+    /// The configured threshold.
+    #[inline]
+    pub fn threshold(&self) -> Duration {
+        self.threshold
+    }
+}
+
+impl Watch {
+    /// Returns a report if the stop tripped and the guard outlived the
+    /// threshold, or `None` once the guard is dropped.
+    fn run(&self, stop: &impl Stop, threshold: Duration) -> Option<SlowDrop> {
+        let mut dropped = self.dropped.lock().unwrap_or_else(|e| e.into_inner());
+        let reason = loop {
+            if *dropped {
+                return None;
+            }
+            if let Err(reason) = stop.check() {
+                break reason;
+            }
+            dropped = self
+                .cond
+                .wait_timeout(dropped, POLL_INTERVAL)
+                .unwrap_or_else(|e| e.into_inner())
+                .0;
+        };
+        let deadline = Instant::now() + threshold;
+        loop {
+            if *dropped {
+                return None;
+            }
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                return Some(SlowDrop { reason, threshold });
+            }
+            dropped = self
+                .cond
+                .wait_timeout(dropped, left)
+                .unwrap_or_else(|e| e.into_inner())
+                .0;
+        }
+    }
+}
+
+impl Drop for SlowDropDetector {
+    fn drop(&mut self) {
+        *self.watch.dropped.lock().unwrap_or_else(|e| e.into_inner()) = true;
+        self.watch.cond.notify_all();
+    }
+}
+
+impl core::fmt::Debug for SlowDropDetector {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("SlowDropDetector")
+            .field("threshold", &self.threshold)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Stopper, TimeoutExt};
+    use std::sync::mpsc;
+
+    fn detector(
+        stop: impl Stop + 'static,
+        threshold: Duration,
+    ) -> (SlowDropDetector, mpsc::Receiver<SlowDrop>) {
+        let (tx, rx) = mpsc::channel();
+        let detector = SlowDropDetector::new(stop, threshold, move |slow| {
+            let _ = tx.send(slow);
+        });
+        (detector, rx)
+    }
+
+    #[test]
+    fn fires_when_scope_ignores_cancellation() {
+        let stop = Stopper::new();
+        let (detector, rx) = detector(stop.clone(), Duration::from_millis(20));
+        stop.cancel();
+
+        let slow = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(slow.reason(), StopReason::Cancelled);
+        assert_eq!(slow.threshold(), Duration::from_millis(20));
+        drop(detector);
+    }
+
+    #[test]
+    fn prompt_drop_does_not_fire() {
+        let stop = Stopper::new();
+        let (detector, rx) = detector(stop.clone(), Duration::from_secs(10));
+        stop.cancel();
+        drop(detector);
+        // The callback (and its sender) is dropped without sending
+        assert_eq!(rx.recv(), Err(mpsc::RecvError));
+    }
+
+    #[test]
+    fn never_cancelled_does_not_fire() {
+        let (detector, rx) = detector(Stopper::new(), Duration::ZERO);
+        std::thread::sleep(Duration::from_millis(30));
+        drop(detector);
+        assert_eq!(rx.recv(), Err(mpsc::RecvError));
+    }
+
+    #[test]
+    fn reports_timeout_reason() {
+        let stop = Stopper::new().with_timeout(Duration::ZERO);
+        let (_detector, rx) = detector(stop, Duration::ZERO);
+        let slow = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(slow.reason(), StopReason::TimedOut);
+    }
+
+    #[test]
+    fn debug_and_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<SlowDropDetector>();
+        let (detector, _rx) = detector(Stopper::new(), Duration::from_secs(1));
+        assert!(format!("{detector:?}").contains("SlowDropDetector"));
+        assert_eq!(detector.threshold(), Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,364 @@
+//! Checkpointable stop state.
+//!
+//! A job system that checkpoints work to disk also needs to checkpoint
+//! *whether the job should keep going*: a job that was cancelled, or had
+//! 20 seconds of its budget left, should come back the same way after a
+//! restart. Instants cannot be persisted, so a [`StopSnapshot`] records
+//! the stop reason (if any) and the deadline as time remaining. Capture
+//! one with [`WithTimeout::snapshot()`], [`RestoredStop::snapshot()`] or
+//! [`StopSnapshot::capture()`], and rebuild an equivalent stop with
+//! [`Stopper::from_snapshot()`].
+//!
+//! With the `serde` feature, `StopSnapshot` implements `Serialize` and
+//! `Deserialize`. Time spent while the job was down does not count against
+//! the restored deadline.
+//!
+//! # Example
+//!
+//! ```rust
+//! use almost_enough::{Stop, StopSnapshot, Stopper, TimeoutExt};
+//! use std::time::Duration;
+//!
+//! let stop = Stopper::new().with_timeout(Duration::from_secs(30));
+//! let checkpoint = stop.snapshot();
+//! assert!(!checkpoint.is_cancelled());
+//!
+//! // ... persist `checkpoint` with the job, restart, load it ...
+//!
+//! let resumed = Stopper::from_snapshot(&checkpoint);
+//! assert!(resumed.remaining().unwrap() <= Duration::from_secs(30));
+//! assert!(resumed.check().is_ok());
+//! resumed.cancel();
+//! assert!(resumed.snapshot().is_cancelled());
+//! ```
+
+use std::time::{Duration, Instant};
+
+use crate::{CheckAt, Stop, StopReason, Stopper, WithTimeout};
+
+/// A persistable copy of a stop's state: its reason and remaining time.
+///
+/// Taken with [`WithTimeout::snapshot()`], [`RestoredStop::snapshot()`] or
+/// [`capture()`](Self::capture); restored with [`Stopper::from_snapshot()`].
+/// Implements `Serialize`/`Deserialize` with the `serde` feature, as
+/// `{"reason": null | "cancelled" | "timed_out" | "failed", "remaining": null | {"secs", "nanos"}}`.
+///
+/// # Example
+///
+/// ```rust
+/// use almost_enough::{StopReason, StopSnapshot, Stopper};
+///
+/// let snapshot = StopSnapshot::new().with_reason(Some(StopReason::Cancelled));
+/// assert!(Stopper::from_snapshot(&snapshot).stopper().is_cancelled());
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StopSnapshot {
+    #[cfg_attr(feature = "serde", serde(with = "serde_reason", default))]
+    reason: Option<StopReason>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    remaining: Option<Duration>,
+}
+
+impl StopSnapshot {
+    /// A running stop with no deadline.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            reason: None,
+            remaining: None,
+        }
+    }
+
+    /// The state of any stop, without a deadline.
+    ///
+    /// Generic stops do not expose their deadline; use
+    /// [`WithTimeout::snapshot()`] to keep it.
+    #[inline]
+    pub fn capture(stop: &impl Stop) -> Self {
+        Self::new().with_reason(stop.check().err())
+    }
+
+    /// Set the stop reason; `None` means still running.
+    #[inline]
+    pub const fn with_reason(mut self, reason: Option<StopReason>) -> Self {
+        self.reason = reason;
+        self
+    }
+
+    /// Set the time left until the deadline; `None` means no deadline.
+    #[inline]
+    pub const fn with_remaining(mut self, remaining: Option<Duration>) -> Self {
+        self.remaining = remaining;
+        self
+    }
+
+    /// Why the stop had stopped, or `None` if it was running.
+    #[inline]
+    pub const fn reason(&self) -> Option<StopReason> {
+        self.reason
+    }
+
+    /// Whether the stop had stopped, for any reason.
+    #[inline]
+    pub const fn is_cancelled(&self) -> bool {
+        self.reason.is_some()
+    }
+
+    /// Time that was left until the deadline, if there was one.
+    #[inline]
+    pub const fn remaining(&self) -> Option<Duration> {
+        self.remaining
+    }
+}
+
+impl Stopper {
+    /// Rebuild the state recorded in `snapshot`.
+    ///
+    /// The returned stop is cancelled with the snapshot's reason, if it has
+    /// one, and times out after the snapshot's remaining time, counted from
+    /// now. The restored stop is a fresh [`Stopper`]: it shares nothing
+    /// with the stop the snapshot was taken from.
+    pub fn from_snapshot(snapshot: &StopSnapshot) -> RestoredStop {
+        RestoredStop {
+            stopper: Stopper::with_reason(snapshot.reason),
+            deadline: snapshot
+                .remaining
+                .and_then(|remaining| Instant::now().checked_add(remaining)),
+        }
+    }
+}
+
+impl<T: Stop> WithTimeout<T> {
+    /// Record the current reason and remaining time.
+    #[inline]
+    pub fn snapshot(&self) -> StopSnapshot {
+        StopSnapshot::new()
+            .with_reason(self.check().err())
+            .with_remaining(Some(self.remaining()))
+    }
+}
+
+/// A [`Stopper`] with an optional deadline, rebuilt from a
+/// [`StopSnapshot`].
+///
+/// Created by [`Stopper::from_snapshot()`]. Reports the stopper's reason
+/// once cancelled, and [`StopReason::TimedOut`] once the deadline passes.
+/// Cloning shares the stopper.
+#[derive(Debug, Clone)]
+pub struct RestoredStop {
+    stopper: Stopper,
+    deadline: Option<Instant>,
+}
+
+impl RestoredStop {
+    /// Cancel the restored stop and all its clones.
+    #[inline]
+    pub fn cancel(&self) {
+        self.stopper.cancel();
+    }
+
+    /// The restored deadline, if any.
+    #[inline]
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// Time left until the deadline, if any; `Duration::ZERO` once it has
+    /// passed.
+    #[inline]
+    pub fn remaining(&self) -> Option<Duration> {
+        self.deadline
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()))
+    }
+
+    /// Record the current state, e.g. for the next checkpoint.
+    #[inline]
+    pub fn snapshot(&self) -> StopSnapshot {
+        StopSnapshot::new()
+            .with_reason(self.check().err())
+            .with_remaining(self.remaining())
+    }
+
+    /// The underlying stopper, without the deadline.
+    #[inline]
+    pub fn stopper(&self) -> &Stopper {
+        &self.stopper
+    }
+
+    /// Unwrap and return the underlying stopper, dropping the deadline.
+    #[inline]
+    pub fn into_inner(self) -> Stopper {
+        self.stopper
+    }
+}
+
+impl Stop for RestoredStop {
+    #[inline]
+    fn check(&self) -> Result<(), StopReason> {
+        self.stopper.check()?;
+        match self.deadline {
+            Some(deadline) if Instant::now() >= deadline => Err(StopReason::TimedOut),
+            _ => Ok(()),
+        }
+    }
+
+    #[inline]
+    fn should_stop(&self) -> bool {
+        self.check().is_err()
+    }
+}
+
+impl CheckAt for RestoredStop {
+    #[inline]
+    fn check_at(&self, now: Instant) -> Result<(), StopReason> {
+        self.stopper.check()?;
+        match self.deadline {
+            Some(deadline) if now >= deadline => Err(StopReason::TimedOut),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// `Option<StopReason>` as `null` or a lowercase name, since `StopReason`
+/// lives in `enough` and has no serde impls of its own.
+#[cfg(feature = "serde")]
+mod serde_reason {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer, de};
+
+    use crate::StopReason;
+
+    pub(super) fn serialize<S: Serializer>(
+        reason: &Option<StopReason>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        reason
+            .map(|reason| match reason {
+                StopReason::Cancelled => "cancelled",
+                StopReason::TimedOut => "timed_out",
+                _ => "failed",
+            })
+            .serialize(serializer)
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<StopReason>, D::Error> {
+        let Some(name) = Option::<String>::deserialize(deserializer)? else {
+            return Ok(None);
+        };
+        match name.as_str() {
+            "cancelled" => Ok(Some(StopReason::Cancelled)),
+            "timed_out" => Ok(Some(StopReason::TimedOut)),
+            "failed" => Ok(Some(StopReason::Failed)),
+            other => Err(de::Error::unknown_variant(
+                other,
+                &["cancelled", "timed_out", "failed"],
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{StopSource, TimeoutExt, Unstoppable};
+
+    #[test]
+    fn running_stop_round_trips() {
+        let stop = Unstoppable.with_timeout(Duration::from_secs(60));
+        let snapshot = stop.snapshot();
+        assert_eq!(snapshot.reason(), None);
+        assert!(snapshot.remaining().unwrap() <= Duration::from_secs(60));
+
+        let restored = Stopper::from_snapshot(&snapshot);
+        assert!(restored.check().is_ok());
+        assert!(restored.deadline().is_some());
+        assert!(restored.remaining().unwrap() <= snapshot.remaining().unwrap());
+
+        restored.clone().cancel();
+        assert_eq!(restored.check(), Err(StopReason::Cancelled));
+        assert!(restored.stopper().is_cancelled());
+    }
+
+    #[test]
+    fn reason_is_restored() {
+        for reason in [
+            StopReason::Cancelled,
+            StopReason::TimedOut,
+            StopReason::Failed,
+        ] {
+            let snapshot = StopSnapshot::new().with_reason(Some(reason));
+            let restored = Stopper::from_snapshot(&snapshot);
+            assert_eq!(restored.check(), Err(reason));
+            assert_eq!(restored.snapshot(), snapshot);
+        }
+    }
+
+    #[test]
+    fn expired_deadline_times_out() {
+        let snapshot = StopSnapshot::new().with_remaining(Some(Duration::ZERO));
+        let restored = Stopper::from_snapshot(&snapshot);
+        assert_eq!(restored.check(), Err(StopReason::TimedOut));
+        assert!(restored.should_stop());
+        assert!(!restored.into_inner().is_cancelled());
+    }
+
+    #[test]
+    fn capture_has_no_deadline() {
+        let source = StopSource::cancelled();
+        let snapshot = StopSnapshot::capture(&source);
+        assert!(snapshot.is_cancelled());
+        assert_eq!(snapshot.remaining(), None);
+        assert_eq!(StopSnapshot::capture(&Unstoppable), StopSnapshot::default());
+
+        let restored = Stopper::from_snapshot(&StopSnapshot::new());
+        assert_eq!(restored.remaining(), None);
+        assert!(!restored.should_stop());
+    }
+
+    #[test]
+    fn check_at_uses_given_timestamp() {
+        let snapshot = StopSnapshot::new().with_remaining(Some(Duration::from_secs(60)));
+        let restored = Stopper::from_snapshot(&snapshot);
+        let deadline = restored.deadline().unwrap();
+        assert!(restored.check_at(Instant::now()).is_ok());
+        assert_eq!(restored.check_at(deadline), Err(StopReason::TimedOut));
+        restored.cancel();
+        assert!(restored.should_stop_at(Instant::now()));
+    }
+
+    #[test]
+    fn huge_remaining_means_no_deadline() {
+        let snapshot = StopSnapshot::new().with_remaining(Some(Duration::MAX));
+        assert_eq!(Stopper::from_snapshot(&snapshot).deadline(), None);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let snapshot = StopSnapshot::new()
+            .with_reason(Some(StopReason::TimedOut))
+            .with_remaining(Some(Duration::from_millis(1500)));
+        let json = serde_json::to_string(&snapshot).unwrap();
+        assert_eq!(
+            json,
+            r#"{"reason":"timed_out","remaining":{"secs":1,"nanos":500000000}}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<StopSnapshot>(&json).unwrap(),
+            snapshot
+        );
+
+        let running: StopSnapshot = serde_json::from_str("{}").unwrap();
+        assert_eq!(running, StopSnapshot::new());
+        assert!(serde_json::from_str::<StopSnapshot>(r#"{"reason":"paused"}"#).is_err());
+    }
+
+    #[test]
+    fn is_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<StopSnapshot>();
+        assert_send_sync::<RestoredStop>();
+    }
+}
@@ -32,6 +32,13 @@
 //! Use [`Stopper`](crate::Stopper) when:
 //! - You need to share ownership (clone instead of borrow)
 //! - You want to pass stops across thread boundaries without lifetimes
+//!
+//! `StopRef` is an ordinary borrow, so a reference that would outlive its
+//! source is a compile error rather than a dangling pointer. When the
+//! borrow cannot be threaded through (spawned threads, `'static` fields,
+//! FFI callbacks), there is no unchecked token to fall back on: switch to
+//! `Stopper`, whose clones and [`StopToken`](crate::StopToken) conversion
+//! are `Arc`-backed and cost one reference-count increment.
 
 use core::sync::atomic::{AtomicBool, Ordering};
 
@@ -192,7 +199,7 @@ pub struct StopRef<'a> {
 }
 
 impl Stop for StopRef<'_> {
-    #[inline]
+    #[inline(always)]
     fn check(&self) -> Result<(), StopReason> {
         if self.cancelled.load(Ordering::Relaxed) {
             Err(StopReason::Cancelled)
@@ -201,7 +208,7 @@ impl Stop for StopRef<'_> {
         }
     }
 
-    #[inline]
+    #[inline(always)]
     fn should_stop(&self) -> bool {
         self.cancelled.load(Ordering::Relaxed)
     }
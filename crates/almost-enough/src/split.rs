@@ -0,0 +1,398 @@
+//! Hedged two-way parallelism with per-branch cancellation.
+//!
+//! Speculative strategies run two approaches to the same problem side by
+//! side (e.g. with `rayon::join`) and keep whichever finishes first. Both
+//! branches must stop when the caller cancels, and the slower branch should
+//! also stop as soon as its sibling wins. [`StopExt::split()`] wraps a stop
+//! in a [`SplitStop`], whose two [`Branch`] handles do exactly that:
+//!
+//! - each branch stops when the parent stops;
+//! - [`Branch::finish()`] claims the win and cancels the sibling only;
+//! - [`SplitStop::merge()`] reports the winner, or the parent's reason if
+//!   the parent stopped.
+//!
+//! Branch cancellation never reaches the parent, so after `merge()` the
+//! parent can be split again. The split state is a single atomic next to
+//! the parent, and branches are `Copy` borrows of it, so splitting does not
+//! allocate and works in `no_std`. Branches implement [`Stop`], so a branch
+//! can itself be split for recursive hedging.
+//!
+//! # Example
+//!
+//! ```rust
+//! use almost_enough::{Side, Stop, StopExt, StopSource};
+//! use std::thread;
+//!
+//! let source = StopSource::new();
+//! let split = source.as_ref().split();
+//! let (left, right) = split.branches();
+//!
+//! // `rayon::join(|| ..., || ...)` works the same way
+//! thread::scope(|s| {
+//!     s.spawn(|| {
+//!         // Fast strategy: done immediately
+//!         left.finish();
+//!     });
+//!     s.spawn(|| {
+//!         // Slow strategy: gives up once the sibling wins
+//!         while !right.should_stop() {
+//!             thread::yield_now();
+//!         }
+//!     });
+//! });
+//!
+//! assert_eq!(split.merge(), Ok(Some(Side::Left)));
+//! assert!(!source.is_cancelled());
+//! ```
+//!
+//! [`StopExt::split()`]: crate::StopExt::split
+
+use core::sync::atomic::{AtomicU8, Ordering};
+
+use crate::{Stop, StopReason};
+
+const LEFT_CANCELLED: u8 = 1;
+const RIGHT_CANCELLED: u8 = 1 << 1;
+const LEFT_WON: u8 = 1 << 2;
+const RIGHT_WON: u8 = 1 << 3;
+const WON: u8 = LEFT_WON | RIGHT_WON;
+
+/// One of the two branches of a [`SplitStop`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Side {
+    /// The first branch returned by [`SplitStop::branches()`].
+    Left,
+    /// The second branch returned by [`SplitStop::branches()`].
+    Right,
+}
+
+impl Side {
+    /// The sibling side.
+    #[inline]
+    pub const fn other(self) -> Side {
+        match self {
+            Side::Left => Side::Right,
+            Side::Right => Side::Left,
+        }
+    }
+
+    const fn cancelled_bit(self) -> u8 {
+        match self {
+            Side::Left => LEFT_CANCELLED,
+            Side::Right => RIGHT_CANCELLED,
+        }
+    }
+
+    const fn won_bit(self) -> u8 {
+        match self {
+            Side::Left => LEFT_WON,
+            Side::Right => RIGHT_WON,
+        }
+    }
+}
+
+/// A stop shared by two independently cancellable branches.
+///
+/// Created by [`StopExt::split()`](crate::StopExt::split) or
+/// [`SplitStop::new()`]. The first branch to [`finish()`](Branch::finish)
+/// cancels the other; [`merge()`](Self::merge) reports the outcome.
+///
+/// # Example
+///
+/// ```rust
+/// use almost_enough::{SplitStop, Stop, StopReason, StopSource};
+///
+/// let source = StopSource::new();
+/// let split = SplitStop::new(source.as_ref());
+/// let (left, right) = split.branches();
+///
+/// left.cancel();
+/// assert!(left.should_stop());
+/// assert!(!right.should_stop());
+///
+/// source.cancel();
+/// assert_eq!(right.check(), Err(StopReason::Cancelled));
+/// assert_eq!(split.merge(), Err(StopReason::Cancelled));
+/// ```
+pub struct SplitStop<S> {
+    parent: S,
+    state: AtomicU8,
+}
+
+impl<S: Stop> SplitStop<S> {
+    /// Split `parent` into two branches, neither of them cancelled.
+    #[inline]
+    pub const fn new(parent: S) -> Self {
+        Self {
+            parent,
+            state: AtomicU8::new(0),
+        }
+    }
+
+    /// The left and right branch handles.
+    ///
+    /// Handles are cheap `Copy` borrows; call this as often as needed.
+    #[inline]
+    pub fn branches(&self) -> (Branch<'_, S>, Branch<'_, S>) {
+        (self.branch(Side::Left), self.branch(Side::Right))
+    }
+
+    /// The handle for one side.
+    #[inline]
+    pub fn branch(&self, side: Side) -> Branch<'_, S> {
+        Branch { split: self, side }
+    }
+
+    /// The branch that called [`Branch::finish()`] first, if any.
+    #[inline]
+    pub fn winner(&self) -> Option<Side> {
+        let state = self.state.load(Ordering::Acquire);
+        if state & LEFT_WON != 0 {
+            Some(Side::Left)
+        } else if state & RIGHT_WON != 0 {
+            Some(Side::Right)
+        } else {
+            None
+        }
+    }
+
+    /// Reconcile the branches with the parent once both have returned.
+    ///
+    /// Returns the parent's reason if the parent stopped, since a result
+    /// produced under a cancelled parent should not be trusted; otherwise
+    /// the [`winner()`](Self::winner). Branch-local cancellation is
+    /// discarded and never reaches the parent.
+    pub fn merge(self) -> Result<Option<Side>, StopReason> {
+        self.parent.check()?;
+        Ok(self.winner())
+    }
+
+    /// Get a reference to the parent stop.
+    #[inline]
+    pub fn parent(&self) -> &S {
+        &self.parent
+    }
+
+    /// Discard the branch state and return the parent stop.
+    #[inline]
+    pub fn into_inner(self) -> S {
+        self.parent
+    }
+}
+
+impl<S: core::fmt::Debug> core::fmt::Debug for SplitStop<S> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let state = self.state.load(Ordering::Relaxed);
+        f.debug_struct("SplitStop")
+            .field("parent", &self.parent)
+            .field("left_cancelled", &(state & LEFT_CANCELLED != 0))
+            .field("right_cancelled", &(state & RIGHT_CANCELLED != 0))
+            .finish()
+    }
+}
+
+/// One branch of a [`SplitStop`].
+///
+/// Stops when the parent stops or this branch is cancelled, either directly
+/// via [`cancel()`](Self::cancel) or because the sibling called
+/// [`finish()`](Self::finish) first. `Send` whenever the parent is `Sync`,
+/// so branches can be moved into `rayon::join` closures.
+pub struct Branch<'a, S> {
+    split: &'a SplitStop<S>,
+    side: Side,
+}
+
+impl<S> Clone for Branch<'_, S> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<S> Copy for Branch<'_, S> {}
+
+impl<'a, S: Stop> Branch<'a, S> {
+    /// Which side this branch is.
+    #[inline]
+    pub fn side(&self) -> Side {
+        self.side
+    }
+
+    /// The other branch of the same split.
+    #[inline]
+    pub fn sibling(&self) -> Branch<'a, S> {
+        self.split.branch(self.side.other())
+    }
+
+    /// Report this branch as finished and cancel the sibling.
+    ///
+    /// Returns `true` if this branch won, i.e. the sibling had not finished
+    /// first. The loser's call is a no-op and returns `false`.
+    pub fn finish(&self) -> bool {
+        let won = self.side.won_bit();
+        let sibling = self.side.other().cancelled_bit();
+        self.split
+            .state
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |state| {
+                (state & WON == 0).then_some(state | won | sibling)
+            })
+            .is_ok()
+    }
+
+    /// Cancel this branch only.
+    #[inline]
+    pub fn cancel(&self) {
+        self.split
+            .state
+            .fetch_or(self.side.cancelled_bit(), Ordering::Relaxed);
+    }
+
+    /// Cancel the sibling only, without claiming the win.
+    #[inline]
+    pub fn cancel_sibling(&self) {
+        self.sibling().cancel();
+    }
+
+    /// Whether this branch was cancelled, ignoring the parent.
+    #[inline]
+    pub fn is_cancelled(&self) -> bool {
+        self.split.state.load(Ordering::Relaxed) & self.side.cancelled_bit() != 0
+    }
+}
+
+impl<S: Stop> Stop for Branch<'_, S> {
+    #[inline]
+    fn check(&self) -> Result<(), StopReason> {
+        self.split.parent.check()?;
+        if self.is_cancelled() {
+            Err(StopReason::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+
+    #[inline]
+    fn should_stop(&self) -> bool {
+        self.is_cancelled() || self.split.parent.should_stop()
+    }
+}
+
+impl<S: core::fmt::Debug> core::fmt::Debug for Branch<'_, S> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let cancelled = self.split.state.load(Ordering::Relaxed) & self.side.cancelled_bit();
+        f.debug_struct("Branch")
+            .field("side", &self.side)
+            .field("cancelled", &(cancelled != 0))
+            .field("parent", &self.split.parent)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{StopExt, StopSource, Unstoppable};
+
+    #[test]
+    fn first_finish_wins_and_cancels_sibling() {
+        let split = Unstoppable.split();
+        let (left, right) = split.branches();
+        assert!(!left.should_stop());
+        assert_eq!(split.winner(), None);
+
+        assert!(right.finish());
+        assert!(left.should_stop());
+        assert_eq!(left.check(), Err(StopReason::Cancelled));
+        assert!(right.check().is_ok());
+
+        // The loser finishing afterwards changes nothing
+        assert!(!left.finish());
+        assert!(!right.should_stop());
+        assert_eq!(split.merge(), Ok(Some(Side::Right)));
+    }
+
+    #[test]
+    fn branch_cancel_is_local() {
+        let source = StopSource::new();
+        let split = source.as_ref().split();
+        let (left, right) = split.branches();
+
+        right.cancel();
+        assert!(right.is_cancelled());
+        assert!(!left.should_stop());
+        left.cancel_sibling();
+        assert_eq!(split.winner(), None);
+        assert_eq!(split.merge(), Ok(None));
+        assert!(!source.is_cancelled());
+    }
+
+    #[test]
+    fn parent_reason_wins_on_merge() {
+        let source = StopSource::new();
+        let split = source.as_ref().split();
+        let (left, right) = split.branches();
+        assert!(left.finish());
+
+        source.cancel();
+        assert!(left.should_stop());
+        assert_eq!(right.check(), Err(StopReason::Cancelled));
+        assert_eq!(split.merge(), Err(StopReason::Cancelled));
+    }
+
+    #[test]
+    fn branches_split_recursively() {
+        let outer = Unstoppable.split();
+        let (left, _right) = outer.branches();
+        let inner = left.split();
+        let (inner_left, inner_right) = inner.branches();
+        assert_eq!(inner_left.sibling().side(), Side::Right);
+
+        left.cancel();
+        assert!(inner_left.should_stop());
+        assert!(inner_right.should_stop());
+        assert_eq!(inner.into_inner().side(), Side::Left);
+    }
+
+    #[test]
+    fn is_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<SplitStop<StopSource>>();
+        assert_send_sync::<Branch<'_, StopSource>>();
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod std_tests {
+    use super::*;
+    use crate::{StopExt, Unstoppable};
+
+    #[test]
+    fn exactly_one_winner_under_contention() {
+        for _ in 0..100 {
+            let split = SplitStop::new(Unstoppable);
+            let (left, right) = split.branches();
+            let (a, b) = std::thread::scope(|s| {
+                let a = s.spawn(move || left.finish());
+                let b = s.spawn(move || right.finish());
+                (a.join().unwrap(), b.join().unwrap())
+            });
+            assert!(a ^ b);
+            assert_eq!(
+                split.winner(),
+                Some(if a { Side::Left } else { Side::Right })
+            );
+        }
+    }
+
+    #[test]
+    fn debug_and_accessors() {
+        let split = Unstoppable.split();
+        let (left, _) = split.branches();
+        left.cancel();
+        assert_eq!(split.branch(Side::Left).side(), Side::Left);
+        assert_eq!(Side::Left.other(), Side::Right);
+        assert!(format!("{split:?}").contains("left_cancelled: true"));
+        assert!(format!("{left:?}").contains("cancelled: true"));
+        let _: &Unstoppable = split.parent();
+    }
+}
@@ -0,0 +1,301 @@
+//! Packed cancellation state shared by the `Stopper` family.
+//!
+//! One atomic word holds everything a cancellable node needs to publish:
+//!
+//! | Bits | Field |
+//! |------|-------|
+//! | 0 | cancelled flag |
+//! | 1..8 | reason code (see [`crate::reason`]) |
+//! | 8.. | generation: number of state transitions so far |
+//!
+//! The hot path (`check()`/`should_stop()`) stays a single atomic load, and
+//! writers publish flag, reason and generation together, so a reader can
+//! never see a cancelled flag paired with a stale reason or generation.
+//!
+//! The word is an `AtomicU64` (56-bit generation) on targets with 64-bit
+//! atomics and an `AtomicU32` (24-bit generation) elsewhere. `cancel()`
+//! saturates the generation at its maximum instead of wrapping, so it never
+//! overflows into the flag bits; once saturated, further transitions repeat
+//! the maximum. Only [`PackedState::renew()`], used by
+//! [`GenerationStopper`](crate::GenerationStopper), wraps it back to 0.
+
+#[cfg(not(target_has_atomic = "64"))]
+use core::sync::atomic::AtomicU32 as AtomicWord;
+#[cfg(target_has_atomic = "64")]
+use core::sync::atomic::AtomicU64 as AtomicWord;
+use core::sync::atomic::Ordering;
+
+use crate::StopReason;
+use crate::reason::{NOT_STOPPED, decode, encode};
+
+#[cfg(target_has_atomic = "64")]
+type Word = u64;
+#[cfg(not(target_has_atomic = "64"))]
+type Word = u32;
+
+const CANCELLED_BIT: Word = 1;
+const REASON_SHIFT: u32 = 1;
+const REASON_MASK: Word = 0x7F << REASON_SHIFT;
+const GENERATION_SHIFT: u32 = 8;
+const MAX_GENERATION: Word = Word::MAX >> GENERATION_SHIFT;
+
+/// A decoded view of one load of a [`PackedState`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Snapshot(Word);
+
+impl Snapshot {
+    #[inline]
+    const fn pack(reason: Option<StopReason>, generation: Word) -> Self {
+        let bits = match reason {
+            Some(reason) => CANCELLED_BIT | ((encode(reason) as Word) << REASON_SHIFT),
+            None => (NOT_STOPPED as Word) << REASON_SHIFT,
+        };
+        Self(bits | (generation << GENERATION_SHIFT))
+    }
+
+    /// Whether the cancelled flag is set.
+    #[inline]
+    pub(crate) const fn is_cancelled(self) -> bool {
+        self.0 & CANCELLED_BIT != 0
+    }
+
+    /// The stored reason, if cancelled.
+    #[inline]
+    pub(crate) fn reason(self) -> Option<StopReason> {
+        if self.is_cancelled() {
+            Some(decode(((self.0 & REASON_MASK) >> REASON_SHIFT) as u8))
+        } else {
+            None
+        }
+    }
+
+    /// The number of transitions recorded so far (saturating).
+    #[inline]
+    pub(crate) const fn generation(self) -> u64 {
+        // A no-op cast where `Word` is already `u64`
+        #[allow(clippy::unnecessary_cast)]
+        let generation = self.raw_generation() as u64;
+        generation
+    }
+
+    #[inline]
+    const fn raw_generation(self) -> Word {
+        self.0 >> GENERATION_SHIFT
+    }
+
+    /// `Err(reason)` if cancelled, else `Ok(())`.
+    #[inline]
+    pub(crate) fn check(self) -> Result<(), StopReason> {
+        match self.reason() {
+            Some(reason) => Err(reason),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The packed `{cancelled, reason, generation}` word.
+pub(crate) struct PackedState(AtomicWord);
+
+impl PackedState {
+    /// A fresh state, at generation 0 or already cancelled at generation 1.
+    #[inline]
+    pub(crate) const fn new(cancelled: bool) -> Self {
+        if cancelled {
+            Self::with_reason(Some(StopReason::Cancelled), 1)
+        } else {
+            Self::with_reason(None, 0)
+        }
+    }
+
+    /// A state at `generation` (saturating), cancelled with `reason` if
+    /// given.
+    #[inline]
+    pub(crate) const fn with_reason(reason: Option<StopReason>, generation: u64) -> Self {
+        // No-op casts where `Word` is already `u64`
+        #[allow(clippy::unnecessary_cast)]
+        let generation = if generation > MAX_GENERATION as u64 {
+            MAX_GENERATION
+        } else {
+            generation as Word
+        };
+        Self(AtomicWord::new(Snapshot::pack(reason, generation).0))
+    }
+
+    /// Load the whole state.
+    ///
+    /// `order` must be a valid load ordering. It is matched explicitly
+    /// rather than passed through: `AtomicU64::load` panics on `Release`
+    /// and `AcqRel`, and with a runtime ordering those checks (plus a jump
+    /// table) would land on the `check()` hot path. Where the orderings
+    /// compile to the same instruction, as on x86, the arms merge into one
+    /// load.
+    #[inline(always)]
+    pub(crate) fn load(&self, order: Ordering) -> Snapshot {
+        Snapshot(match order {
+            Ordering::Relaxed => self.0.load(Ordering::Relaxed),
+            Ordering::Acquire => self.0.load(Ordering::Acquire),
+            _ => self.0.load(Ordering::SeqCst),
+        })
+    }
+
+    /// Whether the cancelled flag is set.
+    #[inline(always)]
+    pub(crate) fn is_cancelled(&self, order: Ordering) -> bool {
+        self.load(order).is_cancelled()
+    }
+
+    /// `Err(reason)` if cancelled, else `Ok(())`.
+    #[inline(always)]
+    pub(crate) fn check(&self, order: Ordering) -> Result<(), StopReason> {
+        self.load(order).check()
+    }
+
+    /// Set the cancelled flag with `reason` and bump the generation.
+    ///
+    /// Returns `true` if this call performed the transition, `false` if the
+    /// state was already cancelled (the first reason is kept). `order` is
+    /// the success ordering; pass at least `Release` to publish prior
+    /// writes.
+    #[inline]
+    pub(crate) fn cancel(&self, reason: StopReason, order: Ordering) -> bool {
+        let mut current = self.0.load(Ordering::Relaxed);
+        loop {
+            let snapshot = Snapshot(current);
+            if snapshot.is_cancelled() {
+                return false;
+            }
+            let generation = snapshot.raw_generation().saturating_add(1);
+            let next = Snapshot::pack(Some(reason), generation.min(MAX_GENERATION));
+            match self
+                .0
+                .compare_exchange_weak(current, next.0, order, Ordering::Relaxed)
+            {
+                Ok(_) => return true,
+                Err(actual) => current = actual,
+            }
+        }
+    }
+
+    /// Clear the cancelled flag and bump the generation, whether or not the
+    /// state was cancelled.
+    ///
+    /// Unlike `cancel()`, the generation wraps to 0 after its maximum, so
+    /// renewing keeps producing a generation different from the last one.
+    /// `order` is the success ordering.
+    #[inline]
+    pub(crate) fn renew(&self, order: Ordering) {
+        let mut current = self.0.load(Ordering::Relaxed);
+        loop {
+            let generation = Snapshot(current).raw_generation().wrapping_add(1) & MAX_GENERATION;
+            let next = Snapshot::pack(None, generation);
+            match self
+                .0
+                .compare_exchange_weak(current, next.0, order, Ordering::Relaxed)
+            {
+                Ok(_) => return,
+                Err(actual) => current = actual,
+            }
+        }
+    }
+}
+
+impl core::fmt::Debug for PackedState {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let snapshot = self.load(Ordering::Relaxed);
+        f.debug_struct("PackedState")
+            .field("reason", &snapshot.reason())
+            .field("generation", &snapshot.generation())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn starts_clear_at_generation_zero() {
+        let state = PackedState::new(false);
+        let snapshot = state.load(Ordering::Relaxed);
+        assert!(!snapshot.is_cancelled());
+        assert_eq!(snapshot.reason(), None);
+        assert_eq!(snapshot.generation(), 0);
+        assert_eq!(state.check(Ordering::Relaxed), Ok(()));
+    }
+
+    #[test]
+    fn cancel_sets_reason_and_bumps_generation_once() {
+        let state = PackedState::new(false);
+        assert!(state.cancel(StopReason::TimedOut, Ordering::Relaxed));
+        assert!(!state.cancel(StopReason::Failed, Ordering::Relaxed));
+
+        let snapshot = state.load(Ordering::Relaxed);
+        assert!(snapshot.is_cancelled());
+        assert_eq!(snapshot.reason(), Some(StopReason::TimedOut));
+        assert_eq!(snapshot.generation(), 1);
+    }
+
+    #[test]
+    fn pre_cancelled_reports_cancelled() {
+        let state = PackedState::new(true);
+        assert!(state.is_cancelled(Ordering::Relaxed));
+        assert_eq!(state.check(Ordering::Relaxed), Err(StopReason::Cancelled));
+        assert_eq!(state.load(Ordering::Relaxed).generation(), 1);
+    }
+
+    #[test]
+    fn every_reason_round_trips() {
+        for reason in [
+            StopReason::Cancelled,
+            StopReason::TimedOut,
+            StopReason::Failed,
+        ] {
+            let snapshot = Snapshot::pack(Some(reason), 7);
+            assert_eq!(snapshot.reason(), Some(reason));
+            assert_eq!(snapshot.generation(), 7);
+        }
+    }
+
+    #[test]
+    fn generation_saturates_without_touching_flags() {
+        let state = PackedState(AtomicWord::new(Snapshot::pack(None, MAX_GENERATION).0));
+        assert!(state.cancel(StopReason::Cancelled, Ordering::Relaxed));
+        let snapshot = state.load(Ordering::Relaxed);
+        assert_eq!(snapshot.raw_generation(), MAX_GENERATION);
+        assert_eq!(snapshot.reason(), Some(StopReason::Cancelled));
+    }
+
+    #[test]
+    fn renew_clears_and_wraps() {
+        let state = PackedState::new(true);
+        state.renew(Ordering::Relaxed);
+        let snapshot = state.load(Ordering::Relaxed);
+        assert_eq!(snapshot.reason(), None);
+        assert_eq!(snapshot.generation(), 2);
+
+        let state = PackedState(AtomicWord::new(
+            Snapshot::pack(Some(StopReason::Failed), MAX_GENERATION).0,
+        ));
+        state.renew(Ordering::Relaxed);
+        assert_eq!(state.load(Ordering::Relaxed), Snapshot::pack(None, 0));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn concurrent_cancels_transition_once() {
+        use std::sync::Arc;
+
+        let state = Arc::new(PackedState::new(false));
+        let winners: usize = (0..8)
+            .map(|_| {
+                let state = Arc::clone(&state);
+                std::thread::spawn(move || state.cancel(StopReason::Cancelled, Ordering::AcqRel))
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|t| t.join().unwrap() as usize)
+            .sum();
+        assert_eq!(winners, 1);
+        assert_eq!(state.load(Ordering::Relaxed).generation(), 1);
+    }
+}
@@ -0,0 +1,277 @@
+//! Resumable stepped computations.
+//!
+//! Incremental algorithms made cancellable all grow the same loop: do one
+//! step, check the stop, keep the partial state so the caller can resume.
+//! [`race_steps()`] is that loop. The step function takes the state by
+//! value and returns it wrapped in a [`StepResult`]; the stop is checked
+//! before every step, and a stop hands the latest state back in
+//! [`StepOutcome::Stopped`] instead of discarding it.
+//!
+//! With `std`, [`race_steps_with_timeout()`] also gives every step its own
+//! deadline. Works in `no_std` otherwise.
+//!
+//! # Example
+//!
+//! ```rust
+//! use almost_enough::{BudgetStop, StepOutcome, StepResult, StopReason, race_steps};
+//!
+//! // Sum 1..=100 one term per step, with room for 10 checks
+//! let budget = BudgetStop::checks(10);
+//! let outcome = race_steps(&budget, (1u32, 0u32), |(n, sum)| {
+//!     if n > 100 {
+//!         StepResult::Done((n, sum))
+//!     } else {
+//!         StepResult::Continue((n + 1, sum + n))
+//!     }
+//! });
+//!
+//! // Ten steps ran; resume from `state` with a fresh budget later
+//! let StepOutcome::Stopped { state, reason } = outcome else { unreachable!() };
+//! assert_eq!(state, (11, 55));
+//! assert_eq!(reason, StopReason::TimedOut);
+//! ```
+
+use crate::{Stop, StopReason};
+
+/// What a step function returns: the new state, and whether to go on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StepResult<S> {
+    /// More steps are needed.
+    Continue(S),
+    /// The computation is finished.
+    Done(S),
+}
+
+/// How a stepped computation ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[must_use]
+pub enum StepOutcome<S> {
+    /// A step returned [`StepResult::Done`].
+    Completed(S),
+    /// The stop fired between steps; `state` is the last state produced
+    /// (or the initial state if no step ran), ready to resume from.
+    Stopped {
+        /// The state to resume from.
+        state: S,
+        /// Why the computation stopped.
+        reason: StopReason,
+    },
+}
+
+impl<S> StepOutcome<S> {
+    /// Whether the computation ran to completion.
+    #[inline]
+    pub fn is_completed(&self) -> bool {
+        matches!(self, Self::Completed(_))
+    }
+
+    /// The stop reason, if the computation was stopped.
+    #[inline]
+    pub fn reason(&self) -> Option<StopReason> {
+        match self {
+            Self::Completed(_) => None,
+            Self::Stopped { reason, .. } => Some(*reason),
+        }
+    }
+
+    /// The final or resumable state, however the computation ended.
+    #[inline]
+    pub fn into_state(self) -> S {
+        match self {
+            Self::Completed(state) | Self::Stopped { state, .. } => state,
+        }
+    }
+
+    /// `Ok(state)` if completed, else `Err(reason)`, dropping the partial
+    /// state.
+    #[inline]
+    pub fn into_result(self) -> Result<S, StopReason> {
+        match self {
+            Self::Completed(state) => Ok(state),
+            Self::Stopped { reason, .. } => Err(reason),
+        }
+    }
+}
+
+/// Run `step` from `init` until it returns [`StepResult::Done`] or `stop`
+/// fires.
+///
+/// `stop` is checked before every step, including the first, so an
+/// already-stopped `stop` returns `init` untouched. A step is never
+/// interrupted; long steps should check `stop` themselves.
+pub fn race_steps<S>(
+    stop: &impl Stop,
+    init: S,
+    mut step: impl FnMut(S) -> StepResult<S>,
+) -> StepOutcome<S> {
+    let mut state = init;
+    loop {
+        if let Err(reason) = stop.check() {
+            return StepOutcome::Stopped { state, reason };
+        }
+        match step(state) {
+            StepResult::Continue(next) => state = next,
+            StepResult::Done(done) => return StepOutcome::Completed(done),
+        }
+    }
+}
+
+/// Like [`race_steps()`], but every step also gets `step_timeout` to run.
+///
+/// Each step receives a stop that fires on `stop` or on its own deadline,
+/// for steps that check internally. A step that returns
+/// [`StepResult::Continue`] after its deadline stops the computation with
+/// [`StopReason::TimedOut`]; one that returns [`StepResult::Done`] late
+/// still completes, since its work is finished.
+///
+/// # Example
+///
+/// ```rust
+/// use almost_enough::{Stop, StepResult, Unstoppable, race_steps_with_timeout};
+/// use std::time::Duration;
+///
+/// let outcome = race_steps_with_timeout(&Unstoppable, Duration::from_secs(1), 0u32, |n, stop| {
+///     assert!(stop.check().is_ok()); // fresh deadline every step
+///     if n == 3 { StepResult::Done(n) } else { StepResult::Continue(n + 1) }
+/// });
+/// assert_eq!(outcome.into_result(), Ok(3));
+/// ```
+#[cfg(feature = "std")]
+pub fn race_steps_with_timeout<St: Stop, S>(
+    stop: &St,
+    step_timeout: std::time::Duration,
+    init: S,
+    mut step: impl FnMut(S, &crate::WithTimeout<&St>) -> StepResult<S>,
+) -> StepOutcome<S> {
+    let mut state = init;
+    loop {
+        if let Err(reason) = stop.check() {
+            return StepOutcome::Stopped { state, reason };
+        }
+        let step_stop = crate::WithTimeout::new(stop, step_timeout);
+        match step(state, &step_stop) {
+            StepResult::Continue(next) => {
+                if let Err(reason) = step_stop.check() {
+                    return StepOutcome::Stopped {
+                        state: next,
+                        reason,
+                    };
+                }
+                state = next;
+            }
+            StepResult::Done(done) => return StepOutcome::Completed(done),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BudgetStop, StopSource, Unstoppable};
+
+    fn count_to(limit: u32) -> impl FnMut(u32) -> StepResult<u32> {
+        move |n| {
+            if n >= limit {
+                StepResult::Done(n)
+            } else {
+                StepResult::Continue(n + 1)
+            }
+        }
+    }
+
+    #[test]
+    fn runs_to_completion() {
+        let outcome = race_steps(&Unstoppable, 0, count_to(10));
+        assert_eq!(outcome, StepOutcome::Completed(10));
+        assert!(outcome.is_completed());
+        assert_eq!(outcome.reason(), None);
+    }
+
+    #[test]
+    fn already_stopped_returns_init() {
+        let source = StopSource::cancelled();
+        let mut steps = 0;
+        let outcome = race_steps(&source, 5, |n| {
+            steps += 1;
+            StepResult::Continue(n)
+        });
+        assert_eq!(steps, 0);
+        assert_eq!(outcome.reason(), Some(StopReason::Cancelled));
+        assert_eq!(outcome.into_state(), 5);
+    }
+
+    #[test]
+    fn stopped_keeps_partial_state_and_resumes() {
+        let outcome = race_steps(&BudgetStop::checks(4), 0, count_to(10));
+        assert_eq!(
+            outcome,
+            StepOutcome::Stopped {
+                state: 4,
+                reason: StopReason::TimedOut
+            }
+        );
+
+        let resumed = race_steps(&Unstoppable, outcome.into_state(), count_to(10));
+        assert_eq!(resumed.into_result(), Ok(10));
+    }
+
+    #[test]
+    fn into_result_drops_partial_state() {
+        let source = StopSource::cancelled();
+        let outcome = race_steps(&source, 0, count_to(10));
+        assert_eq!(outcome.into_result(), Err(StopReason::Cancelled));
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod std_tests {
+    use super::*;
+    use crate::{StopSource, Unstoppable};
+    use std::time::Duration;
+
+    #[test]
+    fn late_step_times_out() {
+        let outcome =
+            race_steps_with_timeout(&Unstoppable, Duration::from_millis(5), 0, |n, stop| {
+                while !stop.should_stop() {
+                    std::thread::yield_now();
+                }
+                StepResult::Continue(n + 1)
+            });
+        assert_eq!(
+            outcome,
+            StepOutcome::Stopped {
+                state: 1,
+                reason: StopReason::TimedOut
+            }
+        );
+    }
+
+    #[test]
+    fn late_done_still_completes() {
+        let outcome = race_steps_with_timeout(&Unstoppable, Duration::ZERO, 7, |n, stop| {
+            assert!(stop.should_stop());
+            StepResult::Done(n)
+        });
+        assert_eq!(outcome, StepOutcome::Completed(7));
+    }
+
+    #[test]
+    fn outer_stop_between_steps() {
+        let source = StopSource::new();
+        let outcome = race_steps_with_timeout(&source, Duration::from_secs(60), 0, |n, stop| {
+            assert!(stop.check().is_ok());
+            if n == 2 {
+                source.cancel();
+            }
+            StepResult::Continue(n + 1)
+        });
+        assert_eq!(
+            outcome,
+            StepOutcome::Stopped {
+                state: 3,
+                reason: StopReason::Cancelled
+            }
+        );
+    }
+}
@@ -198,6 +198,18 @@ impl Stop for StopToken {
     }
 }
 
+impl AsRef<dyn Stop> for StopToken {
+    #[inline]
+    fn as_ref(&self) -> &(dyn Stop + 'static) {
+        match &self.inner {
+            StopTokenInner::None => &crate::Unstoppable,
+            StopTokenInner::Relaxed(inner) => &**inner,
+            StopTokenInner::Acquire(inner) => &**inner,
+            StopTokenInner::Dyn(inner) => &**inner,
+        }
+    }
+}
+
 /// Zero-cost conversion: reuses the Stopper's Arc. Direct atomic dispatch, no vtable.
 impl From<crate::Stopper> for StopToken {
     #[inline]
@@ -28,35 +28,181 @@
 //! a recipient from cancelling. If you need that, use
 //! [`StopSource`](crate::StopSource)/[`StopRef`](crate::StopRef).
 //!
+//! # State Layout
+//!
+//! The shared state is a single atomic word packing the cancelled flag, the
+//! stop reason and a generation counter, so `check()` is one atomic load and
+//! a reader never sees the flag without its matching reason. The counter is
+//! exposed as [`Stopper::epoch()`].
+//!
 //! # Memory Ordering
 //!
 //! Uses Relaxed ordering for best performance. If you need to synchronize
-//! other memory writes with cancellation, use [`SyncStopper`](crate::SyncStopper).
+//! other memory writes with cancellation, use [`SyncStopper`](crate::SyncStopper),
+//! or [`Stopper::with_load_ordering()`] for readers that must see data
+//! published before the cancel. Checks stay a single Relaxed load until
+//! cancellation is observed; only then is the stronger ordering paid for.
+//!
+//! # Waiting
+//!
+//! With the `std` feature, [`Stopper::wait()`] and [`Stopper::wait_timeout()`]
+//! block the calling thread until cancellation instead of polling. The
+//! blocking path is only touched when a thread is actually waiting, so
+//! `cancel()` stays a single compare-exchange plus one load otherwise.
+//! [`Stopper::wait_cancelled()`] is the async counterpart: a runtime-agnostic
+//! future woken by `cancel()`.
+//! It wraps a [`StopListener`] from [`Stopper::listen()`], which can also
+//! be blocked on or checked without blocking, and resolves exactly once.
+//!
+//! [`Stopper::cancel_and_wait()`] goes the other way: it waits for workers
+//! registered with [`Stopper::worker()`] to finish after the cancel.
 
 use alloc::sync::Arc;
-use core::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "std")]
+use core::future::Future;
+#[cfg(feature = "std")]
+use core::pin::Pin;
+#[cfg(feature = "std")]
+use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::Ordering;
+#[cfg(feature = "std")]
+use core::task::{Context, Poll, Waker};
+#[cfg(feature = "std")]
+use std::sync::{Condvar, Mutex};
+#[cfg(feature = "std")]
+use std::time::{Duration, Instant};
 
-use crate::{Stop, StopReason};
+#[cfg(feature = "std")]
+use crate::TraceContext;
+use crate::state::{PackedState, Snapshot};
+use crate::{NodeLabel, Stop, StopReason};
 
 /// Inner state for [`Stopper`] — implements [`Stop`] directly so that
 /// `Arc<StopperInner>` can be widened to `Arc<dyn Stop>` without double-wrapping.
 pub(crate) struct StopperInner {
-    cancelled: AtomicBool,
+    state: PackedState,
+    label: Option<NodeLabel>,
+    /// Fence issued after a check observes cancellation; `Relaxed` for none.
+    load: Ordering,
+    #[cfg(feature = "std")]
+    waiters: Waiters,
+    #[cfg(feature = "std")]
+    pub(crate) workers: crate::workers::Workers,
+    #[cfg(feature = "std")]
+    trace: std::sync::OnceLock<TraceContext>,
+    #[cfg(feature = "debug-history")]
+    pub(crate) history: Arc<crate::ownership::History>,
 }
 
-impl Stop for StopperInner {
-    #[inline]
-    fn check(&self) -> Result<(), StopReason> {
-        if self.cancelled.load(Ordering::Relaxed) {
-            Err(StopReason::Cancelled)
+impl StopperInner {
+    fn new(cancelled: bool) -> Self {
+        Self::with_reason(if cancelled {
+            Some(StopReason::Cancelled)
         } else {
-            Ok(())
+            None
+        })
+    }
+
+    fn with_reason(reason: Option<StopReason>) -> Self {
+        Self {
+            // A stopper created cancelled has made its one transition
+            state: PackedState::with_reason(reason, u64::from(reason.is_some())),
+            label: None,
+            load: Ordering::Relaxed,
+            #[cfg(feature = "std")]
+            waiters: Waiters::default(),
+            #[cfg(feature = "std")]
+            workers: crate::workers::Workers::default(),
+            #[cfg(feature = "std")]
+            trace: std::sync::OnceLock::new(),
+            #[cfg(feature = "debug-history")]
+            history: Arc::default(),
         }
     }
+}
 
-    #[inline]
+/// Blocking state for [`Stopper::wait()`] and [`Stopper::wait_cancelled()`].
+///
+/// `count` lets `cancel()` skip the mutex entirely when nobody is waiting.
+/// Waiters bump `count` before checking the flag and `cancel()` stores the
+/// flag before reading `count` (both `SeqCst`), so at least one side always
+/// sees the other.
+#[cfg(feature = "std")]
+#[derive(Default)]
+struct Waiters {
+    count: AtomicUsize,
+    lock: Mutex<Wakers>,
+    cond: Condvar,
+}
+
+/// Wakers of pending [`StopListener`]s, keyed by slot so a dropped
+/// future removes its own entry.
+#[cfg(feature = "std")]
+#[derive(Default)]
+struct Wakers {
+    slots: alloc::vec::Vec<Option<Waker>>,
+    free: alloc::vec::Vec<usize>,
+}
+
+#[cfg(feature = "std")]
+impl Wakers {
+    fn insert(&mut self, waker: Waker) -> usize {
+        match self.free.pop() {
+            Some(key) => {
+                self.slots[key] = Some(waker);
+                key
+            }
+            None => {
+                self.slots.push(Some(waker));
+                self.slots.len() - 1
+            }
+        }
+    }
+
+    fn remove(&mut self, key: usize) {
+        self.slots[key] = None;
+        self.free.push(key);
+    }
+
+    /// Take every registered waker, leaving the slots allocated.
+    fn take_all(&mut self) -> alloc::vec::Vec<Waker> {
+        self.slots.iter_mut().filter_map(Option::take).collect()
+    }
+}
+
+impl StopperInner {
+    /// Load the state, applying the load ordering once cancelled.
+    ///
+    /// The word is always loaded Relaxed, so the running path is one fixed
+    /// load whatever the ordering. A cancelled state is followed by a fence
+    /// of the configured ordering, which synchronizes with the cancel as a
+    /// load of that ordering would.
+    #[inline(always)]
+    fn observe(&self) -> Snapshot {
+        let snapshot = self.state.load(Ordering::Relaxed);
+        if snapshot.is_cancelled() {
+            self.fence();
+        }
+        snapshot
+    }
+
+    #[cold]
+    fn fence(&self) {
+        if self.load != Ordering::Relaxed {
+            core::sync::atomic::fence(self.load);
+        }
+    }
+}
+
+impl Stop for StopperInner {
+    #[inline(always)]
+    fn check(&self) -> Result<(), StopReason> {
+        self.observe().check()
+    }
+
+    #[inline(always)]
     fn should_stop(&self) -> bool {
-        self.cancelled.load(Ordering::Relaxed)
+        self.observe().is_cancelled()
     }
 }
 
@@ -93,34 +239,129 @@ impl Stop for StopperInner {
 /// - Size: 8 bytes (one pointer)
 /// - `check()`: ~1-2ns (single atomic load with Relaxed ordering)
 /// - `clone()`: atomic increment
-/// - `cancel()`: atomic store
+/// - `cancel()`: one compare-exchange on the packed state word
 /// - `into() -> StopToken`: zero-cost (Arc pointer widening)
 #[derive(Debug, Clone)]
 pub struct Stopper {
     pub(crate) inner: Arc<StopperInner>,
+    /// Held only for its `Drop`, which forgets this handle's backtrace.
+    #[cfg(feature = "debug-history")]
+    #[allow(dead_code)]
+    site: crate::ownership::CloneSite,
 }
 
 impl Stopper {
-    /// Create a new stopper.
+    /// Wrap shared state in a new handle.
     #[inline]
-    pub fn new() -> Self {
+    pub(crate) fn from_inner(inner: Arc<StopperInner>) -> Self {
         Self {
-            inner: Arc::new(StopperInner {
-                cancelled: AtomicBool::new(false),
-            }),
+            #[cfg(feature = "debug-history")]
+            site: crate::ownership::CloneSite::record(&inner.history),
+            inner,
         }
     }
 
+    /// Create a new stopper.
+    #[inline]
+    pub fn new() -> Self {
+        Self::from_inner(Arc::new(StopperInner::new(false)))
+    }
+
     /// Create a stopper that is already cancelled.
     ///
     /// Useful for testing or when you want to signal immediate stop.
     #[inline]
     pub fn cancelled() -> Self {
-        Self {
-            inner: Arc::new(StopperInner {
-                cancelled: AtomicBool::new(true),
-            }),
-        }
+        Self::from_inner(Arc::new(StopperInner::new(true)))
+    }
+
+    /// Create a stopper tagged with a [`NodeLabel`].
+    ///
+    /// A [`ChildStopper`](crate::ChildStopper) created from it with
+    /// [`with_parent()`](crate::ChildStopper::with_parent) reports the label
+    /// from [`cancelled_by()`](crate::ChildStopper::cancelled_by) when this
+    /// stopper fires.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use almost_enough::{NodeLabel, Stopper};
+    ///
+    /// let server = Stopper::with_label("server");
+    /// assert_eq!(server.label(), Some(NodeLabel::Name("server")));
+    /// ```
+    #[inline]
+    pub fn with_label(label: impl Into<NodeLabel>) -> Self {
+        let mut inner = StopperInner::new(false);
+        inner.label = Some(label.into());
+        Self::from_inner(Arc::new(inner))
+    }
+
+    /// Create a stopper whose checks observe cancellation with `load`
+    /// ordering.
+    ///
+    /// Applies to `check()`, `should_stop()`, `is_cancelled()` and
+    /// [`epoch()`](Self::epoch). With `Acquire`, a thread that sees the
+    /// stopper cancelled also sees every write made before the `cancel()`,
+    /// as with [`SyncStopper`](crate::SyncStopper). [`new()`](Self::new)
+    /// is `with_load_ordering(Relaxed)`.
+    ///
+    /// Checks on a running stopper are one Relaxed load whatever `load`
+    /// is; the ordering is applied as a fence once cancellation is
+    /// observed. `cancel()` always publishes with at least `Release`
+    /// (`SeqCst` with the `std` feature), so there is no store side to
+    /// configure.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `load` is `Release` or `AcqRel`, which are not valid for
+    /// a load.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use almost_enough::{Stop, Stopper};
+    /// use core::sync::atomic::Ordering;
+    ///
+    /// // Readers see data published before the cancel
+    /// let stop = Stopper::with_load_ordering(Ordering::Acquire);
+    /// assert_eq!(stop.load_ordering(), Ordering::Acquire);
+    ///
+    /// stop.cancel();
+    /// assert!(stop.should_stop());
+    /// ```
+    #[track_caller]
+    pub fn with_load_ordering(load: Ordering) -> Self {
+        assert!(
+            matches!(
+                load,
+                Ordering::Relaxed | Ordering::Acquire | Ordering::SeqCst
+            ),
+            "invalid load ordering {load:?}: use Relaxed, Acquire or SeqCst"
+        );
+        let mut inner = StopperInner::new(false);
+        inner.load = load;
+        Self::from_inner(Arc::new(inner))
+    }
+
+    /// The ordering used to observe cancellation.
+    #[inline]
+    pub fn load_ordering(&self) -> Ordering {
+        self.inner.load
+    }
+
+    /// Create a stopper that is cancelled with `reason`, or running if `None`.
+    ///
+    /// Used to restore a [`StopSnapshot`](crate::StopSnapshot).
+    #[cfg(feature = "std")]
+    pub(crate) fn with_reason(reason: Option<StopReason>) -> Self {
+        Self::from_inner(Arc::new(StopperInner::with_reason(reason)))
+    }
+
+    /// The label given to [`with_label()`](Self::with_label), if any.
+    #[inline]
+    pub fn label(&self) -> Option<NodeLabel> {
+        self.inner.label
     }
 
     /// Signal all clones to stop.
@@ -128,13 +369,447 @@ impl Stopper {
     /// This is idempotent - calling it multiple times has no additional effect.
     #[inline]
     pub fn cancel(&self) {
-        self.inner.cancelled.store(true, Ordering::Relaxed);
+        self.cancel_with(StopReason::Cancelled);
+    }
+
+    /// Cancel with `reason`; a no-op if already cancelled.
+    #[inline]
+    pub(crate) fn cancel_with(&self, reason: StopReason) {
+        self.transition(reason, || {});
+    }
+
+    /// Cancel with `reason`, running `won` only if this call performed the
+    /// transition, before any waiter is woken.
+    ///
+    /// Returns whether this call performed the transition.
+    #[inline]
+    fn transition(&self, reason: StopReason, won: impl FnOnce()) -> bool {
+        // The blocking wait() handshake needs SeqCst
+        #[cfg(feature = "std")]
+        let order = Ordering::SeqCst;
+        #[cfg(not(feature = "std"))]
+        let order = Ordering::Release;
+        let first = self.inner.state.cancel(reason, order);
+        if first {
+            won();
+        }
+        #[cfg(feature = "std")]
+        if self.inner.waiters.count.load(Ordering::SeqCst) != 0 {
+            self.wake_waiters();
+        }
+        #[cfg(feature = "log")]
+        if first {
+            crate::logging::stopped(self.inner.label, reason);
+        }
+        first
     }
 
     /// Check if cancellation has been requested.
     #[inline]
     pub fn is_cancelled(&self) -> bool {
-        self.inner.cancelled.load(Ordering::Relaxed)
+        self.inner.should_stop()
+    }
+
+    /// The number of state transitions this stopper has gone through.
+    ///
+    /// Bumped by the `cancel()` that performs the transition (not by
+    /// repeated calls), and read from the same atomic word as the
+    /// cancelled flag, so it costs the same as a check, including the
+    /// [`load_ordering()`](Self::load_ordering). Lock-free structures can
+    /// stamp their own state words with the epoch and later compare it to
+    /// spot work that was started before a cancellation, without checking
+    /// the stop on every operation.
+    ///
+    /// Compare epochs for equality only: a stopper starts at 0, or at 1 if
+    /// created [`cancelled()`](Self::cancelled), and the count saturates
+    /// rather than wrapping on targets without 64-bit atomics.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use almost_enough::Stopper;
+    ///
+    /// let stop = Stopper::new();
+    /// let stamp = stop.epoch();
+    ///
+    /// stop.cancel();
+    /// stop.cancel();
+    /// assert_eq!(stop.epoch(), stamp + 1);
+    /// ```
+    #[inline]
+    pub fn epoch(&self) -> u64 {
+        self.inner.observe().generation()
+    }
+}
+
+#[cfg(feature = "std")]
+impl Stopper {
+    /// Cancel, recording the trace and span IDs of the request that caused
+    /// it.
+    ///
+    /// The trace is only recorded if this call cancels the stopper, and
+    /// the return value says whether it did: an already-cancelled stopper,
+    /// or one that a concurrent `cancel()` got to first, keeps the trace
+    /// of its first cancel, or none. Read it back with
+    /// [`cancel_trace()`](Self::cancel_trace).
+    ///
+    /// The trace is recorded right after the cancel and before blocked
+    /// [`wait()`](Self::wait)ers and listeners are woken, so they always
+    /// see it. A thread polling `check()` at that instant can see the
+    /// cancellation a moment before the trace.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use almost_enough::{Stopper, TraceContext};
+    ///
+    /// let stop = Stopper::new();
+    /// let trace = TraceContext::new([0x4b; 16], [0xa0; 8]);
+    /// assert!(stop.cancel_with_trace(trace));
+    /// assert!(!stop.cancel_with_trace(TraceContext::new([1; 16], [2; 8])));
+    /// assert_eq!(stop.cancel_trace(), Some(trace));
+    /// ```
+    pub fn cancel_with_trace(&self, trace: TraceContext) -> bool {
+        self.transition(StopReason::Cancelled, || {
+            let _ = self.inner.trace.set(trace);
+        })
+    }
+
+    /// The trace recorded by [`cancel_with_trace()`](Self::cancel_with_trace),
+    /// if any.
+    #[inline]
+    pub fn cancel_trace(&self) -> Option<TraceContext> {
+        self.inner.trace.get().copied()
+    }
+
+    /// Block the current thread until this stopper is cancelled.
+    ///
+    /// Returns immediately if already cancelled.
+    ///
+    /// ```rust
+    /// use almost_enough::Stopper;
+    ///
+    /// let stop = Stopper::new();
+    /// let canceller = stop.clone();
+    /// std::thread::spawn(move || canceller.cancel());
+    ///
+    /// stop.wait();
+    /// assert!(stop.is_cancelled());
+    /// ```
+    pub fn wait(&self) {
+        if self.is_cancelled() {
+            return;
+        }
+        let waiters = &self.inner.waiters;
+        waiters.count.fetch_add(1, Ordering::SeqCst);
+        let mut guard = waiters.lock.lock().unwrap_or_else(|e| e.into_inner());
+        while !self.inner.state.is_cancelled(Ordering::SeqCst) {
+            guard = waiters.cond.wait(guard).unwrap_or_else(|e| e.into_inner());
+        }
+        drop(guard);
+        waiters.count.fetch_sub(1, Ordering::SeqCst);
+    }
+
+    /// Block the current thread until cancelled or `timeout` elapses.
+    ///
+    /// Returns `true` if the stopper was cancelled, `false` on timeout.
+    ///
+    /// ```rust
+    /// use almost_enough::Stopper;
+    /// use std::time::Duration;
+    ///
+    /// let stop = Stopper::new();
+    /// assert!(!stop.wait_timeout(Duration::from_millis(10)));
+    ///
+    /// stop.cancel();
+    /// assert!(stop.wait_timeout(Duration::from_secs(60)));
+    /// ```
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        if self.is_cancelled() {
+            return true;
+        }
+        let Some(deadline) = Instant::now().checked_add(timeout) else {
+            self.wait();
+            return true;
+        };
+        let waiters = &self.inner.waiters;
+        waiters.count.fetch_add(1, Ordering::SeqCst);
+        let mut guard = waiters.lock.lock().unwrap_or_else(|e| e.into_inner());
+        let cancelled = loop {
+            if self.inner.state.is_cancelled(Ordering::SeqCst) {
+                break true;
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                break false;
+            }
+            guard = waiters
+                .cond
+                .wait_timeout(guard, remaining)
+                .unwrap_or_else(|e| e.into_inner())
+                .0;
+        };
+        drop(guard);
+        waiters.count.fetch_sub(1, Ordering::SeqCst);
+        cancelled
+    }
+
+    #[cold]
+    fn wake_waiters(&self) {
+        let waiters = &self.inner.waiters;
+        // Taking the lock orders this notify after any waiter that checked
+        // the flag and is about to block.
+        let wakers = waiters
+            .lock
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take_all();
+        waiters.cond.notify_all();
+        // Woken outside the lock: a waker may poll the future inline
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+
+    /// A future that completes when this stopper is cancelled.
+    ///
+    /// Works with any executor: `cancel()` wakes the task directly, no
+    /// polling or timer is involved. Completes immediately if already
+    /// cancelled. Dropping the future deregisters it.
+    ///
+    /// ```rust
+    /// use almost_enough::Stopper;
+    /// use std::future::Future;
+    /// use std::pin::pin;
+    /// use std::task::{Context, Poll, Waker};
+    ///
+    /// let stop = Stopper::new();
+    /// let mut cancelled = pin!(stop.wait_cancelled());
+    /// let mut cx = Context::from_waker(Waker::noop());
+    /// assert!(cancelled.as_mut().poll(&mut cx).is_pending());
+    ///
+    /// stop.cancel();
+    /// assert!(cancelled.as_mut().poll(&mut cx).is_ready());
+    /// ```
+    pub fn wait_cancelled(&self) -> WaitCancelled {
+        WaitCancelled {
+            listener: self.listen(),
+        }
+    }
+
+    /// Register a [`StopListener`] for this stopper's cancellation.
+    ///
+    /// The listener can be waited on, checked, or awaited, and resolves
+    /// exactly once. Registration is cheap: no lock is taken until the
+    /// listener is first polled.
+    ///
+    /// ```rust
+    /// use almost_enough::Stopper;
+    ///
+    /// let stop = Stopper::new();
+    /// let listener = stop.listen();
+    /// let canceller = stop.clone();
+    /// std::thread::spawn(move || canceller.cancel());
+    ///
+    /// listener.wait();
+    /// assert!(stop.is_cancelled());
+    /// ```
+    #[inline]
+    pub fn listen(&self) -> StopListener {
+        StopListener {
+            stopper: self.clone(),
+            key: None,
+            notified: false,
+        }
+    }
+}
+
+/// A one-shot registration for a [`Stopper`]'s cancellation, from
+/// [`Stopper::listen()`].
+///
+/// A listener can be waited on by blocking ([`wait()`](Self::wait),
+/// [`wait_timeout()`](Self::wait_timeout)), checked without blocking
+/// ([`try_wait()`](Self::try_wait)), or awaited as a [`Future`] on any
+/// executor. It resolves once: after it has observed the cancellation it
+/// holds no waker slot, and every later call reports it as notified
+/// without touching the stopper's lock.
+///
+/// Creating a listener is one `Arc` clone; a waker slot is only taken the
+/// first time it is polled while the stopper is still running, and freed
+/// when it resolves or is dropped.
+///
+/// Requires the `std` feature.
+///
+/// # Example
+///
+/// ```rust
+/// use almost_enough::Stopper;
+/// use std::future::Future;
+/// use std::pin::pin;
+/// use std::task::{Context, Waker};
+///
+/// let stop = Stopper::new();
+/// let mut listener = pin!(stop.listen());
+/// let mut cx = Context::from_waker(Waker::noop());
+/// assert!(listener.as_mut().poll(&mut cx).is_pending());
+///
+/// stop.cancel();
+/// assert!(listener.as_mut().poll(&mut cx).is_ready());
+/// assert!(listener.is_notified());
+/// ```
+#[cfg(feature = "std")]
+#[must_use = "a listener does nothing unless waited on or polled"]
+pub struct StopListener {
+    stopper: Stopper,
+    /// Slot in the stopper's waker list, while registered.
+    key: Option<usize>,
+    notified: bool,
+}
+
+#[cfg(feature = "std")]
+impl StopListener {
+    /// The stopper this listener waits on.
+    #[inline]
+    pub fn stopper(&self) -> &Stopper {
+        &self.stopper
+    }
+
+    /// Returns `true` once this listener has observed the cancellation,
+    /// through any of its methods or as a future.
+    #[inline]
+    pub fn is_notified(&self) -> bool {
+        self.notified
+    }
+
+    /// Observe the cancellation without blocking.
+    ///
+    /// Returns `true` if the stopper is cancelled, marking the listener
+    /// notified and freeing its waker slot.
+    pub fn try_wait(&mut self) -> bool {
+        if !self.notified && self.stopper.inner.state.is_cancelled(Ordering::SeqCst) {
+            self.resolve();
+        }
+        self.notified
+    }
+
+    /// Block the current thread until the stopper is cancelled.
+    pub fn wait(mut self) {
+        if !self.try_wait() {
+            self.stopper.wait();
+            self.resolve();
+        }
+    }
+
+    /// Block the current thread until the stopper is cancelled or
+    /// `timeout` elapses.
+    ///
+    /// Returns `true` if notified, `false` on timeout; the listener can be
+    /// waited on again after a timeout.
+    pub fn wait_timeout(&mut self, timeout: Duration) -> bool {
+        if !self.try_wait() && self.stopper.wait_timeout(timeout) {
+            self.resolve();
+        }
+        self.notified
+    }
+
+    /// Mark notified and give up the waker slot.
+    fn resolve(&mut self) {
+        self.notified = true;
+        self.deregister();
+    }
+
+    fn deregister(&mut self) {
+        if let Some(key) = self.key.take() {
+            let waiters = &self.stopper.inner.waiters;
+            waiters
+                .lock
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .remove(key);
+            waiters.count.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Future for StopListener {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let this = self.get_mut();
+        if this.try_wait() {
+            return Poll::Ready(());
+        }
+        let waiters = &this.stopper.inner.waiters;
+        {
+            let mut wakers = waiters.lock.lock().unwrap_or_else(|e| e.into_inner());
+            match this.key {
+                None => {
+                    this.key = Some(wakers.insert(cx.waker().clone()));
+                    waiters.count.fetch_add(1, Ordering::SeqCst);
+                }
+                Some(key) => match &mut wakers.slots[key] {
+                    Some(waker) if waker.will_wake(cx.waker()) => {}
+                    slot => *slot = Some(cx.waker().clone()),
+                },
+            }
+        }
+        // A cancel between the first check and registering did not see
+        // this waker; check again now that it is registered.
+        if this.try_wait() {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Drop for StopListener {
+    fn drop(&mut self) {
+        self.deregister();
+    }
+}
+
+#[cfg(feature = "std")]
+impl core::fmt::Debug for StopListener {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("StopListener")
+            .field("stopper", &self.stopper)
+            .field("registered", &self.key.is_some())
+            .field("notified", &self.notified)
+            .finish()
+    }
+}
+
+/// Future returned by [`Stopper::wait_cancelled()`].
+///
+/// A [`StopListener`] used only as a future.
+///
+/// Requires the `std` feature.
+#[cfg(feature = "std")]
+#[must_use = "futures do nothing unless polled"]
+#[derive(Debug)]
+pub struct WaitCancelled {
+    listener: StopListener,
+}
+
+#[cfg(feature = "std")]
+impl WaitCancelled {
+    /// The stopper this future waits on.
+    #[inline]
+    pub fn stopper(&self) -> &Stopper {
+        self.listener.stopper()
+    }
+}
+
+#[cfg(feature = "std")]
+impl Future for WaitCancelled {
+    type Output = ();
+
+    #[inline]
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        Pin::new(&mut self.get_mut().listener).poll(cx)
     }
 }
 
@@ -145,12 +820,12 @@ impl Default for Stopper {
 }
 
 impl Stop for Stopper {
-    #[inline]
+    #[inline(always)]
     fn check(&self) -> Result<(), StopReason> {
         self.inner.check()
     }
 
-    #[inline]
+    #[inline(always)]
     fn should_stop(&self) -> bool {
         self.inner.should_stop()
     }
@@ -159,11 +834,270 @@ impl Stop for Stopper {
 impl core::fmt::Debug for StopperInner {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         f.debug_struct("StopperInner")
-            .field("cancelled", &self.cancelled.load(Ordering::Relaxed))
+            .field("cancelled", &self.state.is_cancelled(Ordering::Relaxed))
+            .field("label", &self.label)
+            .field("load", &self.load)
             .finish()
     }
 }
 
+#[cfg(all(test, feature = "std"))]
+mod wait_tests {
+    use super::*;
+    use std::sync::Arc as StdArc;
+    use std::sync::atomic::AtomicBool;
+    use std::task::Wake;
+    use std::thread;
+
+    struct Flag(AtomicBool);
+
+    impl Wake for Flag {
+        fn wake(self: StdArc<Self>) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    fn flag_waker() -> (StdArc<Flag>, Waker) {
+        let flag = StdArc::new(Flag(AtomicBool::new(false)));
+        (flag.clone(), Waker::from(flag))
+    }
+
+    #[test]
+    fn cancel_trace_is_first_cause_only() {
+        let trace = TraceContext::new([1; 16], [2; 8]);
+        let stop = Stopper::new();
+        assert_eq!(stop.cancel_trace(), None);
+        stop.clone().cancel_with_trace(trace);
+        assert!(stop.is_cancelled());
+        assert_eq!(stop.cancel_trace(), Some(trace));
+
+        let plain = Stopper::new();
+        plain.cancel();
+        assert!(!plain.cancel_with_trace(trace));
+        assert_eq!(plain.cancel_trace(), None);
+    }
+
+    #[test]
+    fn racing_cancels_record_only_the_winners_trace() {
+        for _ in 0..200 {
+            let stop = Stopper::new();
+            let barrier = StdArc::new(std::sync::Barrier::new(5));
+            let traced: Vec<_> = (0..4u8)
+                .map(|i| {
+                    let stop = stop.clone();
+                    let barrier = StdArc::clone(&barrier);
+                    let trace = TraceContext::new([i; 16], [i; 8]);
+                    thread::spawn(move || {
+                        barrier.wait();
+                        stop.cancel_with_trace(trace).then_some(trace)
+                    })
+                })
+                .collect();
+            barrier.wait();
+            stop.cancel();
+            let winners: Vec<_> = traced
+                .into_iter()
+                .filter_map(|handle| handle.join().unwrap())
+                .collect();
+            assert!(winners.len() <= 1);
+            // No trace unless a traced cancel won; never a loser's trace
+            assert_eq!(stop.cancel_trace(), winners.first().copied());
+            assert_eq!(stop.epoch(), 1);
+        }
+    }
+
+    #[test]
+    fn wait_cancelled_is_woken_by_cancel() {
+        let stop = Stopper::new();
+        let (flag, waker) = flag_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut future = stop.wait_cancelled();
+        assert!(Pin::new(&mut future).poll(&mut cx).is_pending());
+        assert_eq!(stop.inner.waiters.count.load(Ordering::SeqCst), 1);
+
+        let canceller = stop.clone();
+        thread::spawn(move || canceller.cancel()).join().unwrap();
+        assert!(flag.0.load(Ordering::SeqCst));
+        assert!(Pin::new(&mut future).poll(&mut cx).is_ready());
+    }
+
+    #[test]
+    fn wait_cancelled_ready_when_already_cancelled() {
+        let stop = Stopper::cancelled();
+        let mut future = stop.wait_cancelled();
+        let mut cx = Context::from_waker(Waker::noop());
+        assert!(Pin::new(&mut future).poll(&mut cx).is_ready());
+        assert!(future.stopper().is_cancelled());
+    }
+
+    #[test]
+    fn wait_cancelled_drop_deregisters() {
+        let stop = Stopper::new();
+        let mut cx = Context::from_waker(Waker::noop());
+        for _ in 0..100 {
+            let mut future = stop.wait_cancelled();
+            assert!(Pin::new(&mut future).poll(&mut cx).is_pending());
+        }
+        assert_eq!(stop.inner.waiters.count.load(Ordering::SeqCst), 0);
+        // Slots are reused rather than growing with every future
+        assert_eq!(stop.inner.waiters.lock.lock().unwrap().slots.len(), 1);
+    }
+
+    #[test]
+    fn listener_resolves_once_and_frees_slot() {
+        let stop = Stopper::new();
+        let mut cx = Context::from_waker(Waker::noop());
+        let mut listener = stop.listen();
+        assert!(!listener.try_wait());
+        assert!(Pin::new(&mut listener).poll(&mut cx).is_pending());
+        assert_eq!(stop.inner.waiters.count.load(Ordering::SeqCst), 1);
+
+        stop.cancel();
+        assert!(!listener.is_notified());
+        assert!(Pin::new(&mut listener).poll(&mut cx).is_ready());
+        assert!(listener.is_notified());
+        // Resolved listeners hold no slot, before being dropped
+        assert_eq!(stop.inner.waiters.count.load(Ordering::SeqCst), 0);
+        assert!(listener.try_wait());
+        assert!(listener.wait_timeout(Duration::ZERO));
+    }
+
+    #[test]
+    fn listener_blocking_wait() {
+        let stop = Stopper::new();
+        let mut listener = stop.listen();
+        assert!(!listener.wait_timeout(Duration::from_millis(5)));
+        assert!(!listener.is_notified());
+
+        let canceller = stop.clone();
+        let handle = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(10));
+            canceller.cancel();
+        });
+        listener.wait();
+        assert!(stop.is_cancelled());
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn wait_cancelled_uses_latest_waker() {
+        let stop = Stopper::new();
+        let (first, first_waker) = flag_waker();
+        let (second, second_waker) = flag_waker();
+        let mut future = stop.wait_cancelled();
+        assert!(
+            Pin::new(&mut future)
+                .poll(&mut Context::from_waker(&first_waker))
+                .is_pending()
+        );
+        assert!(
+            Pin::new(&mut future)
+                .poll(&mut Context::from_waker(&second_waker))
+                .is_pending()
+        );
+        stop.cancel();
+        assert!(!first.0.load(Ordering::SeqCst));
+        assert!(second.0.load(Ordering::SeqCst));
+        assert!(format!("{future:?}").contains("registered: true"));
+    }
+
+    #[test]
+    fn wait_returns_when_already_cancelled() {
+        let stop = Stopper::cancelled();
+        stop.wait();
+        assert!(stop.wait_timeout(Duration::ZERO));
+    }
+
+    #[test]
+    fn wait_wakes_on_cancel() {
+        let stop = Stopper::new();
+        let waiters: Vec<_> = (0..4)
+            .map(|_| {
+                let stop = stop.clone();
+                thread::spawn(move || stop.wait())
+            })
+            .collect();
+
+        thread::sleep(Duration::from_millis(10));
+        stop.cancel();
+        for waiter in waiters {
+            waiter.join().unwrap();
+        }
+        assert_eq!(stop.inner.waiters.count.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn wait_timeout_expires() {
+        let stop = Stopper::new();
+        let start = Instant::now();
+        assert!(!stop.wait_timeout(Duration::from_millis(20)));
+        assert!(start.elapsed() >= Duration::from_millis(20));
+        assert_eq!(stop.inner.waiters.count.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn wait_timeout_wakes_early() {
+        let stop = Stopper::new();
+        let canceller = stop.clone();
+        let handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(10));
+            canceller.cancel();
+        });
+        let start = Instant::now();
+        assert!(stop.wait_timeout(Duration::from_secs(30)));
+        assert!(start.elapsed() < Duration::from_secs(30));
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn wait_timeout_huge_duration() {
+        let stop = Stopper::new();
+        let canceller = stop.clone();
+        let handle = thread::spawn(move || canceller.cancel());
+        assert!(stop.wait_timeout(Duration::MAX));
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn cancel_without_waiters_skips_lock() {
+        let stop = Stopper::new();
+        // Hold the lock: cancel() must not block when nobody is waiting
+        let _guard = stop.inner.waiters.lock.lock().unwrap();
+        stop.cancel();
+        assert!(stop.is_cancelled());
+    }
+
+    #[test]
+    fn racing_cancel_and_wait() {
+        for _ in 0..200 {
+            let stop = Stopper::new();
+            let canceller = stop.clone();
+            let handle = thread::spawn(move || canceller.cancel());
+            stop.wait();
+            handle.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn acquire_load_sees_writes_before_cancel() {
+        let stop = Stopper::with_load_ordering(Ordering::Acquire);
+        let data = StdArc::new(AtomicUsize::new(0));
+        let reader = {
+            let stop = stop.clone();
+            let data = StdArc::clone(&data);
+            thread::spawn(move || {
+                while !stop.should_stop() {
+                    std::hint::spin_loop();
+                }
+                data.load(Ordering::Relaxed)
+            })
+        };
+        data.store(42, Ordering::Relaxed);
+        stop.cancel();
+        assert_eq!(reader.join().unwrap(), 42);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -226,6 +1160,51 @@ mod tests {
         assert!(!stop2.should_stop());
     }
 
+    #[test]
+    fn with_load_ordering() {
+        let stop = Stopper::with_load_ordering(Ordering::Acquire);
+        assert_eq!(stop.load_ordering(), Ordering::Acquire);
+        assert!(!stop.should_stop());
+        stop.clone().cancel();
+        assert!(stop.is_cancelled());
+        assert_eq!(stop.check(), Err(StopReason::Cancelled));
+        assert_eq!(stop.epoch(), 1);
+
+        let stop = Stopper::with_load_ordering(Ordering::SeqCst);
+        stop.cancel();
+        assert!(stop.should_stop());
+        assert_eq!(Stopper::new().load_ordering(), Ordering::Relaxed);
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid load ordering")]
+    fn with_load_ordering_rejects_release() {
+        let _ = Stopper::with_load_ordering(Ordering::Release);
+    }
+
+    #[test]
+    fn epoch_counts_transitions() {
+        let stop = Stopper::new();
+        let clone = stop.clone();
+        assert_eq!(stop.epoch(), 0);
+
+        clone.cancel();
+        assert_eq!(stop.epoch(), 1);
+        clone.cancel();
+        assert_eq!(stop.epoch(), 1);
+
+        assert_eq!(Stopper::cancelled().epoch(), 1);
+    }
+
+    #[test]
+    fn cancelled_epoch_differs_from_fresh() {
+        assert_ne!(Stopper::new().epoch(), Stopper::cancelled().epoch());
+        let restored = Stopper::cancelled();
+        let fresh = Stopper::new();
+        fresh.cancel();
+        assert_eq!(fresh.epoch(), restored.epoch());
+    }
+
     #[test]
     fn cancel_is_idempotent() {
         let stop = Stopper::new();
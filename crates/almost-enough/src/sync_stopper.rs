@@ -42,30 +42,31 @@
 //! | `is_cancelled()` | Acquire | Syncs with Release |
 //! | `should_stop()` | Acquire | Syncs with Release |
 //! | `check()` | Acquire | Syncs with Release |
+//!
+//! To pay for Acquire only once cancellation is observed, or for `SeqCst`
+//! while debugging, use
+//! [`Stopper::with_load_ordering()`](crate::Stopper::with_load_ordering).
 
 use alloc::sync::Arc;
-use core::sync::atomic::{AtomicBool, Ordering};
+use core::sync::atomic::Ordering;
 
+use crate::state::PackedState;
 use crate::{Stop, StopReason};
 
 /// Inner state for [`SyncStopper`] — implements [`Stop`] with Acquire ordering.
 pub(crate) struct SyncStopperInner {
-    cancelled: AtomicBool,
+    state: PackedState,
 }
 
 impl Stop for SyncStopperInner {
     #[inline]
     fn check(&self) -> Result<(), StopReason> {
-        if self.cancelled.load(Ordering::Acquire) {
-            Err(StopReason::Cancelled)
-        } else {
-            Ok(())
-        }
+        self.state.check(Ordering::Acquire)
     }
 
     #[inline]
     fn should_stop(&self) -> bool {
-        self.cancelled.load(Ordering::Acquire)
+        self.state.is_cancelled(Ordering::Acquire)
     }
 }
 
@@ -95,7 +96,7 @@ impl SyncStopper {
     pub fn new() -> Self {
         Self {
             inner: Arc::new(SyncStopperInner {
-                cancelled: AtomicBool::new(false),
+                state: PackedState::new(false),
             }),
         }
     }
@@ -105,7 +106,7 @@ impl SyncStopper {
     pub fn cancelled() -> Self {
         Self {
             inner: Arc::new(SyncStopperInner {
-                cancelled: AtomicBool::new(true),
+                state: PackedState::new(true),
             }),
         }
     }
@@ -116,7 +117,9 @@ impl SyncStopper {
     /// to any clone that subsequently observes `should_stop() == true`.
     #[inline]
     pub fn cancel(&self) {
-        self.inner.cancelled.store(true, Ordering::Release);
+        self.inner
+            .state
+            .cancel(StopReason::Cancelled, Ordering::Release);
     }
 
     /// Check if cancelled with Acquire ordering.
@@ -125,7 +128,15 @@ impl SyncStopper {
     /// the corresponding `cancel()` call are guaranteed to be visible.
     #[inline]
     pub fn is_cancelled(&self) -> bool {
-        self.inner.cancelled.load(Ordering::Acquire)
+        self.inner.state.is_cancelled(Ordering::Acquire)
+    }
+
+    /// Report the strong and weak counts of this stopper's shared state.
+    ///
+    /// See [`Stopper::ownership_report()`](crate::Stopper::ownership_report).
+    #[cfg(feature = "std")]
+    pub fn ownership_report(&self) -> crate::OwnershipReport {
+        crate::OwnershipReport::of(&self.inner)
     }
 }
 
@@ -150,7 +161,7 @@ impl Stop for SyncStopper {
 impl core::fmt::Debug for SyncStopperInner {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         f.debug_struct("SyncStopperInner")
-            .field("cancelled", &self.cancelled.load(Ordering::Relaxed))
+            .field("cancelled", &self.state.is_cancelled(Ordering::Relaxed))
             .finish()
     }
 }
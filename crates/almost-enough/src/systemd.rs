@@ -0,0 +1,405 @@
+//! systemd service lifecycle integration.
+//!
+//! [`SystemdService`] packages the standard daemon lifecycle around a root
+//! [`Stopper`]:
+//!
+//! - [`ready()`](SystemdService::ready) sends `READY=1` once the service is
+//!   live.
+//! - [`spawn_watchdog()`](SystemdService::spawn_watchdog) pets the watchdog
+//!   (`WATCHDOG=1`) at half of `WATCHDOG_USEC` for as long as a health probe
+//!   reports healthy. An unhealthy service stops petting and systemd restarts
+//!   it.
+//! - SIGTERM (via [`SignalStop`]) or [`stopping()`](SystemdService::stopping)
+//!   cancels the root stop, and systemd is told `STOPPING=1`.
+//!
+//! The notification protocol is implemented directly over the
+//! `$NOTIFY_SOCKET` datagram socket; there is no libsystemd dependency. When
+//! the process is not running under systemd every notification is a no-op,
+//! so the same binary works outside a unit.
+//!
+//! Requires the `systemd` feature (unix only).
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use almost_enough::{Stop, StopExt, SystemdService};
+//!
+//! let service = SystemdService::install()?;
+//! let stop = service.stop().clone();
+//!
+//! // ... open sockets, load config ...
+//! service.ready()?;
+//! let watchdog = service.spawn_watchdog(|| true);
+//!
+//! while !stop.should_stop() {
+//!     // ... serve requests with stop.child() ...
+//!     # break;
+//! }
+//! watchdog.join().unwrap();
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::io;
+use std::os::unix::net::{SocketAddr, UnixDatagram};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use crate::{SignalInstallError, SignalStop, Stopper};
+
+/// Connection details for `$NOTIFY_SOCKET`.
+struct NotifySocket {
+    addr: SocketAddr,
+}
+
+impl NotifySocket {
+    fn parse(path: &str) -> Option<Self> {
+        if path.is_empty() {
+            return None;
+        }
+        if let Some(name) = path.strip_prefix('@') {
+            return Self::abstract_name(name);
+        }
+        SocketAddr::from_pathname(path)
+            .ok()
+            .map(|addr| Self { addr })
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn abstract_name(name: &str) -> Option<Self> {
+        #[cfg(target_os = "android")]
+        use std::os::android::net::SocketAddrExt;
+        #[cfg(target_os = "linux")]
+        use std::os::linux::net::SocketAddrExt;
+
+        SocketAddr::from_abstract_name(name.as_bytes())
+            .ok()
+            .map(|addr| Self { addr })
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    fn abstract_name(_name: &str) -> Option<Self> {
+        None
+    }
+
+    fn send(&self, state: &str) -> io::Result<()> {
+        let socket = UnixDatagram::unbound()?;
+        socket.send_to_addr(state.as_bytes(), &self.addr)?;
+        Ok(())
+    }
+}
+
+/// A root stop wired into the systemd service lifecycle.
+///
+/// See the [module docs](crate::SystemdService) for the overall flow.
+pub struct SystemdService {
+    root: Stopper,
+    notify: Option<NotifySocket>,
+    watchdog: Option<Duration>,
+}
+
+impl SystemdService {
+    /// Install the process signal handler and read the systemd environment.
+    ///
+    /// The root stop is the [`SignalStop`] stopper, so SIGINT/SIGTERM/SIGHUP
+    /// cancel it.
+    pub fn install() -> Result<Self, SignalInstallError> {
+        let root = Stopper::from(SignalStop::install()?);
+        Ok(Self::with_stopper(root))
+    }
+
+    /// Read the systemd environment, using `root` as the root stop.
+    ///
+    /// No signal handler is installed; cancel `root` yourself (or call
+    /// [`stopping()`](Self::stopping)) to begin shutdown.
+    pub fn with_stopper(root: Stopper) -> Self {
+        let var = |name| std::env::var(name).ok();
+        Self::from_vars(
+            root,
+            var("NOTIFY_SOCKET").as_deref(),
+            var("WATCHDOG_USEC").as_deref(),
+            var("WATCHDOG_PID").as_deref(),
+        )
+    }
+
+    fn from_vars(
+        root: Stopper,
+        notify_socket: Option<&str>,
+        watchdog_usec: Option<&str>,
+        watchdog_pid: Option<&str>,
+    ) -> Self {
+        let notify = notify_socket.and_then(NotifySocket::parse);
+        let for_us = watchdog_pid
+            .map(|pid| pid.trim().parse::<u32>().ok() == Some(std::process::id()))
+            .unwrap_or(true);
+        let watchdog = watchdog_usec
+            .filter(|_| for_us && notify.is_some())
+            .and_then(|usec| usec.trim().parse::<u64>().ok())
+            .filter(|&usec| usec > 0)
+            .map(|usec| Duration::from_micros(usec / 2));
+        Self {
+            root,
+            notify,
+            watchdog,
+        }
+    }
+
+    /// The root stop. Cancelled on shutdown.
+    #[inline]
+    pub fn stop(&self) -> &Stopper {
+        &self.root
+    }
+
+    /// Returns `true` if `$NOTIFY_SOCKET` is set, i.e. running under systemd
+    /// with notification enabled.
+    #[inline]
+    pub fn is_supervised(&self) -> bool {
+        self.notify.is_some()
+    }
+
+    /// How often the watchdog is petted: half of `WATCHDOG_USEC`, or `None`
+    /// if the watchdog is not enabled for this process.
+    #[inline]
+    pub fn watchdog_interval(&self) -> Option<Duration> {
+        self.watchdog
+    }
+
+    /// Send a raw sd_notify state string (e.g. `"STATUS=warming caches"`).
+    ///
+    /// A no-op returning `Ok(())` when not supervised.
+    pub fn notify(&self, state: &str) -> io::Result<()> {
+        match &self.notify {
+            Some(socket) => socket.send(state),
+            None => Ok(()),
+        }
+    }
+
+    /// Tell systemd the service is ready (`READY=1`).
+    pub fn ready(&self) -> io::Result<()> {
+        self.notify("READY=1")
+    }
+
+    /// Pet the watchdog once (`WATCHDOG=1`).
+    pub fn pet(&self) -> io::Result<()> {
+        self.notify("WATCHDOG=1")
+    }
+
+    /// Begin shutdown: tell systemd `STOPPING=1` and cancel the root stop.
+    pub fn stopping(&self) -> io::Result<()> {
+        self.root.cancel();
+        self.notify("STOPPING=1")
+    }
+
+    /// Spawn a thread that pets the watchdog while `healthy()` returns `true`.
+    ///
+    /// The thread wakes every [`watchdog_interval()`](Self::watchdog_interval)
+    /// and pets the watchdog only if the probe reports healthy. When the root
+    /// stop is cancelled (by a signal, [`stopping()`](Self::stopping), or
+    /// directly) it sends `STOPPING=1` and exits. Without a watchdog it just
+    /// waits for cancellation.
+    pub fn spawn_watchdog<H>(&self, healthy: H) -> JoinHandle<()>
+    where
+        H: Fn() -> bool + Send + 'static,
+    {
+        let root = self.root.clone();
+        let notify = self.notify.as_ref().map(|s| NotifySocket {
+            addr: s.addr.clone(),
+        });
+        let interval = self.watchdog;
+        std::thread::Builder::new()
+            .name("systemd-watchdog".into())
+            .spawn(move || {
+                let send = |state: &str| {
+                    if let Some(socket) = &notify {
+                        let _ = socket.send(state);
+                    }
+                };
+                match interval {
+                    Some(interval) => {
+                        while !root.wait_timeout(interval) {
+                            if healthy() {
+                                send("WATCHDOG=1");
+                            }
+                        }
+                    }
+                    None => root.wait(),
+                }
+                send("STOPPING=1");
+            })
+            .expect("failed to spawn systemd watchdog thread")
+    }
+}
+
+impl core::fmt::Debug for SystemdService {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("SystemdService")
+            .field("root", &self.root)
+            .field("supervised", &self.is_supervised())
+            .field("watchdog_interval", &self.watchdog)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+    struct Listener {
+        socket: UnixDatagram,
+        path: PathBuf,
+    }
+
+    impl Listener {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!(
+                "almost-enough-{}-{}.sock",
+                name,
+                std::process::id()
+            ));
+            let _ = std::fs::remove_file(&path);
+            let socket = UnixDatagram::bind(&path).unwrap();
+            socket
+                .set_read_timeout(Some(Duration::from_secs(5)))
+                .unwrap();
+            Self { socket, path }
+        }
+
+        fn recv(&self) -> String {
+            let mut buf = [0u8; 256];
+            let n = self.socket.recv(&mut buf).unwrap();
+            String::from_utf8_lossy(&buf[..n]).into_owned()
+        }
+    }
+
+    impl Drop for Listener {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+
+    #[test]
+    fn unsupervised_is_noop() {
+        let service = SystemdService::from_vars(Stopper::new(), None, Some("1000000"), None);
+        assert!(!service.is_supervised());
+        assert_eq!(service.watchdog_interval(), None);
+        assert!(service.ready().is_ok());
+        assert!(service.pet().is_ok());
+        assert!(service.stopping().is_ok());
+        assert!(service.stop().is_cancelled());
+    }
+
+    #[test]
+    fn parses_watchdog_settings() {
+        let listener = Listener::new("parse");
+        let path = listener.path.to_str().unwrap();
+        let pid = std::process::id().to_string();
+
+        let service =
+            SystemdService::from_vars(Stopper::new(), Some(path), Some("2000000"), Some(&pid));
+        assert_eq!(service.watchdog_interval(), Some(Duration::from_secs(1)));
+
+        // WATCHDOG_PID for another process disables the watchdog
+        let other =
+            SystemdService::from_vars(Stopper::new(), Some(path), Some("2000000"), Some("1"));
+        assert_eq!(other.watchdog_interval(), None);
+
+        let invalid = SystemdService::from_vars(Stopper::new(), Some(path), Some("soon"), None);
+        assert_eq!(invalid.watchdog_interval(), None);
+        assert!(invalid.is_supervised());
+    }
+
+    #[test]
+    fn sends_lifecycle_notifications() {
+        let listener = Listener::new("lifecycle");
+        let service = SystemdService::from_vars(Stopper::new(), listener.path.to_str(), None, None);
+
+        service.ready().unwrap();
+        assert_eq!(listener.recv(), "READY=1");
+        service.notify("STATUS=serving").unwrap();
+        assert_eq!(listener.recv(), "STATUS=serving");
+        service.stopping().unwrap();
+        assert_eq!(listener.recv(), "STOPPING=1");
+        assert!(service.stop().is_cancelled());
+    }
+
+    #[test]
+    fn watchdog_pets_while_healthy_and_stops_on_cancel() {
+        let listener = Listener::new("watchdog");
+        let service = SystemdService::from_vars(
+            Stopper::new(),
+            listener.path.to_str(),
+            Some("10000"), // 10ms -> pet every 5ms
+            None,
+        );
+
+        // Drain concurrently so a full socket queue never blocks a pet
+        let socket = listener.socket.try_clone().unwrap();
+        let reader = std::thread::spawn(move || {
+            let mut buf = [0u8; 64];
+            let mut pets = 0;
+            loop {
+                let n = socket.recv(&mut buf).unwrap();
+                match &buf[..n] {
+                    b"WATCHDOG=1" => pets += 1,
+                    b"STOPPING=1" => return pets,
+                    other => panic!("unexpected {:?}", String::from_utf8_lossy(other)),
+                }
+            }
+        });
+
+        // Every healthy probe is followed by exactly one pet
+        let healthy = Arc::new(AtomicBool::new(true));
+        let healthy_probes = Arc::new(AtomicUsize::new(0));
+        let unhealthy_probes = Arc::new(AtomicUsize::new(0));
+        let handle = {
+            let healthy = Arc::clone(&healthy);
+            let healthy_probes = Arc::clone(&healthy_probes);
+            let unhealthy_probes = Arc::clone(&unhealthy_probes);
+            service.spawn_watchdog(move || {
+                let ok = healthy.load(Ordering::Relaxed);
+                let counter = if ok {
+                    &healthy_probes
+                } else {
+                    &unhealthy_probes
+                };
+                counter.fetch_add(1, Ordering::SeqCst);
+                ok
+            })
+        };
+
+        while healthy_probes.load(Ordering::SeqCst) < 2 {
+            std::thread::yield_now();
+        }
+
+        // Once an unhealthy probe has run, no further pets are sent
+        healthy.store(false, Ordering::Relaxed);
+        while unhealthy_probes.load(Ordering::SeqCst) < 2 {
+            std::thread::yield_now();
+        }
+        let pets = healthy_probes.load(Ordering::SeqCst);
+
+        // Cancellation sends STOPPING and ends the thread
+        service.stop().cancel();
+        handle.join().unwrap();
+        assert_eq!(healthy_probes.load(Ordering::SeqCst), pets);
+        assert_eq!(reader.join().unwrap(), pets);
+    }
+
+    #[test]
+    fn watchdog_thread_without_interval_waits_for_cancel() {
+        let service = SystemdService::from_vars(Stopper::new(), None, None, None);
+        let handle = service.spawn_watchdog(|| true);
+        service.stop().cancel();
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn debug_and_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<SystemdService>();
+        let service = SystemdService::from_vars(Stopper::new(), None, None, None);
+        assert!(format!("{service:?}").contains("SystemdService"));
+    }
+}
@@ -0,0 +1,407 @@
+//! Cancellation coverage checks for tests.
+//!
+//! A test that cancels a stop proves nothing unless the code under test
+//! actually notices. [`StopTracker`] hands out [`TrackerStop`]s and
+//! remembers every one of them; at the end of the test,
+//! [`assert_settled()`](StopTracker::assert_settled) fails if any stop was
+//! left unsettled. A stop is settled when:
+//!
+//! - it was cancelled **and** a `check()`/`should_stop()` then saw it
+//!   ([`StopOutcome::Observed`]),
+//! - the work it guarded finished normally and said so with
+//!   [`complete()`](TrackerStop::complete) ([`StopOutcome::Completed`]), or
+//! - the test opted out with [`disarm()`](TrackerStop::disarm)
+//!   ([`StopOutcome::Disarmed`]).
+//!
+//! A stop that was cancelled but never observed is unsettled even if
+//! `complete()` was called: the work ran to the end while a cancellation
+//! it should have honored was pending.
+//!
+//! Requires the `test-util` feature; enable it in `[dev-dependencies]`.
+//!
+//! # Example
+//!
+//! ```rust
+//! use almost_enough::Stop;
+//! use almost_enough::test_util::StopTracker;
+//!
+//! fn encode(frames: usize, stop: &impl Stop) -> Result<usize, almost_enough::StopReason> {
+//!     for _ in 0..frames {
+//!         stop.check()?;
+//!     }
+//!     Ok(frames)
+//! }
+//!
+//! let tracker = StopTracker::new();
+//!
+//! let cancelled = tracker.stop_named("cancelled encode");
+//! cancelled.cancel();
+//! assert!(encode(10, &cancelled).is_err());
+//!
+//! let finished = tracker.stop_named("normal encode");
+//! assert_eq!(encode(10, &finished), Ok(10));
+//! finished.complete();
+//!
+//! tracker.assert_settled();
+//! ```
+
+use std::panic::Location;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use crate::{Stop, StopReason, Stopper};
+
+/// Per-stop bookkeeping shared between a [`TrackerStop`], its clones and
+/// the tracker.
+struct Entry {
+    label: Option<String>,
+    location: &'static Location<'static>,
+    stopper: Stopper,
+    observed: AtomicBool,
+    completed: AtomicBool,
+    disarmed: AtomicBool,
+}
+
+impl Entry {
+    fn outcome(&self) -> StopOutcome {
+        let cancelled = self.stopper.is_cancelled();
+        let observed = self.observed.load(Ordering::Relaxed);
+        if self.disarmed.load(Ordering::Relaxed) {
+            StopOutcome::Disarmed
+        } else if observed {
+            StopOutcome::Observed
+        } else if cancelled {
+            StopOutcome::CancelledUnobserved
+        } else if self.completed.load(Ordering::Relaxed) {
+            StopOutcome::Completed
+        } else {
+            StopOutcome::Pending
+        }
+    }
+
+    fn record(&self) -> StopRecord {
+        StopRecord {
+            label: self.label.clone(),
+            location: self.location,
+            outcome: self.outcome(),
+        }
+    }
+}
+
+/// How a tracked stop ended up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum StopOutcome {
+    /// Neither cancelled, completed nor disarmed. Unsettled.
+    Pending,
+    /// Cancelled, but no check ever reported it. Unsettled.
+    CancelledUnobserved,
+    /// Cancelled, and a check reported it. Settled.
+    Observed,
+    /// Not cancelled; the guarded work called
+    /// [`complete()`](TrackerStop::complete). Settled.
+    Completed,
+    /// Excluded from the coverage check with
+    /// [`disarm()`](TrackerStop::disarm). Settled.
+    Disarmed,
+}
+
+impl StopOutcome {
+    /// Whether this outcome passes [`StopTracker::assert_settled()`].
+    #[inline]
+    pub fn is_settled(self) -> bool {
+        matches!(self, Self::Observed | Self::Completed | Self::Disarmed)
+    }
+}
+
+/// A snapshot of one tracked stop, from [`StopTracker::report()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StopRecord {
+    /// The label given to [`StopTracker::stop_named()`], if any.
+    pub label: Option<String>,
+    /// Where the stop was created.
+    pub location: &'static Location<'static>,
+    /// What happened to it so far.
+    pub outcome: StopOutcome,
+}
+
+impl core::fmt::Display for StopRecord {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match &self.label {
+            Some(label) => write!(f, "{label:?} ")?,
+            None => f.write_str("stop ")?,
+        }
+        write!(f, "created at {}: {:?}", self.location, self.outcome)
+    }
+}
+
+/// Registry of the stops created during a test.
+///
+/// Clones share the registry, so a tracker can be handed to helpers and
+/// threads. See the [module docs](self) for what counts as settled.
+///
+/// # Example
+///
+/// ```rust
+/// use almost_enough::Stop;
+/// use almost_enough::test_util::{StopOutcome, StopTracker};
+///
+/// let tracker = StopTracker::new();
+/// let stop = tracker.stop();
+/// stop.cancel(); // ...but nothing checks it
+///
+/// let report = tracker.report();
+/// assert_eq!(report[0].outcome, StopOutcome::CancelledUnobserved);
+/// assert_eq!(tracker.unsettled().len(), 1);
+/// ```
+#[derive(Clone, Default)]
+pub struct StopTracker {
+    entries: Arc<Mutex<Vec<Arc<Entry>>>>,
+}
+
+impl StopTracker {
+    /// Create an empty tracker.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a tracked stop, recording the caller's location.
+    #[track_caller]
+    pub fn stop(&self) -> TrackerStop {
+        self.register(None, Location::caller())
+    }
+
+    /// Create a tracked stop with a label for failure messages.
+    #[track_caller]
+    pub fn stop_named(&self, label: impl Into<String>) -> TrackerStop {
+        self.register(Some(label.into()), Location::caller())
+    }
+
+    fn register(&self, label: Option<String>, location: &'static Location<'static>) -> TrackerStop {
+        let entry = Arc::new(Entry {
+            label,
+            location,
+            stopper: Stopper::new(),
+            observed: AtomicBool::new(false),
+            completed: AtomicBool::new(false),
+            disarmed: AtomicBool::new(false),
+        });
+        self.lock().push(Arc::clone(&entry));
+        TrackerStop { entry }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<Arc<Entry>>> {
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// The current outcome of every stop created so far, in creation order.
+    pub fn report(&self) -> Vec<StopRecord> {
+        self.lock().iter().map(|entry| entry.record()).collect()
+    }
+
+    /// The records of stops that are not yet settled.
+    pub fn unsettled(&self) -> Vec<StopRecord> {
+        self.report()
+            .into_iter()
+            .filter(|record| !record.outcome.is_settled())
+            .collect()
+    }
+
+    /// Panic if any stop created through this tracker is unsettled.
+    ///
+    /// The message lists each offending stop with its label, creation site
+    /// and outcome.
+    #[track_caller]
+    pub fn assert_settled(&self) {
+        let unsettled = self.unsettled();
+        if unsettled.is_empty() {
+            return;
+        }
+        let mut message = format!(
+            "{} of {} tracked stops unsettled:",
+            unsettled.len(),
+            self.lock().len()
+        );
+        for record in &unsettled {
+            message.push_str("\n  ");
+            message.push_str(&format!("{record}"));
+        }
+        panic!("{message}");
+    }
+}
+
+impl core::fmt::Debug for StopTracker {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("StopTracker")
+            .field("stops", &self.lock().len())
+            .finish()
+    }
+}
+
+/// A cancellable stop registered with a [`StopTracker`].
+///
+/// Behaves like a [`Stopper`]: clones share state, and [`cancel()`](Self::cancel)
+/// is visible through every clone. Any `check()` or `should_stop()` that
+/// reports the cancellation marks the stop as observed.
+#[derive(Clone)]
+pub struct TrackerStop {
+    entry: Arc<Entry>,
+}
+
+impl TrackerStop {
+    /// Cancel the stop.
+    #[inline]
+    pub fn cancel(&self) {
+        self.entry.stopper.cancel();
+    }
+
+    /// Check whether the stop has been cancelled, without marking it
+    /// observed.
+    #[inline]
+    pub fn is_cancelled(&self) -> bool {
+        self.entry.stopper.is_cancelled()
+    }
+
+    /// Mark the guarded work as finished normally.
+    ///
+    /// Settles the stop unless it was cancelled without being observed.
+    pub fn complete(&self) {
+        self.entry.completed.store(true, Ordering::Relaxed);
+    }
+
+    /// Exclude this stop from the coverage check.
+    ///
+    /// For stops a test creates deliberately without exercising them.
+    pub fn disarm(&self) {
+        self.entry.disarmed.store(true, Ordering::Relaxed);
+    }
+
+    /// The stop's current outcome.
+    pub fn outcome(&self) -> StopOutcome {
+        self.entry.outcome()
+    }
+}
+
+impl Stop for TrackerStop {
+    #[inline]
+    fn check(&self) -> Result<(), StopReason> {
+        let result = self.entry.stopper.check();
+        if result.is_err() {
+            self.entry.observed.store(true, Ordering::Relaxed);
+        }
+        result
+    }
+
+    #[inline]
+    fn should_stop(&self) -> bool {
+        self.check().is_err()
+    }
+}
+
+impl core::fmt::Debug for TrackerStop {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("TrackerStop")
+            .field("label", &self.entry.label)
+            .field("location", &self.entry.location)
+            .field("outcome", &self.outcome())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn observed_cancellation_is_settled() {
+        let tracker = StopTracker::new();
+        let stop = tracker.stop();
+        assert_eq!(stop.outcome(), StopOutcome::Pending);
+
+        stop.cancel();
+        assert!(stop.is_cancelled());
+        assert_eq!(stop.outcome(), StopOutcome::CancelledUnobserved);
+
+        assert!(stop.should_stop());
+        assert_eq!(stop.outcome(), StopOutcome::Observed);
+        tracker.assert_settled();
+    }
+
+    #[test]
+    fn clones_share_observation() {
+        let tracker = StopTracker::new();
+        let stop = tracker.stop();
+        let worker = stop.clone();
+        stop.cancel();
+        assert_eq!(worker.check(), Err(StopReason::Cancelled));
+        assert_eq!(stop.outcome(), StopOutcome::Observed);
+    }
+
+    #[test]
+    fn completed_and_disarmed_are_settled() {
+        let tracker = StopTracker::new();
+        let done = tracker.stop();
+        assert!(done.check().is_ok());
+        done.complete();
+        tracker.stop().disarm();
+
+        let outcomes: Vec<_> = tracker.report().iter().map(|r| r.outcome).collect();
+        assert_eq!(outcomes, [StopOutcome::Completed, StopOutcome::Disarmed]);
+        tracker.assert_settled();
+    }
+
+    #[test]
+    fn completing_after_unobserved_cancel_is_unsettled() {
+        let tracker = StopTracker::new();
+        let stop = tracker.stop_named("ignored");
+        stop.cancel();
+        stop.complete();
+        let unsettled = tracker.unsettled();
+        assert_eq!(unsettled.len(), 1);
+        assert_eq!(unsettled[0].label.as_deref(), Some("ignored"));
+        assert_eq!(unsettled[0].outcome, StopOutcome::CancelledUnobserved);
+    }
+
+    #[test]
+    fn records_creation_site() {
+        let tracker = StopTracker::new();
+        let line = line!() + 1;
+        let stop = tracker.stop();
+        let record = &tracker.report()[0];
+        assert_eq!(record.location.file(), file!());
+        assert_eq!(record.location.line(), line);
+        stop.disarm();
+    }
+
+    #[test]
+    fn assert_settled_lists_offenders() {
+        let tracker = StopTracker::new();
+        tracker.stop_named("never used");
+        let cancelled = tracker.stop_named("cancelled");
+        cancelled.cancel();
+        tracker.stop().disarm();
+
+        let message = std::panic::catch_unwind(|| tracker.assert_settled())
+            .unwrap_err()
+            .downcast::<String>()
+            .unwrap();
+        assert!(message.starts_with("2 of 3 tracked stops unsettled:"));
+        assert!(message.contains("\"never used\" created at"));
+        assert!(message.contains("Pending"));
+        assert!(message.contains("\"cancelled\" created at"));
+        assert!(message.contains("CancelledUnobserved"));
+    }
+
+    #[test]
+    fn is_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<StopTracker>();
+        assert_send_sync::<TrackerStop>();
+
+        let tracker = StopTracker::new();
+        let stop = tracker.stop_named("debug");
+        assert!(format!("{stop:?}").contains("\"debug\""));
+        assert_eq!(format!("{tracker:?}"), "StopTracker { stops: 1 }");
+        stop.disarm();
+    }
+}
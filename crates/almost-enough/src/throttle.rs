@@ -0,0 +1,264 @@
+//! Count-based rate limiting of expensive checks.
+//!
+//! Some stops are costly to check: `WithTimeout` reads the clock on
+//! every call, an [`FnStop`](crate::FnStop) may take a lock or call into
+//! C. [`Throttled`] consults such a stop only every N-th call and answers
+//! the calls in between from a latched flag — one atomic load and one
+//! counter increment. Works in `no_std` environments.
+//!
+//! Once the inner stop trips, the reason is latched and every later call
+//! reports it without consulting the inner stop again.
+//!
+//! For time-based rather than count-based skipping of clock reads, see
+//! `DebouncedTimeout` (`std`).
+//!
+//! # Example
+//!
+//! ```rust
+//! use almost_enough::{BudgetStop, Stop, StopExt, StopReason};
+//!
+//! // The inner stop is consulted on calls 0, 100, 200, ...
+//! let budget = BudgetStop::checks(2);
+//! let stop = (&budget).throttle_checks(100);
+//!
+//! let calls = (0..1_000).take_while(|_| stop.check().is_ok()).count();
+//! assert_eq!(calls, 200);
+//! assert_eq!(stop.check(), Err(StopReason::TimedOut));
+//! ```
+
+use core::sync::atomic::{AtomicU8, AtomicU32, Ordering};
+
+use crate::reason::{NOT_STOPPED, decode, encode};
+use crate::{Stop, StopReason};
+
+/// A [`Stop`] wrapper that consults its inner stop every `interval` calls.
+///
+/// Created by [`StopExt::throttle_checks()`](crate::StopExt::throttle_checks)
+/// or [`Throttled::new()`]. The first call always consults the inner stop.
+/// An `interval` of 0 is treated as 1 (no throttling).
+///
+/// The trade-off is latency: cancellation of the inner stop is noticed up
+/// to `interval - 1` calls late. Pick an interval that keeps that delay
+/// small compared to how quickly the work must react. The call counter is
+/// shared by all threads using one wrapper; give each worker its own
+/// wrapper to keep the counter uncontended.
+///
+/// # Example
+///
+/// ```rust
+/// # #[cfg(feature = "std")]
+/// # fn main() {
+/// use almost_enough::{Stop, StopExt, Stopper, TimeoutExt};
+/// use std::time::Duration;
+///
+/// let stop = Stopper::new()
+///     .with_timeout(Duration::from_secs(30))
+///     .throttle_checks(128); // read the clock once per 128 checks
+///
+/// for _pixel in 0..10_000 {
+///     if stop.should_stop() {
+///         break;
+///     }
+/// }
+/// # }
+/// # #[cfg(not(feature = "std"))]
+/// # fn main() {}
+/// ```
+pub struct Throttled<T> {
+    inner: T,
+    interval: u32,
+    calls: AtomicU32,
+    state: AtomicU8,
+}
+
+impl<T: Stop> Throttled<T> {
+    /// Consult `inner` on the first call and then every `interval` calls.
+    #[inline]
+    pub fn new(inner: T, interval: u32) -> Self {
+        Self {
+            inner,
+            interval: interval.max(1),
+            calls: AtomicU32::new(0),
+            state: AtomicU8::new(NOT_STOPPED),
+        }
+    }
+
+    /// The configured interval.
+    #[inline]
+    pub fn interval(&self) -> u32 {
+        self.interval
+    }
+
+    /// Get a reference to the inner stop.
+    #[inline]
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Unwrap and return the inner stop.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    /// Check the inner stop and latch its reason if it has stopped.
+    #[inline]
+    fn consult(&self) -> Result<(), StopReason> {
+        let result = self.inner.check();
+        if let Err(reason) = result {
+            self.state.store(encode(reason), Ordering::Relaxed);
+        }
+        result
+    }
+}
+
+impl<T: Stop> Stop for Throttled<T> {
+    #[inline]
+    fn check(&self) -> Result<(), StopReason> {
+        let state = self.state.load(Ordering::Relaxed);
+        if state != NOT_STOPPED {
+            return Err(decode(state));
+        }
+        if self.calls.fetch_add(1, Ordering::Relaxed) % self.interval != 0 {
+            return Ok(());
+        }
+        self.consult()
+    }
+
+    #[inline]
+    fn should_stop(&self) -> bool {
+        self.check().is_err()
+    }
+
+    #[inline]
+    fn may_stop(&self) -> bool {
+        self.inner.may_stop()
+    }
+}
+
+impl<T: Clone> Clone for Throttled<T> {
+    /// The clone keeps any latched reason and starts its own call count.
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            interval: self.interval,
+            calls: AtomicU32::new(0),
+            state: AtomicU8::new(self.state.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+impl<T: core::fmt::Debug> core::fmt::Debug for Throttled<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Throttled")
+            .field("inner", &self.inner)
+            .field("interval", &self.interval)
+            .field(
+                "stopped",
+                &(self.state.load(Ordering::Relaxed) != NOT_STOPPED),
+            )
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{StopExt, StopSource, Unstoppable};
+    use core::sync::atomic::AtomicUsize;
+
+    /// Counts how many times `check()` is called.
+    struct CountingStop<'a>(&'a AtomicUsize);
+
+    impl Stop for CountingStop<'_> {
+        fn check(&self) -> Result<(), StopReason> {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            Ok(())
+        }
+
+        fn should_stop(&self) -> bool {
+            self.check().is_err()
+        }
+    }
+
+    #[test]
+    fn consults_inner_every_interval() {
+        let calls = AtomicUsize::new(0);
+        let stop = CountingStop(&calls).throttle_checks(16);
+        for _ in 0..100 {
+            assert!(stop.check().is_ok());
+        }
+        // Calls 0, 16, 32, 48, 64, 80, 96
+        assert_eq!(calls.load(Ordering::Relaxed), 7);
+    }
+
+    #[test]
+    fn first_call_consults_inner() {
+        let source = StopSource::new();
+        source.cancel();
+        let stop = source.as_ref().throttle_checks(1000);
+        assert_eq!(stop.check(), Err(StopReason::Cancelled));
+    }
+
+    #[test]
+    fn cancellation_noticed_within_interval() {
+        let source = StopSource::new();
+        let stop = source.as_ref().throttle_checks(8);
+        assert!(stop.check().is_ok());
+        source.cancel();
+        let late = (0..100).take_while(|_| stop.check().is_ok()).count();
+        assert_eq!(late, 7);
+    }
+
+    #[test]
+    fn reason_latches() {
+        let calls = AtomicUsize::new(0);
+        let stop = crate::FnStop::with_reason(|| {
+            calls.fetch_add(1, Ordering::Relaxed);
+            Some(StopReason::TimedOut)
+        })
+        .throttle_checks(1);
+
+        assert_eq!(stop.check(), Err(StopReason::TimedOut));
+        assert_eq!(stop.check(), Err(StopReason::TimedOut));
+        assert!(stop.should_stop());
+        // Only the first call reached the inner stop
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn zero_interval_is_one() {
+        let calls = AtomicUsize::new(0);
+        let stop = Throttled::new(CountingStop(&calls), 0);
+        assert_eq!(stop.interval(), 1);
+        for _ in 0..5 {
+            let _ = stop.check();
+        }
+        assert_eq!(calls.load(Ordering::Relaxed), 5);
+    }
+
+    #[test]
+    fn clone_keeps_latched_reason() {
+        let source = StopSource::new();
+        source.cancel();
+        let stop = Throttled::new(source.as_ref(), 4);
+        assert!(stop.should_stop());
+        let clone = stop.clone();
+        assert_eq!(clone.check(), Err(StopReason::Cancelled));
+        assert!(clone.inner().should_stop());
+    }
+
+    #[test]
+    fn forwards_may_stop() {
+        assert!(!Throttled::new(Unstoppable, 8).may_stop());
+        let source = StopSource::new();
+        assert!(Throttled::new(source.as_ref(), 8).may_stop());
+    }
+
+    #[test]
+    fn is_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Throttled<Unstoppable>>();
+        assert_eq!(Throttled::new(Unstoppable, 3).into_inner(), Unstoppable);
+    }
+}
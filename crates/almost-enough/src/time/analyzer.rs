@@ -0,0 +1,470 @@
+//! Deadline instrumentation for tightening timeout budgets.
+//!
+//! [`DeadlineAnalyzer`] records, per labeled timeout site, how much of the
+//! deadline was actually used. Sites whose deadlines are orders of magnitude
+//! larger than their observed worst-case runtime are reported by
+//! [`over_generous()`](DeadlineAnalyzer::over_generous), so budgets can be
+//! tightened from data instead of folklore.
+//!
+//! # Example
+//!
+//! ```rust
+//! use almost_enough::{Stopper, Stop};
+//! use almost_enough::time::DeadlineAnalyzer;
+//! use std::time::Duration;
+//!
+//! static ANALYZER: DeadlineAnalyzer = DeadlineAnalyzer::new();
+//!
+//! let stop = Stopper::new();
+//! for _ in 0..3 {
+//!     let timeout = ANALYZER.with_timeout("thumbnail", stop.clone(), Duration::from_secs(300));
+//!     // ... work that finishes in microseconds ...
+//!     assert!(timeout.check().is_ok());
+//! } // each drop records the remaining time
+//!
+//! let flagged = ANALYZER.over_generous(1000, 3);
+//! assert_eq!(flagged[0].label(), "thumbnail");
+//! ```
+
+use std::collections::BTreeMap;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use super::WithTimeout;
+use crate::{Stop, StopReason};
+
+/// Upper bounds of the remaining-time histogram buckets.
+///
+/// Bucket `i` counts completions with remaining time below
+/// `REMAINING_BUCKET_BOUNDS[i]`; the final bucket counts everything above
+/// the last bound.
+pub const REMAINING_BUCKET_BOUNDS: [Duration; 6] = [
+    Duration::from_millis(1),
+    Duration::from_millis(10),
+    Duration::from_millis(100),
+    Duration::from_secs(1),
+    Duration::from_secs(10),
+    Duration::from_secs(100),
+];
+
+const BUCKETS: usize = REMAINING_BUCKET_BOUNDS.len() + 1;
+
+#[derive(Debug, Clone, Default)]
+struct SiteStats {
+    samples: u64,
+    timeouts: u64,
+    max_budget: Duration,
+    worst_runtime: Duration,
+    min_remaining: Option<Duration>,
+    total_remaining: Duration,
+    histogram: [u64; BUCKETS],
+}
+
+impl SiteStats {
+    fn record(&mut self, budget: Duration, elapsed: Duration) {
+        let remaining = budget.saturating_sub(elapsed);
+        self.samples += 1;
+        if elapsed >= budget {
+            self.timeouts += 1;
+        }
+        self.max_budget = self.max_budget.max(budget);
+        self.worst_runtime = self.worst_runtime.max(elapsed);
+        self.min_remaining = Some(self.min_remaining.map_or(remaining, |m| m.min(remaining)));
+        self.total_remaining = self.total_remaining.saturating_add(remaining);
+        let bucket = REMAINING_BUCKET_BOUNDS
+            .iter()
+            .position(|bound| remaining < *bound)
+            .unwrap_or(BUCKETS - 1);
+        self.histogram[bucket] += 1;
+    }
+}
+
+/// Summary of one labeled timeout site.
+///
+/// Produced by [`DeadlineAnalyzer::report()`] and
+/// [`DeadlineAnalyzer::over_generous()`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SiteReport {
+    label: &'static str,
+    samples: u64,
+    timeouts: u64,
+    max_budget: Duration,
+    worst_runtime: Duration,
+    min_remaining: Duration,
+    mean_remaining: Duration,
+    histogram: [u64; BUCKETS],
+}
+
+impl SiteReport {
+    fn new(label: &'static str, stats: &SiteStats) -> Self {
+        let mean_remaining = u32::try_from(stats.samples)
+            .ok()
+            .and_then(|n| stats.total_remaining.checked_div(n))
+            .unwrap_or(Duration::ZERO);
+        Self {
+            label,
+            samples: stats.samples,
+            timeouts: stats.timeouts,
+            max_budget: stats.max_budget,
+            worst_runtime: stats.worst_runtime,
+            min_remaining: stats.min_remaining.unwrap_or(Duration::ZERO),
+            mean_remaining,
+            histogram: stats.histogram,
+        }
+    }
+
+    /// The site label.
+    #[inline]
+    pub fn label(&self) -> &'static str {
+        self.label
+    }
+
+    /// Number of completions recorded.
+    #[inline]
+    pub fn samples(&self) -> u64 {
+        self.samples
+    }
+
+    /// Number of completions that used up the whole budget.
+    #[inline]
+    pub fn timeouts(&self) -> u64 {
+        self.timeouts
+    }
+
+    /// Largest budget configured at this site.
+    #[inline]
+    pub fn max_budget(&self) -> Duration {
+        self.max_budget
+    }
+
+    /// Longest observed runtime at this site.
+    #[inline]
+    pub fn worst_runtime(&self) -> Duration {
+        self.worst_runtime
+    }
+
+    /// Smallest remaining time observed at completion.
+    #[inline]
+    pub fn min_remaining(&self) -> Duration {
+        self.min_remaining
+    }
+
+    /// Mean remaining time at completion.
+    #[inline]
+    pub fn mean_remaining(&self) -> Duration {
+        self.mean_remaining
+    }
+
+    /// Remaining-time histogram, bucketed by [`REMAINING_BUCKET_BOUNDS`].
+    #[inline]
+    pub fn remaining_histogram(&self) -> &[u64] {
+        &self.histogram
+    }
+
+    /// Ratio of the largest budget to the worst observed runtime.
+    ///
+    /// Returns `f64::INFINITY` if every recorded runtime was zero.
+    #[inline]
+    pub fn headroom(&self) -> f64 {
+        let worst = self.worst_runtime.as_secs_f64();
+        if worst == 0.0 {
+            f64::INFINITY
+        } else {
+            self.max_budget.as_secs_f64() / worst
+        }
+    }
+}
+
+/// Records remaining-time-at-completion for labeled timeout sites.
+///
+/// Create timeouts through [`with_timeout()`](Self::with_timeout) or
+/// [`with_deadline()`](Self::with_deadline); each returned [`SiteTimeout`]
+/// records its outcome when dropped. Code that manages its own deadlines can
+/// feed samples directly via [`record()`](Self::record).
+///
+/// Recording takes a mutex once per completed operation, never on the
+/// `check()` path.
+#[derive(Debug, Default)]
+pub struct DeadlineAnalyzer {
+    sites: Mutex<BTreeMap<&'static str, SiteStats>>,
+}
+
+impl DeadlineAnalyzer {
+    /// Create an empty analyzer.
+    ///
+    /// `const`, so an analyzer can live in a `static`.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            sites: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    fn sites(&self) -> MutexGuard<'_, BTreeMap<&'static str, SiteStats>> {
+        self.sites.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Wrap `inner` in a timeout that records its outcome under `label`.
+    #[inline]
+    pub fn with_timeout<T: Stop>(
+        &self,
+        label: &'static str,
+        inner: T,
+        duration: Duration,
+    ) -> SiteTimeout<'_, T> {
+        let started = Instant::now();
+        SiteTimeout {
+            analyzer: self,
+            label,
+            started,
+            budget: duration,
+            timeout: WithTimeout::with_deadline(inner, started + duration),
+        }
+    }
+
+    /// Wrap `inner` with an absolute deadline that records its outcome under `label`.
+    #[inline]
+    pub fn with_deadline<T: Stop>(
+        &self,
+        label: &'static str,
+        inner: T,
+        deadline: Instant,
+    ) -> SiteTimeout<'_, T> {
+        let started = Instant::now();
+        SiteTimeout {
+            analyzer: self,
+            label,
+            started,
+            budget: deadline.saturating_duration_since(started),
+            timeout: WithTimeout::with_deadline(inner, deadline),
+        }
+    }
+
+    /// Record one completion at `label` that ran for `elapsed` out of `budget`.
+    pub fn record(&self, label: &'static str, budget: Duration, elapsed: Duration) {
+        self.sites()
+            .entry(label)
+            .or_default()
+            .record(budget, elapsed);
+    }
+
+    /// Summaries for every site, ordered by label.
+    pub fn report(&self) -> Vec<SiteReport> {
+        self.sites()
+            .iter()
+            .map(|(label, stats)| SiteReport::new(label, stats))
+            .collect()
+    }
+
+    /// Sites whose budget is at least `factor` times their worst runtime.
+    ///
+    /// Only sites with at least `min_samples` completions and no timeouts
+    /// are considered. Results are ordered by descending
+    /// [`headroom()`](SiteReport::headroom).
+    pub fn over_generous(&self, factor: u32, min_samples: u64) -> Vec<SiteReport> {
+        let mut flagged: Vec<SiteReport> = self
+            .sites()
+            .iter()
+            .filter(|(_, s)| {
+                s.samples >= min_samples
+                    && s.timeouts == 0
+                    && s.max_budget >= s.worst_runtime.saturating_mul(factor)
+            })
+            .map(|(label, stats)| SiteReport::new(label, stats))
+            .collect();
+        flagged.sort_by(|a, b| b.headroom().total_cmp(&a.headroom()));
+        flagged
+    }
+
+    /// Discard all recorded samples.
+    pub fn reset(&self) {
+        self.sites().clear();
+    }
+}
+
+/// A [`WithTimeout`] that reports its outcome to a [`DeadlineAnalyzer`] on drop.
+///
+/// Created by [`DeadlineAnalyzer::with_timeout()`] or
+/// [`DeadlineAnalyzer::with_deadline()`].
+pub struct SiteTimeout<'a, T: Stop> {
+    analyzer: &'a DeadlineAnalyzer,
+    label: &'static str,
+    started: Instant,
+    budget: Duration,
+    timeout: WithTimeout<T>,
+}
+
+impl<T: Stop> SiteTimeout<'_, T> {
+    /// The site label.
+    #[inline]
+    pub fn label(&self) -> &'static str {
+        self.label
+    }
+
+    /// The budget this timeout was created with.
+    #[inline]
+    pub fn budget(&self) -> Duration {
+        self.budget
+    }
+
+    /// Get the remaining time until deadline.
+    #[inline]
+    pub fn remaining(&self) -> Duration {
+        self.timeout.remaining()
+    }
+
+    /// Get a reference to the underlying [`WithTimeout`].
+    #[inline]
+    pub fn timeout(&self) -> &WithTimeout<T> {
+        &self.timeout
+    }
+}
+
+impl<T: Stop> Stop for SiteTimeout<'_, T> {
+    #[inline]
+    fn check(&self) -> Result<(), StopReason> {
+        self.timeout.check()
+    }
+
+    #[inline]
+    fn should_stop(&self) -> bool {
+        self.timeout.should_stop()
+    }
+
+    #[inline]
+    fn may_stop(&self) -> bool {
+        self.timeout.may_stop()
+    }
+}
+
+impl<T: Stop> Drop for SiteTimeout<'_, T> {
+    fn drop(&mut self) {
+        self.analyzer
+            .record(self.label, self.budget, self.started.elapsed());
+    }
+}
+
+impl<T: Stop + core::fmt::Debug> core::fmt::Debug for SiteTimeout<'_, T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("SiteTimeout")
+            .field("label", &self.label)
+            .field("budget", &self.budget)
+            .field("timeout", &self.timeout)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::StopSource;
+
+    #[test]
+    fn records_on_drop() {
+        let analyzer = DeadlineAnalyzer::new();
+        let source = StopSource::new();
+        {
+            let stop = analyzer.with_timeout("a", source.as_ref(), Duration::from_secs(10));
+            assert!(stop.check().is_ok());
+            assert_eq!(stop.label(), "a");
+            assert_eq!(stop.budget(), Duration::from_secs(10));
+        }
+        let report = analyzer.report();
+        assert_eq!(report.len(), 1);
+        assert_eq!(report[0].label(), "a");
+        assert_eq!(report[0].samples(), 1);
+        assert_eq!(report[0].timeouts(), 0);
+        assert!(report[0].min_remaining() > Duration::from_secs(9));
+    }
+
+    #[test]
+    fn flags_over_generous_sites() {
+        let analyzer = DeadlineAnalyzer::new();
+        for _ in 0..5 {
+            analyzer.record(
+                "generous",
+                Duration::from_secs(60),
+                Duration::from_millis(5),
+            );
+            analyzer.record(
+                "tight",
+                Duration::from_millis(100),
+                Duration::from_millis(60),
+            );
+        }
+        let flagged = analyzer.over_generous(100, 5);
+        assert_eq!(flagged.len(), 1);
+        assert_eq!(flagged[0].label(), "generous");
+        assert!(flagged[0].headroom() >= 100.0);
+    }
+
+    #[test]
+    fn sites_with_timeouts_are_not_flagged() {
+        let analyzer = DeadlineAnalyzer::new();
+        analyzer.record("spiky", Duration::from_secs(60), Duration::from_millis(1));
+        analyzer.record("spiky", Duration::from_secs(60), Duration::from_secs(61));
+        assert!(analyzer.over_generous(10, 1).is_empty());
+        assert_eq!(analyzer.report()[0].timeouts(), 1);
+    }
+
+    #[test]
+    fn min_samples_respected() {
+        let analyzer = DeadlineAnalyzer::new();
+        analyzer.record("once", Duration::from_secs(60), Duration::from_millis(1));
+        assert!(analyzer.over_generous(10, 2).is_empty());
+        assert_eq!(analyzer.over_generous(10, 1).len(), 1);
+    }
+
+    #[test]
+    fn histogram_buckets() {
+        let analyzer = DeadlineAnalyzer::new();
+        analyzer.record("h", Duration::from_millis(5), Duration::from_millis(5));
+        analyzer.record("h", Duration::from_secs(5), Duration::ZERO);
+        analyzer.record("h", Duration::from_secs(500), Duration::ZERO);
+        let report = &analyzer.report()[0];
+        let hist = report.remaining_histogram();
+        assert_eq!(hist.len(), REMAINING_BUCKET_BOUNDS.len() + 1);
+        assert_eq!(hist[0], 1); // 0 remaining
+        assert_eq!(hist[4], 1); // 5s
+        assert_eq!(hist[6], 1); // 500s
+        assert_eq!(report.min_remaining(), Duration::ZERO);
+    }
+
+    #[test]
+    fn with_deadline_records_budget() {
+        let analyzer = DeadlineAnalyzer::new();
+        let source = StopSource::new();
+        drop(analyzer.with_deadline(
+            "d",
+            source.as_ref(),
+            Instant::now() + Duration::from_secs(5),
+        ));
+        let report = &analyzer.report()[0];
+        assert!(report.max_budget() <= Duration::from_secs(5));
+        assert!(report.max_budget() > Duration::from_secs(4));
+    }
+
+    #[test]
+    fn reset_clears() {
+        let analyzer = DeadlineAnalyzer::new();
+        analyzer.record("x", Duration::from_secs(1), Duration::ZERO);
+        analyzer.reset();
+        assert!(analyzer.report().is_empty());
+    }
+
+    #[test]
+    fn site_timeout_reports_cancel() {
+        let analyzer = DeadlineAnalyzer::new();
+        let source = StopSource::new();
+        let stop = analyzer.with_timeout("c", source.as_ref(), Duration::from_secs(10));
+        source.cancel();
+        assert_eq!(stop.check(), Err(StopReason::Cancelled));
+    }
+
+    #[test]
+    fn analyzer_is_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<DeadlineAnalyzer>();
+        assert_send_sync::<SiteTimeout<'_, crate::StopRef<'_>>>();
+    }
+}
@@ -0,0 +1,122 @@
+//! Deadline checks against a caller-supplied timestamp.
+//!
+//! Every check of a timeout wrapper reads the clock. A loop that already
+//! calls `Instant::now()` for its own metrics pays for the clock twice per
+//! iteration; [`CheckAt`] lets it pass its timestamp in instead.
+//!
+//! Deadline wrappers ([`WithTimeout`](super::WithTimeout),
+//! [`WithSharedDeadline`](super::WithSharedDeadline),
+//! [`RestoredStop`](crate::RestoredStop)) compare their deadline against
+//! `now`, and combinators such as [`OrStop`](crate::OrStop) forward it to
+//! both halves. Stops without a deadline, and type-erased ones that cannot
+//! forward it, simply `check()`.
+
+use std::time::Instant;
+
+use crate::{Stop, StopReason};
+
+/// A [`Stop`] whose deadline checks can use a timestamp supplied by the
+/// caller.
+///
+/// `check_at(now)` behaves like [`check()`](Stop::check) with `now` in
+/// place of `Instant::now()`. A `now` from the past makes deadlines look
+/// further away than they are, so pass a fresh timestamp; the saving comes
+/// from reusing one the caller took anyway.
+///
+/// # Example
+///
+/// ```rust
+/// use almost_enough::{CheckAt, StopSource, TimeoutExt};
+/// use std::time::{Duration, Instant};
+///
+/// let source = StopSource::new();
+/// let stop = source.as_ref().with_timeout(Duration::from_secs(1));
+///
+/// let start = Instant::now();
+/// for _ in 0..100 {
+///     let now = Instant::now(); // also used for the loop's own metrics
+///     if stop.should_stop_at(now) {
+///         break;
+///     }
+///     let _elapsed = now - start;
+/// }
+/// ```
+pub trait CheckAt: Stop {
+    /// Like [`check()`](Stop::check), comparing deadlines against `now`.
+    fn check_at(&self, now: Instant) -> Result<(), StopReason>;
+
+    /// Like [`should_stop()`](Stop::should_stop), comparing deadlines
+    /// against `now`.
+    #[inline]
+    fn should_stop_at(&self, now: Instant) -> bool {
+        self.check_at(now).is_err()
+    }
+}
+
+impl<T: CheckAt + ?Sized> CheckAt for &T {
+    #[inline]
+    fn check_at(&self, now: Instant) -> Result<(), StopReason> {
+        (**self).check_at(now)
+    }
+}
+
+impl<T: CheckAt> CheckAt for Option<T> {
+    #[inline]
+    fn check_at(&self, now: Instant) -> Result<(), StopReason> {
+        match self {
+            Some(stop) => stop.check_at(now),
+            None => Ok(()),
+        }
+    }
+}
+
+/// `CheckAt` for stops without a deadline: `now` is ignored.
+macro_rules! check_at_without_deadline {
+    ($($ty:ty),* $(,)?) => {$(
+        impl CheckAt for $ty {
+            #[inline]
+            fn check_at(&self, _now: Instant) -> Result<(), StopReason> {
+                self.check()
+            }
+        }
+    )*};
+}
+
+check_at_without_deadline!(
+    crate::Unstoppable,
+    crate::StopSource,
+    crate::StopRef<'_>,
+    crate::Stopper,
+    crate::SyncStopper,
+    crate::ChildStopper,
+);
+
+// Type-erased stops cannot forward `now` to what they wrap, so they check
+// (and read the clock) as usual.
+check_at_without_deadline!(crate::BoxedStop, crate::StopToken);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{StopSource, TimeoutExt, Unstoppable};
+    use std::time::Duration;
+
+    #[test]
+    fn no_deadline_ignores_now() {
+        let source = StopSource::new();
+        let far = Instant::now() + Duration::from_secs(3600);
+        assert!(source.check_at(far).is_ok());
+        assert!(!Unstoppable.should_stop_at(far));
+        assert!(None::<Unstoppable>.check_at(far).is_ok());
+
+        source.cancel();
+        assert_eq!(source.as_ref().check_at(far), Err(StopReason::Cancelled));
+        assert!(<&StopSource as CheckAt>::should_stop_at(&&source, far));
+    }
+
+    #[test]
+    fn erased_stops_still_check_their_deadline() {
+        let stop = crate::BoxedStop::new(Unstoppable.with_timeout(Duration::ZERO));
+        assert_eq!(stop.check_at(Instant::now()), Err(StopReason::TimedOut));
+    }
+}
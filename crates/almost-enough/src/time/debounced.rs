@@ -22,7 +22,7 @@
 use std::sync::atomic::{AtomicU32, AtomicU64, Ordering::Relaxed};
 use std::time::{Duration, Instant};
 
-use crate::{Stop, StopReason};
+use crate::{Behavior, Stop, StopReason};
 
 /// Default target interval between clock reads: 100μs (0.1ms).
 ///
@@ -85,6 +85,7 @@ pub struct DebouncedTimeout<T> {
     deadline_nanos: u64,
     /// Target interval between clock reads, in nanoseconds.
     target_nanos: u64,
+    behavior: Behavior,
 
     // ── Mutable state (atomics for Send+Sync) ──────────────────────
     /// Monotonic call counter (wraps at u32::MAX, which is fine).
@@ -110,6 +111,7 @@ impl<T: Stop> DebouncedTimeout<T> {
             created: now,
             deadline_nanos: duration_to_nanos(duration),
             target_nanos: DEFAULT_TARGET_NANOS,
+            behavior: Behavior::V1,
             call_count: AtomicU32::new(0),
             skip_mod: AtomicU32::new(1),
             last_measured_nanos: AtomicU64::new(0),
@@ -129,6 +131,7 @@ impl<T: Stop> DebouncedTimeout<T> {
             created: now,
             deadline_nanos: duration_to_nanos(deadline.saturating_duration_since(now)),
             target_nanos: DEFAULT_TARGET_NANOS,
+            behavior: Behavior::V1,
             call_count: AtomicU32::new(0),
             skip_mod: AtomicU32::new(1),
             last_measured_nanos: AtomicU64::new(0),
@@ -149,6 +152,22 @@ impl<T: Stop> DebouncedTimeout<T> {
         self
     }
 
+    /// Set the [`Behavior`] used to resolve ties with the inner stop.
+    ///
+    /// With [`TieBreak::Deadline`](crate::TieBreak::Deadline), the clock is
+    /// read whenever the inner stop has stopped, regardless of debouncing.
+    #[inline]
+    pub fn with_behavior(mut self, behavior: Behavior) -> Self {
+        self.behavior = behavior;
+        self
+    }
+
+    /// Get the configured [`Behavior`].
+    #[inline]
+    pub fn behavior(&self) -> Behavior {
+        self.behavior
+    }
+
     /// Get the deadline as an `Instant`.
     #[inline]
     pub fn deadline(&self) -> Instant {
@@ -230,7 +249,11 @@ impl<T: Stop> Stop for DebouncedTimeout<T> {
     #[inline]
     fn check(&self) -> Result<(), StopReason> {
         // Always check the inner stop (typically a single atomic load).
-        self.inner.check()?;
+        if let Err(reason) = self.inner.check() {
+            return Err(self.behavior.resolve_tie(reason, || {
+                self.created.elapsed().as_nanos() as u64 >= self.deadline_nanos
+            }));
+        }
 
         // Increment call counter and decide whether to read the clock.
         let count = self.call_count.fetch_add(1, Relaxed).wrapping_add(1);
@@ -281,6 +304,7 @@ impl<T: Stop> DebouncedTimeout<T> {
             created: self.created,
             deadline_nanos,
             target_nanos: self.target_nanos,
+            behavior: self.behavior,
             call_count: AtomicU32::new(0),
             skip_mod: AtomicU32::new(1),
             last_measured_nanos: AtomicU64::new(0),
@@ -302,6 +326,7 @@ impl<T: Stop> DebouncedTimeout<T> {
             created: self.created,
             deadline_nanos,
             target_nanos: self.target_nanos,
+            behavior: self.behavior,
             call_count: AtomicU32::new(0),
             skip_mod: AtomicU32::new(1),
             last_measured_nanos: AtomicU64::new(0),
@@ -318,6 +343,7 @@ impl<T: Clone + Stop> Clone for DebouncedTimeout<T> {
             created: self.created,
             deadline_nanos: self.deadline_nanos,
             target_nanos: self.target_nanos,
+            behavior: self.behavior,
             call_count: AtomicU32::new(0),
             skip_mod: AtomicU32::new(1),
             last_measured_nanos: AtomicU64::new(0),
@@ -387,6 +413,21 @@ mod tests {
         panic!("should have detected timeout");
     }
 
+    #[test]
+    fn tie_break_reads_clock_when_inner_stopped() {
+        use crate::TieBreak;
+
+        let source = StopSource::new();
+        source.cancel();
+        let stop = DebouncedTimeout::new(source.as_ref(), Duration::ZERO);
+        assert_eq!(stop.check(), Err(StopReason::Cancelled));
+
+        let stop = stop.with_behavior(Behavior::V1.with_tie_break(TieBreak::Deadline));
+        assert_eq!(stop.behavior().tie_break(), TieBreak::Deadline);
+        assert_eq!(stop.check(), Err(StopReason::TimedOut));
+        assert_eq!(stop.clone().check(), Err(StopReason::TimedOut));
+    }
+
     #[test]
     fn cancel_before_timeout() {
         let source = StopSource::new();
@@ -0,0 +1,202 @@
+//! Waiting for an external completion fence while staying cancellable.
+
+use std::time::{Duration, Instant};
+
+use super::sleep::sleep_until;
+use crate::{Stop, StopReason};
+
+/// The first pause of [`FencedStop::wait_fence_or_stop()`]; doubled after
+/// each poll up to the caller's interval.
+const FIRST_BACKOFF: Duration = Duration::from_micros(50);
+
+/// A stop paired with a completion fence, such as a GPU or accelerator
+/// fence.
+///
+/// Device work is submitted, then the host waits for the device to signal
+/// completion. The device APIs expose the fence as a non-blocking query, so
+/// every call site ends up with its own poll loop, sleep interval and
+/// cancellation check. [`wait_fence_or_stop()`](Self::wait_fence_or_stop)
+/// is that loop: it polls the fence with an exponential backoff capped at
+/// the given interval, and returns the stop reason as soon as the stop
+/// fires (a [`Stopper`](crate::Stopper) wakes it immediately).
+///
+/// `FencedStop` also implements [`Stop`] by forwarding to the stop, so it
+/// can be passed on to code that only needs to check.
+///
+/// # Example
+///
+/// ```rust
+/// use almost_enough::time::FencedStop;
+/// use almost_enough::{StopReason, Stopper};
+/// use std::sync::Arc;
+/// use std::sync::atomic::{AtomicBool, Ordering};
+/// use std::time::Duration;
+///
+/// // Stands in for e.g. `device.poll_fence(fence_id)`
+/// let done = Arc::new(AtomicBool::new(false));
+/// let device = Arc::clone(&done);
+/// std::thread::spawn(move || device.store(true, Ordering::Release));
+///
+/// let stop = Stopper::new();
+/// let fenced = FencedStop::new(stop.clone(), || done.load(Ordering::Acquire));
+/// fenced.wait_fence_or_stop(Duration::from_millis(1))?;
+/// // read back the results
+///
+/// stop.cancel();
+/// let pending = FencedStop::new(stop, || false);
+/// assert_eq!(
+///     pending.wait_fence_or_stop(Duration::from_millis(1)),
+///     Err(StopReason::Cancelled)
+/// );
+/// # Ok::<(), StopReason>(())
+/// ```
+pub struct FencedStop<S, F> {
+    stop: S,
+    poll_fence: F,
+}
+
+impl<S: Stop + 'static, F: Fn() -> bool> FencedStop<S, F> {
+    /// Pair `stop` with a fence that has signalled once `poll_fence`
+    /// returns `true`.
+    ///
+    /// `poll_fence` must not block; it is called repeatedly while waiting.
+    #[inline]
+    pub fn new(stop: S, poll_fence: F) -> Self {
+        Self { stop, poll_fence }
+    }
+
+    /// Whether the fence has signalled.
+    #[inline]
+    pub fn is_signaled(&self) -> bool {
+        (self.poll_fence)()
+    }
+
+    /// Block until the fence signals, returning early with the stop reason
+    /// if the stop fires first.
+    ///
+    /// The fence is polled before the stop, so work the device has already
+    /// finished is reported as `Ok` even if the stop fired meanwhile. The
+    /// pause between polls starts at 50µs and doubles up to `interval`.
+    pub fn wait_fence_or_stop(&self, interval: Duration) -> Result<(), StopReason> {
+        let mut pause = FIRST_BACKOFF.min(interval);
+        loop {
+            if self.is_signaled() {
+                return Ok(());
+            }
+            self.stop.check()?;
+            match Instant::now().checked_add(pause) {
+                Some(until) => {
+                    // A stop that fires while pausing is seen by the next check
+                    let _ = sleep_until(&self.stop, until);
+                }
+                None => std::thread::yield_now(),
+            }
+            pause = pause.saturating_mul(2).min(interval);
+        }
+    }
+
+    /// Get a reference to the stop.
+    #[inline]
+    pub fn stop(&self) -> &S {
+        &self.stop
+    }
+
+    /// Unwrap and return the stop.
+    #[inline]
+    pub fn into_inner(self) -> S {
+        self.stop
+    }
+}
+
+impl<S: Stop, F: Fn() -> bool + Send + Sync> Stop for FencedStop<S, F> {
+    #[inline]
+    fn check(&self) -> Result<(), StopReason> {
+        self.stop.check()
+    }
+
+    #[inline]
+    fn should_stop(&self) -> bool {
+        self.stop.should_stop()
+    }
+
+    #[inline]
+    fn may_stop(&self) -> bool {
+        self.stop.may_stop()
+    }
+}
+
+impl<S: core::fmt::Debug, F> core::fmt::Debug for FencedStop<S, F> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("FencedStop")
+            .field("stop", &self.stop)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Stopper, TimeoutExt, Unstoppable};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+
+    const LONG: Duration = Duration::from_secs(60);
+
+    #[test]
+    fn returns_once_signaled() {
+        let polls = AtomicUsize::new(0);
+        let fenced = FencedStop::new(Unstoppable, || polls.fetch_add(1, Ordering::Relaxed) >= 3);
+        assert_eq!(fenced.wait_fence_or_stop(Duration::from_millis(1)), Ok(()));
+        assert_eq!(polls.load(Ordering::Relaxed), 4);
+        assert!(!fenced.may_stop());
+    }
+
+    #[test]
+    fn signaled_fence_wins_over_stop() {
+        let fenced = FencedStop::new(Stopper::cancelled(), || true);
+        assert_eq!(fenced.wait_fence_or_stop(LONG), Ok(()));
+        assert_eq!(fenced.check(), Err(StopReason::Cancelled));
+    }
+
+    #[test]
+    fn cancel_ends_wait() {
+        let stop = Stopper::new();
+        let canceller = stop.clone();
+        let handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(10));
+            canceller.cancel();
+        });
+        let fenced = FencedStop::new(stop, || false);
+        let start = Instant::now();
+        assert_eq!(fenced.wait_fence_or_stop(LONG), Err(StopReason::Cancelled));
+        assert!(start.elapsed() < Duration::from_secs(5));
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn deadline_ends_wait() {
+        let fenced = FencedStop::new(
+            Stopper::new().with_timeout(Duration::from_millis(5)),
+            || false,
+        );
+        assert_eq!(fenced.wait_fence_or_stop(LONG), Err(StopReason::TimedOut));
+        assert!(format!("{fenced:?}").contains("FencedStop"));
+        assert!(fenced.into_inner().should_stop());
+    }
+
+    #[test]
+    fn fence_from_another_thread() {
+        let done = Arc::new(AtomicUsize::new(0));
+        let device = Arc::clone(&done);
+        let handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(5));
+            device.store(1, Ordering::Release);
+        });
+        let fenced = FencedStop::new(Stopper::new(), || done.load(Ordering::Acquire) == 1);
+        assert!(!fenced.stop().is_cancelled());
+        assert_eq!(fenced.wait_fence_or_stop(Duration::from_millis(2)), Ok(()));
+        assert!(fenced.is_signaled());
+        handle.join().unwrap();
+    }
+}
@@ -0,0 +1,302 @@
+//! A timeout that stays expired.
+//!
+//! [`WithTimeout`] compares the clock on every check and remembers nothing,
+//! so after its deadline the reported reason still depends on the inner
+//! stop and on [`Behavior`](crate::Behavior) tie-breaking. A retry loop or
+//! a logger that checks twice can see `TimedOut` and then `Cancelled`.
+//! [`LatchedTimeout`] records the expiry the first time a check observes it
+//! and reports `TimedOut` from then on, without reading the clock or the
+//! inner stop again, and runs its `on_expire` callbacks exactly once.
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use super::{CheckAt, WithTimeout};
+use crate::{Stop, StopReason};
+
+type Callback = Box<dyn FnOnce() + Send>;
+
+/// A [`WithTimeout`] that latches [`StopReason::TimedOut`] once it expires.
+///
+/// Created by [`WithTimeout::latched()`] or [`LatchedTimeout::new()`].
+/// Before the deadline it behaves exactly like the wrapped `WithTimeout`.
+/// The first check that reports `TimedOut` latches it: every later check
+/// returns `TimedOut` immediately, even if the inner stop is cancelled
+/// afterwards, and the callbacks registered with
+/// [`on_expire()`](Self::on_expire) run on the thread that made that check.
+///
+/// Expiry is only noticed by a check, like `WithTimeout`; nothing fires at
+/// the deadline by itself. `should_stop()` goes through `check()` so that it
+/// latches too.
+///
+/// # Example
+///
+/// ```rust
+/// use almost_enough::{Stop, StopReason, Stopper, TimeoutExt};
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+/// use std::sync::Arc;
+/// use std::time::Duration;
+///
+/// let expirations = Arc::new(AtomicUsize::new(0));
+/// let counter = Arc::clone(&expirations);
+///
+/// let request = Stopper::new();
+/// let stop = request
+///     .clone()
+///     .with_timeout(Duration::ZERO)
+///     .latched()
+///     .on_expire(move || {
+///         counter.fetch_add(1, Ordering::Relaxed);
+///     });
+///
+/// assert_eq!(stop.check(), Err(StopReason::TimedOut));
+///
+/// // Still TimedOut, and the callback does not run again
+/// request.cancel();
+/// assert_eq!(stop.check(), Err(StopReason::TimedOut));
+/// assert_eq!(expirations.load(Ordering::Relaxed), 1);
+/// ```
+pub struct LatchedTimeout<T> {
+    inner: WithTimeout<T>,
+    expired: AtomicBool,
+    on_expire: Mutex<Vec<Callback>>,
+}
+
+impl<T: Stop> LatchedTimeout<T> {
+    /// Wrap `inner` with a deadline `duration` from now.
+    #[inline]
+    pub fn new(inner: T, duration: Duration) -> Self {
+        WithTimeout::new(inner, duration).latched()
+    }
+
+    /// Wrap `inner` with an absolute deadline.
+    #[inline]
+    pub fn with_deadline(inner: T, deadline: Instant) -> Self {
+        WithTimeout::with_deadline(inner, deadline).latched()
+    }
+
+    /// Run `f` once, when a check first observes the expiry.
+    ///
+    /// Callbacks run in registration order. If this timeout has already
+    /// expired, `f` runs immediately. A panicking callback propagates out
+    /// of the check that ran it; the timeout stays expired, and callbacks
+    /// after it are dropped without running.
+    pub fn on_expire(self, f: impl FnOnce() + Send + 'static) -> Self {
+        if self.is_expired() {
+            f();
+        } else {
+            self.callbacks().push(Box::new(f));
+            // A check may have latched while we pushed
+            if self.is_expired() {
+                self.run_callbacks();
+            }
+        }
+        self
+    }
+
+    /// Returns `true` once a check has observed the expiry.
+    ///
+    /// Reads the latch only; never the clock.
+    #[inline]
+    pub fn is_expired(&self) -> bool {
+        self.expired.load(Ordering::Acquire)
+    }
+
+    /// Get the deadline.
+    #[inline]
+    pub fn deadline(&self) -> Instant {
+        self.inner.deadline()
+    }
+
+    /// Get the remaining time until the deadline, or `Duration::ZERO` once
+    /// expired.
+    #[inline]
+    pub fn remaining(&self) -> Duration {
+        if self.is_expired() {
+            Duration::ZERO
+        } else {
+            self.inner.remaining()
+        }
+    }
+
+    /// Get a reference to the wrapped [`WithTimeout`].
+    #[inline]
+    pub fn inner(&self) -> &WithTimeout<T> {
+        &self.inner
+    }
+
+    /// Unwrap and return the wrapped [`WithTimeout`], dropping any
+    /// callbacks that have not run.
+    #[inline]
+    pub fn into_inner(self) -> WithTimeout<T> {
+        self.inner
+    }
+
+    fn callbacks(&self) -> std::sync::MutexGuard<'_, Vec<Callback>> {
+        self.on_expire
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn run_callbacks(&self) {
+        // Taken under the lock, run outside it: a callback may check again
+        let callbacks = core::mem::take(&mut *self.callbacks());
+        for callback in callbacks {
+            callback();
+        }
+    }
+
+    /// Latch the expiry for `result`, running the callbacks if this call
+    /// is the one that latched.
+    #[inline]
+    fn latch(&self, result: Result<(), StopReason>) -> Result<(), StopReason> {
+        if result == Err(StopReason::TimedOut) && !self.expired.swap(true, Ordering::AcqRel) {
+            #[cfg(feature = "log")]
+            crate::logging::deadline_expired(None);
+            self.run_callbacks();
+        }
+        result
+    }
+}
+
+impl<T: Stop> WithTimeout<T> {
+    /// Latch [`StopReason::TimedOut`] once this timeout expires.
+    ///
+    /// See [`LatchedTimeout`].
+    #[inline]
+    pub fn latched(self) -> LatchedTimeout<T> {
+        LatchedTimeout {
+            inner: self,
+            expired: AtomicBool::new(false),
+            on_expire: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+impl<T: Stop> Stop for LatchedTimeout<T> {
+    #[inline]
+    fn check(&self) -> Result<(), StopReason> {
+        if self.is_expired() {
+            return Err(StopReason::TimedOut);
+        }
+        self.latch(self.inner.check())
+    }
+
+    #[inline]
+    fn should_stop(&self) -> bool {
+        self.check().is_err()
+    }
+}
+
+impl<T: CheckAt> CheckAt for LatchedTimeout<T> {
+    #[inline]
+    fn check_at(&self, now: Instant) -> Result<(), StopReason> {
+        if self.is_expired() {
+            return Err(StopReason::TimedOut);
+        }
+        self.latch(self.inner.check_at(now))
+    }
+}
+
+impl<T: core::fmt::Debug> core::fmt::Debug for LatchedTimeout<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let pending = self
+            .on_expire
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .len();
+        f.debug_struct("LatchedTimeout")
+            .field("inner", &self.inner)
+            .field("expired", &self.expired.load(Ordering::Acquire))
+            .field("pending_callbacks", &pending)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{StopSource, TimeoutExt, Unstoppable};
+    use alloc::sync::Arc;
+    use core::sync::atomic::AtomicUsize;
+
+    fn counter() -> (Arc<AtomicUsize>, impl FnOnce() + Send + 'static) {
+        let count = Arc::new(AtomicUsize::new(0));
+        let inc = Arc::clone(&count);
+        (count, move || {
+            inc.fetch_add(1, Ordering::Relaxed);
+        })
+    }
+
+    #[test]
+    fn running_until_deadline() {
+        let source = StopSource::new();
+        let (count, inc) = counter();
+        let stop = LatchedTimeout::new(source.as_ref(), Duration::from_secs(60)).on_expire(inc);
+
+        assert!(!stop.should_stop());
+        assert!(!stop.is_expired());
+
+        // Cancellation before the deadline is reported but not latched
+        source.cancel();
+        assert_eq!(stop.check(), Err(StopReason::Cancelled));
+        assert!(!stop.is_expired());
+        assert_eq!(count.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn expiry_is_permanent() {
+        let source = StopSource::new();
+        let (count, inc) = counter();
+        let stop = source
+            .as_ref()
+            .with_timeout(Duration::ZERO)
+            .latched()
+            .on_expire(inc);
+
+        assert!(stop.should_stop());
+        assert!(stop.is_expired());
+        assert_eq!(stop.remaining(), Duration::ZERO);
+
+        // A plain WithTimeout would now report Cancelled
+        source.cancel();
+        assert_eq!(stop.inner().check(), Err(StopReason::Cancelled));
+        assert_eq!(stop.check(), Err(StopReason::TimedOut));
+        assert_eq!(count.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn callback_added_after_expiry_runs_now() {
+        let stop = Unstoppable.with_timeout(Duration::ZERO).latched();
+        assert!(stop.should_stop());
+        let (count, inc) = counter();
+        let stop = stop.on_expire(inc);
+        assert_eq!(count.load(Ordering::Relaxed), 1);
+        stop.check().unwrap_err();
+        assert_eq!(count.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn callbacks_run_once_across_threads() {
+        let (count, inc) = counter();
+        let stop = LatchedTimeout::with_deadline(Unstoppable, Instant::now()).on_expire(inc);
+        std::thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| assert_eq!(stop.check(), Err(StopReason::TimedOut)));
+            }
+        });
+        assert_eq!(count.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn check_at_latches() {
+        let stop = LatchedTimeout::new(Unstoppable, Duration::from_secs(60));
+        let later = Instant::now() + Duration::from_secs(120);
+        assert_eq!(stop.check_at(later), Err(StopReason::TimedOut));
+        // Latched: a real clock reading no longer matters
+        assert_eq!(stop.check(), Err(StopReason::TimedOut));
+    }
+}
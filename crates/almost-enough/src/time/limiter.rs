@@ -0,0 +1,231 @@
+//! A token-bucket rate limiter that gives up when a stop fires.
+
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+use super::sleep::sleep_until;
+use crate::{Stop, StopReason};
+
+/// A token-bucket rate limiter whose [`acquire()`](Self::acquire) returns
+/// early when a stop fires.
+///
+/// Throttled batch jobs need both a rate limit and prompt cancellation. A
+/// third-party limiter blocks inside its own wait, where no stop is
+/// checked, so a job cancelled while waiting for a permit hangs until the
+/// permit arrives. [`acquire()`](Self::acquire) waits the way
+/// [`sleep_until_cancelled()`](crate::time::sleep_until_cancelled) does:
+/// it returns the stop reason as soon as the stop fires or its deadline
+/// passes, and a [`Stopper`](crate::Stopper) wakes it the moment it is
+/// cancelled.
+///
+/// One permit is added every `interval`, and up to `burst` unused permits
+/// are saved. The bucket starts full. Share it between workers with an
+/// `Arc`.
+///
+/// Once an `acquire()` has seen its stop fire, the limiter is closed: it
+/// refuses all further permits with that reason, so workers sharing it
+/// drain without starting new requests.
+///
+/// # Example
+///
+/// ```rust
+/// use almost_enough::time::StopAwareLimiter;
+/// use almost_enough::{StopReason, Stopper};
+/// use std::time::Duration;
+///
+/// // 100 requests per second, bursts of up to 10
+/// let limiter = StopAwareLimiter::new(Duration::from_millis(10), 10);
+/// let stop = Stopper::new();
+///
+/// for _ in 0..3 {
+///     limiter.acquire(&stop)?;
+///     // send a request, ...
+/// }
+///
+/// stop.cancel();
+/// assert_eq!(limiter.acquire(&stop), Err(StopReason::Cancelled));
+/// assert!(!limiter.try_acquire());
+/// # Ok::<(), StopReason>(())
+/// ```
+#[derive(Debug)]
+pub struct StopAwareLimiter {
+    interval: Duration,
+    burst: u32,
+    state: Mutex<Bucket>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    /// When the bucket is empty again after the permits granted so far
+    /// (the "theoretical arrival time" of the next permit at a steady
+    /// rate). A permit is available while `now >= empty_at - tolerance`.
+    empty_at: Instant,
+    /// Set once an `acquire()` has seen its stop fire.
+    closed: Option<StopReason>,
+}
+
+impl StopAwareLimiter {
+    /// One permit every `interval`, saving up to `burst` permits.
+    ///
+    /// A `burst` of 0 is treated as 1.
+    pub fn new(interval: Duration, burst: u32) -> Self {
+        Self {
+            interval,
+            burst: burst.max(1),
+            state: Mutex::new(Bucket {
+                empty_at: Instant::now(),
+                closed: None,
+            }),
+        }
+    }
+
+    fn bucket(&self) -> MutexGuard<'_, Bucket> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// How far ahead of the steady schedule grants may run.
+    fn tolerance(&self) -> Duration {
+        self.interval.saturating_mul(self.burst - 1)
+    }
+
+    /// Take a permit if one is available now, or return when the next one
+    /// will be.
+    fn take(&self, bucket: &mut Bucket, now: Instant) -> Result<(), Instant> {
+        let start = bucket.empty_at.max(now);
+        let available_at = bucket.empty_at.checked_sub(self.tolerance()).unwrap_or(now);
+        if available_at > now {
+            return Err(available_at);
+        }
+        // An unrepresentable schedule never refills
+        bucket.empty_at = start.checked_add(self.interval).unwrap_or(start);
+        Ok(())
+    }
+
+    /// Wait for a permit, returning early with the stop reason if `stop`
+    /// fires first.
+    ///
+    /// The stop is checked before anything else, so a stopped stop gets
+    /// no permit even if one is available. Once this returns the stop
+    /// reason, the limiter is [closed](Self::closed).
+    pub fn acquire<S: Stop + 'static>(&self, stop: &S) -> Result<(), StopReason> {
+        loop {
+            let available_at = {
+                let mut bucket = self.bucket();
+                if let Some(reason) = bucket.closed {
+                    return Err(reason);
+                }
+                if let Err(reason) = stop.check() {
+                    bucket.closed = Some(reason);
+                    return Err(reason);
+                }
+                match self.take(&mut bucket, Instant::now()) {
+                    Ok(()) => return Ok(()),
+                    Err(at) => at,
+                }
+            };
+            // Another waiter may take the permit first; then wait again
+            if let Err(reason) = sleep_until(stop, available_at).into_result() {
+                self.bucket().closed.get_or_insert(reason);
+                return Err(reason);
+            }
+        }
+    }
+
+    /// Take a permit without waiting.
+    ///
+    /// Returns `false` if none is available or the limiter is closed.
+    pub fn try_acquire(&self) -> bool {
+        let mut bucket = self.bucket();
+        bucket.closed.is_none() && self.take(&mut bucket, Instant::now()).is_ok()
+    }
+
+    /// The stop reason that closed the limiter, or `None` while it grants
+    /// permits.
+    pub fn closed(&self) -> Option<StopReason> {
+        self.bucket().closed
+    }
+
+    /// The interval between permits.
+    #[inline]
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// The number of permits that may be saved up.
+    #[inline]
+    pub fn burst(&self) -> u32 {
+        self.burst
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Stopper, TimeoutExt, Unstoppable};
+    use std::sync::Arc;
+    use std::thread;
+
+    const LONG: Duration = Duration::from_secs(3600);
+
+    #[test]
+    fn burst_then_rate() {
+        let limiter = StopAwareLimiter::new(Duration::from_millis(5), 3);
+        for _ in 0..3 {
+            assert!(limiter.try_acquire());
+        }
+        assert!(!limiter.try_acquire());
+
+        let start = Instant::now();
+        limiter.acquire(&Unstoppable).unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(4));
+        assert_eq!(limiter.closed(), None);
+    }
+
+    #[test]
+    fn zero_burst_is_one() {
+        let limiter = StopAwareLimiter::new(LONG, 0);
+        assert_eq!(limiter.burst(), 1);
+        assert_eq!(limiter.interval(), LONG);
+        assert!(limiter.try_acquire());
+        assert!(!limiter.try_acquire());
+    }
+
+    #[test]
+    fn cancel_wakes_waiter_and_closes() {
+        let limiter = Arc::new(StopAwareLimiter::new(LONG, 1));
+        assert!(limiter.try_acquire());
+        let stop = Stopper::new();
+        let waiter = {
+            let limiter = Arc::clone(&limiter);
+            let stop = stop.clone();
+            thread::spawn(move || limiter.acquire(&stop))
+        };
+        thread::sleep(Duration::from_millis(10));
+        stop.cancel();
+        assert_eq!(waiter.join().unwrap(), Err(StopReason::Cancelled));
+
+        // Closed for everyone, including unstoppable callers
+        assert_eq!(limiter.closed(), Some(StopReason::Cancelled));
+        assert_eq!(limiter.acquire(&Unstoppable), Err(StopReason::Cancelled));
+        assert!(!limiter.try_acquire());
+    }
+
+    #[test]
+    fn deadline_ends_wait() {
+        let limiter = StopAwareLimiter::new(LONG, 1);
+        assert!(limiter.try_acquire());
+        let stop = Stopper::new().with_timeout(Duration::from_millis(10));
+        let start = Instant::now();
+        assert_eq!(limiter.acquire(&stop), Err(StopReason::TimedOut));
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert_eq!(limiter.closed(), Some(StopReason::TimedOut));
+    }
+
+    #[test]
+    fn stopped_stop_gets_no_permit() {
+        let limiter = StopAwareLimiter::new(LONG, 5);
+        let stop = Stopper::cancelled();
+        assert_eq!(limiter.acquire(&stop), Err(StopReason::Cancelled));
+        assert!(!limiter.try_acquire());
+    }
+}
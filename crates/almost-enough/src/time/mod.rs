@@ -7,6 +7,16 @@
 //!
 //! - [`WithTimeout`] - Wraps any `Stop` and adds a deadline
 //! - [`TimeoutExt`] - Extension trait providing `.with_timeout()` and `.with_deadline()`
+//! - [`LatchedTimeout`] - Like `WithTimeout`, reports `TimedOut` permanently once expired
+//! - [`WithSharedDeadline`] - Like `WithTimeout`, with one [`SharedDeadlineHandle`] behind many stops
+//! - [`DeadlineAnalyzer`] - Records how much of each labeled deadline is used
+//! - [`WithSystemDeadline`] - Stops at a wall-clock time, robust to suspend and clock changes
+//! - [`sleep_until_cancelled()`] / [`every()`] - Sleep or tick until a stop fires
+//! - [`StopTicker`] - Owned drift-free ticker; `wait_tick()` returns the stop reason on cancel
+//! - [`StopAwareLimiter`] - Token-bucket rate limiter whose `acquire()` returns early when a stop fires
+//! - [`FencedStop`] - Wait for a GPU/accelerator fence with backoff, returning early when a stop fires
+//! - [`CheckAt`] - Check deadlines against a timestamp the caller already has
+//! - `parse_duration()` - Parse `"30s"`, `"1m30s"`, `"250ms"` from configuration (`config` feature)
 //!
 //! # Example
 //!
@@ -37,18 +47,46 @@
 //! // Effective timeout is ~10 seconds (the tighter of the two)
 //! ```
 
+mod analyzer;
+mod at;
 mod debounced;
-
+mod fence;
+mod latched;
+mod limiter;
+#[cfg(any(feature = "config", feature = "signal"))]
+pub(crate) mod parse;
+mod shared;
+mod sleep;
+mod wall;
+
+pub use analyzer::{DeadlineAnalyzer, REMAINING_BUCKET_BOUNDS, SiteReport, SiteTimeout};
+pub use at::CheckAt;
 pub use debounced::{DebouncedTimeout, DebouncedTimeoutExt};
+pub use fence::FencedStop;
+pub use latched::LatchedTimeout;
+pub use limiter::StopAwareLimiter;
+#[cfg(feature = "config")]
+pub use parse::{ParseDurationError, parse_duration};
+pub use shared::{SharedDeadlineHandle, WithSharedDeadline};
+pub use sleep::{Every, SleepOutcome, StopTicker, every, sleep_until_cancelled};
+pub use wall::WithSystemDeadline;
 
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime};
 
-use crate::{Stop, StopReason};
+use crate::{Behavior, Stop, StopReason};
 
 /// A [`Stop`] wrapper that adds a deadline.
 ///
 /// The wrapped stop will return [`StopReason::TimedOut`] if the deadline
-/// passes, or propagate the inner stop's reason if it stops first.
+/// passes, or propagate the inner stop's reason if it stops first. If both
+/// have happened, the wrapper's [`Behavior`] decides which is reported.
+///
+/// Each check re-reads the clock, so a reason is not remembered between
+/// calls; [`cache_reason()`](crate::StopExt::cache_reason) keeps the one
+/// behind a `should_stop() == true`.
+///
+/// `Instant::now()` panics on `wasm32-unknown-unknown`; there, use
+/// `with_performance_timeout()` from the `wasm` feature instead.
 ///
 /// # Example
 ///
@@ -69,6 +107,7 @@ use crate::{Stop, StopReason};
 pub struct WithTimeout<T> {
     inner: T,
     deadline: Instant,
+    behavior: Behavior,
 }
 
 impl<T: Stop> WithTimeout<T> {
@@ -77,16 +116,30 @@ impl<T: Stop> WithTimeout<T> {
     /// The deadline is calculated as `Instant::now() + duration`.
     #[inline]
     pub fn new(inner: T, duration: Duration) -> Self {
+        Self::with_deadline(inner, Instant::now() + duration)
+    }
+
+    /// Create a timeout wrapper with an absolute deadline.
+    #[inline]
+    pub fn with_deadline(inner: T, deadline: Instant) -> Self {
         Self {
             inner,
-            deadline: Instant::now() + duration,
+            deadline,
+            behavior: Behavior::V1,
         }
     }
 
-    /// Create a timeout wrapper with an absolute deadline.
+    /// Set the [`Behavior`] used to resolve ties with the inner stop.
     #[inline]
-    pub fn with_deadline(inner: T, deadline: Instant) -> Self {
-        Self { inner, deadline }
+    pub fn with_behavior(mut self, behavior: Behavior) -> Self {
+        self.behavior = behavior;
+        self
+    }
+
+    /// Get the configured [`Behavior`].
+    #[inline]
+    pub fn behavior(&self) -> Behavior {
+        self.behavior
     }
 
     /// Get the deadline.
@@ -120,7 +173,11 @@ impl<T: Stop> Stop for WithTimeout<T> {
     #[inline]
     fn check(&self) -> Result<(), StopReason> {
         // Check inner first (may be Cancelled)
-        self.inner.check()?;
+        if let Err(reason) = self.inner.check() {
+            return Err(self
+                .behavior
+                .resolve_tie(reason, || Instant::now() >= self.deadline));
+        }
         // Then check timeout
         if Instant::now() >= self.deadline {
             Err(StopReason::TimedOut)
@@ -135,6 +192,20 @@ impl<T: Stop> Stop for WithTimeout<T> {
     }
 }
 
+impl<T: CheckAt> CheckAt for WithTimeout<T> {
+    #[inline]
+    fn check_at(&self, now: Instant) -> Result<(), StopReason> {
+        if let Err(reason) = self.inner.check_at(now) {
+            return Err(self.behavior.resolve_tie(reason, || now >= self.deadline));
+        }
+        if now >= self.deadline {
+            Err(StopReason::TimedOut)
+        } else {
+            Ok(())
+        }
+    }
+}
+
 /// Extension trait for adding timeouts to any [`Stop`] implementation.
 ///
 /// This trait is automatically implemented for all `Stop` types.
@@ -184,6 +255,51 @@ pub trait TimeoutExt: Stop + Sized {
     fn with_deadline(self, deadline: Instant) -> WithTimeout<Self> {
         WithTimeout::with_deadline(self, deadline)
     }
+
+    /// Add a deadline shared with other stops.
+    ///
+    /// The returned [`WithSharedDeadline`] holds a clone of `deadline`, so
+    /// [`SharedDeadlineHandle::tighten()`] moves it together with every
+    /// other stop created from the same handle.
+    ///
+    /// ```rust
+    /// use almost_enough::time::SharedDeadlineHandle;
+    /// use almost_enough::{StopSource, TimeoutExt};
+    /// use std::time::Duration;
+    ///
+    /// let source = StopSource::new();
+    /// let deadline = SharedDeadlineHandle::after(Duration::from_secs(30));
+    /// let stop = source.as_ref().with_shared_deadline(&deadline);
+    ///
+    /// deadline.tighten_after(Duration::from_secs(1));
+    /// assert!(stop.remaining() <= Duration::from_secs(1));
+    /// ```
+    #[inline]
+    fn with_shared_deadline(self, deadline: &SharedDeadlineHandle) -> WithSharedDeadline<Self> {
+        WithSharedDeadline::new(self, deadline.clone())
+    }
+
+    /// Stop at a wall-clock time.
+    ///
+    /// Unlike [`with_deadline()`](Self::with_deadline), the target follows
+    /// the system clock: time spent suspended and wall-clock adjustments are
+    /// taken into account. Use this for "stop at the maintenance window"
+    /// rather than "stop after N seconds".
+    ///
+    /// ```rust
+    /// use almost_enough::{StopSource, Stop, TimeoutExt};
+    /// use std::time::{Duration, SystemTime};
+    ///
+    /// let source = StopSource::new();
+    /// let stop = source.as_ref()
+    ///     .cancel_at_system_time(SystemTime::now() + Duration::from_secs(600));
+    ///
+    /// assert!(!stop.should_stop());
+    /// ```
+    #[inline]
+    fn cancel_at_system_time(self, target: SystemTime) -> WithSystemDeadline<Self> {
+        WithSystemDeadline::new(self, target)
+    }
 }
 
 impl<T: Stop> TimeoutExt for T {}
@@ -198,6 +314,7 @@ impl<T: Stop> WithTimeout<T> {
         Self {
             inner: self.inner,
             deadline: self.deadline.min(new_deadline),
+            behavior: self.behavior,
         }
     }
 
@@ -209,6 +326,7 @@ impl<T: Stop> WithTimeout<T> {
         Self {
             inner: self.inner,
             deadline: self.deadline.min(deadline),
+            behavior: self.behavior,
         }
     }
 }
@@ -232,6 +350,31 @@ mod tests {
         assert_eq!(stop.check(), Err(StopReason::TimedOut));
     }
 
+    #[test]
+    fn check_at_uses_given_timestamp() {
+        let source = StopSource::new();
+        let stop = source.as_ref().with_timeout(Duration::from_secs(60));
+        let deadline = stop.deadline();
+
+        assert!(stop.check_at(deadline - Duration::from_millis(1)).is_ok());
+        assert_eq!(stop.check_at(deadline), Err(StopReason::TimedOut));
+        assert!(stop.should_stop_at(deadline + Duration::from_secs(1)));
+
+        // Nested wrappers see the same `now`
+        let inner_first = source
+            .as_ref()
+            .with_timeout(Duration::from_secs(10))
+            .with_timeout(Duration::from_secs(3600));
+        let at = Instant::now() + Duration::from_secs(30);
+        assert_eq!(inner_first.check_at(at), Err(StopReason::TimedOut));
+
+        source.cancel();
+        assert_eq!(
+            stop.check_at(deadline - Duration::from_secs(1)),
+            Err(StopReason::Cancelled)
+        );
+    }
+
     #[test]
     fn cancel_before_timeout() {
         let source = StopSource::new();
@@ -243,6 +386,27 @@ mod tests {
         assert_eq!(stop.check(), Err(StopReason::Cancelled));
     }
 
+    #[test]
+    fn tie_break_selects_reported_reason() {
+        use crate::TieBreak;
+
+        let source = StopSource::new();
+        source.cancel();
+        let stop = source.as_ref().with_timeout(Duration::ZERO);
+        assert_eq!(stop.behavior(), Behavior::V1);
+        assert_eq!(stop.check(), Err(StopReason::Cancelled));
+
+        let stop = stop.with_behavior(Behavior::V1.with_tie_break(TieBreak::Deadline));
+        assert_eq!(stop.check(), Err(StopReason::TimedOut));
+
+        // Deadline-wins only applies once the deadline has passed
+        let stop = source
+            .as_ref()
+            .with_timeout(Duration::from_secs(60))
+            .with_behavior(Behavior::V1.with_tie_break(TieBreak::Deadline));
+        assert_eq!(stop.check(), Err(StopReason::Cancelled));
+    }
+
     #[test]
     fn timeout_tightens() {
         let source = StopSource::new();
@@ -0,0 +1,213 @@
+//! Durations written as strings.
+//!
+//! Timeouts that come from configuration files, environment variables or
+//! command lines arrive as strings. [`parse_duration()`] reads them with one
+//! grammar, shared with [`cli::parse_timeout()`](crate::cli::parse_timeout),
+//! so a service does not pull in a parsing crate per config source.
+//!
+//! # Grammar
+//!
+//! One or more `<number><unit>` segments, optionally separated by spaces,
+//! whose durations are added: `"30s"`, `"1m30s"`, `"1h 15m"`, `"250ms"`.
+//! Units are `ns`, `us`, `ms`, `s`, `m`, `h` and `d`. Numbers may have a
+//! fraction (`"1.5m"`). A bare number with no unit is seconds (`"30"`).
+//! Signs, exponents and anything else are rejected.
+//!
+//! The public functions require the `config` feature.
+
+use std::time::Duration;
+
+#[cfg(feature = "config")]
+use super::WithTimeout;
+#[cfg(feature = "config")]
+use crate::Stop;
+
+/// Error returned by [`parse_duration()`].
+#[cfg(feature = "config")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseDurationError {
+    input: String,
+}
+
+#[cfg(feature = "config")]
+impl ParseDurationError {
+    /// The string that failed to parse.
+    #[inline]
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+}
+
+#[cfg(feature = "config")]
+impl core::fmt::Display for ParseDurationError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "invalid duration {:?}: expected e.g. 30s, 1m30s or 250ms",
+            self.input
+        )
+    }
+}
+
+#[cfg(feature = "config")]
+impl std::error::Error for ParseDurationError {}
+
+/// Parse a duration such as `"30s"`, `"1m30s"` or `"250ms"`.
+///
+/// See the [grammar](self#grammar). Requires the `config` feature.
+///
+/// # Example
+///
+/// ```rust
+/// use almost_enough::time::parse_duration;
+/// use std::time::Duration;
+///
+/// assert_eq!(parse_duration("1m30s"), Ok(Duration::from_secs(90)));
+/// assert_eq!(parse_duration("250ms"), Ok(Duration::from_millis(250)));
+/// assert_eq!(parse_duration("30"), Ok(Duration::from_secs(30)));
+/// assert!(parse_duration("30 seconds").is_err());
+/// ```
+#[cfg(feature = "config")]
+pub fn parse_duration(input: &str) -> Result<Duration, ParseDurationError> {
+    parse(input).ok_or_else(|| ParseDurationError {
+        input: input.to_owned(),
+    })
+}
+
+#[cfg(feature = "config")]
+impl<T: Stop> WithTimeout<T> {
+    /// Wrap `inner` with a deadline `duration` from now, parsed with
+    /// [`parse_duration()`].
+    ///
+    /// Requires the `config` feature.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use almost_enough::{Stop, Stopper, WithTimeout};
+    ///
+    /// // e.g. from REQUEST_TIMEOUT=1m30s
+    /// let stop = WithTimeout::from_str_duration(Stopper::new(), "1m30s")?;
+    /// assert!(!stop.should_stop());
+    /// # Ok::<(), almost_enough::time::ParseDurationError>(())
+    /// ```
+    pub fn from_str_duration(inner: T, duration: &str) -> Result<Self, ParseDurationError> {
+        parse_duration(duration).map(|duration| Self::new(inner, duration))
+    }
+}
+
+/// Nanoseconds per unit.
+fn unit_nanos(unit: &str) -> Option<u128> {
+    Some(match unit {
+        "ns" => 1,
+        "us" => 1_000,
+        "ms" => 1_000_000,
+        "s" => 1_000_000_000,
+        "m" => 60 * 1_000_000_000,
+        "h" => 3_600 * 1_000_000_000,
+        "d" => 86_400 * 1_000_000_000,
+        _ => return None,
+    })
+}
+
+/// `number` units of `nanos` each.
+fn segment(number: &str, nanos: u128) -> Option<Duration> {
+    if number.is_empty() || number.starts_with('.') || number.ends_with('.') {
+        return None;
+    }
+    if number.contains('.') {
+        let value: f64 = number.parse().ok()?;
+        return Duration::try_from_secs_f64(value * nanos as f64 / 1e9).ok();
+    }
+    // Integers stay exact
+    let total = number.parse::<u128>().ok()?.checked_mul(nanos)?;
+    let secs = u64::try_from(total / 1_000_000_000).ok()?;
+    Some(Duration::new(secs, (total % 1_000_000_000) as u32))
+}
+
+/// The grammar behind [`parse_duration()`] and `cli::parse_timeout()`.
+pub(crate) fn parse(input: &str) -> Option<Duration> {
+    let is_number = |c: char| c.is_ascii_digit() || c == '.';
+    let mut rest = input.trim();
+    if !rest.is_empty() && rest.chars().all(is_number) {
+        return segment(rest, 1_000_000_000);
+    }
+    let mut total = Duration::ZERO;
+    let mut any = false;
+    while !rest.is_empty() {
+        let (number, tail) = rest.split_at(rest.find(|c| !is_number(c))?);
+        let unit_end = tail
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(tail.len());
+        let (unit, tail) = tail.split_at(unit_end);
+        total = total.checked_add(segment(number, unit_nanos(unit)?)?)?;
+        any = true;
+        rest = tail.trim_start();
+    }
+    any.then_some(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn single_units() {
+        assert_eq!(parse("30"), Some(Duration::from_secs(30)));
+        assert_eq!(parse("30s"), Some(Duration::from_secs(30)));
+        assert_eq!(parse(" 250ms "), Some(Duration::from_millis(250)));
+        assert_eq!(parse("15us"), Some(Duration::from_micros(15)));
+        assert_eq!(parse("7ns"), Some(Duration::from_nanos(7)));
+        assert_eq!(parse("2h"), Some(Duration::from_secs(7200)));
+        assert_eq!(parse("1d"), Some(Duration::from_secs(86_400)));
+        assert_eq!(parse("1.5m"), Some(Duration::from_secs(90)));
+        assert_eq!(parse("0.25"), Some(Duration::from_millis(250)));
+    }
+
+    #[test]
+    fn compound() {
+        assert_eq!(parse("1m30s"), Some(Duration::from_secs(90)));
+        assert_eq!(parse("1h 15m"), Some(Duration::from_secs(4500)));
+        assert_eq!(parse("1s500ms"), Some(Duration::from_millis(1500)));
+        assert_eq!(parse("2m2m"), Some(Duration::from_secs(240)));
+    }
+
+    #[test]
+    fn rejects() {
+        for bad in [
+            "",
+            " ",
+            "s",
+            "-1s",
+            "+1s",
+            "1e3",
+            "10x",
+            "1m30",
+            "30 seconds",
+            "1..5s",
+            ".5s",
+            "5.s",
+            "1 m",
+            "99999999999999999999999d",
+        ] {
+            assert_eq!(parse(bad), None, "{bad:?}");
+        }
+    }
+
+    #[cfg(feature = "config")]
+    #[test]
+    fn with_timeout_from_str() {
+        use crate::{StopReason, Unstoppable};
+        let stop = WithTimeout::from_str_duration(Unstoppable, "0s").unwrap();
+        assert_eq!(stop.check(), Err(StopReason::TimedOut));
+        assert!(WithTimeout::from_str_duration(Unstoppable, "later").is_err());
+    }
+
+    #[cfg(feature = "config")]
+    #[test]
+    fn error_keeps_input() {
+        let err = parse_duration("soon").unwrap_err();
+        assert_eq!(err.input(), "soon");
+        assert!(err.to_string().contains("\"soon\""));
+    }
+}
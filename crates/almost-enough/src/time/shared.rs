@@ -0,0 +1,369 @@
+//! One deadline shared by many stops.
+//!
+//! A service handler typically wraps dozens of sub-operations with the same
+//! request deadline. With [`WithTimeout`](super::WithTimeout) each wrapper
+//! stores its own copy, so moving the deadline (a client lowers its budget,
+//! a downstream call returns a `Retry-After`) means rebuilding every
+//! wrapper. A [`SharedDeadlineHandle`] is one reference-counted deadline;
+//! every [`WithSharedDeadline`] created from it reads the same value, so
+//! [`remaining()`](SharedDeadlineHandle::remaining) agrees everywhere and
+//! [`tighten()`](SharedDeadlineHandle::tighten) moves all of them at once.
+//!
+//! As with `WithTimeout`, deadlines only ever get earlier.
+//!
+//! # Example
+//!
+//! ```rust
+//! use almost_enough::time::SharedDeadlineHandle;
+//! use almost_enough::{Stop, StopSource, TimeoutExt};
+//! use std::time::Duration;
+//!
+//! let source = StopSource::new();
+//! let deadline = SharedDeadlineHandle::after(Duration::from_secs(30));
+//!
+//! let parse = source.as_ref().with_shared_deadline(&deadline);
+//! let render = source.as_ref().with_shared_deadline(&deadline);
+//! assert!(!parse.should_stop());
+//!
+//! // The client gave up early: every wrapper sees the new deadline
+//! deadline.tighten_after(Duration::ZERO);
+//! assert!(parse.should_stop());
+//! assert!(render.should_stop());
+//! ```
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use super::CheckAt;
+use crate::{Behavior, Stop, StopReason};
+
+/// The shared state: the deadline as nanoseconds after `epoch`.
+struct Deadline {
+    epoch: Instant,
+    nanos: AtomicU64,
+}
+
+impl Deadline {
+    /// Nanoseconds after `epoch`, clamped to the representable range.
+    /// Instants before the epoch map to 0, i.e. already expired.
+    fn offset(&self, deadline: Instant) -> u64 {
+        let nanos = deadline.saturating_duration_since(self.epoch).as_nanos();
+        nanos.min(u64::MAX as u128) as u64
+    }
+}
+
+/// A reference-counted deadline that many stops can share.
+///
+/// Cloning is cheap and yields a handle to the same deadline. Reading is a
+/// single atomic load plus a clock read; tightening is lock-free.
+///
+/// # Example
+///
+/// ```rust
+/// use almost_enough::time::SharedDeadlineHandle;
+/// use std::time::Duration;
+///
+/// let deadline = SharedDeadlineHandle::after(Duration::from_secs(60));
+/// let view = deadline.clone();
+///
+/// deadline.tighten_after(Duration::from_secs(5));
+/// assert!(view.remaining() <= Duration::from_secs(5));
+///
+/// // Later deadlines are ignored
+/// deadline.tighten_after(Duration::from_secs(600));
+/// assert!(view.remaining() <= Duration::from_secs(5));
+/// ```
+#[derive(Clone)]
+pub struct SharedDeadlineHandle {
+    inner: Arc<Deadline>,
+}
+
+impl SharedDeadlineHandle {
+    /// Create a shared deadline at `deadline`.
+    pub fn new(deadline: Instant) -> Self {
+        let inner = Deadline {
+            epoch: Instant::now(),
+            nanos: AtomicU64::new(0),
+        };
+        inner.nanos.store(inner.offset(deadline), Ordering::Relaxed);
+        Self {
+            inner: Arc::new(inner),
+        }
+    }
+
+    /// Create a shared deadline `duration` from now.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the deadline is not representable, like
+    /// [`WithTimeout::new()`](super::WithTimeout::new).
+    #[inline]
+    pub fn after(duration: Duration) -> Self {
+        Self::new(Instant::now() + duration)
+    }
+
+    /// Move the deadline to `deadline` if that is earlier.
+    ///
+    /// Every stop sharing this handle observes the change on its next
+    /// check.
+    #[inline]
+    pub fn tighten(&self, deadline: Instant) {
+        let nanos = self.inner.offset(deadline);
+        self.inner.nanos.fetch_min(nanos, Ordering::Relaxed);
+    }
+
+    /// Move the deadline to `duration` from now if that is earlier.
+    #[inline]
+    pub fn tighten_after(&self, duration: Duration) {
+        if let Some(deadline) = Instant::now().checked_add(duration) {
+            self.tighten(deadline);
+        }
+    }
+
+    /// The current deadline.
+    #[inline]
+    pub fn deadline(&self) -> Instant {
+        self.inner.epoch + Duration::from_nanos(self.inner.nanos.load(Ordering::Relaxed))
+    }
+
+    /// Time left until the deadline, or `Duration::ZERO` if it has passed.
+    #[inline]
+    pub fn remaining(&self) -> Duration {
+        self.deadline().saturating_duration_since(Instant::now())
+    }
+
+    /// Whether the deadline has passed.
+    #[inline]
+    pub fn is_expired(&self) -> bool {
+        let elapsed = self.inner.epoch.elapsed().as_nanos();
+        elapsed >= u128::from(self.inner.nanos.load(Ordering::Relaxed))
+    }
+
+    /// Whether the deadline is at or before `now`.
+    #[inline]
+    pub fn is_expired_at(&self, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.inner.epoch).as_nanos();
+        elapsed >= u128::from(self.inner.nanos.load(Ordering::Relaxed))
+    }
+
+    /// Whether `self` and `other` are handles to the same deadline.
+    #[inline]
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+}
+
+impl core::fmt::Debug for SharedDeadlineHandle {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("SharedDeadlineHandle")
+            .field("remaining", &self.remaining())
+            .finish()
+    }
+}
+
+/// A [`Stop`] wrapper whose deadline is a [`SharedDeadlineHandle`].
+///
+/// Behaves like [`WithTimeout`](super::WithTimeout) — returns
+/// [`StopReason::TimedOut`] once the deadline passes, propagates the inner
+/// stop's reason otherwise, and resolves ties with its [`Behavior`] — but
+/// the deadline can be moved after construction, for every wrapper sharing
+/// the handle at once.
+///
+/// Created by [`WithSharedDeadline::new()`] or
+/// [`TimeoutExt::with_shared_deadline()`](crate::TimeoutExt::with_shared_deadline).
+#[derive(Debug, Clone)]
+pub struct WithSharedDeadline<T> {
+    inner: T,
+    deadline: SharedDeadlineHandle,
+    behavior: Behavior,
+}
+
+impl<T: Stop> WithSharedDeadline<T> {
+    /// Wrap `inner` with the deadline behind `deadline`.
+    #[inline]
+    pub fn new(inner: T, deadline: SharedDeadlineHandle) -> Self {
+        Self {
+            inner,
+            deadline,
+            behavior: Behavior::V1,
+        }
+    }
+
+    /// Set the [`Behavior`] used to resolve ties with the inner stop.
+    #[inline]
+    pub fn with_behavior(mut self, behavior: Behavior) -> Self {
+        self.behavior = behavior;
+        self
+    }
+
+    /// Get the configured [`Behavior`].
+    #[inline]
+    pub fn behavior(&self) -> Behavior {
+        self.behavior
+    }
+
+    /// The shared deadline handle, e.g. to tighten it or wrap another stop.
+    #[inline]
+    pub fn handle(&self) -> &SharedDeadlineHandle {
+        &self.deadline
+    }
+
+    /// Get the current deadline.
+    #[inline]
+    pub fn deadline(&self) -> Instant {
+        self.deadline.deadline()
+    }
+
+    /// Get the remaining time until the deadline.
+    ///
+    /// Returns `Duration::ZERO` if the deadline has passed.
+    #[inline]
+    pub fn remaining(&self) -> Duration {
+        self.deadline.remaining()
+    }
+
+    /// Get a reference to the inner stop.
+    #[inline]
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Unwrap and return the inner stop.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T: Stop> Stop for WithSharedDeadline<T> {
+    #[inline]
+    fn check(&self) -> Result<(), StopReason> {
+        if let Err(reason) = self.inner.check() {
+            return Err(self
+                .behavior
+                .resolve_tie(reason, || self.deadline.is_expired()));
+        }
+        if self.deadline.is_expired() {
+            Err(StopReason::TimedOut)
+        } else {
+            Ok(())
+        }
+    }
+
+    #[inline]
+    fn should_stop(&self) -> bool {
+        self.inner.should_stop() || self.deadline.is_expired()
+    }
+}
+
+impl<T: CheckAt> CheckAt for WithSharedDeadline<T> {
+    #[inline]
+    fn check_at(&self, now: Instant) -> Result<(), StopReason> {
+        if let Err(reason) = self.inner.check_at(now) {
+            return Err(self
+                .behavior
+                .resolve_tie(reason, || self.deadline.is_expired_at(now)));
+        }
+        if self.deadline.is_expired_at(now) {
+            Err(StopReason::TimedOut)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{StopSource, TieBreak, TimeoutExt, Unstoppable};
+
+    const LONG: Duration = Duration::from_secs(60);
+
+    #[test]
+    fn wrappers_share_one_deadline() {
+        let deadline = SharedDeadlineHandle::after(LONG);
+        let stops: Vec<_> = (0..8)
+            .map(|_| Unstoppable.with_shared_deadline(&deadline))
+            .collect();
+        assert!(stops.iter().all(|s| s.check().is_ok()));
+        assert!(stops.iter().all(|s| s.deadline() == deadline.deadline()));
+
+        deadline.tighten(Instant::now());
+        assert!(stops.iter().all(|s| s.check() == Err(StopReason::TimedOut)));
+        assert!(stops.iter().all(|s| s.remaining().is_zero()));
+        assert!(stops[0].handle().ptr_eq(&deadline));
+    }
+
+    #[test]
+    fn only_tightens() {
+        let deadline = SharedDeadlineHandle::after(Duration::from_secs(10));
+        let before = deadline.deadline();
+        deadline.tighten_after(LONG);
+        deadline.tighten(before + LONG);
+        deadline.tighten_after(Duration::MAX);
+        assert_eq!(deadline.deadline(), before);
+        assert!(!deadline.is_expired());
+
+        deadline.tighten_after(Duration::from_secs(1));
+        assert!(deadline.deadline() < before);
+    }
+
+    #[test]
+    fn check_at_uses_given_timestamp() {
+        let deadline = SharedDeadlineHandle::after(LONG);
+        let stop = Unstoppable.with_shared_deadline(&deadline);
+        let now = Instant::now();
+        assert!(stop.check_at(now).is_ok());
+        assert!(!deadline.is_expired_at(now));
+        assert_eq!(stop.check_at(now + LONG), Err(StopReason::TimedOut));
+        assert!(stop.should_stop_at(deadline.deadline()));
+    }
+
+    #[test]
+    fn past_deadline_is_expired() {
+        let past = Instant::now();
+        std::thread::sleep(Duration::from_millis(1));
+        let deadline = SharedDeadlineHandle::new(past);
+        assert!(deadline.is_expired());
+        assert_eq!(deadline.remaining(), Duration::ZERO);
+    }
+
+    #[test]
+    fn inner_reason_and_tie_break() {
+        let source = StopSource::cancelled();
+        let deadline = SharedDeadlineHandle::after(LONG);
+        let stop = source.as_ref().with_shared_deadline(&deadline);
+        assert_eq!(stop.check(), Err(StopReason::Cancelled));
+        assert!(stop.should_stop());
+
+        let stop = stop.with_behavior(Behavior::V1.with_tie_break(TieBreak::Deadline));
+        assert_eq!(stop.check(), Err(StopReason::Cancelled));
+        deadline.tighten_after(Duration::ZERO);
+        assert_eq!(stop.check(), Err(StopReason::TimedOut));
+        assert_eq!(stop.behavior().tie_break(), TieBreak::Deadline);
+        assert!(stop.into_inner().should_stop());
+    }
+
+    #[test]
+    fn concurrent_tightening_keeps_earliest() {
+        let deadline = SharedDeadlineHandle::after(LONG);
+        let base = Instant::now() + Duration::from_secs(30);
+        std::thread::scope(|s| {
+            for i in 0..8u64 {
+                let deadline = deadline.clone();
+                s.spawn(move || deadline.tighten(base + Duration::from_secs(i)));
+            }
+        });
+        let got = deadline.deadline();
+        assert!(got <= base && base - got < Duration::from_micros(1));
+        assert!(format!("{deadline:?}").contains("remaining"));
+    }
+
+    #[test]
+    fn is_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<SharedDeadlineHandle>();
+        assert_send_sync::<WithSharedDeadline<StopSource>>();
+    }
+}
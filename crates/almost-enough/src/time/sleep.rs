@@ -0,0 +1,422 @@
+//! Sleeping that wakes up on cancellation.
+//!
+//! Background maintenance threads (compaction, cache expiry, metrics
+//! flushes) sleep for long stretches between rounds. A plain
+//! `thread::sleep` holds up shutdown for the rest of the interval, and
+//! sleeping in small chunks between checks burns wakeups.
+//! [`sleep_until_cancelled()`] returns as soon as the stop fires, and
+//! [`every()`] turns that into a ticking loop. [`StopTicker`] is the same
+//! schedule as an owned value, for loops that keep it in a struct or want
+//! the stop reason.
+//!
+//! A [`Stopper`] is waited on directly through
+//! [`Stopper::wait_timeout()`], so the sleep ends the moment `cancel()` is
+//! called. Any other stop is re-checked every 10ms.
+//!
+//! # Example
+//!
+//! ```rust
+//! use almost_enough::Stopper;
+//! use almost_enough::time::every;
+//! use std::time::Duration;
+//!
+//! let stop = Stopper::new();
+//! let canceller = stop.clone();
+//! std::thread::spawn(move || {
+//!     std::thread::sleep(Duration::from_millis(30));
+//!     canceller.cancel();
+//! });
+//!
+//! let mut rounds = 0;
+//! for _tick in every(&stop, Duration::from_secs(60)) {
+//!     rounds += 1; // expire cache entries, ...
+//! }
+//! assert_eq!(rounds, 1); // the first tick is immediate; cancel ends the wait
+//! ```
+
+use core::any::Any;
+use std::time::{Duration, Instant};
+
+use crate::{Stop, StopReason, Stopper};
+
+/// How often a stop without wakeup support is re-checked while sleeping.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// The result of [`sleep_until_cancelled()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SleepOutcome {
+    /// The full duration elapsed.
+    Completed,
+    /// The stop fired first.
+    Stopped(StopReason),
+}
+
+impl SleepOutcome {
+    /// Whether the stop fired before the duration elapsed.
+    #[inline]
+    pub fn is_stopped(self) -> bool {
+        matches!(self, Self::Stopped(_))
+    }
+
+    /// `Ok(())` if completed, or the stop reason.
+    #[inline]
+    pub fn into_result(self) -> Result<(), StopReason> {
+        match self {
+            Self::Completed => Ok(()),
+            Self::Stopped(reason) => Err(reason),
+        }
+    }
+}
+
+/// Sleep for `duration`, returning early if `stop` fires.
+///
+/// The stop is checked first, so an already-stopped stop returns without
+/// sleeping.
+///
+/// # Example
+///
+/// ```rust
+/// use almost_enough::time::{SleepOutcome, sleep_until_cancelled};
+/// use almost_enough::{StopReason, Stopper};
+/// use std::time::Duration;
+///
+/// let stop = Stopper::new();
+/// let outcome = sleep_until_cancelled(&stop, Duration::from_millis(1));
+/// assert_eq!(outcome, SleepOutcome::Completed);
+///
+/// stop.cancel();
+/// let outcome = sleep_until_cancelled(&stop, Duration::from_secs(3600));
+/// assert_eq!(outcome, SleepOutcome::Stopped(StopReason::Cancelled));
+/// ```
+pub fn sleep_until_cancelled<S: Stop + 'static>(stop: &S, duration: Duration) -> SleepOutcome {
+    match Instant::now().checked_add(duration) {
+        Some(deadline) => sleep_until(stop, deadline),
+        // Too far out to represent: sleep until stopped
+        None => loop {
+            if let Err(reason) = stop.check() {
+                return SleepOutcome::Stopped(reason);
+            }
+            nap(stop, Duration::MAX);
+        },
+    }
+}
+
+pub(super) fn sleep_until<S: Stop + 'static>(stop: &S, deadline: Instant) -> SleepOutcome {
+    loop {
+        if let Err(reason) = stop.check() {
+            return SleepOutcome::Stopped(reason);
+        }
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return SleepOutcome::Completed;
+        }
+        nap(stop, remaining);
+    }
+}
+
+/// Sleep for at most `limit`, waking early if `stop` can signal it.
+fn nap<S: Stop + 'static>(stop: &S, limit: Duration) {
+    if let Some(stopper) = (stop as &dyn Any).downcast_ref::<Stopper>() {
+        stopper.wait_timeout(limit);
+    } else if stop.may_stop() {
+        std::thread::sleep(limit.min(POLL_INTERVAL));
+    } else {
+        std::thread::sleep(limit);
+    }
+}
+
+/// Iterate once per `interval` until `stop` fires.
+///
+/// The first tick is yielded immediately; later ticks follow a fixed
+/// schedule from the first (`start + n * interval`), skipping any ticks
+/// missed while the loop body ran long. Each item is the scheduled
+/// `Instant` of that tick. Iteration ends as soon as the stop fires, even
+/// in the middle of a wait.
+pub fn every<S: Stop + 'static>(stop: &S, interval: Duration) -> Every<'_, S> {
+    Every {
+        stop,
+        interval,
+        next: None,
+    }
+}
+
+/// Iterator returned by [`every()`].
+#[must_use = "iterators are lazy and do nothing unless consumed"]
+pub struct Every<'a, S> {
+    stop: &'a S,
+    interval: Duration,
+    next: Option<Instant>,
+}
+
+impl<S> Every<'_, S> {
+    /// The configured interval.
+    #[inline]
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+}
+
+impl<S: Stop + 'static> Iterator for Every<'_, S> {
+    type Item = Instant;
+
+    fn next(&mut self) -> Option<Instant> {
+        let tick = match self.next {
+            None => Instant::now(),
+            Some(at) => {
+                if sleep_until(self.stop, at).is_stopped() {
+                    return None;
+                }
+                at
+            }
+        };
+        if self.stop.should_stop() {
+            return None;
+        }
+        // Skip ticks missed while the caller was busy
+        let now = Instant::now();
+        self.next = Some(next_after(tick, self.interval, now).unwrap_or(now));
+        Some(tick)
+    }
+}
+
+/// The first scheduled tick after `now`, on the grid `tick + n * period`,
+/// or `None` if it is too far out to represent.
+fn next_after(tick: Instant, period: Duration, now: Instant) -> Option<Instant> {
+    let mut next = tick.checked_add(period);
+    while let Some(at) = next.filter(|at| *at <= now && !period.is_zero()) {
+        next = at.checked_add(period);
+    }
+    next
+}
+
+/// A drift-free periodic timer that stops waiting when a stop fires.
+///
+/// Ticks fall on a fixed grid, `start + n * period`, so time spent in the
+/// loop body does not push later ticks back. A tick missed because the
+/// body ran past it is delivered once, late, and the grid resumes from
+/// there rather than firing a burst of catch-up ticks.
+///
+/// Unlike [`every()`], the first tick is one period after creation, and
+/// the ticker owns its stop.
+///
+/// # Example
+///
+/// ```rust
+/// use almost_enough::time::StopTicker;
+/// use almost_enough::{StopReason, Stopper};
+/// use std::time::Duration;
+///
+/// let stop = Stopper::new();
+/// let mut ticker = StopTicker::new(stop.clone(), Duration::from_millis(1));
+///
+/// // A maintenance loop: evict, flush metrics, ...
+/// let mut rounds = 0;
+/// let reason = loop {
+///     if let Err(reason) = ticker.wait_tick() {
+///         break reason;
+///     }
+///     rounds += 1;
+///     if rounds == 3 {
+///         stop.cancel();
+///     }
+/// };
+/// assert_eq!(reason, StopReason::Cancelled);
+/// ```
+#[derive(Debug, Clone)]
+pub struct StopTicker<S> {
+    stop: S,
+    period: Duration,
+    /// `None` once the schedule runs past what `Instant` can represent.
+    next: Option<Instant>,
+}
+
+impl<S: Stop + 'static> StopTicker<S> {
+    /// Tick every `period`, starting one period from now.
+    pub fn new(stop: S, period: Duration) -> Self {
+        Self {
+            next: Instant::now().checked_add(period),
+            stop,
+            period,
+        }
+    }
+
+    /// Block until the next tick, returning its scheduled time.
+    ///
+    /// Returns the stop reason as soon as the stop fires, including while
+    /// waiting, and without waiting if it already has.
+    pub fn wait_tick(&mut self) -> Result<Instant, StopReason> {
+        let Some(tick) = self.next else {
+            return Err(sleep_until_cancelled(&self.stop, Duration::MAX)
+                .into_result()
+                .expect_err("an unbounded sleep only ends when stopped"));
+        };
+        sleep_until(&self.stop, tick).into_result()?;
+        self.next = next_after(tick, self.period, Instant::now());
+        Ok(tick)
+    }
+
+    /// The scheduled time of the next tick, or `None` if the period is too
+    /// long for it to be representable (the ticker then only waits for
+    /// the stop).
+    #[inline]
+    pub fn next_tick(&self) -> Option<Instant> {
+        self.next
+    }
+
+    /// The configured period.
+    #[inline]
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+
+    /// Get a reference to the stop.
+    #[inline]
+    pub fn stop(&self) -> &S {
+        &self.stop
+    }
+
+    /// Unwrap and return the stop.
+    #[inline]
+    pub fn into_inner(self) -> S {
+        self.stop
+    }
+}
+
+impl<S: core::fmt::Debug> core::fmt::Debug for Every<'_, S> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Every")
+            .field("stop", self.stop)
+            .field("interval", &self.interval)
+            .field("next", &self.next)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{StopSource, Unstoppable};
+    use std::sync::Arc;
+    use std::thread;
+
+    const LONG: Duration = Duration::from_secs(60);
+
+    #[test]
+    fn completes_without_cancel() {
+        let start = Instant::now();
+        let outcome = sleep_until_cancelled(&Unstoppable, Duration::from_millis(5));
+        assert_eq!(outcome, SleepOutcome::Completed);
+        assert!(outcome.into_result().is_ok());
+        assert!(start.elapsed() >= Duration::from_millis(5));
+    }
+
+    #[test]
+    fn stopper_wakes_promptly() {
+        let stop = Stopper::new();
+        let canceller = stop.clone();
+        let handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(10));
+            canceller.cancel();
+        });
+        let start = Instant::now();
+        let outcome = sleep_until_cancelled(&stop, LONG);
+        assert_eq!(outcome, SleepOutcome::Stopped(StopReason::Cancelled));
+        assert!(start.elapsed() < Duration::from_secs(5));
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn other_stops_are_polled() {
+        let source = Arc::new(StopSource::new());
+        let handle = {
+            let source = Arc::clone(&source);
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(10));
+                source.cancel();
+            })
+        };
+        let outcome = sleep_until_cancelled(&source, LONG);
+        assert!(outcome.is_stopped());
+        assert_eq!(outcome.into_result(), Err(StopReason::Cancelled));
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn already_stopped_returns_immediately() {
+        let outcome = sleep_until_cancelled(&Stopper::cancelled(), Duration::MAX);
+        assert!(outcome.is_stopped());
+    }
+
+    #[test]
+    fn every_ticks_until_stopped() {
+        let stop = Stopper::new();
+        let mut ticks = Vec::new();
+        for tick in every(&stop, Duration::from_millis(2)) {
+            ticks.push(tick);
+            if ticks.len() == 3 {
+                stop.cancel();
+            }
+        }
+        assert_eq!(ticks.len(), 3);
+        assert!(
+            ticks
+                .windows(2)
+                .all(|w| w[1] - w[0] >= Duration::from_millis(2))
+        );
+    }
+
+    #[test]
+    fn every_skips_missed_ticks() {
+        let stop = Stopper::new();
+        let mut ticker = every(&stop, Duration::from_millis(2));
+        assert_eq!(ticker.interval(), Duration::from_millis(2));
+        let first = ticker.next().unwrap();
+        thread::sleep(Duration::from_millis(11));
+        let second = ticker.next().unwrap();
+        // The overdue tick is delivered once, then the schedule resumes
+        // from it rather than bursting through the missed ones
+        assert_eq!((second - first).as_nanos() % 2_000_000, 0);
+        assert!(format!("{ticker:?}").contains("Every"));
+    }
+
+    #[test]
+    fn ticker_keeps_its_grid() {
+        let period = Duration::from_millis(2);
+        let mut ticker = StopTicker::new(Unstoppable, period);
+        let first = ticker.next_tick().unwrap();
+        assert_eq!(ticker.wait_tick(), Ok(first));
+        assert!(Instant::now() >= first);
+
+        // A slow round: the overdue tick comes once, still on the grid
+        thread::sleep(Duration::from_millis(7));
+        let late = ticker.wait_tick().unwrap();
+        assert_eq!((late - first).as_nanos() % period.as_nanos(), 0);
+        assert!(ticker.next_tick().unwrap() > late);
+        assert_eq!(ticker.period(), period);
+    }
+
+    #[test]
+    fn ticker_returns_on_cancel() {
+        let stop = Stopper::new();
+        let mut ticker = StopTicker::new(stop.clone(), LONG);
+        let handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(10));
+            stop.cancel();
+        });
+        let start = Instant::now();
+        assert_eq!(ticker.wait_tick(), Err(StopReason::Cancelled));
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert!(ticker.stop().is_cancelled());
+        assert_eq!(ticker.wait_tick(), Err(StopReason::Cancelled));
+        handle.join().unwrap();
+
+        let mut unbounded = StopTicker::new(Stopper::cancelled(), Duration::MAX);
+        assert_eq!(unbounded.next_tick(), None);
+        assert_eq!(unbounded.wait_tick(), Err(StopReason::Cancelled));
+    }
+
+    #[test]
+    fn every_on_stopped_yields_nothing() {
+        assert_eq!(every(&Stopper::cancelled(), LONG).count(), 0);
+    }
+}
@@ -0,0 +1,355 @@
+//! Wall-clock deadlines that survive suspend and clock adjustments.
+//!
+//! [`WithTimeout`](super::WithTimeout) measures with [`Instant`], a monotonic
+//! clock. That is right for "stop after 30 seconds", but wrong for "stop at
+//! 02:00, when the maintenance window starts": the monotonic clock may not
+//! advance while the machine is suspended, and it ignores NTP or manual
+//! wall-clock adjustments.
+//!
+//! [`WithSystemDeadline`] targets a [`SystemTime`]. It keeps the fast path
+//! monotonic — comparing `Instant::now()` against a derived deadline — and
+//! periodically re-derives that deadline from the wall clock, so suspend,
+//! resume and clock jumps are picked up within one
+//! [resync interval](WithSystemDeadline::with_resync_interval).
+//!
+//! # Example
+//!
+//! ```rust
+//! use almost_enough::{StopSource, Stop, TimeoutExt};
+//! use std::time::{Duration, SystemTime};
+//!
+//! let source = StopSource::new();
+//! let window_start = SystemTime::now() + Duration::from_secs(3600);
+//! let stop = source.as_ref().cancel_at_system_time(window_start);
+//!
+//! assert!(!stop.should_stop());
+//! ```
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering::Relaxed};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::{Behavior, Stop, StopReason};
+
+/// Default interval between wall-clock re-reads: 1 second.
+const DEFAULT_RESYNC_NANOS: u64 = 1_000_000_000;
+
+/// Convert a Duration to nanoseconds as u64, clamping at u64::MAX.
+#[inline]
+fn duration_to_nanos(d: Duration) -> u64 {
+    d.as_nanos().min(u64::MAX as u128) as u64
+}
+
+/// A [`Stop`] wrapper that trips at a wall-clock time.
+///
+/// Returns [`StopReason::TimedOut`] once [`SystemTime::now()`] reaches the
+/// target, or propagates the inner stop's reason if it stops first. Once
+/// the target has been observed as reached, the stop stays tripped even if
+/// the wall clock is later set back.
+///
+/// # Clock Changes
+///
+/// Between resyncs the deadline is tracked with [`Instant`]. The wall clock
+/// is re-read:
+///
+/// - whenever the derived monotonic deadline is reached (so a clock set
+///   *back* extends the wait instead of stopping early), and
+/// - at least once per resync interval of monotonic time (so a clock set
+///   *forward*, or time spent suspended, is noticed within one interval of
+///   resuming).
+///
+/// # Example
+///
+/// ```rust
+/// use almost_enough::{Stopper, Stop, StopReason, TimeoutExt};
+/// use std::time::{Duration, SystemTime};
+///
+/// let stop = Stopper::new()
+///     .cancel_at_system_time(SystemTime::now() - Duration::from_secs(1));
+///
+/// assert_eq!(stop.check(), Err(StopReason::TimedOut));
+/// ```
+pub struct WithSystemDeadline<T> {
+    inner: T,
+    target: SystemTime,
+    base: Instant,
+    /// Derived monotonic deadline, nanos since `base`.
+    deadline_nanos: AtomicU64,
+    /// Next forced wall-clock re-read, nanos since `base`.
+    next_sync_nanos: AtomicU64,
+    resync_nanos: u64,
+    expired: AtomicBool,
+    behavior: Behavior,
+}
+
+impl<T: Stop> WithSystemDeadline<T> {
+    /// Create a wrapper that stops at wall-clock time `target`.
+    pub fn new(inner: T, target: SystemTime) -> Self {
+        let this = Self {
+            inner,
+            target,
+            base: Instant::now(),
+            deadline_nanos: AtomicU64::new(0),
+            next_sync_nanos: AtomicU64::new(0),
+            resync_nanos: DEFAULT_RESYNC_NANOS,
+            expired: AtomicBool::new(false),
+            behavior: Behavior::V1,
+        };
+        this.resync(0);
+        this
+    }
+
+    /// Set how often the wall clock is re-read (default: 1 second).
+    ///
+    /// This bounds how late the stop can trip after a suspend/resume or a
+    /// forward clock jump. Shorter intervals cost more `SystemTime::now()`
+    /// calls on the check path.
+    pub fn with_resync_interval(mut self, interval: Duration) -> Self {
+        self.resync_nanos = duration_to_nanos(interval);
+        let now = self.now_nanos();
+        self.resync(now);
+        self
+    }
+
+    /// Set the [`Behavior`] used to resolve ties with the inner stop.
+    pub fn with_behavior(mut self, behavior: Behavior) -> Self {
+        self.behavior = behavior;
+        self
+    }
+
+    /// Get the configured [`Behavior`].
+    #[inline]
+    pub fn behavior(&self) -> Behavior {
+        self.behavior
+    }
+
+    /// Get the wall-clock target.
+    #[inline]
+    pub fn target(&self) -> SystemTime {
+        self.target
+    }
+
+    /// Get the wall-clock time remaining until the target.
+    ///
+    /// Returns `Duration::ZERO` if the target has passed.
+    #[inline]
+    pub fn remaining(&self) -> Duration {
+        if self.expired.load(Relaxed) {
+            return Duration::ZERO;
+        }
+        self.target
+            .duration_since(SystemTime::now())
+            .unwrap_or(Duration::ZERO)
+    }
+
+    /// Get a reference to the inner stop.
+    #[inline]
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Unwrap and return the inner stop.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    #[inline]
+    fn now_nanos(&self) -> u64 {
+        duration_to_nanos(self.base.elapsed())
+    }
+
+    /// Re-derive the monotonic deadline from the wall clock.
+    ///
+    /// Returns `true` if the target has been reached.
+    #[cold]
+    fn resync(&self, now_nanos: u64) -> bool {
+        match self.target.duration_since(SystemTime::now()) {
+            Ok(left) if !left.is_zero() => {
+                self.deadline_nanos
+                    .store(now_nanos.saturating_add(duration_to_nanos(left)), Relaxed);
+                self.next_sync_nanos
+                    .store(now_nanos.saturating_add(self.resync_nanos), Relaxed);
+                false
+            }
+            _ => {
+                self.expired.store(true, Relaxed);
+                true
+            }
+        }
+    }
+
+    #[inline]
+    fn wall_expired(&self) -> bool {
+        if self.expired.load(Relaxed) {
+            return true;
+        }
+        let now = self.now_nanos();
+        if now < self.deadline_nanos.load(Relaxed) && now < self.next_sync_nanos.load(Relaxed) {
+            return false;
+        }
+        self.resync(now)
+    }
+}
+
+impl<T: Stop> Stop for WithSystemDeadline<T> {
+    #[inline]
+    fn check(&self) -> Result<(), StopReason> {
+        if let Err(reason) = self.inner.check() {
+            return Err(self.behavior.resolve_tie(reason, || self.wall_expired()));
+        }
+        if self.wall_expired() {
+            Err(StopReason::TimedOut)
+        } else {
+            Ok(())
+        }
+    }
+
+    #[inline]
+    fn should_stop(&self) -> bool {
+        self.inner.should_stop() || self.wall_expired()
+    }
+}
+
+impl<T: Clone> Clone for WithSystemDeadline<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            target: self.target,
+            base: self.base,
+            deadline_nanos: AtomicU64::new(self.deadline_nanos.load(Relaxed)),
+            next_sync_nanos: AtomicU64::new(self.next_sync_nanos.load(Relaxed)),
+            resync_nanos: self.resync_nanos,
+            expired: AtomicBool::new(self.expired.load(Relaxed)),
+            behavior: self.behavior,
+        }
+    }
+}
+
+impl<T: core::fmt::Debug> core::fmt::Debug for WithSystemDeadline<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("WithSystemDeadline")
+            .field("inner", &self.inner)
+            .field("target", &self.target)
+            .field("resync_interval", &Duration::from_nanos(self.resync_nanos))
+            .field("expired", &self.expired.load(Relaxed))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{StopSource, TimeoutExt};
+
+    #[test]
+    fn past_target_stops_immediately() {
+        let source = StopSource::new();
+        let stop = source
+            .as_ref()
+            .cancel_at_system_time(SystemTime::now() - Duration::from_secs(5));
+        assert!(stop.should_stop());
+        assert_eq!(stop.check(), Err(StopReason::TimedOut));
+        assert_eq!(stop.remaining(), Duration::ZERO);
+    }
+
+    #[test]
+    fn tie_break_selects_reported_reason() {
+        use crate::TieBreak;
+
+        let source = StopSource::new();
+        source.cancel();
+        let past = SystemTime::now() - Duration::from_secs(5);
+        let stop = source.as_ref().cancel_at_system_time(past);
+        assert_eq!(stop.check(), Err(StopReason::Cancelled));
+
+        let stop = stop.with_behavior(Behavior::V1.with_tie_break(TieBreak::Deadline));
+        assert_eq!(stop.check(), Err(StopReason::TimedOut));
+    }
+
+    #[test]
+    fn future_target_trips_after_it_passes() {
+        let source = StopSource::new();
+        let stop = source
+            .as_ref()
+            .cancel_at_system_time(SystemTime::now() + Duration::from_millis(50));
+
+        assert!(!stop.should_stop());
+        assert!(stop.remaining() > Duration::ZERO);
+
+        std::thread::sleep(Duration::from_millis(80));
+
+        assert!(stop.should_stop());
+        assert_eq!(stop.check(), Err(StopReason::TimedOut));
+    }
+
+    #[test]
+    fn cancel_before_target() {
+        let source = StopSource::new();
+        let stop = source
+            .as_ref()
+            .cancel_at_system_time(SystemTime::now() + Duration::from_secs(3600));
+        source.cancel();
+        assert_eq!(stop.check(), Err(StopReason::Cancelled));
+    }
+
+    #[test]
+    fn resync_interval_forces_wall_clock_reads() {
+        let source = StopSource::new();
+        let stop = source
+            .as_ref()
+            .cancel_at_system_time(SystemTime::now() + Duration::from_secs(3600))
+            .with_resync_interval(Duration::from_millis(1));
+        let first_sync = stop.next_sync_nanos.load(Relaxed);
+
+        std::thread::sleep(Duration::from_millis(5));
+        assert!(!stop.should_stop());
+
+        // Crossing the resync point re-derived the deadline
+        assert!(stop.next_sync_nanos.load(Relaxed) > first_sync);
+    }
+
+    #[test]
+    fn stale_monotonic_deadline_is_rechecked() {
+        // Simulate a clock set back: the derived monotonic deadline has been
+        // reached, but the wall clock says the target is still ahead.
+        let source = StopSource::new();
+        let stop = source
+            .as_ref()
+            .cancel_at_system_time(SystemTime::now() + Duration::from_secs(3600));
+        stop.deadline_nanos.store(0, Relaxed);
+
+        assert!(!stop.should_stop());
+        assert!(stop.deadline_nanos.load(Relaxed) > 0);
+    }
+
+    #[test]
+    fn expiry_latches() {
+        let source = StopSource::new();
+        let stop = source
+            .as_ref()
+            .cancel_at_system_time(SystemTime::now() - Duration::from_secs(1));
+        assert!(stop.should_stop());
+        // Even if re-derived state says otherwise, the latch holds
+        stop.deadline_nanos.store(u64::MAX, Relaxed);
+        stop.next_sync_nanos.store(u64::MAX, Relaxed);
+        assert!(stop.should_stop());
+    }
+
+    #[test]
+    fn clone_and_debug() {
+        let target = SystemTime::now() + Duration::from_secs(60);
+        let stop = crate::Unstoppable.cancel_at_system_time(target);
+        let cloned = stop.clone();
+        assert_eq!(cloned.target(), target);
+        assert!(!cloned.should_stop());
+
+        let debug = format!("{stop:?}");
+        assert!(debug.contains("WithSystemDeadline"));
+    }
+
+    #[test]
+    fn with_system_deadline_is_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<WithSystemDeadline<crate::StopRef<'_>>>();
+    }
+}
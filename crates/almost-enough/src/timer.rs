@@ -0,0 +1,254 @@
+//! Active deadlines driven by one shared timer thread.
+//!
+//! [`WithTimeout`](crate::WithTimeout) is passive: it compares the clock on
+//! every check, and nothing happens at the deadline itself, so
+//! [`Stopper::wait()`] and [`Stopper::wait_cancelled()`] never notice it.
+//! [`Stopper::cancel_after()`] instead cancels the stopper when the
+//! deadline passes, waking anything blocked on it.
+//!
+//! All deadlines share a single background thread, started on first use and
+//! kept for the life of the process, which sleeps until the earliest one.
+//! Scheduling is a heap push under a mutex, so a server can give every
+//! request its own deadline without a thread per request.
+//!
+//! Requires the `timer` feature.
+
+use alloc::collections::BinaryHeap;
+use alloc::sync::{Arc, Weak};
+use core::cmp::{Ordering, Reverse};
+use std::sync::{Condvar, Mutex, OnceLock, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::StopReason;
+use crate::stopper::{Stopper, StopperInner};
+
+/// A scheduled cancellation.
+///
+/// Holds the stopper weakly: a stopper dropped before its deadline is not
+/// kept alive, and its entry is discarded when the deadline arrives.
+struct Entry {
+    deadline: Instant,
+    target: Weak<StopperInner>,
+}
+
+impl PartialEq for Entry {
+    fn eq(&self, other: &Self) -> bool {
+        self.deadline == other.deadline
+    }
+}
+
+impl Eq for Entry {}
+
+impl PartialOrd for Entry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Entry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.deadline.cmp(&other.deadline)
+    }
+}
+
+/// The shared deadline heap, earliest first.
+#[derive(Default)]
+struct Timer {
+    heap: Mutex<BinaryHeap<Reverse<Entry>>>,
+    cond: Condvar,
+}
+
+impl Timer {
+    /// The process-wide timer, starting its thread on first use.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the OS fails to create the thread, like [`thread::spawn`].
+    fn get() -> &'static Timer {
+        static TIMER: OnceLock<Timer> = OnceLock::new();
+        static THREAD: OnceLock<()> = OnceLock::new();
+        let timer = TIMER.get_or_init(Timer::default);
+        THREAD.get_or_init(|| {
+            thread::Builder::new()
+                .name("almost-enough-timer".into())
+                .spawn(move || timer.run())
+                .expect("failed to spawn the almost-enough timer thread");
+        });
+        timer
+    }
+
+    fn schedule(&self, deadline: Instant, target: &Arc<StopperInner>) {
+        let mut heap = self.heap.lock().unwrap_or_else(PoisonError::into_inner);
+        let earliest = heap
+            .peek()
+            .is_none_or(|Reverse(top)| deadline < top.deadline);
+        heap.push(Reverse(Entry {
+            deadline,
+            target: Arc::downgrade(target),
+        }));
+        drop(heap);
+        // Only a new earliest deadline shortens the thread's sleep
+        if earliest {
+            self.cond.notify_one();
+        }
+    }
+
+    fn run(&self) -> ! {
+        let mut due = alloc::vec::Vec::new();
+        loop {
+            let mut heap = self.heap.lock().unwrap_or_else(PoisonError::into_inner);
+            loop {
+                let now = Instant::now();
+                while heap.peek().is_some_and(|Reverse(top)| top.deadline <= now) {
+                    due.push(heap.pop().unwrap().0.target);
+                }
+                if !due.is_empty() {
+                    break;
+                }
+                heap = match heap.peek() {
+                    Some(Reverse(top)) => {
+                        let wait = top.deadline - now;
+                        self.cond
+                            .wait_timeout(heap, wait)
+                            .unwrap_or_else(PoisonError::into_inner)
+                            .0
+                    }
+                    None => self.cond.wait(heap).unwrap_or_else(PoisonError::into_inner),
+                };
+            }
+            drop(heap);
+            // Cancelled outside the lock: waking waiters may run wakers inline
+            for target in due.drain(..) {
+                if let Some(inner) = target.upgrade() {
+                    Stopper::from_inner(inner).cancel_with(StopReason::TimedOut);
+                }
+            }
+        }
+    }
+}
+
+impl Stopper {
+    /// Cancel this stopper with [`StopReason::TimedOut`] once `duration`
+    /// has passed.
+    ///
+    /// Unlike [`with_timeout()`](crate::TimeoutExt::with_timeout), the
+    /// deadline actively flips the flag: [`wait()`](Self::wait),
+    /// [`wait_cancelled()`](Self::wait_cancelled) and every clone observe
+    /// it without reading the clock. Deadlines are served by one shared
+    /// thread, so scheduling many is cheap. A zero `duration` cancels
+    /// immediately; one too large to represent never fires.
+    ///
+    /// Scheduling cannot be undone, and calling this again adds another
+    /// deadline rather than replacing the first. If the stopper is
+    /// cancelled first it keeps its reason, and if every clone is dropped
+    /// first nothing happens.
+    ///
+    /// Requires the `timer` feature.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the timer thread has not been started yet and the OS fails
+    /// to create it.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use almost_enough::{Stop, StopReason, Stopper};
+    /// use std::time::Duration;
+    ///
+    /// let stop = Stopper::new();
+    /// stop.cancel_after(Duration::from_millis(10));
+    ///
+    /// stop.wait();
+    /// assert_eq!(stop.check(), Err(StopReason::TimedOut));
+    /// ```
+    pub fn cancel_after(&self, duration: Duration) {
+        if self.is_cancelled() {
+            return;
+        }
+        if duration.is_zero() {
+            self.cancel_with(StopReason::TimedOut);
+            return;
+        }
+        if let Some(deadline) = Instant::now().checked_add(duration) {
+            Timer::get().schedule(deadline, &self.inner);
+        }
+    }
+
+    /// Create a stopper that is cancelled with [`StopReason::TimedOut`]
+    /// after `duration`.
+    ///
+    /// Shorthand for [`new()`](Self::new) followed by
+    /// [`cancel_after()`](Self::cancel_after), typically one per request.
+    ///
+    /// Requires the `timer` feature.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use almost_enough::Stopper;
+    /// use std::time::Duration;
+    ///
+    /// let request = Stopper::with_timeout_active(Duration::from_millis(10));
+    /// assert!(request.wait_timeout(Duration::from_secs(5)));
+    /// ```
+    pub fn with_timeout_active(duration: Duration) -> Self {
+        let stop = Self::new();
+        stop.cancel_after(duration);
+        stop
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Stop;
+
+    #[test]
+    fn fires_earliest_deadlines_first() {
+        let late = Stopper::with_timeout_active(Duration::from_secs(3600));
+        let soon = Stopper::with_timeout_active(Duration::from_millis(20));
+        let sooner = Stopper::with_timeout_active(Duration::from_millis(5));
+
+        assert!(sooner.wait_timeout(Duration::from_secs(10)));
+        assert!(soon.wait_timeout(Duration::from_secs(10)));
+        assert_eq!(soon.check(), Err(StopReason::TimedOut));
+        assert!(!late.is_cancelled());
+    }
+
+    #[test]
+    fn many_deadlines_share_one_thread() {
+        let stops: Vec<_> = (0..1000)
+            .map(|i| Stopper::with_timeout_active(Duration::from_millis(1 + i % 20)))
+            .collect();
+        for stop in &stops {
+            assert!(stop.wait_timeout(Duration::from_secs(10)));
+        }
+    }
+
+    #[test]
+    fn earlier_cancel_keeps_its_reason() {
+        let stop = Stopper::new();
+        stop.cancel_after(Duration::from_millis(1));
+        stop.cancel();
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(stop.check(), Err(StopReason::Cancelled));
+    }
+
+    #[test]
+    fn zero_and_huge_durations() {
+        let now = Stopper::with_timeout_active(Duration::ZERO);
+        assert_eq!(now.check(), Err(StopReason::TimedOut));
+
+        let never = Stopper::with_timeout_active(Duration::MAX);
+        assert!(!never.is_cancelled());
+    }
+
+    #[test]
+    fn does_not_keep_stopper_alive() {
+        let stop = Stopper::new();
+        stop.cancel_after(Duration::from_secs(3600));
+        assert_eq!(Arc::strong_count(&stop.inner), 1);
+    }
+}
@@ -0,0 +1,367 @@
+//! `tracing` events for cancellation.
+//!
+//! A stuck cancellation in production usually raises two questions: was
+//! `cancel()` called at all, and did the worker ever notice? [`TracedStop`]
+//! answers both in the logs. It emits a `DEBUG` event when it is cancelled
+//! and another when a check first observes the stop, both carrying the
+//! stop's [`NodeLabel`] and the reason. Events are emitted in whatever span
+//! is current, so the cancel event lands in the canceller's span and the
+//! observation in the worker's.
+//!
+//! A cancel caused by a remote request abort can carry the request's
+//! [`TraceContext`] via [`TracedStop::cancel_with_trace()`]; both events
+//! then include `trace_id` and `span_id` fields (lowercase hex) for
+//! correlation in the tracing backend.
+//!
+//! Checks that find the stop still running emit nothing and cost one
+//! extra branch. Requires the `tracing` feature.
+//!
+//! # Example
+//!
+//! ```rust
+//! use almost_enough::{Stop, TracedStopper};
+//!
+//! let stop = TracedStopper::named("jpeg-decode");
+//! let worker = stop.clone();
+//!
+//! stop.cancel(); // DEBUG stop=jpeg-decode "cancel requested"
+//! assert!(worker.should_stop()); // DEBUG stop=jpeg-decode reason=... "cancellation observed"
+//! assert!(worker.should_stop()); // nothing: already observed
+//! ```
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+
+use crate::label::Hex;
+use crate::{Cancellable, NodeLabel, Stop, StopReason, Stopper, TraceContext};
+
+/// A [`Stop`] wrapper that emits `tracing` events on cancellation.
+///
+/// Created by [`TracedStop::new()`],
+/// [`StopExt::traced()`](crate::StopExt::traced) or, for a fresh
+/// [`Stopper`], [`TracedStopper::named()`]. Clones share one "observed"
+/// flag, so the observation event is emitted once per stop, not once per
+/// clone, and one recorded [`TraceContext`].
+///
+/// Only cancellation requested through this wrapper
+/// ([`cancel()`](Self::cancel) or [`Cancellable::stop()`]) produces the
+/// "cancel requested" event; stops that fire on their own, such as
+/// timeouts, are reported by the observation event alone.
+pub struct TracedStop<T> {
+    inner: T,
+    name: NodeLabel,
+    shared: Arc<Shared>,
+}
+
+/// State shared by the clones of a [`TracedStop`].
+#[derive(Default)]
+struct Shared {
+    observed: AtomicBool,
+    trace: OnceLock<TraceContext>,
+}
+
+/// A [`Stopper`] that emits `tracing` events; see [`TracedStop`].
+pub type TracedStopper = TracedStop<Stopper>;
+
+impl TracedStopper {
+    /// Create a new, uncancelled stopper named `name` in its events.
+    #[inline]
+    pub fn named(name: impl Into<NodeLabel>) -> Self {
+        Self::new(Stopper::new(), name)
+    }
+}
+
+impl<T: Stop> TracedStop<T> {
+    /// Wrap `inner`, naming it `name` in events.
+    #[inline]
+    pub fn new(inner: T, name: impl Into<NodeLabel>) -> Self {
+        Self {
+            inner,
+            name: name.into(),
+            shared: Arc::default(),
+        }
+    }
+
+    /// The name used in events.
+    #[inline]
+    pub fn name(&self) -> NodeLabel {
+        self.name
+    }
+
+    /// Whether a check has observed the stop (and emitted its event).
+    #[inline]
+    pub fn is_observed(&self) -> bool {
+        self.shared.observed.load(Ordering::Relaxed)
+    }
+
+    /// The trace recorded by [`cancel_with_trace()`](Self::cancel_with_trace),
+    /// if any.
+    #[inline]
+    pub fn cancel_trace(&self) -> Option<TraceContext> {
+        self.shared.trace.get().copied()
+    }
+
+    /// Get a reference to the inner stop.
+    #[inline]
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Unwrap and return the inner stop.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    #[cold]
+    fn observed(&self, reason: StopReason) {
+        if !self.shared.observed.swap(true, Ordering::Relaxed) {
+            match self.cancel_trace() {
+                Some(trace) => tracing::debug!(
+                    stop = %self.name,
+                    %reason,
+                    trace_id = %Hex(&trace.trace_id),
+                    span_id = %Hex(&trace.span_id),
+                    "cancellation observed"
+                ),
+                None => tracing::debug!(stop = %self.name, %reason, "cancellation observed"),
+            }
+        }
+    }
+}
+
+impl<T: Cancellable + Stop> TracedStop<T> {
+    /// Emit the "cancel requested" event, then cancel the inner stop.
+    ///
+    /// The event is emitted on every call, so repeated cancellation from
+    /// several places shows up in the logs.
+    #[inline]
+    pub fn cancel(&self) {
+        tracing::debug!(
+            stop = %self.name,
+            already_stopped = self.inner.should_stop(),
+            "cancel requested"
+        );
+        self.inner.stop();
+    }
+
+    /// Like [`cancel()`](Self::cancel), recording the trace and span IDs
+    /// of the request that caused the cancel.
+    ///
+    /// The "cancel requested" event always carries `trace`. It is recorded
+    /// for the observation event and [`cancel_trace()`](Self::cancel_trace)
+    /// only if the inner stop had not stopped yet.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use almost_enough::{TraceContext, TracedStopper};
+    ///
+    /// let stop = TracedStopper::named("upload");
+    /// // From the aborted request's `traceparent` header
+    /// let trace = TraceContext::new([0x4b; 16], [0xa0; 8]);
+    /// stop.cancel_with_trace(trace); // DEBUG stop=upload trace_id=4b4b... "cancel requested"
+    /// assert_eq!(stop.cancel_trace(), Some(trace));
+    /// ```
+    pub fn cancel_with_trace(&self, trace: TraceContext) {
+        let already_stopped = self.inner.should_stop();
+        if !already_stopped {
+            let _ = self.shared.trace.set(trace);
+        }
+        tracing::debug!(
+            stop = %self.name,
+            already_stopped,
+            trace_id = %Hex(&trace.trace_id),
+            span_id = %Hex(&trace.span_id),
+            "cancel requested"
+        );
+        self.inner.stop();
+    }
+}
+
+impl<T: Stop> Stop for TracedStop<T> {
+    #[inline]
+    fn check(&self) -> Result<(), StopReason> {
+        let result = self.inner.check();
+        if let Err(reason) = result {
+            self.observed(reason);
+        }
+        result
+    }
+
+    #[inline]
+    fn should_stop(&self) -> bool {
+        if self.inner.should_stop() {
+            if !self.is_observed() {
+                // The reason is only needed for the first event
+                self.observed(self.inner.check().err().unwrap_or(StopReason::Cancelled));
+            }
+            return true;
+        }
+        false
+    }
+
+    #[inline]
+    fn may_stop(&self) -> bool {
+        self.inner.may_stop()
+    }
+}
+
+impl<T: Cancellable + Stop> Cancellable for TracedStop<T> {
+    #[inline]
+    fn stop(&self) {
+        self.cancel();
+    }
+}
+
+impl<T: Clone> Clone for TracedStop<T> {
+    /// The clone shares this wrapper's "observed" flag and trace.
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            name: self.name,
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl<T: core::fmt::Debug> core::fmt::Debug for TracedStop<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("TracedStop")
+            .field("inner", &self.inner)
+            .field("name", &self.name)
+            .field("observed", &self.shared.observed.load(Ordering::Relaxed))
+            .field("trace", &self.shared.trace.get())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{StopExt, TimeoutExt, Unstoppable};
+    use std::fmt::Write as _;
+    use std::sync::Mutex;
+    use std::time::Duration;
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    /// Records every event as `field=value ...` lines.
+    #[derive(Default, Clone)]
+    struct Recorder(Arc<Mutex<Vec<String>>>);
+
+    impl Recorder {
+        fn take(&self) -> Vec<String> {
+            std::mem::take(&mut *self.0.lock().unwrap())
+        }
+    }
+
+    struct Line(String);
+
+    impl Visit for Line {
+        fn record_debug(&mut self, field: &Field, value: &dyn core::fmt::Debug) {
+            let _ = write!(self.0, "{}={:?} ", field.name(), value);
+        }
+    }
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+        fn new_span(&self, _: &Attributes<'_>) -> Id {
+            Id::from_u64(1)
+        }
+        fn record(&self, _: &Id, _: &Record<'_>) {}
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+        fn event(&self, event: &Event<'_>) {
+            let mut line = Line(String::new());
+            event.record(&mut line);
+            self.0.lock().unwrap().push(line.0);
+        }
+        fn enter(&self, _: &Id) {}
+        fn exit(&self, _: &Id) {}
+    }
+
+    #[test]
+    fn emits_cancel_and_first_observation() {
+        let recorder = Recorder::default();
+        tracing::subscriber::with_default(recorder.clone(), || {
+            let stop = TracedStopper::named("jpeg-decode");
+            let worker = stop.clone();
+            assert!(worker.check().is_ok());
+            assert!(recorder.take().is_empty());
+
+            stop.cancel();
+            let events = recorder.take();
+            assert_eq!(events.len(), 1);
+            assert!(events[0].contains("cancel requested"));
+            assert!(events[0].contains("stop=jpeg-decode"));
+            assert!(events[0].contains("already_stopped=false"));
+
+            assert_eq!(worker.check(), Err(StopReason::Cancelled));
+            assert!(worker.should_stop());
+            assert!(stop.is_observed());
+            let events = recorder.take();
+            assert_eq!(events.len(), 1);
+            assert!(events[0].contains("cancellation observed"));
+            assert!(events[0].contains("reason=operation cancelled"));
+        });
+    }
+
+    #[test]
+    fn events_carry_trace() {
+        let recorder = Recorder::default();
+        tracing::subscriber::with_default(recorder.clone(), || {
+            let trace = TraceContext::new([0xab; 16], [0x01; 8]);
+            let stop = TracedStopper::named("upload");
+            let worker = stop.clone();
+
+            stop.cancel_with_trace(trace);
+            stop.cancel_with_trace(TraceContext::new([0; 16], [0; 8]));
+            assert_eq!(worker.cancel_trace(), Some(trace));
+            let events = recorder.take();
+            assert_eq!(events.len(), 2);
+            assert!(events[0].contains(&format!("trace_id={}", "ab".repeat(16))));
+            assert!(events[0].contains("span_id=0101010101010101"));
+            assert!(events[1].contains("already_stopped=true"));
+            assert!(events[1].contains("span_id=0000000000000000"));
+
+            assert!(worker.should_stop());
+            let events = recorder.take();
+            assert_eq!(events.len(), 1);
+            assert!(events[0].contains("cancellation observed"));
+            assert!(events[0].contains("span_id=0101010101010101"));
+        });
+    }
+
+    #[test]
+    fn timeouts_are_observed_without_cancel_event() {
+        let recorder = Recorder::default();
+        tracing::subscriber::with_default(recorder.clone(), || {
+            let stop = Unstoppable.with_timeout(Duration::ZERO).traced(7u64);
+            assert!(stop.should_stop());
+            let events = recorder.take();
+            assert_eq!(events.len(), 1);
+            assert!(events[0].contains("stop=#7"));
+            assert!(events[0].contains("reason=operation timed out"));
+        });
+    }
+
+    #[test]
+    fn accessors() {
+        let stop = TracedStop::new(Stopper::new(), "resize");
+        assert_eq!(stop.name(), NodeLabel::Name("resize"));
+        assert!(stop.may_stop());
+        Cancellable::stop(&stop);
+        assert!(stop.inner().is_cancelled());
+        assert!(format!("{stop:?}").contains("observed: false"));
+        assert!(stop.into_inner().is_cancelled());
+    }
+
+    #[test]
+    fn is_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<TracedStopper>();
+    }
+}
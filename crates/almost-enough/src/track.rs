@@ -0,0 +1,243 @@
+//! Record where a stop was last checked.
+//!
+//! When a job hangs, the useful question is "which loop stopped checking?".
+//! [`TrackedStop`] wraps a stop and, with the `check-location` feature,
+//! records the source location of the most recent `check()` or
+//! `should_stop()` call via `#[track_caller]`. Query it with
+//! [`last_check_location()`](TrackedStop::last_check_location) from a
+//! watchdog, a signal handler or a debugger.
+//!
+//! Without the feature, `TrackedStop` is a zero-cost passthrough and
+//! `last_check_location()` always returns `None`, so the instrumentation
+//! can stay in the code and be compiled in only when diagnosing a hang.
+//!
+//! # Example
+//!
+//! ```rust
+//! # #[cfg(feature = "alloc")]
+//! # fn main() {
+//! use almost_enough::{Stop, StopExt, Stopper};
+//!
+//! let stop = Stopper::new().track_checks();
+//!
+//! for _ in 0..3 {
+//!     stop.check().unwrap();
+//! }
+//!
+//! if let Some(loc) = stop.last_check_location() {
+//!     eprintln!("last checked at {}:{}", loc.file(), loc.line());
+//! }
+//! # }
+//! # #[cfg(not(feature = "alloc"))]
+//! # fn main() {}
+//! ```
+
+use core::panic::Location;
+
+use crate::{Stop, StopReason};
+
+#[cfg(feature = "check-location")]
+type Slot = std::sync::Mutex<Option<&'static Location<'static>>>;
+
+/// A [`Stop`] wrapper that remembers where it was last checked.
+///
+/// Created by [`StopExt::track_checks()`](crate::StopExt::track_checks) or
+/// [`TrackedStop::new()`]. Recording requires the `check-location`
+/// feature; otherwise the wrapper only forwards to the inner stop.
+///
+/// The recorded location is the caller of `check()`/`should_stop()` on
+/// this wrapper, including calls through `impl Stop` generics. Put the
+/// wrapper outermost: when another wrapper such as
+/// [`OrStop`](crate::OrStop) calls it, that wrapper's source line is
+/// recorded instead. Calls through `&dyn Stop` (and so through `StopToken`
+/// or `BoxedStop`) cannot carry the caller's location and record a line in
+/// this module.
+///
+/// Each wrapper records independently; a clone starts with the original's
+/// last location. When several threads check one shared wrapper at the
+/// same instant, a record may be skipped rather than block the hot path.
+///
+/// # Example
+///
+/// ```rust
+/// use almost_enough::{Stop, StopSource, TrackedStop};
+///
+/// fn decode_rows(stop: &impl Stop) {
+///     stop.check().unwrap(); // recorded here
+/// }
+///
+/// let source = StopSource::new();
+/// let stop = TrackedStop::new(source.as_ref());
+/// decode_rows(&stop);
+///
+/// if let Some(loc) = stop.last_check_location() {
+///     println!("last check: {loc}"); // points into decode_rows
+/// }
+/// ```
+pub struct TrackedStop<T> {
+    inner: T,
+    #[cfg(feature = "check-location")]
+    last: Slot,
+}
+
+impl<T: Stop> TrackedStop<T> {
+    /// Wrap `inner`.
+    #[inline]
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            #[cfg(feature = "check-location")]
+            last: Slot::new(None),
+        }
+    }
+
+    /// Where this stop was most recently checked, if it has been.
+    ///
+    /// Always `None` without the `check-location` feature.
+    #[inline]
+    pub fn last_check_location(&self) -> Option<&'static Location<'static>> {
+        #[cfg(feature = "check-location")]
+        {
+            *self.last.lock().unwrap_or_else(|e| e.into_inner())
+        }
+        #[cfg(not(feature = "check-location"))]
+        {
+            None
+        }
+    }
+
+    /// Get a reference to the inner stop.
+    #[inline]
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Unwrap and return the inner stop.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    #[cfg(feature = "check-location")]
+    #[inline]
+    fn record(&self, location: &'static Location<'static>) {
+        if let Ok(mut last) = self.last.try_lock() {
+            *last = Some(location);
+        }
+    }
+}
+
+impl<T: Stop> Stop for TrackedStop<T> {
+    #[cfg_attr(feature = "check-location", track_caller)]
+    #[inline]
+    fn check(&self) -> Result<(), StopReason> {
+        #[cfg(feature = "check-location")]
+        self.record(Location::caller());
+        self.inner.check()
+    }
+
+    #[cfg_attr(feature = "check-location", track_caller)]
+    #[inline]
+    fn should_stop(&self) -> bool {
+        #[cfg(feature = "check-location")]
+        self.record(Location::caller());
+        self.inner.should_stop()
+    }
+
+    #[inline]
+    fn may_stop(&self) -> bool {
+        self.inner.may_stop()
+    }
+}
+
+impl<T: Stop + Clone> Clone for TrackedStop<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            #[cfg(feature = "check-location")]
+            last: Slot::new(self.last_check_location()),
+        }
+    }
+}
+
+impl<T: Stop + core::fmt::Debug> core::fmt::Debug for TrackedStop<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("TrackedStop")
+            .field("inner", &self.inner)
+            .field("last_check_location", &self.last_check_location())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{StopExt, StopSource, Unstoppable};
+
+    #[test]
+    fn forwards_to_inner() {
+        let source = StopSource::new();
+        let stop = source.as_ref().track_checks();
+        assert!(!stop.should_stop());
+        source.cancel();
+        assert_eq!(stop.check(), Err(StopReason::Cancelled));
+        assert!(stop.inner().should_stop());
+        assert!(stop.may_stop());
+        assert!(!TrackedStop::new(Unstoppable).may_stop());
+    }
+
+    #[test]
+    fn is_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<TrackedStop<Unstoppable>>();
+    }
+
+    #[cfg(not(feature = "check-location"))]
+    #[test]
+    fn records_nothing_without_feature() {
+        let stop = TrackedStop::new(Unstoppable);
+        let _ = stop.check();
+        assert!(stop.last_check_location().is_none());
+        assert_eq!(core::mem::size_of_val(&stop), 0);
+    }
+
+    #[cfg(feature = "check-location")]
+    mod located {
+        use super::*;
+
+        fn generic(stop: &impl Stop) -> u32 {
+            let _ = stop.check();
+            line!() - 1
+        }
+
+        #[test]
+        fn records_direct_call() {
+            let stop = TrackedStop::new(Unstoppable);
+            assert!(stop.last_check_location().is_none());
+
+            let _ = stop.check();
+            let loc = stop.last_check_location().unwrap();
+            assert_eq!(loc.line(), line!() - 2);
+            assert!(loc.file().ends_with("track.rs"));
+        }
+
+        #[test]
+        fn records_through_generics() {
+            let stop = TrackedStop::new(Unstoppable);
+            let line = generic(&stop);
+            assert_eq!(stop.last_check_location().unwrap().line(), line);
+
+            let _ = stop.should_stop();
+            assert_eq!(stop.last_check_location().unwrap().line(), line!() - 1);
+        }
+
+        #[test]
+        fn clone_keeps_last_location() {
+            let stop = TrackedStop::new(Unstoppable);
+            let line = generic(&stop);
+            let clone = stop.clone();
+            assert_eq!(clone.last_check_location().unwrap().line(), line);
+            assert!(format!("{clone:?}").contains("track.rs"));
+        }
+    }
+}
@@ -10,6 +10,32 @@
 //! - [`ChildStopper::with_parent()`] - Create a child of any `Stop` implementation
 //! - [`tree.child()`](ChildStopper::child) - Create a child of this tree node
 //!
+//! # Diagnostics
+//!
+//! Each node knows its [`depth()`](ChildStopper::depth), how many of its
+//! children are still alive ([`child_count()`](ChildStopper::child_count)),
+//! and, once stopped, where the cancellation came from
+//! ([`cancel_origin()`](ChildStopper::cancel_origin)):
+//!
+//! ```rust
+//! use almost_enough::{CancelOrigin, ChildStopper};
+//!
+//! let request = ChildStopper::new();
+//! let decode = request.child();
+//! let resize = decode.child();
+//! assert_eq!(resize.depth(), 2);
+//! assert_eq!(request.child_count(), 1);
+//!
+//! request.cancel();
+//! assert_eq!(resize.cancel_origin(), Some(CancelOrigin::Ancestor(2)));
+//! assert_eq!(request.cancel_origin(), Some(CancelOrigin::Own));
+//! ```
+//!
+//! Nodes created with [`with_label()`](ChildStopper::with_label) or
+//! [`child_with_label()`](ChildStopper::child_with_label) also say *which*
+//! node fired, via [`cancelled_by()`](ChildStopper::cancelled_by) and
+//! [`check_labeled()`](ChildStopper::check_labeled).
+//!
 //! # Example
 //!
 //! ```rust
@@ -61,23 +87,129 @@
 //! ```
 
 use alloc::sync::Arc;
-use core::sync::atomic::{AtomicBool, Ordering};
+use core::any::Any;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+#[cfg(feature = "std")]
+use crate::TraceContext;
+use crate::state::PackedState;
+use crate::{BoxedStop, LabeledReason, NodeLabel, Stop, StopReason, Stopper};
+
+/// Where a [`ChildStopper`]'s cancellation came from.
+///
+/// Returned by [`ChildStopper::cancel_origin()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CancelOrigin {
+    /// This node (or a clone of it) was cancelled directly.
+    Own,
+    /// A `ChildStopper` ancestor this many levels up was cancelled
+    /// (1 = parent, 2 = grandparent, ...).
+    Ancestor(usize),
+    /// A parent that is not a `ChildStopper` (a [`Stopper`](crate::Stopper),
+    /// a timeout, ...) stopped with this reason.
+    External(StopReason),
+}
+
+/// Parent of a tree node.
+enum Parent {
+    /// Another tree node, tracked for depth, child counts and origin.
+    Node(ChildStopper),
+    /// A [`Stopper`], kept unboxed for its label and trace.
+    Stopper(Stopper),
+    /// Any other stop.
+    Other(BoxedStop),
+}
+
+impl Parent {
+    #[inline]
+    fn check(&self) -> Result<(), StopReason> {
+        match self {
+            Parent::Node(node) => node.check(),
+            Parent::Stopper(stopper) => stopper.check(),
+            Parent::Other(stop) => stop.check(),
+        }
+    }
 
-use crate::{BoxedStop, Stop, StopReason};
+    #[inline]
+    fn should_stop(&self) -> bool {
+        match self {
+            Parent::Node(node) => node.should_stop(),
+            Parent::Stopper(stopper) => stopper.should_stop(),
+            Parent::Other(stop) => stop.should_stop(),
+        }
+    }
+}
 
 /// Inner state for a tree node.
 struct TreeInner {
-    /// This node's own cancellation flag.
-    self_cancelled: AtomicBool,
+    /// This node's own cancellation state.
+    own: PackedState,
     /// Parent to check for inherited cancellation (None for root).
-    parent: Option<BoxedStop>,
+    parent: Option<Parent>,
+    /// Distance from the root (0 for a root).
+    depth: usize,
+    /// Set by the `*_with_label()` constructors.
+    label: Option<NodeLabel>,
+    /// Live direct children (decremented when a child's last clone drops).
+    children: AtomicUsize,
+    /// Optional limit on live children, set by `with_child_limit()`.
+    #[cfg(feature = "std")]
+    limiter: std::sync::OnceLock<Limiter>,
+    /// Set by `cancel_with_trace()`.
+    #[cfg(feature = "std")]
+    trace: std::sync::OnceLock<TraceContext>,
+}
+
+impl TreeInner {
+    fn new(parent: Option<Parent>, label: Option<NodeLabel>) -> Self {
+        let depth = match &parent {
+            None => 0,
+            Some(Parent::Node(node)) => {
+                node.inner.children.fetch_add(1, Ordering::Relaxed);
+                node.inner.depth + 1
+            }
+            Some(Parent::Stopper(_) | Parent::Other(_)) => 1,
+        };
+        Self {
+            own: PackedState::new(false),
+            parent,
+            depth,
+            label,
+            children: AtomicUsize::new(0),
+            #[cfg(feature = "std")]
+            limiter: std::sync::OnceLock::new(),
+            #[cfg(feature = "std")]
+            trace: std::sync::OnceLock::new(),
+        }
+    }
+}
+
+impl Drop for TreeInner {
+    fn drop(&mut self) {
+        if let Some(Parent::Node(node)) = &self.parent {
+            node.inner.children.fetch_sub(1, Ordering::Relaxed);
+            #[cfg(feature = "std")]
+            if let Some(limiter) = node.inner.limiter.get() {
+                limiter.notify();
+            }
+        }
+    }
 }
 
 impl core::fmt::Debug for TreeInner {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let parent = self.parent.as_ref().map(|p| match p {
+            Parent::Node(_) => "<ChildStopper>",
+            Parent::Stopper(_) => "<Stopper>",
+            Parent::Other(_) => "<BoxedStop>",
+        });
         f.debug_struct("TreeInner")
-            .field("self_cancelled", &self.self_cancelled)
-            .field("parent", &self.parent.as_ref().map(|_| "<BoxedStop>"))
+            .field("self_cancelled", &self.own.is_cancelled(Ordering::Relaxed))
+            .field("parent", &parent)
+            .field("depth", &self.depth)
+            .field("label", &self.label)
+            .field("children", &self.children)
             .finish()
     }
 }
@@ -136,10 +268,27 @@ impl ChildStopper {
     #[inline]
     pub fn new() -> Self {
         Self {
-            inner: Arc::new(TreeInner {
-                self_cancelled: AtomicBool::new(false),
-                parent: None,
-            }),
+            inner: Arc::new(TreeInner::new(None, None)),
+        }
+    }
+
+    /// Create a new root tree node tagged with a [`NodeLabel`].
+    ///
+    /// Nodes below it report the label from
+    /// [`cancelled_by()`](Self::cancelled_by) when this node fires.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use almost_enough::{ChildStopper, NodeLabel};
+    ///
+    /// let job = ChildStopper::with_label(42u64);
+    /// assert_eq!(job.label(), Some(NodeLabel::Id(42)));
+    /// ```
+    #[inline]
+    pub fn with_label(label: impl Into<NodeLabel>) -> Self {
+        Self {
+            inner: Arc::new(TreeInner::new(None, Some(label.into()))),
         }
     }
 
@@ -160,14 +309,85 @@ impl ChildStopper {
     /// root.cancel();
     /// assert!(child.should_stop());
     /// ```
+    ///
+    /// A `ChildStopper` parent is recognized and linked as a tree node, so
+    /// this is equivalent to [`parent.child()`](Self::child) in that case.
+    ///
+    /// A parent that has already stopped is inherited silently; use
+    /// [`try_with_parent()`](Self::try_with_parent) or
+    /// [`with_parent_policy()`](Self::with_parent_policy) to fail or detach
+    /// instead.
     #[inline]
     pub fn with_parent<T: Stop + 'static>(parent: T) -> Self {
+        let mut slot = Some(parent);
+        if let Some(node) = (&mut slot as &mut dyn Any).downcast_mut::<Option<ChildStopper>>() {
+            return node.take().expect("slot is filled").child();
+        }
+        let parent = match (&mut slot as &mut dyn Any).downcast_mut::<Option<Stopper>>() {
+            Some(stopper) => Parent::Stopper(stopper.take().expect("slot is filled")),
+            None => Parent::Other(BoxedStop::new(slot.take().expect("slot is filled"))),
+        };
         Self {
-            inner: Arc::new(TreeInner {
-                self_cancelled: AtomicBool::new(false),
-                parent: Some(BoxedStop::new(parent)),
-            }),
+            inner: Arc::new(TreeInner::new(Some(parent), None)),
+        }
+    }
+
+    /// Like [`with_parent()`](Self::with_parent), but fails if `parent`
+    /// has already stopped.
+    ///
+    /// Same as [`with_parent_policy(parent, StoppedParentPolicy::Fail)`](Self::with_parent_policy).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use almost_enough::{AlreadyStopped, ChildStopper, StopReason, Stopper};
+    ///
+    /// let server = Stopper::new();
+    /// assert!(ChildStopper::try_with_parent(server.clone()).is_ok());
+    ///
+    /// server.cancel();
+    /// let err = ChildStopper::try_with_parent(server).unwrap_err();
+    /// assert_eq!(err, AlreadyStopped(StopReason::Cancelled));
+    /// ```
+    #[inline]
+    pub fn try_with_parent<T: Stop + 'static>(parent: T) -> Result<Self, AlreadyStopped> {
+        Self::with_parent_policy(parent, StoppedParentPolicy::Fail)
+    }
+
+    /// Like [`with_parent()`](Self::with_parent), with `policy` deciding
+    /// what happens if `parent` has already stopped.
+    ///
+    /// Only the parent's state at construction is considered: a parent
+    /// that stops afterwards is inherited as usual. An error is only
+    /// returned under [`StoppedParentPolicy::Fail`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use almost_enough::{ChildStopper, Stop, StoppedParentPolicy, Stopper};
+    ///
+    /// let server = Stopper::new();
+    /// server.cancel();
+    ///
+    /// // Flush logs during shutdown, still cancellable on its own
+    /// let flush = ChildStopper::with_parent_policy(server, StoppedParentPolicy::Drain)?;
+    /// assert!(!flush.should_stop());
+    /// flush.cancel();
+    /// assert!(flush.should_stop());
+    /// # Ok::<(), almost_enough::AlreadyStopped>(())
+    /// ```
+    pub fn with_parent_policy<T: Stop + 'static>(
+        parent: T,
+        policy: StoppedParentPolicy,
+    ) -> Result<Self, AlreadyStopped> {
+        if let Err(reason) = parent.check() {
+            match policy {
+                StoppedParentPolicy::Inherit => {}
+                StoppedParentPolicy::Fail => return Err(AlreadyStopped(reason)),
+                StoppedParentPolicy::Drain => return Ok(Self::new()),
+            }
         }
+        Ok(Self::with_parent(parent))
     }
 
     /// Create a child of this tree node.
@@ -189,9 +409,83 @@ impl ChildStopper {
     /// assert!(child.should_stop());
     /// assert!(grandchild.should_stop());  // Inherits from parent
     /// ```
+    ///
+    /// # Child Limits
+    ///
+    /// If this node has a [child limit](Self::with_child_limit) that
+    /// refuses the child, a detached node that has already stopped is
+    /// returned instead. Its `check()` reports [`StopReason::Failed`] when
+    /// the limit was reached, or this node's reason if it stopped while a
+    /// [`Block`](ChildLimitPolicy::Block) policy was waiting, so a refusal
+    /// is not mistaken for a cancellation. Use
+    /// [`try_child()`](Self::try_child) to get the
+    /// [`ChildLimitError`] instead.
+    ///
+    /// ```rust
+    /// # #[cfg(feature = "std")]
+    /// # fn main() {
+    /// use almost_enough::{ChildLimitPolicy, ChildStopper, Stop, StopReason};
+    ///
+    /// let request = ChildStopper::new().with_child_limit(1, ChildLimitPolicy::Fail);
+    /// let first = request.child();
+    /// assert_eq!(request.child().check(), Err(StopReason::Failed));
+    /// # drop(first);
+    /// # }
+    /// # #[cfg(not(feature = "std"))]
+    /// # fn main() {}
+    /// ```
     #[inline]
     pub fn child(&self) -> ChildStopper {
-        ChildStopper::with_parent(self.clone())
+        self.spawn(None)
+    }
+
+    /// Create a child of this tree node tagged with a [`NodeLabel`].
+    ///
+    /// Otherwise the same as [`child()`](Self::child), including the
+    /// [child limit](Self::with_child_limit).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use almost_enough::{ChildStopper, NodeLabel};
+    ///
+    /// let request = ChildStopper::new();
+    /// let decode = request.child_with_label("decode");
+    /// let rows = decode.child();
+    ///
+    /// decode.cancel();
+    /// assert_eq!(rows.cancelled_by(), Some(NodeLabel::Name("decode")));
+    /// ```
+    #[inline]
+    pub fn child_with_label(&self, label: impl Into<NodeLabel>) -> ChildStopper {
+        self.spawn(Some(label.into()))
+    }
+
+    #[inline]
+    fn spawn(&self, label: Option<NodeLabel>) -> ChildStopper {
+        #[cfg(feature = "std")]
+        if self.inner.limiter.get().is_some() {
+            return self.try_attach(label).unwrap_or_else(|err| {
+                let reason = match err {
+                    ChildLimitError::Stopped(reason) => reason,
+                    ChildLimitError::LimitReached { .. } => StopReason::Failed,
+                };
+                let refused = Self {
+                    inner: Arc::new(TreeInner::new(None, label)),
+                };
+                refused.inner.own.cancel(reason, Ordering::Relaxed);
+                refused
+            });
+        }
+        self.attach(label)
+    }
+
+    /// Attach a new child, ignoring any limit.
+    #[inline]
+    fn attach(&self, label: Option<NodeLabel>) -> ChildStopper {
+        Self {
+            inner: Arc::new(TreeInner::new(Some(Parent::Node(self.clone())), label)),
+        }
     }
 
     /// Cancel this node (and all its children).
@@ -199,13 +493,20 @@ impl ChildStopper {
     /// This does NOT affect the parent or siblings.
     #[inline]
     pub fn cancel(&self) {
-        self.inner.self_cancelled.store(true, Ordering::Relaxed);
+        let _first = self
+            .inner
+            .own
+            .cancel(StopReason::Cancelled, Ordering::Relaxed);
+        #[cfg(feature = "log")]
+        if _first {
+            crate::logging::stopped(self.inner.label, StopReason::Cancelled);
+        }
     }
 
     /// Check if this node is cancelled (either directly or via ancestor).
     #[inline]
     pub fn is_cancelled(&self) -> bool {
-        if self.inner.self_cancelled.load(Ordering::Relaxed) {
+        if self.inner.own.is_cancelled(Ordering::Relaxed) {
             return true;
         }
         if let Some(ref parent) = self.inner.parent {
@@ -214,6 +515,458 @@ impl ChildStopper {
             false
         }
     }
+
+    /// Distance from the root: 0 for [`new()`](Self::new), 1 for a child
+    /// of a root or of a non-tree stop, and so on.
+    #[inline]
+    pub fn depth(&self) -> usize {
+        self.inner.depth
+    }
+
+    /// Number of direct children that are still alive.
+    ///
+    /// A child counts until its last clone is dropped.
+    #[inline]
+    pub fn child_count(&self) -> usize {
+        self.inner.children.load(Ordering::Relaxed)
+    }
+
+    /// Where this node's cancellation came from, or `None` if it hasn't
+    /// stopped.
+    ///
+    /// Walks the parent chain and reports the nearest cause, so a node
+    /// that was cancelled directly reports [`CancelOrigin::Own`] even if
+    /// an ancestor was cancelled too.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use almost_enough::{CancelOrigin, ChildStopper, StopReason, Stopper};
+    ///
+    /// let root = Stopper::new();
+    /// let node = ChildStopper::with_parent(root.clone()).child();
+    /// assert_eq!(node.cancel_origin(), None);
+    ///
+    /// root.cancel();
+    /// assert_eq!(
+    ///     node.cancel_origin(),
+    ///     Some(CancelOrigin::External(StopReason::Cancelled))
+    /// );
+    /// ```
+    pub fn cancel_origin(&self) -> Option<CancelOrigin> {
+        let mut node = self;
+        let mut levels = 0;
+        loop {
+            if node.inner.own.is_cancelled(Ordering::Relaxed) {
+                return Some(if levels == 0 {
+                    CancelOrigin::Own
+                } else {
+                    CancelOrigin::Ancestor(levels)
+                });
+            }
+            match &node.inner.parent {
+                None => return None,
+                Some(Parent::Node(parent)) => {
+                    node = parent;
+                    levels += 1;
+                }
+                Some(Parent::Stopper(stopper)) => {
+                    return stopper.check().err().map(CancelOrigin::External);
+                }
+                Some(Parent::Other(stop)) => {
+                    return stop.check().err().map(CancelOrigin::External);
+                }
+            }
+        }
+    }
+
+    /// The label given to [`with_label()`](Self::with_label) or
+    /// [`child_with_label()`](Self::child_with_label), if any.
+    #[inline]
+    pub fn label(&self) -> Option<NodeLabel> {
+        self.inner.label
+    }
+
+    /// The label of the node this node's cancellation came from, or `None`
+    /// if it hasn't stopped or that node is unlabeled.
+    ///
+    /// Follows the same nearest-cause walk as
+    /// [`cancel_origin()`](Self::cancel_origin). A non-tree parent reports
+    /// the label of a [`Stopper::with_label()`](crate::Stopper::with_label)
+    /// passed to [`with_parent()`](Self::with_parent).
+    #[inline]
+    pub fn cancelled_by(&self) -> Option<NodeLabel> {
+        self.check_labeled().err()?.label
+    }
+
+    /// Like [`check()`](Stop::check), but the error also carries the label
+    /// of the node that fired.
+    ///
+    /// Walks the parent chain like `check()`, so it costs the same.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use almost_enough::{ChildStopper, NodeLabel, StopReason};
+    ///
+    /// fn stage(stop: &ChildStopper) -> Result<(), StopReason> {
+    ///     if let Err(err) = stop.check_labeled() {
+    ///         eprintln!("stage stopped: {err}");
+    ///         return Err(err.into());
+    ///     }
+    ///     Ok(())
+    /// }
+    ///
+    /// let root = ChildStopper::with_label("shutdown");
+    /// let node = root.child();
+    /// root.cancel();
+    /// assert_eq!(stage(&node), Err(StopReason::Cancelled));
+    /// ```
+    pub fn check_labeled(&self) -> Result<(), LabeledReason> {
+        let mut node = self;
+        loop {
+            if let Err(reason) = node.inner.own.check(Ordering::Relaxed) {
+                return Err(LabeledReason {
+                    reason,
+                    label: node.inner.label,
+                });
+            }
+            match &node.inner.parent {
+                None => return Ok(()),
+                Some(Parent::Node(parent)) => node = parent,
+                Some(Parent::Stopper(stopper)) => {
+                    return stopper.check().map_err(|reason| LabeledReason {
+                        reason,
+                        label: stopper.label(),
+                    });
+                }
+                Some(Parent::Other(stop)) => {
+                    return stop.check().map_err(|reason| LabeledReason {
+                        reason,
+                        label: None,
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// What [`ChildStopper::with_parent_policy()`] does when the parent has
+/// already stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum StoppedParentPolicy {
+    /// Link the child anyway; it reports the parent's reason on its first
+    /// check. This is what [`ChildStopper::with_parent()`] does.
+    #[default]
+    Inherit,
+    /// Refuse to create the child with [`AlreadyStopped`].
+    Fail,
+    /// Create a detached root instead, which only stops when cancelled
+    /// directly.
+    ///
+    /// For cleanup work started during shutdown (flushing, releasing
+    /// leases) that must not see the shutdown as its own cancellation but
+    /// should still be cancellable.
+    Drain,
+}
+
+/// Error returned when a [`ChildStopper`] is refused because its parent has
+/// already stopped, with the parent's reason.
+///
+/// Returned by [`ChildStopper::try_with_parent()`] and
+/// [`ChildStopper::with_parent_policy()`]. Converts into [`StopReason`], so
+/// `?` works in functions returning `Result<_, StopReason>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AlreadyStopped(pub StopReason);
+
+impl From<AlreadyStopped> for StopReason {
+    #[inline]
+    fn from(err: AlreadyStopped) -> Self {
+        err.0
+    }
+}
+
+impl core::fmt::Display for AlreadyStopped {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "parent already stopped: {}", self.0)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for AlreadyStopped {}
+
+/// What [`ChildStopper::try_child()`] does when the child limit is reached.
+///
+/// Set with [`ChildStopper::with_child_limit()`]. Requires the `std` feature.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ChildLimitPolicy {
+    /// Refuse the new child with [`ChildLimitError::LimitReached`].
+    Fail,
+    /// Wait for a child to be dropped. Gives up with
+    /// [`ChildLimitError::Stopped`] if the parent node stops while waiting.
+    Block,
+    /// Cancel the oldest uncancelled child and attach the new one.
+    ///
+    /// The cancelled child still counts as live until its last clone is
+    /// dropped, so this bounds the number of *running* children.
+    CancelOldest,
+}
+
+#[cfg(feature = "std")]
+impl ChildLimitPolicy {
+    const fn to_u8(self) -> u8 {
+        match self {
+            ChildLimitPolicy::Fail => 0,
+            ChildLimitPolicy::Block => 1,
+            ChildLimitPolicy::CancelOldest => 2,
+        }
+    }
+
+    const fn from_u8(value: u8) -> Self {
+        match value {
+            1 => ChildLimitPolicy::Block,
+            2 => ChildLimitPolicy::CancelOldest,
+            _ => ChildLimitPolicy::Fail,
+        }
+    }
+}
+
+/// Error returned by [`ChildStopper::try_child()`].
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ChildLimitError {
+    /// The node already has `limit` live children.
+    LimitReached {
+        /// The configured limit.
+        limit: usize,
+    },
+    /// The node stopped while waiting for room under
+    /// [`ChildLimitPolicy::Block`].
+    Stopped(StopReason),
+}
+
+#[cfg(feature = "std")]
+impl core::fmt::Display for ChildLimitError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ChildLimitError::LimitReached { limit } => {
+                write!(f, "child limit of {limit} reached")
+            }
+            ChildLimitError::Stopped(reason) => {
+                write!(f, "stopped while waiting for a child slot: {reason}")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ChildLimitError {}
+
+/// How often a blocked [`ChildStopper::try_child()`] re-checks the parent.
+#[cfg(feature = "std")]
+const LIMIT_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(10);
+
+/// Child-limit state of a tree node.
+#[cfg(feature = "std")]
+struct Limiter {
+    max: AtomicUsize,
+    policy: core::sync::atomic::AtomicU8,
+    /// Children attached under the limit, oldest first.
+    tracked: std::sync::Mutex<alloc::vec::Vec<alloc::sync::Weak<TreeInner>>>,
+    /// Signalled when a child drops.
+    freed: std::sync::Condvar,
+}
+
+#[cfg(feature = "std")]
+impl Limiter {
+    fn notify(&self) {
+        drop(self.tracked.lock().unwrap_or_else(|e| e.into_inner()));
+        self.freed.notify_all();
+    }
+}
+
+#[cfg(feature = "std")]
+impl ChildStopper {
+    /// Report the strong and weak counts of this node's shared state.
+    ///
+    /// Every live child holds its parent node strongly, so children that
+    /// were never dropped show up here. See
+    /// [`Stopper::ownership_report()`](crate::Stopper::ownership_report).
+    pub fn ownership_report(&self) -> crate::OwnershipReport {
+        crate::OwnershipReport::of(&self.inner)
+    }
+
+    /// Cancel this node, recording the trace and span IDs of the request
+    /// that caused it.
+    ///
+    /// Like [`Stopper::cancel_with_trace()`], the trace is only recorded if
+    /// this call cancels the node, which the return value reports.
+    ///
+    /// Requires the `std` feature.
+    pub fn cancel_with_trace(&self, trace: TraceContext) -> bool {
+        let first = self
+            .inner
+            .own
+            .cancel(StopReason::Cancelled, Ordering::Release);
+        if first {
+            let _ = self.inner.trace.set(trace);
+            #[cfg(feature = "log")]
+            crate::logging::stopped(self.inner.label, StopReason::Cancelled);
+        }
+        first
+    }
+
+    /// The trace of the cancel this node's cancellation came from, or
+    /// `None` if it hasn't stopped or that cancel carried no trace.
+    ///
+    /// Follows the same nearest-cause walk as
+    /// [`cancel_origin()`](Self::cancel_origin). A non-tree parent reports
+    /// the trace of a [`Stopper`] passed to
+    /// [`with_parent()`](Self::with_parent).
+    ///
+    /// Requires the `std` feature.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use almost_enough::{ChildStopper, Stopper, TraceContext};
+    ///
+    /// let request = Stopper::new();
+    /// let decode = ChildStopper::with_parent(request.clone()).child();
+    ///
+    /// // The remote client aborted the request
+    /// let trace = TraceContext::new([0x4b; 16], [0xa0; 8]);
+    /// request.cancel_with_trace(trace);
+    /// assert_eq!(decode.cancel_trace(), Some(trace));
+    /// ```
+    pub fn cancel_trace(&self) -> Option<TraceContext> {
+        let mut node = self;
+        loop {
+            if node.inner.own.is_cancelled(Ordering::Acquire) {
+                return node.inner.trace.get().copied();
+            }
+            match &node.inner.parent {
+                None => return None,
+                Some(Parent::Node(parent)) => node = parent,
+                Some(Parent::Stopper(stopper)) => {
+                    if !stopper.should_stop() {
+                        return None;
+                    }
+                    return stopper.cancel_trace();
+                }
+                Some(Parent::Other(_)) => return None,
+            }
+        }
+    }
+
+    /// Limit how many live children this node may have.
+    ///
+    /// Applies to all clones of this node and to children created after
+    /// the call, whether via [`child()`](Self::child),
+    /// [`try_child()`](Self::try_child) or [`with_parent()`](Self::with_parent).
+    /// Calling it again replaces the limit and policy.
+    ///
+    /// Requires the `std` feature.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use almost_enough::{ChildLimitError, ChildLimitPolicy, ChildStopper};
+    ///
+    /// let request = ChildStopper::new().with_child_limit(2, ChildLimitPolicy::Fail);
+    /// let a = request.try_child().unwrap();
+    /// let b = request.try_child().unwrap();
+    /// assert_eq!(
+    ///     request.try_child().unwrap_err(),
+    ///     ChildLimitError::LimitReached { limit: 2 }
+    /// );
+    ///
+    /// drop(a);
+    /// assert!(request.try_child().is_ok());
+    /// # drop(b);
+    /// ```
+    pub fn with_child_limit(self, limit: usize, policy: ChildLimitPolicy) -> Self {
+        let limiter = self.inner.limiter.get_or_init(|| Limiter {
+            max: AtomicUsize::new(usize::MAX),
+            policy: core::sync::atomic::AtomicU8::new(0),
+            tracked: std::sync::Mutex::new(alloc::vec::Vec::new()),
+            freed: std::sync::Condvar::new(),
+        });
+        limiter.max.store(limit, Ordering::Relaxed);
+        limiter.policy.store(policy.to_u8(), Ordering::Relaxed);
+        // Waiters re-read the new limit
+        limiter.notify();
+        self
+    }
+
+    /// The configured child limit and policy, if any.
+    pub fn child_limit(&self) -> Option<(usize, ChildLimitPolicy)> {
+        self.inner.limiter.get().map(|limiter| {
+            (
+                limiter.max.load(Ordering::Relaxed),
+                ChildLimitPolicy::from_u8(limiter.policy.load(Ordering::Relaxed)),
+            )
+        })
+    }
+
+    /// Create a child, applying this node's [child limit](Self::with_child_limit).
+    ///
+    /// Without a limit this is the same as [`child()`](Self::child).
+    /// Requires the `std` feature.
+    pub fn try_child(&self) -> Result<ChildStopper, ChildLimitError> {
+        self.try_attach(None)
+    }
+
+    fn try_attach(&self, label: Option<NodeLabel>) -> Result<ChildStopper, ChildLimitError> {
+        let Some(limiter) = self.inner.limiter.get() else {
+            return Ok(self.attach(label));
+        };
+        // Declared before the guard so a last reference is released
+        // after unlocking (dropping a child re-locks to notify)
+        let mut victim: Option<Arc<TreeInner>> = None;
+        let mut tracked = limiter.tracked.lock().unwrap_or_else(|e| e.into_inner());
+        loop {
+            tracked.retain(|child| child.strong_count() > 0);
+            let max = limiter.max.load(Ordering::Relaxed);
+            if self.inner.children.load(Ordering::Relaxed) < max {
+                break;
+            }
+            match ChildLimitPolicy::from_u8(limiter.policy.load(Ordering::Relaxed)) {
+                ChildLimitPolicy::Fail => {
+                    return Err(ChildLimitError::LimitReached { limit: max });
+                }
+                ChildLimitPolicy::CancelOldest => {
+                    victim = tracked
+                        .iter()
+                        .filter_map(alloc::sync::Weak::upgrade)
+                        .find(|child| !child.own.is_cancelled(Ordering::Relaxed));
+                    if let Some(child) = &victim {
+                        child.own.cancel(StopReason::Cancelled, Ordering::Relaxed);
+                    }
+                    break;
+                }
+                ChildLimitPolicy::Block => {
+                    if let Err(reason) = self.check() {
+                        return Err(ChildLimitError::Stopped(reason));
+                    }
+                    tracked = limiter
+                        .freed
+                        .wait_timeout(tracked, LIMIT_POLL_INTERVAL)
+                        .unwrap_or_else(|e| e.into_inner())
+                        .0;
+                }
+            }
+        }
+        let child = self.attach(label);
+        tracked.push(Arc::downgrade(&child.inner));
+        drop(tracked);
+        drop(victim);
+        Ok(child)
+    }
 }
 
 impl Default for ChildStopper {
@@ -225,9 +978,7 @@ impl Default for ChildStopper {
 impl Stop for ChildStopper {
     #[inline]
     fn check(&self) -> Result<(), StopReason> {
-        if self.inner.self_cancelled.load(Ordering::Relaxed) {
-            return Err(StopReason::Cancelled);
-        }
+        self.inner.own.check(Ordering::Relaxed)?;
         if let Some(ref parent) = self.inner.parent {
             parent.check()
         } else {
@@ -299,7 +1050,7 @@ mod tests {
     }
 
     #[test]
-    fn tree_grandchild() {
+    fn treegrandchild() {
         let grandparent = ChildStopper::new();
         let parent = grandparent.child();
         let child = parent.child();
@@ -355,9 +1106,342 @@ mod tests {
         assert_send_sync::<ChildStopper>();
     }
 
+    #[test]
+    fn depth_counts_levels() {
+        let root = ChildStopper::new();
+        let child = root.child();
+        let grandchild = child.child();
+        assert_eq!(root.depth(), 0);
+        assert_eq!(child.depth(), 1);
+        assert_eq!(grandchild.depth(), 2);
+
+        let external = ChildStopper::with_parent(Stopper::new());
+        assert_eq!(external.depth(), 1);
+        assert_eq!(external.child().depth(), 2);
+
+        // A ChildStopper passed to with_parent links as a tree node
+        assert_eq!(ChildStopper::with_parent(grandchild).depth(), 3);
+    }
+
+    #[test]
+    fn child_count_tracks_live_children() {
+        let root = ChildStopper::new();
+        assert_eq!(root.child_count(), 0);
+
+        let a = root.child();
+        let b = ChildStopper::with_parent(root.clone());
+        let a_clone = a.clone();
+        let grandchild = a.child();
+        assert_eq!(root.child_count(), 2);
+        assert_eq!(a.child_count(), 1);
+
+        drop(a);
+        assert_eq!(root.child_count(), 2); // a_clone keeps it alive
+        drop(a_clone);
+        // The grandchild still holds `a` as its parent
+        assert_eq!(root.child_count(), 2);
+        drop(grandchild);
+        assert_eq!(root.child_count(), 1);
+        drop(b);
+        assert_eq!(root.child_count(), 0);
+    }
+
+    #[test]
+    fn cancel_origin_reports_nearest_cause() {
+        let root = ChildStopper::new();
+        let mid = root.child();
+        let leaf = mid.child();
+        assert_eq!(leaf.cancel_origin(), None);
+
+        root.cancel();
+        assert_eq!(root.cancel_origin(), Some(CancelOrigin::Own));
+        assert_eq!(mid.cancel_origin(), Some(CancelOrigin::Ancestor(1)));
+        assert_eq!(leaf.cancel_origin(), Some(CancelOrigin::Ancestor(2)));
+
+        mid.cancel();
+        assert_eq!(leaf.cancel_origin(), Some(CancelOrigin::Ancestor(1)));
+        leaf.cancel();
+        assert_eq!(leaf.cancel_origin(), Some(CancelOrigin::Own));
+    }
+
+    #[test]
+    fn cancel_origin_external_parent() {
+        let root = Stopper::new();
+        let leaf = ChildStopper::with_parent(root.clone()).child();
+        root.cancel();
+        assert_eq!(
+            leaf.cancel_origin(),
+            Some(CancelOrigin::External(StopReason::Cancelled))
+        );
+    }
+
+    #[test]
+    fn cancelled_by_reports_nearest_labeled_cause() {
+        let root = ChildStopper::with_label("request");
+        let mid = root.child_with_label(2u64);
+        let leaf = mid.child();
+        assert_eq!(leaf.cancelled_by(), None);
+        assert_eq!(leaf.check_labeled(), Ok(()));
+
+        root.cancel();
+        assert_eq!(leaf.cancelled_by(), Some(NodeLabel::Name("request")));
+
+        mid.cancel();
+        assert_eq!(
+            leaf.check_labeled(),
+            Err(LabeledReason {
+                reason: StopReason::Cancelled,
+                label: Some(NodeLabel::Id(2)),
+            })
+        );
+
+        // An unlabeled node that fired reports no label
+        leaf.cancel();
+        assert_eq!(leaf.cancelled_by(), None);
+        assert_eq!(leaf.check_labeled().unwrap_err().label, None);
+    }
+
+    #[test]
+    fn cancelled_by_labeled_stopper_parent() {
+        let server = Stopper::with_label("server");
+        let leaf = ChildStopper::with_parent(server.clone()).child();
+        assert_eq!(leaf.label(), None);
+        server.cancel();
+        assert_eq!(leaf.cancelled_by(), Some(NodeLabel::Name("server")));
+
+        let plain = Stopper::cancelled();
+        let node = ChildStopper::with_parent(plain);
+        assert_eq!(node.check_labeled().unwrap_err().label, None);
+    }
+
+    #[test]
+    fn with_parent_of_tree_node_is_child() {
+        let root = ChildStopper::new();
+        let child = ChildStopper::with_parent(root.clone());
+        assert_eq!(root.child_count(), 1);
+        root.cancel();
+        assert_eq!(child.cancel_origin(), Some(CancelOrigin::Ancestor(1)));
+    }
+
+    #[test]
+    fn stopped_parent_policies() {
+        let parent = Stopper::new();
+        for policy in [
+            StoppedParentPolicy::Inherit,
+            StoppedParentPolicy::Fail,
+            StoppedParentPolicy::Drain,
+        ] {
+            let child = ChildStopper::with_parent_policy(parent.clone(), policy).unwrap();
+            assert_eq!(child.depth(), 1);
+        }
+
+        parent.cancel();
+        let inherited =
+            ChildStopper::with_parent_policy(parent.clone(), StoppedParentPolicy::default())
+                .unwrap();
+        assert_eq!(inherited.check(), Err(StopReason::Cancelled));
+
+        let err = ChildStopper::try_with_parent(parent.clone()).unwrap_err();
+        assert_eq!(StopReason::from(err), StopReason::Cancelled);
+        assert_eq!(
+            alloc::string::ToString::to_string(&err),
+            "parent already stopped: operation cancelled"
+        );
+
+        let node = ChildStopper::new();
+        node.cancel();
+        let draining = ChildStopper::with_parent_policy(node, StoppedParentPolicy::Drain).unwrap();
+        assert!(draining.check().is_ok());
+        assert_eq!(draining.depth(), 0);
+        draining.cancel();
+        assert_eq!(draining.cancel_origin(), Some(CancelOrigin::Own));
+    }
+
     #[test]
     fn tree_is_default() {
         let t: ChildStopper = Default::default();
         assert!(!t.is_cancelled());
     }
 }
+
+#[cfg(all(test, feature = "std"))]
+mod trace_tests {
+    use super::*;
+
+    #[test]
+    fn cancel_trace_reports_nearest_cause() {
+        let outer = TraceContext::new([1; 16], [1; 8]);
+        let inner = TraceContext::new([2; 16], [2; 8]);
+        let root = Stopper::new();
+        let node = ChildStopper::with_parent(root.clone());
+        let leaf = node.child();
+        assert_eq!(leaf.cancel_trace(), None);
+
+        root.cancel_with_trace(outer);
+        assert_eq!(leaf.cancel_trace(), Some(outer));
+
+        // A later traced cancel nearer the leaf is the nearest cause
+        node.cancel_with_trace(inner);
+        assert_eq!(leaf.cancel_trace(), Some(inner));
+        assert_eq!(node.cancel_trace(), Some(inner));
+
+        // An untraced own cancel hides the ancestors' traces
+        leaf.cancel();
+        assert_eq!(leaf.cancel_trace(), None);
+    }
+
+    #[test]
+    fn cancel_trace_of_non_stopper_parent() {
+        let node = ChildStopper::with_parent(crate::Unstoppable);
+        assert_eq!(node.cancel_trace(), None);
+        node.cancel_with_trace(TraceContext::new([3; 16], [3; 8]));
+        assert!(node.cancel_trace().is_some());
+    }
+
+    #[test]
+    fn racing_cancels_record_only_the_winners_trace() {
+        for _ in 0..200 {
+            let node = ChildStopper::new();
+            let barrier = std::sync::Arc::new(std::sync::Barrier::new(5));
+            let traced: Vec<_> = (0..4u8)
+                .map(|i| {
+                    let node = node.clone();
+                    let barrier = std::sync::Arc::clone(&barrier);
+                    let trace = TraceContext::new([i; 16], [i; 8]);
+                    std::thread::spawn(move || {
+                        barrier.wait();
+                        node.cancel_with_trace(trace).then_some(trace)
+                    })
+                })
+                .collect();
+            barrier.wait();
+            node.cancel();
+            let winners: Vec<_> = traced
+                .into_iter()
+                .filter_map(|handle| handle.join().unwrap())
+                .collect();
+            assert!(winners.len() <= 1);
+            assert_eq!(node.cancel_trace(), winners.first().copied());
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod limit_tests {
+    use super::*;
+    use crate::Stopper;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn unlimited_by_default() {
+        let root = ChildStopper::new();
+        assert_eq!(root.child_limit(), None);
+        let children: Vec<_> = (0..100).map(|_| root.try_child().unwrap()).collect();
+        assert_eq!(root.child_count(), children.len());
+    }
+
+    #[test]
+    fn fail_policy_refuses_and_recovers() {
+        let root = ChildStopper::new().with_child_limit(1, ChildLimitPolicy::Fail);
+        assert_eq!(root.child_limit(), Some((1, ChildLimitPolicy::Fail)));
+
+        let first = root.try_child().unwrap();
+        let err = root.try_child().unwrap_err();
+        assert_eq!(err, ChildLimitError::LimitReached { limit: 1 });
+        assert!(err.to_string().contains("limit of 1"));
+
+        // child() hands back a detached node that reports the refusal
+        let refused = root.child();
+        assert!(refused.is_cancelled());
+        assert_eq!(refused.check(), Err(StopReason::Failed));
+        assert_eq!(refused.cancel_origin(), Some(CancelOrigin::Own));
+        assert_eq!(root.child_count(), 1);
+
+        drop(first);
+        assert!(!root.try_child().unwrap().is_cancelled());
+    }
+
+    #[test]
+    fn labeled_child_respects_limit() {
+        let root = ChildStopper::new().with_child_limit(1, ChildLimitPolicy::Fail);
+        let first = root.child_with_label("first");
+        assert_eq!(first.label(), Some(NodeLabel::Name("first")));
+        assert_eq!(root.child_count(), 1);
+
+        let refused = root.child_with_label("second");
+        assert_eq!(refused.check(), Err(StopReason::Failed));
+        assert_eq!(refused.cancelled_by(), Some(NodeLabel::Name("second")));
+    }
+
+    #[test]
+    fn limit_applies_to_with_parent() {
+        let root = ChildStopper::new().with_child_limit(0, ChildLimitPolicy::Fail);
+        assert!(ChildStopper::with_parent(root.clone()).is_cancelled());
+        assert_eq!(root.child_count(), 0);
+    }
+
+    #[test]
+    fn cancel_oldest_policy() {
+        let root = ChildStopper::new().with_child_limit(2, ChildLimitPolicy::CancelOldest);
+        let a = root.try_child().unwrap();
+        let b = root.try_child().unwrap();
+        let c = root.try_child().unwrap();
+        assert!(a.is_cancelled());
+        assert!(!b.is_cancelled());
+        assert!(!c.is_cancelled());
+
+        // `a` is still draining, so the next victim is `b`
+        let d = root.try_child().unwrap();
+        assert!(b.is_cancelled());
+        assert!(!d.is_cancelled());
+        assert!(!root.is_cancelled());
+    }
+
+    #[test]
+    fn block_policy_waits_for_drop() {
+        let root = ChildStopper::new().with_child_limit(1, ChildLimitPolicy::Block);
+        let first = root.try_child().unwrap();
+
+        let releaser = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            drop(first);
+        });
+        let second = root.try_child().unwrap();
+        assert!(!second.is_cancelled());
+        releaser.join().unwrap();
+        assert_eq!(root.child_count(), 1);
+    }
+
+    #[test]
+    fn block_policy_gives_up_when_stopped() {
+        let parent = Stopper::new();
+        let root =
+            ChildStopper::with_parent(parent.clone()).with_child_limit(1, ChildLimitPolicy::Block);
+        let _first = root.try_child().unwrap();
+
+        let canceller = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            parent.cancel();
+        });
+        assert_eq!(
+            root.try_child().unwrap_err(),
+            ChildLimitError::Stopped(StopReason::Cancelled)
+        );
+        canceller.join().unwrap();
+    }
+
+    #[test]
+    fn raising_limit_wakes_blocked_callers() {
+        let root = ChildStopper::new().with_child_limit(0, ChildLimitPolicy::Block);
+        let raiser = {
+            let root = root.clone();
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(20));
+                let _ = root.with_child_limit(1, ChildLimitPolicy::Block);
+            })
+        };
+        assert!(root.try_child().is_ok());
+        raiser.join().unwrap();
+    }
+}
@@ -0,0 +1,181 @@
+//! Deadlines for `wasm32-unknown-unknown`.
+//!
+//! `WithTimeout` reads `std::time::Instant`, which panics on
+//! `wasm32-unknown-unknown` because the target has no clock of its own.
+//! [`PerformanceClock`] reads the host's `performance.now()` through
+//! `js-sys` instead, and plugs into [`WithClockTimeout`] like any other
+//! [`Clock`]. [`PerformanceTimeoutExt`] wraps that up behind a
+//! `Duration`-based method, so browser code keeps the familiar shape:
+//!
+//! ```rust
+//! use almost_enough::{PerformanceTimeoutExt, Stop, Stopper};
+//! use std::time::Duration;
+//!
+//! let stopper = Stopper::new();
+//! let stop = stopper.clone().with_performance_timeout(Duration::from_secs(5));
+//! assert!(stop.check().is_ok());
+//!
+//! stopper.cancel();
+//! assert!(stop.should_stop());
+//! ```
+//!
+//! On other targets (including WASI, which has a working `Instant`) the
+//! clock falls back to `Instant`, so the same code runs in native tests.
+//! Requires the `wasm` feature.
+
+use core::time::Duration;
+
+use crate::{Clock, Stop, WithClockTimeout};
+
+/// A [`Clock`] backed by `performance.now()`, in microseconds.
+///
+/// Works on the main thread and in workers. Where the global has no
+/// `performance` object, falls back to `Date.now()`, which is not
+/// monotonic; deadlines then follow wall-clock adjustments.
+///
+/// Each read crosses into JavaScript, so for very tight loops prefer
+/// checking every N iterations (see `StopIterExt::stop_every`).
+///
+/// On targets other than `wasm32-unknown-unknown`, reads
+/// `std::time::Instant` relative to the first use of the clock in the
+/// process.
+///
+/// # Example
+///
+/// ```rust
+/// use almost_enough::{PerformanceClock, Stop, Unstoppable, WithClockTimeout};
+/// use std::time::Duration;
+///
+/// let stop = WithClockTimeout::new(
+///     Unstoppable,
+///     PerformanceClock,
+///     PerformanceClock::ticks(Duration::from_millis(250)),
+/// );
+/// assert!(stop.check().is_ok());
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PerformanceClock;
+
+impl PerformanceClock {
+    /// Convert `duration` to ticks of this clock (microseconds),
+    /// saturating at `u64::MAX`.
+    #[inline]
+    pub const fn ticks(duration: Duration) -> u64 {
+        let micros = duration.as_micros();
+        if micros > u64::MAX as u128 {
+            u64::MAX
+        } else {
+            micros as u64
+        }
+    }
+}
+
+impl Clock for PerformanceClock {
+    #[inline]
+    fn now_ticks(&self) -> u64 {
+        now_micros()
+    }
+}
+
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+fn now_micros() -> u64 {
+    use js_sys::Function;
+    use js_sys::wasm_bindgen::{JsCast, JsValue};
+
+    std::thread_local! {
+        // `(performance, performance.now)`, looked up once per thread
+        static NOW: Option<(JsValue, Function)> = {
+            let performance = js_sys::Reflect::get(&js_sys::global(), &"performance".into())
+                .ok()
+                .filter(|p| p.is_object());
+            performance.and_then(|performance| {
+                let now = js_sys::Reflect::get(&performance, &"now".into()).ok()?;
+                Some((performance, now.dyn_into::<Function>().ok()?))
+            })
+        };
+    }
+
+    let millis = NOW
+        .with(|now| {
+            let (performance, now) = now.as_ref()?;
+            now.call0(performance).ok()?.as_f64()
+        })
+        .unwrap_or_else(js_sys::Date::now);
+    // `as` saturates, and maps NaN to 0
+    (millis * 1000.0) as u64
+}
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+fn now_micros() -> u64 {
+    use std::sync::OnceLock;
+    use std::time::Instant;
+
+    static EPOCH: OnceLock<Instant> = OnceLock::new();
+    PerformanceClock::ticks(EPOCH.get_or_init(Instant::now).elapsed())
+}
+
+/// Extension trait adding [`PerformanceClock`] deadlines to any [`Stop`].
+///
+/// The `wasm32` counterpart of `TimeoutExt::with_timeout`. Requires the
+/// `wasm` feature.
+pub trait PerformanceTimeoutExt: Stop + Sized {
+    /// Add a deadline `duration` from now, measured by `performance.now()`.
+    ///
+    /// Returns [`StopReason::TimedOut`](crate::StopReason::TimedOut) once it
+    /// passes. Use [`WithClockTimeout::tighten`] with
+    /// [`PerformanceClock::ticks`] to shorten it later.
+    #[inline]
+    fn with_performance_timeout(
+        self,
+        duration: Duration,
+    ) -> WithClockTimeout<Self, PerformanceClock> {
+        WithClockTimeout::new(self, PerformanceClock, PerformanceClock::ticks(duration))
+    }
+}
+
+impl<T: Stop> PerformanceTimeoutExt for T {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{StopReason, StopSource, Unstoppable};
+
+    #[test]
+    fn ticks_are_microseconds() {
+        assert_eq!(PerformanceClock::ticks(Duration::from_millis(3)), 3_000);
+        assert_eq!(PerformanceClock::ticks(Duration::MAX), u64::MAX);
+    }
+
+    #[test]
+    fn clock_is_monotonic() {
+        let a = PerformanceClock.now_ticks();
+        std::thread::sleep(Duration::from_millis(2));
+        let b = PerformanceClock.now_ticks();
+        assert!(b >= a + 1_000);
+    }
+
+    #[test]
+    fn times_out() {
+        let stop = Unstoppable.with_performance_timeout(Duration::from_millis(1));
+        assert!(stop.remaining_ticks() <= 1_000);
+        std::thread::sleep(Duration::from_millis(5));
+        assert_eq!(stop.check(), Err(StopReason::TimedOut));
+    }
+
+    #[test]
+    fn inner_stop_still_applies() {
+        let source = StopSource::new();
+        let stop = source
+            .as_ref()
+            .with_performance_timeout(Duration::from_secs(60));
+        assert!(stop.check().is_ok());
+        source.cancel();
+        assert_eq!(stop.check(), Err(StopReason::Cancelled));
+    }
+
+    #[test]
+    fn is_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<WithClockTimeout<Unstoppable, PerformanceClock>>();
+    }
+}
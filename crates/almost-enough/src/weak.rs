@@ -0,0 +1,168 @@
+//! Observing a [`Stopper`] without keeping it alive.
+//!
+//! A cache entry or a background thread that holds a `Stopper` clone keeps
+//! the stopper's shared state alive for as long as it exists, and with it
+//! anything that owns the controlling side, such as a slot in a long-lived
+//! registry. A [`WeakStopper`] from [`Stopper::downgrade()`] observes the
+//! same cancellation without counting as an owner. Once every `Stopper`
+//! clone is gone, nobody can cancel any more, and the weak handle reports
+//! the stop as cancelled, or, with
+//! [`stop_when_dropped(false)`](WeakStopper::stop_when_dropped), never
+//! stops.
+//!
+//! # Example
+//!
+//! ```rust
+//! use almost_enough::{Stop, Stopper};
+//!
+//! let request = Stopper::new();
+//! let cache_entry = request.downgrade();
+//! assert!(!cache_entry.should_stop());
+//!
+//! // The request finished and its stopper was dropped
+//! drop(request);
+//! assert!(cache_entry.should_stop());
+//! assert!(cache_entry.upgrade().is_none());
+//! ```
+
+use alloc::sync::{Arc, Weak};
+
+use crate::stopper::StopperInner;
+use crate::{Stop, StopReason, Stopper};
+
+/// A non-owning handle to a [`Stopper`]; see [`Stopper::downgrade()`].
+///
+/// Checks cost an upgrade of the weak reference (two atomic
+/// read-modify-writes) on top of the stopper's own load. Loops that check
+/// very often should [`upgrade()`](Self::upgrade) once and check the
+/// `Stopper`.
+#[derive(Clone)]
+pub struct WeakStopper {
+    inner: Weak<StopperInner>,
+    stop_when_dropped: bool,
+}
+
+impl Stopper {
+    /// Create a [`WeakStopper`] that observes this stopper without keeping
+    /// it alive.
+    ///
+    /// By default the weak handle reports [`StopReason::Cancelled`] once
+    /// every `Stopper` clone has been dropped.
+    #[inline]
+    pub fn downgrade(&self) -> WeakStopper {
+        WeakStopper {
+            inner: Arc::downgrade(&self.inner),
+            stop_when_dropped: true,
+        }
+    }
+}
+
+impl WeakStopper {
+    /// Choose what checks report once the stopper has been dropped:
+    /// [`StopReason::Cancelled`] if `stop` is `true` (the default), never
+    /// stopping if `false`.
+    ///
+    /// ```rust
+    /// use almost_enough::{Stop, Stopper};
+    ///
+    /// let stop = Stopper::new();
+    /// let weak = stop.downgrade().stop_when_dropped(false);
+    /// drop(stop);
+    /// assert!(weak.check().is_ok());
+    /// assert!(!weak.may_stop());
+    /// ```
+    #[inline]
+    pub fn stop_when_dropped(mut self, stop: bool) -> Self {
+        self.stop_when_dropped = stop;
+        self
+    }
+
+    /// A strong handle, or `None` if every `Stopper` clone has been dropped.
+    #[inline]
+    pub fn upgrade(&self) -> Option<Stopper> {
+        self.inner.upgrade().map(Stopper::from_inner)
+    }
+
+    /// Whether every `Stopper` clone has been dropped.
+    #[inline]
+    pub fn is_dropped(&self) -> bool {
+        self.inner.strong_count() == 0
+    }
+
+    fn dropped(&self) -> Result<(), StopReason> {
+        if self.stop_when_dropped {
+            Err(StopReason::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Stop for WeakStopper {
+    #[inline]
+    fn check(&self) -> Result<(), StopReason> {
+        match self.inner.upgrade() {
+            Some(inner) => inner.check(),
+            None => self.dropped(),
+        }
+    }
+
+    #[inline]
+    fn should_stop(&self) -> bool {
+        match self.inner.upgrade() {
+            Some(inner) => inner.should_stop(),
+            None => self.stop_when_dropped,
+        }
+    }
+
+    #[inline]
+    fn may_stop(&self) -> bool {
+        self.stop_when_dropped || !self.is_dropped()
+    }
+}
+
+impl core::fmt::Debug for WeakStopper {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("WeakStopper")
+            .field("dropped", &self.is_dropped())
+            .field("stop_when_dropped", &self.stop_when_dropped)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn follows_the_stopper() {
+        let stop = Stopper::new();
+        let weak = stop.downgrade();
+        assert!(weak.check().is_ok());
+        assert!(weak.may_stop());
+
+        stop.cancel();
+        assert_eq!(weak.check(), Err(StopReason::Cancelled));
+        assert!(weak.upgrade().unwrap().is_cancelled());
+    }
+
+    #[test]
+    fn does_not_keep_the_stopper_alive() {
+        let stop = Stopper::new();
+        let weak = stop.downgrade();
+        let never = weak.clone().stop_when_dropped(false);
+        assert!(!weak.is_dropped());
+        drop(stop);
+
+        assert!(weak.is_dropped());
+        assert!(weak.upgrade().is_none());
+        assert_eq!(weak.check(), Err(StopReason::Cancelled));
+        assert!(weak.should_stop());
+        assert!(weak.may_stop());
+
+        assert!(never.check().is_ok());
+        assert!(!never.should_stop());
+        assert!(!never.may_stop());
+        assert!(alloc::format!("{never:?}").contains("dropped: true"));
+    }
+}
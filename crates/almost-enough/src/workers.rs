@@ -0,0 +1,230 @@
+//! Waiting for a stopper's workers to finish after cancelling.
+//!
+//! `cancel()` only asks workers to stop; the owner does not learn when they
+//! actually did. Freeing a buffer or closing a connection the workers share
+//! right after cancelling races with workers that have not reached their
+//! next check. A worker that calls [`Stopper::worker()`] holds a
+//! [`WorkerGuard`] for as long as it runs, and
+//! [`Stopper::cancel_and_wait()`] cancels and then blocks until every guard
+//! has been dropped.
+//!
+//! For named workers and a report of stragglers, see
+//! [`ShutdownCoordinator`](crate::ShutdownCoordinator).
+//!
+//! # Example
+//!
+//! ```rust
+//! use almost_enough::{Stop, Stopper};
+//! use std::time::Duration;
+//!
+//! let stop = Stopper::new();
+//! for _ in 0..4 {
+//!     let guard = stop.worker().expect("not cancelled yet");
+//!     std::thread::spawn(move || {
+//!         while !guard.should_stop() {
+//!             std::thread::sleep(Duration::from_millis(1));
+//!         }
+//!         // guard dropped here: this worker no longer touches shared state
+//!     });
+//! }
+//!
+//! assert!(stop.cancel_and_wait(Duration::from_secs(5)));
+//! assert_eq!(stop.active_workers(), 0);
+//! // Safe to free what the workers were using
+//! ```
+
+use std::sync::{Condvar, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+use crate::{Stop, StopReason, Stopper};
+
+/// Count of live [`WorkerGuard`]s of one stopper.
+#[derive(Default)]
+pub(crate) struct Workers {
+    active: Mutex<usize>,
+    idle: Condvar,
+}
+
+impl Workers {
+    fn lock(&self) -> MutexGuard<'_, usize> {
+        self.active.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// A worker's registration with a [`Stopper`], from [`Stopper::worker()`].
+///
+/// The worker counts as running until the guard is dropped. The guard also
+/// holds a clone of the stopper and implements [`Stop`], so it can be the
+/// only thing a worker thread is given.
+///
+/// Requires the `std` feature.
+#[derive(Debug)]
+#[must_use = "the worker is deregistered as soon as the guard is dropped"]
+pub struct WorkerGuard {
+    stopper: Stopper,
+}
+
+impl WorkerGuard {
+    /// The stopper this worker is registered with.
+    #[inline]
+    pub fn stopper(&self) -> &Stopper {
+        &self.stopper
+    }
+}
+
+impl Stop for WorkerGuard {
+    #[inline]
+    fn check(&self) -> Result<(), StopReason> {
+        self.stopper.check()
+    }
+
+    #[inline]
+    fn should_stop(&self) -> bool {
+        self.stopper.should_stop()
+    }
+}
+
+impl Drop for WorkerGuard {
+    fn drop(&mut self) {
+        let workers = &self.stopper.inner.workers;
+        let mut active = workers.lock();
+        *active -= 1;
+        if *active == 0 {
+            workers.idle.notify_all();
+        }
+    }
+}
+
+impl Stopper {
+    /// Register the calling worker until the returned guard is dropped.
+    ///
+    /// Fails with the stop reason if the stopper is already cancelled: a
+    /// worker that starts after [`cancel_and_wait()`](Self::cancel_and_wait)
+    /// could otherwise run after it has returned.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use almost_enough::{StopReason, Stopper};
+    ///
+    /// let stop = Stopper::new();
+    /// let guard = stop.worker()?;
+    /// assert_eq!(stop.active_workers(), 1);
+    /// drop(guard);
+    ///
+    /// stop.cancel();
+    /// assert_eq!(stop.worker().unwrap_err(), StopReason::Cancelled);
+    /// # Ok::<(), StopReason>(())
+    /// ```
+    pub fn worker(&self) -> Result<WorkerGuard, StopReason> {
+        let mut active = self.inner.workers.lock();
+        // Checked under the lock `cancel_and_wait()` counts under, after
+        // it has cancelled, so no registration slips in between.
+        self.check()?;
+        *active += 1;
+        drop(active);
+        Ok(WorkerGuard {
+            stopper: self.clone(),
+        })
+    }
+
+    /// The number of live [`WorkerGuard`]s.
+    pub fn active_workers(&self) -> usize {
+        *self.inner.workers.lock()
+    }
+
+    /// Cancel, then block until every [`WorkerGuard`] has been dropped or
+    /// `timeout` elapses.
+    ///
+    /// Returns `true` if all workers finished, `false` on timeout. Once it
+    /// returns `true`, no worker is running and none can register again.
+    pub fn cancel_and_wait(&self, timeout: Duration) -> bool {
+        self.cancel();
+        let workers = &self.inner.workers;
+        let deadline = Instant::now().checked_add(timeout);
+        let mut active = workers.lock();
+        while *active != 0 {
+            active = match deadline {
+                None => workers
+                    .idle
+                    .wait(active)
+                    .unwrap_or_else(PoisonError::into_inner),
+                Some(deadline) => {
+                    let remaining = deadline.saturating_duration_since(Instant::now());
+                    if remaining.is_zero() {
+                        return false;
+                    }
+                    workers
+                        .idle
+                        .wait_timeout(active, remaining)
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .0
+                }
+            };
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    #[test]
+    fn waits_for_guards() {
+        let stop = Stopper::new();
+        let freed = Arc::new(AtomicBool::new(false));
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let guard = stop.worker().unwrap();
+                let freed = Arc::clone(&freed);
+                std::thread::spawn(move || {
+                    while !guard.should_stop() {
+                        std::thread::yield_now();
+                    }
+                    std::thread::sleep(Duration::from_millis(5));
+                    assert!(!freed.load(Ordering::SeqCst));
+                })
+            })
+            .collect();
+        assert_eq!(stop.active_workers(), 4);
+
+        assert!(stop.cancel_and_wait(Duration::from_secs(60)));
+        freed.store(true, Ordering::SeqCst);
+        for handle in handles {
+            handle.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn times_out_on_stuck_worker() {
+        let stop = Stopper::new();
+        let guard = stop.worker().unwrap();
+        assert!(!stop.cancel_and_wait(Duration::from_millis(10)));
+        assert!(guard.should_stop());
+        drop(guard);
+        assert!(stop.cancel_and_wait(Duration::MAX));
+    }
+
+    #[test]
+    fn no_workers_returns_immediately() {
+        let stop = Stopper::new();
+        assert!(stop.cancel_and_wait(Duration::ZERO));
+        assert!(stop.is_cancelled());
+    }
+
+    #[test]
+    fn refuses_registration_after_cancel() {
+        let stop = Stopper::new();
+        let guard = stop.worker().unwrap();
+        assert!(guard.check().is_ok());
+        assert_eq!(guard.stopper().active_workers(), 1);
+        stop.cancel();
+        assert_eq!(stop.worker().unwrap_err(), StopReason::Cancelled);
+        assert_eq!(guard.check(), Err(StopReason::Cancelled));
+        drop(guard);
+        assert_eq!(stop.active_workers(), 0);
+    }
+}
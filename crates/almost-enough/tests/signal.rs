@@ -0,0 +1,35 @@
+//! Delivers a real SIGTERM to the test process.
+//!
+//! This permanently cancels the process-wide `SignalStop`, so it runs in its
+//! own test binary rather than alongside tests that install it.
+
+#![cfg(all(feature = "signal", unix))]
+
+use almost_enough::{SignalStop, Stop, StopExt, StopReason};
+use std::time::{Duration, Instant};
+
+#[test]
+fn sigterm_cancels_and_propagates_to_children() {
+    let stop = SignalStop::install().unwrap();
+    let again = SignalStop::install().unwrap();
+    let child = stop.child();
+    assert!(!stop.should_stop());
+    assert!(!child.should_stop());
+
+    let status = std::process::Command::new("kill")
+        .arg("-TERM")
+        .arg(std::process::id().to_string())
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    // ctrlc dispatches from a helper thread
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while !stop.should_stop() && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    assert_eq!(stop.check(), Err(StopReason::Cancelled));
+    assert!(again.is_cancelled());
+    assert!(child.should_stop());
+    assert!(SignalStop::signal_count() >= 1);
+}
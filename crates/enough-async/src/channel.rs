@@ -0,0 +1,145 @@
+//! Stop-aware `async-channel` receives.
+//!
+//! [`recv_or_stopped()`] waits for the next message on an
+//! [`async_channel::Receiver`] or for an [`AsyncStop`] to be cancelled,
+//! whichever comes first:
+//!
+//! ```rust
+//! use enough_async::{AsyncStop, Recv, recv_or_stopped};
+//!
+//! async fn process_messages(stop: AsyncStop, rx: async_channel::Receiver<String>) {
+//!     loop {
+//!         match recv_or_stopped(&rx, &stop).await {
+//!             Recv::Msg(m) => println!("Got: {m}"),
+//!             Recv::Stopped(_) | Recv::Closed => break,
+//!             _ => {}
+//!         }
+//!     }
+//! }
+//! ```
+//!
+//! # Biasing
+//!
+//! The stop is polled before the channel on every wakeup. Once the stop is
+//! cancelled, `recv_or_stopped()` returns [`Recv::Stopped`] even if messages
+//! are still queued, and it never consumes a message it does not return.
+
+use core::future::{Future, poll_fn};
+use core::pin::pin;
+use core::task::Poll;
+
+use async_channel::Receiver;
+use enough::StopReason;
+
+use crate::AsyncStop;
+
+/// Outcome of [`recv_or_stopped()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Recv<T> {
+    /// A message was received.
+    Msg(T),
+    /// The stop was cancelled first.
+    Stopped(StopReason),
+    /// The channel is closed and empty.
+    Closed,
+}
+
+impl<T> Recv<T> {
+    /// The message, if one was received.
+    #[inline]
+    pub fn msg(self) -> Option<T> {
+        match self {
+            Recv::Msg(msg) => Some(msg),
+            _ => None,
+        }
+    }
+
+    /// Whether the stop was cancelled first.
+    #[inline]
+    pub fn is_stopped(&self) -> bool {
+        matches!(self, Recv::Stopped(_))
+    }
+}
+
+/// Receive the next message, or return early if `stop` is cancelled.
+///
+/// # Example
+///
+/// ```rust
+/// use enough_async::{AsyncStop, Recv, recv_or_stopped};
+///
+/// # futures_lite::future::block_on(async {
+/// let stop = AsyncStop::new();
+/// let (tx, rx) = async_channel::bounded(4);
+///
+/// tx.send(1).await.unwrap();
+/// assert_eq!(recv_or_stopped(&rx, &stop).await, Recv::Msg(1));
+///
+/// stop.cancel();
+/// tx.send(2).await.unwrap();
+/// assert!(recv_or_stopped(&rx, &stop).await.is_stopped());
+/// # });
+/// ```
+pub async fn recv_or_stopped<T>(rx: &Receiver<T>, stop: &AsyncStop) -> Recv<T> {
+    let mut cancelled = stop.cancelled();
+    let mut recv = pin!(rx.recv());
+    poll_fn(|cx| {
+        if core::pin::Pin::new(&mut cancelled).poll(cx).is_ready() {
+            return Poll::Ready(Recv::Stopped(StopReason::Cancelled));
+        }
+        recv.as_mut()
+            .poll(cx)
+            .map(|msg| msg.map_or(Recv::Closed, Recv::Msg))
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_lite::future::block_on;
+    use std::time::Duration;
+
+    #[test]
+    fn messages_then_closed() {
+        let stop = AsyncStop::new();
+        let (tx, rx) = async_channel::unbounded();
+        tx.try_send(1).unwrap();
+        tx.try_send(2).unwrap();
+        drop(tx);
+
+        block_on(async {
+            assert_eq!(recv_or_stopped(&rx, &stop).await, Recv::Msg(1));
+            assert_eq!(recv_or_stopped(&rx, &stop).await.msg(), Some(2));
+            assert_eq!(recv_or_stopped(&rx, &stop).await, Recv::Closed);
+        });
+    }
+
+    #[test]
+    fn stop_wins_over_queued_message() {
+        let stop = AsyncStop::new();
+        let (tx, rx) = async_channel::unbounded();
+        tx.try_send(1).unwrap();
+        stop.cancel();
+
+        let outcome = block_on(recv_or_stopped(&rx, &stop));
+        assert_eq!(outcome, Recv::Stopped(StopReason::Cancelled));
+        assert_eq!(rx.try_recv(), Ok(1));
+    }
+
+    #[test]
+    fn cancel_while_waiting() {
+        let stop = AsyncStop::new();
+        let (_tx, rx) = async_channel::unbounded::<u8>();
+        let canceller = {
+            let stop = stop.clone();
+            std::thread::spawn(move || {
+                std::thread::sleep(Duration::from_millis(10));
+                stop.cancel();
+            })
+        };
+        assert!(block_on(recv_or_stopped(&rx, &stop)).is_stopped());
+        canceller.join().unwrap();
+    }
+}
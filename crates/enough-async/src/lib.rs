@@ -0,0 +1,426 @@
+//! # enough-async
+//!
+//! A cancellation token for async-std, smol and other non-tokio runtimes,
+//! implementing the [`Stop`] trait.
+//!
+//! ## When to Use
+//!
+//! Use this crate when you have:
+//! - async-std or smol code that needs to cancel CPU-intensive work in
+//!   `spawn_blocking` / `unblock`
+//! - Libraries that accept `impl Stop` and you want the same ergonomics as
+//!   [`enough-tokio`](https://docs.rs/enough-tokio) without a tokio
+//!   dependency
+//!
+//! [`AsyncStop`] is built on [`event-listener`](https://docs.rs/event-listener),
+//! the notification primitive under async-std and smol, so
+//! [`cancelled()`](AsyncStop::cancelled) works on any executor.
+//!
+//! ## Complete Example
+//!
+//! ```rust,ignore
+//! use enough::Stop;
+//! use enough_async::AsyncStop;
+//! use std::time::Duration;
+//!
+//! fn main() {
+//!     smol::block_on(async {
+//!         let stop = AsyncStop::new();
+//!
+//!         // Spawn CPU-intensive work
+//!         let worker = smol::unblock({
+//!             let stop = stop.clone();
+//!             move || {
+//!                 for i in 0..1_000_000 {
+//!                     if i % 1000 == 0 && stop.should_stop() {
+//!                         return Err("cancelled");
+//!                     }
+//!                     // ... do work ...
+//!                 }
+//!                 Ok("done")
+//!             }
+//!         });
+//!
+//!         // Cancel after timeout
+//!         smol::Timer::after(Duration::from_millis(10)).await;
+//!         stop.cancel();
+//!
+//!         println!("{:?}", worker.await);
+//!     });
+//! }
+//! ```
+//!
+//! ## Quick Reference
+//!
+//! ```rust
+//! # use enough_async::AsyncStop;
+//! # use enough::Stop;
+//! let stop = AsyncStop::new();
+//!
+//! stop.should_stop();         // Check if cancelled (sync)
+//! stop.cancel();              // Trigger cancellation
+//! // stop.cancelled().await;  // Wait for cancellation (async)
+//! // stop.clone().await;      // Same, via IntoFuture
+//! stop.wait();                // Wait for cancellation (blocking)
+//! let child = stop.child();   // Create child token
+//! ```
+
+#![warn(missing_docs)]
+#![warn(clippy::all)]
+
+#[cfg(feature = "channel")]
+mod channel;
+
+#[cfg(feature = "channel")]
+pub use channel::{Recv, recv_or_stopped};
+
+use core::future::{Future, IntoFuture};
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError, Weak};
+use std::time::{Duration, Instant};
+
+use enough::{Stop, StopReason};
+use event_listener::{Event, EventListener, Listener};
+
+struct Inner {
+    cancelled: AtomicBool,
+    event: Event,
+    /// Children to cancel along with this token.
+    children: Mutex<Vec<Weak<Inner>>>,
+}
+
+impl Inner {
+    fn new() -> Self {
+        Self {
+            cancelled: AtomicBool::new(false),
+            event: Event::new(),
+            children: Mutex::new(Vec::new()),
+        }
+    }
+
+    fn cancel(&self) {
+        if self.cancelled.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        self.event.notify(usize::MAX);
+        let children =
+            core::mem::take(&mut *self.children.lock().unwrap_or_else(PoisonError::into_inner));
+        for child in children.iter().filter_map(Weak::upgrade) {
+            child.cancel();
+        }
+    }
+}
+
+/// A cloneable cancellation token that can be awaited on any executor.
+///
+/// All clones share one cancellation state. Cancelling a token cancels its
+/// [`child()`](Self::child) tokens too, but not the other way round.
+///
+/// # Example
+///
+/// ```rust
+/// use enough_async::AsyncStop;
+/// use enough::Stop;
+///
+/// let stop = AsyncStop::new();
+/// let worker_stop = stop.clone();
+///
+/// assert!(!worker_stop.should_stop());
+///
+/// stop.cancel();
+///
+/// assert!(worker_stop.should_stop());
+/// ```
+#[derive(Clone)]
+pub struct AsyncStop {
+    inner: Arc<Inner>,
+}
+
+impl AsyncStop {
+    /// Create a new, uncancelled token.
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(Inner::new()),
+        }
+    }
+
+    /// Cancel this token and all its children.
+    #[inline]
+    pub fn cancel(&self) {
+        self.inner.cancel();
+    }
+
+    /// Whether this token has been cancelled.
+    #[inline]
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Wait for cancellation.
+    ///
+    /// The returned future owns a clone of the token, so it can be moved
+    /// into a spawned task or raced with `futures_lite::future::or`.
+    #[inline]
+    pub fn cancelled(&self) -> Cancelled {
+        Cancelled {
+            stop: self.clone(),
+            listener: None,
+        }
+    }
+
+    /// Block the current thread until cancelled.
+    ///
+    /// Not available on wasm, where the main thread cannot block.
+    #[cfg(not(target_family = "wasm"))]
+    pub fn wait(&self) {
+        while !self.is_cancelled() {
+            let listener = self.inner.event.listen();
+            if self.is_cancelled() {
+                return;
+            }
+            listener.wait();
+        }
+    }
+
+    /// Block the current thread until cancelled or `timeout` elapses.
+    ///
+    /// Returns `true` if cancelled.
+    #[cfg(not(target_family = "wasm"))]
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        let Some(deadline) = Instant::now().checked_add(timeout) else {
+            self.wait();
+            return true;
+        };
+        while !self.is_cancelled() {
+            let listener = self.inner.event.listen();
+            if self.is_cancelled() {
+                break;
+            }
+            if listener.wait_deadline(deadline).is_none() {
+                return self.is_cancelled();
+            }
+        }
+        true
+    }
+
+    /// Create a child token that is cancelled when this one is.
+    ///
+    /// Cancelling the child does not affect this token.
+    pub fn child(&self) -> AsyncStop {
+        let child = AsyncStop::new();
+        {
+            let mut children = self
+                .inner
+                .children
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            children.retain(|weak| weak.strong_count() > 0);
+            children.push(Arc::downgrade(&child.inner));
+        }
+        // A cancel that drained the list before the push has already set
+        // the flag
+        if self.is_cancelled() {
+            child.cancel();
+        }
+        child
+    }
+}
+
+impl Default for AsyncStop {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Stop for AsyncStop {
+    #[inline]
+    fn check(&self) -> Result<(), StopReason> {
+        if self.is_cancelled() {
+            Err(StopReason::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+
+    #[inline]
+    fn should_stop(&self) -> bool {
+        self.is_cancelled()
+    }
+}
+
+impl IntoFuture for AsyncStop {
+    type Output = ();
+    type IntoFuture = Cancelled;
+
+    /// `stop.await` waits for cancellation, like `stop.cancelled().await`.
+    fn into_future(self) -> Cancelled {
+        Cancelled {
+            stop: self,
+            listener: None,
+        }
+    }
+}
+
+impl std::fmt::Debug for AsyncStop {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AsyncStop")
+            .field("cancelled", &self.is_cancelled())
+            .finish()
+    }
+}
+
+/// Future returned by [`AsyncStop::cancelled()`]; resolves once the token
+/// is cancelled.
+#[must_use = "futures do nothing unless polled"]
+pub struct Cancelled {
+    stop: AsyncStop,
+    listener: Option<EventListener>,
+}
+
+impl Future for Cancelled {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        loop {
+            if self.stop.is_cancelled() {
+                self.listener = None;
+                return Poll::Ready(());
+            }
+            match &mut self.listener {
+                // Register, then re-check the flag before sleeping
+                None => self.listener = Some(self.stop.inner.event.listen()),
+                Some(listener) => {
+                    if Pin::new(listener).poll(cx).is_pending() {
+                        return Poll::Pending;
+                    }
+                    self.listener = None;
+                }
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for Cancelled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Cancelled")
+            .field("stop", &self.stop)
+            .field("listening", &self.listener.is_some())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_lite::future::{self, block_on};
+
+    #[test]
+    fn async_stop_reflects_cancel() {
+        let stop = AsyncStop::new();
+        assert!(!stop.should_stop());
+        assert!(stop.check().is_ok());
+        assert!(stop.may_stop());
+
+        stop.cancel();
+
+        assert!(stop.should_stop());
+        assert_eq!(stop.check(), Err(StopReason::Cancelled));
+    }
+
+    #[test]
+    fn child_follows_parent_only() {
+        let parent = AsyncStop::new();
+        let child = parent.child();
+        let grandchild = child.child();
+
+        child.cancel();
+        assert!(!parent.should_stop());
+        assert!(grandchild.should_stop());
+
+        let sibling = parent.child();
+        parent.cancel();
+        assert!(sibling.should_stop());
+        assert!(parent.child().should_stop());
+    }
+
+    #[test]
+    fn dropped_children_are_pruned() {
+        let parent = AsyncStop::new();
+        for _ in 0..100 {
+            drop(parent.child());
+        }
+        let _live = parent.child();
+        assert_eq!(parent.inner.children.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn cancelled_wakes_across_threads() {
+        let stop = AsyncStop::new();
+        let canceller = {
+            let stop = stop.clone();
+            std::thread::spawn(move || {
+                std::thread::sleep(Duration::from_millis(10));
+                stop.cancel();
+            })
+        };
+        block_on(stop.cancelled());
+        assert!(stop.is_cancelled());
+        canceller.join().unwrap();
+    }
+
+    #[test]
+    fn child_cancelled_wakes_on_parent_cancel() {
+        let parent = AsyncStop::new();
+        let child = parent.child();
+        let waiter = std::thread::spawn(move || block_on(child.into_future()));
+        parent.cancel();
+        waiter.join().unwrap();
+    }
+
+    #[test]
+    fn cancelled_races_with_work() {
+        let stop = AsyncStop::new();
+        let raced = block_on(future::or(
+            async {
+                stop.cancelled().await;
+                "cancelled"
+            },
+            async {
+                stop.cancel();
+                future::pending::<&str>().await
+            },
+        ));
+        assert_eq!(raced, "cancelled");
+    }
+
+    #[test]
+    fn blocking_wait() {
+        let stop = AsyncStop::new();
+        assert!(!stop.wait_timeout(Duration::from_millis(1)));
+
+        let canceller = {
+            let stop = stop.clone();
+            std::thread::spawn(move || stop.cancel())
+        };
+        stop.wait();
+        assert!(stop.wait_timeout(Duration::MAX));
+        canceller.join().unwrap();
+    }
+
+    #[test]
+    fn debug_and_default() {
+        let stop = AsyncStop::default();
+        assert_eq!(format!("{stop:?}"), "AsyncStop { cancelled: false }");
+        assert!(format!("{:?}", stop.cancelled()).contains("listening: false"));
+    }
+
+    #[test]
+    fn async_stop_is_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<AsyncStop>();
+        assert_send_sync::<Cancelled>();
+    }
+}
@@ -0,0 +1,219 @@
+//! # enough-derive
+//!
+//! `#[derive(FromStopReason)]` for error enums that wrap a
+//! [`StopReason`](https://docs.rs/enough/latest/enough/enum.StopReason.html).
+//!
+//! Every crate that accepts a `Stop` writes the same boilerplate: a variant
+//! holding the `StopReason`, a `From<StopReason>` impl so `stop.check()?`
+//! works, and `is_cancelled()` / `is_timed_out()` helpers. The derive
+//! generates all of it.
+//!
+//! ## Generated Items
+//!
+//! | Item | Returns |
+//! |------|---------|
+//! | `From<StopReason>` | The stop variant |
+//! | `stop_reason()` | `Some(reason)` for the stop variant, else `None` |
+//! | `is_stopped()` | Whether this is the stop variant |
+//! | `is_cancelled()` | Stop variant with `StopReason::Cancelled` |
+//! | `is_timed_out()` | Stop variant with `StopReason::TimedOut` |
+//!
+//! ## The Stop Variant
+//!
+//! The stop variant is the one variant with a single field, tuple or named,
+//! whose type is named `StopReason`. If several variants qualify, or the
+//! type is behind an alias, mark the variant with `#[stop_reason]`.
+//!
+//! The field type is used as written, so a `StopReason` imported from
+//! `almost-enough` works without a direct dependency on `enough`.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use enough::{Stop, StopReason, Unstoppable};
+//! use enough_derive::FromStopReason;
+//!
+//! #[derive(Debug, FromStopReason)]
+//! pub enum DecodeError {
+//!     Stopped(StopReason),
+//!     InvalidData(&'static str),
+//! }
+//!
+//! fn decode(data: &[u8], stop: impl Stop) -> Result<Vec<u8>, DecodeError> {
+//!     for _chunk in data.chunks(1024) {
+//!         stop.check()?; // From<StopReason> for DecodeError
+//!     }
+//!     Ok(data.to_vec())
+//! }
+//!
+//! assert!(decode(b"abc", Unstoppable).is_ok());
+//!
+//! let err = DecodeError::from(StopReason::Cancelled);
+//! assert!(err.is_cancelled() && err.is_stopped());
+//! assert_eq!(err.stop_reason(), Some(StopReason::Cancelled));
+//! assert!(!DecodeError::InvalidData("bad header").is_stopped());
+//! ```
+//!
+//! A named field and an explicit marker:
+//!
+//! ```rust
+//! use enough::StopReason;
+//! use enough_derive::FromStopReason;
+//!
+//! type Reason = StopReason;
+//!
+//! #[derive(FromStopReason)]
+//! enum JobError<E> {
+//!     #[stop_reason]
+//!     Stopped { reason: Reason },
+//!     Inner(E),
+//! }
+//!
+//! let err: JobError<std::io::Error> = StopReason::TimedOut.into();
+//! assert!(err.is_timed_out());
+//! ```
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::spanned::Spanned;
+use syn::{Data, DeriveInput, Error, Fields, Type, Variant, parse_macro_input};
+
+/// Derive `From<StopReason>` and stop-reason helpers for an error enum.
+///
+/// See the [crate docs](crate) for the generated items and how the stop
+/// variant is chosen.
+#[proc_macro_derive(FromStopReason, attributes(stop_reason))]
+pub fn derive_from_stop_reason(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let Data::Enum(data) = &input.data else {
+        return Err(Error::new(
+            input.ident.span(),
+            "FromStopReason can only be derived for enums",
+        ));
+    };
+
+    let marked: Vec<&Variant> = data
+        .variants
+        .iter()
+        .filter(|v| v.attrs.iter().any(|a| a.path().is_ident("stop_reason")))
+        .collect();
+    let variant = match marked.as_slice() {
+        [variant] => *variant,
+        [_, second, ..] => {
+            return Err(Error::new(
+                second.ident.span(),
+                "only one variant can be marked #[stop_reason]",
+            ));
+        }
+        [] => {
+            let candidates: Vec<&Variant> = data
+                .variants
+                .iter()
+                .filter(|v| single_field(v).is_some_and(is_stop_reason))
+                .collect();
+            match candidates.as_slice() {
+                [variant] => *variant,
+                [] => {
+                    return Err(Error::new(
+                        input.ident.span(),
+                        "no variant holds a single StopReason; \
+                         mark the stop variant with #[stop_reason]",
+                    ));
+                }
+                [_, second, ..] => {
+                    return Err(Error::new(
+                        second.ident.span(),
+                        "several variants hold a StopReason; \
+                         mark the stop variant with #[stop_reason]",
+                    ));
+                }
+            }
+        }
+    };
+    let Some(ty) = single_field(variant) else {
+        return Err(Error::new(
+            variant.span(),
+            "the #[stop_reason] variant must have exactly one field",
+        ));
+    };
+
+    let name = &input.ident;
+    let ident = &variant.ident;
+    // Both the constructor in `from()` and the pattern in `stop_reason()`
+    let stopped = match &variant.fields {
+        Fields::Named(fields) => {
+            let field = fields.named[0].ident.as_ref().expect("named field");
+            quote!(Self::#ident { #field: reason })
+        }
+        _ => quote!(Self::#ident(reason)),
+    };
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::core::convert::From<#ty> for #name #ty_generics #where_clause {
+            #[inline]
+            fn from(reason: #ty) -> Self {
+                #stopped
+            }
+        }
+
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// The stop reason, if this is the stop variant.
+            #[inline]
+            pub fn stop_reason(&self) -> ::core::option::Option<#ty> {
+                match self {
+                    #stopped => ::core::option::Option::Some(*reason),
+                    #[allow(unreachable_patterns)]
+                    _ => ::core::option::Option::None,
+                }
+            }
+
+            /// Whether this is the stop variant.
+            #[inline]
+            pub fn is_stopped(&self) -> bool {
+                self.stop_reason().is_some()
+            }
+
+            /// Whether this is the stop variant with `StopReason::Cancelled`.
+            #[inline]
+            pub fn is_cancelled(&self) -> bool {
+                self.stop_reason().is_some_and(|reason| reason.is_cancelled())
+            }
+
+            /// Whether this is the stop variant with `StopReason::TimedOut`.
+            #[inline]
+            pub fn is_timed_out(&self) -> bool {
+                self.stop_reason().is_some_and(|reason| reason.is_timed_out())
+            }
+        }
+    })
+}
+
+/// The type of a variant's only field, if it has exactly one.
+fn single_field(variant: &Variant) -> Option<&Type> {
+    let mut fields = variant.fields.iter();
+    match (fields.next(), fields.next()) {
+        (Some(field), None) => Some(&field.ty),
+        _ => None,
+    }
+}
+
+/// Whether `ty` is a path ending in `StopReason`.
+fn is_stop_reason(ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "StopReason"),
+        Type::Group(group) => is_stop_reason(&group.elem),
+        _ => false,
+    }
+}
@@ -0,0 +1,200 @@
+/* enough-ffi: C API for cooperative cancellation. Generated by cbindgen; do not edit. */
+
+#ifndef ENOUGH_H
+#define ENOUGH_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+#if defined(ENOUGH_HANDLE_TABLE)
+// A handle value that is never valid; returned when creation fails.
+#define ENOUGH_INVALID_HANDLE 0
+#endif
+
+#if defined(ENOUGH_VTABLE)
+// The [`EnoughTokenVTable::version`] of this crate's table.
+#define ENOUGH_TOKEN_VTABLE_VERSION 2
+#endif
+
+// A token's state as a C enum, returned by [`enough_token_check`].
+typedef enum EnoughStopReason {
+  // Not stopped.
+  ENOUGH_STOP_REASON_RUNNING = 0,
+  // Explicitly cancelled, directly or through an ancestor.
+  ENOUGH_STOP_REASON_CANCELLED = 1,
+  // The token's deadline passed.
+  ENOUGH_STOP_REASON_TIMED_OUT = 2,
+  // The check itself failed.
+  ENOUGH_STOP_REASON_FAILED = 3,
+} EnoughStopReason;
+
+// Handle for a callback registered with [`enough_token_register_callback`].
+typedef struct FfiCallbackRegistration FfiCallbackRegistration;
+
+// FFI-safe cancellation source.
+typedef struct FfiCancellationSource FfiCancellationSource;
+
+// FFI-safe cancellation token.
+typedef struct FfiCancellationToken FfiCancellationToken;
+
+#if defined(ENOUGH_VTABLE)
+// Token operations as function pointers; see [`enough_token_vtable()`].
+typedef struct EnoughTokenVTable {
+  // `sizeof(EnoughTokenVTable)` for the layout this table was built with.
+  size_t size;
+  // [`ENOUGH_TOKEN_VTABLE_VERSION`] when this table was built.
+  uint32_t version;
+  // [`enough_token_is_cancelled`](crate::enough_token_is_cancelled).
+  bool (*is_cancelled)(const FfiCancellationToken*);
+  // [`enough_token_register_callback`](crate::enough_token_register_callback).
+  FfiCallbackRegistration *(*register_callback)(const FfiCancellationToken*, void (*)(void*), void*);
+  // [`enough_callback_unregister`](crate::enough_callback_unregister).
+  bool (*unregister)(FfiCallbackRegistration*);
+  // [`enough_token_destroy`](crate::enough_token_destroy).
+  void (*destroy)(FfiCancellationToken*);
+  // [`enough_token_check`](crate::enough_token_check). Version 2.
+  EnoughStopReason (*check)(const FfiCancellationToken*);
+} EnoughTokenVTable;
+#endif
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Create a new cancellation source.
+FfiCancellationSource *enough_cancellation_create(void);
+
+// Cancel a cancellation source.
+void enough_cancellation_cancel(const FfiCancellationSource *ptr);
+
+// Cancel a cancellation source, returning true if this call cancelled it.
+bool enough_cancellation_cancel_was_first(const FfiCancellationSource *ptr);
+
+// Check if a cancellation source is cancelled.
+bool enough_cancellation_is_cancelled(const FfiCancellationSource *ptr);
+
+// Check if a source was poisoned by a panic inside this library.
+bool enough_cancellation_is_poisoned(const FfiCancellationSource *ptr);
+
+// Destroy a cancellation source.
+void enough_cancellation_destroy(FfiCancellationSource *ptr);
+
+// Create a child cancellation source.
+FfiCancellationSource *enough_child_create(const FfiCancellationSource *parent);
+
+// Cancel a child source and all of its descendants.
+void enough_child_cancel(const FfiCancellationSource *child);
+
+// Check if a child source is cancelled, either directly or via an ancestor.
+bool enough_child_is_cancelled(const FfiCancellationSource *child);
+
+// Destroy a child source.
+void enough_child_destroy(FfiCancellationSource *child);
+
+// Create a token from a cancellation source.
+FfiCancellationToken *enough_token_create(const FfiCancellationSource *source);
+
+// Create a "never cancelled" token.
+FfiCancellationToken *enough_token_create_never(void);
+
+// Create a token from a cancellation source with a timeout.
+FfiCancellationToken *enough_token_create_with_timeout_ms(const FfiCancellationSource *source,
+                                                          uint64_t timeout_ms);
+
+// Derive a token with a timeout from an existing token.
+FfiCancellationToken *enough_token_with_timeout_ms(const FfiCancellationToken *token,
+                                                   uint64_t timeout_ms);
+
+// Get the milliseconds remaining until a token's deadline.
+uint64_t enough_token_remaining_ms(const FfiCancellationToken *token);
+
+// Check if a token is cancelled.
+bool enough_token_is_cancelled(const FfiCancellationToken *token);
+
+// Check a token and report why it stopped.
+EnoughStopReason enough_token_check(const FfiCancellationToken *token);
+
+// Destroy a token.
+void enough_token_destroy(FfiCancellationToken *token);
+
+// Register a callback invoked once when a token's source is cancelled.
+FfiCallbackRegistration *enough_token_register_callback(const FfiCancellationToken *token,
+                                                        void (*callback)(void*),
+                                                        void *context);
+
+// Register a callback with an explicit priority.
+FfiCallbackRegistration *enough_token_register_callback_with_priority(const FfiCancellationToken *token,
+                                                                      void (*callback)(void*),
+                                                                      void *context,
+                                                                      int32_t priority);
+
+// Unregister a callback and release its handle.
+bool enough_callback_unregister(FfiCallbackRegistration *registration);
+
+// Describe the last failure on the calling thread.
+const char *enough_last_error(void);
+
+// Clear the calling thread's last error.
+void enough_clear_last_error(void);
+
+#if defined(ENOUGH_HANDLE_TABLE)
+// Create a cancellation source and return its handle.
+uint64_t enough_handle_create(void);
+#endif
+
+#if defined(ENOUGH_HANDLE_TABLE)
+// Create a child source of `parent` and return its handle.
+uint64_t enough_handle_create_child(uint64_t parent);
+#endif
+
+#if defined(ENOUGH_HANDLE_TABLE)
+// Check whether `handle` refers to a live source.
+bool enough_handle_is_valid(uint64_t handle);
+#endif
+
+#if defined(ENOUGH_HANDLE_TABLE)
+// Cancel the source behind `handle`, and its descendants.
+bool enough_handle_cancel(uint64_t handle);
+#endif
+
+#if defined(ENOUGH_HANDLE_TABLE)
+// Cancel the source behind `handle`, returning true if this call
+// cancelled it.
+bool enough_handle_cancel_was_first(uint64_t handle);
+#endif
+
+#if defined(ENOUGH_HANDLE_TABLE)
+// Check if the source behind `handle` (or an ancestor) is cancelled.
+bool enough_handle_is_cancelled(uint64_t handle);
+#endif
+
+#if defined(ENOUGH_HANDLE_TABLE)
+// Check if the source behind `handle` was poisoned by a panic.
+bool enough_handle_is_poisoned(uint64_t handle);
+#endif
+
+#if defined(ENOUGH_HANDLE_TABLE)
+// Destroy a handle.
+bool enough_handle_destroy(uint64_t handle);
+#endif
+
+#if defined(ENOUGH_VTABLE)
+// Get the token vtable.
+const EnoughTokenVTable *enough_token_vtable(void);
+#endif
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#if defined(ENOUGH_VTABLE)
+// True if `table` (a `const EnoughTokenVTable*` from any version of this
+// library) is large enough to contain `field`.
+#define ENOUGH_TOKEN_VTABLE_HAS(table, field) \
+  ((table)->size >= offsetof(EnoughTokenVTable, field) + sizeof((table)->field))
+#endif
+
+#endif  /* ENOUGH_H */
@@ -0,0 +1,432 @@
+//! Handle-based API backed by a global table.
+//!
+//! The pointer API hands out raw `Box` pointers, so a managed host that
+//! destroys a source twice, or uses it after destroying it, triggers
+//! undefined behavior. The functions here hand out `u64` handles instead.
+//! Every lookup is validated against a global slab: an unknown, destroyed
+//! or forged handle is rejected with a "not valid" result, never UB.
+//!
+//! A handle packs a slot index with the slot's generation, which changes
+//! each time the slot is freed, so a stale handle is not confused with
+//! whatever source reuses its slot. `0` ([`ENOUGH_INVALID_HANDLE`]) is never
+//! a valid handle.
+//!
+//! Requires the `handle-table` feature.
+
+use std::sync::{PoisonError, RwLock};
+
+use crate::shared::Shared;
+use crate::{
+    CancellationState, FfiCancellationToken, OUT_OF_MEMORY, catch, catch_on, set_last_error,
+};
+
+/// A handle value that is never valid; returned when creation fails.
+pub const ENOUGH_INVALID_HANDLE: u64 = 0;
+
+struct Slot {
+    /// Bumped when the slot is freed; never 0.
+    generation: u32,
+    state: Option<Shared<CancellationState>>,
+}
+
+struct Table {
+    slots: Vec<Slot>,
+    free: Vec<u32>,
+}
+
+static TABLE: RwLock<Table> = RwLock::new(Table {
+    slots: Vec::new(),
+    free: Vec::new(),
+});
+
+#[inline]
+fn encode(index: u32, generation: u32) -> u64 {
+    (u64::from(generation) << 32) | u64::from(index)
+}
+
+#[inline]
+fn decode(handle: u64) -> (usize, u32) {
+    (
+        (handle & u64::from(u32::MAX)) as usize,
+        (handle >> 32) as u32,
+    )
+}
+
+impl Table {
+    fn insert(&mut self, state: Shared<CancellationState>) -> u64 {
+        if let Some(index) = self.free.pop() {
+            let slot = &mut self.slots[index as usize];
+            slot.state = Some(state);
+            return encode(index, slot.generation);
+        }
+        let Ok(index) = u32::try_from(self.slots.len()) else {
+            set_last_error(c"handle table is full");
+            return ENOUGH_INVALID_HANDLE;
+        };
+        // `free` must be able to take the index back without allocating
+        if self.slots.try_reserve(1).is_err()
+            || self.free.try_reserve(self.slots.len() + 1).is_err()
+        {
+            set_last_error(OUT_OF_MEMORY);
+            return ENOUGH_INVALID_HANDLE;
+        }
+        self.slots.push(Slot {
+            generation: 1,
+            state: Some(state),
+        });
+        encode(index, 1)
+    }
+
+    fn get(&self, handle: u64) -> Option<&Shared<CancellationState>> {
+        let (index, generation) = decode(handle);
+        let slot = self.slots.get(index)?;
+        if slot.generation != generation {
+            return None;
+        }
+        slot.state.as_ref()
+    }
+
+    fn remove(&mut self, handle: u64) -> Option<Shared<CancellationState>> {
+        let (index, generation) = decode(handle);
+        let slot = self.slots.get_mut(index)?;
+        if slot.generation != generation {
+            return None;
+        }
+        let state = slot.state.take()?;
+        slot.generation = slot.generation.wrapping_add(1).max(1);
+        self.free.push(index as u32);
+        Some(state)
+    }
+}
+
+/// Look up a handle, releasing the table lock before returning.
+///
+/// Cancelling runs registered callbacks, which may call back into this
+/// module, so no operation holds the lock while touching the state.
+fn lookup(handle: u64) -> Option<Shared<CancellationState>> {
+    let table = TABLE.read().unwrap_or_else(PoisonError::into_inner);
+    table.get(handle).cloned()
+}
+
+/// [`lookup`] for a function that requires a valid handle, logging misuse.
+fn resolve(handle: u64) -> Option<Shared<CancellationState>> {
+    let state = lookup(handle);
+    if state.is_none() {
+        invalid(handle);
+    }
+    state
+}
+
+/// Report an invalid handle passed by the host.
+#[cold]
+#[cfg_attr(not(feature = "log"), allow(unused_variables))]
+fn invalid(handle: u64) {
+    #[cfg(feature = "log")]
+    log::warn!("invalid handle: handle={handle:#x}");
+}
+
+fn insert(state: CancellationState) -> u64 {
+    let Ok(state) = Shared::try_new(state) else {
+        set_last_error(OUT_OF_MEMORY);
+        return ENOUGH_INVALID_HANDLE;
+    };
+    let mut table = TABLE.write().unwrap_or_else(PoisonError::into_inner);
+    table.insert(state)
+}
+
+impl FfiCancellationToken {
+    /// Create a token sharing the cancellation state of a table handle.
+    ///
+    /// Returns `None` if `handle` is not valid. Like tokens created from a
+    /// source pointer, the token stays usable after the handle is destroyed
+    /// (it then never becomes cancelled). Look the handle up once and poll
+    /// the token, rather than going through the table on every check.
+    ///
+    /// Requires the `handle-table` feature.
+    pub fn from_handle(handle: u64) -> Option<Self> {
+        lookup(handle).map(|state| Self {
+            inner: Some(state),
+            deadline: None,
+        })
+    }
+}
+
+/// Create a cancellation source and return its handle.
+///
+/// Must be released with [`enough_handle_destroy`]. Returns
+/// [`ENOUGH_INVALID_HANDLE`] if the table is full or cannot grow; see
+/// [`crate::enough_last_error`].
+#[unsafe(no_mangle)]
+pub extern "C" fn enough_handle_create() -> u64 {
+    catch(ENOUGH_INVALID_HANDLE, || insert(CancellationState::new()))
+}
+
+/// Create a child source of `parent` and return its handle.
+///
+/// The child is cancelled with its parent (see [`crate::enough_child_create`]).
+/// Unlike the pointer API, an invalid `parent` is an error: returns
+/// [`ENOUGH_INVALID_HANDLE`] rather than creating a root source.
+#[unsafe(no_mangle)]
+pub extern "C" fn enough_handle_create_child(parent: u64) -> u64 {
+    catch(ENOUGH_INVALID_HANDLE, || match resolve(parent) {
+        Some(parent) => catch_on(Some(&parent), ENOUGH_INVALID_HANDLE, || {
+            insert(CancellationState::with_parent(parent.clone()))
+        }),
+        None => ENOUGH_INVALID_HANDLE,
+    })
+}
+
+/// Check whether `handle` refers to a live source.
+#[unsafe(no_mangle)]
+pub extern "C" fn enough_handle_is_valid(handle: u64) -> bool {
+    catch(false, || lookup(handle).is_some())
+}
+
+/// Cancel the source behind `handle`, and its descendants.
+///
+/// Returns false if `handle` is not valid.
+#[unsafe(no_mangle)]
+pub extern "C" fn enough_handle_cancel(handle: u64) -> bool {
+    catch(false, || match resolve(handle) {
+        Some(state) => catch_on(Some(&state), false, || {
+            // Callback panics cannot cross the C ABI; see
+            // `enough_cancellation_cancel`
+            let _ = state.cancel();
+            true
+        }),
+        None => false,
+    })
+}
+
+/// Cancel the source behind `handle`, returning true if this call
+/// cancelled it.
+///
+/// See [`crate::enough_cancellation_cancel_was_first`]. Returns false if
+/// `handle` is not valid or the source was already cancelled.
+#[unsafe(no_mangle)]
+pub extern "C" fn enough_handle_cancel_was_first(handle: u64) -> bool {
+    catch(false, || {
+        resolve(handle).is_some_and(|state| catch_on(Some(&state), false, || state.cancel().first))
+    })
+}
+
+/// Check if the source behind `handle` (or an ancestor) is cancelled.
+///
+/// Returns false if `handle` is not valid.
+#[unsafe(no_mangle)]
+pub extern "C" fn enough_handle_is_cancelled(handle: u64) -> bool {
+    catch(false, || {
+        resolve(handle).is_some_and(|state| catch_on(Some(&state), false, || state.is_cancelled()))
+    })
+}
+
+/// Check if the source behind `handle` was poisoned by a panic.
+///
+/// See [`crate::enough_cancellation_is_poisoned`]: a poisoned source should
+/// be destroyed and replaced. Returns false if `handle` is not valid.
+#[unsafe(no_mangle)]
+pub extern "C" fn enough_handle_is_poisoned(handle: u64) -> bool {
+    catch(false, || {
+        resolve(handle).is_some_and(|state| state.is_poisoned())
+    })
+}
+
+/// Destroy a handle.
+///
+/// Returns false if `handle` is not valid, including when it was already
+/// destroyed. Tokens and children created from it remain valid.
+#[unsafe(no_mangle)]
+pub extern "C" fn enough_handle_destroy(handle: u64) -> bool {
+    catch(false, || {
+        let state = {
+            let mut table = TABLE.write().unwrap_or_else(PoisonError::into_inner);
+            table.remove(handle)
+        };
+        // Dropped outside the lock
+        if state.is_none() {
+            invalid(handle);
+        }
+        state.is_some()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use enough::{Stop, StopReason};
+
+    #[test]
+    fn lifecycle() {
+        let handle = enough_handle_create();
+        assert_ne!(handle, ENOUGH_INVALID_HANDLE);
+        assert!(enough_handle_is_valid(handle));
+        assert!(!enough_handle_is_cancelled(handle));
+
+        assert!(enough_handle_cancel(handle));
+        assert!(enough_handle_is_cancelled(handle));
+
+        assert!(enough_handle_destroy(handle));
+        assert!(!enough_handle_is_valid(handle));
+    }
+
+    #[test]
+    fn double_destroy_is_rejected() {
+        let handle = enough_handle_create();
+        assert!(enough_handle_destroy(handle));
+        assert!(!enough_handle_destroy(handle));
+        assert!(!enough_handle_cancel(handle));
+        assert!(!enough_handle_cancel_was_first(handle));
+        assert!(!enough_handle_is_cancelled(handle));
+    }
+
+    #[test]
+    fn cancel_was_first_once() {
+        let handle = enough_handle_create();
+        assert!(enough_handle_cancel_was_first(handle));
+        assert!(!enough_handle_cancel_was_first(handle));
+        assert!(enough_handle_cancel(handle));
+        assert!(enough_handle_destroy(handle));
+    }
+
+    #[test]
+    fn stale_handle_does_not_alias_reused_slot() {
+        let mut table = Table {
+            slots: Vec::new(),
+            free: Vec::new(),
+        };
+        let stale = table.insert(Shared::new(CancellationState::new()));
+        assert!(table.remove(stale).is_some());
+
+        let reused = table.insert(Shared::new(CancellationState::new()));
+        assert_eq!(decode(reused).0, decode(stale).0);
+        assert_ne!(reused, stale);
+        assert!(table.get(stale).is_none());
+        assert!(table.remove(stale).is_none());
+        assert!(table.get(reused).is_some());
+    }
+
+    #[test]
+    fn invalid_and_forged_handles() {
+        assert!(!enough_handle_is_valid(ENOUGH_INVALID_HANDLE));
+        assert!(!enough_handle_cancel(ENOUGH_INVALID_HANDLE));
+        assert!(!enough_handle_destroy(u64::MAX));
+        assert!(!enough_handle_is_cancelled(encode(u32::MAX, 1)));
+        assert_eq!(
+            enough_handle_create_child(ENOUGH_INVALID_HANDLE),
+            ENOUGH_INVALID_HANDLE
+        );
+        assert!(FfiCancellationToken::from_handle(u64::MAX).is_none());
+    }
+
+    #[test]
+    fn child_follows_parent() {
+        let parent = enough_handle_create();
+        let child = enough_handle_create_child(parent);
+        assert_ne!(child, ENOUGH_INVALID_HANDLE);
+
+        assert!(enough_handle_cancel(child));
+        assert!(!enough_handle_is_cancelled(parent));
+
+        let sibling = enough_handle_create_child(parent);
+        assert!(enough_handle_cancel(parent));
+        assert!(enough_handle_is_cancelled(sibling));
+
+        for handle in [sibling, child, parent] {
+            assert!(enough_handle_destroy(handle));
+        }
+    }
+
+    #[test]
+    fn token_outlives_handle() {
+        let handle = enough_handle_create();
+        let token = FfiCancellationToken::from_handle(handle).unwrap();
+        assert!(token.check().is_ok());
+
+        assert!(enough_handle_cancel(handle));
+        assert_eq!(token.check(), Err(StopReason::Cancelled));
+
+        assert!(enough_handle_destroy(handle));
+        assert!(token.should_stop());
+    }
+
+    #[test]
+    fn panic_poisons_handle() {
+        let handle = enough_handle_create();
+        let other = enough_handle_create();
+        assert!(!enough_handle_is_poisoned(handle));
+
+        // Panic inside the guard around the state, not the table lookup
+        crate::PANIC_IN_GUARD.with(|p| p.set(2));
+        assert!(!enough_handle_cancel(handle));
+        assert!(enough_handle_is_poisoned(handle));
+        assert!(!enough_handle_is_poisoned(other));
+
+        let child = enough_handle_create_child(handle);
+        assert!(enough_handle_is_poisoned(child));
+
+        // A panic outside any state poisons nothing
+        crate::PANIC_IN_GUARD.with(|p| p.set(1));
+        assert!(!enough_handle_cancel(other));
+        assert!(!enough_handle_is_poisoned(other));
+
+        for h in [handle, other, child] {
+            assert!(enough_handle_destroy(h));
+        }
+        assert!(!enough_handle_is_poisoned(handle));
+        crate::enough_clear_last_error();
+    }
+
+    #[test]
+    fn concurrent_create_destroy() {
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                std::thread::spawn(|| {
+                    for _ in 0..200 {
+                        let handle = enough_handle_create();
+                        assert!(enough_handle_cancel(handle));
+                        assert!(enough_handle_destroy(handle));
+                        assert!(!enough_handle_destroy(handle));
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+    }
+
+    #[cfg(feature = "log")]
+    #[test]
+    fn invalid_handles_are_logged() {
+        use std::sync::Mutex;
+
+        struct Recorder(Mutex<Vec<String>>);
+
+        impl log::Log for Recorder {
+            fn enabled(&self, _: &log::Metadata<'_>) -> bool {
+                true
+            }
+
+            fn log(&self, record: &log::Record<'_>) {
+                if record.level() == log::Level::Warn {
+                    self.0.lock().unwrap().push(record.args().to_string());
+                }
+            }
+
+            fn flush(&self) {}
+        }
+
+        static RECORDER: Recorder = Recorder(Mutex::new(Vec::new()));
+        log::set_logger(&RECORDER).unwrap();
+        log::set_max_level(log::LevelFilter::Warn);
+
+        let handle = enough_handle_create();
+        assert!(enough_handle_destroy(handle));
+        assert!(!enough_handle_is_valid(handle));
+        assert!(!enough_handle_destroy(handle));
+        let message = format!("invalid handle: handle={handle:#x}");
+        // Other tests may log concurrently; is_valid() logs nothing
+        let logged = RECORDER.0.lock().unwrap();
+        assert_eq!(logged.iter().filter(|m| **m == message).count(), 1);
+    }
+}
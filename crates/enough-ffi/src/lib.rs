@@ -9,11 +9,19 @@
 //!
 //! This crate uses reference counting internally to prevent use-after-free:
 //!
-//! - Sources and tokens use `Arc` internally
+//! - Sources and tokens share reference-counted state
 //! - Destroying a source while tokens exist is safe - tokens remain valid
 //!   but can never become cancelled (since no one can call cancel anymore)
 //! - Each token must be explicitly destroyed when no longer needed
 //!
+//! ## C Header
+//!
+//! `include/enough.h` in the crate declares every exported function and
+//! the [`EnoughStopReason`] enum. It is generated by cbindgen with the
+//! crate's `cbindgen.toml` (`just ffi-header` in the repository), and a
+//! test checks that it covers every export. The `handle-table` and
+//! `vtable` items are guarded by `ENOUGH_HANDLE_TABLE` and `ENOUGH_VTABLE`.
+//!
 //! ## C# Integration Example
 //!
 //! ```csharp
@@ -56,6 +64,118 @@
 //! }
 //! ```
 //!
+//! ## Child Sources
+//!
+//! [`enough_child_create`] creates a source whose cancellation state is
+//! linked to a parent source, mirroring `almost_enough`'s `ChildStopper`:
+//! cancelling the parent cancels every descendant, while cancelling a child
+//! leaves its parent and siblings untouched. Children are ordinary sources,
+//! so tokens are created from them with [`enough_token_create`] and they can
+//! have children of their own.
+//!
+//! ## Timeouts
+//!
+//! [`enough_token_create_with_timeout_ms`] creates a token that also stops
+//! once a deadline passes, and [`enough_token_with_timeout_ms`] derives a
+//! token with a tighter deadline from an existing one. As with
+//! `almost_enough`'s `WithTimeout`, the earliest deadline always wins and an
+//! expired deadline reports [`StopReason::TimedOut`] (explicit cancellation
+//! takes precedence). [`enough_token_remaining_ms`] reports the time left.
+//!
+//! For per-call budgets, keep one long-lived token and derive a token per
+//! call with [`enough_token_with_timeout_ms`]: it stops on the parent's
+//! cancellation or its own deadline, and is destroyed independently of the
+//! parent, so no source is created per call.
+//!
+//! ## Callbacks
+//!
+//! Hosts that would rather be notified than poll register a C callback with
+//! [`enough_token_register_callback`]. It runs exactly once, on the thread
+//! that cancels the token's source or any ancestor (immediately if the
+//! token is already cancelled). [`enough_callback_unregister`] releases the
+//! handle; once it returns the callback is not running and never will, so
+//! the context can be freed. Deadline expiry does not invoke callbacks.
+//! Rust code holding a token registers closures with
+//! [`FfiCancellationToken::register_callback`] and removes them by
+//! [`RegistrationId`].
+//!
+//! Dispatch follows fixed rules:
+//!
+//! - **Order**: higher priority first (see
+//!   [`enough_token_register_callback_with_priority`]; the default is 0),
+//!   equal priorities in registration order. Callbacks registered through
+//!   child tokens are ordered with the ancestor's own by the same rule.
+//! - **Reentrancy**: a callback may unregister itself or any other
+//!   callback; one removed before its turn is skipped. Registering on the
+//!   cancelled token from inside a callback runs the new callback right
+//!   away, nested in the current one. Cancelling again is a no-op.
+//! - **Concurrency**: when several threads cancel at once, only the call
+//!   that sets the flag dispatches; the others return immediately.
+//!   [`enough_cancellation_cancel_was_first`] returns true to that one
+//!   call, so the host can tell which path caused the cancellation.
+//! - **Panics**: C callbacks must not unwind. A panic in a Rust closure
+//!   does not skip the callbacks after it; the first one is resumed from
+//!   [`FfiCancellationSource::cancel`], or only reported by the panic hook
+//!   when cancelling through the C functions.
+//!
+//! ## Errors
+//!
+//! Creation functions return null (or `ENOUGH_INVALID_HANDLE`) instead of
+//! aborting when the handle cannot be allocated, and record why in a
+//! thread-local error retrieved with [`enough_last_error`]. As with
+//! `errno`, check the return value first: the error is only meaningful
+//! after a failed call, and successful calls leave it unchanged.
+//!
+//! This covers the shared state behind a new source, and a callback's
+//! registration record and its place in each source's callback list, as
+//! well as the handle itself: all are allocated fallibly, so creation and
+//! registration fail cleanly under memory exhaustion. Tokens only add a
+//! reference to existing state.
+//!
+//! ## Panics
+//!
+//! A panic must not unwind into C, so every exported function catches it
+//! and returns its failure value instead (null, false, `0`,
+//! `ENOUGH_INVALID_HANDLE` or [`EnoughStopReason::Failed`]), recording
+//! "internal panic" as the last error. A panic while acting on a source
+//! may leave its state half-updated, so it also *poisons* the source (and
+//! its descendants): [`enough_cancellation_is_poisoned`] and
+//! `enough_handle_is_poisoned` let the host notice and replace it instead
+//! of trusting it.
+//!
+//! ## Handles
+//!
+//! With the `handle-table` feature, `enough_handle_create` and friends
+//! offer the same sources as `u64` handles validated against a global
+//! table. Double destroys, use after destroy and forged values are rejected
+//! with a `false`/`0` result instead of undefined behavior, which suits
+//! managed hosts where a stray pointer is easy to keep around. Rust code
+//! receiving a handle gets a token with `FfiCancellationToken::from_handle`.
+//!
+//! ## Token VTable
+//!
+//! With the `vtable` feature, `enough_token_vtable` returns a static
+//! `EnoughTokenVTable` holding the token functions (`is_cancelled`,
+//! `register_callback`, `unregister`, `destroy`, `check`) as pointers, so a
+//! language wrapper binds one struct instead of each function. The table
+//! leads with its `size` and `version`, and new fields are only appended.
+//! A plugin given a table by a host built against another version of this
+//! crate validates it with `EnoughTokenVTable::negotiate`.
+//!
+//! ## Logging
+//!
+//! With the `log` feature, cancels are reported at `DEBUG` through the
+//! `log` facade ("cancel requested", as in `almost-enough`), and host
+//! misuse at `WARN`: invalid handles, rejected vtables, and panics caught
+//! at the boundary.
+//!
+//! ## Plugin Poll Functions
+//!
+//! The other direction: a plugin that already has a C predicate for "stop
+//! now" wraps it in an [`ExternFnStop`] and passes that to any Rust
+//! library taking `impl Stop`. Each check calls the function with the
+//! plugin's context pointer.
+//!
 //! ## Rust FFI Functions
 //!
 //! ```rust
@@ -82,35 +202,429 @@
 #![warn(missing_docs)]
 #![warn(clippy::all)]
 
-use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::alloc::{self, Layout};
+use std::any::Any;
+use std::cell::Cell;
+use std::collections::TryReserveError;
+use std::ffi::{CStr, c_char, c_void};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::thread::{self, ThreadId};
+use std::time::{Duration, Instant};
 
 use enough::{Stop, StopReason};
 
+mod poll;
+pub use poll::ExternFnStop;
+
+mod shared;
+use shared::{Shared, WeakShared};
+
+#[cfg(feature = "handle-table")]
+mod handle;
+#[cfg(feature = "handle-table")]
+pub use handle::{
+    ENOUGH_INVALID_HANDLE, enough_handle_cancel, enough_handle_cancel_was_first,
+    enough_handle_create, enough_handle_create_child, enough_handle_destroy,
+    enough_handle_is_cancelled, enough_handle_is_poisoned, enough_handle_is_valid,
+};
+#[cfg(feature = "vtable")]
+mod vtable;
+#[cfg(feature = "vtable")]
+pub use vtable::{
+    ENOUGH_TOKEN_VTABLE_VERSION, EnoughTokenVTable, NegotiatedVTable, VTableError,
+    enough_token_vtable,
+};
+
 // ============================================================================
 // Internal Types
 // ============================================================================
 
 /// Shared cancellation state, reference counted.
+///
+/// Child states hold a reference to their parent's state, so a parent
+/// cancellation is observed by every descendant. Callback registrations are
+/// stored on every state from the token's own up to the root, so whichever
+/// of them is cancelled first fires the callback.
 struct CancellationState {
     cancelled: AtomicBool,
+    /// Set when an exported function panicked while acting on this state.
+    poisoned: AtomicBool,
+    parent: Option<Shared<CancellationState>>,
+    registrations: Mutex<Vec<Shared<Registration>>>,
 }
 
 impl CancellationState {
     fn new() -> Self {
         Self {
             cancelled: AtomicBool::new(false),
+            poisoned: AtomicBool::new(false),
+            parent: None,
+            registrations: Mutex::new(Vec::new()),
         }
     }
 
-    #[inline]
-    fn cancel(&self) {
-        self.cancelled.store(true, Ordering::Relaxed);
+    fn with_parent(parent: Shared<CancellationState>) -> Self {
+        Self {
+            cancelled: AtomicBool::new(false),
+            poisoned: AtomicBool::new(false),
+            parent: Some(parent),
+            registrations: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Cancel and run the registered callbacks in order.
+    ///
+    /// Only the call that sets the flag dispatches, so concurrent cancels
+    /// run each callback once between them. The panic in the result is the
+    /// first from a Rust callback; the remaining callbacks still run.
+    fn cancel(&self) -> CancelOutcome {
+        // Read before the swap: once an ancestor is cancelled, this state's
+        // tokens already were, whoever sets its own flag
+        let inherited = self.parent.as_ref().is_some_and(|p| p.is_cancelled());
+        if self.cancelled.swap(true, Ordering::Relaxed) {
+            return CancelOutcome::default();
+        }
+        #[cfg(feature = "log")]
+        log::debug!("cancel requested: stop=- reason=Cancelled");
+        // Registering takes this lock before checking `cancelled`, so a
+        // registration either sees the flag or is in the snapshot taken
+        // here. The list itself stays in place during dispatch so that a
+        // callback can still be found and removed by id before its turn.
+        let fired = lock(&self.registrations).clone();
+        let mut first_panic = None;
+        for registration in fired {
+            if let Some(payload) = registration.fire() {
+                first_panic.get_or_insert(payload);
+            }
+        }
+        lock(&self.registrations).clear();
+        CancelOutcome {
+            first: !inherited,
+            panic: first_panic,
+        }
     }
 
     #[inline]
     fn is_cancelled(&self) -> bool {
         self.cancelled.load(Ordering::Relaxed)
+            || self.parent.as_ref().is_some_and(|p| p.is_cancelled())
+    }
+
+    /// Whether this state or an ancestor was poisoned by a caught panic.
+    fn is_poisoned(&self) -> bool {
+        self.poisoned.load(Ordering::Relaxed)
+            || self.parent.as_ref().is_some_and(|p| p.is_poisoned())
+    }
+
+    /// Add `registration` to this state and every ancestor, after any
+    /// registration of equal or higher priority.
+    ///
+    /// Returns the states it was added to, or `None` if one of them is
+    /// already cancelled (the caller then fires it directly). If a list
+    /// cannot grow, it is removed from the states it was already added to;
+    /// the caller must still [`cancel`](Registration::cancel) it, since one
+    /// of them may have fired it in the meantime.
+    fn register(
+        this: &Shared<Self>,
+        registration: &Shared<Registration>,
+    ) -> Result<Option<Vec<WeakShared<Self>>>, TryReserveError> {
+        let mut states = Vec::new();
+        let mut current = Some(this);
+        while let Some(state) = current {
+            let reserved = states.try_reserve(1);
+            let mut list = lock(&state.registrations);
+            if state.cancelled.load(Ordering::Relaxed) {
+                drop(list);
+                unregister_from(&states, registration);
+                return Ok(None);
+            }
+            if let Err(error) = reserved.and_then(|()| list.try_reserve(1)) {
+                drop(list);
+                unregister_from(&states, registration);
+                return Err(error);
+            }
+            let at = list.partition_point(|r| r.priority >= registration.priority);
+            list.insert(at, registration.clone());
+            drop(list);
+            states.push(Shared::downgrade(state));
+            current = state.parent.as_ref();
+        }
+        Ok(Some(states))
+    }
+}
+
+/// What one [`CancellationState::cancel`] call did.
+#[derive(Default)]
+struct CancelOutcome {
+    /// This call moved the state from running to cancelled.
+    first: bool,
+    /// The first panic from a Rust callback run by this call.
+    panic: Option<PanicPayload>,
+}
+
+/// Remove `registration` from every state that is still alive.
+fn unregister_from(states: &[WeakShared<CancellationState>], registration: &Shared<Registration>) {
+    for state in states.iter().filter_map(WeakShared::upgrade) {
+        lock(&state.registrations).retain(|r| !Shared::ptr_eq(r, registration));
+    }
+}
+
+/// Lock a mutex, ignoring poisoning (no code here panics while holding one).
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// A panic caught from a Rust callback.
+type PanicPayload = Box<dyn Any + Send + 'static>;
+
+/// Source of [`RegistrationId`]s; also breaks priority ties by age.
+static NEXT_REGISTRATION_ID: AtomicU64 = AtomicU64::new(1);
+
+enum Callback {
+    /// A C callback and its context pointer.
+    Extern {
+        function: extern "C" fn(*mut c_void),
+        context: *mut c_void,
+    },
+    /// A closure registered from Rust.
+    Rust(Box<dyn FnOnce() + Send>),
+}
+
+// SAFETY: `enough_token_register_callback` requires the caller to accept the
+// callback being invoked, with its context, from any thread; closures are
+// `Send` by construction.
+unsafe impl Send for Callback {}
+
+impl Callback {
+    /// Run the callback, catching a panic from a Rust closure. C callbacks
+    /// cannot unwind: the `extern "C"` ABI aborts instead.
+    fn invoke(self) -> Option<PanicPayload> {
+        match self {
+            Callback::Extern { function, context } => {
+                function(context);
+                None
+            }
+            Callback::Rust(closure) => panic::catch_unwind(AssertUnwindSafe(closure)).err(),
+        }
+    }
+}
+
+enum CallbackState {
+    Pending(Callback),
+    Running(ThreadId),
+    Done,
+}
+
+/// One registered callback, shared by every state it is registered on.
+///
+/// The state machine guarantees the callback runs at most once, and lets
+/// unregistration wait for an invocation in progress on another thread.
+struct Registration {
+    id: u64,
+    priority: i32,
+    state: Mutex<CallbackState>,
+    finished: Condvar,
+}
+
+impl Registration {
+    fn new(callback: Callback, priority: i32) -> Self {
+        Self {
+            id: NEXT_REGISTRATION_ID.fetch_add(1, Ordering::Relaxed),
+            priority,
+            state: Mutex::new(CallbackState::Pending(callback)),
+            finished: Condvar::new(),
+        }
+    }
+
+    /// Invoke the callback if it has neither run nor been unregistered.
+    fn fire(&self) -> Option<PanicPayload> {
+        let callback = {
+            let mut state = lock(&self.state);
+            if !matches!(*state, CallbackState::Pending(_)) {
+                return None;
+            }
+            match std::mem::replace(&mut *state, CallbackState::Running(thread::current().id())) {
+                CallbackState::Pending(callback) => callback,
+                _ => unreachable!("checked above"),
+            }
+        };
+        let panicked = callback.invoke();
+        *lock(&self.state) = CallbackState::Done;
+        self.finished.notify_all();
+        panicked
+    }
+
+    /// Prevent the callback from running.
+    ///
+    /// Returns `true` if it had not started. If it is running on another
+    /// thread, waits for it to return; if it is running on this thread
+    /// (unregistering from inside the callback), returns immediately.
+    fn cancel(&self) -> bool {
+        let mut state = lock(&self.state);
+        loop {
+            match *state {
+                CallbackState::Pending(_) => {
+                    *state = CallbackState::Done;
+                    return true;
+                }
+                CallbackState::Running(id) if id != thread::current().id() => {
+                    state = self.finished.wait(state).unwrap_or_else(|e| e.into_inner());
+                }
+                _ => return false,
+            }
+        }
+    }
+
+    fn is_pending(&self) -> bool {
+        matches!(*lock(&self.state), CallbackState::Pending(_))
+    }
+}
+
+// ============================================================================
+// Stop Reason
+// ============================================================================
+
+/// A token's state as a C enum, returned by [`enough_token_check`].
+///
+/// In C the variants are `ENOUGH_STOP_REASON_RUNNING`,
+/// `ENOUGH_STOP_REASON_CANCELLED` and so on; the values are stable.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EnoughStopReason {
+    /// Not stopped.
+    Running = 0,
+    /// Explicitly cancelled, directly or through an ancestor.
+    Cancelled = 1,
+    /// The token's deadline passed.
+    TimedOut = 2,
+    /// The check itself failed.
+    Failed = 3,
+}
+
+impl From<StopReason> for EnoughStopReason {
+    fn from(reason: StopReason) -> Self {
+        match reason {
+            StopReason::Cancelled => Self::Cancelled,
+            StopReason::TimedOut => Self::TimedOut,
+            _ => Self::Failed,
+        }
+    }
+}
+
+impl From<Result<(), StopReason>> for EnoughStopReason {
+    fn from(result: Result<(), StopReason>) -> Self {
+        result.map_or_else(Self::from, |()| Self::Running)
+    }
+}
+
+// ============================================================================
+// Allocation and Errors
+// ============================================================================
+
+thread_local! {
+    static LAST_ERROR: Cell<Option<&'static CStr>> = const { Cell::new(None) };
+}
+
+#[cfg(test)]
+thread_local! {
+    /// Makes the next `try_box` on this thread fail, to test the null paths.
+    static FAIL_NEXT_ALLOC: Cell<bool> = const { Cell::new(false) };
+    /// Makes the n-th guard entered on this thread panic (counting nested
+    /// guards, 0 = never), to test `catch_on`.
+    static PANIC_IN_GUARD: Cell<u32> = const { Cell::new(0) };
+}
+
+const OUT_OF_MEMORY: &CStr = c"out of memory";
+const PANICKED: &CStr = c"internal panic";
+
+/// Record `message` as this thread's last error.
+fn set_last_error(message: &'static CStr) {
+    // Ignored during thread teardown, when the slot is already gone
+    let _ = LAST_ERROR.try_with(|e| e.set(Some(message)));
+}
+
+/// Run the body of an exported function, returning `fallback` if it panics.
+///
+/// Unwinding out of an `extern "C"` function aborts the host, so every
+/// export goes through here or [`catch_on`]. The panic hook has already
+/// reported the panic; the caller sees `fallback` and [`enough_last_error`].
+fn catch<T>(fallback: T, body: impl FnOnce() -> T) -> T {
+    catch_on(None, fallback, body)
+}
+
+/// [`catch`] for a call acting on `state`, which a panic poisons.
+///
+/// The state may have been left half-updated, so the host should replace
+/// the source; [`enough_cancellation_is_poisoned`] reports it.
+fn catch_on<T>(state: Option<&CancellationState>, fallback: T, body: impl FnOnce() -> T) -> T {
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        #[cfg(test)]
+        if PANIC_IN_GUARD.with(|p| p.replace(p.get().saturating_sub(1))) == 1 {
+            panic!("injected panic");
+        }
+        body()
+    }));
+    result.unwrap_or_else(|payload| {
+        // Dropping the payload can panic again; only a payload whose drop
+        // panicked is leaked, as dropping that one could panic in turn
+        if let Err(again) = panic::catch_unwind(AssertUnwindSafe(|| drop(payload))) {
+            std::mem::forget(again);
+        }
+        if let Some(state) = state {
+            state.poisoned.store(true, Ordering::Relaxed);
+        }
+        #[cfg(feature = "log")]
+        log::warn!(
+            "internal panic caught at the FFI boundary: poisoned={}",
+            state.is_some()
+        );
+        set_last_error(PANICKED);
+        fallback
+    })
+}
+
+/// Move `value` to the heap like `Box::into_raw(Box::new(value))`, but
+/// hand it back instead of aborting if the allocation fails.
+///
+/// The pointer is released with `Box::from_raw`, as usual.
+fn try_box<T>(value: T) -> Result<*mut T, T> {
+    let layout = Layout::new::<T>();
+    #[cfg(test)]
+    if FAIL_NEXT_ALLOC.with(|f| f.replace(false)) {
+        return Err(value);
+    }
+    if layout.size() == 0 {
+        return Ok(Box::into_raw(Box::new(value)));
+    }
+    // SAFETY: the layout has a non-zero size
+    let ptr = unsafe { alloc::alloc(layout) }.cast::<T>();
+    if ptr.is_null() {
+        return Err(value);
+    }
+    // SAFETY: `ptr` is a fresh allocation with `T`'s layout, which is what
+    // `Box<T>` uses with the global allocator, so `Box::from_raw` may free it
+    unsafe { ptr.write(value) };
+    Ok(ptr)
+}
+
+/// [`try_box`], returning null and setting the last error on failure.
+fn into_raw_or_null<T>(value: T) -> *mut T {
+    try_box(value).unwrap_or_else(|_| {
+        set_last_error(OUT_OF_MEMORY);
+        std::ptr::null_mut()
+    })
+}
+
+/// [`into_raw_or_null`] for a source whose state may have failed to allocate.
+fn source_into_raw(source: Option<FfiCancellationSource>) -> *mut FfiCancellationSource {
+    match source {
+        Some(source) => into_raw_or_null(source),
+        None => {
+            set_last_error(OUT_OF_MEMORY);
+            std::ptr::null_mut()
+        }
     }
 }
 
@@ -126,25 +640,62 @@ impl CancellationState {
 /// Create with [`enough_cancellation_create`], destroy with
 /// [`enough_cancellation_destroy`].
 ///
-/// **Safety**: This type uses `Arc` internally. Destroying the source while
+/// **Safety**: This type is reference counted internally. Destroying the source while
 /// tokens exist is safe - tokens will continue to work but can never become
 /// cancelled.
-#[repr(C)]
+///
+/// Opaque to C: the generated header only declares it.
 pub struct FfiCancellationSource {
-    inner: Arc<CancellationState>,
+    inner: Shared<CancellationState>,
 }
 
 impl FfiCancellationSource {
-    fn new() -> Self {
-        Self {
-            inner: Arc::new(CancellationState::new()),
-        }
+    /// A root source, or `None` if its state cannot be allocated.
+    fn new() -> Option<Self> {
+        let inner = Shared::try_new(CancellationState::new()).ok()?;
+        Some(Self { inner })
+    }
+
+    /// A child of this source, or `None` if its state cannot be allocated.
+    fn new_child(&self) -> Option<Self> {
+        let state = CancellationState::with_parent(self.inner.clone());
+        let inner = Shared::try_new(state).ok()?;
+        Some(Self { inner })
     }
 
     /// Cancel this source.
+    ///
+    /// Child sources (and their tokens) are cancelled too; the parent is not.
+    /// Registered callbacks run on this thread before it returns.
+    ///
+    /// # Panics
+    ///
+    /// If a callback registered with
+    /// [`FfiCancellationToken::register_callback`] panics, the remaining
+    /// callbacks still run and the first panic is then resumed here.
     #[inline]
     pub fn cancel(&self) {
-        self.inner.cancel();
+        self.cancel_was_first();
+    }
+
+    /// Cancel this source, returning `true` if this call is the one that
+    /// cancelled it.
+    ///
+    /// When several threads cancel the same source (a timer, a UI button,
+    /// a dropped connection), exactly one call returns `true`, and that
+    /// call runs the callbacks; the others return `false` without waiting
+    /// for them. A child whose ancestor was already cancelled was cancelled
+    /// by that ancestor, so cancelling it returns `false` too.
+    ///
+    /// # Panics
+    ///
+    /// As [`cancel()`](Self::cancel).
+    pub fn cancel_was_first(&self) -> bool {
+        let outcome = self.inner.cancel();
+        if let Some(payload) = outcome.panic {
+            panic::resume_unwind(payload);
+        }
+        outcome.first
     }
 
     /// Check if cancelled.
@@ -156,7 +707,8 @@ impl FfiCancellationSource {
     /// Create a token from this source.
     fn create_token(&self) -> FfiCancellationToken {
         FfiCancellationToken {
-            inner: Some(Arc::clone(&self.inner)),
+            inner: Some(self.inner.clone()),
+            deadline: None,
         }
     }
 }
@@ -172,9 +724,14 @@ impl FfiCancellationSource {
 ///
 /// The token remains valid even after the source is destroyed - it will
 /// just never become cancelled.
-#[repr(C)]
+///
+/// A token may also carry a deadline, after which it reports
+/// [`StopReason::TimedOut`].
+///
+/// Opaque to C: the generated header only declares it.
 pub struct FfiCancellationToken {
-    inner: Option<Arc<CancellationState>>,
+    inner: Option<Shared<CancellationState>>,
+    deadline: Option<Instant>,
 }
 
 impl FfiCancellationToken {
@@ -183,7 +740,187 @@ impl FfiCancellationToken {
     /// This token will never report as cancelled.
     #[inline]
     pub fn never() -> Self {
-        Self { inner: None }
+        Self {
+            inner: None,
+            deadline: None,
+        }
+    }
+
+    /// Return a token sharing this token's cancellation state, with a
+    /// deadline of `duration` from now.
+    ///
+    /// If this token already has an earlier deadline, it is kept (the
+    /// earliest deadline wins). A duration too large to represent leaves the
+    /// deadline unchanged.
+    #[inline]
+    pub fn with_timeout(&self, duration: Duration) -> Self {
+        let new_deadline = Instant::now().checked_add(duration);
+        let deadline = match (self.deadline, new_deadline) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        Self {
+            inner: self.inner.clone(),
+            deadline,
+        }
+    }
+
+    /// Get the deadline, if this token has one.
+    #[inline]
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// Get the remaining time until the deadline.
+    ///
+    /// Returns `None` if the token has no deadline, and `Duration::ZERO` if
+    /// the deadline has passed.
+    #[inline]
+    pub fn remaining(&self) -> Option<Duration> {
+        self.deadline
+            .map(|d| d.saturating_duration_since(Instant::now()))
+    }
+
+    /// Like [`Stop::check`], comparing the deadline against `now` instead
+    /// of reading the clock.
+    ///
+    /// For loops that already take a timestamp for their own use.
+    #[inline]
+    pub fn check_at(&self, now: Instant) -> Result<(), StopReason> {
+        match &self.inner {
+            Some(state) if state.is_cancelled() => return Err(StopReason::Cancelled),
+            _ => {}
+        }
+        match self.deadline {
+            Some(deadline) if now >= deadline => Err(StopReason::TimedOut),
+            _ => Ok(()),
+        }
+    }
+
+    /// Like [`Stop::should_stop`], comparing the deadline against `now`.
+    #[inline]
+    pub fn should_stop_at(&self, now: Instant) -> bool {
+        self.check_at(now).is_err()
+    }
+
+    /// Run `callback` once when this token's source, or any ancestor, is
+    /// cancelled.
+    ///
+    /// Equivalent to [`register_callback_with_priority`] with priority `0`.
+    /// The returned id removes the callback again via
+    /// [`unregister_callback`].
+    ///
+    /// [`register_callback_with_priority`]: Self::register_callback_with_priority
+    /// [`unregister_callback`]: Self::unregister_callback
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use enough_ffi::{enough_cancellation_create, enough_cancellation_destroy};
+    /// use enough_ffi::{enough_token_create, enough_token_destroy};
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// let order = Arc::new(Mutex::new(Vec::new()));
+    /// unsafe {
+    ///     let source = enough_cancellation_create();
+    ///     let token = enough_token_create(source);
+    ///     for (priority, name) in [(0, "flush"), (10, "stop io"), (0, "log")] {
+    ///         let order = Arc::clone(&order);
+    ///         (*token).register_callback_with_priority(priority, move || {
+    ///             order.lock().unwrap().push(name);
+    ///         });
+    ///     }
+    ///     let skipped = (*token).register_callback(|| unreachable!());
+    ///     assert!((*token).unregister_callback(skipped));
+    ///
+    ///     (*source).cancel();
+    ///     enough_token_destroy(token);
+    ///     enough_cancellation_destroy(source);
+    /// }
+    /// assert_eq!(*order.lock().unwrap(), ["stop io", "flush", "log"]);
+    /// ```
+    pub fn register_callback<F>(&self, callback: F) -> RegistrationId
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        self.register_callback_with_priority(0, callback)
+    }
+
+    /// Run `callback` once on cancellation, ordered by `priority`.
+    ///
+    /// Callbacks with a higher priority run first; equal priorities run in
+    /// registration order. See [Callbacks](crate#callbacks) for the full
+    /// dispatch rules.
+    ///
+    /// A token that can never be cancelled drops `callback` immediately.
+    ///
+    /// # Panics
+    ///
+    /// If the token is already cancelled, `callback` runs on this thread
+    /// before returning, and a panic from it propagates to the caller.
+    pub fn register_callback_with_priority<F>(&self, priority: i32, callback: F) -> RegistrationId
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        let registration = Shared::new(Registration::new(
+            Callback::Rust(Box::new(callback)),
+            priority,
+        ));
+        let id = RegistrationId(registration.id);
+        match self.attach(&registration) {
+            Ok((_, Some(payload))) => panic::resume_unwind(payload),
+            Ok((_, None)) => {}
+            // As `Vec` does when it cannot grow; there is no error to return
+            Err(_) => alloc::handle_alloc_error(Layout::new::<Shared<Registration>>()),
+        }
+        id
+    }
+
+    /// Remove a callback registered with
+    /// [`register_callback`](Self::register_callback).
+    ///
+    /// Returns true if the callback had not run and now never will, false
+    /// if it already ran or `id` is not registered through this token's
+    /// source chain. If it is running on another thread, this waits for it
+    /// to return.
+    pub fn unregister_callback(&self, id: RegistrationId) -> bool {
+        let mut found = None;
+        let mut current = self.inner.as_ref();
+        while let Some(state) = current {
+            let mut list = lock(&state.registrations);
+            if let Some(at) = list.iter().position(|r| r.id == id.0) {
+                found = Some(list.remove(at));
+            }
+            drop(list);
+            current = state.parent.as_ref();
+        }
+        found.is_some_and(|registration| registration.cancel())
+    }
+
+    /// The shared state, or `None` for a "never cancelled" token.
+    fn state(&self) -> Option<&CancellationState> {
+        self.inner.as_deref()
+    }
+
+    /// Register on every state from this token's up to the root, or fire
+    /// right away if one of them is already cancelled.
+    ///
+    /// If a registration list cannot grow, the callback will never fire.
+    fn attach(
+        &self,
+        registration: &Shared<Registration>,
+    ) -> Result<(Vec<WeakShared<CancellationState>>, Option<PanicPayload>), TryReserveError> {
+        match &self.inner {
+            Some(state) => match CancellationState::register(state, registration) {
+                Ok(Some(states)) => Ok((states, None)),
+                Ok(None) => Ok((Vec::new(), registration.fire())),
+                Err(error) => {
+                    registration.cancel();
+                    Err(error)
+                }
+            },
+            None => Ok((Vec::new(), None)),
+        }
     }
 
     /// Create a token view from a raw pointer.
@@ -205,7 +942,11 @@ impl Stop for FfiCancellationToken {
     #[inline]
     fn check(&self) -> Result<(), StopReason> {
         match &self.inner {
-            Some(state) if state.is_cancelled() => Err(StopReason::Cancelled),
+            Some(state) if state.is_cancelled() => return Err(StopReason::Cancelled),
+            _ => {}
+        }
+        match self.deadline {
+            Some(deadline) if Instant::now() >= deadline => Err(StopReason::TimedOut),
             _ => Ok(()),
         }
     }
@@ -216,6 +957,7 @@ impl Stop for FfiCancellationToken {
             .as_ref()
             .map(|s| s.is_cancelled())
             .unwrap_or(false)
+            || self.deadline.is_some_and(|d| Instant::now() >= d)
     }
 }
 
@@ -224,6 +966,7 @@ impl std::fmt::Debug for FfiCancellationToken {
         f.debug_struct("FfiCancellationToken")
             .field("is_cancelled", &self.should_stop())
             .field("is_never", &self.inner.is_none())
+            .field("deadline", &self.deadline)
             .finish()
     }
 }
@@ -242,7 +985,7 @@ pub struct FfiCancellationTokenView {
 }
 
 // SAFETY: The view only reads through the pointer, and the underlying
-// Arc<CancellationState> is Send + Sync.
+// shared `CancellationState` is Send + Sync.
 unsafe impl Send for FfiCancellationTokenView {}
 unsafe impl Sync for FfiCancellationTokenView {}
 
@@ -263,13 +1006,7 @@ impl Stop for FfiCancellationTokenView {
             return Ok(());
         }
         // SAFETY: Caller guarantees ptr is valid
-        unsafe {
-            if (*self.ptr).should_stop() {
-                Err(StopReason::Cancelled)
-            } else {
-                Ok(())
-            }
-        }
+        unsafe { (*self.ptr).check() }
     }
 
     #[inline]
@@ -291,6 +1028,49 @@ impl std::fmt::Debug for FfiCancellationTokenView {
     }
 }
 
+// ============================================================================
+// Callback Registration
+// ============================================================================
+
+/// Identifies a callback registered with
+/// [`FfiCancellationToken::register_callback`].
+///
+/// Ids are unique for the life of the process and never reused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct RegistrationId(u64);
+
+impl RegistrationId {
+    /// The raw id, e.g. for logging or to key a host-side table.
+    #[inline]
+    pub fn get(self) -> u64 {
+        self.0
+    }
+}
+
+/// Handle for a callback registered with [`enough_token_register_callback`].
+///
+/// Must be released with [`enough_callback_unregister`], whether or not the
+/// callback has fired.
+pub struct FfiCallbackRegistration {
+    registration: Shared<Registration>,
+    states: Vec<WeakShared<CancellationState>>,
+}
+
+impl FfiCallbackRegistration {
+    fn unregister(self) -> bool {
+        unregister_from(&self.states, &self.registration);
+        self.registration.cancel()
+    }
+}
+
+impl std::fmt::Debug for FfiCallbackRegistration {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FfiCallbackRegistration")
+            .field("is_pending", &self.registration.is_pending())
+            .finish()
+    }
+}
+
 // ============================================================================
 // C FFI Functions - Source Management
 // ============================================================================
@@ -300,10 +1080,12 @@ impl std::fmt::Debug for FfiCancellationTokenView {
 /// Returns a pointer to the source. Must be destroyed with
 /// [`enough_cancellation_destroy`].
 ///
-/// Returns null if allocation fails.
+/// Returns null if allocation fails; see [`enough_last_error`].
 #[unsafe(no_mangle)]
 pub extern "C" fn enough_cancellation_create() -> *mut FfiCancellationSource {
-    Box::into_raw(Box::new(FfiCancellationSource::new()))
+    catch(std::ptr::null_mut(), || {
+        source_into_raw(FfiCancellationSource::new())
+    })
 }
 
 /// Cancel a cancellation source.
@@ -318,10 +1100,35 @@ pub extern "C" fn enough_cancellation_create() -> *mut FfiCancellationSource {
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn enough_cancellation_cancel(ptr: *const FfiCancellationSource) {
     if let Some(source) = unsafe { ptr.as_ref() } {
-        source.cancel();
+        catch_on(Some(&source.inner), (), || {
+            // A panicking Rust callback cannot unwind through the C ABI; the
+            // panic hook has already reported it, and the other callbacks ran.
+            let _ = source.inner.cancel();
+        });
     }
 }
 
+/// Cancel a cancellation source, returning true if this call cancelled it.
+///
+/// Like [`enough_cancellation_cancel`], but tells concurrent callers apart:
+/// exactly one call returns true, and it is the one that ran the callbacks.
+/// Returns false if the source (or an ancestor, for a child) was already
+/// cancelled, or if `ptr` is null. Hosts use it to attribute a
+/// cancellation to the timer, UI or network path that caused it.
+///
+/// # Safety
+///
+/// `ptr` must be a valid pointer returned by [`enough_cancellation_create`]
+/// or [`enough_child_create`], or null.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn enough_cancellation_cancel_was_first(
+    ptr: *const FfiCancellationSource,
+) -> bool {
+    // Callback panics are dropped as in `enough_cancellation_cancel`
+    unsafe { ptr.as_ref() }
+        .is_some_and(|source| catch_on(Some(&source.inner), false, || source.inner.cancel().first))
+}
+
 /// Check if a cancellation source is cancelled.
 ///
 /// # Safety
@@ -332,9 +1139,27 @@ pub unsafe extern "C" fn enough_cancellation_cancel(ptr: *const FfiCancellationS
 pub unsafe extern "C" fn enough_cancellation_is_cancelled(
     ptr: *const FfiCancellationSource,
 ) -> bool {
-    unsafe { ptr.as_ref() }
-        .map(|s| s.is_cancelled())
-        .unwrap_or(false)
+    unsafe { ptr.as_ref() }.is_some_and(|s| catch_on(Some(&s.inner), false, || s.is_cancelled()))
+}
+
+/// Check if a source was poisoned by a panic inside this library.
+///
+/// Every exported function catches panics instead of unwinding into the
+/// caller: it returns its failure value (null, false, `0`) and sets
+/// [`enough_last_error`]. A panic while acting on a source also poisons it,
+/// since its state may be half-updated; a poisoned source should be
+/// destroyed and replaced. A child of a poisoned source is poisoned too.
+/// Returns false for null.
+///
+/// # Safety
+///
+/// `ptr` must be a valid pointer returned by [`enough_cancellation_create`]
+/// or [`enough_child_create`], or null.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn enough_cancellation_is_poisoned(
+    ptr: *const FfiCancellationSource,
+) -> bool {
+    unsafe { ptr.as_ref() }.is_some_and(|s| catch(false, || s.inner.is_poisoned()))
 }
 
 /// Destroy a cancellation source.
@@ -350,34 +1175,111 @@ pub unsafe extern "C" fn enough_cancellation_is_cancelled(
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn enough_cancellation_destroy(ptr: *mut FfiCancellationSource) {
     if !ptr.is_null() {
-        drop(unsafe { Box::from_raw(ptr) });
+        let source = unsafe { Box::from_raw(ptr) };
+        catch((), || drop(source));
     }
 }
 
 // ============================================================================
-// C FFI Functions - Token Management
+// C FFI Functions - Child Sources
 // ============================================================================
 
-/// Create a token from a cancellation source.
+/// Create a child cancellation source.
 ///
-/// The token holds a reference to the shared state and must be destroyed
-/// with [`enough_token_destroy`].
+/// The child is cancelled when `parent` (or any of its ancestors) is
+/// cancelled, or when [`enough_child_cancel`] is called on it. Cancelling the
+/// child does not affect the parent or siblings.
 ///
-/// The token remains valid even after the source is destroyed.
+/// The returned pointer is a regular [`FfiCancellationSource`]: tokens are
+/// created from it with [`enough_token_create`], and it can be passed as the
+/// parent of further children. Must be destroyed with
+/// [`enough_child_destroy`] (or [`enough_cancellation_destroy`]).
+///
+/// The child keeps the parent's state alive, so destroying the parent first
+/// is safe; the child then only becomes cancelled through its own cancel.
 ///
 /// # Safety
 ///
-/// `source` must be a valid pointer returned by [`enough_cancellation_create`],
-/// or null (which creates a "never cancelled" token).
+/// `parent` must be a valid pointer returned by [`enough_cancellation_create`]
+/// or [`enough_child_create`], or null (which creates a root source).
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn enough_child_create(
+    parent: *const FfiCancellationSource,
+) -> *mut FfiCancellationSource {
+    let parent = unsafe { parent.as_ref() };
+    catch_on(parent.map(|p| &*p.inner), std::ptr::null_mut(), || {
+        let child = match parent {
+            Some(p) => p.new_child(),
+            None => FfiCancellationSource::new(),
+        };
+        source_into_raw(child)
+    })
+}
+
+/// Cancel a child source and all of its descendants.
+///
+/// # Safety
+///
+/// `child` must be a valid pointer returned by [`enough_child_create`],
+/// or null (which is a no-op).
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn enough_child_cancel(child: *const FfiCancellationSource) {
+    unsafe { enough_cancellation_cancel(child) }
+}
+
+/// Check if a child source is cancelled, either directly or via an ancestor.
+///
+/// # Safety
+///
+/// `child` must be a valid pointer returned by [`enough_child_create`],
+/// or null (which returns false).
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn enough_child_is_cancelled(child: *const FfiCancellationSource) -> bool {
+    unsafe { enough_cancellation_is_cancelled(child) }
+}
+
+/// Destroy a child source.
+///
+/// Tokens created from the child remain valid and still observe the
+/// parent's cancellation.
+///
+/// # Safety
+///
+/// - `child` must be a valid pointer returned by [`enough_child_create`],
+///   or null (which is a no-op)
+/// - The pointer must not be used after this call
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn enough_child_destroy(child: *mut FfiCancellationSource) {
+    unsafe { enough_cancellation_destroy(child) }
+}
+
+// ============================================================================
+// C FFI Functions - Token Management
+// ============================================================================
+
+/// Create a token from a cancellation source.
+///
+/// The token holds a reference to the shared state and must be destroyed
+/// with [`enough_token_destroy`].
+///
+/// The token remains valid even after the source is destroyed.
+///
+/// # Safety
+///
+/// `source` must be a valid pointer returned by [`enough_cancellation_create`],
+/// or null (which creates a "never cancelled" token).
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn enough_token_create(
     source: *const FfiCancellationSource,
 ) -> *mut FfiCancellationToken {
-    let token = match unsafe { source.as_ref() } {
-        Some(s) => s.create_token(),
-        None => FfiCancellationToken::never(),
-    };
-    Box::into_raw(Box::new(token))
+    let source = unsafe { source.as_ref() };
+    catch_on(source.map(|s| &*s.inner), std::ptr::null_mut(), || {
+        let token = match source {
+            Some(s) => s.create_token(),
+            None => FfiCancellationToken::never(),
+        };
+        into_raw_or_null(token)
+    })
 }
 
 /// Create a "never cancelled" token.
@@ -386,20 +1288,115 @@ pub unsafe extern "C" fn enough_token_create(
 /// [`enough_token_destroy`].
 #[unsafe(no_mangle)]
 pub extern "C" fn enough_token_create_never() -> *mut FfiCancellationToken {
-    Box::into_raw(Box::new(FfiCancellationToken::never()))
+    catch(std::ptr::null_mut(), || {
+        into_raw_or_null(FfiCancellationToken::never())
+    })
+}
+
+/// Create a token from a cancellation source with a timeout.
+///
+/// The token reports cancelled once the source is cancelled or `timeout_ms`
+/// milliseconds have elapsed, whichever comes first. Must be destroyed with
+/// [`enough_token_destroy`].
+///
+/// # Safety
+///
+/// `source` must be a valid pointer returned by [`enough_cancellation_create`],
+/// or null (which creates a token that only times out).
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn enough_token_create_with_timeout_ms(
+    source: *const FfiCancellationSource,
+    timeout_ms: u64,
+) -> *mut FfiCancellationToken {
+    let source = unsafe { source.as_ref() };
+    catch_on(source.map(|s| &*s.inner), std::ptr::null_mut(), || {
+        let token = match source {
+            Some(s) => s.create_token(),
+            None => FfiCancellationToken::never(),
+        };
+        into_raw_or_null(token.with_timeout(Duration::from_millis(timeout_ms)))
+    })
+}
+
+/// Derive a token with a timeout from an existing token.
+///
+/// The new token shares the original's cancellation state. Its deadline is
+/// `timeout_ms` from now, or the original's deadline if that is earlier.
+/// Both tokens must be destroyed with [`enough_token_destroy`].
+///
+/// # Safety
+///
+/// `token` must be a valid pointer returned by one of the `enough_token_create*`
+/// functions, or null (which creates a token that only times out).
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn enough_token_with_timeout_ms(
+    token: *const FfiCancellationToken,
+    timeout_ms: u64,
+) -> *mut FfiCancellationToken {
+    let token = unsafe { token.as_ref() };
+    catch_on(token.and_then(|t| t.state()), std::ptr::null_mut(), || {
+        let duration = Duration::from_millis(timeout_ms);
+        let derived = match token {
+            Some(t) => t.with_timeout(duration),
+            None => FfiCancellationToken::never().with_timeout(duration),
+        };
+        into_raw_or_null(derived)
+    })
+}
+
+/// Get the milliseconds remaining until a token's deadline.
+///
+/// Returns `0` if the deadline has passed, and `u64::MAX` if the token has no
+/// deadline (or is null). Cancellation does not affect the result.
+///
+/// # Safety
+///
+/// `token` must be a valid pointer returned by one of the `enough_token_create*`
+/// functions, or null.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn enough_token_remaining_ms(token: *const FfiCancellationToken) -> u64 {
+    let token = unsafe { token.as_ref() };
+    catch_on(token.and_then(|t| t.state()), 0, || {
+        match token.and_then(|t| t.remaining()) {
+            Some(remaining) => u64::try_from(remaining.as_millis()).unwrap_or(u64::MAX),
+            None => u64::MAX,
+        }
+    })
 }
 
 /// Check if a token is cancelled.
 ///
+/// Returns true if the token's source was cancelled or its deadline passed.
+///
 /// # Safety
 ///
 /// `token` must be a valid pointer returned by [`enough_token_create`],
 /// or null (which returns false).
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn enough_token_is_cancelled(token: *const FfiCancellationToken) -> bool {
-    unsafe { token.as_ref() }
-        .map(|t| t.should_stop())
-        .unwrap_or(false)
+    unsafe { token.as_ref() }.is_some_and(|t| catch_on(t.state(), false, || t.should_stop()))
+}
+
+/// Check a token and report why it stopped.
+///
+/// Returns [`EnoughStopReason::Running`] if the token has not stopped (or
+/// is null), otherwise the reason, with explicit cancellation taking
+/// precedence over an expired deadline.
+///
+/// # Safety
+///
+/// `token` must be a valid pointer returned by one of the
+/// `enough_token_create*` functions, or null.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn enough_token_check(
+    token: *const FfiCancellationToken,
+) -> EnoughStopReason {
+    match unsafe { token.as_ref() } {
+        Some(token) => catch_on(token.state(), EnoughStopReason::Failed, || {
+            token.check().into()
+        }),
+        None => EnoughStopReason::Running,
+    }
 }
 
 /// Destroy a token.
@@ -412,8 +1409,141 @@ pub unsafe extern "C" fn enough_token_is_cancelled(token: *const FfiCancellation
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn enough_token_destroy(token: *mut FfiCancellationToken) {
     if !token.is_null() {
-        drop(unsafe { Box::from_raw(token) });
+        let token = unsafe { Box::from_raw(token) };
+        catch((), || drop(token));
+    }
+}
+
+// ============================================================================
+// C FFI Functions - Callbacks
+// ============================================================================
+
+/// Register a callback invoked once when a token's source is cancelled.
+///
+/// Lets native hosts react to cancellation immediately instead of polling
+/// [`enough_token_is_cancelled`]. `callback(context)` runs exactly once, on
+/// the thread that calls [`enough_cancellation_cancel`] (or
+/// [`enough_child_cancel`] on any ancestor), unless it is unregistered
+/// first. If the token is already cancelled, it runs on this thread before
+/// this function returns.
+///
+/// Deadline expiry does not invoke callbacks; only explicit cancellation
+/// does. A "never cancelled" token returns a registration that never fires.
+///
+/// Returns a handle that must be released with
+/// [`enough_callback_unregister`], or null if `token` or `callback` is null
+/// or allocation fails (see [`enough_last_error`]). After an allocation
+/// failure the callback is not registered.
+///
+/// # Safety
+///
+/// - `token` must be a valid pointer returned by one of the
+///   `enough_token_create*` functions, or null
+/// - `callback` must be safe to call with `context` from any thread until
+///   the registration is released
+/// - `callback` must not unwind
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn enough_token_register_callback(
+    token: *const FfiCancellationToken,
+    callback: Option<extern "C" fn(*mut c_void)>,
+    context: *mut c_void,
+) -> *mut FfiCallbackRegistration {
+    unsafe { enough_token_register_callback_with_priority(token, callback, context, 0) }
+}
+
+/// Register a callback with an explicit priority.
+///
+/// Like [`enough_token_register_callback`] (which uses priority 0), but on
+/// cancellation callbacks with a higher `priority` run first, and equal
+/// priorities run in registration order. Use it to, say, stop I/O before
+/// flushing logs regardless of which was registered first.
+///
+/// # Safety
+///
+/// Same requirements as [`enough_token_register_callback`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn enough_token_register_callback_with_priority(
+    token: *const FfiCancellationToken,
+    callback: Option<extern "C" fn(*mut c_void)>,
+    context: *mut c_void,
+    priority: i32,
+) -> *mut FfiCallbackRegistration {
+    let (Some(token), Some(function)) = (unsafe { token.as_ref() }, callback) else {
+        return std::ptr::null_mut();
+    };
+    catch_on(token.state(), std::ptr::null_mut(), || {
+        let Ok(registration) = Shared::try_new(Registration::new(
+            Callback::Extern { function, context },
+            priority,
+        )) else {
+            set_last_error(OUT_OF_MEMORY);
+            return std::ptr::null_mut();
+        };
+        // C callbacks cannot unwind, so there is no panic to report
+        let Ok((states, _)) = token.attach(&registration) else {
+            set_last_error(OUT_OF_MEMORY);
+            return std::ptr::null_mut();
+        };
+        match try_box(FfiCallbackRegistration {
+            registration,
+            states,
+        }) {
+            Ok(handle) => handle,
+            Err(handle) => {
+                // Nobody can release the registration, so it must never fire
+                handle.unregister();
+                set_last_error(OUT_OF_MEMORY);
+                std::ptr::null_mut()
+            }
+        }
+    })
+}
+
+/// Unregister a callback and release its handle.
+///
+/// Returns true if the callback had not run and now never will, false if
+/// it already ran. If it is running on another thread, this waits for it to
+/// return, so `context` may be freed afterwards. Calling this from inside
+/// the callback itself is allowed and does not wait.
+///
+/// # Safety
+///
+/// - `registration` must be a valid pointer returned by
+///   [`enough_token_register_callback`], or null (which returns false)
+/// - The pointer must not be used after this call
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn enough_callback_unregister(
+    registration: *mut FfiCallbackRegistration,
+) -> bool {
+    if registration.is_null() {
+        return false;
     }
+    let registration = unsafe { Box::from_raw(registration) };
+    catch(false, || registration.unregister())
+}
+
+// ============================================================================
+// C FFI Functions - Errors
+// ============================================================================
+
+/// Describe the last failure on the calling thread.
+///
+/// Returns a static, NUL-terminated message that the caller must not free,
+/// or null if no call on this thread has failed since the last
+/// [`enough_clear_last_error`]. Only failures that return null or
+/// `ENOUGH_INVALID_HANDLE` set it; passing null where null is allowed is
+/// not a failure.
+#[unsafe(no_mangle)]
+pub extern "C" fn enough_last_error() -> *const c_char {
+    LAST_ERROR
+        .try_with(|e| e.get().map_or(std::ptr::null(), CStr::as_ptr))
+        .unwrap_or(std::ptr::null())
+}
+
+/// Clear the calling thread's last error.
+#[unsafe(no_mangle)]
+pub extern "C" fn enough_clear_last_error() {
+    let _ = LAST_ERROR.try_with(|e| e.set(None));
 }
 
 // ============================================================================
@@ -423,6 +1553,7 @@ pub unsafe extern "C" fn enough_token_destroy(token: *mut FfiCancellationToken)
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::Arc;
 
     /// Wrapper to send raw pointers across threads in tests.
     /// Sound because `FfiCancellationToken` is backed by `Arc` and is thread-safe.
@@ -565,6 +1696,7 @@ mod tests {
         fn assert_send_sync<T: Send + Sync>() {}
         assert_send_sync::<FfiCancellationToken>();
         assert_send_sync::<FfiCancellationTokenView>();
+        assert_send_sync::<FfiCallbackRegistration>();
     }
 
     #[test]
@@ -793,6 +1925,79 @@ mod tests {
         }
     }
 
+    #[test]
+    fn token_check_at_uses_given_timestamp() {
+        let token = FfiCancellationToken::never().with_timeout(Duration::from_secs(60));
+        let deadline = token.deadline().unwrap();
+        assert!(token.check_at(deadline - Duration::from_secs(1)).is_ok());
+        assert_eq!(token.check_at(deadline), Err(StopReason::TimedOut));
+        assert!(token.should_stop_at(deadline));
+        assert!(!FfiCancellationToken::never().should_stop_at(deadline));
+    }
+
+    #[test]
+    fn token_check_reports_reason() {
+        unsafe {
+            assert_eq!(
+                enough_token_check(std::ptr::null()),
+                EnoughStopReason::Running
+            );
+            let source = enough_cancellation_create();
+            let token = enough_token_create(source);
+            let expired = enough_token_with_timeout_ms(token, 0);
+            assert_eq!(enough_token_check(token), EnoughStopReason::Running);
+            assert_eq!(enough_token_check(expired), EnoughStopReason::TimedOut);
+
+            enough_cancellation_cancel(source);
+            assert_eq!(enough_token_check(token), EnoughStopReason::Cancelled);
+            assert_eq!(enough_token_check(expired), EnoughStopReason::Cancelled);
+
+            enough_token_destroy(expired);
+            enough_token_destroy(token);
+            enough_cancellation_destroy(source);
+        }
+        assert_eq!(
+            EnoughStopReason::from(StopReason::Failed),
+            EnoughStopReason::Failed
+        );
+    }
+
+    #[test]
+    fn header_declares_every_export() {
+        let header = include_str!("../include/enough.h");
+        let sources = [
+            include_str!("lib.rs"),
+            include_str!("handle.rs"),
+            include_str!("vtable.rs"),
+        ];
+        let exports: Vec<&str> = sources
+            .iter()
+            .flat_map(|source| source.lines())
+            .filter(|line| line.starts_with("pub "))
+            .filter_map(|line| line.split_once("extern \"C\" fn "))
+            .map(|(_, rest)| rest.split('(').next().unwrap())
+            .collect();
+        assert!(exports.len() > 20);
+        for name in exports {
+            assert!(
+                header.contains(&format!(" {name}(")) || header.contains(&format!("*{name}(")),
+                "include/enough.h does not declare `{name}`; run `just ffi-header`"
+            );
+        }
+        for (variant, value) in [
+            ("RUNNING", EnoughStopReason::Running),
+            ("CANCELLED", EnoughStopReason::Cancelled),
+            ("TIMED_OUT", EnoughStopReason::TimedOut),
+            ("FAILED", EnoughStopReason::Failed),
+        ] {
+            let line = format!("ENOUGH_STOP_REASON_{variant} = {},", value as i32);
+            assert!(
+                header.contains(&line),
+                "missing `{line}` in include/enough.h"
+            );
+        }
+    }
+
     #[test]
     fn check_returns_correct_reason() {
         unsafe {
@@ -831,6 +2036,747 @@ mod tests {
         }
     }
 
+    #[test]
+    fn child_inherits_parent_cancellation() {
+        unsafe {
+            let parent = enough_cancellation_create();
+            let child = enough_child_create(parent);
+            let grandchild = enough_child_create(child);
+            let token = enough_token_create(grandchild);
+
+            assert!(!enough_child_is_cancelled(child));
+            assert!(!enough_token_is_cancelled(token));
+
+            enough_cancellation_cancel(parent);
+
+            assert!(enough_child_is_cancelled(child));
+            assert!(enough_child_is_cancelled(grandchild));
+            assert!(enough_token_is_cancelled(token));
+
+            enough_token_destroy(token);
+            enough_child_destroy(grandchild);
+            enough_child_destroy(child);
+            enough_cancellation_destroy(parent);
+        }
+    }
+
+    #[test]
+    fn child_cancellation_is_isolated() {
+        unsafe {
+            let parent = enough_cancellation_create();
+            let a = enough_child_create(parent);
+            let b = enough_child_create(parent);
+            let a_child = enough_child_create(a);
+            let parent_token = enough_token_create(parent);
+            let b_token = enough_token_create(b);
+
+            enough_child_cancel(a);
+
+            assert!(enough_child_is_cancelled(a));
+            assert!(enough_child_is_cancelled(a_child));
+            assert!(!enough_cancellation_is_cancelled(parent));
+            assert!(!enough_child_is_cancelled(b));
+            assert!(!enough_token_is_cancelled(parent_token));
+            assert!(!enough_token_is_cancelled(b_token));
+
+            enough_token_destroy(b_token);
+            enough_token_destroy(parent_token);
+            enough_child_destroy(a_child);
+            enough_child_destroy(b);
+            enough_child_destroy(a);
+            enough_cancellation_destroy(parent);
+        }
+    }
+
+    #[test]
+    fn child_survives_parent_destruction() {
+        unsafe {
+            let parent = enough_cancellation_create();
+            let child = enough_child_create(parent);
+            let token = enough_token_create(child);
+
+            enough_cancellation_destroy(parent);
+            assert!(!enough_token_is_cancelled(token));
+
+            enough_child_cancel(child);
+            assert!(enough_token_is_cancelled(token));
+
+            enough_child_destroy(child);
+            // Token outlives both sources
+            assert!(enough_token_is_cancelled(token));
+            enough_token_destroy(token);
+        }
+    }
+
+    #[test]
+    fn child_null_safety() {
+        unsafe {
+            let root = enough_child_create(std::ptr::null());
+            assert!(!root.is_null());
+            assert!(!enough_child_is_cancelled(root));
+            enough_child_cancel(root);
+            assert!(enough_child_is_cancelled(root));
+            enough_child_destroy(root);
+
+            enough_child_cancel(std::ptr::null());
+            assert!(!enough_child_is_cancelled(std::ptr::null()));
+            enough_child_destroy(std::ptr::null_mut());
+        }
+    }
+
+    #[test]
+    fn timeout_token_expires() {
+        unsafe {
+            let source = enough_cancellation_create();
+            let token = enough_token_create_with_timeout_ms(source, 20);
+            let view = FfiCancellationToken::from_ptr(token);
+
+            assert!(!enough_token_is_cancelled(token));
+            let remaining = enough_token_remaining_ms(token);
+            assert!(remaining > 0 && remaining <= 20);
+
+            std::thread::sleep(std::time::Duration::from_millis(40));
+
+            assert!(enough_token_is_cancelled(token));
+            assert_eq!(view.check(), Err(StopReason::TimedOut));
+            assert_eq!(enough_token_remaining_ms(token), 0);
+            // The source itself is not cancelled by the timeout
+            assert!(!enough_cancellation_is_cancelled(source));
+
+            enough_token_destroy(token);
+            enough_cancellation_destroy(source);
+        }
+    }
+
+    #[test]
+    fn timeout_token_cancellation_takes_precedence() {
+        unsafe {
+            let source = enough_cancellation_create();
+            let token = enough_token_create_with_timeout_ms(source, 0);
+            let view = FfiCancellationToken::from_ptr(token);
+
+            assert_eq!(view.check(), Err(StopReason::TimedOut));
+            enough_cancellation_cancel(source);
+            assert_eq!(view.check(), Err(StopReason::Cancelled));
+
+            enough_token_destroy(token);
+            enough_cancellation_destroy(source);
+        }
+    }
+
+    #[test]
+    fn derived_timeout_tightens() {
+        unsafe {
+            let source = enough_cancellation_create();
+            let outer = enough_token_create_with_timeout_ms(source, 10_000);
+            let tighter = enough_token_with_timeout_ms(outer, 1_000);
+            let looser = enough_token_with_timeout_ms(tighter, 60_000);
+
+            assert!(enough_token_remaining_ms(tighter) <= 1_000);
+            // Loosening is not possible: the earliest deadline wins
+            assert!(enough_token_remaining_ms(looser) <= 1_000);
+            assert!(enough_token_remaining_ms(outer) > 1_000);
+
+            // Derived tokens share the source's cancellation state
+            enough_cancellation_cancel(source);
+            assert!(enough_token_is_cancelled(looser));
+
+            enough_token_destroy(looser);
+            enough_token_destroy(tighter);
+            enough_token_destroy(outer);
+            enough_cancellation_destroy(source);
+        }
+    }
+
+    #[test]
+    fn per_call_tokens_derive_from_long_lived_parent() {
+        unsafe {
+            let source = enough_cancellation_create();
+            let session = enough_token_create(source);
+
+            for _ in 0..3 {
+                let call = enough_token_with_timeout_ms(session, 0);
+                // Each call's deadline expires without affecting the parent
+                assert!(enough_token_is_cancelled(call));
+                enough_token_destroy(call);
+            }
+            assert!(!enough_token_is_cancelled(session));
+            assert_eq!(enough_token_remaining_ms(session), u64::MAX);
+
+            // An outstanding call token sees the parent's cancellation
+            let call = enough_token_with_timeout_ms(session, 60_000);
+            assert!(!enough_token_is_cancelled(call));
+            enough_cancellation_cancel(source);
+            assert!(enough_token_is_cancelled(call));
+            assert_eq!(
+                (*call).check(),
+                Err(StopReason::Cancelled),
+                "explicit cancellation wins over the deadline"
+            );
+
+            enough_token_destroy(call);
+            enough_token_destroy(session);
+            enough_cancellation_destroy(source);
+        }
+    }
+
+    #[test]
+    fn remaining_without_deadline() {
+        unsafe {
+            let source = enough_cancellation_create();
+            let token = enough_token_create(source);
+            assert_eq!(enough_token_remaining_ms(token), u64::MAX);
+            assert_eq!(enough_token_remaining_ms(std::ptr::null()), u64::MAX);
+
+            // Deriving from a plain token adds a deadline
+            let derived = enough_token_with_timeout_ms(token, 500);
+            assert!(enough_token_remaining_ms(derived) <= 500);
+
+            // Null source/token: timeout only
+            let timeout_only = enough_token_create_with_timeout_ms(std::ptr::null(), 0);
+            assert!(enough_token_is_cancelled(timeout_only));
+            let timeout_only2 = enough_token_with_timeout_ms(std::ptr::null(), u64::MAX);
+            assert!(!enough_token_is_cancelled(timeout_only2));
+
+            enough_token_destroy(timeout_only2);
+            enough_token_destroy(timeout_only);
+            enough_token_destroy(derived);
+            enough_token_destroy(token);
+            enough_cancellation_destroy(source);
+        }
+    }
+
+    extern "C" fn count_call(context: *mut c_void) {
+        let counter = unsafe { &*(context as *const std::sync::atomic::AtomicUsize) };
+        counter.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn counter_ptr(counter: &std::sync::atomic::AtomicUsize) -> *mut c_void {
+        counter as *const _ as *mut c_void
+    }
+
+    #[test]
+    fn callback_fires_once_on_cancel() {
+        let calls = std::sync::atomic::AtomicUsize::new(0);
+        unsafe {
+            let source = enough_cancellation_create();
+            let token = enough_token_create(source);
+            let reg = enough_token_register_callback(token, Some(count_call), counter_ptr(&calls));
+            assert!(!reg.is_null());
+            assert_eq!(calls.load(Ordering::SeqCst), 0);
+
+            enough_cancellation_cancel(source);
+            enough_cancellation_cancel(source);
+            assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+            assert!(!enough_callback_unregister(reg));
+            enough_token_destroy(token);
+            enough_cancellation_destroy(source);
+        }
+    }
+
+    #[test]
+    fn exactly_one_concurrent_cancel_is_first() {
+        const THREADS: usize = 8;
+        let calls = std::sync::atomic::AtomicUsize::new(0);
+        let barrier = std::sync::Barrier::new(THREADS);
+        unsafe {
+            let source = enough_cancellation_create();
+            let token = enough_token_create(source);
+            let reg = enough_token_register_callback(token, Some(count_call), counter_ptr(&calls));
+
+            // Shared as an address: raw pointers are not `Send`
+            let address = source as usize;
+            let firsts: usize = std::thread::scope(|scope| {
+                let cancellers: Vec<_> = (0..THREADS)
+                    .map(|_| {
+                        scope.spawn(|| {
+                            barrier.wait();
+                            let source = address as *const FfiCancellationSource;
+                            usize::from(enough_cancellation_cancel_was_first(source))
+                        })
+                    })
+                    .collect();
+                cancellers.into_iter().map(|c| c.join().unwrap()).sum()
+            });
+            assert_eq!(firsts, 1);
+            assert_eq!(calls.load(Ordering::SeqCst), 1);
+            assert!(!(*source).cancel_was_first());
+
+            enough_callback_unregister(reg);
+            enough_token_destroy(token);
+            enough_cancellation_destroy(source);
+        }
+    }
+
+    #[test]
+    fn cancel_was_first_after_ancestor_is_false() {
+        unsafe {
+            assert!(!enough_cancellation_cancel_was_first(std::ptr::null()));
+            let parent = enough_cancellation_create();
+            let child = enough_child_create(parent);
+            let other = enough_child_create(parent);
+            assert!((*other).cancel_was_first());
+            assert!((*parent).cancel_was_first());
+            assert!(!enough_cancellation_cancel_was_first(child));
+            enough_child_destroy(other);
+            enough_child_destroy(child);
+            enough_cancellation_destroy(parent);
+        }
+    }
+
+    #[test]
+    fn callback_fires_immediately_if_cancelled() {
+        let calls = std::sync::atomic::AtomicUsize::new(0);
+        unsafe {
+            let source = enough_cancellation_create();
+            let token = enough_token_create(source);
+            enough_cancellation_cancel(source);
+
+            let reg = enough_token_register_callback(token, Some(count_call), counter_ptr(&calls));
+            assert_eq!(calls.load(Ordering::SeqCst), 1);
+            assert!(!enough_callback_unregister(reg));
+
+            enough_token_destroy(token);
+            enough_cancellation_destroy(source);
+        }
+    }
+
+    #[test]
+    fn unregistered_callback_never_fires() {
+        let calls = std::sync::atomic::AtomicUsize::new(0);
+        unsafe {
+            let source = enough_cancellation_create();
+            let token = enough_token_create(source);
+            let reg = enough_token_register_callback(token, Some(count_call), counter_ptr(&calls));
+
+            assert!(enough_callback_unregister(reg));
+            enough_cancellation_cancel(source);
+            assert_eq!(calls.load(Ordering::SeqCst), 0);
+            assert!((&*source).inner.registrations.lock().unwrap().is_empty());
+
+            enough_token_destroy(token);
+            enough_cancellation_destroy(source);
+        }
+    }
+
+    #[test]
+    fn callback_fires_on_parent_cancel_only_once() {
+        let calls = std::sync::atomic::AtomicUsize::new(0);
+        unsafe {
+            let parent = enough_cancellation_create();
+            let child = enough_child_create(parent);
+            let token = enough_token_create(child);
+            let reg = enough_token_register_callback(token, Some(count_call), counter_ptr(&calls));
+
+            enough_cancellation_cancel(parent);
+            assert_eq!(calls.load(Ordering::SeqCst), 1);
+            enough_child_cancel(child);
+            assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+            enough_callback_unregister(reg);
+            enough_token_destroy(token);
+            enough_child_destroy(child);
+            enough_cancellation_destroy(parent);
+        }
+    }
+
+    #[test]
+    fn child_cancel_does_not_fire_parent_callbacks() {
+        let parent_calls = std::sync::atomic::AtomicUsize::new(0);
+        let child_calls = std::sync::atomic::AtomicUsize::new(0);
+        unsafe {
+            let parent = enough_cancellation_create();
+            let child = enough_child_create(parent);
+            let parent_token = enough_token_create(parent);
+            let child_token = enough_token_create(child);
+            let parent_reg = enough_token_register_callback(
+                parent_token,
+                Some(count_call),
+                counter_ptr(&parent_calls),
+            );
+            let child_reg = enough_token_register_callback(
+                child_token,
+                Some(count_call),
+                counter_ptr(&child_calls),
+            );
+
+            enough_child_cancel(child);
+            assert_eq!(child_calls.load(Ordering::SeqCst), 1);
+            assert_eq!(parent_calls.load(Ordering::SeqCst), 0);
+
+            assert!(enough_callback_unregister(parent_reg));
+            enough_callback_unregister(child_reg);
+            enough_token_destroy(child_token);
+            enough_token_destroy(parent_token);
+            enough_child_destroy(child);
+            enough_cancellation_destroy(parent);
+        }
+    }
+
+    #[test]
+    fn callback_null_and_never_tokens() {
+        let calls = std::sync::atomic::AtomicUsize::new(0);
+        unsafe {
+            let reg = enough_token_register_callback(
+                std::ptr::null(),
+                Some(count_call),
+                counter_ptr(&calls),
+            );
+            assert!(reg.is_null());
+            assert!(!enough_callback_unregister(std::ptr::null_mut()));
+
+            let token = enough_token_create_never();
+            assert!(enough_token_register_callback(token, None, std::ptr::null_mut()).is_null());
+            let reg = enough_token_register_callback(token, Some(count_call), counter_ptr(&calls));
+            assert!(!reg.is_null());
+            assert!(format!("{:?}", *reg).contains("is_pending: true"));
+            assert!(enough_callback_unregister(reg));
+            enough_token_destroy(token);
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn allocation_failure_returns_null_and_sets_last_error() {
+        fn fail_next_alloc() {
+            FAIL_NEXT_ALLOC.with(|f| f.set(true));
+        }
+
+        enough_clear_last_error();
+        assert!(enough_last_error().is_null());
+        let calls = std::sync::atomic::AtomicUsize::new(0);
+        unsafe {
+            fail_next_alloc();
+            assert!(enough_cancellation_create().is_null());
+            let message = CStr::from_ptr(enough_last_error());
+            assert_eq!(message.to_str(), Ok("out of memory"));
+
+            let source = enough_cancellation_create();
+            assert!(!source.is_null());
+            // Success leaves the error in place, like errno
+            assert!(!enough_last_error().is_null());
+            enough_clear_last_error();
+
+            fail_next_alloc();
+            assert!(enough_token_create(source).is_null());
+            fail_next_alloc();
+            assert!(enough_child_create(source).is_null());
+            fail_next_alloc();
+            assert!(enough_token_create_with_timeout_ms(source, 10).is_null());
+            assert!(!enough_last_error().is_null());
+
+            // A registration whose handle could not be allocated never fires
+            let token = enough_token_create(source);
+            fail_next_alloc();
+            let reg = enough_token_register_callback(token, Some(count_call), counter_ptr(&calls));
+            assert!(reg.is_null());
+            enough_cancellation_cancel(source);
+            assert!(enough_token_is_cancelled(token));
+
+            enough_token_destroy(token);
+            enough_cancellation_destroy(source);
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+        enough_clear_last_error();
+        assert!(enough_last_error().is_null());
+    }
+
+    #[test]
+    fn panics_are_caught_and_poison_the_source() {
+        fn panic_next_call() {
+            PANIC_IN_GUARD.with(|p| p.set(1));
+        }
+
+        enough_clear_last_error();
+        unsafe {
+            panic_next_call();
+            assert!(enough_cancellation_create().is_null());
+            let message = CStr::from_ptr(enough_last_error());
+            assert_eq!(message.to_str(), Ok("internal panic"));
+            enough_clear_last_error();
+
+            let source = enough_cancellation_create();
+            let child = enough_child_create(source);
+            let token = enough_token_create(child);
+            assert!(!enough_cancellation_is_poisoned(source));
+
+            // A call that panics before acting leaves the flag alone
+            panic_next_call();
+            enough_cancellation_cancel(source);
+            assert!(!enough_cancellation_is_cancelled(source));
+            assert!(!enough_last_error().is_null());
+
+            // The source and its descendants are poisoned, the rest works
+            assert!(enough_cancellation_is_poisoned(source));
+            assert!(enough_cancellation_is_poisoned(child));
+            assert!(!enough_cancellation_is_poisoned(std::ptr::null()));
+            enough_cancellation_cancel(source);
+            assert!(enough_token_is_cancelled(token));
+
+            panic_next_call();
+            assert_eq!(enough_token_check(token), EnoughStopReason::Failed);
+            panic_next_call();
+            assert!(enough_token_create(child).is_null());
+
+            enough_token_destroy(token);
+            enough_child_destroy(child);
+            enough_cancellation_destroy(source);
+        }
+        enough_clear_last_error();
+    }
+
+    #[test]
+    fn callback_may_unregister_itself() {
+        struct Context {
+            registration: std::sync::atomic::AtomicPtr<FfiCallbackRegistration>,
+            result: std::sync::atomic::AtomicU8,
+        }
+
+        extern "C" fn unregister_self(context: *mut c_void) {
+            let context = unsafe { &*(context as *const Context) };
+            let reg = context
+                .registration
+                .swap(std::ptr::null_mut(), Ordering::SeqCst);
+            let removed = unsafe { enough_callback_unregister(reg) };
+            context.result.store(1 + removed as u8, Ordering::SeqCst);
+        }
+
+        let context = Context {
+            registration: std::sync::atomic::AtomicPtr::new(std::ptr::null_mut()),
+            result: std::sync::atomic::AtomicU8::new(0),
+        };
+        unsafe {
+            let source = enough_cancellation_create();
+            let token = enough_token_create(source);
+            let reg = enough_token_register_callback(
+                token,
+                Some(unregister_self),
+                &context as *const Context as *mut c_void,
+            );
+            context.registration.store(reg, Ordering::SeqCst);
+
+            enough_cancellation_cancel(source);
+            // Ran, and unregistering from inside did not deadlock
+            assert_eq!(context.result.load(Ordering::SeqCst), 1);
+
+            enough_token_destroy(token);
+            enough_cancellation_destroy(source);
+        }
+    }
+
+    #[test]
+    fn unregister_waits_for_running_callback() {
+        use std::sync::atomic::AtomicUsize;
+
+        extern "C" fn slow_call(context: *mut c_void) {
+            let counter = unsafe { &*(context as *const AtomicUsize) };
+            counter.store(1, Ordering::SeqCst);
+            thread::sleep(Duration::from_millis(50));
+            counter.store(2, Ordering::SeqCst);
+        }
+
+        let state = AtomicUsize::new(0);
+        let source = Arc::new(FfiCancellationSource::new().unwrap());
+        let token = source.create_token();
+        let reg =
+            unsafe { enough_token_register_callback(&token, Some(slow_call), counter_ptr(&state)) };
+
+        let canceller = {
+            let source = Arc::clone(&source);
+            thread::spawn(move || source.cancel())
+        };
+        while state.load(Ordering::SeqCst) == 0 {
+            thread::yield_now();
+        }
+        assert!(!unsafe { enough_callback_unregister(reg) });
+        // The callback had finished before unregister returned
+        assert_eq!(state.load(Ordering::SeqCst), 2);
+        canceller.join().unwrap();
+    }
+
+    fn recorder() -> (Arc<Mutex<Vec<&'static str>>>, impl Fn(&'static str) + Clone) {
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let push = {
+            let order = Arc::clone(&order);
+            move |name| order.lock().unwrap().push(name)
+        };
+        (order, push)
+    }
+
+    #[test]
+    fn callbacks_run_by_priority_then_registration_order() {
+        extern "C" fn record_extern(context: *mut c_void) {
+            let order = unsafe { &*(context as *const Mutex<Vec<&'static str>>) };
+            order.lock().unwrap().push("extern");
+        }
+
+        let (order, push) = recorder();
+        let parent = FfiCancellationSource::new().unwrap();
+        let child = parent.new_child().unwrap();
+        let parent_token = parent.create_token();
+        let child_token = child.create_token();
+
+        let p = push.clone();
+        parent_token.register_callback(move || p("parent 0"));
+        let p = push.clone();
+        child_token.register_callback_with_priority(5, move || p("child 5"));
+        let reg = unsafe {
+            enough_token_register_callback_with_priority(
+                &parent_token,
+                Some(record_extern),
+                Arc::as_ptr(&order) as *mut c_void,
+                5,
+            )
+        };
+        let p = push.clone();
+        child_token.register_callback_with_priority(-1, move || p("child -1"));
+        let p = push.clone();
+        parent_token.register_callback(move || p("parent 0 later"));
+
+        parent.cancel();
+        assert_eq!(
+            *order.lock().unwrap(),
+            [
+                "child 5",
+                "extern",
+                "parent 0",
+                "parent 0 later",
+                "child -1"
+            ]
+        );
+        assert!(!unsafe { enough_callback_unregister(reg) });
+    }
+
+    #[test]
+    fn callback_removed_during_dispatch_is_skipped() {
+        let (order, push) = recorder();
+        let source = FfiCancellationSource::new().unwrap();
+        let token = Arc::new(source.create_token());
+
+        let victim = Arc::new(Mutex::new(None));
+        let (p, t, v) = (push.clone(), Arc::clone(&token), Arc::clone(&victim));
+        token.register_callback(move || {
+            p("first");
+            let id = v.lock().unwrap().take().unwrap();
+            assert!(t.unregister_callback(id));
+        });
+        let p = push.clone();
+        *victim.lock().unwrap() = Some(token.register_callback(move || p("removed")));
+        let p = push.clone();
+        token.register_callback(move || p("last"));
+
+        source.cancel();
+        assert_eq!(*order.lock().unwrap(), ["first", "last"]);
+    }
+
+    #[test]
+    fn registering_during_dispatch_runs_nested() {
+        let (order, push) = recorder();
+        let source = FfiCancellationSource::new().unwrap();
+        let token = Arc::new(source.create_token());
+
+        let (p, t) = (push.clone(), Arc::clone(&token));
+        token.register_callback(move || {
+            p("outer");
+            let p = p.clone();
+            t.register_callback_with_priority(i32::MAX, move || p("nested"));
+        });
+        let p = push.clone();
+        token.register_callback(move || p("sibling"));
+
+        source.cancel();
+        source.cancel();
+        assert_eq!(*order.lock().unwrap(), ["outer", "nested", "sibling"]);
+    }
+
+    #[test]
+    fn panicking_callback_does_not_skip_the_rest() {
+        let (order, push) = recorder();
+        let source = FfiCancellationSource::new().unwrap();
+        let token = source.create_token();
+
+        token.register_callback(|| panic!("first"));
+        let p = push.clone();
+        token.register_callback(move || p("after panic"));
+        token.register_callback(|| panic!("second"));
+
+        let payload = panic::catch_unwind(AssertUnwindSafe(|| source.cancel())).unwrap_err();
+        assert_eq!(payload.downcast_ref::<&str>(), Some(&"first"));
+        assert_eq!(*order.lock().unwrap(), ["after panic"]);
+        assert!(source.is_cancelled());
+
+        // Already cancelled: runs inline and the panic reaches the caller
+        let late = panic::catch_unwind(AssertUnwindSafe(|| {
+            token.register_callback(|| panic!("late"));
+        }));
+        assert!(late.is_err());
+    }
+
+    #[test]
+    fn c_cancel_swallows_rust_callback_panics() {
+        let (order, push) = recorder();
+        unsafe {
+            let source = enough_cancellation_create();
+            let token = enough_token_create(source);
+            (*token).register_callback(|| panic!("isolated"));
+            (*token).register_callback(move || push("ran"));
+
+            enough_cancellation_cancel(source);
+            assert_eq!(*order.lock().unwrap(), ["ran"]);
+
+            enough_token_destroy(token);
+            enough_cancellation_destroy(source);
+        }
+    }
+
+    #[test]
+    fn unregister_callback_by_id() {
+        let source = FfiCancellationSource::new().unwrap();
+        let child = source.new_child().unwrap();
+        let token = child.create_token();
+        let other = FfiCancellationSource::new().unwrap().create_token();
+
+        let id = token.register_callback(|| unreachable!());
+        let kept = token.register_callback(|| {});
+        assert!(id < kept);
+        assert_ne!(id.get(), kept.get());
+        assert!(!other.unregister_callback(id));
+        assert!(token.unregister_callback(id));
+        assert!(!token.unregister_callback(id));
+        assert_eq!(lock(&source.inner.registrations).len(), 1);
+
+        source.cancel();
+        assert!(!token.unregister_callback(kept));
+
+        let never = FfiCancellationToken::never();
+        let dropped = never.register_callback(|| unreachable!());
+        assert!(!never.unregister_callback(dropped));
+    }
+
+    #[test]
+    fn concurrent_cancel_fires_once() {
+        let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let source = Arc::new(FfiCancellationSource::new().unwrap());
+        let token = source.create_token();
+        let reg = unsafe {
+            enough_token_register_callback(&token, Some(count_call), counter_ptr(&calls))
+        };
+
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let source = Arc::clone(&source);
+                thread::spawn(move || source.cancel())
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        unsafe { enough_callback_unregister(reg) };
+    }
+
     #[test]
     fn simulated_ffi_pattern() {
         // Simulates how a C caller would use this API
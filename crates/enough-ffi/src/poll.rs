@@ -0,0 +1,149 @@
+//! C poll functions as stops.
+//!
+//! A plugin loaded with `dlopen` often has its own idea of "should I stop"
+//! — a host flag, an event loop query — and exposes it as a C function
+//! taking a context pointer. [`ExternFnStop`] wraps that function so the
+//! plugin can hand it straight to a Rust library taking `impl Stop`,
+//! without creating a source and mirroring its state into it.
+
+use std::ffi::c_void;
+
+use enough::{Stop, StopReason};
+
+/// A C poll function with its context, usable as a [`Stop`].
+///
+/// Every [`check()`](Stop::check) calls `poll(context)`; `true` means stop
+/// and reports [`StopReason::Cancelled`]. Nothing is cached, so the
+/// function should be cheap — wrap the stop with `almost_enough`'s
+/// `throttle_checks()` if it is not.
+///
+/// # Example
+///
+/// ```rust
+/// use enough::Stop;
+/// use enough_ffi::ExternFnStop;
+/// use std::ffi::c_void;
+/// use std::sync::atomic::{AtomicBool, Ordering};
+///
+/// // What a plugin would export
+/// unsafe extern "C" fn host_wants_stop(context: *mut c_void) -> bool {
+///     unsafe { (*context.cast::<AtomicBool>()).load(Ordering::Relaxed) }
+/// }
+///
+/// let flag = AtomicBool::new(false);
+/// let context = (&raw const flag).cast_mut().cast::<c_void>();
+/// // SAFETY: `flag` outlives `stop`, and the function is thread-safe
+/// let stop = unsafe { ExternFnStop::new(host_wants_stop, context) };
+///
+/// assert!(!stop.should_stop());
+/// flag.store(true, Ordering::Relaxed);
+/// assert!(stop.should_stop());
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct ExternFnStop {
+    poll: unsafe extern "C" fn(*mut c_void) -> bool,
+    context: *mut c_void,
+}
+
+// SAFETY: `new` requires `poll(context)` to be callable from any thread,
+// including concurrently, for as long as the stop exists. The pointer is
+// only ever passed back to `poll`, never dereferenced here.
+unsafe impl Send for ExternFnStop {}
+unsafe impl Sync for ExternFnStop {}
+
+impl ExternFnStop {
+    /// Wrap `poll` and the `context` it is called with.
+    ///
+    /// # Safety
+    ///
+    /// For as long as the returned stop or any copy of it exists:
+    ///
+    /// - `poll(context)` must be safe to call, from any thread and from
+    ///   several threads at once; `context` must stay valid that long
+    /// - `poll` must not unwind (the `extern "C"` ABI aborts if it does)
+    /// - the code behind `poll` must stay loaded, so do not `dlclose` the
+    ///   plugin while a Rust library may still check the stop
+    #[inline]
+    pub const unsafe fn new(
+        poll: unsafe extern "C" fn(*mut c_void) -> bool,
+        context: *mut c_void,
+    ) -> Self {
+        Self { poll, context }
+    }
+
+    /// The context pointer passed to the poll function.
+    #[inline]
+    pub fn context(&self) -> *mut c_void {
+        self.context
+    }
+}
+
+impl Stop for ExternFnStop {
+    #[inline]
+    fn check(&self) -> Result<(), StopReason> {
+        if self.should_stop() {
+            Err(StopReason::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+
+    #[inline]
+    fn should_stop(&self) -> bool {
+        // SAFETY: guaranteed by the contract of `new`
+        unsafe { (self.poll)(self.context) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+    struct Context {
+        stop: AtomicBool,
+        calls: AtomicUsize,
+    }
+
+    unsafe extern "C" fn poll(context: *mut c_void) -> bool {
+        let context = unsafe { &*context.cast::<Context>() };
+        context.calls.fetch_add(1, Ordering::Relaxed);
+        context.stop.load(Ordering::Relaxed)
+    }
+
+    fn context_ptr(context: &Context) -> *mut c_void {
+        (context as *const Context).cast_mut().cast()
+    }
+
+    #[test]
+    fn polls_on_every_check() {
+        let context = Context {
+            stop: AtomicBool::new(false),
+            calls: AtomicUsize::new(0),
+        };
+        let stop = unsafe { ExternFnStop::new(poll, context_ptr(&context)) };
+        assert_eq!(stop.context(), context_ptr(&context));
+
+        assert!(stop.check().is_ok());
+        context.stop.store(true, Ordering::Relaxed);
+        assert_eq!(stop.check(), Err(StopReason::Cancelled));
+        assert!(stop.should_stop());
+        assert_eq!(context.calls.load(Ordering::Relaxed), 3);
+    }
+
+    #[test]
+    fn shared_across_threads() {
+        let context = Context {
+            stop: AtomicBool::new(true),
+            calls: AtomicUsize::new(0),
+        };
+        let stop = unsafe { ExternFnStop::new(poll, context_ptr(&context)) };
+        let dyn_stop: &dyn Stop = &stop;
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| assert!(dyn_stop.should_stop()));
+            }
+        });
+        assert_eq!(context.calls.load(Ordering::Relaxed), 4);
+    }
+}
@@ -0,0 +1,230 @@
+//! Reference counting with fallible allocation.
+//!
+//! [`Shared`] is a minimal `Arc` whose constructor hands the value back
+//! instead of aborting when the allocation fails. `Arc::try_new` is not
+//! stable, and the shared state behind a new source is the allocation every
+//! create call makes, so it goes through [`try_box`](crate::try_box) here.
+//!
+//! The counting follows `std::sync::Arc`: all strong references together
+//! hold one weak reference, the value is dropped with the last strong
+//! reference, and the allocation is freed with the last weak one.
+
+use std::alloc::{self, Layout};
+use std::mem::ManuallyDrop;
+use std::ops::Deref;
+use std::ptr::NonNull;
+use std::sync::atomic::{self, AtomicUsize, Ordering};
+
+/// Counts above this abort, as `Arc` does, rather than risk overflow.
+const MAX_REFCOUNT: usize = isize::MAX as usize;
+
+struct Inner<T> {
+    strong: AtomicUsize,
+    weak: AtomicUsize,
+    /// Dropped by hand when `strong` reaches zero.
+    value: ManuallyDrop<T>,
+}
+
+/// A strong reference; see the [module docs](self).
+pub(crate) struct Shared<T> {
+    ptr: NonNull<Inner<T>>,
+}
+
+/// A weak reference to a [`Shared`] value.
+pub(crate) struct WeakShared<T> {
+    ptr: NonNull<Inner<T>>,
+}
+
+// SAFETY: the same bounds as `Arc<T>` and `Weak<T>`: the value is shared
+// between threads and dropped on whichever thread releases it last.
+unsafe impl<T: Send + Sync> Send for Shared<T> {}
+unsafe impl<T: Send + Sync> Sync for Shared<T> {}
+unsafe impl<T: Send + Sync> Send for WeakShared<T> {}
+unsafe impl<T: Send + Sync> Sync for WeakShared<T> {}
+
+impl<T> Shared<T> {
+    /// Move `value` to the heap, or hand it back if the allocation fails.
+    pub(crate) fn try_new(value: T) -> Result<Self, T> {
+        let inner = Inner {
+            strong: AtomicUsize::new(1),
+            weak: AtomicUsize::new(1),
+            value: ManuallyDrop::new(value),
+        };
+        match crate::try_box(inner) {
+            // SAFETY: `try_box` never returns null on success
+            Ok(ptr) => Ok(Self {
+                ptr: unsafe { NonNull::new_unchecked(ptr) },
+            }),
+            Err(inner) => Err(ManuallyDrop::into_inner(inner.value)),
+        }
+    }
+
+    /// Like [`try_new`](Self::try_new), aborting on allocation failure as
+    /// `Arc::new` does. For the Rust API, which has no error to return.
+    pub(crate) fn new(value: T) -> Self {
+        Self::try_new(value)
+            .unwrap_or_else(|_| alloc::handle_alloc_error(Layout::new::<Inner<T>>()))
+    }
+
+    /// Whether both references point to the same allocation.
+    #[inline]
+    pub(crate) fn ptr_eq(this: &Self, other: &Self) -> bool {
+        this.ptr == other.ptr
+    }
+
+    /// Create a weak reference to the value.
+    pub(crate) fn downgrade(this: &Self) -> WeakShared<T> {
+        let old = this.inner().weak.fetch_add(1, Ordering::Relaxed);
+        if old > MAX_REFCOUNT {
+            std::process::abort();
+        }
+        WeakShared { ptr: this.ptr }
+    }
+
+    #[inline]
+    fn inner(&self) -> &Inner<T> {
+        // SAFETY: a strong reference keeps the allocation alive
+        unsafe { self.ptr.as_ref() }
+    }
+}
+
+impl<T> Clone for Shared<T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        let old = self.inner().strong.fetch_add(1, Ordering::Relaxed);
+        if old > MAX_REFCOUNT {
+            std::process::abort();
+        }
+        Self { ptr: self.ptr }
+    }
+}
+
+impl<T> Deref for Shared<T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        &self.inner().value
+    }
+}
+
+impl<T> Drop for Shared<T> {
+    fn drop(&mut self) {
+        if self.inner().strong.fetch_sub(1, Ordering::Release) != 1 {
+            return;
+        }
+        // Every other strong reference's use of the value happens before
+        // the drop
+        atomic::fence(Ordering::Acquire);
+        // SAFETY: this was the last strong reference, so nothing else can
+        // reach the value; the weak count keeps the allocation alive
+        unsafe { ManuallyDrop::drop(&mut (*self.ptr.as_ptr()).value) };
+        // Release the weak reference the strong ones held together
+        drop(WeakShared { ptr: self.ptr });
+    }
+}
+
+impl<T> WeakShared<T> {
+    /// A strong reference, or `None` if the value was already dropped.
+    pub(crate) fn upgrade(&self) -> Option<Shared<T>> {
+        // SAFETY: a weak reference keeps the allocation alive. Only the
+        // count is borrowed: the value may be being dropped concurrently
+        let strong = unsafe { &(*self.ptr.as_ptr()).strong };
+        let mut count = strong.load(Ordering::Relaxed);
+        loop {
+            if count == 0 {
+                return None;
+            }
+            if count > MAX_REFCOUNT {
+                std::process::abort();
+            }
+            match strong.compare_exchange_weak(
+                count,
+                count + 1,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return Some(Shared { ptr: self.ptr }),
+                Err(actual) => count = actual,
+            }
+        }
+    }
+}
+
+impl<T> Drop for WeakShared<T> {
+    fn drop(&mut self) {
+        // SAFETY: this weak reference keeps the allocation alive until here;
+        // as in `upgrade`, only the count is borrowed
+        let weak = unsafe { &(*self.ptr.as_ptr()).weak };
+        if weak.fetch_sub(1, Ordering::Release) != 1 {
+            return;
+        }
+        atomic::fence(Ordering::Acquire);
+        // SAFETY: the allocation came from `try_box`, which `Box::from_raw`
+        // may free, and the value inside was already dropped (it is
+        // `ManuallyDrop`, so freeing does not drop it again)
+        drop(unsafe { Box::from_raw(self.ptr.as_ptr()) });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    /// Counts its drops.
+    struct Tracked(Arc<AtomicUsize>);
+
+    impl Drop for Tracked {
+        fn drop(&mut self) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn value_drops_with_last_strong_reference() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let first = Shared::new(Tracked(Arc::clone(&drops)));
+        let second = first.clone();
+        assert!(Shared::ptr_eq(&first, &second));
+        let weak = Shared::downgrade(&first);
+
+        drop(first);
+        assert!(weak.upgrade().is_some());
+        drop(second);
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+        assert!(weak.upgrade().is_none());
+        drop(weak);
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn failed_allocation_hands_the_value_back() {
+        crate::FAIL_NEXT_ALLOC.with(|f| f.set(true));
+        let value = Shared::try_new(String::from("kept")).err();
+        assert_eq!(value.as_deref(), Some("kept"));
+    }
+
+    #[test]
+    fn concurrent_clones_and_upgrades() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let shared = Shared::new(Tracked(Arc::clone(&drops)));
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let shared = shared.clone();
+                std::thread::spawn(move || {
+                    let weak = Shared::downgrade(&shared);
+                    for _ in 0..1000 {
+                        drop(weak.upgrade().unwrap().clone());
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        assert_eq!(drops.load(Ordering::SeqCst), 0);
+        drop(shared);
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+    }
+}
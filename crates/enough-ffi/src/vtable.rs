@@ -0,0 +1,542 @@
+//! Token operations as one C struct of function pointers.
+//!
+//! Higher-level wrappers (a C# package, a generated binding) otherwise
+//! declare and bind each `enough_token_*` function separately.
+//! [`enough_token_vtable()`] returns a static [`EnoughTokenVTable`] instead:
+//! one symbol to bind, and the table can be handed to code that never links
+//! against this library, such as a plugin loaded from another module.
+//!
+//! The table starts with its own `size` and `version`. Fields are only ever
+//! appended, so a wrapper built against an older layout can check `size`
+//! before touching a field it knows about.
+//!
+//! # Version Negotiation
+//!
+//! A host and its plugins are not rebuilt in lockstep, so a plugin may be
+//! handed a table from an older or newer copy of this crate. Rust plugins
+//! call [`EnoughTokenVTable::negotiate()`] on the pointer they receive: it
+//! rejects tables too small to be any version, and returns a
+//! [`NegotiatedVTable`] that only reads the fields the table's `size`
+//! covers, falling back for newer operations an old table lacks. C plugins
+//! test fields with the `ENOUGH_TOKEN_VTABLE_HAS(table, field)` macro.
+//!
+//! | Version | Appended fields |
+//! |---------|-----------------|
+//! | 1 | `is_cancelled`, `register_callback`, `unregister`, `destroy` |
+//! | 2 | `check` |
+//!
+//! Requires the `vtable` feature.
+
+use core::fmt;
+use core::marker::PhantomData;
+use core::mem::offset_of;
+use std::ffi::c_void;
+
+use crate::{
+    EnoughStopReason, FfiCallbackRegistration, FfiCancellationToken, enough_callback_unregister,
+    enough_token_check, enough_token_destroy, enough_token_is_cancelled,
+    enough_token_register_callback,
+};
+
+/// The [`EnoughTokenVTable::version`] of this crate's table.
+pub const ENOUGH_TOKEN_VTABLE_VERSION: u32 = 2;
+
+/// `size` of a version 1 table: the smallest table
+/// [`EnoughTokenVTable::negotiate()`] accepts.
+const V1_SIZE: usize = offset_of!(EnoughTokenVTable, check);
+
+/// Token operations as function pointers; see [`enough_token_vtable()`].
+///
+/// Every pointer has the contract of the function it points to.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct EnoughTokenVTable {
+    /// `sizeof(EnoughTokenVTable)` for the layout this table was built with.
+    pub size: usize,
+    /// [`ENOUGH_TOKEN_VTABLE_VERSION`] when this table was built.
+    pub version: u32,
+    /// [`enough_token_is_cancelled`](crate::enough_token_is_cancelled).
+    pub is_cancelled: unsafe extern "C" fn(*const FfiCancellationToken) -> bool,
+    /// [`enough_token_register_callback`](crate::enough_token_register_callback).
+    ///
+    /// Not named `register`, which is a C keyword.
+    pub register_callback: unsafe extern "C" fn(
+        *const FfiCancellationToken,
+        Option<extern "C" fn(*mut c_void)>,
+        *mut c_void,
+    ) -> *mut FfiCallbackRegistration,
+    /// [`enough_callback_unregister`](crate::enough_callback_unregister).
+    pub unregister: unsafe extern "C" fn(*mut FfiCallbackRegistration) -> bool,
+    /// [`enough_token_destroy`](crate::enough_token_destroy).
+    pub destroy: unsafe extern "C" fn(*mut FfiCancellationToken),
+    /// [`enough_token_check`](crate::enough_token_check). Version 2.
+    pub check: unsafe extern "C" fn(*const FfiCancellationToken) -> EnoughStopReason,
+}
+
+impl EnoughTokenVTable {
+    /// The table for this crate's token functions.
+    pub const fn new() -> Self {
+        Self {
+            size: size_of::<Self>(),
+            version: ENOUGH_TOKEN_VTABLE_VERSION,
+            is_cancelled: enough_token_is_cancelled,
+            register_callback: enough_token_register_callback,
+            unregister: enough_callback_unregister,
+            destroy: enough_token_destroy,
+            check: enough_token_check,
+        }
+    }
+
+    /// The static table returned by [`enough_token_vtable()`].
+    #[inline]
+    pub fn get() -> &'static Self {
+        &VTABLE
+    }
+
+    /// Validate a table from another build of this crate and wrap it for
+    /// use across versions.
+    ///
+    /// Tables older and newer than this crate's are accepted: `size`, not
+    /// `version`, decides which fields are read. A table is rejected if it
+    /// is null, has a `version` of 0, or a `size` smaller than the version 1
+    /// layout.
+    ///
+    /// # Safety
+    ///
+    /// `table` must be null or point to at least `size` readable bytes,
+    /// laid out as this struct's first `size` bytes, that stay valid and
+    /// unchanged for `'a`. Every function pointer within `size` must have
+    /// the contract of the field it is stored in.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use enough_ffi::{EnoughStopReason, EnoughTokenVTable, enough_token_create_never};
+    ///
+    /// // In a plugin, `table` comes from the host
+    /// let table: *const EnoughTokenVTable = EnoughTokenVTable::get();
+    /// let vtable = unsafe { EnoughTokenVTable::negotiate(table) }.expect("incompatible host");
+    ///
+    /// let token = enough_token_create_never();
+    /// unsafe {
+    ///     assert_eq!(vtable.check(token), EnoughStopReason::Running);
+    ///     vtable.destroy(token);
+    /// }
+    /// ```
+    pub unsafe fn negotiate<'a>(table: *const Self) -> Result<NegotiatedVTable<'a>, VTableError> {
+        let negotiated = unsafe { Self::negotiate_unlogged(table) };
+        #[cfg(feature = "log")]
+        if let Err(error) = &negotiated {
+            log::warn!("token vtable rejected: {error}");
+        }
+        negotiated
+    }
+
+    /// [`negotiate()`](Self::negotiate) without the misuse event.
+    unsafe fn negotiate_unlogged<'a>(
+        table: *const Self,
+    ) -> Result<NegotiatedVTable<'a>, VTableError> {
+        if table.is_null() {
+            return Err(VTableError::Null);
+        }
+        // SAFETY: `size` and `version` lead every layout, and the caller
+        // guarantees they are readable.
+        let (size, version) = unsafe { ((*table).size, (*table).version) };
+        if version == 0 {
+            return Err(VTableError::Version(version));
+        }
+        if size < V1_SIZE {
+            return Err(VTableError::TooSmall(size));
+        }
+        Ok(NegotiatedVTable {
+            table,
+            size,
+            version,
+            _table: PhantomData,
+        })
+    }
+}
+
+impl Default for EnoughTokenVTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+static VTABLE: EnoughTokenVTable = EnoughTokenVTable::new();
+
+/// Get the token vtable.
+///
+/// The returned pointer is never null and stays valid for the life of the
+/// process; do not free it.
+#[unsafe(no_mangle)]
+pub extern "C" fn enough_token_vtable() -> *const EnoughTokenVTable {
+    &VTABLE
+}
+
+/// Why [`EnoughTokenVTable::negotiate()`] rejected a table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum VTableError {
+    /// The table pointer was null.
+    Null,
+    /// The table's `size` is smaller than the version 1 layout.
+    TooSmall(usize),
+    /// The table's `version` is not a valid version.
+    Version(u32),
+}
+
+impl fmt::Display for VTableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Null => f.write_str("token vtable pointer is null"),
+            Self::TooSmall(size) => write!(
+                f,
+                "token vtable size {size} is smaller than the version 1 layout ({V1_SIZE})"
+            ),
+            Self::Version(version) => write!(f, "invalid token vtable version {version}"),
+        }
+    }
+}
+
+impl std::error::Error for VTableError {}
+
+/// A validated [`EnoughTokenVTable`] of any version, from
+/// [`EnoughTokenVTable::negotiate()`].
+///
+/// Each method calls the table's function if the table has it. Operations
+/// added after version 1 fall back to the version 1 functions when the
+/// table predates them.
+#[derive(Debug, Clone, Copy)]
+pub struct NegotiatedVTable<'a> {
+    table: *const EnoughTokenVTable,
+    size: usize,
+    version: u32,
+    _table: PhantomData<&'a EnoughTokenVTable>,
+}
+
+// SAFETY: the table is immutable for `'a` and its functions are callable
+// from any thread, per the contract of `negotiate`.
+unsafe impl Send for NegotiatedVTable<'_> {}
+unsafe impl Sync for NegotiatedVTable<'_> {}
+
+/// Read `$field` of the table if its `size` covers it.
+macro_rules! field {
+    ($vtable:expr, $field:ident) => {{
+        let vtable = $vtable;
+        let end = offset_of!(EnoughTokenVTable, $field) + size_of_val(&VTABLE.$field);
+        // SAFETY: `negotiate` checked that `size` bytes are readable
+        (vtable.size >= end).then(|| unsafe { (*vtable.table).$field })
+    }};
+}
+
+impl NegotiatedVTable<'_> {
+    /// The table's `version`. Informational: fields are chosen by `size`.
+    #[inline]
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// The table's `size` in bytes.
+    #[inline]
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Returns `true` if the table has its own `check` (version 2).
+    #[inline]
+    pub fn has_check(&self) -> bool {
+        field!(self, check).is_some()
+    }
+
+    /// Call the table's `is_cancelled`.
+    ///
+    /// # Safety
+    ///
+    /// As for [`enough_token_is_cancelled`](crate::enough_token_is_cancelled),
+    /// with `token` created by the library that provided the table.
+    #[inline]
+    pub unsafe fn is_cancelled(&self, token: *const FfiCancellationToken) -> bool {
+        // SAFETY: version 1 fields are within `size`, checked by `negotiate`
+        unsafe { ((*self.table).is_cancelled)(token) }
+    }
+
+    /// Call the table's `check`, or derive the reason from `is_cancelled`
+    /// on a version 1 table, which cannot tell a timeout from a cancel
+    /// and reports both as [`EnoughStopReason::Cancelled`].
+    ///
+    /// # Safety
+    ///
+    /// As for [`enough_token_check`](crate::enough_token_check), with
+    /// `token` created by the library that provided the table.
+    #[inline]
+    pub unsafe fn check(&self, token: *const FfiCancellationToken) -> EnoughStopReason {
+        match field!(self, check) {
+            Some(check) => unsafe { check(token) },
+            None if unsafe { self.is_cancelled(token) } => EnoughStopReason::Cancelled,
+            None => EnoughStopReason::Running,
+        }
+    }
+
+    /// Call the table's `register_callback`.
+    ///
+    /// # Safety
+    ///
+    /// As for
+    /// [`enough_token_register_callback`](crate::enough_token_register_callback),
+    /// with `token` created by the library that provided the table.
+    #[inline]
+    pub unsafe fn register_callback(
+        &self,
+        token: *const FfiCancellationToken,
+        callback: Option<extern "C" fn(*mut c_void)>,
+        context: *mut c_void,
+    ) -> *mut FfiCallbackRegistration {
+        unsafe { ((*self.table).register_callback)(token, callback, context) }
+    }
+
+    /// Call the table's `unregister`.
+    ///
+    /// # Safety
+    ///
+    /// As for [`enough_callback_unregister`](crate::enough_callback_unregister),
+    /// with `registration` returned by this table's `register_callback`.
+    #[inline]
+    pub unsafe fn unregister(&self, registration: *mut FfiCallbackRegistration) -> bool {
+        unsafe { ((*self.table).unregister)(registration) }
+    }
+
+    /// Call the table's `destroy`.
+    ///
+    /// # Safety
+    ///
+    /// As for [`enough_token_destroy`](crate::enough_token_destroy), with
+    /// `token` created by the library that provided the table.
+    #[inline]
+    pub unsafe fn destroy(&self, token: *mut FfiCancellationToken) {
+        unsafe { ((*self.table).destroy)(token) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        enough_cancellation_cancel, enough_cancellation_create, enough_cancellation_destroy,
+    };
+    use crate::{enough_token_create, enough_token_create_never, enough_token_with_timeout_ms};
+    use std::sync::Barrier;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Wrapper to send raw pointers across threads in tests.
+    struct SendPtr<T>(*mut T);
+    unsafe impl<T> Send for SendPtr<T> {}
+    unsafe impl<T> Sync for SendPtr<T> {}
+
+    impl<T> SendPtr<T> {
+        /// A method, so closures capture the whole wrapper, not the field.
+        fn get(&self) -> *mut T {
+            self.0
+        }
+    }
+
+    static FIRED: AtomicUsize = AtomicUsize::new(0);
+
+    extern "C" fn count(_: *mut c_void) {
+        FIRED.fetch_add(1, Ordering::SeqCst);
+    }
+
+    #[test]
+    fn header_fields() {
+        let vtable = unsafe { &*enough_token_vtable() };
+        assert!(std::ptr::eq(vtable, EnoughTokenVTable::get()));
+        assert_eq!(vtable.size, size_of::<EnoughTokenVTable>());
+        assert_eq!(vtable.version, ENOUGH_TOKEN_VTABLE_VERSION);
+        assert_eq!(
+            EnoughTokenVTable::default().is_cancelled as usize,
+            vtable.is_cancelled as usize
+        );
+    }
+
+    /// The version 1 layout, as a plugin built against it sees the table.
+    #[repr(C)]
+    struct V1 {
+        size: usize,
+        version: u32,
+        is_cancelled: unsafe extern "C" fn(*const FfiCancellationToken) -> bool,
+        register_callback: unsafe extern "C" fn(
+            *const FfiCancellationToken,
+            Option<extern "C" fn(*mut c_void)>,
+            *mut c_void,
+        ) -> *mut FfiCallbackRegistration,
+        unregister: unsafe extern "C" fn(*mut FfiCallbackRegistration) -> bool,
+        destroy: unsafe extern "C" fn(*mut FfiCancellationToken),
+    }
+
+    /// A later layout with a field this crate does not know about.
+    #[repr(C)]
+    struct Future {
+        current: EnoughTokenVTable,
+        extra: usize,
+    }
+
+    fn v1() -> V1 {
+        V1 {
+            size: size_of::<V1>(),
+            version: 1,
+            is_cancelled: enough_token_is_cancelled,
+            register_callback: enough_token_register_callback,
+            unregister: enough_callback_unregister,
+            destroy: enough_token_destroy,
+        }
+    }
+
+    /// Run every operation through `vtable` and return what `check`
+    /// reported for a timed-out token.
+    fn exercise(vtable: NegotiatedVTable<'_>) -> EnoughStopReason {
+        unsafe {
+            let source = enough_cancellation_create();
+            let token = enough_token_create(source);
+            let expired = enough_token_with_timeout_ms(token, 0);
+
+            assert!(!vtable.is_cancelled(token));
+            assert_eq!(vtable.check(token), EnoughStopReason::Running);
+            assert!(vtable.is_cancelled(expired));
+            let timed_out = vtable.check(expired);
+
+            let registration = vtable.register_callback(token, Some(count), std::ptr::null_mut());
+            assert!(vtable.unregister(registration));
+            enough_cancellation_cancel(source);
+            assert_eq!(vtable.check(token), EnoughStopReason::Cancelled);
+
+            vtable.destroy(expired);
+            vtable.destroy(token);
+            enough_cancellation_destroy(source);
+            timed_out
+        }
+    }
+
+    #[test]
+    fn v1_layout_matches() {
+        assert_eq!(V1_SIZE, size_of::<V1>());
+        assert_eq!(
+            offset_of!(V1, destroy),
+            offset_of!(EnoughTokenVTable, destroy)
+        );
+    }
+
+    #[test]
+    fn compatibility_matrix() {
+        let v1 = v1();
+        let future = Future {
+            current: EnoughTokenVTable {
+                size: size_of::<Future>(),
+                version: ENOUGH_TOKEN_VTABLE_VERSION + 1,
+                ..EnoughTokenVTable::new()
+            },
+            extra: 0,
+        };
+        // (layout, table, has `check`, `check` on a timed-out token)
+        let tables: [(&str, *const EnoughTokenVTable, bool, EnoughStopReason); 3] = [
+            (
+                "v1",
+                (&raw const v1).cast(),
+                false,
+                EnoughStopReason::Cancelled,
+            ),
+            ("current", &VTABLE, true, EnoughStopReason::TimedOut),
+            (
+                "future",
+                (&raw const future).cast(),
+                true,
+                EnoughStopReason::TimedOut,
+            ),
+        ];
+        for (layout, table, has_check, timed_out) in tables {
+            let vtable = unsafe { EnoughTokenVTable::negotiate(table) }.unwrap();
+            assert_eq!(vtable.size(), unsafe { (*table).size }, "{layout}");
+            assert_eq!(vtable.has_check(), has_check, "{layout}");
+            assert_eq!(exercise(vtable), timed_out, "{layout}");
+        }
+        assert_eq!(future.extra, 0);
+    }
+
+    #[test]
+    fn rejects_incompatible_tables() {
+        let unversioned = V1 { version: 0, ..v1() };
+        let truncated = V1 {
+            size: V1_SIZE - size_of::<usize>(),
+            ..v1()
+        };
+        unsafe {
+            assert_eq!(
+                EnoughTokenVTable::negotiate(std::ptr::null()).unwrap_err(),
+                VTableError::Null
+            );
+            assert_eq!(
+                EnoughTokenVTable::negotiate((&raw const unversioned).cast()).unwrap_err(),
+                VTableError::Version(0)
+            );
+            let err = EnoughTokenVTable::negotiate((&raw const truncated).cast()).unwrap_err();
+            assert_eq!(err, VTableError::TooSmall(truncated.size));
+            assert!(err.to_string().contains("smaller"));
+        }
+    }
+
+    #[test]
+    fn null_arguments_are_tolerated() {
+        let vtable = EnoughTokenVTable::get();
+        unsafe {
+            assert!(!(vtable.is_cancelled)(std::ptr::null()));
+            assert!(
+                (vtable.register_callback)(std::ptr::null(), Some(count), std::ptr::null_mut())
+                    .is_null()
+            );
+            assert!(!(vtable.unregister)(std::ptr::null_mut()));
+            (vtable.destroy)(std::ptr::null_mut());
+
+            let never = enough_token_create_never();
+            let registration = (vtable.register_callback)(never, Some(count), std::ptr::null_mut());
+            assert!(!registration.is_null());
+            assert!((vtable.unregister)(registration));
+            (vtable.destroy)(never);
+        }
+    }
+
+    #[test]
+    fn contracts_hold_across_threads() {
+        const THREADS: usize = 8;
+        let vtable = EnoughTokenVTable::get();
+        let source = SendPtr(enough_cancellation_create());
+        let before = FIRED.load(Ordering::SeqCst);
+        let barrier = Barrier::new(THREADS + 1);
+
+        let removed: usize = std::thread::scope(|scope| {
+            let workers: Vec<_> = (0..THREADS)
+                .map(|_| {
+                    scope.spawn(|| unsafe {
+                        let token = enough_token_create(source.get());
+                        let registration =
+                            (vtable.register_callback)(token, Some(count), std::ptr::null_mut());
+                        assert!(!registration.is_null());
+                        barrier.wait();
+                        while !(vtable.is_cancelled)(token) {
+                            std::thread::yield_now();
+                        }
+                        // Dispatch may not have reached this callback yet
+                        let removed = (vtable.unregister)(registration);
+                        (vtable.destroy)(token);
+                        usize::from(removed)
+                    })
+                })
+                .collect();
+            barrier.wait();
+            unsafe { enough_cancellation_cancel(source.0) };
+            workers.into_iter().map(|w| w.join().unwrap()).sum()
+        });
+
+        // Every callback either ran exactly once or was removed first
+        assert_eq!(FIRED.load(Ordering::SeqCst) - before + removed, THREADS);
+        unsafe { enough_cancellation_destroy(source.0) };
+    }
+}
@@ -0,0 +1,149 @@
+//! Creation and registration under memory exhaustion, with a global
+//! allocator that fails.
+//!
+//! The allocator is process-wide, so this runs in its own test binary. It
+//! only fails on a thread that asked it to, leaving the test harness alone.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::ffi::CStr;
+
+use std::ffi::c_void;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use enough_ffi::{enough_cancellation_create, enough_clear_last_error, enough_last_error};
+
+struct FailingAlloc;
+
+thread_local! {
+    /// Allocations this thread may still make, or `None` for no limit.
+    static BUDGET: Cell<Option<usize>> = const { Cell::new(None) };
+}
+
+// SAFETY: forwards to `System`, or reports failure with null
+unsafe impl GlobalAlloc for FailingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let allowed = BUDGET
+            .try_with(|budget| match budget.get() {
+                None => true,
+                Some(0) => false,
+                Some(left) => {
+                    budget.set(Some(left - 1));
+                    true
+                }
+            })
+            .unwrap_or(true);
+        if !allowed {
+            return std::ptr::null_mut();
+        }
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: FailingAlloc = FailingAlloc;
+
+/// Run `body` with only the first `allowed` allocations on this thread
+/// succeeding.
+fn with_budget<T>(allowed: usize, body: impl FnOnce() -> T) -> T {
+    BUDGET.with(|b| b.set(Some(allowed)));
+    let result = body();
+    BUDGET.with(|b| b.set(None));
+    result
+}
+
+/// Run `body` with every allocation on this thread failing.
+fn out_of_memory<T>(body: impl FnOnce() -> T) -> T {
+    with_budget(0, body)
+}
+
+fn last_error() -> Option<&'static str> {
+    let message = enough_last_error();
+    // SAFETY: the last error is null or a static C string
+    (!message.is_null()).then(|| unsafe { CStr::from_ptr(message) }.to_str().unwrap())
+}
+
+#[test]
+fn cancellation_create_returns_null() {
+    enough_clear_last_error();
+    let source = out_of_memory(|| enough_cancellation_create());
+    assert!(source.is_null());
+    assert_eq!(last_error(), Some("out of memory"));
+
+    let source = enough_cancellation_create();
+    assert!(!source.is_null());
+    unsafe { enough_ffi::enough_cancellation_destroy(source) };
+}
+
+#[test]
+fn child_create_returns_null() {
+    let parent = enough_cancellation_create();
+    enough_clear_last_error();
+    let child = out_of_memory(|| unsafe { enough_ffi::enough_child_create(parent) });
+    assert!(child.is_null());
+    assert_eq!(last_error(), Some("out of memory"));
+    unsafe { enough_ffi::enough_cancellation_destroy(parent) };
+}
+
+#[cfg(feature = "handle-table")]
+#[test]
+fn handle_create_returns_invalid_handle() {
+    use enough_ffi::{ENOUGH_INVALID_HANDLE, enough_handle_create, enough_handle_destroy};
+
+    enough_clear_last_error();
+    let handle = out_of_memory(|| enough_handle_create());
+    assert_eq!(handle, ENOUGH_INVALID_HANDLE);
+    assert_eq!(last_error(), Some("out of memory"));
+
+    let handle = enough_handle_create();
+    assert_ne!(handle, ENOUGH_INVALID_HANDLE);
+    assert!(enough_handle_destroy(handle));
+}
+
+extern "C" fn count_call(context: *mut c_void) {
+    // SAFETY: the context is the test's counter, alive until after cancel
+    unsafe { &*context.cast::<AtomicUsize>() }.fetch_add(1, Ordering::SeqCst);
+}
+
+#[test]
+fn register_callback_returns_null_at_every_allocation() {
+    use enough_ffi::*;
+
+    let calls = AtomicUsize::new(0);
+    let context = std::ptr::from_ref(&calls).cast_mut().cast::<c_void>();
+    unsafe {
+        let root = enough_cancellation_create();
+        let child = enough_child_create(root);
+        // Registering on a child also adds to the root's callback list
+        let token = enough_token_create(child);
+
+        // Fail each allocation the call makes in turn: the record, the list
+        // of states, each state's callback list, then the handle
+        let mut failures = 0;
+        let registration = loop {
+            enough_clear_last_error();
+            let registration = with_budget(failures, || {
+                enough_token_register_callback(token, Some(count_call), context)
+            });
+            if !registration.is_null() {
+                break registration;
+            }
+            assert_eq!(last_error(), Some("out of memory"));
+            failures += 1;
+        };
+        assert!(failures >= 4, "only {failures} allocations failed");
+
+        enough_cancellation_cancel(root);
+        // Only the registration that succeeded fires
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert!(!enough_callback_unregister(registration));
+
+        enough_token_destroy(token);
+        enough_cancellation_destroy(child);
+        enough_cancellation_destroy(root);
+    }
+}
@@ -0,0 +1,289 @@
+//! [`StopFuture`]: a future that resolves when a stop fires.
+
+use core::any::Any;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
+
+use almost_enough::{Stopper, WaitCancelled};
+use enough::{Stop, StopReason};
+use futures_core::FusedFuture;
+
+use crate::ticker;
+
+/// How often a stop without wakeup support is re-checked, by default.
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+enum Mode {
+    /// Woken directly by `Stopper::cancel()`.
+    Wake(WaitCancelled),
+    /// Re-checked on a timer.
+    Poll {
+        interval: Duration,
+        /// When the timer is next due, and the waker it will wake.
+        scheduled: Option<(Instant, Waker)>,
+    },
+}
+
+/// A future that resolves to the [`StopReason`] once a stop fires.
+///
+/// Created by [`stopped()`](crate::stopped), [`StopFuture::new()`] or
+/// [`StopFuture::polling()`]. Runtime-agnostic: it needs no tokio,
+/// async-std or smol reactor.
+///
+/// - A [`Stopper`] wakes the task directly from `cancel()`, with no delay
+///   and no background work.
+/// - Any other [`Stop`] is re-checked every
+///   [poll interval](Self::with_poll_interval) (10ms by default) by a
+///   single timer thread shared by all such futures. Cancellation is
+///   noticed up to one interval late.
+///
+/// Also implements [`FusedFuture`], so it can be used directly in
+/// `futures::select!`.
+///
+/// # Example
+///
+/// ```rust
+/// use almost_enough::Stopper;
+/// use enough::StopReason;
+/// use enough_futures::StopFuture;
+/// use std::future::Future;
+/// use std::pin::pin;
+/// use std::task::{Context, Waker};
+///
+/// let stopper = Stopper::new();
+/// let mut stopped = pin!(StopFuture::new(stopper.clone()));
+/// let mut cx = Context::from_waker(Waker::noop());
+/// assert!(stopped.as_mut().poll(&mut cx).is_pending());
+///
+/// stopper.cancel();
+/// assert_eq!(stopped.as_mut().poll(&mut cx), std::task::Poll::Ready(StopReason::Cancelled));
+/// ```
+#[must_use = "futures do nothing unless polled"]
+pub struct StopFuture<S> {
+    stop: S,
+    mode: Mode,
+    done: bool,
+}
+
+impl<S: Stop + 'static> StopFuture<S> {
+    /// Create a future over `stop`, using direct wakeups when `stop` is a
+    /// [`Stopper`] and timed re-checks otherwise.
+    pub fn new(stop: S) -> Self {
+        let mode = match (&stop as &dyn Any).downcast_ref::<Stopper>() {
+            Some(stopper) => Mode::Wake(stopper.wait_cancelled()),
+            None => Mode::Poll {
+                interval: DEFAULT_POLL_INTERVAL,
+                scheduled: None,
+            },
+        };
+        Self {
+            stop,
+            mode,
+            done: false,
+        }
+    }
+}
+
+impl<S: Stop> StopFuture<S> {
+    /// Create a future that re-checks `stop` on a timer.
+    ///
+    /// Unlike [`new()`](StopFuture::new), accepts borrowed stops such as
+    /// `&Stopper` or `StopRef`.
+    pub fn polling(stop: S) -> Self {
+        Self {
+            stop,
+            mode: Mode::Poll {
+                interval: DEFAULT_POLL_INTERVAL,
+                scheduled: None,
+            },
+            done: false,
+        }
+    }
+
+    /// Set how often the stop is re-checked when it cannot wake the task
+    /// itself.
+    ///
+    /// Has no effect on a future woken directly by a [`Stopper`].
+    pub fn with_poll_interval(mut self, interval: Duration) -> Self {
+        if let Mode::Poll {
+            interval: current, ..
+        } = &mut self.mode
+        {
+            *current = interval;
+        }
+        self
+    }
+
+    /// Whether this future is woken directly rather than by the timer.
+    #[inline]
+    pub fn is_wakeup_driven(&self) -> bool {
+        matches!(self.mode, Mode::Wake(_))
+    }
+
+    /// Get a reference to the stop.
+    #[inline]
+    pub fn stop(&self) -> &S {
+        &self.stop
+    }
+
+    /// Unwrap and return the stop.
+    #[inline]
+    pub fn into_inner(self) -> S {
+        self.stop
+    }
+}
+
+impl<S: Stop + Unpin> Future for StopFuture<S> {
+    type Output = StopReason;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<StopReason> {
+        let this = self.get_mut();
+        if let Err(reason) = this.stop.check() {
+            this.done = true;
+            return Poll::Ready(reason);
+        }
+        match &mut this.mode {
+            Mode::Wake(wait) => {
+                if Pin::new(wait).poll(cx).is_pending() {
+                    return Poll::Pending;
+                }
+                this.done = true;
+                Poll::Ready(this.stop.check().err().unwrap_or(StopReason::Cancelled))
+            }
+            Mode::Poll {
+                interval,
+                scheduled,
+            } => {
+                let now = Instant::now();
+                let rearm = match scheduled {
+                    Some((at, waker)) => *at <= now || !waker.will_wake(cx.waker()),
+                    None => true,
+                };
+                if rearm {
+                    let at = match scheduled {
+                        Some((at, _)) if *at > now => *at,
+                        _ => now + *interval,
+                    };
+                    ticker::wake_at(at, cx.waker().clone());
+                    *scheduled = Some((at, cx.waker().clone()));
+                }
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl<S: Stop + Unpin> FusedFuture for StopFuture<S> {
+    fn is_terminated(&self) -> bool {
+        self.done
+    }
+}
+
+impl<S: core::fmt::Debug> core::fmt::Debug for StopFuture<S> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let mode = match &self.mode {
+            Mode::Wake(_) => "wake",
+            Mode::Poll { .. } => "poll",
+        };
+        f.debug_struct("StopFuture")
+            .field("stop", &self.stop)
+            .field("mode", &mode)
+            .field("done", &self.done)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use almost_enough::{FnStop, StopSource};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::task::Wake;
+    use std::thread::{self, Thread};
+
+    struct Unpark(Thread);
+
+    impl Wake for Unpark {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    /// Minimal executor: poll, park until woken, repeat.
+    fn block_on<F: Future + Unpin>(mut future: F) -> F::Output {
+        let waker = Waker::from(Arc::new(Unpark(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            if let Poll::Ready(output) = Pin::new(&mut future).poll(&mut cx) {
+                return output;
+            }
+            thread::park();
+        }
+    }
+
+    #[test]
+    fn stopper_is_wakeup_driven() {
+        let stopper = Stopper::new();
+        let future = StopFuture::new(stopper.clone());
+        assert!(future.is_wakeup_driven());
+
+        let canceller = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(10));
+            stopper.cancel();
+        });
+        assert_eq!(block_on(future), StopReason::Cancelled);
+        canceller.join().unwrap();
+    }
+
+    #[test]
+    fn other_stops_are_polled() {
+        static FLAG: AtomicBool = AtomicBool::new(false);
+        let stop = FnStop::new(|| FLAG.load(Ordering::Relaxed));
+        let future = StopFuture::new(stop).with_poll_interval(Duration::from_millis(1));
+        assert!(!future.is_wakeup_driven());
+
+        let setter = thread::spawn(|| {
+            thread::sleep(Duration::from_millis(10));
+            FLAG.store(true, Ordering::Relaxed);
+        });
+        assert_eq!(block_on(future), StopReason::Cancelled);
+        setter.join().unwrap();
+    }
+
+    #[test]
+    fn borrowed_stop_with_polling() {
+        let source = StopSource::new();
+        thread::scope(|scope| {
+            let future = StopFuture::polling(source.as_ref());
+            scope.spawn(|| {
+                thread::sleep(Duration::from_millis(10));
+                source.cancel();
+            });
+            assert_eq!(block_on(future), StopReason::Cancelled);
+        });
+    }
+
+    #[test]
+    fn already_stopped_resolves_immediately() {
+        let mut future = StopFuture::new(Stopper::cancelled());
+        let mut cx = Context::from_waker(Waker::noop());
+        assert!(!future.is_terminated());
+        assert_eq!(
+            Pin::new(&mut future).poll(&mut cx),
+            Poll::Ready(StopReason::Cancelled)
+        );
+        assert!(future.is_terminated());
+        assert!(format!("{future:?}").contains("mode: \"wake\""));
+        assert!(future.into_inner().is_cancelled());
+    }
+
+    #[test]
+    fn is_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<StopFuture<Stopper>>();
+        assert_send_sync::<StopFuture<almost_enough::StopToken>>();
+    }
+}
@@ -0,0 +1,65 @@
+//! # enough-futures
+//!
+//! Await any [`Stop`] without tying yourself to an async runtime.
+//!
+//! ## When to Use
+//!
+//! Use this crate when you have:
+//! - async-std, smol or executor-agnostic code that needs to `select!` on
+//!   cancellation
+//! - A library that accepts `impl Stop` and wants to stop awaiting when it
+//!   fires, without depending on tokio
+//!
+//! For tokio, prefer [`enough-tokio`](https://docs.rs/enough-tokio), which
+//! waits on the `CancellationToken` directly.
+//!
+//! ## How It Wakes
+//!
+//! - [`Stopper`](almost_enough::Stopper) registers the task's waker and is
+//!   woken directly by `cancel()`.
+//! - Any other `Stop` is re-checked on a timer (10ms by default) served by
+//!   one shared background thread.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! use almost_enough::Stopper;
+//! use enough_futures::stopped;
+//! use futures::{FutureExt, select};
+//!
+//! async fn fetch_or_cancel(stop: Stopper) -> Option<Vec<u8>> {
+//!     select! {
+//!         data = fetch().fuse() => Some(data),
+//!         _ = stopped(stop) => None,
+//!     }
+//! }
+//! ```
+
+#![warn(missing_docs)]
+#![warn(clippy::all)]
+
+mod future;
+mod ticker;
+
+pub use future::{DEFAULT_POLL_INTERVAL, StopFuture};
+
+use enough::Stop;
+
+/// Create a future that resolves to the [`StopReason`](enough::StopReason)
+/// once `stop` fires.
+///
+/// Shorthand for [`StopFuture::new()`].
+///
+/// # Example
+///
+/// ```rust
+/// use almost_enough::Stopper;
+/// use enough_futures::stopped;
+///
+/// let stopper = Stopper::new();
+/// let future = stopped(stopper.clone());
+/// assert!(future.is_wakeup_driven());
+/// ```
+pub fn stopped<S: Stop + 'static>(stop: S) -> StopFuture<S> {
+    StopFuture::new(stop)
+}
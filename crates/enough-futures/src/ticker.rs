@@ -0,0 +1,146 @@
+//! A shared timer thread for the polling fallback.
+//!
+//! Futures over stops that cannot wake a task themselves ask to be woken
+//! again after their poll interval. One lazily spawned thread serves every
+//! such request in the process, so no async runtime is needed.
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::sync::{Condvar, Mutex, MutexGuard, OnceLock, PoisonError};
+use std::task::Waker;
+use std::time::Instant;
+
+struct Entry {
+    at: Instant,
+    waker: Waker,
+}
+
+// Ordered so the `BinaryHeap` (a max-heap) pops the earliest entry first
+impl Ord for Entry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.at.cmp(&self.at)
+    }
+}
+
+impl PartialOrd for Entry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Entry {
+    fn eq(&self, other: &Self) -> bool {
+        self.at == other.at
+    }
+}
+
+impl Eq for Entry {}
+
+struct Ticker {
+    queue: Mutex<BinaryHeap<Entry>>,
+    cond: Condvar,
+}
+
+impl Ticker {
+    fn lock(&self) -> MutexGuard<'_, BinaryHeap<Entry>> {
+        self.queue.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn run(&self) {
+        let mut queue = self.lock();
+        loop {
+            let now = Instant::now();
+            let mut due = Vec::new();
+            while queue.peek().is_some_and(|entry| entry.at <= now) {
+                due.extend(queue.pop().map(|entry| entry.waker));
+            }
+            if !due.is_empty() {
+                // Woken outside the lock: a waker may poll, and so schedule
+                drop(queue);
+                due.into_iter().for_each(Waker::wake);
+                queue = self.lock();
+                continue;
+            }
+            queue = match queue.peek() {
+                None => self
+                    .cond
+                    .wait(queue)
+                    .unwrap_or_else(PoisonError::into_inner),
+                Some(entry) => {
+                    let timeout = entry.at - now;
+                    self.cond
+                        .wait_timeout(queue, timeout)
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .0
+                }
+            };
+        }
+    }
+}
+
+/// The shared ticker, or `None` if its thread could not be spawned.
+fn ticker() -> Option<&'static Ticker> {
+    static TICKER: OnceLock<Option<&'static Ticker>> = OnceLock::new();
+    *TICKER.get_or_init(|| {
+        let ticker: &'static Ticker = Box::leak(Box::new(Ticker {
+            queue: Mutex::new(BinaryHeap::new()),
+            cond: Condvar::new(),
+        }));
+        std::thread::Builder::new()
+            .name("enough-futures-ticker".into())
+            .spawn(move || ticker.run())
+            .ok()
+            .map(|_| ticker)
+    })
+}
+
+/// Wake `waker` at (or shortly after) `at`.
+///
+/// If the timer thread cannot be spawned, wakes immediately instead, which
+/// degrades to busy polling rather than never waking.
+pub(crate) fn wake_at(at: Instant, waker: Waker) {
+    let Some(ticker) = ticker() else {
+        waker.wake();
+        return;
+    };
+    let mut queue = ticker.lock();
+    let earliest = queue.peek().is_none_or(|entry| at < entry.at);
+    queue.push(Entry { at, waker });
+    drop(queue);
+    if earliest {
+        ticker.cond.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+    use std::task::Wake;
+    use std::time::Duration;
+
+    struct Counter(AtomicUsize);
+
+    impl Wake for Counter {
+        fn wake(self: Arc<Self>) {
+            self.0.fetch_add(1, AtomicOrdering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn wakes_in_deadline_order() {
+        let late = Arc::new(Counter(AtomicUsize::new(0)));
+        let early = Arc::new(Counter(AtomicUsize::new(0)));
+        let now = Instant::now();
+        wake_at(now + Duration::from_secs(60), Waker::from(late.clone()));
+        wake_at(now + Duration::from_millis(5), Waker::from(early.clone()));
+
+        let deadline = now + Duration::from_secs(5);
+        while early.0.load(AtomicOrdering::SeqCst) == 0 && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(early.0.load(AtomicOrdering::SeqCst), 1);
+        assert_eq!(late.0.load(AtomicOrdering::SeqCst), 0);
+    }
+}
@@ -0,0 +1,59 @@
+const test = require('node:test');
+const assert = require('node:assert');
+
+const { CancellationController, CancellationToken } = require('../index.js');
+
+test('abort reaches tokens and children', () => {
+  const controller = new CancellationController();
+  const child = controller.child();
+  const token = controller.token();
+  const childToken = child.token();
+  assert.equal(token.aborted, false);
+  assert.equal(token.reason, null);
+  token.throwIfAborted();
+
+  child.abort();
+  assert.equal(childToken.aborted, true);
+  assert.equal(controller.aborted, false);
+
+  controller.abort();
+  assert.equal(token.reason, 'cancelled');
+  assert.throws(() => token.throwIfAborted(), /cancelled/);
+});
+
+test('fromSignal follows an AbortSignal', () => {
+  const web = new AbortController();
+  const controller = CancellationController.fromSignal(web.signal);
+  const token = controller.token();
+  assert.equal(token.aborted, false);
+
+  web.abort();
+  assert.equal(controller.aborted, true);
+  assert.equal(token.reason, 'cancelled');
+
+  assert.equal(CancellationController.fromSignal(AbortSignal.abort()).aborted, true);
+});
+
+test('timeouts and the earliest deadline', async () => {
+  const token = CancellationToken.never().withTimeout(1);
+  await new Promise((resolve) => setTimeout(resolve, 10));
+  assert.equal(token.reason, 'timed_out');
+  assert.equal(token.remainingMs(), 0);
+
+  const short = new CancellationController().timeout(1000);
+  assert.ok(short.withTimeout(60000).remainingMs() <= 1000);
+  assert.equal(CancellationToken.never().remainingMs(), null);
+  assert.throws(() => short.withTimeout(-1));
+  assert.throws(() => short.withTimeout(NaN));
+});
+
+test('ptr is valid until close', () => {
+  const token = new CancellationController().token();
+  assert.equal(typeof token.ptr(), 'bigint');
+  assert.notEqual(token.ptr(), 0n);
+
+  token.close();
+  token.close();
+  assert.equal(token.closed, true);
+  assert.throws(() => token.ptr(), /closed/);
+});
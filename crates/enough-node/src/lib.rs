@@ -0,0 +1,283 @@
+//! # enough-node
+//!
+//! Node.js bindings for [`enough_ffi`] cancellation sources and tokens.
+//!
+//! JavaScript that calls Rust native addons cancels them the web way, with
+//! an `AbortController` and its `AbortSignal`. This addon provides
+//! `CancellationController`, an `AbortController`-like wrapper around an
+//! `enough-ffi` source, and `CancellationToken`, whose `ptr()` hands the
+//! raw `FfiCancellationToken` to any addon that accepts one.
+//! `CancellationController.fromSignal(signal)` wires an existing
+//! `AbortSignal` in, so web-style cancellation reaches Rust codecs
+//! unchanged.
+//!
+//! ## JavaScript Usage
+//!
+//! ```js
+//! const { CancellationController } = require('enough-node');
+//!
+//! const web = new AbortController();
+//! const controller = CancellationController.fromSignal(web.signal);
+//! const token = controller.timeout(5000);
+//! try {
+//!   codec.decode(data, token.ptr());   // a Rust addon taking the token
+//! } finally {
+//!   token.close();
+//! }
+//!
+//! web.abort();                         // reaches the Rust side
+//! token.throwIfAborted();              // throws Error('cancelled')
+//! ```
+//!
+//! ## Pointer Lifetime
+//!
+//! `CancellationToken.ptr()` returns the address of the token owned by the
+//! JavaScript object, as a `BigInt`. It stays valid until the token is
+//! closed or garbage collected, so keep the `CancellationToken` reachable
+//! for as long as the Rust side may use the pointer. The receiving addon
+//! reads it with
+//! [`FfiCancellationToken::from_ptr`](enough_ffi::FfiCancellationToken::from_ptr).
+//!
+//! ## Building
+//!
+//! The crate is built with `@napi-rs/cli` and is not part of the Cargo
+//! workspace; see `just node-test`.
+
+use std::ffi::CStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use enough::{Stop, StopReason};
+use enough_ffi::{
+    FfiCancellationSource, FfiCancellationToken, enough_cancellation_create, enough_child_create,
+    enough_last_error, enough_token_create, enough_token_create_never,
+};
+use napi::bindgen_prelude::BigInt;
+use napi::{Env, Error, JsFunction, JsObject, JsUnknown, Result, Status};
+use napi_derive::napi;
+
+/// Take ownership of a pointer returned by an `enough_*_create` function.
+fn owned<T>(ptr: *mut T) -> Result<Box<T>> {
+    if ptr.is_null() {
+        // SAFETY: `enough_last_error` returns null or a static C string.
+        let message = unsafe { enough_last_error().as_ref() }
+            .map(|message| unsafe { CStr::from_ptr(message) }.to_string_lossy())
+            .unwrap_or("allocation failed".into());
+        return Err(Error::new(Status::GenericFailure, message.into_owned()));
+    }
+    // SAFETY: non-null pointers from enough-ffi are allocated with the global
+    // allocator and `Layout::new::<T>()`, exactly like `Box::new`, and its
+    // destroy functions free them with `Box::from_raw`.
+    Ok(unsafe { Box::from_raw(ptr) })
+}
+
+/// A timeout in milliseconds from JavaScript, rejecting negative and NaN
+/// values.
+fn duration(ms: f64) -> Result<Duration> {
+    Duration::try_from_secs_f64(ms / 1000.0)
+        .map_err(|_| Error::new(Status::InvalidArg, format!("invalid timeout: {ms} ms")))
+}
+
+fn reason_name(reason: StopReason) -> &'static str {
+    match reason {
+        StopReason::Cancelled => "cancelled",
+        StopReason::TimedOut => "timed_out",
+        _ => "failed",
+    }
+}
+
+/// An `AbortController`-like cancellation source.
+///
+/// `abort()` cancels every token created from it and from its children.
+#[napi]
+pub struct CancellationController {
+    inner: Arc<FfiCancellationSource>,
+}
+
+impl CancellationController {
+    fn from_box(source: Box<FfiCancellationSource>) -> Self {
+        Self {
+            inner: Arc::from(source),
+        }
+    }
+}
+
+#[napi]
+impl CancellationController {
+    #[napi(constructor)]
+    pub fn new() -> Result<Self> {
+        Ok(Self::from_box(owned(enough_cancellation_create())?))
+    }
+
+    /// A controller aborted when `signal` aborts (or already aborted if it
+    /// has).
+    ///
+    /// The listener holds the controller's source, not the controller, so
+    /// the signal keeps working after the controller object is collected.
+    #[napi(factory)]
+    pub fn from_signal(env: Env, signal: JsObject) -> Result<Self> {
+        let controller = Self::new()?;
+        if signal.get_named_property::<bool>("aborted")? {
+            controller.inner.cancel();
+            return Ok(controller);
+        }
+
+        let source = Arc::clone(&controller.inner);
+        let listener = env.create_function_from_closure("onabort", move |ctx| {
+            source.cancel();
+            ctx.env.get_undefined()
+        })?;
+        let mut options = env.create_object()?;
+        options.set_named_property("once", true)?;
+        let add: JsFunction = signal.get_named_property("addEventListener")?;
+        let args: [JsUnknown; 3] = [
+            env.create_string("abort")?.into_unknown(),
+            listener.into_unknown(),
+            options.into_unknown(),
+        ];
+        add.call(Some(&signal), &args)?;
+        Ok(controller)
+    }
+
+    /// Cancel this controller, its children and all their tokens.
+    ///
+    /// Callbacks registered on the tokens by Rust code run synchronously on
+    /// the JavaScript thread.
+    #[napi]
+    pub fn abort(&self) {
+        self.inner.cancel();
+    }
+
+    /// Whether this controller (or an ancestor) has been aborted.
+    #[napi(getter)]
+    pub fn aborted(&self) -> bool {
+        self.inner.is_cancelled()
+    }
+
+    /// A child controller, aborted with this one but not vice versa.
+    #[napi]
+    pub fn child(&self) -> Result<CancellationController> {
+        // SAFETY: `self.inner` is a live source for the whole call.
+        Ok(Self::from_box(owned(unsafe {
+            enough_child_create(&*self.inner)
+        })?))
+    }
+
+    /// A token that stops when this controller aborts.
+    #[napi]
+    pub fn token(&self) -> Result<CancellationToken> {
+        // SAFETY: as in `child()`.
+        Ok(CancellationToken::from_box(owned(unsafe {
+            enough_token_create(&*self.inner)
+        })?))
+    }
+
+    /// A token that also stops after `ms` milliseconds.
+    #[napi]
+    pub fn timeout(&self, ms: f64) -> Result<CancellationToken> {
+        let duration = duration(ms)?;
+        // SAFETY: as in `child()`.
+        let token = owned(unsafe { enough_token_create(&*self.inner) })?;
+        Ok(CancellationToken::new(token.with_timeout(duration)))
+    }
+}
+
+/// A token observing a controller's cancellation and an optional deadline.
+///
+/// After `close()` every method except `close()` and `closed` throws.
+#[napi]
+pub struct CancellationToken {
+    inner: Mutex<Option<Box<FfiCancellationToken>>>,
+}
+
+impl CancellationToken {
+    fn new(token: FfiCancellationToken) -> Self {
+        Self::from_box(Box::new(token))
+    }
+
+    fn from_box(token: Box<FfiCancellationToken>) -> Self {
+        Self {
+            inner: Mutex::new(Some(token)),
+        }
+    }
+
+    /// Run `f` on the token, or throw if it is closed.
+    fn with<R>(&self, f: impl FnOnce(&FfiCancellationToken) -> R) -> Result<R> {
+        let guard = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        match guard.as_deref() {
+            Some(token) => Ok(f(token)),
+            None => Err(Error::new(Status::InvalidArg, "token is closed")),
+        }
+    }
+}
+
+#[napi]
+impl CancellationToken {
+    /// A token that never stops (unless given a timeout).
+    #[napi(factory)]
+    pub fn never() -> Result<Self> {
+        Ok(Self::from_box(owned(enough_token_create_never())?))
+    }
+
+    /// Whether the controller aborted or the deadline has passed.
+    #[napi(getter)]
+    pub fn aborted(&self) -> Result<bool> {
+        self.with(|token| token.should_stop())
+    }
+
+    /// `null` while running, else `"cancelled"`, `"timed_out"` or
+    /// `"failed"`.
+    #[napi(getter)]
+    pub fn reason(&self) -> Result<Option<&'static str>> {
+        self.with(|token| token.check().err().map(reason_name))
+    }
+
+    /// Throw an `Error` whose message is the reason, like
+    /// `AbortSignal.throwIfAborted()`.
+    #[napi]
+    pub fn throw_if_aborted(&self) -> Result<()> {
+        match self.reason()? {
+            Some(reason) => Err(Error::new(Status::Cancelled, reason)),
+            None => Ok(()),
+        }
+    }
+
+    /// Milliseconds left until the deadline (`0` once passed), or `null`.
+    #[napi]
+    pub fn remaining_ms(&self) -> Result<Option<f64>> {
+        self.with(|token| token.remaining().map(|r| r.as_secs_f64() * 1000.0))
+    }
+
+    /// A new token sharing this one's controller, with a deadline `ms` from
+    /// now or this token's deadline, whichever is earlier.
+    #[napi]
+    pub fn with_timeout(&self, ms: f64) -> Result<CancellationToken> {
+        let duration = duration(ms)?;
+        Ok(Self::new(self.with(|token| token.with_timeout(duration))?))
+    }
+
+    /// The address of the underlying `FfiCancellationToken`, for Rust
+    /// addons that take `*const FfiCancellationToken`.
+    ///
+    /// Valid until this token is closed.
+    #[napi]
+    pub fn ptr(&self) -> Result<BigInt> {
+        self.with(|token| BigInt::from(token as *const FfiCancellationToken as u64))
+    }
+
+    /// Release the underlying token. Idempotent.
+    #[napi]
+    pub fn close(&self) {
+        let token = self.inner.lock().unwrap_or_else(|e| e.into_inner()).take();
+        drop(token);
+    }
+
+    /// Whether `close()` has been called.
+    #[napi(getter)]
+    pub fn closed(&self) -> bool {
+        self.inner
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .is_none()
+    }
+}
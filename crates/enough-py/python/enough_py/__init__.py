@@ -0,0 +1,8 @@
+"""Cooperative cancellation for Python hosts of Rust extensions.
+
+See the crate README for the pointer-lifetime rules of ``Token.ptr()``.
+"""
+
+from enough_py._enough import CancellationSource, Cancelled, Token
+
+__all__ = ["CancellationSource", "Cancelled", "Token"]
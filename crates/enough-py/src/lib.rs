@@ -0,0 +1,274 @@
+//! # enough-py
+//!
+//! Python bindings for [`enough_ffi`] cancellation sources and tokens.
+//!
+//! Python code that drives Rust extensions needs a way to cancel them. This
+//! module wraps the `enough-ffi` source and token in two Python classes,
+//! `CancellationSource` and `Token`, and hands the raw token pointer to any
+//! other extension that accepts `*const FfiCancellationToken`. Both sides
+//! share one cancellation state, so `source.cancel()` in Python stops the
+//! Rust work.
+//!
+//! ## Python Usage
+//!
+//! ```python
+//! from enough_py import CancellationSource, Cancelled
+//!
+//! source = CancellationSource()
+//!
+//! # A token with a 5 second deadline, closed when the block exits
+//! with source.timeout(5) as token:
+//!     my_codec.decode(data, token.ptr())
+//!
+//! # Poll from Python
+//! token = source.token()
+//! source.cancel()
+//! try:
+//!     token.check()
+//! except Cancelled as e:
+//!     assert e.args == ("cancelled",)
+//! ```
+//!
+//! ## Pointer Lifetime
+//!
+//! `Token.ptr()` returns the address of the token owned by the Python
+//! object. It stays valid until the token is closed (explicitly, by leaving
+//! its `with` block, or by garbage collection), so keep the `Token` alive
+//! for as long as the Rust side may use the pointer. The receiving
+//! extension reads it with
+//! [`FfiCancellationToken::from_ptr`](enough_ffi::FfiCancellationToken::from_ptr).
+//!
+//! ## Building
+//!
+//! The crate is built with maturin and is not part of the Cargo workspace;
+//! see `just py-test`.
+
+use std::ffi::CStr;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use enough::{Stop, StopReason};
+use enough_ffi::{
+    FfiCancellationSource, FfiCancellationToken, enough_cancellation_create, enough_child_create,
+    enough_last_error, enough_token_create, enough_token_create_never,
+};
+use pyo3::create_exception;
+use pyo3::exceptions::{PyException, PyMemoryError, PyValueError};
+use pyo3::prelude::*;
+
+create_exception!(
+    enough_py,
+    Cancelled,
+    PyException,
+    "Raised by `Token.check()` once the token has stopped; the reason is its only argument."
+);
+
+/// Take ownership of a pointer returned by an `enough_*_create` function.
+fn owned<T>(ptr: *mut T) -> PyResult<Box<T>> {
+    if ptr.is_null() {
+        // SAFETY: `enough_last_error` returns null or a static C string.
+        let message = unsafe { enough_last_error().as_ref() }
+            .map(|message| unsafe { CStr::from_ptr(message) }.to_string_lossy())
+            .unwrap_or("allocation failed".into());
+        return Err(PyMemoryError::new_err(message.into_owned()));
+    }
+    // SAFETY: non-null pointers from enough-ffi are allocated with the global
+    // allocator and `Layout::new::<T>()`, exactly like `Box::new`, and its
+    // destroy functions free them with `Box::from_raw`.
+    Ok(unsafe { Box::from_raw(ptr) })
+}
+
+/// A timeout in seconds from Python, rejecting negative and NaN values.
+fn duration(seconds: f64) -> PyResult<Duration> {
+    Duration::try_from_secs_f64(seconds)
+        .map_err(|_| PyValueError::new_err(format!("invalid timeout: {seconds} seconds")))
+}
+
+fn reason_name(reason: StopReason) -> &'static str {
+    match reason {
+        StopReason::Cancelled => "cancelled",
+        StopReason::TimedOut => "timed_out",
+        _ => "failed",
+    }
+}
+
+/// A cancellation source: cancel it to stop every token created from it.
+#[pyclass(name = "CancellationSource", module = "enough_py", frozen)]
+pub struct PySource {
+    inner: Box<FfiCancellationSource>,
+}
+
+#[pymethods]
+impl PySource {
+    #[new]
+    fn new() -> PyResult<Self> {
+        Ok(Self {
+            inner: owned(enough_cancellation_create())?,
+        })
+    }
+
+    /// Create a child source, cancelled with this one but not vice versa.
+    fn child(&self) -> PyResult<Self> {
+        // SAFETY: `self.inner` is a live source for the whole call.
+        Ok(Self {
+            inner: owned(unsafe { enough_child_create(&*self.inner) })?,
+        })
+    }
+
+    /// Cancel this source, its children and all their tokens.
+    ///
+    /// Callbacks registered on the tokens by Rust code run on this thread,
+    /// with the GIL released.
+    fn cancel(&self, py: Python<'_>) {
+        py.allow_threads(|| self.inner.cancel());
+    }
+
+    /// Whether this source (or an ancestor) has been cancelled.
+    fn is_cancelled(&self) -> bool {
+        self.inner.is_cancelled()
+    }
+
+    /// Create a token that stops when this source is cancelled.
+    fn token(&self) -> PyResult<PyToken> {
+        // SAFETY: as in `child()`.
+        Ok(PyToken::from_box(owned(unsafe {
+            enough_token_create(&*self.inner)
+        })?))
+    }
+
+    /// Create a token that also stops after `seconds`.
+    ///
+    /// The token is a context manager, so `with source.timeout(5) as token:`
+    /// closes it when the block exits.
+    fn timeout(&self, seconds: f64) -> PyResult<PyToken> {
+        let duration = duration(seconds)?;
+        // SAFETY: as in `child()`.
+        let token = owned(unsafe { enough_token_create(&*self.inner) })?;
+        Ok(PyToken::new(token.with_timeout(duration)))
+    }
+
+    fn __repr__(&self) -> String {
+        format!("CancellationSource(cancelled={})", self.is_cancelled())
+    }
+}
+
+/// A token observing a source's cancellation and an optional deadline.
+///
+/// A context manager: leaving a `with` block closes the token, after which
+/// every method except `close()` raises `ValueError`.
+#[pyclass(name = "Token", module = "enough_py", frozen)]
+pub struct PyToken {
+    inner: Mutex<Option<Box<FfiCancellationToken>>>,
+}
+
+impl PyToken {
+    fn new(token: FfiCancellationToken) -> Self {
+        Self::from_box(Box::new(token))
+    }
+
+    fn from_box(token: Box<FfiCancellationToken>) -> Self {
+        Self {
+            inner: Mutex::new(Some(token)),
+        }
+    }
+
+    /// Run `f` on the token, or raise `ValueError` if it is closed.
+    fn with<R>(&self, f: impl FnOnce(&FfiCancellationToken) -> R) -> PyResult<R> {
+        let guard = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        match guard.as_deref() {
+            Some(token) => Ok(f(token)),
+            None => Err(PyValueError::new_err("token is closed")),
+        }
+    }
+}
+
+#[pymethods]
+impl PyToken {
+    /// A token that never stops (unless given a timeout).
+    #[staticmethod]
+    fn never() -> PyResult<Self> {
+        Ok(Self::from_box(owned(enough_token_create_never())?))
+    }
+
+    /// Whether the source was cancelled or the deadline has passed.
+    fn is_cancelled(&self) -> PyResult<bool> {
+        self.with(|token| token.should_stop())
+    }
+
+    /// `None` while running, else `"cancelled"`, `"timed_out"` or `"failed"`.
+    fn reason(&self) -> PyResult<Option<&'static str>> {
+        self.with(|token| token.check().err().map(reason_name))
+    }
+
+    /// Raise `Cancelled(reason)` if the token has stopped.
+    fn check(&self) -> PyResult<()> {
+        match self.reason()? {
+            Some(reason) => Err(Cancelled::new_err(reason)),
+            None => Ok(()),
+        }
+    }
+
+    /// Seconds left until the deadline (`0.0` once passed), or `None`.
+    fn remaining(&self) -> PyResult<Option<f64>> {
+        self.with(|token| token.remaining().map(|r| r.as_secs_f64()))
+    }
+
+    /// A new token sharing this one's source, with a deadline `seconds`
+    /// from now or this token's deadline, whichever is earlier.
+    fn with_timeout(&self, seconds: f64) -> PyResult<Self> {
+        let duration = duration(seconds)?;
+        Ok(Self::new(self.with(|token| token.with_timeout(duration))?))
+    }
+
+    /// The address of the underlying `FfiCancellationToken`, for Rust
+    /// extensions that take `*const FfiCancellationToken`.
+    ///
+    /// Valid until this token is closed.
+    fn ptr(&self) -> PyResult<usize> {
+        self.with(|token| token as *const FfiCancellationToken as usize)
+    }
+
+    /// Release the underlying token. Idempotent.
+    fn close(&self) {
+        let token = self.inner.lock().unwrap_or_else(|e| e.into_inner()).take();
+        drop(token);
+    }
+
+    /// Whether `close()` has been called.
+    fn is_closed(&self) -> bool {
+        self.inner
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .is_none()
+    }
+
+    fn __enter__(slf: Py<Self>) -> Py<Self> {
+        slf
+    }
+
+    fn __exit__(
+        &self,
+        _exc_type: &Bound<'_, PyAny>,
+        _exc: &Bound<'_, PyAny>,
+        _traceback: &Bound<'_, PyAny>,
+    ) -> bool {
+        self.close();
+        false
+    }
+
+    fn __repr__(&self) -> String {
+        match self.reason() {
+            Ok(Some(reason)) => format!("Token(reason={reason:?})"),
+            Ok(None) => "Token(running)".into(),
+            Err(_) => "Token(closed)".into(),
+        }
+    }
+}
+
+#[pymodule]
+fn _enough(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PySource>()?;
+    m.add_class::<PyToken>()?;
+    m.add("Cancelled", m.py().get_type::<Cancelled>())?;
+    Ok(())
+}
@@ -0,0 +1,68 @@
+import time
+
+import pytest
+
+from enough_py import CancellationSource, Cancelled, Token
+
+
+def test_cancel_reaches_tokens_and_children():
+    source = CancellationSource()
+    child = source.child()
+    token = source.token()
+    child_token = child.token()
+    assert not token.is_cancelled()
+    assert token.reason() is None
+    token.check()
+
+    child.cancel()
+    assert child_token.is_cancelled()
+    assert not source.is_cancelled()
+
+    source.cancel()
+    assert token.is_cancelled()
+    assert token.reason() == "cancelled"
+    with pytest.raises(Cancelled) as info:
+        token.check()
+    assert info.value.args == ("cancelled",)
+
+
+def test_timeout_context_manager_closes_token():
+    source = CancellationSource()
+    with source.timeout(60) as token:
+        assert 0 < token.remaining() <= 60
+        assert token.ptr() != 0
+    assert token.is_closed()
+    with pytest.raises(ValueError):
+        token.ptr()
+    token.close()  # idempotent
+
+
+def test_deadline_times_out():
+    token = Token.never().with_timeout(0.001)
+    time.sleep(0.01)
+    assert token.reason() == "timed_out"
+    assert token.remaining() == 0.0
+
+
+def test_earliest_deadline_wins():
+    short = CancellationSource().timeout(1)
+    assert short.with_timeout(60).remaining() <= 1
+
+
+def test_never_token():
+    token = Token.never()
+    assert token.remaining() is None
+    assert not token.is_cancelled()
+    assert repr(token) == "Token(running)"
+
+
+@pytest.mark.parametrize("seconds", [-1.0, float("nan"), float("inf")])
+def test_invalid_timeout(seconds):
+    with pytest.raises(ValueError):
+        CancellationSource().timeout(seconds)
+
+
+def test_exit_does_not_swallow_exceptions():
+    with pytest.raises(KeyError):
+        with CancellationSource().timeout(5):
+            raise KeyError("boom")
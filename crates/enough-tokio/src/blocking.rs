@@ -0,0 +1,172 @@
+//! `spawn_blocking` that cancels the blocking side when you stop waiting.
+//!
+//! The usual pattern clones a stop into a `spawn_blocking` closure. The
+//! common mistake is to forget the other direction: when the async caller
+//! gives up (its task is aborted, a `select!` picks another branch, a
+//! request is dropped), the `JoinHandle` is dropped but the blocking work
+//! keeps running to completion. [`spawn_blocking_stoppable()`] hands the
+//! closure a child stop and returns a [`StoppableJoinHandle`] that cancels
+//! it on drop.
+//!
+//! Requires the `rt` feature.
+//!
+//! # Example
+//!
+//! ```rust
+//! use enough::Stop;
+//! use enough_tokio::spawn_blocking_stoppable;
+//! use tokio_util::sync::CancellationToken;
+//!
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() {
+//! let shutdown = CancellationToken::new();
+//!
+//! let handle = spawn_blocking_stoppable(&shutdown, |stop| {
+//!     let mut sum = 0u64;
+//!     for i in 0..1_000_000u64 {
+//!         if i % 1000 == 0 {
+//!             stop.check()?;
+//!         }
+//!         sum += i;
+//!     }
+//!     Ok::<_, enough::StopReason>(sum)
+//! });
+//!
+//! // Dropping `handle` instead of awaiting it would cancel the work
+//! assert!(handle.await.unwrap().is_ok());
+//! # }
+//! ```
+
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+use tokio::task::{JoinError, JoinHandle};
+use tokio_util::sync::{CancellationToken, DropGuard};
+
+use crate::TokioStop;
+
+/// Run `work` on tokio's blocking pool with a stop that is cancelled when
+/// `token` is, or when the returned handle is dropped.
+///
+/// `work` receives a [`TokioStop`] for a child of `token`, so cancelling
+/// the handle never cancels `token` itself. Unlike
+/// [`TokioStop::wrap_blocking()`], dropping the handle does not detach the
+/// work silently; call [`StoppableJoinHandle::detach()`] for that.
+///
+/// Must be called from within a tokio runtime.
+pub fn spawn_blocking_stoppable<F, T>(token: &CancellationToken, work: F) -> StoppableJoinHandle<T>
+where
+    F: FnOnce(TokioStop) -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let child = token.child_token();
+    let stop = TokioStop::new(child.clone());
+    StoppableJoinHandle {
+        handle: tokio::task::spawn_blocking(move || work(stop)),
+        token: child.clone(),
+        guard: child.drop_guard(),
+    }
+}
+
+/// A [`JoinHandle`] that cancels its blocking work's stop when dropped.
+///
+/// Returned by [`spawn_blocking_stoppable()`]. Awaiting it yields the same
+/// `Result<T, JoinError>` as the `JoinHandle`. Blocking tasks cannot be
+/// aborted, so the work itself decides how quickly to return once its stop
+/// is cancelled.
+#[must_use = "dropping the handle cancels the blocking work"]
+#[derive(Debug)]
+pub struct StoppableJoinHandle<T> {
+    handle: JoinHandle<T>,
+    token: CancellationToken,
+    guard: DropGuard,
+}
+
+impl<T> StoppableJoinHandle<T> {
+    /// Cancel the blocking work's stop without dropping the handle.
+    ///
+    /// The handle can still be awaited for whatever the work returns.
+    #[inline]
+    pub fn cancel(&self) {
+        self.token.cancel();
+    }
+
+    /// Whether the blocking work has finished.
+    #[inline]
+    pub fn is_finished(&self) -> bool {
+        self.handle.is_finished()
+    }
+
+    /// Give up cancel-on-drop and return the plain [`JoinHandle`].
+    ///
+    /// The work's stop is still cancelled when the parent token is.
+    pub fn detach(self) -> JoinHandle<T> {
+        self.guard.disarm();
+        self.handle
+    }
+}
+
+impl<T> Future for StoppableJoinHandle<T> {
+    type Output = Result<T, JoinError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.handle).poll(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use enough::{Stop, StopReason};
+
+    /// Spin until stopped, then report why.
+    fn until_stopped(stop: TokioStop) -> Result<(), StopReason> {
+        while !stop.should_stop() {
+            std::thread::yield_now();
+        }
+        stop.check()
+    }
+
+    #[tokio::test]
+    async fn parent_cancel_reaches_work() {
+        let token = CancellationToken::new();
+        let handle = spawn_blocking_stoppable(&token, until_stopped);
+        token.cancel();
+        assert_eq!(handle.await.unwrap(), Err(StopReason::Cancelled));
+    }
+
+    #[tokio::test]
+    async fn drop_cancels_work_but_not_parent() {
+        let token = CancellationToken::new();
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let handle = spawn_blocking_stoppable(&token, |stop| {
+            let _ = tx.send(until_stopped(stop));
+        });
+        drop(handle);
+        assert_eq!(rx.await.unwrap(), Err(StopReason::Cancelled));
+        assert!(!token.is_cancelled());
+    }
+
+    #[tokio::test]
+    async fn cancel_keeps_result() {
+        let token = CancellationToken::new();
+        let handle = spawn_blocking_stoppable(&token, until_stopped);
+        handle.cancel();
+        assert_eq!(handle.await.unwrap(), Err(StopReason::Cancelled));
+        assert!(!token.is_cancelled());
+    }
+
+    #[tokio::test]
+    async fn detach_disarms_drop() {
+        let token = CancellationToken::new();
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let handle = spawn_blocking_stoppable(&token, |stop| {
+            rx.blocking_recv().unwrap();
+            stop.should_stop()
+        });
+        let detached = handle.detach();
+        tx.send(()).unwrap();
+        assert!(!detached.await.unwrap());
+    }
+}
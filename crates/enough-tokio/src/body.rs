@@ -0,0 +1,208 @@
+//! Cancel work when an HTTP client goes away.
+//!
+//! When a client disconnects from a streaming response (long-poll, SSE,
+//! chunked downloads), hyper drops the response body. [`CancelOnDropBody`]
+//! wraps that body and cancels a [`TokioStop`] when it is dropped, so the
+//! task producing the stream stops promptly instead of computing data
+//! nobody will read.
+//!
+//! This works with any [`http_body::Body`], so it needs neither a tower
+//! layer nor a particular server framework. Requires the `http-body`
+//! feature.
+//!
+//! # Example
+//!
+//! ```rust
+//! use enough::Stop;
+//! use enough_tokio::{CancelOnDropBody, TokioStop};
+//! use http_body_util::Full;
+//! use tokio_util::sync::CancellationToken;
+//!
+//! let server = TokioStop::new(CancellationToken::new());
+//!
+//! // One child per response, so a disconnect cancels only this request
+//! let request = server.child();
+//! let body = CancelOnDropBody::new(Full::new(bytes::Bytes::from("data: hi\n\n")), request.clone());
+//!
+//! // ... hand `body` to hyper; feed it from work that checks `request` ...
+//!
+//! drop(body); // client disconnected
+//! assert!(request.should_stop());
+//! assert!(!server.should_stop());
+//! ```
+
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+use http_body::{Body, Frame, SizeHint};
+
+use crate::TokioStop;
+
+/// An [`http_body::Body`] that cancels a [`TokioStop`] when dropped.
+///
+/// Frames, trailers, size hints and errors pass through unchanged. The
+/// stop is cancelled when the body is dropped — whether because the
+/// client disconnected, the server shut the connection down, or the body
+/// was fully sent.
+///
+/// Since completion also cancels, pass a stop dedicated to this response
+/// (typically [`TokioStop::child()`]) rather than a shared one.
+///
+/// # Example
+///
+/// ```rust
+/// use enough::Stop;
+/// use enough_tokio::{CancelOnDropBody, TokioStop};
+/// use http_body_util::Empty;
+/// use tokio_util::sync::CancellationToken;
+///
+/// let stop = TokioStop::new(CancellationToken::new());
+/// let body = CancelOnDropBody::new(Empty::<bytes::Bytes>::new(), stop.clone());
+///
+/// assert!(!stop.should_stop());
+/// drop(body);
+/// assert!(stop.should_stop());
+/// ```
+pub struct CancelOnDropBody<B> {
+    body: Pin<Box<B>>,
+    stop: TokioStop,
+}
+
+impl<B> CancelOnDropBody<B> {
+    /// Wrap `body`, cancelling `stop` when the wrapper is dropped.
+    pub fn new(body: B, stop: TokioStop) -> Self {
+        Self {
+            body: Box::pin(body),
+            stop,
+        }
+    }
+
+    /// The stop that is cancelled on drop.
+    #[inline]
+    pub fn stop(&self) -> &TokioStop {
+        &self.stop
+    }
+}
+
+impl<B: Body> Body for CancelOnDropBody<B> {
+    type Data = B::Data;
+    type Error = B::Error;
+
+    #[inline]
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        self.get_mut().body.as_mut().poll_frame(cx)
+    }
+
+    #[inline]
+    fn is_end_stream(&self) -> bool {
+        self.body.is_end_stream()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> SizeHint {
+        self.body.size_hint()
+    }
+}
+
+impl<B> Drop for CancelOnDropBody<B> {
+    fn drop(&mut self) {
+        self.stop.cancel();
+    }
+}
+
+impl<B> std::fmt::Debug for CancelOnDropBody<B> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CancelOnDropBody")
+            .field("stop", &self.stop)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+    use enough::Stop;
+    use http_body_util::{BodyExt, Full};
+    use std::time::Duration;
+    use tokio_util::sync::CancellationToken;
+
+    /// A body that never produces a frame, like a long-poll with no events.
+    struct Pending;
+
+    impl Body for Pending {
+        type Data = Bytes;
+        type Error = std::convert::Infallible;
+
+        fn poll_frame(
+            self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+        ) -> Poll<Option<Result<Frame<Bytes>, Self::Error>>> {
+            Poll::Pending
+        }
+    }
+
+    fn stop() -> TokioStop {
+        TokioStop::new(CancellationToken::new())
+    }
+
+    #[tokio::test]
+    async fn passes_frames_through() {
+        let stop = stop();
+        let body = CancelOnDropBody::new(Full::new(Bytes::from_static(b"hello")), stop.clone());
+        assert_eq!(body.size_hint().exact(), Some(5));
+        assert!(!body.is_end_stream());
+
+        let collected = body.collect().await.unwrap().to_bytes();
+        assert_eq!(collected, "hello");
+        // `collect` consumed and dropped the body
+        assert!(stop.should_stop());
+    }
+
+    #[tokio::test]
+    async fn disconnect_stops_producer() {
+        let request = stop();
+        let producer = tokio::spawn({
+            let request = request.clone();
+            async move {
+                while !request.should_stop() {
+                    tokio::time::sleep(Duration::from_millis(1)).await;
+                }
+            }
+        });
+
+        let mut body = CancelOnDropBody::new(Pending, request.clone());
+        let frame = tokio::time::timeout(Duration::from_millis(10), body.frame()).await;
+        assert!(frame.is_err(), "pending body should not yield a frame");
+        assert!(!request.should_stop());
+
+        drop(body);
+        tokio::time::timeout(Duration::from_secs(5), producer)
+            .await
+            .expect("producer did not observe cancellation")
+            .unwrap();
+    }
+
+    #[test]
+    fn cancels_only_its_own_child() {
+        let server = stop();
+        let a = server.child();
+        let b = server.child();
+        drop(CancelOnDropBody::new(Pending, a.clone()));
+        assert!(a.should_stop());
+        assert!(!b.should_stop());
+        assert!(!server.should_stop());
+    }
+
+    #[test]
+    fn debug_and_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<CancelOnDropBody<Full<Bytes>>>();
+        let body = CancelOnDropBody::new(Pending, stop());
+        assert!(format!("{body:?}").contains("CancelOnDropBody"));
+        assert!(!body.stop().should_stop());
+    }
+}
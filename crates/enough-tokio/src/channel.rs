@@ -0,0 +1,258 @@
+//! Stop-aware channel receives.
+//!
+//! [`recv_or_stopped()`] waits for the next message on a tokio channel
+//! receiver or for a [`TokioStop`] to be cancelled, whichever comes first,
+//! replacing the pin-and-`select!` loop:
+//!
+//! ```rust
+//! use enough_tokio::{Recv, TokioStop, recv_or_stopped};
+//! use tokio::sync::mpsc;
+//!
+//! async fn process_messages(stop: TokioStop, mut rx: mpsc::Receiver<String>) {
+//!     loop {
+//!         match recv_or_stopped(&mut rx, &stop).await {
+//!             Recv::Msg(m) => println!("Got: {m}"),
+//!             Recv::Stopped(_) | Recv::Closed => break,
+//!             _ => {}
+//!         }
+//!     }
+//! }
+//! ```
+//!
+//! # Biasing
+//!
+//! The stop is polled before the channel on every wakeup. Once the stop is
+//! cancelled, `recv_or_stopped()` returns [`Recv::Stopped`] even if messages
+//! are still queued, and it never consumes a message it does not return.
+//! Drain the channel explicitly after stopping if queued messages matter.
+
+use core::future::{Future, poll_fn};
+use core::pin::pin;
+use core::task::Poll;
+
+use enough::StopReason;
+use tokio::sync::{broadcast, mpsc, watch};
+
+use crate::TokioStop;
+
+/// Outcome of [`recv_or_stopped()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Recv<T> {
+    /// A message was received.
+    Msg(T),
+    /// The stop was cancelled first.
+    Stopped(StopReason),
+    /// The channel is closed and empty.
+    Closed,
+    /// A broadcast receiver fell behind and this many messages were skipped.
+    ///
+    /// The next receive continues with the oldest retained message.
+    Lagged(u64),
+}
+
+impl<T> Recv<T> {
+    /// The message, if one was received.
+    #[inline]
+    pub fn msg(self) -> Option<T> {
+        match self {
+            Recv::Msg(msg) => Some(msg),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if the stop was cancelled.
+    #[inline]
+    pub fn is_stopped(&self) -> bool {
+        matches!(self, Recv::Stopped(_))
+    }
+}
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// Tokio channel receivers supported by [`recv_or_stopped()`].
+///
+/// Implemented for `mpsc::Receiver`, `mpsc::UnboundedReceiver`,
+/// `broadcast::Receiver` and `watch::Receiver`. Sealed.
+pub trait StopReceiver: sealed::Sealed {
+    /// The message type yielded in [`Recv::Msg`].
+    type Msg;
+
+    /// Receive the next message.
+    #[doc(hidden)]
+    fn recv_next(&mut self) -> impl Future<Output = Recv<Self::Msg>> + Send + '_;
+}
+
+impl<T: Send> sealed::Sealed for mpsc::Receiver<T> {}
+impl<T: Send> StopReceiver for mpsc::Receiver<T> {
+    type Msg = T;
+
+    async fn recv_next(&mut self) -> Recv<T> {
+        self.recv().await.map_or(Recv::Closed, Recv::Msg)
+    }
+}
+
+impl<T: Send> sealed::Sealed for mpsc::UnboundedReceiver<T> {}
+impl<T: Send> StopReceiver for mpsc::UnboundedReceiver<T> {
+    type Msg = T;
+
+    async fn recv_next(&mut self) -> Recv<T> {
+        self.recv().await.map_or(Recv::Closed, Recv::Msg)
+    }
+}
+
+impl<T: Clone + Send> sealed::Sealed for broadcast::Receiver<T> {}
+impl<T: Clone + Send> StopReceiver for broadcast::Receiver<T> {
+    type Msg = T;
+
+    async fn recv_next(&mut self) -> Recv<T> {
+        match self.recv().await {
+            Ok(msg) => Recv::Msg(msg),
+            Err(broadcast::error::RecvError::Closed) => Recv::Closed,
+            Err(broadcast::error::RecvError::Lagged(skipped)) => Recv::Lagged(skipped),
+        }
+    }
+}
+
+impl<T: Clone + Send + Sync> sealed::Sealed for watch::Receiver<T> {}
+/// A watch receiver yields the new value each time it changes.
+impl<T: Clone + Send + Sync> StopReceiver for watch::Receiver<T> {
+    type Msg = T;
+
+    async fn recv_next(&mut self) -> Recv<T> {
+        match self.changed().await {
+            Ok(()) => Recv::Msg(self.borrow_and_update().clone()),
+            Err(_) => Recv::Closed,
+        }
+    }
+}
+
+/// Receive the next message from `rx`, or return early if `stop` is
+/// cancelled.
+///
+/// Works with tokio `mpsc` (bounded and unbounded), `broadcast` and `watch`
+/// receivers. For `watch`, a "message" is the new value after a change.
+///
+/// The stop is polled first, so it wins ties and a queued message is not
+/// consumed once the stop is cancelled. Cancel-safe: if the returned
+/// future is dropped, no message is lost.
+///
+/// # Example
+///
+/// ```rust
+/// use enough_tokio::{Recv, TokioStop, recv_or_stopped};
+/// use tokio::sync::mpsc;
+/// use tokio_util::sync::CancellationToken;
+///
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// let stop = TokioStop::new(CancellationToken::new());
+/// let (tx, mut rx) = mpsc::channel(4);
+///
+/// tx.send(1).await.unwrap();
+/// assert_eq!(recv_or_stopped(&mut rx, &stop).await, Recv::Msg(1));
+///
+/// stop.cancel();
+/// tx.send(2).await.unwrap();
+/// assert!(recv_or_stopped(&mut rx, &stop).await.is_stopped());
+/// # }
+/// ```
+pub async fn recv_or_stopped<R: StopReceiver>(rx: &mut R, stop: &TokioStop) -> Recv<R::Msg> {
+    let mut cancelled = pin!(stop.token().cancelled());
+    let mut recv = pin!(rx.recv_next());
+    poll_fn(|cx| {
+        if cancelled.as_mut().poll(cx).is_ready() {
+            return Poll::Ready(Recv::Stopped(StopReason::Cancelled));
+        }
+        recv.as_mut().poll(cx)
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tokio_util::sync::CancellationToken;
+
+    fn stop() -> TokioStop {
+        TokioStop::new(CancellationToken::new())
+    }
+
+    #[tokio::test]
+    async fn mpsc_messages_then_closed() {
+        let stop = stop();
+        let (tx, mut rx) = mpsc::channel(4);
+        tx.send(1).await.unwrap();
+        tx.send(2).await.unwrap();
+        drop(tx);
+
+        assert_eq!(recv_or_stopped(&mut rx, &stop).await, Recv::Msg(1));
+        assert_eq!(recv_or_stopped(&mut rx, &stop).await, Recv::Msg(2));
+        assert_eq!(recv_or_stopped(&mut rx, &stop).await, Recv::Closed);
+    }
+
+    #[tokio::test]
+    async fn stop_wins_over_queued_message() {
+        let stop = stop();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        tx.send(42).unwrap();
+        stop.cancel();
+
+        let result = recv_or_stopped(&mut rx, &stop).await;
+        assert_eq!(result, Recv::Stopped(StopReason::Cancelled));
+        // The queued message was not consumed
+        assert_eq!(rx.try_recv(), Ok(42));
+    }
+
+    #[tokio::test]
+    async fn cancellation_wakes_pending_recv() {
+        let stop = stop();
+        let (_tx, mut rx) = mpsc::channel::<()>(1);
+
+        let canceller = stop.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            canceller.cancel();
+        });
+
+        assert!(recv_or_stopped(&mut rx, &stop).await.is_stopped());
+    }
+
+    #[tokio::test]
+    async fn broadcast_reports_lag() {
+        let stop = stop();
+        let (tx, mut rx) = broadcast::channel(2);
+        for i in 0..4 {
+            tx.send(i).unwrap();
+        }
+
+        assert_eq!(recv_or_stopped(&mut rx, &stop).await, Recv::Lagged(2));
+        assert_eq!(recv_or_stopped(&mut rx, &stop).await, Recv::Msg(2));
+        assert_eq!(recv_or_stopped(&mut rx, &stop).await.msg(), Some(3));
+        drop(tx);
+        assert_eq!(recv_or_stopped(&mut rx, &stop).await, Recv::Closed);
+    }
+
+    #[tokio::test]
+    async fn watch_yields_changes() {
+        let stop = stop();
+        let (tx, mut rx) = watch::channel("initial");
+
+        tx.send("updated").unwrap();
+        assert_eq!(recv_or_stopped(&mut rx, &stop).await, Recv::Msg("updated"));
+
+        drop(tx);
+        assert_eq!(recv_or_stopped(&mut rx, &stop).await, Recv::Closed);
+    }
+
+    #[tokio::test]
+    async fn future_is_send() {
+        fn assert_send<T: Send>(_: T) {}
+        let stop = stop();
+        let (_tx, mut rx) = mpsc::channel::<u8>(1);
+        assert_send(recv_or_stopped(&mut rx, &stop));
+    }
+}
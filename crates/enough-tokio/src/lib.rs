@@ -58,6 +58,26 @@
 #![warn(missing_docs)]
 #![warn(clippy::all)]
 
+#[cfg(feature = "rt")]
+mod blocking;
+#[cfg(feature = "http-body")]
+mod body;
+mod channel;
+#[cfg(feature = "stream")]
+mod stream;
+
+#[cfg(feature = "rt")]
+pub use blocking::{StoppableJoinHandle, spawn_blocking_stoppable};
+#[cfg(feature = "http-body")]
+pub use body::CancelOnDropBody;
+pub use channel::{Recv, StopReceiver, recv_or_stopped};
+#[cfg(feature = "stream")]
+pub use stream::{StopStreamExt, TakeUntilStopped, TimeoutItems};
+
+use core::future::{Future, poll_fn};
+use core::pin::pin;
+use core::task::Poll;
+
 use enough::{Stop, StopReason};
 use tokio_util::sync::CancellationToken;
 
@@ -114,6 +134,73 @@ impl TokioStop {
         self.token.cancelled().await;
     }
 
+    /// Run `future` until it completes or this stop is cancelled.
+    ///
+    /// Returns `None` if cancelled first. Cancellation is checked before
+    /// each poll of `future`, so an already-cancelled stop never polls it.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use enough_tokio::TokioStop;
+    /// use tokio_util::sync::CancellationToken;
+    ///
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// let stop = TokioStop::new(CancellationToken::new());
+    /// assert_eq!(stop.run_until_cancelled(async { 42 }).await, Some(42));
+    ///
+    /// stop.cancel();
+    /// assert_eq!(stop.run_until_cancelled(async { 42 }).await, None);
+    /// # }
+    /// ```
+    pub async fn run_until_cancelled<F: Future>(&self, future: F) -> Option<F::Output> {
+        let mut cancelled = pin!(self.token.cancelled());
+        let mut future = pin!(future);
+        poll_fn(|cx| {
+            if cancelled.as_mut().poll(cx).is_ready() {
+                return Poll::Ready(None);
+            }
+            future.as_mut().poll(cx).map(Some)
+        })
+        .await
+    }
+
+    /// Run `work` on tokio's blocking pool, passing it a clone of this stop.
+    ///
+    /// Shorthand for cloning the stop into a `spawn_blocking` closure.
+    /// Must be called from within a tokio runtime.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use enough::Stop;
+    /// use enough_tokio::TokioStop;
+    /// use tokio_util::sync::CancellationToken;
+    ///
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// let stop = TokioStop::new(CancellationToken::new());
+    /// let handle = stop.wrap_blocking(|stop| {
+    ///     let mut count = 0u32;
+    ///     while !stop.should_stop() && count < 1000 {
+    ///         count += 1;
+    ///     }
+    ///     count
+    /// });
+    /// assert_eq!(handle.await.unwrap(), 1000);
+    /// # }
+    /// ```
+    #[cfg(feature = "rt")]
+    pub fn wrap_blocking<F, T>(&self, work: F) -> tokio::task::JoinHandle<T>
+    where
+        F: FnOnce(TokioStop) -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let stop = self.clone();
+        tokio::task::spawn_blocking(move || work(stop))
+    }
+
     /// Create a child token that is cancelled when this one is.
     #[inline]
     pub fn child(&self) -> TokioStop {
@@ -294,6 +381,46 @@ mod tests {
         assert!(result.is_ok() || result == Err("cancelled"));
     }
 
+    #[tokio::test]
+    async fn run_until_cancelled_completes_or_stops() {
+        let stop = TokioStop::new(CancellationToken::new());
+        assert_eq!(stop.run_until_cancelled(async { 1 }).await, Some(1));
+
+        let canceller = stop.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            canceller.cancel();
+        });
+        let result = stop
+            .run_until_cancelled(tokio::time::sleep(std::time::Duration::from_secs(10)))
+            .await;
+        assert_eq!(result, None);
+    }
+
+    #[tokio::test]
+    async fn run_until_cancelled_never_polls_when_cancelled() {
+        let stop = TokioStop::new(CancellationToken::new());
+        stop.cancel();
+        let result = stop
+            .run_until_cancelled(async { unreachable!("polled after cancel") })
+            .await;
+        assert_eq!(result, None::<()>);
+    }
+
+    #[cfg(feature = "rt")]
+    #[tokio::test]
+    async fn wrap_blocking_injects_stop() {
+        let stop = TokioStop::new(CancellationToken::new());
+        let handle = stop.wrap_blocking(|stop| {
+            while !stop.should_stop() {
+                std::thread::yield_now();
+            }
+            stop.check()
+        });
+        stop.cancel();
+        assert_eq!(handle.await.unwrap(), Err(StopReason::Cancelled));
+    }
+
     #[tokio::test]
     async fn select_with_cancellation() {
         let token = CancellationToken::new();
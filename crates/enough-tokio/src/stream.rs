@@ -0,0 +1,319 @@
+//! Stop-aware stream adapters.
+//!
+//! [`StopStreamExt`] gives async pipelines the vocabulary of the sync
+//! iterator adapters in `almost_enough::StopIterExt`:
+//!
+//! | Stream adapter | Iterator counterpart | On stop |
+//! |----------------|----------------------|---------|
+//! | [`take_until_stopped()`](StopStreamExt::take_until_stopped) | `until_stopped()` | ends; reason in [`TakeUntilStopped::stopped()`] |
+//! | [`timeout_items()`](StopStreamExt::timeout_items) | `stop_every()` | yields one `Err(reason)`, then ends |
+//!
+//! `take_until_stopped()` takes a [`TokioStop`] and wakes as soon as it is
+//! cancelled, even while the stream is waiting for its next item.
+//! `timeout_items()` accepts any [`Stop`] (a `WithTimeout` deadline, a
+//! `Stopper`, ...) and checks it each time the stream is polled; it cannot
+//! interrupt a stream that stays pending, so pair it with
+//! `take_until_stopped()` or `tokio::time::timeout` when items can stall.
+//!
+//! Requires the `stream` feature.
+//!
+//! # Example
+//!
+//! ```rust
+//! use enough_tokio::{StopStreamExt, TokioStop};
+//! use futures_core::Stream;
+//!
+//! async fn drain<S: Stream<Item = u32>>(items: S, stop: TokioStop) -> Vec<u32> {
+//!     let mut items = std::pin::pin!(items.take_until_stopped(stop));
+//!     let mut out = Vec::new();
+//!     while let Some(item) = std::future::poll_fn(|cx| items.as_mut().poll_next(cx)).await {
+//!         out.push(item);
+//!     }
+//!     out
+//! }
+//! ```
+
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+use enough::{Stop, StopReason};
+use futures_core::{FusedStream, Stream};
+
+use crate::TokioStop;
+
+/// Extension trait adding cancellation-aware adapters to streams.
+///
+/// Automatically implemented for all [`Stream`]s. Requires the `stream`
+/// feature.
+pub trait StopStreamExt: Stream + Sized {
+    /// Yield items until `stop` is cancelled, then end the stream.
+    ///
+    /// The stop is polled before the stream on every wakeup, so no item is
+    /// pulled from the stream once it is cancelled. The reason is available
+    /// afterwards via [`TakeUntilStopped::stopped()`].
+    #[inline]
+    fn take_until_stopped(self, stop: TokioStop) -> TakeUntilStopped<Self> {
+        let token = stop.into_token();
+        TakeUntilStopped {
+            stream: Box::pin(self),
+            cancelled: Some(Box::pin(async move { token.cancelled().await })),
+            stopped: None,
+            done: false,
+        }
+    }
+
+    /// Yield `Ok(item)` until `stop` trips, then yield one `Err(reason)`
+    /// and end.
+    ///
+    /// `stop` is checked each time the stream is polled, before the stream
+    /// itself.
+    #[inline]
+    fn timeout_items<S: Stop>(self, stop: S) -> TimeoutItems<Self, S> {
+        TimeoutItems {
+            stream: Box::pin(self),
+            stop,
+            done: false,
+        }
+    }
+}
+
+impl<St: Stream> StopStreamExt for St {}
+
+type Cancelled = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// Stream returned by [`StopStreamExt::take_until_stopped()`].
+#[must_use = "streams do nothing unless polled"]
+pub struct TakeUntilStopped<St> {
+    stream: Pin<Box<St>>,
+    cancelled: Option<Cancelled>,
+    stopped: Option<StopReason>,
+    done: bool,
+}
+
+impl<St> TakeUntilStopped<St> {
+    /// The reason the stream ended early, or `None` if it hasn't.
+    #[inline]
+    pub fn stopped(&self) -> Option<StopReason> {
+        self.stopped
+    }
+}
+
+impl<St: Stream> Stream for TakeUntilStopped<St> {
+    type Item = St::Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<St::Item>> {
+        let this = self.get_mut();
+        if this.done {
+            return Poll::Ready(None);
+        }
+        if let Some(cancelled) = &mut this.cancelled {
+            if cancelled.as_mut().poll(cx).is_ready() {
+                this.cancelled = None;
+                this.stopped = Some(StopReason::Cancelled);
+                this.done = true;
+                return Poll::Ready(None);
+            }
+        }
+        let item = this.stream.as_mut().poll_next(cx);
+        if let Poll::Ready(None) = item {
+            this.done = true;
+        }
+        item
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.done {
+            (0, Some(0))
+        } else {
+            (0, self.stream.size_hint().1)
+        }
+    }
+}
+
+impl<St: Stream> FusedStream for TakeUntilStopped<St> {
+    fn is_terminated(&self) -> bool {
+        self.done
+    }
+}
+
+impl<St> core::fmt::Debug for TakeUntilStopped<St> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("TakeUntilStopped")
+            .field("stopped", &self.stopped)
+            .field("done", &self.done)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Stream returned by [`StopStreamExt::timeout_items()`].
+#[must_use = "streams do nothing unless polled"]
+pub struct TimeoutItems<St, S> {
+    stream: Pin<Box<St>>,
+    stop: S,
+    done: bool,
+}
+
+impl<St, S> TimeoutItems<St, S> {
+    /// Get a reference to the stop.
+    #[inline]
+    pub fn stop(&self) -> &S {
+        &self.stop
+    }
+}
+
+impl<St: Stream, S: Stop + Unpin> Stream for TimeoutItems<St, S> {
+    type Item = Result<St::Item, StopReason>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if this.done {
+            return Poll::Ready(None);
+        }
+        if let Err(reason) = this.stop.check() {
+            this.done = true;
+            return Poll::Ready(Some(Err(reason)));
+        }
+        match this.stream.as_mut().poll_next(cx) {
+            Poll::Ready(Some(item)) => Poll::Ready(Some(Ok(item))),
+            Poll::Ready(None) => {
+                this.done = true;
+                Poll::Ready(None)
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.done {
+            return (0, Some(0));
+        }
+        // May end early on a stop, and may add one `Err`
+        let (_, upper) = self.stream.size_hint();
+        (0, upper.and_then(|n| n.checked_add(1)))
+    }
+}
+
+impl<St: Stream, S: Stop + Unpin> FusedStream for TimeoutItems<St, S> {
+    fn is_terminated(&self) -> bool {
+        self.done
+    }
+}
+
+impl<St, S: core::fmt::Debug> core::fmt::Debug for TimeoutItems<St, S> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("TimeoutItems")
+            .field("stop", &self.stop)
+            .field("done", &self.done)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::future::poll_fn;
+    use std::time::Duration;
+    use tokio::sync::mpsc;
+    use tokio_util::sync::CancellationToken;
+
+    /// Minimal stream over an iterator.
+    struct Iter<I>(I);
+
+    impl<I: Iterator + Unpin> Stream for Iter<I> {
+        type Item = I::Item;
+
+        fn poll_next(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Option<I::Item>> {
+            Poll::Ready(self.get_mut().0.next())
+        }
+    }
+
+    /// Stream over an mpsc receiver; pending while the channel is empty.
+    struct Channel<T>(mpsc::UnboundedReceiver<T>);
+
+    impl<T> Stream for Channel<T> {
+        type Item = T;
+
+        fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+            self.get_mut().0.poll_recv(cx)
+        }
+    }
+
+    async fn next<St: Stream + Unpin>(stream: &mut St) -> Option<St::Item> {
+        poll_fn(|cx| Pin::new(&mut *stream).poll_next(cx)).await
+    }
+
+    #[tokio::test]
+    async fn take_until_stopped_yields_all_when_not_cancelled() {
+        let stop = TokioStop::new(CancellationToken::new());
+        let mut stream = Iter(0..3).take_until_stopped(stop);
+        let mut items = Vec::new();
+        while let Some(item) = next(&mut stream).await {
+            items.push(item);
+        }
+        assert_eq!(items, [0, 1, 2]);
+        assert_eq!(stream.stopped(), None);
+        assert!(stream.is_terminated());
+    }
+
+    #[tokio::test]
+    async fn take_until_stopped_wakes_pending_stream() {
+        let token = CancellationToken::new();
+        let (tx, rx) = mpsc::unbounded_channel::<u32>();
+        let mut stream = Channel(rx).take_until_stopped(TokioStop::new(token.clone()));
+
+        tx.send(1).unwrap();
+        assert_eq!(next(&mut stream).await, Some(1));
+
+        // The channel stays empty; only the cancellation can end the wait
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            token.cancel();
+        });
+        assert_eq!(next(&mut stream).await, None);
+        assert_eq!(stream.stopped(), Some(StopReason::Cancelled));
+        drop(tx);
+    }
+
+    #[tokio::test]
+    async fn take_until_stopped_prefers_stop_over_ready_items() {
+        let token = CancellationToken::new();
+        token.cancel();
+        let mut stream = Iter(0..3).take_until_stopped(TokioStop::new(token));
+        assert_eq!(next(&mut stream).await, None);
+        assert_eq!(stream.size_hint(), (0, Some(0)));
+        assert!(format!("{stream:?}").contains("Cancelled"));
+    }
+
+    #[tokio::test]
+    async fn timeout_items_yields_err_once() {
+        let token = CancellationToken::new();
+        let stop = TokioStop::new(token.clone());
+        let mut stream = Iter(0..10).timeout_items(stop);
+
+        assert_eq!(next(&mut stream).await, Some(Ok(0)));
+        assert_eq!(next(&mut stream).await, Some(Ok(1)));
+        token.cancel();
+        assert_eq!(next(&mut stream).await, Some(Err(StopReason::Cancelled)));
+        assert_eq!(next(&mut stream).await, None);
+        assert!(stream.is_terminated());
+        assert!(stream.stop().should_stop());
+    }
+
+    #[tokio::test]
+    async fn timeout_items_ends_with_stream() {
+        let mut stream = Iter(0..2).timeout_items(enough::Unstoppable);
+        assert_eq!(stream.size_hint(), (0, None));
+        assert_eq!(next(&mut stream).await, Some(Ok(0)));
+        assert_eq!(next(&mut stream).await, Some(Ok(1)));
+        assert_eq!(next(&mut stream).await, None);
+        assert_eq!(next(&mut stream).await, None);
+    }
+
+    #[test]
+    fn adapters_are_send() {
+        fn assert_send<T: Send>() {}
+        assert_send::<TakeUntilStopped<Iter<std::vec::IntoIter<u8>>>>();
+        assert_send::<TimeoutItems<Iter<std::vec::IntoIter<u8>>, TokioStop>>();
+    }
+}
@@ -62,6 +62,7 @@
 //!
 //! - **None (default)** - Core trait only, `no_std` compatible
 //! - **`std`** - Implies `alloc` (kept for downstream compatibility)
+//! - **`defmt`** - [`defmt::Format`](https://docs.rs/defmt/latest/defmt/trait.Format.html) for `StopReason`, for logging on microcontrollers
 
 #![cfg_attr(not(feature = "std"), no_std)]
 #![forbid(unsafe_code)]
@@ -38,6 +38,13 @@ pub enum StopReason {
     /// This means a timeout was set and the deadline passed before the
     /// operation completed.
     TimedOut,
+
+    /// The cancellation check itself failed.
+    ///
+    /// This means the stop could not determine whether to continue — for
+    /// example a user-supplied probe panicked — and conservatively reported
+    /// that the operation should stop.
+    Failed,
 }
 
 impl StopReason {
@@ -47,6 +54,7 @@ impl StopReason {
     /// succeed with a longer timeout or under less load.
     ///
     /// `Cancelled` is not transient - it represents an explicit decision to stop.
+    /// `Failed` is not transient either - a broken check will stay broken.
     #[inline]
     pub fn is_transient(&self) -> bool {
         matches!(self, Self::TimedOut)
@@ -63,6 +71,12 @@ impl StopReason {
     pub fn is_timed_out(&self) -> bool {
         matches!(self, Self::TimedOut)
     }
+
+    /// Returns `true` if the cancellation check itself failed.
+    #[inline]
+    pub fn is_failed(&self) -> bool {
+        matches!(self, Self::Failed)
+    }
 }
 
 impl fmt::Display for StopReason {
@@ -70,6 +84,19 @@ impl fmt::Display for StopReason {
         match self {
             Self::Cancelled => write!(f, "operation cancelled"),
             Self::TimedOut => write!(f, "operation timed out"),
+            Self::Failed => write!(f, "cancellation check failed"),
+        }
+    }
+}
+
+/// Formats like [`Display`](fmt::Display), with interned strings.
+#[cfg(feature = "defmt")]
+impl defmt::Format for StopReason {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        match self {
+            Self::Cancelled => defmt::write!(f, "operation cancelled"),
+            Self::TimedOut => defmt::write!(f, "operation timed out"),
+            Self::Failed => defmt::write!(f, "cancellation check failed"),
         }
     }
 }
@@ -84,6 +111,10 @@ mod tests {
         use alloc::format;
         assert_eq!(format!("{}", StopReason::Cancelled), "operation cancelled");
         assert_eq!(format!("{}", StopReason::TimedOut), "operation timed out");
+        assert_eq!(
+            format!("{}", StopReason::Failed),
+            "cancellation check failed"
+        );
     }
 
     #[test]
@@ -97,6 +128,15 @@ mod tests {
     fn stop_reason_is_transient() {
         assert!(!StopReason::Cancelled.is_transient());
         assert!(StopReason::TimedOut.is_transient());
+        assert!(!StopReason::Failed.is_transient());
+    }
+
+    #[test]
+    fn stop_reason_predicates() {
+        assert!(StopReason::Failed.is_failed());
+        assert!(!StopReason::Failed.is_cancelled());
+        assert!(!StopReason::Failed.is_timed_out());
+        assert!(!StopReason::Cancelled.is_failed());
     }
 
     #[test]
@@ -106,6 +146,14 @@ mod tests {
         assert_eq!(a, b);
     }
 
+    #[cfg(feature = "defmt")]
+    #[test]
+    fn stop_reason_is_defmt_format() {
+        // Encoding needs a global logger; check the impl exists.
+        fn assert_format<T: defmt::Format>(_: T) {}
+        assert_format(StopReason::Cancelled);
+    }
+
     #[test]
     fn stop_reason_hash() {
         use core::hash::{Hash, Hasher};
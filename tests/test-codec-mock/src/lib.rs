@@ -2,6 +2,7 @@
 #![allow(unused_imports, dead_code)]
 
 use almost_enough::{Stop, StopReason, Stopper, TimeoutExt, Unstoppable};
+use enough_derive::FromStopReason;
 use std::sync::Arc;
 use std::thread;
 use std::time::Duration;
@@ -11,29 +12,13 @@ use std::time::Duration;
 // ============================================================================
 
 /// Mock codec error type
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, FromStopReason)]
 pub enum CodecError {
     Stopped(StopReason),
     InvalidData(&'static str),
     OutputTooLarge,
 }
 
-impl From<StopReason> for CodecError {
-    fn from(r: StopReason) -> Self {
-        CodecError::Stopped(r)
-    }
-}
-
-impl CodecError {
-    fn is_cancelled(&self) -> bool {
-        matches!(self, CodecError::Stopped(StopReason::Cancelled))
-    }
-
-    fn is_timed_out(&self) -> bool {
-        matches!(self, CodecError::Stopped(StopReason::TimedOut))
-    }
-}
-
 // ============================================================================
 // Mock Decoder
 // ============================================================================
@@ -0,0 +1,132 @@
+//! Codegen tests: the hot check paths must stay a load and a branch.
+//!
+//! Each `probe_*` function below is `#[no_mangle]` so it survives in the
+//! emitted assembly under a stable name. The test builds this crate in
+//! release mode with `--emit asm` and checks every probe against a budget:
+//!
+//! | Probe | Guarantee |
+//! |-------|-----------|
+//! | `probe_unstoppable_check` | `Unstoppable::check()` is a constant |
+//! | `probe_stop_ref_check` | `StopRef::check()` is one load, no calls |
+//! | `probe_stop_ref_should_stop` | `StopRef::should_stop()` is one load, no calls |
+//! | `probe_stopper_check` | `Stopper::check()` has no calls, panics or jump tables |
+//! | `probe_stopper_should_stop` | `Stopper::should_stop()` has no calls, panics or jump tables |
+//!
+//! A regression (an `#[inline]` lost, an ordering matched at runtime, a
+//! panic path added) shows up as a `call`, an indirect jump or a blown
+//! instruction budget. Instruction selection changes between compiler
+//! releases, so the test is ignored by default and run on stable x86_64
+//! only:
+//!
+//! ```text
+//! cargo test -p test-codegen -- --ignored
+//! ```
+
+use almost_enough::{Stop, StopReason, StopRef, Stopper, Unstoppable};
+
+#[unsafe(no_mangle)]
+pub fn probe_unstoppable_check(stop: &Unstoppable) -> Result<(), StopReason> {
+    stop.check()
+}
+
+#[unsafe(no_mangle)]
+pub fn probe_stop_ref_check(stop: &StopRef<'_>) -> Result<(), StopReason> {
+    stop.check()
+}
+
+#[unsafe(no_mangle)]
+pub fn probe_stop_ref_should_stop(stop: &StopRef<'_>) -> bool {
+    stop.should_stop()
+}
+
+#[unsafe(no_mangle)]
+pub fn probe_stopper_check(stop: &Stopper) -> Result<(), StopReason> {
+    stop.check()
+}
+
+#[unsafe(no_mangle)]
+pub fn probe_stopper_should_stop(stop: &Stopper) -> bool {
+    stop.should_stop()
+}
+
+#[cfg(all(test, target_arch = "x86_64"))]
+mod tests {
+    use std::path::{Path, PathBuf};
+    use std::process::Command;
+
+    /// Maximum instructions per probe, including the `ret`.
+    ///
+    /// `Stopper` pays for decoding the reason from its packed state word
+    /// and for reading its configured load ordering; the rest is the load.
+    const BUDGETS: &[(&str, usize)] = &[
+        ("probe_unstoppable_check", 2),
+        ("probe_stop_ref_check", 8),
+        ("probe_stop_ref_should_stop", 5),
+        ("probe_stopper_check", 20),
+        ("probe_stopper_should_stop", 10),
+    ];
+
+    /// Build this crate in release mode and return its assembly.
+    fn emit_asm() -> String {
+        let manifest = Path::new(env!("CARGO_MANIFEST_DIR"));
+        // A separate target dir: the outer `cargo test` holds the lock on
+        // the workspace one
+        let target = manifest.join("../../target/codegen");
+        let status = Command::new(env!("CARGO"))
+            .args(["rustc", "--release", "--lib", "--manifest-path"])
+            .arg(manifest.join("Cargo.toml"))
+            .args(["--", "--emit", "asm", "-C", "codegen-units=1"])
+            .env("CARGO_TARGET_DIR", &target)
+            .status()
+            .expect("failed to run cargo");
+        assert!(status.success(), "building the probes failed");
+
+        let newest: PathBuf = std::fs::read_dir(target.join("release/deps"))
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| {
+                let name = path.file_name().unwrap().to_string_lossy();
+                name.starts_with("test_codegen-") && name.ends_with(".s")
+            })
+            .max_by_key(|path| path.metadata().unwrap().modified().unwrap())
+            .expect("no assembly emitted");
+        std::fs::read_to_string(newest).unwrap()
+    }
+
+    /// The instructions of `symbol`, without labels and directives.
+    fn instructions<'a>(asm: &'a str, symbol: &str) -> Vec<&'a str> {
+        let start = format!("{symbol}:");
+        asm.lines()
+            .skip_while(|line| *line != start)
+            .skip(1)
+            .take_while(|line| !line.starts_with(".Lfunc_end"))
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('.') && !line.ends_with(':'))
+            .filter(|line| !line.starts_with('#'))
+            .collect()
+    }
+
+    #[test]
+    #[ignore = "codegen varies by toolchain; run with --ignored on stable x86_64"]
+    fn hot_paths_stay_minimal() {
+        let asm = emit_asm();
+        let mut failures = Vec::new();
+        for &(probe, budget) in BUDGETS {
+            let body = instructions(&asm, probe);
+            let listing = body.join("\n    ");
+            if body.is_empty() {
+                failures.push(format!("{probe}: not found in the assembly"));
+            } else if body.iter().any(|i| i.starts_with("call")) {
+                failures.push(format!("{probe}: calls out\n    {listing}"));
+            } else if body.iter().any(|i| i.starts_with("jmp") && i.contains('*')) {
+                failures.push(format!("{probe}: indirect jump\n    {listing}"));
+            } else if body.len() > budget {
+                failures.push(format!(
+                    "{probe}: {} instructions, budget {budget}\n    {listing}",
+                    body.len()
+                ));
+            }
+        }
+        assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+    }
+}
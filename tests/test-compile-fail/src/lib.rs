@@ -0,0 +1,33 @@
+//! Compile-fail tests: misuse the type system must keep rejecting.
+//!
+//! Each file in `ui/` is a program that must NOT compile, with the
+//! expected diagnostic next to it in a `.stderr` file. If a refactor of
+//! lifetimes or bounds makes one of them compile, this test fails.
+//!
+//! | Case | Guarantee |
+//! |------|-----------|
+//! | `stop_ref_outlives_source` | A `StopRef` cannot outlive its `StopSource` |
+//! | `stop_ref_into_token` | Borrowed stops cannot be erased into a `'static` `StopToken` |
+//! | `stop_ref_cancel` | A `StopRef` is a read-only view; only the source can cancel |
+//! | `stop_token_cancel` | An erased `StopToken` is read-only |
+//! | `non_sync_stop` | `Stop` implementors must be `Send + Sync` |
+//! | `fn_stop_non_sync_closure` | `FnStop` rejects closures that aren't `Send + Sync` |
+//! | `scope_spawn_non_send` | `StopScope::spawn` rejects non-`Send` tasks |
+//!
+//! Diagnostics change between compiler releases, so the test is ignored by
+//! default and run on stable only:
+//!
+//! ```text
+//! cargo test -p test-compile-fail -- --ignored
+//! TRYBUILD=overwrite cargo test -p test-compile-fail -- --ignored  # refresh .stderr
+//! ```
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    #[ignore = "diagnostics vary by toolchain; run with --ignored on stable"]
+    fn ui() {
+        let t = trybuild::TestCases::new();
+        t.compile_fail("ui/*.rs");
+    }
+}
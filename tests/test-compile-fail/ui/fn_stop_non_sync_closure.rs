@@ -0,0 +1,9 @@
+use almost_enough::{FnStop, Stop};
+use std::rc::Rc;
+use std::cell::Cell;
+
+fn main() {
+    let flag = Rc::new(Cell::new(false));
+    let stop = FnStop::new(move || flag.get());
+    let _ = stop.should_stop();
+}
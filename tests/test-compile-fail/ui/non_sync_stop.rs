@@ -0,0 +1,12 @@
+use almost_enough::{Stop, StopReason};
+use std::cell::Cell;
+
+struct CellStop(Cell<bool>);
+
+impl Stop for CellStop {
+    fn check(&self) -> Result<(), StopReason> {
+        if self.0.get() { Err(StopReason::Cancelled) } else { Ok(()) }
+    }
+}
+
+fn main() {}
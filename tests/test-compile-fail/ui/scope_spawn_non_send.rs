@@ -0,0 +1,13 @@
+use almost_enough::{Unstoppable, stop_scope};
+use std::rc::Rc;
+
+fn main() {
+    let _ = stop_scope(Unstoppable, |scope| {
+        let local = Rc::new(1);
+        scope.spawn(move |_stop| {
+            drop(local);
+            Ok::<(), ()>(())
+        });
+        Ok::<(), ()>(())
+    });
+}
@@ -0,0 +1,7 @@
+use almost_enough::StopSource;
+
+fn main() {
+    let source = StopSource::new();
+    let view = source.as_ref();
+    view.cancel();
+}
@@ -0,0 +1,6 @@
+use almost_enough::{StopSource, StopToken};
+
+fn main() {
+    let source = StopSource::new();
+    let _token = StopToken::new(source.as_ref());
+}
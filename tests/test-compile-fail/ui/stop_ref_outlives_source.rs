@@ -0,0 +1,10 @@
+use almost_enough::{StopRef, StopSource};
+
+fn escape() -> StopRef<'static> {
+    let source = StopSource::new();
+    source.as_ref()
+}
+
+fn main() {
+    let _ = escape();
+}
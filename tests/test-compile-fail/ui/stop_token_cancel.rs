@@ -0,0 +1,6 @@
+use almost_enough::{Stopper, StopToken};
+
+fn main() {
+    let token = StopToken::new(Stopper::new());
+    token.cancel();
+}
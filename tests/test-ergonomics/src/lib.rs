@@ -29,7 +29,7 @@
 //! | SyncStopper   |  yes  |  no  |   yes   | Acquire/Release ordering |
 //! | ChildStopper  |  yes  |  no  |   yes   | Hierarchical cancellation |
 //! | StopToken       |  yes  |  no  |   yes   | Cloneable type erasure (Arc) |
-//! | BoxedStop     |   no  |  no  |   yes   | Non-clonable type erasure |
+//! | BoxedStop     |  yes  |  no  |   yes   | Type erasure; `cloneable()` clones the stop |
 //!
 //! # What DOESN'T compile
 //!
@@ -37,7 +37,6 @@
 //! |---------|-----|
 //! | `StopToken::new(source.as_ref())` | StopRef not 'static |
 //! | `BoxedStop::new(source.as_ref())` | StopRef not 'static |
-//! | `impl CloneStop` with BoxedStop | BoxedStop: !Clone |
 //! | `impl CloneStop` with StopSource | StopSource: !Clone |
 //! | `stop.clone()` on `&dyn Stop` | Clone not object-safe |
 //! | `thread::spawn` with `&dyn Stop` | not 'static |
@@ -47,8 +46,8 @@
 #![allow(unused_imports)]
 
 use almost_enough::{
-    BoxedStop, ChildStopper, FnStop, OrStop, Stop, StopExt, StopRef, StopSource, StopToken,
-    Stopper, SyncStopper, Unstoppable,
+    BoxedStop, ChildStopper, CloneStop, FnStop, OrStop, Stop, StopExt, StopRef, StopSource,
+    StopToken, Stopper, SyncStopper, Unstoppable,
 };
 
 // ═══════════════════════════════════════════════════════════════════
@@ -247,15 +246,18 @@ fn dynstop_to_dynstop() {
 }
 
 // ═══════════════════════════════════════════════════════════════════
-// BoxedStop → NOT Clone
+// BoxedStop → Clone
 // ═══════════════════════════════════════════════════════════════════
 
 #[test]
 fn boxedstop_to_impl() {
     assert!(!accept_impl(Stopper::new().into_boxed()));
 }
-// BoxedStop NOT Clone — can't pass to impl Stop + Clone:
-//   accept_clone_stop(Stopper::new().into_boxed()); // ERROR
+#[test]
+fn boxedstop_to_clone() {
+    assert!(!accept_clone_stop(Stopper::new().into_boxed()));
+    assert!(!accept_clone_stop(Stopper::new().clone_boxed()));
+}
 #[test]
 fn boxedstop_to_dyn() {
     let s = Stopper::new().into_boxed();
@@ -414,7 +416,6 @@ mod cloning {
 // |--------------------------------------|----------------------|
 // | StopToken::new(source.as_ref())        | StopRef not 'static  |
 // | BoxedStop::new(source.as_ref())      | StopRef not 'static  |
-// | accept_clone_stop(boxed_stop)        | BoxedStop: !Clone     |
 // | stop.clone() on &dyn Stop            | Clone not object-safe |
 // | thread::spawn with &dyn Stop         | not 'static          |
 // | accept_option_token(None)          | can't infer type     |
@@ -4,9 +4,12 @@
 use almost_enough::Stop;
 use enough_ffi::{
     FfiCancellationSource, FfiCancellationToken, FfiCancellationTokenView,
-    enough_cancellation_cancel, enough_cancellation_create, enough_cancellation_destroy,
-    enough_cancellation_is_cancelled, enough_token_create, enough_token_create_never,
-    enough_token_destroy, enough_token_is_cancelled,
+    enough_callback_unregister, enough_cancellation_cancel, enough_cancellation_create,
+    enough_cancellation_destroy, enough_cancellation_is_cancelled, enough_child_cancel,
+    enough_child_create, enough_child_destroy, enough_child_is_cancelled, enough_token_create,
+    enough_token_create_never, enough_token_create_with_timeout_ms, enough_token_destroy,
+    enough_token_is_cancelled, enough_token_register_callback, enough_token_remaining_ms,
+    enough_token_with_timeout_ms,
 };
 use std::thread;
 
@@ -278,3 +281,114 @@ fn ffi_interop_with_enough() {
         enough_cancellation_destroy(ffi_source);
     }
 }
+
+#[test]
+fn ffi_timeout_token_matches_with_timeout() {
+    use almost_enough::{StopReason, StopSource, TimeoutExt};
+    use std::time::Duration;
+
+    unsafe {
+        let source = enough_cancellation_create();
+        let token = enough_token_create_with_timeout_ms(source, 50);
+        let tighter = enough_token_with_timeout_ms(token, 5);
+        let view = FfiCancellationToken::from_ptr(tighter);
+
+        // Same tightening behavior as the Rust-side WithTimeout
+        let rust_source = StopSource::new();
+        let rust_stop = rust_source
+            .as_ref()
+            .with_timeout(Duration::from_millis(50))
+            .tighten(Duration::from_millis(5));
+        assert!(enough_token_remaining_ms(tighter) <= 5);
+        assert!(rust_stop.remaining() <= Duration::from_millis(5));
+
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(view.check(), Err(StopReason::TimedOut));
+        assert_eq!(rust_stop.check(), Err(StopReason::TimedOut));
+        assert!(!enough_token_is_cancelled(token));
+
+        enough_token_destroy(tighter);
+        enough_token_destroy(token);
+        enough_cancellation_destroy(source);
+    }
+}
+
+#[test]
+fn ffi_child_tree_matches_child_stopper() {
+    use almost_enough::{ChildStopper, StopExt};
+
+    unsafe {
+        // Host request -> two subtasks
+        let request = enough_cancellation_create();
+        let decode = enough_child_create(request);
+        let resize = enough_child_create(request);
+        let decode_token = enough_token_create(decode);
+        let resize_token = enough_token_create(resize);
+
+        let rust_request = ChildStopper::new();
+        let rust_decode = rust_request.child();
+        let rust_resize = rust_request.child();
+
+        // Child cancellation stays isolated
+        enough_child_cancel(decode);
+        rust_decode.cancel();
+        assert_eq!(
+            enough_token_is_cancelled(decode_token),
+            rust_decode.should_stop()
+        );
+        assert_eq!(
+            enough_token_is_cancelled(resize_token),
+            rust_resize.should_stop()
+        );
+        assert!(!enough_cancellation_is_cancelled(request));
+
+        // Parent cancellation propagates
+        enough_cancellation_cancel(request);
+        rust_request.cancel();
+        assert!(enough_child_is_cancelled(resize));
+        assert_eq!(
+            enough_token_is_cancelled(resize_token),
+            rust_resize.should_stop()
+        );
+
+        enough_token_destroy(resize_token);
+        enough_token_destroy(decode_token);
+        enough_child_destroy(resize);
+        enough_child_destroy(decode);
+        enough_cancellation_destroy(request);
+    }
+}
+
+#[test]
+fn ffi_callback_bridges_to_stopper() {
+    use almost_enough::Stopper;
+    use std::ffi::c_void;
+
+    // A host callback forwarding cancellation into a Rust Stopper
+    extern "C" fn cancel_stopper(context: *mut c_void) {
+        unsafe { &*(context as *const Stopper) }.cancel();
+    }
+
+    let stopper = Stopper::new();
+    unsafe {
+        let source = enough_cancellation_create();
+        let token = enough_token_create(source);
+        let registration = enough_token_register_callback(
+            token,
+            Some(cancel_stopper),
+            &stopper as *const Stopper as *mut c_void,
+        );
+
+        let waiter = {
+            let stopper = stopper.clone();
+            thread::spawn(move || stopper.wait())
+        };
+        enough_cancellation_cancel(source);
+        waiter.join().unwrap();
+        assert!(stopper.should_stop());
+
+        assert!(!enough_callback_unregister(registration));
+        enough_token_destroy(token);
+        enough_cancellation_destroy(source);
+    }
+}
@@ -205,3 +205,32 @@ fn nested_parallel_with_token() {
     // Sum of 0..100
     assert_eq!(result, 4950);
 }
+
+/// Hedged join: the first branch to finish cancels its sibling, and the
+/// parent stays usable afterwards.
+#[test]
+fn hedged_join_cancels_loser() {
+    use almost_enough::{Side, StopExt};
+
+    let stop = Stopper::new();
+    let split = stop.clone().split();
+    let (left, right) = split.branches();
+
+    let (fast, slow) = rayon::join(
+        move || {
+            left.finish();
+            "fast"
+        },
+        move || {
+            while !right.should_stop() {
+                std::thread::yield_now();
+            }
+            right.check()
+        },
+    );
+
+    assert_eq!(fast, "fast");
+    assert_eq!(slow, Err(StopReason::Cancelled));
+    assert_eq!(split.merge(), Ok(Some(Side::Left)));
+    assert!(!stop.is_cancelled());
+}